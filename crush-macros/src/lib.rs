@@ -0,0 +1,354 @@
+//! The `bdd!` and `system!` macros of crush, re-exported by crush at its root.
+//!
+//! Both macros take the specs of bdds written inline, with the layout of the .bdd format (see
+//! `crush::soc::parse`), and check them at compile time: a malformed lhs, a variable out of the
+//! `nvar` variables of the system, a node id of 0 or used twice in a bdd, and an edge to a node
+//! which isn't in a level below its parent are reported as compile errors, spanning the offending
+//! part of the spec. Bdds with unreachable nodes or dead ends are accepted, as the algorithms
+//! building and cleaning up bdds are tested on them (see `Bdd::validate` to detect them).
+//!
+//! The expanded code refers to crush by the path `::crush`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{bracketed, parenthesized, Error, LitInt, LitStr, Token};
+
+/// Build a `Bdd` from its spec: `nvar; id; [levels]`, where each level is `("lhs", [nodes])`,
+/// the lhs listing the variables of the level separated by `+` (-1 being the constant 1), and each
+/// node is `(id; e0, e1)`, an edge of 0 meaning no child.
+///
+/// ### Example :
+///
+/// ```
+/// use crush::bdd;
+///
+/// let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;0,4);(3;4,0)]);("",[(4;0,0)])]);
+/// ```
+/// will create a bdd of id 0 over 5 variables, with 3 levels. The spec is checked at compile
+/// time, see the crate documentation.
+#[proc_macro]
+pub fn bdd(input: TokenStream) -> TokenStream {
+    expand_bdd(input.into()).unwrap_or_else(|error| error.to_compile_error()).into()
+}
+
+/// Build a `System`, returning a `Result` as `System::from_elem` does. Either from bdds, as in
+/// `system![bdd, bdd_2]`, failing if the bdds have different `nvar`, or from the specs of its bdds
+/// in the syntax of `bdd!`, sharing `nvar`: `system!(nvar; id; [levels]; id_2; [levels_2])`.
+///
+/// ### Example :
+///
+/// ```
+/// use crush::system;
+///
+/// let system = system!(3; 0; [("0+1",[(1;2,2)]);("",[(2;0,0)])]; 1; [("2",[(1;0,2)]);("",[(2;0,0)])]).unwrap();
+/// ```
+/// The specs are checked at compile time as with `bdd!`, along with the ids of the bdds, which
+/// must be different.
+#[proc_macro]
+pub fn system(input: TokenStream) -> TokenStream {
+    expand_system(input.into()).unwrap_or_else(|error| error.to_compile_error()).into()
+}
+
+/// A number of a spec, with its span for the errors.
+struct Number {
+    value: usize,
+    span: Span,
+}
+
+impl Parse for Number {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lit: LitInt = input.parse()?;
+        Ok(Number { value: lit.base10_parse()?, span: lit.span() })
+    }
+}
+
+/// A node: `(id; e0, e1)`.
+struct NodeInput {
+    id: Number,
+    e0: Number,
+    e1: Number,
+}
+
+impl Parse for NodeInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        parenthesized!(content in input);
+        let id = content.parse()?;
+        content.parse::<Token![;]>()?;
+        let e0 = content.parse()?;
+        content.parse::<Token![,]>()?;
+        let e1 = content.parse()?;
+        Ok(NodeInput { id, e0, e1 })
+    }
+}
+
+/// A level: `("lhs", [nodes])`.
+struct LevelInput {
+    lhs: LitStr,
+    nodes: Vec<NodeInput>,
+}
+
+impl Parse for LevelInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        parenthesized!(content in input);
+        let lhs = content.parse()?;
+        content.parse::<Token![,]>()?;
+        let nodes;
+        bracketed!(nodes in content);
+        let nodes = Punctuated::<NodeInput, Token![;]>::parse_terminated(&nodes)?;
+        Ok(LevelInput { lhs, nodes: nodes.into_iter().collect() })
+    }
+}
+
+/// A bdd without its nvar: `id; [levels]`.
+struct BddInput {
+    id: Number,
+    levels: Vec<LevelInput>,
+}
+
+impl Parse for BddInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let id = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let levels;
+        bracketed!(levels in input);
+        let levels = Punctuated::<LevelInput, Token![;]>::parse_terminated(&levels)?;
+        Ok(BddInput { id, levels: levels.into_iter().collect() })
+    }
+}
+
+/// The input of `bdd!`: `nvar; id; [levels]`.
+struct BddMacroInput {
+    nvar: Number,
+    bdd: BddInput,
+}
+
+impl Parse for BddMacroInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let nvar = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let bdd = input.parse()?;
+        Ok(BddMacroInput { nvar, bdd })
+    }
+}
+
+/// The input of `system!` made of specs: `nvar; id; [levels]; id_2; [levels_2]`.
+struct SystemMacroInput {
+    nvar: Number,
+    bdds: Vec<BddInput>,
+}
+
+impl Parse for SystemMacroInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let nvar = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let mut bdds = vec![input.parse()?];
+        while !input.is_empty() {
+            input.parse::<Token![;]>()?;
+            if input.is_empty() {
+                break;
+            }
+            bdds.push(input.parse()?);
+        }
+        Ok(SystemMacroInput { nvar, bdds })
+    }
+}
+
+fn expand_bdd(input: TokenStream2) -> syn::Result<TokenStream2> {
+    let input: BddMacroInput = syn::parse2(input)?;
+    expand_spec(&input.nvar, &input.bdd)
+}
+
+fn expand_system(input: TokenStream2) -> syn::Result<TokenStream2> {
+    // A spec starts with nvar followed by a ';', while bdds are separated by ','
+    let is_spec = syn::parse2::<SpecStart>(input.clone()).is_ok();
+    if !is_spec {
+        return Ok(quote! { ::crush::soc::system::System::from_elem(vec![#input]) });
+    }
+    let input: SystemMacroInput = syn::parse2(input)?;
+    let mut errors = Errors::default();
+    let mut bdds = Vec::with_capacity(input.bdds.len());
+    for (i, bdd) in input.bdds.iter().enumerate() {
+        if input.bdds[..i].iter().any(|other| other.id.value == bdd.id.value) {
+            errors.push(bdd.id.span, format!("bdd {} is already in the system", bdd.id.value));
+        }
+        match expand_spec(&input.nvar, bdd) {
+            Ok(bdd) => bdds.push(bdd),
+            Err(error) => errors.combine(error),
+        }
+    }
+    errors.into_result()?;
+    Ok(quote! { ::crush::soc::system::System::from_elem(vec![#(#bdds),*]) })
+}
+
+/// The start of the input of `system!` when made of specs: nvar followed by a ';'.
+struct SpecStart;
+
+impl Parse for SpecStart {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<LitInt>()?;
+        input.parse::<Token![;]>()?;
+        input.parse::<proc_macro2::TokenStream>()?;
+        Ok(SpecStart)
+    }
+}
+
+/// The code building the bdd of `spec` over `nvar` variables, once checked.
+fn expand_spec(nvar: &Number, spec: &BddInput) -> syn::Result<TokenStream2> {
+    let lhss = check_spec(nvar, spec)?;
+    let levels = spec.levels.iter().zip(lhss).map(|(level, lhs)| {
+        let nodes = level.nodes.iter().map(|node| {
+            let (id, e0, e1) = (node.id.value, node.e0.value, node.e1.value);
+            quote! {
+                ::crush::soc::utils::NodeSpec::new(::crush::soc::Id::new(#id),
+                                                   ::crush::soc::Id::new(#e0),
+                                                   ::crush::soc::Id::new(#e1))
+            }
+        });
+        quote! { ::crush::soc::utils::LevelSpec::new(vec![#(#lhs),*], vec![#(#nodes),*]) }
+    });
+    let (id, nvar) = (spec.id.value, nvar.value);
+    Ok(quote! {
+        ::crush::soc::utils::build_bdd_from_spec(
+            &mut ::crush::soc::utils::BddSpec::new(::crush::soc::Id::new(#id), vec![#(#levels),*]),
+            #nvar)
+    })
+}
+
+/// Check `spec` over `nvar` variables as told in the crate documentation, returning the variables
+/// of the lhs of each level, or all the errors found.
+fn check_spec(nvar: &Number, spec: &BddInput) -> syn::Result<Vec<Vec<i64>>> {
+    let mut errors = Errors::default();
+    if spec.levels.is_empty() {
+        errors.push(spec.id.span, format!("bdd {} has no level", spec.id.value));
+    }
+
+    let mut lhss = Vec::with_capacity(spec.levels.len());
+    for level in spec.levels.iter() {
+        match parse_lhs(&level.lhs.value(), nvar.value) {
+            Ok(lhs) => lhss.push(lhs),
+            Err(msg) => errors.push(level.lhs.span(), msg),
+        }
+    }
+
+    // The level of each node
+    let mut levels = std::collections::HashMap::new();
+    for (depth, level) in spec.levels.iter().enumerate() {
+        for node in level.nodes.iter() {
+            if node.id.value == 0 {
+                errors.push(node.id.span, "node ids start at 1, an edge of 0 meaning no child".to_string());
+            } else if let Some(other) = levels.insert(node.id.value, depth) {
+                errors.push(node.id.span, format!("node {} is already in level {}", node.id.value, other));
+            }
+        }
+    }
+    for (depth, level) in spec.levels.iter().enumerate() {
+        for edge in level.nodes.iter().flat_map(|node| vec![&node.e0, &node.e1]) {
+            match levels.get(&edge.value) {
+                _ if edge.value == 0 => (),
+                None => errors.push(edge.span, format!("node {} doesn't exist", edge.value)),
+                Some(child) if *child <= depth => errors.push(
+                    edge.span,
+                    format!("node {} is in level {}, not below level {}", edge.value, child, depth)),
+                Some(_) => (),
+            }
+        }
+    }
+    errors.into_result()?;
+    Ok(lhss)
+}
+
+/// The variables of `lhs`, separated by '+', in a system of `nvar` variables. The empty lhs is
+/// the constant 0, and -1 the constant 1.
+fn parse_lhs(lhs: &str, nvar: usize) -> Result<Vec<i64>, String> {
+    if lhs.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    lhs.split('+')
+        .map(|var| {
+            let var = var.trim();
+            match var.parse::<i64>() {
+                Ok(-1) => Ok(-1),
+                Ok(v) if v >= 0 && (v as usize) < nvar => Ok(v),
+                Ok(v) => Err(format!("variable {} out of the {} variables of the system", v, nvar)),
+                Err(_) => Err(format!("expected variables separated by '+' in the lhs, found {:?}", var)),
+            }
+        })
+        .collect()
+}
+
+/// The errors found in a spec, combined to be reported together.
+#[derive(Default)]
+struct Errors(Option<Error>);
+
+impl Errors {
+    fn push(&mut self, span: Span, msg: String) {
+        self.combine(Error::new(span, msg));
+    }
+
+    fn combine(&mut self, error: Error) {
+        match &mut self.0 {
+            Some(errors) => errors.combine(error),
+            None => self.0 = Some(error),
+        }
+    }
+
+    fn into_result(self) -> syn::Result<()> {
+        match self.0 {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The messages of the errors of `bdd!` on `input`, if any.
+    fn bdd_errors(input: &str) -> Vec<String> {
+        match expand_bdd(input.parse().unwrap()) {
+            Ok(_) => Vec::new(),
+            Err(error) => error.into_iter().map(|error| error.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn valid_specs() {
+        assert!(bdd_errors("5;0;[(\"1+2\",[(1;2,3)]);(\"3 + 2\",[(2;0,4);(3;4,0)]);(\"\",[(4;0,0)])]").is_empty());
+        // A constant lhs, a jumping edge and an unreachable node
+        assert!(bdd_errors("2;1;[(\"0+-1\",[(1;2,3)]);(\"1\",[(2;3,0);(5;3,3)]);(\"\",[(3;0,0)])]").is_empty());
+        assert_eq!(vec![1, -1], parse_lhs("1 + -1", 2).unwrap());
+    }
+
+    #[test]
+    fn invalid_specs() {
+        assert_eq!(vec!["bdd 0 has no level"], bdd_errors("5;0;[]"));
+        assert_eq!(vec!["variable 5 out of the 5 variables of the system",
+                        "expected variables separated by '+' in the lhs, found \"\""],
+                   bdd_errors("5;0;[(\"1+5\",[(1;2,2)]);(\"1++2\",[(2;3,0)]);(\"\",[(3;0,0)])]"));
+        assert_eq!(vec!["node 2 is already in level 1", "node 4 doesn't exist"],
+                   bdd_errors("5;0;[(\"0\",[(1;2,4)]);(\"1\",[(2;3,0)]);(\"\",[(2;0,0);(3;0,0)])]"));
+        assert_eq!(vec!["node ids start at 1, an edge of 0 meaning no child", "node 1 is in level 0, not below level 1"],
+                   bdd_errors("5;0;[(\"0\",[(1;2,0)]);(\"1\",[(2;1,0);(0;0,0)])]"));
+        // Not a spec at all
+        assert_eq!(1, bdd_errors("5;0;(\"0\",[(1;2,0)])").len());
+    }
+
+    #[test]
+    fn system_specs() {
+        let system = |input: &str| expand_system(input.parse().unwrap());
+        assert!(system("bdd, bdd_2").is_ok());
+        assert!(system("3; 0; [(\"0\",[(1;2,2)]);(\"\",[(2;0,0)])]; 1; [(\"2\",[(1;2,0)]);(\"\",[(2;0,0)])];").is_ok());
+        let error = system("3; 0; [(\"0\",[(1;2,2)]);(\"\",[(2;0,0)])]; 0; [(\"3\",[(1;2,0)]);(\"\",[(2;0,0)])]")
+            .unwrap_err();
+        assert_eq!(vec!["bdd 0 is already in the system", "variable 3 out of the 3 variables of the system"],
+                   error.into_iter().map(|error| error.to_string()).collect::<Vec<_>>());
+    }
+}
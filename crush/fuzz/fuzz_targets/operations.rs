@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+// Random sequences of restrict/swap/add/join/reduce, cross-checked against brute force.
+fuzz_target!(|data: &[u8]| {
+    crush::soc::fuzz::check_operations(data);
+});
@@ -0,0 +1,217 @@
+//! The bit vectors of the linear algebra, behind the `LhsBits` trait.
+//!
+//! The LHS's of the levels are `Vob`s, and so are the rows of a `Matrix`. The kernels of the
+//! Gaussian eliminations (`extract_linear_dependencies` and `rank`) are dominated by the XOR of
+//! two rows and the scan for the highest set bit, which `Vob` does a bit at a time for the scan.
+//! They are generic over `LhsBits`, and run on `Bits`, the implementation selected at compile
+//! time:
+//! - `Vob` by default;
+//! - `BlockBits` with the `block-bits` feature, a fixed width vector of `u64` blocks whose
+//!   kernels go through the blocks four at a time, which the compiler turns into SIMD
+//!   instructions, and scan the highest set bit a block at a time.
+//!
+//! The rows are converted from and to `Vob`s at the boundaries of the kernels, which is linear in
+//! the size of the matrix while the elimination is cubic.
+
+use alloc::{vec, vec::Vec};
+use vob::Vob;
+
+/// The bit vector of the kernels of the linear algebra, see the module documentation.
+#[cfg(feature = "block-bits")]
+pub type Bits = BlockBits;
+/// The bit vector of the kernels of the linear algebra, see the module documentation.
+#[cfg(not(feature = "block-bits"))]
+pub type Bits = Vob;
+
+/// A bit vector of fixed length, as used for a LHS or a row of a `Matrix`.
+pub trait LhsBits: Clone + PartialEq {
+    /// Create a vector of `len` bits, all unset.
+    fn zeros(len: usize) -> Self;
+
+    fn from_vob(vob: &Vob) -> Self;
+
+    fn to_vob(&self) -> Vob;
+
+    /// Return the number of bits.
+    fn len(&self) -> usize;
+
+    /// Return true if the vector has no bits.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the bit `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    fn get_bit(&self, index: usize) -> bool;
+
+    /// Set the bit `index` to `value`.
+    ///
+    /// Panics if `index` is out of bounds.
+    fn set_bit(&mut self, index: usize, value: bool);
+
+    /// XOR `other` into `self`.
+    ///
+    /// Panics if they are not of the same length.
+    fn xor_assign(&mut self, other: &Self);
+
+    /// Return the number of set bits.
+    fn count_ones(&self) -> usize;
+
+    /// Return the highest set bit, if any.
+    fn max_set_bit(&self) -> Option<usize>;
+
+    /// Return true if no bit is set.
+    fn is_zero(&self) -> bool {
+        self.max_set_bit().is_none()
+    }
+}
+
+impl LhsBits for Vob {
+    fn zeros(len: usize) -> Self {
+        Vob::from_elem(len, false)
+    }
+
+    fn from_vob(vob: &Vob) -> Self {
+        vob.clone()
+    }
+
+    fn to_vob(&self) -> Vob {
+        self.clone()
+    }
+
+    fn len(&self) -> usize {
+        Vob::len(self)
+    }
+
+    fn get_bit(&self, index: usize) -> bool {
+        self[index]
+    }
+
+    fn set_bit(&mut self, index: usize, value: bool) {
+        self.set(index, value);
+    }
+
+    fn xor_assign(&mut self, other: &Self) {
+        assert_eq!(Vob::len(self), Vob::len(other), "XOR of bit vectors of different lengths");
+        self.xor(other);
+    }
+
+    fn count_ones(&self) -> usize {
+        self.iter_set_bits(..).count()
+    }
+
+    fn max_set_bit(&self) -> Option<usize> {
+        self.iter_set_bits(..).last()
+    }
+}
+
+/// A bit vector of fixed length held in `u64` blocks, the bit `i` being the bit `i % 64` of the
+/// block `i / 64`. The bits past the length in the last block are always unset.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockBits {
+    len: usize,
+    blocks: Vec<u64>,
+}
+
+impl BlockBits {
+    /// The number of blocks processed together by the kernels.
+    const LANES: usize = 4;
+
+    /// Return the blocks of the vector.
+    #[inline]
+    pub fn blocks(&self) -> &[u64] {
+        &self.blocks
+    }
+
+    #[inline]
+    fn check_index(&self, index: usize) {
+        assert!(index < self.len, "bit {} out of a vector of {} bits", index, self.len);
+    }
+}
+
+impl LhsBits for BlockBits {
+    fn zeros(len: usize) -> Self {
+        BlockBits { len, blocks: vec![0; len.div_ceil(64)] }
+    }
+
+    fn from_vob(vob: &Vob) -> Self {
+        let mut bits = BlockBits::zeros(vob.len());
+        for index in vob.iter_set_bits(..) {
+            bits.blocks[index / 64] |= 1 << (index % 64);
+        }
+        bits
+    }
+
+    fn to_vob(&self) -> Vob {
+        let mut vob = Vob::from_elem(self.len, false);
+        for (b, block) in self.blocks.iter().enumerate() {
+            let mut block = *block;
+            while block != 0 {
+                vob.set(b * 64 + block.trailing_zeros() as usize, true);
+                block &= block - 1;
+            }
+        }
+        vob
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn get_bit(&self, index: usize) -> bool {
+        self.check_index(index);
+        (self.blocks[index / 64] >> (index % 64)) & 1 == 1
+    }
+
+    #[inline]
+    fn set_bit(&mut self, index: usize, value: bool) {
+        self.check_index(index);
+        let mask = 1 << (index % 64);
+        if value {
+            self.blocks[index / 64] |= mask;
+        } else {
+            self.blocks[index / 64] &= !mask;
+        }
+    }
+
+    fn xor_assign(&mut self, other: &Self) {
+        assert_eq!(self.len, other.len, "XOR of bit vectors of different lengths");
+        let mut lhs = self.blocks.chunks_exact_mut(Self::LANES);
+        let mut rhs = other.blocks.chunks_exact(Self::LANES);
+        for (lhs, rhs) in (&mut lhs).zip(&mut rhs) {
+            for (lhs, rhs) in lhs.iter_mut().zip(rhs) {
+                *lhs ^= rhs;
+            }
+        }
+        for (lhs, rhs) in lhs.into_remainder().iter_mut().zip(rhs.remainder()) {
+            *lhs ^= rhs;
+        }
+    }
+
+    fn count_ones(&self) -> usize {
+        let mut chunks = self.blocks.chunks_exact(Self::LANES);
+        let mut counts = [0; BlockBits::LANES];
+        for chunk in &mut chunks {
+            for (count, block) in counts.iter_mut().zip(chunk) {
+                *count += block.count_ones() as usize;
+            }
+        }
+        counts.iter().sum::<usize>()
+            + chunks.remainder().iter().map(|block| block.count_ones() as usize).sum::<usize>()
+    }
+
+    fn max_set_bit(&self) -> Option<usize> {
+        self.blocks.iter()
+            .enumerate()
+            .rev()
+            .find(|(_, block)| **block != 0)
+            .map(|(b, block)| b * 64 + 63 - block.leading_zeros() as usize)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.blocks.iter().all(|block| *block == 0)
+    }
+}
@@ -7,8 +7,10 @@
 //! * Resolve and absorb a linear dependency.
 //! * Transpose matrices.
 //! * Left mul two matrices.
+//! * Invert square matrices.
 //! * Extract a linear layer from a System description.
 //! * Extract any solution(s) to a matrix and its right-hand side vector.
+//! * Row reduce a matrix, and compute its rank and its kernel.
 //!
 //! More functions are expected to be added when the need arise.
 //!
@@ -20,12 +22,17 @@
 //! libraries in this workspace. Any suggestions for good pre-existing libraries out there which
 //! is suitable to replace this module is appreciated.
 
-use std::fmt;
-use std::iter;
-use std::slice::Iter;
+use alloc::{vec, vec::Vec};
+use core::fmt;
+use core::iter;
+use core::slice::Iter;
 
 use vob::{vob, Vob};
 
+pub use bits::{BlockBits, Bits, LhsBits};
+
+pub mod bits;
+
 /// `matrix!` is sugar around Matrix::from_rows().
 ///
 /// Macro to easily create a `Matrix` object from a
@@ -72,7 +79,7 @@ impl Matrix {
 
     /// Return an iterator over the rows of the Matrix
     #[inline]
-    pub fn iter_rows(&self) -> Iter<Vob> {
+    pub fn iter_rows(&self) -> Iter<'_, Vob> {
         self.rows.iter()
     }
 
@@ -98,6 +105,36 @@ impl Matrix {
         self.rows.get(depth)
     }
 
+    /// Return the element at (`row`, `column`).
+    ///
+    /// Panics if `row` or `column` is out of bounds.
+    #[inline]
+    pub fn get(&self, row: usize, column: usize) -> bool {
+        self.rows[row][column]
+    }
+
+    /// Set the element at (`row`, `column`) to `value`.
+    ///
+    /// Panics if `row` or `column` is out of bounds.
+    #[inline]
+    pub fn set(&mut self, row: usize, column: usize, value: bool) {
+        assert!(column < self.column_size(), "column {} out of a matrix of {} columns", column, self.column_size());
+        self.rows[row].set(column, value);
+    }
+
+    /// Perform a Self * `vector` op, `vector` being a column vector of `column_size` bits, and
+    /// return the resulting column vector of `row_size` bits.
+    pub fn mul_vob(&self, vector: &Vob) -> Vob {
+        assert_eq!(self.column_size(), vector.len());
+        let mut out = Vob::from_elem(self.row_size(), false);
+        for (i, row) in self.iter_rows().enumerate() {
+            let mut row = row.clone();
+            row.and(vector);
+            out.set(i, row.iter_set_bits(..).count() % 2 == 1);
+        }
+        out
+    }
+
     /// Returns true if rows and/or columns are 0.
     #[inline]
     pub fn is_empty(&self) -> bool {
@@ -177,6 +214,32 @@ pub fn transpose(matrix: &Matrix) -> Matrix {
     trans
 }
 
+/// Return the inverse of a square matrix, or `None` if it is not square or not invertible.
+///
+/// To compute the inverse we augment the matrix with the identity matrix and use Gauss-Jordan
+/// elimination, applying the same operations on the identity matrix.
+pub fn inverse(matrix: &Matrix) -> Option<Matrix> {
+    let size = matrix.row_size();
+    if matrix.rows.iter().any(|row| row.len() != size) {
+        return None;
+    }
+    let mut mat = matrix.clone();
+    let mut inv = identity(size);
+    for column in 0..size {
+        let pivot = (column..size).find(|row| mat.rows[*row][column])?;
+        mat.rows.swap(column, pivot);
+        inv.rows.swap(column, pivot);
+        for row in 0..size {
+            if row != column && mat.rows[row][column] {
+                let (to_add, to_add_inv) = (mat.rows[column].clone(), inv.rows[column].clone());
+                mat.rows[row].xor(&to_add);
+                inv.rows[row].xor(&to_add_inv);
+            }
+        }
+    }
+    Some(inv)
+}
+
 /// Return the highest set bit with little endianness.
 ///
 /// ex : 01001 will return 4
@@ -195,34 +258,38 @@ pub fn get_max_set_bit(vob: &Vob) -> Option<usize> {
 /// -> gauss the matrix and apply the same operations on the identity matrix
 ///
 /// -> return the lower part of the identity containing the dependencies
-pub fn extract_linear_dependencies(mut mat: Matrix) -> Matrix {
-    let mut id = identity(mat.row_size());
+///
+/// The elimination runs on `bits::Bits`, see the `bits` module.
+pub fn extract_linear_dependencies(mat: Matrix) -> Matrix {
+    extract_linear_dependencies_with::<Bits>(mat)
+}
+
+/// As `extract_linear_dependencies`, with the elimination running on `B`.
+pub fn extract_linear_dependencies_with<B: LhsBits>(mat: Matrix) -> Matrix {
+    let mut mat: Vec<B> = mat.rows.iter().map(B::from_vob).collect();
+    let mut id: Vec<B> = identity(mat.len()).rows.iter().map(B::from_vob).collect();
     let mut loop_id = 0;
-    for i in (0..mat.row_size()).rev() {
-        let mut highest_set_bit = get_max_set_bit(&mat.rows[i]);
+    for i in (0..mat.len()).rev() {
+        let mut highest_set_bit = mat[i].max_set_bit();
         let mut max_row = i;
         for j in (0..i).rev() {
-            if get_max_set_bit(&mat.rows[j]).is_some()
-                && (highest_set_bit.is_none()
-                    || get_max_set_bit(&mat.rows[j]).unwrap() > highest_set_bit.unwrap())
-            {
-                highest_set_bit = get_max_set_bit(&mat.rows[j]);
+            let bit = mat[j].max_set_bit();
+            if bit.is_some() && (highest_set_bit.is_none() || bit > highest_set_bit) {
+                highest_set_bit = bit;
                 max_row = j;
             }
         }
         if let Some(highest_set_bit) = highest_set_bit {
             if max_row < i {
-                mat.rows.swap(i, max_row);
-                id.rows.swap(i, max_row);
+                mat.swap(i, max_row);
+                id.swap(i, max_row);
             }
+            let (above, below) = mat.split_at_mut(i);
+            let (id_above, id_below) = id.split_at_mut(i);
             for j in (0..i).rev() {
-                if get_max_set_bit(&mat.rows[j]).is_some()
-                    && get_max_set_bit(&mat.rows[j]).unwrap() == highest_set_bit
-                {
-                    let to_add = mat.rows[i].clone();
-                    mat.rows[j].xor(&to_add);
-                    let to_add = id.rows[i].clone();
-                    id.rows[j].xor(&to_add);
+                if above[j].max_set_bit() == Some(highest_set_bit) {
+                    above[j].xor_assign(&below[0]);
+                    id_above[j].xor_assign(&id_below[0]);
                 }
             }
         } else {
@@ -230,45 +297,161 @@ pub fn extract_linear_dependencies(mut mat: Matrix) -> Matrix {
         }
         loop_id = i;
     }
-    id.rows.drain(loop_id..id.row_size());
-    for i in (0..id.row_size()).rev() {
-        let mut highest_set_bit = get_max_set_bit(&id.rows[i]);
+    id.drain(loop_id..id.len());
+    for i in (0..id.len()).rev() {
+        let mut highest_set_bit = id[i].max_set_bit();
         let mut max_row = i;
         for j in (0..i).rev() {
-            if get_max_set_bit(&id.rows[j]).is_some()
-                && (highest_set_bit.is_none()
-                    || get_max_set_bit(&id.rows[j]).unwrap() > highest_set_bit.unwrap())
-            {
-                highest_set_bit = get_max_set_bit(&id.rows[j]);
+            let bit = id[j].max_set_bit();
+            if bit.is_some() && (highest_set_bit.is_none() || bit > highest_set_bit) {
+                highest_set_bit = bit;
                 max_row = j;
             }
         }
         if let Some(highest_set_bit) = highest_set_bit {
             if max_row < i {
-                id.rows.swap(i, max_row);
+                id.swap(i, max_row);
             }
-            for j in (0..i).rev() {
-                if get_max_set_bit(&id.rows[j]).is_some()
-                    && get_max_set_bit(&id.rows[j]).unwrap() == highest_set_bit
-                {
-                    let to_add = id.rows[i].clone();
-                    id.rows[j].xor(&to_add);
+            let (above, below) = id.split_at_mut(i);
+            for row in above.iter_mut().rev() {
+                if row.max_set_bit() == Some(highest_set_bit) {
+                    row.xor_assign(&below[0]);
                 }
             }
         } else {
             break;
         }
     }
-    for i in 0..id.row_size() {
-        let highest_set_bit = get_max_set_bit(&id.rows[i]);
-        for j in i + 1..id.row_size() {
-            if id.rows[j][highest_set_bit.unwrap()] {
-                let to_add = id.rows[i].clone();
-                id.rows[j].xor(&to_add);
+    for i in 0..id.len() {
+        let highest_set_bit = id[i].max_set_bit().unwrap();
+        let (above, below) = id.split_at_mut(i + 1);
+        for row in below.iter_mut() {
+            if row.get_bit(highest_set_bit) {
+                row.xor_assign(&above[i]);
+            }
+        }
+    }
+    Matrix { rows: id.iter().map(LhsBits::to_vob).collect() }
+}
+
+/// Return the rank of `matrix`, the number of linearly independent rows.
+///
+/// Each row is reduced by the rows kept so far, indexed by their highest set bit, and kept if it
+/// doesn't reduce to zero. The reduction runs on `bits::Bits`, see the `bits` module.
+pub fn rank(matrix: &Matrix) -> usize {
+    let mut pivots: Vec<Option<Bits>> = vec![None; matrix.column_size()];
+    let mut rank = 0;
+    for row in matrix.iter_rows() {
+        let mut row = Bits::from_vob(row);
+        while let Some(bit) = row.max_set_bit() {
+            match &pivots[bit] {
+                Some(pivot) => {
+                    row.xor_assign(pivot);
+                }
+                None => {
+                    pivots[bit] = Some(row);
+                    rank += 1;
+                    break;
+                }
+            }
+        }
+    }
+    rank
+}
+
+/// Return the reduced row echelon form of `matrix` and its pivots, with the pivot of a row being
+/// its highest set bit, as in the other eliminations of this module.
+///
+/// The zero rows are left out, such that the number of rows is the rank of `matrix`. The rows are
+/// sorted by increasing pivot, which is the only set bit of its column. The elimination runs on
+/// `bits::Bits`, see the `bits` module.
+pub fn row_reduce(matrix: &Matrix) -> (Matrix, Vec<usize>) {
+    let (rows, pivots, _) = eliminate(matrix, None);
+    (Matrix { rows }, pivots)
+}
+
+/// Return a basis of the kernel of `matrix`, the vectors `x` such that `matrix * x` is zero, one
+/// per row. There is one vector per column of `matrix` without pivot (see `row_reduce`), with the
+/// bit of this column set and the bits of the other columns without pivot unset.
+pub fn kernel(matrix: &Matrix) -> Matrix {
+    let columns = matrix.column_size();
+    let (rows, pivots, _) = eliminate(matrix, None);
+    let mut basis = Vec::with_capacity(columns - pivots.len());
+    for free in (0..columns).filter(|column| pivots.binary_search(column).is_err()) {
+        let mut vector = Vob::from_elem(columns, false);
+        vector.set(free, true);
+        for (row, pivot) in rows.iter().zip(&pivots) {
+            vector.set(*pivot, row[free]);
+        }
+        basis.push(vector);
+    }
+    Matrix { rows: basis }
+}
+
+/// Return a solution `x` to `lhs * x = rhs`, with the variables not fixed by `lhs` set to false,
+/// or `None` if there is no solution. The solutions are the sum of `x` and any vector of
+/// `kernel(lhs)`.
+///
+/// Panics if `rhs` isn't of `lhs.row_size()` bits.
+pub fn solve(lhs: &Matrix, rhs: &Vob) -> Option<Vob> {
+    assert_eq!(lhs.row_size(), rhs.len());
+    let (_, pivots, rhs) = eliminate(lhs, Some(rhs));
+    let rhs = rhs?;
+    let mut solution = Vob::from_elem(lhs.column_size(), false);
+    for (pivot, value) in pivots.iter().zip(rhs.iter()) {
+        solution.set(*pivot, value);
+    }
+    Some(solution)
+}
+
+/// Compute the reduced row echelon form of `matrix` for `row_reduce`, applying the same row
+/// operations on `rhs` if given. Return the nonzero rows, their pivots, and the bits of `rhs` of
+/// these rows, or `None` for `rhs` if a zero row has its bit set (the system is inconsistent).
+fn eliminate(matrix: &Matrix, rhs: Option<&Vob>) -> (Vec<Vob>, Vec<usize>, Option<Vob>) {
+    let mut by_pivot: Vec<Option<(Bits, bool)>> = vec![None; matrix.column_size()];
+    let mut consistent = true;
+    for (index, row) in matrix.iter_rows().enumerate() {
+        let mut row = Bits::from_vob(row);
+        let mut value = rhs.is_some_and(|rhs| rhs[index]);
+        loop {
+            match row.max_set_bit() {
+                Some(bit) => match &by_pivot[bit] {
+                    Some((pivot, pivot_value)) => {
+                        row.xor_assign(pivot);
+                        value ^= pivot_value;
+                    }
+                    None => {
+                        by_pivot[bit] = Some((row, value));
+                        break;
+                    }
+                },
+                None => {
+                    consistent &= !value;
+                    break;
+                }
+            }
+        }
+    }
+    // Clear each pivot from the rows of higher pivots, the lower pivots first
+    let pivots: Vec<usize> = (0..by_pivot.len()).filter(|bit| by_pivot[*bit].is_some()).collect();
+    for (i, pivot) in pivots.iter().enumerate() {
+        let (below, above) = by_pivot.split_at_mut(pivot + 1);
+        let (row, value) = below[*pivot].clone().unwrap();
+        for higher in &pivots[i + 1..] {
+            let (higher_row, higher_value) = above[higher - pivot - 1].as_mut().unwrap();
+            if higher_row.get_bit(*pivot) {
+                higher_row.xor_assign(&row);
+                *higher_value ^= value;
             }
         }
     }
-    id
+    let (rows, values): (Vec<Vob>, Vec<bool>) = by_pivot
+        .into_iter()
+        .flatten()
+        .map(|(row, value)| (row.to_vob(), value))
+        .unzip();
+    let rhs = if consistent { Some(values.into_iter().collect()) } else { None };
+    (rows, pivots, rhs)
 }
 
 /// Solve a linear system represented by a `Matrix` (left hand side) and a `Vob` (right hand side).
@@ -32,6 +32,28 @@ fn transpose_test() {
     assert_eq!(trans, expected_result);
 }
 
+#[test]
+fn inverse_test() {
+    let m = matrix![vec![
+        vob![true, false, true, false],
+        vob![false, true, true, true],
+        vob![false, false, true, true],
+        vob![true, true, false, false]
+    ]];
+    let inv = algebra::inverse(&m).unwrap();
+    assert_eq!(m.left_mul(&inv), algebra::identity(4));
+    assert_eq!(inv.left_mul(&m), algebra::identity(4));
+    assert_eq!(algebra::inverse(&algebra::identity(3)), Some(algebra::identity(3)));
+
+    let singular = matrix![vec![
+        vob![true, true, false],
+        vob![false, true, true],
+        vob![true, false, true]
+    ]];
+    assert_eq!(algebra::inverse(&singular), None);
+    assert_eq!(algebra::inverse(&matrix![vec![vob![true, false]]]), None);
+}
+
 #[test]
 fn identity_test() {
     let id = algebra::identity(4);
@@ -43,3 +65,118 @@ fn identity_test() {
     ]];
     assert_eq!(id, expected);
 }
+
+#[test]
+fn rank_test() {
+    let m = matrix![vec![
+        vob![true, false, true, false],
+        vob![false, true, true, true],
+        vob![true, true, false, true],
+        vob![false, false, false, false],
+        vob![false, false, false, true]
+    ]];
+    assert_eq!(algebra::rank(&m), 3);
+    assert_eq!(algebra::rank(&algebra::identity(4)), 4);
+    assert_eq!(algebra::rank(&algebra::Matrix::new(0, 0)), 0);
+}
+
+#[test]
+fn row_reduce_test() {
+    let m = matrix![vec![
+        vob![true, false, true, false],
+        vob![false, true, true, true],
+        vob![true, true, false, true],
+        vob![false, false, false, false]
+    ]];
+    let (reduced, pivots) = algebra::row_reduce(&m);
+    assert_eq!(pivots, vec![2, 3]);
+    let expected = matrix![vec![
+        vob![true, false, true, false],
+        vob![true, true, false, true]
+    ]];
+    assert_eq!(reduced, expected);
+
+    let kernel = algebra::kernel(&m);
+    let expected = matrix![vec![
+        vob![true, false, true, true],
+        vob![false, true, false, true]
+    ]];
+    assert_eq!(kernel, expected);
+    for vector in kernel.iter_rows() {
+        assert!(m.mul_vob(vector).iter_set_bits(..).next().is_none());
+    }
+    assert_eq!(algebra::kernel(&algebra::identity(3)).row_size(), 0);
+}
+
+#[test]
+fn solve_test() {
+    let m = matrix![vec![
+        vob![true, false, true, false],
+        vob![false, true, true, true],
+        vob![true, true, false, true]
+    ]];
+    let rhs = vob![true, false, true];
+    let solution = algebra::solve(&m, &rhs).unwrap();
+    assert_eq!(m.mul_vob(&solution), rhs);
+    assert_eq!(algebra::solve(&m, &vob![true, false, false]), None);
+
+    for seed in 0..20 {
+        let m = pseudo_random_matrix(seed, 30, 40);
+        let x = pseudo_random_matrix(seed + 100, 1, 40).get_row(0).unwrap().clone();
+        let rhs = m.mul_vob(&x);
+        let solution = algebra::solve(&m, &rhs).unwrap();
+        assert_eq!(m.mul_vob(&solution), rhs);
+        let kernel = algebra::kernel(&m);
+        assert_eq!(kernel.row_size() + algebra::rank(&m), 40);
+        assert_eq!(algebra::row_reduce(&m).0.row_size(), algebra::rank(&m));
+    }
+}
+
+/// A pseudo random matrix of `rows` rows of `columns` bits, from `seed`.
+fn pseudo_random_matrix(seed: u64, rows: usize, columns: usize) -> algebra::Matrix {
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    let mut next_bit = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state.is_multiple_of(3)
+    };
+    matrix![(0..rows).map(|_| (0..columns).map(|_| next_bit()).collect()).collect()]
+}
+
+#[test]
+fn block_bits_test() {
+    use algebra::{BlockBits, LhsBits};
+
+    for len in [0, 1, 63, 64, 65, 200, 300].iter() {
+        let m = pseudo_random_matrix(*len as u64, 2, *len);
+        let (a, b) = (m.get_row(0).unwrap(), m.get_row(1).unwrap());
+        let (mut block_a, block_b) = (BlockBits::from_vob(a), BlockBits::from_vob(b));
+        assert_eq!(*a, block_a.to_vob());
+        assert_eq!(LhsBits::count_ones(a), block_a.count_ones());
+        assert_eq!(algebra::get_max_set_bit(a), block_a.max_set_bit());
+        let mut xored = a.clone();
+        xored.xor(b);
+        block_a.xor_assign(&block_b);
+        assert_eq!(xored, block_a.to_vob());
+        assert_eq!(xored.iter_set_bits(..).next().is_none(), block_a.is_zero());
+        if *len > 0 {
+            block_a.set_bit(len - 1, true);
+            assert!(block_a.get_bit(len - 1));
+            assert_eq!(Some(len - 1), block_a.max_set_bit());
+        }
+    }
+    assert_eq!(0, BlockBits::zeros(130).blocks().iter().map(|block| block.count_ones()).sum::<u32>());
+}
+
+#[test]
+fn linear_dependencies_bits_test() {
+    // More rows than columns, such that there are dependencies
+    for seed in 0..20 {
+        let m = pseudo_random_matrix(seed, 90, 70);
+        let with_vob = algebra::extract_linear_dependencies_with::<vob::Vob>(m.clone());
+        let with_blocks = algebra::extract_linear_dependencies_with::<algebra::BlockBits>(m.clone());
+        assert_eq!(with_vob, with_blocks);
+        assert_eq!(90 - algebra::rank(&m), with_vob.row_size());
+    }
+}
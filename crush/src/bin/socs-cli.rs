@@ -0,0 +1,44 @@
+//! Explore a system interactively: `socs-cli [FILE]` loads the system of `FILE` if given, then
+//! executes the commands read from the standard input, one per line. See the `soc::repl` module
+//! for the commands, or type `help`.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use crush::soc::repl::Repl;
+
+fn main() {
+    #[cfg(feature = "interrupt")]
+    crush::interrupt::install_handler().expect("failed to install the Ctrl-C handler");
+
+    let mut repl = Repl::new();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if let Some(path) = std::env::args().nth(1) {
+        if let Err(e) = repl.execute(&format!("load {}", path), &mut out) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    }
+    // The prompt is only shown to a user, not when a script is piped in
+    let interactive = io::stdin().is_terminal();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            write!(out, "> ").and_then(|_| out.flush()).expect("failed to write to the standard output");
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(e)) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+            None => break,
+        };
+        match repl.execute(&line, &mut out) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => eprintln!("error: {}", e),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! A budget on the memory used by the nodes of the `Bdd`s of a computation, such that it can react
+//! before running out of memory rather than being killed.
+//!
+//! A `MemoryBudget` is given a limit on the number of nodes (or the estimated number of bytes they
+//! use, see `metrics::estimated_bytes`), and is told the current number of nodes with `observe`
+//! as the computation goes. It answers with a `BudgetStatus`: within the budget, approaching the
+//! limit (beyond a fraction of it, by default `DEFAULT_WARNING`), or beyond the limit. It is up to
+//! the computation to react, e.g. by pruning more or by stopping with a partial result.
+//!
+//! Every observation is reported with `reporting::Event::MemorySnapshot`, each change of status
+//! with `reporting::Event::BudgetStatusChanged`, and the fraction of the limit in use as the gauge
+//! `memory.budget_used`.
+
+use std::fmt;
+
+use crate::{metrics, reporting};
+use crate::metrics::BYTES_PER_NODE;
+use crate::reporting::Event;
+use crate::soc::system::System;
+
+/// The default fraction of the limit beyond which a budget is approached.
+pub const DEFAULT_WARNING: f64 = 0.8;
+
+/// Where a number of nodes stands in a `MemoryBudget`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BudgetStatus {
+    Within,
+    /// Beyond the warning fraction of the limit, but not beyond the limit.
+    Approaching,
+    Exceeded,
+}
+
+impl BudgetStatus {
+    /// Return the name of the status, in snake case.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BudgetStatus::Within => "within",
+            BudgetStatus::Approaching => "approaching",
+            BudgetStatus::Exceeded => "exceeded",
+        }
+    }
+}
+
+impl fmt::Display for BudgetStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A limit on the number of nodes in memory, see the module documentation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryBudget {
+    limit: usize,
+    warning: f64,
+    peak: usize,
+    status: BudgetStatus,
+}
+
+impl MemoryBudget {
+    /// Construct a budget of `limit` nodes.
+    pub fn new(limit: usize) -> MemoryBudget {
+        MemoryBudget {
+            limit,
+            warning: DEFAULT_WARNING,
+            peak: 0,
+            status: BudgetStatus::Within,
+        }
+    }
+
+    /// Construct a budget of the number of nodes fitting in `bytes` bytes, see
+    /// `metrics::estimated_bytes`.
+    pub fn from_bytes(bytes: usize) -> MemoryBudget {
+        MemoryBudget::new(bytes / BYTES_PER_NODE)
+    }
+
+    /// Make the budget approached beyond the fraction `warning` of the limit, which is clamped
+    /// to [0, 1].
+    pub fn with_warning(mut self, warning: f64) -> MemoryBudget {
+        self.warning = warning.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Return the limit, in nodes.
+    #[inline]
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Return the estimated number of bytes used by `limit` nodes.
+    #[inline]
+    pub fn limit_bytes(&self) -> usize {
+        metrics::estimated_bytes(self.limit)
+    }
+
+    /// Return the number of nodes beyond which the budget is approached.
+    pub fn warning_nodes(&self) -> usize {
+        (self.limit as f64 * self.warning) as usize
+    }
+
+    /// Return the highest number of nodes observed so far.
+    #[inline]
+    pub fn peak(&self) -> usize {
+        self.peak
+    }
+
+    /// Return the status of the last observation, `Within` if there was none.
+    #[inline]
+    pub fn status(&self) -> BudgetStatus {
+        self.status
+    }
+
+    /// Return where `nodes` nodes stand in the budget.
+    pub fn status_of(&self, nodes: usize) -> BudgetStatus {
+        if nodes > self.limit {
+            BudgetStatus::Exceeded
+        } else if nodes > self.warning_nodes() {
+            BudgetStatus::Approaching
+        } else {
+            BudgetStatus::Within
+        }
+    }
+
+    /// Record that `nodes` nodes are in memory, reporting it in `source` (see the module
+    /// documentation), and return where they stand in the budget.
+    pub fn observe(&mut self, source: &'static str, nodes: usize) -> BudgetStatus {
+        self.peak = self.peak.max(nodes);
+        let status = self.status_of(nodes);
+        reporting::memory_snapshot(source, nodes);
+        metrics::gauge("memory.budget_used", nodes as f64 / self.limit.max(1) as f64);
+        if status != self.status {
+            reporting::report(source, Event::BudgetStatusChanged { nodes, limit: self.limit, status });
+            self.status = status;
+        }
+        status
+    }
+
+    /// Record the number of nodes of all the `Bdd`s of `system`, see `observe`.
+    pub fn observe_system(&mut self, source: &'static str, system: &System) -> BudgetStatus {
+        self.observe(source, system.get_size())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::reporting::InMemoryReporter;
+
+    use super::*;
+
+    #[test]
+    fn budget_status() {
+        let budget = MemoryBudget::new(100);
+        assert_eq!(80, budget.warning_nodes());
+        assert_eq!(BudgetStatus::Within, budget.status_of(80));
+        assert_eq!(BudgetStatus::Approaching, budget.status_of(81));
+        assert_eq!(BudgetStatus::Approaching, budget.status_of(100));
+        assert_eq!(BudgetStatus::Exceeded, budget.status_of(101));
+
+        let budget = MemoryBudget::from_bytes(10 * BYTES_PER_NODE + 1).with_warning(2.0);
+        assert_eq!((10, 10), (budget.limit(), budget.warning_nodes()));
+        assert_eq!(metrics::estimated_bytes(10), budget.limit_bytes());
+        assert_eq!(BudgetStatus::Within, budget.status_of(10));
+    }
+
+    #[test]
+    fn observe_budget() {
+        // Other tests may report concurrently, only the records of this one are looked at.
+        let _lock = reporting::TEST_REPORTER_LOCK.lock().unwrap();
+        let reporter = Arc::new(InMemoryReporter::new());
+        reporting::set_reporter(reporter.clone());
+        let mut budget = MemoryBudget::new(100);
+        for nodes in [10, 90, 95, 150, 20].iter() {
+            budget.observe("test.budget", *nodes);
+        }
+        reporting::clear_reporter();
+        assert_eq!((150, BudgetStatus::Within), (budget.peak(), budget.status()));
+
+        let changes: Vec<Event> = reporter.records()
+            .into_iter()
+            .filter(|record| record.source == "test.budget")
+            .map(|record| record.event)
+            .filter(|event| matches!(event, Event::BudgetStatusChanged { .. }))
+            .collect();
+        assert_eq!(vec![
+            Event::BudgetStatusChanged { nodes: 90, limit: 100, status: BudgetStatus::Approaching },
+            Event::BudgetStatusChanged { nodes: 150, limit: 100, status: BudgetStatus::Exceeded },
+            Event::BudgetStatusChanged { nodes: 20, limit: 100, status: BudgetStatus::Within },
+        ], changes);
+    }
+}
@@ -0,0 +1,141 @@
+//! Cooperative interruption of long running solves.
+//!
+//! Nothing is ever stopped abruptly: an interruption only raises a process wide flag, which the
+//! solvers check between two steps (see `check`). When they see it, they return an `Error` of kind
+//! `ErrorKind::Interrupted`, leaving the `System` in a consistent state. It is then up to the
+//! caller to save what was achieved so far, typically with `soc::io::print_checkpoint_to_file`.
+//!
+//! With the `interrupt` feature, `install_handler` raises the flag on Ctrl-C. A second Ctrl-C
+//! exits immediately, in case the current step takes too long to complete.
+//!
+//! A single solve is stopped with a `Cancellation` instead, given to the solvers taking one (e.g.
+//! `SimpleSolver::run_with` of pathfinder): cancelled from another thread, or once its deadline
+//! is passed. A `Cancellation` is also cancelled by the process wide flag.
+
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Exit code used when exiting because of an interruption, as done by shells for SIGINT.
+pub const EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Return true if an interruption was requested and not reset since.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Request the running solves to stop at the end of their current step.
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Forget any requested interruption, such that solves can be run again.
+pub fn reset() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+/// Return an `Error` of kind `ErrorKind::Interrupted` if an interruption was requested.
+pub fn check() -> Result<(), Error> {
+    if interrupted() {
+        Err(Error::new(ErrorKind::Interrupted, "solving was interrupted"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Install a Ctrl-C handler requesting an interruption, or exiting with `EXIT_CODE` if one was
+/// already requested.
+///
+/// Return an `Error` if a handler was already installed.
+#[cfg(feature = "interrupt")]
+pub fn install_handler() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            eprintln!("interrupted again, exiting without saving");
+            std::process::exit(EXIT_CODE);
+        }
+        eprintln!("interrupted, stopping after the current step (Ctrl-C again to exit now)");
+    })
+}
+
+/// A request to stop a single solve, see the module documentation. The clones of a `Cancellation`
+/// share its flag, such that one of them can be kept to cancel the solve given another one.
+#[derive(Clone, Debug, Default)]
+pub struct Cancellation {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl Cancellation {
+    /// Construct a `Cancellation` without deadline, only cancelled by `cancel` or `interrupt`.
+    pub fn new() -> Cancellation {
+        Cancellation::default()
+    }
+
+    /// Construct a `Cancellation` cancelled once `deadline` is passed.
+    pub fn with_deadline(deadline: Instant) -> Cancellation {
+        Cancellation { deadline: Some(deadline), ..Cancellation::default() }
+    }
+
+    /// Construct a `Cancellation` cancelled once `timeout` has elapsed from now.
+    pub fn with_timeout(timeout: Duration) -> Cancellation {
+        Cancellation::with_deadline(Instant::now() + timeout)
+    }
+
+    /// Request the solve to stop at the end of its current step.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    #[inline]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Return true if the deadline, if any, is passed.
+    pub fn deadline_passed(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Return true if the solve was cancelled, its deadline is passed or an interruption was
+    /// requested (see `interrupted`).
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || self.deadline_passed() || interrupted()
+    }
+
+    /// Return an `Error` of kind `ErrorKind::Interrupted` if the solve is cancelled, or of kind
+    /// `ErrorKind::TimedOut` if its deadline is passed.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.deadline_passed() {
+            Err(Error::new(ErrorKind::TimedOut, "solving reached its deadline"))
+        } else if self.is_cancelled() {
+            Err(Error::new(ErrorKind::Interrupted, "solving was cancelled"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cancellation() {
+        let cancellation = Cancellation::new();
+        let clone = cancellation.clone();
+        assert!(!clone.is_cancelled() && clone.check().is_ok());
+        cancellation.cancel();
+        assert!(clone.is_cancelled());
+        assert_eq!(ErrorKind::Interrupted, clone.check().unwrap_err().kind());
+        assert!(!Cancellation::new().is_cancelled());
+
+        let expired = Cancellation::with_deadline(Instant::now());
+        assert!(expired.is_cancelled());
+        assert_eq!(ErrorKind::TimedOut, expired.check().unwrap_err().kind());
+        assert!(!Cancellation::with_timeout(Duration::from_secs(3600)).is_cancelled());
+    }
+}
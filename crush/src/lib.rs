@@ -1,15 +1,96 @@
+//! Systems of equations represented by binary decision diagrams, and the algebra and solvers
+//! absorbing their linear dependencies.
+//!
+//! Without the `std` feature, crush is `no_std` and only the core is built: the bdds and their
+//! counting (`soc::bdd`, `soc::store`) and the Gaussian elimination (`algebra`), on `alloc`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "parse")]
 #[macro_use]
 extern crate nom;
 #[macro_use]
 #[cfg(test)]
 extern crate vob;
 
+extern crate alloc;
+// The code expanded from the `bdd!` and `system!` macros refers to this crate as `::crush`
+extern crate self as crush;
+
+/// Build a bdd or a system from specs checked at compile time, see the `crush_macros` crate.
+pub use crush_macros::{bdd, system};
+
 #[macro_use]
 pub mod algebra;
+#[cfg(feature = "std")]
+pub mod budget;
+#[cfg(feature = "std")]
+pub mod interrupt;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod reporting;
 pub mod soc;
+#[cfg(feature = "std")]
 pub mod solver;
 
-use core::hash::BuildHasherDefault;
-use std::collections::{HashMap, HashSet};
-type AHashMap<K, V> = HashMap<K, V, BuildHasherDefault<ahash::AHasher>>;
-type AHashSet<K> = HashSet<K, BuildHasherDefault<ahash::AHasher>>;
+use core::hash::{BuildHasherDefault, Hasher};
+use hashbrown::{HashMap, HashSet};
+type AHashMap<K, V> = HashMap<K, V, BuildHasherDefault<FixedHasher>>;
+type AHashSet<K> = HashSet<K, BuildHasherDefault<FixedHasher>>;
+
+/// The hasher of the maps and sets of crush: an `AHasher` with fixed keys.
+///
+/// The default keys of `AHasher` are drawn when compiling, such that the iteration orders of the
+/// maps, and thus the order in which bdds are absorbed or nodes are renamed, changed from one
+/// build to the next. With fixed keys, a run can be reproduced by any build of crush, e.g. from a
+/// bug report.
+pub struct FixedHasher(ahash::AHasher);
+
+impl Default for FixedHasher {
+    fn default() -> Self {
+        FixedHasher(ahash::AHasher::new_with_keys(0x243f_6a88_85a3_08d3, 0x1319_8a2e_0370_7344))
+    }
+}
+
+impl Hasher for FixedHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes)
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.0.write_u8(i)
+    }
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.0.write_u16(i)
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.0.write_u32(i)
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.0.write_u64(i)
+    }
+
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        self.0.write_u128(i)
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.0.write_usize(i)
+    }
+}
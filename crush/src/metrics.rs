@@ -0,0 +1,406 @@
+//! Metrics (counters, gauges and timings) of crush and the crates built on it, the numbers side of
+//! `reporting`.
+//!
+//! All metrics go through the `MetricsSink` trait. The free functions of this module (`counter`,
+//! `gauge`, `timing`, `time`) aggregate them in a process-wide `InMemoryMetrics`, whose content can
+//! be read at any time with `snapshot`, and forward them to the sink installed with `set_sink` and
+//! to the `Reporter` installed with `reporting::set_reporter`, if any. This way the algorithms don't
+//! have to carry a sink around, and user code can plug any exporter (Prometheus, statsd, ...) by
+//! implementing `MetricsSink` for it, or the metric methods of `Reporter` to get the events too.
+//!
+//! Metric names are dot separated, starting with the crate or component reporting them, e.g.
+//! `solver.nodes_remaining`.
+//!
+//! The memory use of the phases of a computation (building a system, a join, ...) can be profiled
+//! with `phase`: while a phase runs, the node counts reported with `observe_nodes` are tracked, and
+//! its peak is reported as gauges when it ends.
+
+use core::mem::size_of;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+
+use crate::reporting::{self, Record, Reporter};
+use crate::soc::{Id, Node};
+
+/// A destination for metrics.
+///
+/// The methods take `&self` as the sink is shared by the whole process (and possibly threads),
+/// implementations are expected to use interior mutability.
+pub trait MetricsSink: Send + Sync {
+    /// Increment the counter `name` by `value`.
+    fn counter(&self, name: &str, value: u64);
+    /// Set the gauge `name` to `value`.
+    fn gauge(&self, name: &str, value: f64);
+    /// Record that one occurrence of `name` took `duration`.
+    fn timing(&self, name: &str, duration: Duration);
+}
+
+/// A sink dropping every metric.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopMetrics;
+
+impl MetricsSink for NoopMetrics {
+    fn counter(&self, _name: &str, _value: u64) {}
+    fn gauge(&self, _name: &str, _value: f64) {}
+    fn timing(&self, _name: &str, _duration: Duration) {}
+}
+
+/// Summary of the occurrences of a timing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TimingSummary {
+    pub count: u64,
+    pub total: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl TimingSummary {
+    fn record(&mut self, duration: Duration) {
+        if self.count == 0 || duration < self.min {
+            self.min = duration;
+        }
+        if duration > self.max {
+            self.max = duration;
+        }
+        self.count += 1;
+        self.total += duration;
+    }
+
+    /// Return the mean duration of the occurrences, zero if there was none.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::default()
+        } else {
+            self.total / self.count as u32
+        }
+    }
+}
+
+/// The content of an `InMemoryMetrics` at a given time, sorted by name.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+    pub counters: BTreeMap<String, u64>,
+    pub gauges: BTreeMap<String, f64>,
+    pub timings: BTreeMap<String, TimingSummary>,
+}
+
+/// A sink keeping every metric in memory: the total of the counters, the last value of the gauges
+/// and a summary of the timings.
+#[derive(Debug, Default)]
+pub struct InMemoryMetrics {
+    metrics: Mutex<MetricsSnapshot>,
+}
+
+impl InMemoryMetrics {
+    /// Construct an empty `InMemoryMetrics`
+    pub fn new() -> InMemoryMetrics {
+        Default::default()
+    }
+
+    /// Return a copy of the metrics recorded so far.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.metrics.lock().unwrap().clone()
+    }
+
+    /// Forget every metric recorded so far.
+    pub fn clear(&self) {
+        *self.metrics.lock().unwrap() = MetricsSnapshot::default();
+    }
+}
+
+impl MetricsSink for InMemoryMetrics {
+    fn counter(&self, name: &str, value: u64) {
+        *self.metrics.lock().unwrap().counters.entry(name.to_string()).or_insert(0) += value;
+    }
+
+    fn gauge(&self, name: &str, value: f64) {
+        self.metrics.lock().unwrap().gauges.insert(name.to_string(), value);
+    }
+
+    fn timing(&self, name: &str, duration: Duration) {
+        self.metrics
+            .lock()
+            .unwrap()
+            .timings
+            .entry(name.to_string())
+            .or_default()
+            .record(duration);
+    }
+}
+
+/// A reporter sending the metrics to a sink and ignoring the records, e.g. to send the metrics to
+/// a sink from the `reporting::Reporters` of a run.
+pub struct SinkReporter {
+    sink: Arc<dyn MetricsSink>,
+}
+
+impl SinkReporter {
+    /// Construct a `SinkReporter` sending the metrics to `sink`
+    pub fn new(sink: Arc<dyn MetricsSink>) -> SinkReporter {
+        SinkReporter { sink }
+    }
+}
+
+impl Reporter for SinkReporter {
+    fn report(&self, _record: &Record) {}
+
+    fn counter(&self, name: &str, value: u64) {
+        self.sink.counter(name, value);
+    }
+
+    fn gauge(&self, name: &str, value: f64) {
+        self.sink.gauge(name, value);
+    }
+
+    fn timing(&self, name: &str, duration: Duration) {
+        self.sink.timing(name, duration);
+    }
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in self.counters.iter() {
+            writeln!(f, "{} {}", name, value)?;
+        }
+        for (name, value) in self.gauges.iter() {
+            writeln!(f, "{} {}", name, value)?;
+        }
+        for (name, t) in self.timings.iter() {
+            writeln!(
+                f,
+                "{} count {} total {:?} mean {:?} min {:?} max {:?}",
+                name,
+                t.count,
+                t.total,
+                t.mean(),
+                t.min,
+                t.max
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The metrics reported so far.
+static METRICS: InMemoryMetrics = InMemoryMetrics {
+    metrics: Mutex::new(MetricsSnapshot {
+        counters: BTreeMap::new(),
+        gauges: BTreeMap::new(),
+        timings: BTreeMap::new(),
+    }),
+};
+
+/// The sink installed with `set_sink`, `None` until then.
+static SINK: RwLock<Option<Arc<dyn MetricsSink>>> = RwLock::new(None);
+
+/// Install `sink` as a destination of all the metrics reported from now on, on top of the
+/// process-wide `InMemoryMetrics` and the installed `Reporter`.
+pub fn set_sink(sink: Arc<dyn MetricsSink>) {
+    *SINK.write().unwrap() = Some(sink);
+}
+
+/// Remove the sink installed with `set_sink`, if any.
+pub fn clear_sink() {
+    *SINK.write().unwrap() = None;
+}
+
+/// Return a copy of the metrics reported so far by the whole process.
+pub fn snapshot() -> MetricsSnapshot {
+    METRICS.snapshot()
+}
+
+fn with_sink<F: FnOnce(&dyn MetricsSink)>(f: F) {
+    if let Some(sink) = SINK.read().unwrap().as_ref() {
+        f(sink.as_ref());
+    }
+}
+
+/// Increment the counter `name` by `value`.
+pub fn counter(name: &str, value: u64) {
+    METRICS.counter(name, value);
+    with_sink(|sink| sink.counter(name, value));
+    reporting::with_reporter(|reporter| reporter.counter(name, value));
+}
+
+/// Set the gauge `name` to `value`.
+pub fn gauge(name: &str, value: f64) {
+    METRICS.gauge(name, value);
+    with_sink(|sink| sink.gauge(name, value));
+    reporting::with_reporter(|reporter| reporter.gauge(name, value));
+}
+
+/// Record that one occurrence of `name` took `duration`.
+pub fn timing(name: &str, duration: Duration) {
+    METRICS.timing(name, duration);
+    with_sink(|sink| sink.timing(name, duration));
+    reporting::with_reporter(|reporter| reporter.timing(name, duration));
+}
+
+/// Run `f`, record the time it took as a timing of `name` and return its result.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub fn time<T, F: FnOnce() -> T>(name: &str, f: F) -> T {
+    let start = Instant::now();
+    let result = f();
+    timing(name, start.elapsed());
+    result
+}
+
+/// Run `f` and return its result. There is no clock on wasm32-unknown-unknown (`Instant::now`
+/// panics), so nothing is recorded.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub fn time<T, F: FnOnce() -> T>(_name: &str, f: F) -> T {
+    f()
+}
+
+/// Estimated number of bytes used by a node of a `Bdd`: its entry in the map of its level, plus
+/// the control byte of the map, with the map at its maximal load factor of 7/8.
+pub const BYTES_PER_NODE: usize = (size_of::<(Id, Node)>() + 1) * 8 / 7;
+
+/// Return the estimated number of bytes used by `nodes` nodes, see `BYTES_PER_NODE`.
+pub fn estimated_bytes(nodes: usize) -> usize {
+    nodes * BYTES_PER_NODE
+}
+
+/// A running phase, see `phase`.
+pub struct Phase {
+    id: u64,
+    name: String,
+}
+
+/// Peak node count of a running phase.
+struct ActivePhase {
+    id: u64,
+    peak_nodes: usize,
+}
+
+static NEXT_PHASE_ID: AtomicU64 = AtomicU64::new(0);
+
+static ACTIVE_PHASES: Mutex<Vec<ActivePhase>> = Mutex::new(Vec::new());
+
+/// Peak node count of every phase which ended, over all its occurrences.
+static PHASE_PEAKS: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
+
+/// Start the phase `name`, which runs until the returned `Phase` is dropped.
+///
+/// When it ends, the highest node count reported with `observe_nodes` while it was running is
+/// reported as the gauges `<name>.peak_nodes` and `<name>.peak_bytes` (see `estimated_bytes`).
+/// For a phase occurring several times, like a join, the gauges hold the peak over all the
+/// occurrences. Phases can be nested, a node count is then accounted to all of them.
+pub fn phase(name: &str) -> Phase {
+    let id = NEXT_PHASE_ID.fetch_add(1, Ordering::Relaxed);
+    ACTIVE_PHASES.lock().unwrap().push(ActivePhase { id, peak_nodes: 0 });
+    Phase { id, name: name.to_string() }
+}
+
+/// Report `nodes` as the number of nodes currently in memory, for the peaks of the running phases.
+pub fn observe_nodes(nodes: usize) {
+    for phase in ACTIVE_PHASES.lock().unwrap().iter_mut() {
+        phase.peak_nodes = phase.peak_nodes.max(nodes);
+    }
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        let peak_nodes = {
+            let mut active = ACTIVE_PHASES.lock().unwrap();
+            let index = active.iter().position(|phase| phase.id == self.id).unwrap();
+            active.swap_remove(index).peak_nodes
+        };
+        let peak_nodes = {
+            let mut peaks = PHASE_PEAKS.lock().unwrap();
+            let peak = peaks.entry(self.name.clone()).or_insert(0);
+            *peak = (*peak).max(peak_nodes);
+            *peak
+        };
+        gauge(&format!("{}.peak_nodes", self.name), peak_nodes as f64);
+        gauge(&format!("{}.peak_bytes", self.name), estimated_bytes(peak_nodes) as f64);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn in_memory_metrics() {
+        let sink = InMemoryMetrics::new();
+        sink.counter("a.count", 2);
+        sink.counter("a.count", 3);
+        sink.gauge("a.size", 4.0);
+        sink.gauge("a.size", 1.0);
+        sink.timing("a.time", Duration::from_millis(10));
+        sink.timing("a.time", Duration::from_millis(30));
+        let snapshot = sink.snapshot();
+        assert_eq!(snapshot.counters["a.count"], 5);
+        assert_eq!(snapshot.gauges["a.size"], 1.0);
+        let t = snapshot.timings["a.time"];
+        assert_eq!(t.count, 2);
+        assert_eq!(t.min, Duration::from_millis(10));
+        assert_eq!(t.max, Duration::from_millis(30));
+        assert_eq!(t.mean(), Duration::from_millis(20));
+        sink.clear();
+        assert_eq!(sink.snapshot(), MetricsSnapshot::default());
+    }
+
+    #[test]
+    fn phase_peaks() {
+        // Other tests may observe nodes concurrently, but far fewer than here.
+        let outer = phase("test.outer");
+        observe_nodes(1 << 40);
+        {
+            let _inner = phase("test.inner");
+            observe_nodes(1 << 41);
+            observe_nodes(1 << 39);
+        }
+        drop(outer);
+        let inner = phase("test.inner");
+        observe_nodes(1 << 38);
+        drop(inner);
+        let gauges = snapshot().gauges;
+        assert_eq!(gauges["test.outer.peak_nodes"], (1u64 << 41) as f64);
+        assert_eq!(gauges["test.inner.peak_nodes"], (1u64 << 41) as f64);
+        assert_eq!(gauges["test.inner.peak_bytes"], estimated_bytes(1 << 41) as f64);
+    }
+
+    #[test]
+    fn installed_sink() {
+        let installed = Arc::new(InMemoryMetrics::new());
+        set_sink(installed.clone());
+        counter("test.sunk", 2);
+        timing("test.sunk_time", Duration::from_millis(5));
+        set_sink(Arc::new(NoopMetrics));
+        counter("test.sunk", 3);
+        clear_sink();
+        counter("test.sunk", 4);
+
+        // Aggregated here whatever the sink, sent to each sink while it is installed
+        assert_eq!(snapshot().counters["test.sunk"], 9);
+        let sunk = installed.snapshot();
+        assert_eq!(sunk.counters["test.sunk"], 2);
+        assert_eq!(sunk.timings["test.sunk_time"].count, 1);
+    }
+
+    #[test]
+    fn reported_metrics() {
+        let _lock = reporting::TEST_REPORTER_LOCK.lock().unwrap();
+        let installed = Arc::new(InMemoryMetrics::new());
+        reporting::set_reporter(Arc::new(reporting::Reporters::new(vec![Arc::new(SinkReporter::new(installed.clone()))])));
+        counter("test.reported", 2);
+        gauge("test.reported_size", 3.0);
+        reporting::clear_reporter();
+        counter("test.reported", 5);
+
+        // Aggregated here whether a reporter is installed or not, sent to the reporter meanwhile
+        assert_eq!(snapshot().counters["test.reported"], 7);
+        let reported = installed.snapshot();
+        assert_eq!(reported.counters["test.reported"], 2);
+        assert_eq!(reported.gauges["test.reported_size"], 3.0);
+    }
+}
@@ -0,0 +1,683 @@
+//! Structured reporting of what crush and the crates built on it are doing: joins, absorptions,
+//! prunings, reorderings, memory use (see also `budget`) and the stages of long computations.
+//!
+//! Each `Event` is sent as a timestamped `Record` to the `Reporter` installed for the whole process
+//! with `set_reporter`, such that a run can be followed while it goes on, or replayed afterwards.
+//! The same reporter receives the metrics of `metrics` (counters, gauges and timings), which are
+//! also aggregated there. Until a reporter is installed, the events are dropped.
+//!
+//! The records can be written to several sinks: `StderrReporter` for a human, `CsvReporter` and
+//! `JsonReporter` for tools, and `InMemoryReporter` to inspect them from code. `Reporters` sends
+//! each record to several of them. Any other destination can be plugged by implementing `Reporter`
+//! for it. An exporter of the metrics alone (Prometheus, statsd, ...) can rather implement
+//! `metrics::MetricsSink`, see `metrics::set_sink` and `metrics::SinkReporter`.
+//!
+//! The reporters writing to a file (`CsvReporter::create`, `JsonReporter::create`) require the
+//! `io` feature.
+//!
+//! The source of a record is dot separated, starting with the crate or component reporting it,
+//! e.g. `crush.solver`, as for the names of the metrics.
+
+use std::fmt;
+#[cfg(feature = "io")]
+use std::fs::File;
+use std::io::{self, Write};
+#[cfg(feature = "io")]
+use std::io::BufWriter;
+#[cfg(feature = "io")]
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::budget::BudgetStatus;
+use crate::metrics;
+use crate::soc::{Id, stats::SystemStats};
+
+/// Something which happened, see the module documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The Bdds `bdds` are about to be joined into the first one, the system having `nodes` nodes.
+    JoinStarted { bdds: Vec<Id>, nodes: usize },
+    /// A linear dependency was resolved in `bdd`, which has `nodes` nodes afterwards.
+    DependencyResolved { bdd: Id, nodes: usize },
+    /// An independency was dropped from `bdd`, which has `nodes` nodes afterwards.
+    IndependencyDropped { bdd: Id, nodes: usize },
+    /// `levels` levels of `bdd` were absorbed.
+    Absorbed {
+        bdd: Id,
+        levels: usize,
+        nodes_before: usize,
+        nodes_after: usize,
+    },
+    /// `bdd` was pruned, `threshold` being the lowest weight of the trails pruned away, if known.
+    Pruned {
+        bdd: Id,
+        nodes_before: usize,
+        nodes_after: usize,
+        threshold: Option<u32>,
+    },
+    /// The levels of `bdd` were reordered by sifting, with `swaps` swaps, see `Bdd::sift_levels`.
+    Sifted {
+        bdd: Id,
+        swaps: usize,
+        nodes_before: usize,
+        nodes_after: usize,
+    },
+    /// `nodes` nodes are in memory, using an estimated `bytes` bytes (see
+    /// `metrics::estimated_bytes`).
+    MemorySnapshot { nodes: usize, bytes: usize },
+    /// The shape of the system at some point, see `stats_snapshot`: the number of nodes of each
+    /// `Bdd` by increasing id, the widths of the levels of the biggest one, the number of
+    /// variables in some LHS, the rank of the LHS's and the number of jumping edges.
+    StatsSnapshot {
+        bdd_nodes: Vec<usize>,
+        biggest_widths: Vec<usize>,
+        active_vars: usize,
+        lhs_rank: usize,
+        jumping_edges: usize,
+    },
+    /// The number of nodes in memory moved to `status` in a budget of `limit` nodes, see
+    /// `budget::MemoryBudget`.
+    BudgetStatusChanged { nodes: usize, limit: usize, status: BudgetStatus },
+    /// The stage `stage` of a computation started, see `stage`.
+    StageStarted { stage: String },
+    /// The stage `stage` of a computation ended, after `duration`.
+    StageDone { stage: String, duration: Duration },
+    /// The step `step` (from 1) of a solving is done with, e.g. a join and the absorptions and
+    /// pruning following it. The system is left with `bdds` Bdds of `nodes` nodes in total, and
+    /// its widest level has `max_width` nodes.
+    Step {
+        step: usize,
+        bdds: usize,
+        nodes: usize,
+        max_width: usize,
+    },
+    /// The round `round` (from 1) of `rounds` of a solving is done with, the weight of the best
+    /// trails being within the bounds, if known.
+    RoundDone {
+        round: usize,
+        rounds: usize,
+        lower_bound: Option<u32>,
+        upper_bound: Option<u32>,
+    },
+    /// The estimated work left to a solving: `bdds` Bdds to join, and `dependencies` linear
+    /// dependencies among the LHS's to absorb (see `System::lhs_dependency_count`).
+    RemainingWork { bdds: usize, dependencies: usize },
+    /// An operation failed with `error` without stopping the computation, e.g. writing a
+    /// checkpoint: `operation` tells which and on what.
+    Failure { operation: String, error: String },
+}
+
+/// The value of a field of an `Event`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(u64),
+    Text(String),
+    List(Vec<u64>),
+    /// An unknown value.
+    Null,
+}
+
+impl Event {
+    /// Return the name of the kind of the event, in snake case.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::JoinStarted { .. } => "join_started",
+            Event::DependencyResolved { .. } => "dependency_resolved",
+            Event::IndependencyDropped { .. } => "independency_dropped",
+            Event::Absorbed { .. } => "absorbed",
+            Event::Pruned { .. } => "pruned",
+            Event::Sifted { .. } => "sifted",
+            Event::MemorySnapshot { .. } => "memory_snapshot",
+            Event::StatsSnapshot { .. } => "stats_snapshot",
+            Event::BudgetStatusChanged { .. } => "budget_status_changed",
+            Event::StageStarted { .. } => "stage_started",
+            Event::StageDone { .. } => "stage_done",
+            Event::Step { .. } => "step",
+            Event::RoundDone { .. } => "round_done",
+            Event::RemainingWork { .. } => "remaining_work",
+            Event::Failure { .. } => "failure",
+        }
+    }
+
+    /// Return the fields of the event as (name, value) pairs, a duration being in milliseconds.
+    pub fn fields(&self) -> Vec<(&'static str, Value)> {
+        let int = |value: usize| Value::Int(value as u64);
+        let bound = |bound: Option<u32>| bound.map_or(Value::Null, |bound| Value::Int(u64::from(bound)));
+        match self {
+            Event::JoinStarted { bdds, nodes } => vec![
+                ("bdds", Value::List(bdds.iter().map(|id| **id as u64).collect())),
+                ("nodes", int(*nodes)),
+            ],
+            Event::DependencyResolved { bdd, nodes } | Event::IndependencyDropped { bdd, nodes } => {
+                vec![("bdd", int(**bdd)), ("nodes", int(*nodes))]
+            }
+            Event::Absorbed { bdd, levels, nodes_before, nodes_after } => vec![
+                ("bdd", int(**bdd)),
+                ("levels", int(*levels)),
+                ("nodes_before", int(*nodes_before)),
+                ("nodes_after", int(*nodes_after)),
+            ],
+            Event::Pruned { bdd, nodes_before, nodes_after, threshold } => vec![
+                ("bdd", int(**bdd)),
+                ("nodes_before", int(*nodes_before)),
+                ("nodes_after", int(*nodes_after)),
+                ("threshold", bound(*threshold)),
+            ],
+            Event::Sifted { bdd, swaps, nodes_before, nodes_after } => vec![
+                ("bdd", int(**bdd)),
+                ("swaps", int(*swaps)),
+                ("nodes_before", int(*nodes_before)),
+                ("nodes_after", int(*nodes_after)),
+            ],
+            Event::MemorySnapshot { nodes, bytes } => vec![("nodes", int(*nodes)), ("bytes", int(*bytes))],
+            Event::StatsSnapshot { bdd_nodes, biggest_widths, active_vars, lhs_rank, jumping_edges } => vec![
+                ("bdd_nodes", Value::List(bdd_nodes.iter().map(|nodes| *nodes as u64).collect())),
+                ("biggest_widths", Value::List(biggest_widths.iter().map(|width| *width as u64).collect())),
+                ("active_vars", int(*active_vars)),
+                ("lhs_rank", int(*lhs_rank)),
+                ("jumping_edges", int(*jumping_edges)),
+            ],
+            Event::BudgetStatusChanged { nodes, limit, status } => vec![
+                ("nodes", int(*nodes)),
+                ("limit", int(*limit)),
+                ("status", Value::Text(status.name().to_string())),
+            ],
+            Event::StageStarted { stage } => vec![("stage", Value::Text(stage.clone()))],
+            Event::StageDone { stage, duration } => vec![
+                ("stage", Value::Text(stage.clone())),
+                ("duration_ms", Value::Int(duration.as_millis() as u64)),
+            ],
+            Event::Step { step, bdds, nodes, max_width } => vec![
+                ("step", int(*step)),
+                ("bdds", int(*bdds)),
+                ("nodes", int(*nodes)),
+                ("max_width", int(*max_width)),
+            ],
+            Event::RoundDone { round, rounds, lower_bound, upper_bound } => vec![
+                ("round", int(*round)),
+                ("rounds", int(*rounds)),
+                ("lower_bound", bound(*lower_bound)),
+                ("upper_bound", bound(*upper_bound)),
+            ],
+            Event::RemainingWork { bdds, dependencies } => vec![
+                ("bdds", int(*bdds)),
+                ("dependencies", int(*dependencies)),
+            ],
+            Event::Failure { operation, error } => vec![
+                ("operation", Value::Text(operation.clone())),
+                ("error", Value::Text(error.clone())),
+            ],
+        }
+    }
+}
+
+/// An `Event`, when and where it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub timestamp: SystemTime,
+    pub source: &'static str,
+    pub event: Event,
+}
+
+impl Record {
+    /// Return the milliseconds elapsed between the Unix epoch and the record.
+    pub fn timestamp_ms(&self) -> u64 {
+        self.timestamp.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64)
+    }
+}
+
+impl fmt::Display for Record {
+    /// The timestamp in seconds, the source, the name of the event then its fields, e.g.
+    /// `[1760000000.123] crush.solver memory_snapshot nodes=42 bytes=1344`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ms = self.timestamp_ms();
+        write!(f, "[{}.{:03}] {} {}", ms / 1000, ms % 1000, self.source, self.event.name())?;
+        for (name, value) in self.event.fields() {
+            match value {
+                Value::Int(value) => write!(f, " {}={}", name, value)?,
+                Value::Text(text) => write!(f, " {}={:?}", name, text)?,
+                Value::List(values) => write!(f, " {}={:?}", name, values)?,
+                Value::Null => write!(f, " {}=?", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A destination for records and metrics.
+///
+/// The methods take `&self` as the reporter is shared by the whole process (and possibly threads),
+/// implementations are expected to use interior mutability. A failure to write a record is
+/// reported to stderr, and doesn't stop the computation reporting it. The metrics are ignored
+/// unless the reporter overrides their methods, as they are aggregated by `metrics` anyway.
+pub trait Reporter: Send + Sync {
+    /// Report `record`.
+    fn report(&self, record: &Record);
+    /// Write out the records buffered so far, if any.
+    fn flush(&self) {}
+    /// Increment the counter `name` by `value`, see `metrics::counter`.
+    fn counter(&self, _name: &str, _value: u64) {}
+    /// Set the gauge `name` to `value`, see `metrics::gauge`.
+    fn gauge(&self, _name: &str, _value: f64) {}
+    /// Record that one occurrence of `name` took `duration`, see `metrics::timing`.
+    fn timing(&self, _name: &str, _duration: Duration) {}
+}
+
+/// A reporter printing each record on its own line to stderr, see the `Display` of `Record`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StderrReporter;
+
+impl Reporter for StderrReporter {
+    fn report(&self, record: &Record) {
+        eprintln!("{}", record);
+    }
+}
+
+/// A reporter writing the records as CSV, with the columns `timestamp_ms`, `source`, `event` and
+/// `fields`, the last one holding the fields of the event as `name=value` separated by spaces,
+/// e.g.
+///
+/// ```text
+/// timestamp_ms,source,event,fields
+/// 1760000000123,crush.solver,join_started,bdds=3;5 nodes=1024
+/// ```
+#[derive(Debug)]
+pub struct CsvReporter<W: Write + Send> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> CsvReporter<W> {
+    /// Create a reporter writing to `writer`, starting with the header.
+    pub fn new(mut writer: W) -> io::Result<CsvReporter<W>> {
+        writeln!(writer, "timestamp_ms,source,event,fields")?;
+        Ok(CsvReporter { writer: Mutex::new(writer) })
+    }
+
+    /// Return the writer, e.g. to read back what was written.
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap()
+    }
+}
+
+#[cfg(feature = "io")]
+impl CsvReporter<BufWriter<File>> {
+    /// Create a reporter writing to the file at `path`, truncating it if it exists.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write + Send> Reporter for CsvReporter<W> {
+    fn report(&self, record: &Record) {
+        let fields: Vec<String> = record.event.fields()
+            .into_iter()
+            .map(|(name, value)| match value {
+                Value::Int(value) => format!("{}={}", name, value),
+                Value::Text(text) => format!("{}={}", name, text),
+                Value::List(values) => {
+                    let values: Vec<String> = values.iter().map(u64::to_string).collect();
+                    format!("{}={}", name, values.join(";"))
+                }
+                Value::Null => format!("{}=", name),
+            })
+            .collect();
+        let line = format!("{},{},{},{}", record.timestamp_ms(), csv_escape(record.source),
+                           record.event.name(), csv_escape(&fields.join(" ")));
+        if let Err(e) = writeln!(self.writer.lock().unwrap(), "{}", line) {
+            eprintln!("Failed to report a record: {}", e);
+        }
+    }
+
+    fn flush(&self) {
+        if let Err(e) = self.writer.lock().unwrap().flush() {
+            eprintln!("Failed to flush the records: {}", e);
+        }
+    }
+}
+
+/// A reporter writing each record as a JSON object on its own line, e.g.
+///
+/// ```text
+/// {"timestamp_ms":1760000000123,"source":"crush.solver","event":"join_started","bdds":[3,5],"nodes":1024}
+/// ```
+///
+/// with the fields of the event after its name, an unknown value being `null`.
+#[derive(Debug)]
+pub struct JsonReporter<W: Write + Send> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonReporter<W> {
+    pub fn new(writer: W) -> JsonReporter<W> {
+        JsonReporter { writer: Mutex::new(writer) }
+    }
+
+    /// Return the writer, e.g. to read back what was written.
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap()
+    }
+}
+
+#[cfg(feature = "io")]
+impl JsonReporter<BufWriter<File>> {
+    /// Create a reporter writing to the file at `path`, truncating it if it exists.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write + Send> Reporter for JsonReporter<W> {
+    fn report(&self, record: &Record) {
+        let mut line = format!("{{\"timestamp_ms\":{},\"source\":{},\"event\":\"{}\"",
+                               record.timestamp_ms(), json_string(record.source), record.event.name());
+        for (name, value) in record.event.fields() {
+            let value = match value {
+                Value::Int(value) => value.to_string(),
+                Value::Text(text) => json_string(&text),
+                Value::List(values) => {
+                    let values: Vec<String> = values.iter().map(u64::to_string).collect();
+                    format!("[{}]", values.join(","))
+                }
+                Value::Null => "null".to_string(),
+            };
+            line.push_str(&format!(",\"{}\":{}", name, value));
+        }
+        line.push('}');
+        if let Err(e) = writeln!(self.writer.lock().unwrap(), "{}", line) {
+            eprintln!("Failed to report a record: {}", e);
+        }
+    }
+
+    fn flush(&self) {
+        if let Err(e) = self.writer.lock().unwrap().flush() {
+            eprintln!("Failed to flush the records: {}", e);
+        }
+    }
+}
+
+/// A reporter keeping every record in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryReporter {
+    records: Mutex<Vec<Record>>,
+}
+
+impl InMemoryReporter {
+    /// Construct an empty `InMemoryReporter`
+    pub fn new() -> InMemoryReporter {
+        Default::default()
+    }
+
+    /// Return a copy of the records reported so far, in order.
+    pub fn records(&self) -> Vec<Record> {
+        self.records.lock().unwrap().clone()
+    }
+
+    /// Forget every record reported so far.
+    pub fn clear(&self) {
+        self.records.lock().unwrap().clear();
+    }
+}
+
+impl Reporter for InMemoryReporter {
+    fn report(&self, record: &Record) {
+        self.records.lock().unwrap().push(record.clone());
+    }
+}
+
+/// A reporter sending each record and metric to several reporters, in order.
+#[derive(Default, Clone)]
+pub struct Reporters {
+    reporters: Vec<Arc<dyn Reporter>>,
+}
+
+impl Reporters {
+    pub fn new(reporters: Vec<Arc<dyn Reporter>>) -> Reporters {
+        Reporters { reporters }
+    }
+
+    /// Add `reporter` to the reporters the records are sent to.
+    pub fn push(&mut self, reporter: Arc<dyn Reporter>) {
+        self.reporters.push(reporter);
+    }
+}
+
+impl Reporter for Reporters {
+    fn report(&self, record: &Record) {
+        for reporter in self.reporters.iter() {
+            reporter.report(record);
+        }
+    }
+
+    fn flush(&self) {
+        for reporter in self.reporters.iter() {
+            reporter.flush();
+        }
+    }
+
+    fn counter(&self, name: &str, value: u64) {
+        for reporter in self.reporters.iter() {
+            reporter.counter(name, value);
+        }
+    }
+
+    fn gauge(&self, name: &str, value: f64) {
+        for reporter in self.reporters.iter() {
+            reporter.gauge(name, value);
+        }
+    }
+
+    fn timing(&self, name: &str, duration: Duration) {
+        for reporter in self.reporters.iter() {
+            reporter.timing(name, duration);
+        }
+    }
+}
+
+/// Held by the tests installing a reporter, such that they don't replace each other's.
+#[cfg(test)]
+pub(crate) static TEST_REPORTER_LOCK: Mutex<()> = Mutex::new(());
+
+/// The reporter installed with `set_reporter`, `None` until then.
+static REPORTER: RwLock<Option<Arc<dyn Reporter>>> = RwLock::new(None);
+
+/// Install `reporter` as the destination of all the records reported from now on, flushing the
+/// previous one.
+pub fn set_reporter(reporter: Arc<dyn Reporter>) {
+    if let Some(previous) = REPORTER.write().unwrap().replace(reporter) {
+        previous.flush();
+    }
+}
+
+/// Flush and remove the installed reporter, dropping the events reported from now on.
+pub fn clear_reporter() {
+    if let Some(previous) = REPORTER.write().unwrap().take() {
+        previous.flush();
+    }
+}
+
+/// Return whether a reporter is installed, for the callers to skip building costly events.
+pub fn enabled() -> bool {
+    REPORTER.read().unwrap().is_some()
+}
+
+/// Report that `event` happened now in `source`.
+pub fn report(source: &'static str, event: Event) {
+    if let Some(reporter) = REPORTER.read().unwrap().as_ref() {
+        reporter.report(&Record { timestamp: SystemTime::now(), source, event });
+    }
+}
+
+/// Write out the records buffered by the installed reporter, if any.
+pub fn flush() {
+    with_reporter(|reporter| reporter.flush());
+}
+
+/// Call `f` with the installed reporter, if any.
+pub(crate) fn with_reporter<F: FnOnce(&dyn Reporter)>(f: F) {
+    if let Some(reporter) = REPORTER.read().unwrap().as_ref() {
+        f(reporter.as_ref());
+    }
+}
+
+/// Report `nodes` as the number of nodes in memory, with `Event::MemorySnapshot`.
+pub fn memory_snapshot(source: &'static str, nodes: usize) {
+    report(source, Event::MemorySnapshot { nodes, bytes: metrics::estimated_bytes(nodes) });
+}
+
+/// Report the shape of a system given by its `stats` (see `System::stats`), with
+/// `Event::StatsSnapshot`.
+pub fn stats_snapshot(source: &'static str, stats: &SystemStats) {
+    report(source, Event::StatsSnapshot {
+        bdd_nodes: stats.bdds.iter().map(|bdd| bdd.nodes).collect(),
+        biggest_widths: stats.biggest_bdd().map_or_else(Vec::new, |bdd| bdd.level_widths.clone()),
+        active_vars: stats.active_vars(),
+        lhs_rank: stats.lhs_rank,
+        jumping_edges: stats.jumping_edges(),
+    });
+}
+
+/// A running stage, see `stage`.
+pub struct Stage {
+    source: &'static str,
+    name: String,
+    start: Instant,
+}
+
+/// Start the stage `name` of a computation in `source`, which runs until the returned `Stage` is
+/// dropped. Its start and end are reported with `Event::StageStarted` and `Event::StageDone`.
+pub fn stage(source: &'static str, name: &str) -> Stage {
+    report(source, Event::StageStarted { stage: name.to_string() });
+    Stage { source, name: name.to_string(), start: Instant::now() }
+}
+
+impl Drop for Stage {
+    fn drop(&mut self) {
+        let stage = std::mem::take(&mut self.name);
+        report(self.source, Event::StageDone { stage, duration: self.start.elapsed() });
+    }
+}
+
+/// Quote `field` if it contains a character special to CSV.
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// `text` as a JSON string.
+pub(crate) fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(event: Event) -> Record {
+        Record {
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_760_000_000_123),
+            source: "test.source",
+            event,
+        }
+    }
+
+    #[test]
+    fn display_records() {
+        let join = record(Event::JoinStarted { bdds: vec![Id::new(3), Id::new(5)], nodes: 1024 });
+        assert_eq!("[1760000000.123] test.source join_started bdds=[3, 5] nodes=1024", join.to_string());
+        let pruned = record(Event::Pruned { bdd: Id::new(0), nodes_before: 9, nodes_after: 4, threshold: None });
+        assert_eq!("[1760000000.123] test.source pruned bdd=0 nodes_before=9 nodes_after=4 threshold=?",
+                   pruned.to_string());
+        let stats = record(Event::StatsSnapshot {
+            bdd_nodes: vec![6, 4],
+            biggest_widths: vec![1, 2, 2],
+            active_vars: 5,
+            lhs_rank: 4,
+            jumping_edges: 0,
+        });
+        assert_eq!("[1760000000.123] test.source stats_snapshot bdd_nodes=[6, 4] biggest_widths=[1, 2, 2] \
+                    active_vars=5 lhs_rank=4 jumping_edges=0", stats.to_string());
+        let round = record(Event::RoundDone { round: 2, rounds: 3, lower_bound: Some(4), upper_bound: None });
+        assert_eq!("[1760000000.123] test.source round_done round=2 rounds=3 lower_bound=4 upper_bound=?",
+                   round.to_string());
+        let work = record(Event::RemainingWork { bdds: 7, dependencies: 2 });
+        assert_eq!("[1760000000.123] test.source remaining_work bdds=7 dependencies=2", work.to_string());
+    }
+
+    #[test]
+    fn csv_reporter() {
+        let reporter = CsvReporter::new(Vec::new()).unwrap();
+        reporter.report(&record(Event::JoinStarted { bdds: vec![Id::new(3), Id::new(5)], nodes: 1024 }));
+        reporter.report(&record(Event::StageStarted { stage: "hull, then \"trails\"".to_string() }));
+        let csv = String::from_utf8(reporter.into_inner()).unwrap();
+        assert_eq!("timestamp_ms,source,event,fields\n\
+                    1760000000123,test.source,join_started,bdds=3;5 nodes=1024\n\
+                    1760000000123,test.source,stage_started,\"stage=hull, then \"\"trails\"\"\"\n", csv);
+    }
+
+    #[test]
+    fn json_reporter() {
+        let reporter = JsonReporter::new(Vec::new());
+        reporter.report(&record(Event::Pruned { bdd: Id::new(0), nodes_before: 9, nodes_after: 4, threshold: Some(12) }));
+        reporter.report(&record(Event::StageDone { stage: "a \"b\"\n".to_string(), duration: Duration::from_millis(1500) }));
+        let json = String::from_utf8(reporter.into_inner()).unwrap();
+        assert_eq!("{\"timestamp_ms\":1760000000123,\"source\":\"test.source\",\"event\":\"pruned\",\
+                    \"bdd\":0,\"nodes_before\":9,\"nodes_after\":4,\"threshold\":12}\n\
+                    {\"timestamp_ms\":1760000000123,\"source\":\"test.source\",\"event\":\"stage_done\",\
+                    \"stage\":\"a \\\"b\\\"\\n\",\"duration_ms\":1500}\n", json);
+    }
+
+    #[test]
+    fn several_reporters() {
+        let first = Arc::new(InMemoryReporter::new());
+        let second = Arc::new(InMemoryReporter::new());
+        let reporters = Reporters::new(vec![first.clone(), second.clone()]);
+        let snapshot = record(Event::MemorySnapshot { nodes: 2, bytes: 64 });
+        reporters.report(&snapshot);
+        assert_eq!(vec![snapshot.clone()], first.records());
+        assert_eq!(vec![snapshot], second.records());
+        first.clear();
+        assert!(first.records().is_empty());
+    }
+
+    #[test]
+    fn installed_reporter() {
+        // Other tests may report concurrently, only the records of this one are looked at.
+        let _lock = TEST_REPORTER_LOCK.lock().unwrap();
+        let reporter = Arc::new(InMemoryReporter::new());
+        set_reporter(reporter.clone());
+        assert!(enabled());
+        {
+            let _stage = stage("test.installed", "outer");
+            memory_snapshot("test.installed", 10);
+        }
+        clear_reporter();
+        report("test.installed", Event::MemorySnapshot { nodes: 0, bytes: 0 });
+        let events: Vec<Event> = reporter.records()
+            .into_iter()
+            .filter(|record| record.source == "test.installed")
+            .map(|record| record.event)
+            .collect();
+        assert_eq!(3, events.len());
+        assert_eq!(Event::StageStarted { stage: "outer".to_string() }, events[0]);
+        assert_eq!(Event::MemorySnapshot { nodes: 10, bytes: metrics::estimated_bytes(10) }, events[1]);
+        assert!(matches!(&events[2], Event::StageDone { stage, .. } if stage == "outer"));
+    }
+}
@@ -0,0 +1,250 @@
+//! Reader of systems written as S-boxes applied to XORs of variables and linear equations, in
+//! algebraic normal form (ANF), into a `SystemSpec`. For example:
+//!
+//! ```text
+//! # The S-box of PRESENT: its name, its number of input and output bits and its lookup table
+//! sbox present 4 4 c 5 6 b 9 0 a d 3 e f 8 4 7 1 2
+//! # An application of the S-box to 4 XORs of variables, equal to 4 XORs of variables
+//! present(x0 + x16, x1 + x17, x2 + x18 + 1, x3 + x19) = (x32, x33, x34, x35)
+//! # A linear equation
+//! x32 + x36 = x40 + 1
+//! ```
+//!
+//! Each line holds an S-box definition, an S-box application or a linear equation. Empty lines and
+//! the text following a `#` are skipped. An XOR is a sum of the terms `x<i>` (the variable `i`),
+//! `0` and `1`, and the inputs and outputs of an application must each involve a variable. The
+//! entries of a lookup table are written in hexadecimal, the first input (resp. output) being the
+//! most significant bit of the index (resp. of the entry). An S-box is defined before it is
+//! applied.
+//!
+//! Each application becomes a bdd with a level per input then a level per output, each linear
+//! equation a bdd with a single level. The bdds are numbered from 0 in the order of the lines, and
+//! the `nvar` of the spec covers the largest variable.
+
+use std::collections::BTreeSet;
+use std::io::BufRead;
+
+use crate::AHashMap;
+use crate::soc::{
+    error::SocError,
+    Id,
+    utils::{BddSpec, LevelSpec, NodeSpec, SystemSpec}};
+
+/// The largest number of input bits of an S-box, its bdds holding `2^inputs` nodes.
+pub const MAX_SBOX_INPUTS: usize = 16;
+
+/// An S-box defined in the input.
+struct Sbox {
+    inputs: usize,
+    outputs: usize,
+    table: Vec<usize>,
+}
+
+/// Return the `SystemSpec` of `input`, see the module documentation for the format.
+pub fn parse_anf(input: &str) -> Result<SystemSpec, SocError> {
+    read_anf(input.as_bytes())
+}
+
+/// Read a system in the ANF format from `reader` and return its `SystemSpec`, see the module
+/// documentation for the format.
+///
+/// Will return a `SocError::Parse` telling the first line which isn't in the format.
+pub fn read_anf<R: BufRead>(reader: R) -> Result<SystemSpec, SocError> {
+    let mut sboxes: AHashMap<String, Sbox> = AHashMap::default();
+    let mut spec = SystemSpec::new(0, Vec::new());
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let invalid = |message: String| SocError::parse(index + 1, &message);
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let levels = if let Some(definition) = line.strip_prefix("sbox ") {
+            let (name, sbox) = parse_sbox(definition).map_err(invalid)?;
+            if sboxes.insert(name.clone(), sbox).is_some() {
+                return Err(invalid(format!("S-box `{}` is already defined", name)));
+            }
+            continue;
+        } else if line.contains('(') {
+            parse_application(line, &sboxes).map_err(invalid)?
+        } else {
+            parse_linear_equation(line).map_err(invalid)?
+        };
+        spec.push_bdd(BddSpec::new(spec.next_bdd_id(), levels));
+    }
+    Ok(spec)
+}
+
+/// Parse `name inputs outputs table...`.
+fn parse_sbox(definition: &str) -> Result<(String, Sbox), String> {
+    let mut tokens = definition.split_whitespace();
+    let name = tokens.next().ok_or("expected the name of the S-box")?;
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("`{}` is not a valid S-box name", name));
+    }
+    let mut size = |what: &str| {
+        tokens.next()
+            .and_then(|token| token.parse::<usize>().ok())
+            .filter(|size| (1..=MAX_SBOX_INPUTS).contains(size))
+            .ok_or(format!("expected the number of {} bits, from 1 to {}", what, MAX_SBOX_INPUTS))
+    };
+    let inputs = size("input")?;
+    let outputs = size("output")?;
+    let table = tokens
+        .map(|entry| match usize::from_str_radix(entry, 16) {
+            Ok(value) if value < 1 << outputs => Ok(value),
+            _ => Err(format!("`{}` is not an entry of {} bits in hexadecimal", entry, outputs)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if table.len() != 1 << inputs {
+        return Err(format!("expected {} entries in the table, found {}", 1 << inputs, table.len()));
+    }
+    Ok((name.to_string(), Sbox { inputs, outputs, table }))
+}
+
+/// Parse `name(xor, ...) = (xor, ...)` into the levels of its bdd.
+fn parse_application(line: &str, sboxes: &AHashMap<String, Sbox>) -> Result<Vec<LevelSpec>, String> {
+    let open = line.find('(').unwrap();
+    let name = line[..open].trim();
+    let sbox = sboxes.get(name).ok_or(format!("unknown S-box `{}`", name))?;
+    let (inputs, rest) = parenthesized(&line[open..])?;
+    let rest = rest.trim_start().strip_prefix('=').ok_or("expected `=` after the inputs")?;
+    let (outputs, rest) = parenthesized(rest.trim_start())?;
+    if !rest.trim().is_empty() {
+        return Err(format!("unexpected `{}` after the outputs", rest.trim()));
+    }
+    if inputs.len() != sbox.inputs || outputs.len() != sbox.outputs {
+        return Err(format!(
+            "`{}` maps {} inputs to {} outputs, found {} inputs and {} outputs",
+            name, sbox.inputs, sbox.outputs, inputs.len(), outputs.len()
+        ));
+    }
+    let lhs = |xor: &str| {
+        let (vars, constant) = parse_xor(xor)?;
+        if vars.is_empty() {
+            return Err(format!("`{}` involves no variable", xor.trim()));
+        }
+        let mut lhs: Vec<i64> = vars.into_iter().map(|var| var as i64).collect();
+        if constant {
+            lhs.push(-1);
+        }
+        Ok(lhs)
+    };
+    let inputs = inputs.into_iter().map(lhs).collect::<Result<Vec<_>, String>>()?;
+    let outputs = outputs.into_iter().map(lhs).collect::<Result<Vec<_>, String>>()?;
+    Ok(sbox_levels(sbox, inputs, outputs))
+}
+
+/// Split `(a, b, c)rest` into a, b, c and rest.
+fn parenthesized(text: &str) -> Result<(Vec<&str>, &str), String> {
+    let text = text.strip_prefix('(').ok_or("expected `(`")?;
+    let close = text.find(')').ok_or("expected `)`")?;
+    Ok((text[..close].split(',').collect(), &text[close + 1..]))
+}
+
+/// Parse `xor = xor` into the level of its bdd.
+fn parse_linear_equation(line: &str) -> Result<Vec<LevelSpec>, String> {
+    let (left, right) = line.split_once('=').ok_or("expected an S-box application or a linear equation")?;
+    let (mut vars, left_constant) = parse_xor(left)?;
+    let (right_vars, right_constant) = parse_xor(right)?;
+    for var in right_vars {
+        if !vars.insert(var) {
+            vars.remove(&var);
+        }
+    }
+    if vars.is_empty() {
+        return Err("the equation involves no variable".to_string());
+    }
+    // Node 1 only has the edge of the rhs, pointing to the sink node 2.
+    let (e0, e1) = if left_constant != right_constant { (0, 2) } else { (2, 0) };
+    Ok(vec![
+        LevelSpec::new(
+            vars.into_iter().map(|var| var as i64).collect(),
+            vec![NodeSpec::new(Id::new(1), Id::new(e0), Id::new(e1))],
+        ),
+        LevelSpec::new(vec![], vec![NodeSpec::new(Id::new(2), Id::new(0), Id::new(0))]),
+    ])
+}
+
+/// Parse a sum of terms `x<i>`, `0` and `1` into its variables, the ones appearing twice cancelling
+/// out, and its constant.
+fn parse_xor(xor: &str) -> Result<(BTreeSet<usize>, bool), String> {
+    let mut vars = BTreeSet::new();
+    let mut constant = false;
+    for term in xor.split('+').map(str::trim) {
+        match term {
+            "0" => {}
+            "1" => constant = !constant,
+            _ => match term.strip_prefix('x').and_then(|var| var.parse::<usize>().ok()) {
+                Some(var) => {
+                    if !vars.insert(var) {
+                        vars.remove(&var);
+                    }
+                }
+                None => return Err(format!("expected a variable x<i>, 0 or 1, found `{}`", term)),
+            },
+        }
+    }
+    Ok((vars, constant))
+}
+
+/// Return the levels of the bdd of an application of `sbox`, given the lhs of its inputs and
+/// outputs.
+///
+/// The input levels form a full binary tree, the node `k` of the level `i` getting the id `2^i + k`.
+/// Each leaf of the tree, i.e. each input value, then leads to the path spelling the outputs of
+/// the table. The nodes of the output level `j` stand for the values of the outputs `j` onwards,
+/// only the ones appearing in the table being kept such that every node lies on a path.
+fn sbox_levels(sbox: &Sbox, inputs: Vec<Vec<i64>>, outputs: Vec<Vec<i64>>) -> Vec<LevelSpec> {
+    let (n, m) = (sbox.inputs, sbox.outputs);
+    let suffixes: Vec<Vec<usize>> = (0..m)
+        .map(|j| {
+            let mask = (1 << (m - j)) - 1;
+            let suffixes: BTreeSet<usize> = sbox.table.iter().map(|entry| entry & mask).collect();
+            suffixes.into_iter().collect()
+        })
+        .collect();
+    let mut first_ids = vec![1 << n];
+    for level in suffixes.iter() {
+        first_ids.push(first_ids.last().unwrap() + level.len());
+    }
+    let sink = first_ids[m];
+    let output_node = |j: usize, suffix: usize| {
+        if j == m {
+            sink
+        } else {
+            first_ids[j] + suffixes[j].binary_search(&suffix).unwrap()
+        }
+    };
+
+    let mut levels = Vec::with_capacity(n + m + 1);
+    for (i, lhs) in inputs.into_iter().enumerate() {
+        let nodes = (0..1 << i)
+            .map(|k| {
+                let child = |bit: usize| {
+                    if i + 1 < n {
+                        (1 << (i + 1)) + 2 * k + bit
+                    } else {
+                        output_node(0, sbox.table[2 * k + bit])
+                    }
+                };
+                NodeSpec::new(Id::new((1 << i) + k), Id::new(child(0)), Id::new(child(1)))
+            })
+            .collect();
+        levels.push(LevelSpec::new(lhs, nodes));
+    }
+    for (j, lhs) in outputs.into_iter().enumerate() {
+        let shift = m - j - 1;
+        let nodes = suffixes[j]
+            .iter()
+            .map(|suffix| {
+                let child = output_node(j + 1, suffix & ((1 << shift) - 1));
+                let (e0, e1) = if (suffix >> shift) & 1 == 0 { (child, 0) } else { (0, child) };
+                NodeSpec::new(Id::new(output_node(j, *suffix)), Id::new(e0), Id::new(e1))
+            })
+            .collect();
+        levels.push(LevelSpec::new(lhs, nodes));
+    }
+    levels.push(LevelSpec::new(vec![], vec![NodeSpec::new(Id::new(sink), Id::new(0), Id::new(0))]));
+    levels
+}
@@ -25,9 +25,10 @@
 //! - removing the dead end nodes (skip the last level)
 //! - removing the orphan nodes (skip the first level)
 
-use std::collections::{HashMap, HashSet};
-use std::fmt;
-use std::hash::BuildHasherDefault;
+use alloc::{vec, vec::Vec};
+use hashbrown::{HashMap, HashSet};
+use core::fmt;
+use core::hash::BuildHasherDefault;
 
 use num_bigint::ToBigUint;
 use vob::Vob;
@@ -35,6 +36,30 @@ use vob::Vob;
 use crate::{AHashMap, AHashSet};
 use crate::soc::{Id, level::Level};
 use crate::soc::node::Node;
+use crate::soc::store::{HashNodeStore, NodeStore};
+
+#[cfg(feature = "std")]
+pub use cursor::PathCursor;
+pub use prune::WeightPruneStats;
+pub use reduce::ReduceStats;
+#[cfg(feature = "std")]
+pub use sift::{LevelSwap, SiftStats, SIFT_MAX_GROWTH};
+pub use transfer::TransferMatrices;
+
+mod count;
+#[cfg(feature = "std")]
+mod cursor;
+#[cfg(feature = "std")]
+mod edit;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod prune;
+mod reduce;
+#[cfg(feature = "std")]
+mod sample;
+#[cfg(feature = "std")]
+mod sift;
+mod transfer;
 
 #[allow(unused_variables)] // FIXME remove unused variables when ready
 #[cfg(feature = "differential")]
@@ -47,6 +72,7 @@ pub mod differential;
 /// be used for solving the system at the end
 
 #[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LinEq {
     lhs: Vob,
     rhs: bool,
@@ -87,14 +113,70 @@ impl LinEq {
         self.lhs.xor(&lin_eq.get_lhs());
         self.rhs ^= lin_eq.get_rhs();
     }
+
+    /// Return the fingerprint of the `Bdd` with a single level holding this `LinEq`, i.e. a root
+    /// whose only edge is the one of the `rhs` and goes to the sink (see `Bdd::fingerprint`).
+    pub fn fingerprint(&self) -> u64 {
+        let mut fingerprinter = Fingerprinter::new();
+        fingerprinter.write_lhs(self.lhs.iter_set_bits(..));
+        fingerprinter.write(1);
+        if self.rhs {
+            fingerprinter.write_edges(0, 1);
+        } else {
+            fingerprinter.write_edges(1, 0);
+        }
+        fingerprinter.write_lhs(core::iter::empty());
+        fingerprinter.write(1);
+        fingerprinter.write_edges(0, 0);
+        fingerprinter.finish()
+    }
 }
 
-/// Cloning a Shard should only happen when the Shard is of a sensible size.
+/// 64 bits FNV-1a hash, used for the fingerprints as it gives the same value on every run and
+/// every platform, unlike the hashers of the collections.
+pub(crate) struct Fingerprinter(u64);
+
+impl Fingerprinter {
+    pub(crate) fn new() -> Fingerprinter {
+        Fingerprinter(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub(crate) fn write(&mut self, value: u64) {
+        for byte in value.to_le_bytes().iter() {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Write a lhs as its number of set bits followed by the set bits.
+    fn write_lhs<I: Iterator<Item = usize>>(&mut self, set_bits: I) {
+        let set_bits: Vec<usize> = set_bits.collect();
+        self.write(set_bits.len() as u64);
+        for bit in set_bits {
+            self.write(bit as u64);
+        }
+    }
+
+    /// Write the edges of a node, as the indexes of the nodes they point to plus one, 0 meaning
+    /// no edge.
+    fn write_edges(&mut self, e0: usize, e1: usize) {
+        self.write(e0 as u64);
+        self.write(e1 as u64);
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Cloning a Shard is cheap, the clones share the nodes of their levels until they change them
+/// (see the `level` module).
 #[derive(Clone)]
 /// A Binary Decision Diagram (see module documentation for more details)
 #[derive(Default)]
-pub struct Bdd {
-    levels: Vec<Level>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bdd<S: NodeStore = HashNodeStore> {
+    levels: Vec<Level<S>>,
     id: Id,
     next_id: usize,
 }
@@ -104,6 +186,28 @@ impl Bdd {
     pub fn new() -> Bdd {
         Default::default()
     }
+}
+
+impl<S: NodeStore> Bdd<S> {
+    /// Construct a new `Bdd` with default parameters, storing the nodes of its levels in `S`
+    pub fn with_store() -> Bdd<S> {
+        Bdd { levels: Vec::new(), id: Id::default(), next_id: 0 }
+    }
+
+    /// Return the same `Bdd`, with the nodes of its levels moved to the store `T`. The ids of the
+    /// nodes, the `id` and the `next_id` are unchanged.
+    pub fn into_store<T: NodeStore>(mut self) -> Bdd<T> {
+        let mut bdd = Bdd::with_store();
+        bdd.id = self.id;
+        bdd.next_id = self.next_id;
+        for mut level in self.levels.drain(..) {
+            let nodes: Vec<(Id, Node)> = level.iter_mut_nodes()
+                .map(|(id, node)| (*id, core::mem::take(node)))
+                .collect();
+            bdd.add_level_with_nodes(level.get_lhs(), nodes);
+        }
+        bdd
+    }
 
     /// Set the id of the `Bdd` to the given id
     #[inline]
@@ -123,6 +227,12 @@ impl Bdd {
         self.next_id = next_id;
     }
 
+    /// Return the next id for the next node to be inserted
+    #[inline]
+    pub fn get_next_id(&self) -> usize {
+        self.next_id
+    }
+
     /// Return the index of the last level
     #[inline]
     pub fn get_sink_level_index(&self) -> usize {
@@ -132,30 +242,42 @@ impl Bdd {
     /// Add an empty level at the end of the `Bdd`
     #[inline]
     pub fn add_level(&mut self) {
-        self.levels.push(Level::new());
+        self.levels.push(Level::with_store());
+    }
+
+    /// Push at the end of the `Bdd` a new level with the given `lhs` and nodes, see
+    /// `Level::extend_nodes`.
+    ///
+    /// The ids are used as given: they are expected to already follow the `next_id*10000 + id`
+    /// scheme, and `next_id` is not updated, you are expected to set it yourself.
+    pub fn add_level_with_nodes<I: IntoIterator<Item = (Id, Node)>>(&mut self, lhs: Vob, nodes: I) {
+        let mut level = Level::with_store();
+        level.set_lhs_from_vob(lhs);
+        level.extend_nodes(nodes);
+        self.levels.push(level);
     }
 
     /// Push at the end of the `Bdd` an existing level (used for joining BDDs)
     #[inline]
-    pub fn add_existing_level(&mut self, level: Level) {
+    pub fn add_existing_level(&mut self, level: Level<S>) {
         self.levels.push(level);
     }
 
     /// Return an iterator over the levels of the `Bdd`.
     /// This includes the sink level.
     #[inline]
-    pub fn iter_levels(&self) -> std::slice::Iter<Level> {
+    pub fn iter_levels(&self) -> core::slice::Iter<'_, Level<S>> {
         self.levels.iter()
     }
 
     /// FIXME keep or remove? Why wasn't this a fn in the first place? Maybe for a good reason?
-    pub fn level(&self, depth: usize) -> Option<&Level> {
+    pub fn level(&self, depth: usize) -> Option<&Level<S>> {
         self.levels.get(depth)
     }
 
     /// Return a draining iterator over the levels of the `Bdd`
     #[inline]
-    pub fn drain_levels(&mut self) -> std::vec::Drain<Level> {
+    pub fn drain_levels(&mut self) -> alloc::vec::Drain<'_, Level<S>> {
         self.levels.drain(..)
     }
 
@@ -183,6 +305,12 @@ impl Bdd {
             .collect()
     }
 
+    /// Return the number of levels whose nodes are shared with a clone of the `Bdd`, see
+    /// `Level::is_shared`.
+    pub fn count_shared_levels(&self) -> usize {
+        self.levels.iter().filter(|level| level.is_shared()).count()
+    }
+
     /// Return the total number of nodes inside the BDD
     pub fn get_size(&self) -> usize {
         self.levels
@@ -211,13 +339,13 @@ impl Bdd {
     /// Repeatedly calls the `add_node` function on the level specified by the `level_index`
     /// for each id in `nodes_id`
     /// /!\ no update is made to self.next_id, you are expected to set it yourself
+    ///
+    /// Meant for `build_bdd_from_spec`, use `new_node` to add nodes to an existing `Bdd`.
     pub fn add_nodes_to_level(&mut self, level_index: usize, nodes_id: Vec<Id>) {
-        let mut nodes = Vec::new();
-        for node_id in nodes_id.iter() {
-            let new_id = Id::new(**node_id * 10000 + *self.id);
-            self.levels[level_index].add_new_node(new_id);
-            nodes.push(new_id);//Why this?  Is the vector 'nodes' used for anything?
-        }
+        let bdd_id = *self.id;
+        self.levels[level_index].extend_nodes(
+            nodes_id.iter().map(|node_id| (Id::new(**node_id * 10000 + bdd_id), Node::new())),
+        );
     }
 
     /// Iterate through the levels of the BDD to find a node of id `parent` and set its
@@ -226,6 +354,8 @@ impl Bdd {
     /// This is obviously very slow on large BDD but this method is only use when constructing
     /// the BDDs initially making it virtually no cost as BDDs are usually extremely small
     /// at this stage
+    ///
+    /// Meant for `build_bdd_from_spec`, use `connect` to add edges to an existing `Bdd`.
     pub fn connect_nodes_from_spec(&mut self, parent: Id, child_id: Id, edge: i8) {
         assert!(edge == 0 || edge == 1);
         let child_id = Id::new(*child_id * 10000 + *self.id);
@@ -351,13 +481,14 @@ impl Bdd {
     /// -> instead of generating, connect `node` to this already existing node
     ///
     /// Finally swap the lhs of `level_1` and `level_2`
+    ///
+    /// Nothing is checked besides the indexes, see `swap_adjacent_levels` for the checked version.
     pub fn swap(&mut self, level_index_above: usize, level_index_below: usize) {
         assert!(level_index_above + 1 == level_index_below);
         let max_level_size = self.levels[level_index_below].get_nodes_len() * 2;
         let mut known_functions: AHashMap<(Option<Id>, Option<Id>), Id> =
             AHashMap::with_capacity_and_hasher(max_level_size, Default::default());
-        let mut nodes: AHashMap<Id, Node> =
-            AHashMap::with_capacity_and_hasher(max_level_size, Default::default());
+        let mut nodes = S::with_capacity(max_level_size);
         let (above, below) = self.levels.split_at_mut(level_index_above + 1);
         let mut next_id = self.next_id;
         let bdd_id = *self.id;
@@ -464,8 +595,7 @@ impl Bdd {
             level_index_above += 1;
         }
         let max_level_size = self.levels[level_index_below].get_nodes_len() * 2;
-        let mut nodes: AHashMap<Id, Node> =
-            AHashMap::with_capacity_and_hasher(max_level_size, Default::default());
+        let mut nodes = S::with_capacity(max_level_size);
         let mut known_functions: AHashMap<(Option<Id>, Option<Id>), Id> =
             AHashMap::with_capacity_and_hasher(max_level_size, Default::default());
         let (above, below) = self.levels.split_at_mut(level_index_above + 1);
@@ -656,15 +786,54 @@ impl Bdd {
         lin_eqs_absorbed
     }
 
-    /// Used to remove any jumping edges in a bdd, ensuring that if a node has a parent
-    /// it is located in the level just above. This is important for performance since we don't
-    /// keep track of the parents of a node.
+    /// Remove all the jumping edges of the `Bdd`, i.e. the edges skipping levels, ensuring that if
+    /// a node has a parent it is located in the level just above. This is important for
+    /// performance since we don't keep track of the parents of a node.
+    ///
+    /// An edge skipping levels is replaced by a chain of pass-through nodes, one in each skipped
+    /// level, whose both edges lead to the next one, which keeps the function of the `Bdd`. The
+    /// edges of several nodes jumping to the same node share the pass-through nodes.
     ///
-    /// Should be use only when loading the system at the start (jumping edges cannot appear after).
+    /// Should be used only when loading the system at the start (jumping edges cannot appear
+    /// after). See `jumping_edges` to check that none is left.
+    pub fn normalize_jumping_edges(&mut self) {
+        // The sink level is the last level a jumping edge can reach, the levels above it are the
+        // last ones which can be skipped
+        for level_index in 1..self.levels.len().saturating_sub(1) {
+            self.add_same_edges_node_at_level(level_index);
+        }
+    }
+
+    /// Return the edges not leading to a node of the level just below their parent, as
+    /// (level of the parent, parent, child), from the top. This includes the jumping edges and the
+    /// edges to a node which doesn't exist.
+    pub fn jumping_edges(&self) -> Vec<(usize, Id, Id)> {
+        let mut jumping = Vec::new();
+        for (level_index, pair) in self.levels.windows(2).enumerate() {
+            for (id, node) in pair[0].iter_nodes() {
+                for child in node.get_e0().into_iter().chain(node.get_e1()) {
+                    if pair[1].get_node(&child).is_none() {
+                        jumping.push((level_index, *id, child));
+                    }
+                }
+            }
+        }
+        if let Some(sink) = self.levels.last() {
+            for (id, node) in sink.iter_nodes() {
+                for child in node.get_e0().into_iter().chain(node.get_e1()) {
+                    jumping.push((self.levels.len() - 1, *id, child));
+                }
+            }
+        }
+        jumping
+    }
+
+    /// Give a pass-through node in the level `level_index` to each child of the level above which
+    /// is not in the level, see `normalize_jumping_edges`.
     pub fn add_same_edges_node_at_level(&mut self, level_index: usize) {
         let mut changed = false;
         if level_index != 0 {
-            let mut childs: HashSet<Id, BuildHasherDefault<ahash::AHasher>> =
+            let mut childs: HashSet<Id, BuildHasherDefault<crate::FixedHasher>> =
                 AHashSet::with_capacity_and_hasher(
                     self.levels[level_index - 1].get_nodes_len(),
                     Default::default(),
@@ -680,7 +849,7 @@ impl Bdd {
             for (id, _) in self.levels[level_index].iter_nodes() {
                 childs.remove(id);
             }
-            let mut new_level: HashMap<Id, Id, BuildHasherDefault<ahash::AHasher>> =
+            let mut new_level: HashMap<Id, Id, BuildHasherDefault<crate::FixedHasher>> =
                 AHashMap::with_capacity_and_hasher(childs.len(), Default::default());
             if !childs.is_empty() {
                 changed = true;
@@ -710,9 +879,9 @@ impl Bdd {
         let mut known_functions: HashMap<
             (Option<Id>, Option<Id>),
             Id,
-            BuildHasherDefault<ahash::AHasher>,
+            BuildHasherDefault<crate::FixedHasher>,
         > = AHashMap::with_capacity_and_hasher(max_size_map, Default::default());
-        let mut map: HashMap<Id, Id, BuildHasherDefault<ahash::AHasher>> =
+        let mut map: HashMap<Id, Id, BuildHasherDefault<crate::FixedHasher>> =
             AHashMap::with_capacity_and_hasher(max_size_map, Default::default());
         while changed && level_index > 1 {
             changed = false;
@@ -771,12 +940,16 @@ impl Bdd {
     /// with the sink of the BDD above it
     pub fn merge_sink_source(&mut self, sink_level_index: usize) {
         let (sink_bdd, source_bdd) = self.levels.split_at_mut(sink_level_index + 1);
-        if let Some((_, source)) = source_bdd[0].iter_nodes().next() {
+        let source_edges = source_bdd[0]
+            .iter_nodes()
+            .next()
+            .map(|(_, source)| (source.get_e0(), source.get_e1()));
+        if let Some((e0, e1)) = source_edges {
             if let Some((_, sink)) = sink_bdd.last_mut().unwrap().iter_mut_nodes().next() {
-                if let Some(e0) = source.get_e0() {
+                if let Some(e0) = e0 {
                     sink.connect_e0(e0);
                 }
-                if let Some(e1) = source.get_e1() {
+                if let Some(e1) = e1 {
                     sink.connect_e1(e1);
                 }
             }
@@ -894,7 +1067,7 @@ impl Bdd {
     /// So we can grab it and its weight will be the number of paths of the bdd
     /// If the bdd is only a sink (number of level < 2), we return `0`
     pub fn count_paths(&self) -> num_bigint::BigUint {
-        let mut previous_level_weigths: HashMap<Id, num_bigint::BigUint, BuildHasherDefault<ahash::AHasher>> =
+        let mut previous_level_weigths: HashMap<Id, num_bigint::BigUint, BuildHasherDefault<crate::FixedHasher>> =
             AHashMap::with_hasher(Default::default());
         if self.levels.len() < 2 {
             return 0.to_biguint().unwrap();
@@ -935,6 +1108,116 @@ impl Bdd {
         previous_level_weigths.iter().next().unwrap().1.clone()
     }
 
+    /// Return true if the given assignment of the variables is accepted by the `Bdd`, i.e. if
+    /// the path it selects from the source reaches the sink.
+    ///
+    /// At each level the value of the lhs under `assignment` selects the edge to follow. A missing
+    /// node or edge means the assignment is rejected.
+    /// This walks a single path and is meant for checking small systems by brute force.
+    pub fn accepts(&self, assignment: &Vob) -> bool {
+        let sink_level_index = self.get_sink_level_index();
+        let mut current = match self.levels[0].iter_nodes().next() {
+            Some((id, _)) => *id,
+            None => return false,
+        };
+        for level in self.levels.iter().take(sink_level_index) {
+            let node = match level.get_node(&current) {
+                Some(node) => node,
+                None => return false,
+            };
+            let value = level
+                .iter_set_lhs()
+                .fold(false, |acc, var| acc ^ assignment.get(var).unwrap_or(false));
+            let edge = if value { node.get_e1() } else { node.get_e0() };
+            current = match edge {
+                Some(edge) => edge,
+                None => return false,
+            };
+        }
+        self.levels[sink_level_index].get_node(&current).is_some()
+    }
+
+    /// Give the nodes dense ids, ascending from the first level to the sink: the k-th node gets the
+    /// id `k * 10000 + bdd_id` (see the module documentation), the nodes of a level keeping the
+    /// order of their previous ids. The structure of the `Bdd` is unchanged.
+    ///
+    /// After heavy pruning and absorbing the ids can be sparse and very large, renumbering keeps
+    /// them small, e.g. before writing the `Bdd` to a file.
+    pub fn renumber(&mut self) {
+        let bdd_id = *self.id;
+        let mut new_ids: AHashMap<Id, Id> = AHashMap::default();
+        new_ids.reserve(self.get_size());
+        let mut next_id = 0;
+        for level in self.levels.iter() {
+            let mut ids: Vec<Id> = level.iter_nodes().map(|(id, _)| *id).collect();
+            ids.sort_unstable();
+            for id in ids {
+                next_id += 1;
+                new_ids.insert(id, Id::new(next_id * 10000 + bdd_id));
+            }
+        }
+        let renumber_edge = |edge: Option<Id>| edge.map(|edge| new_ids[&edge]);
+        for level in self.levels.iter_mut() {
+            let mut nodes = S::with_capacity(level.get_nodes_len());
+            for (id, node) in level.iter_nodes() {
+                nodes.insert(
+                    new_ids[id],
+                    Node::with_edges(renumber_edge(node.get_e0()), renumber_edge(node.get_e1())),
+                );
+            }
+            level.replace_nodes(nodes);
+        }
+        self.next_id = next_id;
+    }
+
+    /// Return a fingerprint of the structure of the `Bdd`: two `Bdd`s with the same levels and
+    /// the same nodes connected the same way have the same fingerprint, whatever the `Id` of the
+    /// `Bdd` and of its nodes. The fingerprint is the same on every run, so it can be stored.
+    ///
+    /// The nodes are numbered in the order they are reached from the level above, 0-edge first,
+    /// such that the numbering doesn't depend on their ids.
+    pub fn fingerprint(&self) -> u64 {
+        let mut fingerprinter = Fingerprinter::new();
+        let mut order: Vec<Id> = match self.levels.first() {
+            Some(level) => level.iter_nodes().map(|(id, _)| *id).collect(),
+            None => return fingerprinter.finish(),
+        };
+        order.sort();
+        for (i, level) in self.levels.iter().enumerate() {
+            fingerprinter.write_lhs(level.iter_set_lhs());
+            fingerprinter.write(order.len() as u64);
+            let mut next_order = Vec::new();
+            let mut next_index: AHashMap<Id, usize> = AHashMap::default();
+            let mut index_of = |edge: Option<Id>| match edge {
+                Some(edge) => {
+                    1 + *next_index.entry(edge).or_insert_with(|| {
+                        next_order.push(edge);
+                        next_order.len() - 1
+                    })
+                }
+                None => 0,
+            };
+            for id in order.iter() {
+                let node = level.get_node(id).expect("node reached by an edge should exist");
+                let e0 = index_of(node.get_e0());
+                let e1 = index_of(node.get_e1());
+                fingerprinter.write_edges(e0, e1);
+            }
+            // Nodes not reached from the level above come last, by id.
+            if let Some(next) = self.levels.get(i + 1) {
+                let mut unreached: Vec<Id> = next
+                    .iter_nodes()
+                    .map(|(id, _)| *id)
+                    .filter(|id| !next_index.contains_key(id))
+                    .collect();
+                unreached.sort();
+                next_order.extend(unreached);
+            }
+            order = next_order;
+        }
+        fingerprinter.finish()
+    }
+
     /// Replace a variable in all the lhs of the bdd by a linear combination.
     /// If the linear combination is equal to true:flip all the edges of the level.
     /// If when replacing the lhs a zero level is created -> absorb it along its zero edges.
@@ -961,7 +1244,7 @@ impl Bdd {
     }
 }
 
-impl fmt::Debug for Bdd {
+impl<S: NodeStore> fmt::Debug for Bdd<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "Bdd id {}", *self.id)?;
         if self.levels.is_empty() {
@@ -983,8 +1266,8 @@ impl fmt::Debug for Bdd {
 /// we compare 2 bdd by mapping nodes to each other following their edges.
 /// Nodes should always represent the same function as their mapped node
 /// on the other bdd or else the bdds are not equal.
-impl PartialEq for Bdd {
-    fn eq(&self, other: &Bdd) -> bool {
+impl<S: NodeStore> PartialEq for Bdd<S> {
+    fn eq(&self, other: &Bdd<S>) -> bool {
         if self.get_levels_size() != other.get_levels_size() {
             return false;
         }
@@ -995,7 +1278,7 @@ impl PartialEq for Bdd {
             return false;
         }
         // node_mapping will map the id of a node in self to ref of a node in other
-        let mut node_mapping: HashMap<Id, Id, BuildHasherDefault<ahash::AHasher>> =
+        let mut node_mapping: HashMap<Id, Id, BuildHasherDefault<crate::FixedHasher>> =
             AHashMap::with_hasher(Default::default());
 
         // Initialize the hashmap with the sources
@@ -1063,4 +1346,4 @@ impl PartialEq for Bdd {
     }
 }
 
-impl Eq for Bdd {}
+impl<S: NodeStore> Eq for Bdd<S> {}
@@ -0,0 +1,250 @@
+//! Exact counting of the solutions of a `Bdd`, weighted counting of its paths, and the
+//! distribution of the weights of its paths.
+//!
+//! A path fixes the value of the lhs of each level. When the lhs are linearly independent, each
+//! path is met by `2^(nvar - rank)` assignments of the variables, `rank` being the rank of the
+//! lhs. Otherwise the values of dependent lhs are tied, and only the paths consistent with the
+//! linear dependencies between the lhs have solutions. `count_solutions` counts these paths from
+//! top to bottom, keeping for each node the number of paths reaching it by the parities of the
+//! dependencies they accumulated.
+//!
+//! `weight_distribution` counts the paths the same way, keeping for each node the number of paths
+//! reaching it by their weight so far, the weight of a path being its number of groups of levels
+//! where it takes a 1-edge (e.g. its number of active S-boxes).
+
+use core::ops::{Add, Mul, Range};
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use num_bigint::BigUint;
+use vob::Vob;
+
+use crate::AHashMap;
+use crate::soc::{Id, store::NodeStore};
+
+use super::Bdd;
+
+impl<S: NodeStore> Bdd<S> {
+    /// Count the solutions of the `Bdd` over `nvar` variables, i.e. the assignments of the
+    /// variables accepted by it (see `accepts`). The return value is a BigUint, as the number of
+    /// solutions may be huge.
+    ///
+    /// See the `count` module documentation for how the linear dependencies between the lhs are
+    /// handled. `nvar` must be at least the rank of the lhs. If the bdd is only a sink, we return
+    /// `0`.
+    pub fn count_solutions(&self, nvar: usize) -> BigUint {
+        if self.levels.len() < 2 {
+            return BigUint::from(0u8);
+        }
+        let sink_level_index = self.levels.len() - 1;
+        let lhss: Vec<Vob> = self.levels[..sink_level_index].iter().map(|level| level.get_lhs()).collect();
+        let (dependencies, rank) = linear_dependencies(&lhss);
+        // The dependencies whose parity is flipped by the 1-edge of each level
+        let flips: Vec<Vob> = (0..sink_level_index)
+            .map(|level_index| dependencies.iter().map(|dependency| dependency[level_index]).collect())
+            .collect();
+        let even = Vob::from_elem(dependencies.len(), false);
+
+        let mut counts: AHashMap<Id, AHashMap<Vob, BigUint>> = AHashMap::default();
+        for (id, _) in self.levels[0].iter_nodes() {
+            counts.entry(*id).or_default().insert(even.clone(), BigUint::from(1u8));
+        }
+        for (level_index, level) in self.levels[..sink_level_index].iter().enumerate() {
+            for (id, node) in level.iter_nodes() {
+                let node_counts = match counts.remove(id) {
+                    Some(node_counts) => node_counts,
+                    None => continue,
+                };
+                let mut forward = |child: Id, flip: Option<&Vob>| {
+                    let child_counts = counts.entry(child).or_default();
+                    for (parity, count) in node_counts.iter() {
+                        let mut parity = parity.clone();
+                        if let Some(flip) = flip {
+                            parity.xor(flip);
+                        }
+                        *child_counts.entry(parity).or_default() += count;
+                    }
+                };
+                if let Some(e0) = node.get_e0() {
+                    forward(e0, None);
+                }
+                if let Some(e1) = node.get_e1() {
+                    forward(e1, Some(&flips[level_index]));
+                }
+            }
+        }
+        let consistent: BigUint = self.levels[sink_level_index]
+            .iter_nodes()
+            .filter_map(|(id, _)| counts.get(id).and_then(|sink_counts| sink_counts.get(&even)))
+            .sum();
+        consistent << (nvar - rank)
+    }
+
+    /// Return the number of paths of the `Bdd` of each weight, the weight of a path being the number
+    /// of groups of `step` consecutive levels of `active_area` where it takes at least one 1-edge.
+    /// For a differential (or linear) `Bdd` whose levels in `active_area` are grouped by S-box, this
+    /// is the number of trails by their number of active S-boxes. A last group of less than `step`
+    /// levels counts as a group, and the levels out of `active_area` don't count.
+    ///
+    /// The counts are exact, the weights without any path are left out. If the bdd is only a sink,
+    /// the distribution is empty.
+    ///
+    /// Panics if `step` is zero, or `active_area` ends below the last level above the sink.
+    pub fn weight_distribution(&self, active_area: &Range<usize>, step: usize) -> BTreeMap<usize, BigUint> {
+        assert_ne!(step, 0, "Step cannot be 0!");
+        let mut distribution = BTreeMap::new();
+        if self.levels.len() < 2 {
+            return distribution;
+        }
+        let sink_level_index = self.levels.len() - 1;
+        assert!(active_area.end <= sink_level_index,
+                "The active area {:?} ends below the last level {}", active_area, sink_level_index - 1);
+
+        // The number of paths reaching each node by their weight, and whether they took a 1-edge in
+        // the current group
+        let mut counts: AHashMap<Id, AHashMap<(usize, bool), BigUint>> = AHashMap::default();
+        for (id, _) in self.levels[0].iter_nodes() {
+            counts.entry(*id).or_default().insert((0, false), BigUint::from(1u8));
+        }
+        for (level_index, level) in self.levels[..sink_level_index].iter().enumerate() {
+            let active = active_area.contains(&level_index);
+            let group_end = active
+                && ((level_index + 1 - active_area.start).is_multiple_of(step) || level_index + 1 == active_area.end);
+            for (id, node) in level.iter_nodes() {
+                let node_counts = match counts.remove(id) {
+                    Some(node_counts) => node_counts,
+                    None => continue,
+                };
+                let mut forward = |child: Id, edge: bool| {
+                    let child_counts = counts.entry(child).or_default();
+                    for ((weight, taken), count) in node_counts.iter() {
+                        let taken = *taken || (active && edge);
+                        let key = if group_end { (weight + taken as usize, false) } else { (*weight, taken) };
+                        *child_counts.entry(key).or_default() += count;
+                    }
+                };
+                if let Some(e0) = node.get_e0() {
+                    forward(e0, false);
+                }
+                if let Some(e1) = node.get_e1() {
+                    forward(e1, true);
+                }
+            }
+        }
+        for (id, _) in self.levels[sink_level_index].iter_nodes() {
+            for ((weight, _), count) in counts.remove(id).unwrap_or_default() {
+                *distribution.entry(weight).or_default() += count;
+            }
+        }
+        distribution
+    }
+
+    /// Return the sum over the paths of the `Bdd` of the product of the weights of their edges,
+    /// `weight(level, edge)` being the weight of the `edge` edge (false for the 0-edge) of a node of
+    /// the level `level`.
+    ///
+    /// With a weight of one for each edge this is `count_paths`, while with the probability of
+    /// each value of the lhs of each level (e.g. the probability of the differential transitions
+    /// of an S-box) this is the probability of the whole `Bdd`. If the bdd is only a sink, we
+    /// return the weight of the empty path, one.
+    pub fn count_weighted_paths<W, F>(&self, weight: F) -> W
+    where
+        W: Clone + From<u8> + Add<Output = W> + Mul<Output = W>,
+        F: Fn(usize, bool) -> W,
+    {
+        if self.levels.is_empty() {
+            return W::from(0);
+        }
+        let weights = self.weighted_paths_to_sink(weight);
+        self.levels[0]
+            .iter_nodes()
+            .fold(W::from(0), |total, (id, _)| total + weights[id].clone())
+    }
+
+    /// Return the sum over the paths from each node to the sink of the product of the weights of
+    /// their edges, see `count_weighted_paths`. The nodes of the sink level have the weight of the
+    /// empty path, one.
+    pub(crate) fn weighted_paths_to_sink<W, F>(&self, weight: F) -> AHashMap<Id, W>
+    where
+        W: Clone + From<u8> + Add<Output = W> + Mul<Output = W>,
+        F: Fn(usize, bool) -> W,
+    {
+        let mut weights: AHashMap<Id, W> = AHashMap::default();
+        let sink_level_index = match self.levels.len() {
+            0 => return weights,
+            len => len - 1,
+        };
+        for (id, _) in self.levels[sink_level_index].iter_nodes() {
+            weights.insert(*id, W::from(1));
+        }
+        for (level_index, level) in self.levels[..sink_level_index].iter().enumerate().rev() {
+            for (id, node) in level.iter_nodes() {
+                let mut node_weight = W::from(0);
+                for (edge, child) in [(false, node.get_e0()), (true, node.get_e1())].iter() {
+                    if let Some(child_weight) = child.and_then(|child| weights.get(&child)) {
+                        node_weight = node_weight + weight(level_index, *edge) * child_weight.clone();
+                    }
+                }
+                weights.insert(*id, node_weight);
+            }
+        }
+        weights
+    }
+
+    /// Return the number of paths from the top level to each node reached by one, the nodes of the
+    /// top level being reached by the empty path.
+    #[cfg(feature = "std")]
+    pub(crate) fn paths_from_source(&self) -> AHashMap<Id, BigUint> {
+        let mut counts: AHashMap<Id, BigUint> = AHashMap::default();
+        let top = match self.levels.first() {
+            Some(top) => top,
+            None => return counts,
+        };
+        for (id, _) in top.iter_nodes() {
+            counts.insert(*id, BigUint::from(1u8));
+        }
+        for level in self.levels.iter() {
+            for (id, node) in level.iter_nodes() {
+                let count = match counts.get(id) {
+                    Some(count) => count.clone(),
+                    None => continue,
+                };
+                for child in node.get_e0().into_iter().chain(node.get_e1()) {
+                    *counts.entry(child).or_default() += &count;
+                }
+            }
+        }
+        counts
+    }
+}
+
+/// Return the linear dependencies between `lhss`, each as the set of the indices of the lhs
+/// XORing to zero, and the rank of `lhss`. A null lhs is a dependency by itself.
+pub(super) fn linear_dependencies(lhss: &[Vob]) -> (Vec<Vob>, usize) {
+    // The echelon basis by pivot: each row, reduced, with the set of the lhs it is the XOR of
+    let mut basis: AHashMap<usize, (Vob, Vob)> = AHashMap::default();
+    let mut dependencies = Vec::new();
+    for (index, lhs) in lhss.iter().enumerate() {
+        let mut row = lhs.clone();
+        let mut combination = Vob::from_elem(lhss.len(), false);
+        combination.set(index, true);
+        let pivot = loop {
+            match row.iter_set_bits(..).last() {
+                Some(pivot) => match basis.get(&pivot) {
+                    Some((basis_row, basis_combination)) => {
+                        row.xor(basis_row);
+                        combination.xor(basis_combination);
+                    }
+                    None => break Some(pivot),
+                },
+                None => break None,
+            }
+        };
+        match pivot {
+            Some(pivot) => {
+                basis.insert(pivot, (row, combination));
+            }
+            None => dependencies.push(combination),
+        }
+    }
+    (dependencies, basis.len())
+}
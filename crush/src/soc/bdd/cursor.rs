@@ -0,0 +1,267 @@
+//! Resumable enumeration of the paths of a `Bdd`.
+//!
+//! Enumerating every path of a large `Bdd` (or every solution of a `System`) may take far longer
+//! than a single run, or may be split among several workers. A `PathCursor` records how far an
+//! enumeration has come, such that it can be stored and later handed back to continue where the
+//! previous call stopped.
+//!
+//! Paths are enumerated depth first, following the 0-edge before the 1-edge, which is the same
+//! order as `get_all_valid_path`. A cursor only remembers the edges taken by the last returned
+//! path, and does not depend on the `Id`s of the nodes. A cursor may therefore be used on any
+//! `Bdd` equal to the one it was produced from (see the `PartialEq` impl of `Bdd`), for instance
+//! a copy loaded from a .bdd file by another process.
+//!
+//! The textual representation of a cursor (see `Display` and `FromStr`) is one of:
+//! - `start`: nothing has been enumerated yet.
+//! - `after:<edges>`: `<edges>` is the last returned path, one `0` or `1` per level.
+//! - `done`: the enumeration is exhausted.
+
+use core::fmt;
+use std::io::{Error, ErrorKind};
+use core::str::FromStr;
+
+use crate::AHashSet;
+use crate::soc::Id;
+
+use super::{Bdd, LinEq};
+
+/// An opaque position in the enumeration of the paths of a `Bdd`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathCursor {
+    state: CursorState,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CursorState {
+    Start,
+    After(Vec<bool>),
+    Exhausted,
+}
+
+impl PathCursor {
+    /// Return a cursor pointing to the very first path.
+    pub fn start() -> PathCursor {
+        PathCursor {
+            state: CursorState::Start,
+        }
+    }
+
+    /// Return a cursor for an enumeration with no paths left.
+    pub(crate) fn exhausted() -> PathCursor {
+        PathCursor {
+            state: CursorState::Exhausted,
+        }
+    }
+
+    /// Return true if there are no paths left to enumerate.
+    pub fn is_exhausted(&self) -> bool {
+        self.state == CursorState::Exhausted
+    }
+}
+
+impl Default for PathCursor {
+    fn default() -> Self {
+        PathCursor::start()
+    }
+}
+
+impl fmt::Display for PathCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.state {
+            CursorState::Start => write!(f, "start"),
+            CursorState::Exhausted => write!(f, "done"),
+            CursorState::After(edges) => {
+                write!(f, "after:")?;
+                for edge in edges.iter() {
+                    write!(f, "{}", if *edge { '1' } else { '0' })?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for PathCursor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let state = match s.trim() {
+            "start" => CursorState::Start,
+            "done" => CursorState::Exhausted,
+            other => {
+                let edges = other.strip_prefix("after:").ok_or_else(|| {
+                    Error::new(ErrorKind::InvalidData, format!("Not a valid cursor: {}", other))
+                })?;
+                let edges = edges
+                    .chars()
+                    .map(|c| match c {
+                        '0' => Ok(false),
+                        '1' => Ok(true),
+                        _ => Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("Illegal char '{}' in cursor, expected 0 or 1", c),
+                        )),
+                    })
+                    .collect::<Result<Vec<bool>, Error>>()?;
+                CursorState::After(edges)
+            }
+        };
+        Ok(PathCursor { state })
+    }
+}
+
+impl Bdd {
+    /// Return at most `limit` valid paths following the position given by `cursor`, along with
+    /// a cursor pointing past the last returned path.
+    ///
+    /// A path is defined as in `get_all_valid_path`: a `Vec` of `LinEq` made of the `lhs` of the
+    /// `levels` and the outgoing edge taken at that `level`. Starting from `PathCursor::start()`
+    /// and feeding each returned cursor back in yields every path exactly once, without the cap
+    /// on the number of paths that `get_all_valid_path` has.
+    ///
+    /// Returns an `Error` if the cursor does not describe a path of this `Bdd`, which typically
+    /// means it was produced by another `Bdd`.
+    pub fn valid_paths_from(
+        &self,
+        cursor: &PathCursor,
+        limit: usize,
+    ) -> Result<(Vec<Vec<LinEq>>, PathCursor), Error> {
+        let mut paths = Vec::new();
+        let mut state = cursor.state.clone();
+        while paths.len() < limit {
+            let next = match &state {
+                CursorState::Exhausted => break,
+                CursorState::Start => self.leftmost_path_below(0, self.source_edges()),
+                CursorState::After(edges) => self.next_path_after(edges)?,
+            };
+            match next {
+                Some(edges) => {
+                    paths.push(self.edges_to_lin_eqs(&edges));
+                    state = CursorState::After(edges);
+                }
+                None => state = CursorState::Exhausted,
+            }
+        }
+        Ok((paths, PathCursor { state }))
+    }
+
+    /// Return the edges of the source node.
+    fn source_edges(&self) -> (Option<Id>, Option<Id>) {
+        match self.levels[0].iter_nodes().next() {
+            Some((_, n)) => (n.get_e0(), n.get_e1()),
+            None => (None, None),
+        }
+    }
+
+    /// Return the edges of the node `id` at `level_index`, or `None` if the node does not exist.
+    fn edges_of(&self, level_index: usize, id: Id) -> Option<(Option<Id>, Option<Id>)> {
+        self.levels
+            .get(level_index)?
+            .get_node(&id)
+            .map(|n| (n.get_e0(), n.get_e1()))
+    }
+
+    /// Starting at a node at `level_index` with the given `edges`, return the edges of the first
+    /// path down to the sink in the enumeration order, or `None` if no path exists.
+    ///
+    /// The 0-edge is followed whenever possible, backtracking to the 1-edge of the deepest node
+    /// left at a dead end: in a `Bdd` which is not reduced, a branch may end before the sink.
+    /// The nodes found to lead nowhere are remembered, such that each is only explored once.
+    fn leftmost_path_below(
+        &self,
+        level_index: usize,
+        edges: (Option<Id>, Option<Id>),
+    ) -> Option<Vec<bool>> {
+        let sink = self.get_sink_level_index();
+        let mut path = Vec::with_capacity(sink - level_index);
+        // The node at each level of the path so far (`None` for the starting one), its edges, and
+        // the number of its edges tried
+        let mut stack = vec![(None, edges, 0)];
+        let mut dead: AHashSet<Id> = AHashSet::default();
+        while let Some((id, (e0, e1), tried)) = stack.last_mut() {
+            let depth = level_index + path.len();
+            if depth == sink {
+                return Some(path);
+            }
+            let (edge, child) = match tried {
+                0 => (false, *e0),
+                1 => (true, *e1),
+                _ => {
+                    if let Some(id) = id {
+                        dead.insert(*id);
+                    }
+                    stack.pop();
+                    path.pop();
+                    continue;
+                }
+            };
+            *tried += 1;
+            let child_edges = child
+                .filter(|child| !dead.contains(child))
+                .and_then(|child| self.edges_of(depth + 1, child).map(|edges| (child, edges)));
+            if let Some((child, child_edges)) = child_edges {
+                path.push(edge);
+                stack.push((Some(child), child_edges, 0));
+            }
+        }
+        None
+    }
+
+    /// Return the edges of the path directly following `last` in the enumeration order, or
+    /// `None` if `last` was the final path.
+    fn next_path_after(&self, last: &[bool]) -> Result<Option<Vec<bool>>, Error> {
+        let sink = self.get_sink_level_index();
+        if last.len() != sink {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Cursor holds a path of length {}, but the Bdd has {} levels above its sink",
+                    last.len(),
+                    sink
+                ),
+            ));
+        }
+        // Replay `last` to recover the node visited at each level
+        let mut visited = Vec::with_capacity(sink);
+        let mut edges = self.source_edges();
+        for (level_index, edge) in last.iter().enumerate() {
+            visited.push(edges);
+            let child = if *edge { edges.1 } else { edges.0 };
+            edges = match child.and_then(|c| self.edges_of(level_index + 1, c)) {
+                Some(edges) => edges,
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Cursor does not describe a path of Bdd {}", self.id),
+                    ))
+                }
+            };
+        }
+        // Backtrack to the deepest 0-edge whose node also has a 1-edge, and branch off there
+        for level_index in (0..sink).rev() {
+            if last[level_index] {
+                continue;
+            }
+            if let Some(e1) = visited[level_index].1 {
+                if let Some(child_edges) = self.edges_of(level_index + 1, e1) {
+                    if let Some(tail) = self.leftmost_path_below(level_index + 1, child_edges) {
+                        let mut path = last[..level_index].to_vec();
+                        path.push(true);
+                        path.extend(tail);
+                        return Ok(Some(path));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Turn a sequence of edges into the corresponding `LinEq`s.
+    fn edges_to_lin_eqs(&self, edges: &[bool]) -> Vec<LinEq> {
+        edges
+            .iter()
+            .enumerate()
+            .map(|(i, edge)| LinEq::new(self.levels[i].get_lhs(), *edge))
+            .collect()
+    }
+}
@@ -2,6 +2,7 @@ use std::fmt::{self, Formatter};
 use std::ops::Range;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PruneRecord {
     pub(super) step: usize,
     pub(super) active_area: Range<usize>,
@@ -18,6 +19,14 @@ impl PruneRecord {
         self.loop_recs.push(rec);
     }
 
+    /// Returns the lowest prune threshold used by any of the prune loops, or None if no loop was
+    /// run. Every node deleted during this prune had no trail lighter than this threshold.
+    pub fn lowest_prune_threshold(&self) -> Option<u32> {
+        self.loop_recs.iter()
+            .map(|rec| rec.prune_threshold)
+            .min()
+    }
+
     pub fn print_verbose(&self) {
         let inline = 15;
         println!("Pruning:");
@@ -59,6 +68,7 @@ impl fmt::Display for PruneRecord {
 // =============================================================================================
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PruneLoopRecord {
     pub(super) end_complexity: usize,
     /// The widest level, and its number of nodes
@@ -86,6 +96,7 @@ pub struct PruneLoopRecord {
 
 ///
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DepthDeletionRecord {
     pub(super) at_depth: usize,
     pub(super) nodes_at_level: usize,
@@ -120,6 +131,7 @@ impl DepthDeletionRecord {
 ///
 /// All values are calculated at the beginning of a batch.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BatchRecord {
     /// Run the 'reduction' algorithm after 'reduce_after' number of node deletions.
     /// The reduction algorithm has a throughput of 10 ~ 20 nodes per second, but by bulk
@@ -39,7 +39,7 @@
 //! prune
 //!
 
-use ahash::AHasher;
+use crate::FixedHasher;
 pub use dependency_finder::{DepBoolFinder, DepPathFinder};
 use logging::builders::*;
 pub use logging::PruneLogger;
@@ -198,9 +198,9 @@ impl Bdd {
 
         // Init "base case": Go `step` down, and see if an 1-edge or more was traversed, set weight accordingly
         // (We use u128, as we expect to prune before we exceed a path w/ weight 127).
-        let bc_arena: HashMap<Id, u128, BuildHasherDefault<ahash::AHasher>> =
+        let bc_arena: HashMap<Id, u128, BuildHasherDefault<FixedHasher>> =
             // "- top" is an offset, since work_area is a slice of all Levels in self.
-            work_area[base_case_index - top].get_nodes().keys()
+            work_area[base_case_index - top].iter_nodes().map(|(id, _)| id)
                 .map(|id| (id, DepBoolFinder::new(*id, base_case_index, nz_step,
                                                   self)))
                 .map(|(id, deps)| {
@@ -230,7 +230,7 @@ impl Bdd {
             .for_each(|(i, level)| {
                 let depth = base_case_index - step*(i+1);
                 let next_level = base_case_index - step*i;
-                for id in level.get_nodes().keys() {
+                for id in level.iter_nodes().map(|(id, _)| id) {
                     let trails = self.calculate_trail_weights_for_node(&id, depth,
                                                                        nz_step,
                                                                        &arena.arena.get(&next_level).unwrap());
@@ -259,7 +259,7 @@ impl Bdd {
         debug_assert_eq!(c_depth + m_step.clone().get() + short_step.clone().get(), p_depth);
 
         let mut m_level: NWAreaLevel = HashMap::default();
-        for c_id in self.levels[c_depth].get_nodes().keys() {
+        for c_id in self.levels[c_depth].iter_nodes().map(|(id, _)| id) {
 
             // We want to know the intersection of dependencies between Centurion and Member
             // Therefore, for each node in the Centurion, we first step down to the dependencies
@@ -305,7 +305,7 @@ impl Bdd {
         let deps = DepBoolFinder::new(*node_id, node_depth, step, &self);
         // Then calculate the trails weights for root
         deps.iter()
-            .map(|(id, one_edge)| (level.get(&id).unwrap(), one_edge))
+            .map(|(id, one_edge)| (level.get(id).unwrap(), one_edge))
             .map(|(trails, one_edge)| {
                 // shift bits once left iff we traversed at least one 1-edge as part of the path
                 if *one_edge { trails * 2 }
@@ -338,8 +338,8 @@ impl Bdd {
         // We need to take into account that previous_centurion may be the sink node.
         if top == bottom {
             return PWCArenaLevel::new_from(
-                self.levels[bottom].get_nodes()
-                    .keys()
+                self.levels[bottom].iter_nodes()
+                    .map(|(id, _)| id)
                     .map(|id| (*id, PWCount::fresh_trivial()))
                     .collect()
             )
@@ -347,8 +347,8 @@ impl Bdd {
 
 
         // Init "base case": Go `step` down, and see if an 1-edge or more was traversed, set weight accordingly
-        let base_case: HashMap<Id, PWCount, BuildHasherDefault<ahash::AHasher>> =
-            work_area[base_case_index - top].get_nodes().keys()
+        let base_case: HashMap<Id, PWCount, BuildHasherDefault<FixedHasher>> =
+            work_area[base_case_index - top].iter_nodes().map(|(id, _)| id)
                 .map(|id| (id, DepBoolFinder::new(*id, base_case_index, nz_step,
                                                   self)))
 
@@ -402,7 +402,7 @@ impl Bdd {
             };
 
             let node_depth = base_case_index - step*(i+1);
-            for id in level.get_nodes().keys() {
+            for id in level.iter_nodes().map(|(id, _)| id) {
                 // Calculate weights
                 let counts = self.calculate_trail_counts_for_node(id, node_depth,
                                                                   nz_step, prev);
@@ -435,7 +435,7 @@ impl Bdd {
         debug_assert_eq!(c_depth + m_step.clone().get() + short_step.clone().get(), p_depth);
 
         let mut m_level: PWCArenaLevel = PWCArenaLevel::new_from(HashMap::default());
-        for c_id in self.levels[c_depth].get_nodes().keys() {
+        for c_id in self.levels[c_depth].iter_nodes().map(|(id, _)| id) {
             for (m_id, c_edge) in DepBoolFinder::new(*c_id, c_depth,
                                                      m_step,
                                                      self) {
@@ -474,7 +474,7 @@ impl Bdd {
         let mut counts = PWCount::new();
 
         for (id, one_edge) in deps.iter() {
-            let mut trails = prev_level.get(&id).unwrap().clone();
+            let mut trails = prev_level.get(id).unwrap().clone();
             if *one_edge { trails.increment_indices(); }
             counts += trails;
         }
@@ -509,7 +509,7 @@ impl Bdd {
                 .get_mut_nodes();
             for id in delete.iter() {
                 // Remove the node
-                children.remove(id);
+                children.remove(*id);
             }
         }
 
@@ -605,14 +605,14 @@ impl Bdd {
     fn delete_nodes_from_level_until(&mut self,
                                      complexity_target: usize,
                                      delete: Vec<Id>,
-                                     // delete: HashMap<Id, u128, BuildHasherDefault<AHasher>>,
+                                     // delete: HashMap<Id, u128, BuildHasherDefault<FixedHasher>>,
                                      depth: usize,
                                      step: usize,
                                      loop_logger: &mut PruneLoopRecordBuilder,
     )
     {
         // For logging purposes:
-        // let mut deleted: HashMap<Id, u128, BuildHasherDefault<AHasher>> = Default::default();
+        // let mut deleted: HashMap<Id, u128, BuildHasherDefault<FixedHasher>> = Default::default();
         let nodes_at_level = self.levels.get(depth).unwrap().get_nodes_len() as f64;
         let nr_marked = delete.len() as f64;
 
@@ -769,8 +769,8 @@ impl Bdd {
 
     /// Crate a mapping from each child to its parents, i.e. we make indirect edges from child to
     /// parents.
-    fn children_parent_map(&mut self, parents_depth: usize) -> HashMap<Id, HashSet<Id>, BuildHasherDefault<AHasher>> {
-        let mut child_parent_map: HashMap<Id, HashSet<Id>, BuildHasherDefault<AHasher>> = Default::default();
+    fn children_parent_map(&mut self, parents_depth: usize) -> HashMap<Id, HashSet<Id>, BuildHasherDefault<FixedHasher>> {
+        let mut child_parent_map: HashMap<Id, HashSet<Id>, BuildHasherDefault<FixedHasher>> = Default::default();
 
          self.levels
              // Get nodes on parent level
@@ -834,7 +834,7 @@ impl Bdd {
 // =============================================================================================
 // ======================================== Mod Test ===========================================
 // =============================================================================================
-#[cfg(test)]
+#[cfg(all(test, feature = "parse"))]
 mod test;
 
 
@@ -25,6 +25,27 @@ fn simple_test_depfinder_iter() {
 }
 
 
+#[test]
+fn lowest_non_trivial_weight_in_level_test() {
+    let simple = crate::bdd!(6;1;
+            [
+            ("0",[(1;2,3)]);
+            ("1",[(2;4,5);(3;6,7)]);
+            ("2",[(4;8,9);(5;10,11);(6;11,12);(7;0,12)]);
+            ("3",[(8;13,14);(9;14,0);(10;13,14);(11;0,15);(12;15,0)]);
+            ("4",[(13;16,0);(14;0,16);(15;0,17)]);
+            ("5",[(16;18,0);(17;0,18)]);
+            ("",[(18;0,0)])
+            ]);
+
+    // Weights at depth 0 are 0b111, the trivial path is ignored
+    let arena = simple.identify_trails_and_weights(.., 3);
+    assert_eq!(arena.lowest_non_trivial_weight_in_level(&0), Some(1));
+    // Weights at depth 2 are 0b101 and 0b100
+    let arena = simple.identify_trails_and_weights(.., 2);
+    assert_eq!(arena.lowest_non_trivial_weight_in_level(&2), Some(2));
+    assert_eq!(arena.lowest_non_trivial_weight_in_level(&0), Some(1));
+}
 #[test]
 fn simple_test_sbox_count() {
     // First and second assert checks full range, for step 3 and 2.
@@ -43,7 +64,7 @@ fn simple_test_sbox_count() {
     let arena = simple.identify_trails_and_weights(.., 3);
 
     let arena_e: BTreeMap<usize,
-        HashMap<Id, u128, BuildHasherDefault<ahash::AHasher>>> =
+        HashMap<Id, u128, BuildHasherDefault<crate::FixedHasher>>> =
         [(3,
           [(Id::new(80001), 3),
               (Id::new(90001), 2),(Id::new(100001), 3),
@@ -60,7 +81,7 @@ fn simple_test_sbox_count() {
 
     let arena = simple.identify_trails_and_weights(.., 2);
     let arena_e: BTreeMap<usize,
-        HashMap<Id, u128, BuildHasherDefault<ahash::AHasher>>> =
+        HashMap<Id, u128, BuildHasherDefault<crate::FixedHasher>>> =
         [(0,
           [(Id::new(10001), 15)
           ].iter().cloned().collect()),
@@ -97,7 +118,7 @@ fn simple_test_sbox_count() {
     //Testing offsets from top and bottom:
 
     let arena_e: BTreeMap<usize,
-        HashMap<Id, u128, BuildHasherDefault<ahash::AHasher>>> =
+        HashMap<Id, u128, BuildHasherDefault<crate::FixedHasher>>> =
         [(2,
           [(Id::new(40001), 5),(Id::new(50001), 5),
               (Id::new(60001), 4), (Id::new(70001), 4)
@@ -115,7 +136,7 @@ fn simple_test_sbox_count() {
 
     // Testing offset, but also that we only have "one step" then return
     let arena_e: BTreeMap<usize,
-        HashMap<Id, u128, BuildHasherDefault<ahash::AHasher>>> =
+        HashMap<Id, u128, BuildHasherDefault<crate::FixedHasher>>> =
         [(2,
           [(Id::new(40001), 3),(Id::new(50001), 3),
               (Id::new(60001), 2), (Id::new(70001), 2)
@@ -149,7 +170,7 @@ fn test_prune_simple() {
     // println!("After : \n{:#?}", actual);
     //
     // let arena_e: BTreeMap<usize,
-    //     HashMap<Id, u128, BuildHasherDefault<ahash::AHasher>>> =
+    //     HashMap<Id, u128, BuildHasherDefault<crate::FixedHasher>>> =
     //     [
     //         (2,
     //          [(Id::new(70001), 4),
@@ -1,7 +1,8 @@
-use ahash::AHasher;
+use crate::FixedHasher;
 use num_traits::{One, Zero};
 use std::cell::RefCell;
-use std::collections::{btree_map, BTreeMap, BTreeSet, HashMap};
+use std::collections::{btree_map, BTreeMap, BTreeSet};
+use hashbrown::HashMap;
 use std::collections::VecDeque;
 use std::convert::From;
 use std::fmt;
@@ -13,8 +14,8 @@ use crate::soc::bdd::differential::Depth;
 use crate::soc::Id;
 
 pub type PathCount = u128;
-pub type NWAreaLevel = HashMap<Id, u128, BuildHasherDefault<AHasher>>;
-// pub type PWCArenaLevel = HashMap<Id, PWCount, BuildHasherDefault<AHasher>>;
+pub type NWAreaLevel = HashMap<Id, u128, BuildHasherDefault<FixedHasher>>;
+// pub type PWCArenaLevel = HashMap<Id, PWCount, BuildHasherDefault<FixedHasher>>;
 /// Capacity of a PathWeightCount
 // (We use 128, as we expect to prune before we exceed a path w/ weight 127).
 const CAPACITY: usize = 128;
@@ -159,6 +160,20 @@ impl NWArena {
             .map(|(lsb, count)| (*lsb, *count) ).expect("Level is empty")
     }
 
+    /// Returns the lowest **non-trivial** weight present at the given level 'depth', regardless
+    /// of whether the node holding it also holds the trivial path. Returns None if the level
+    /// only contains the trivial path.
+    /// Panics if the level is not present in self.
+    pub fn lowest_non_trivial_weight_in_level(&self, depth: &Depth) -> Option<u32> {
+        self.arena.get(depth)
+            .unwrap_or_else(|| panic!("Level not part of the Arena. Given depth was: {}", depth))
+            .values()
+            .map(|weight| weight & (u128::MAX << 1))
+            .filter(|weight| *weight != 0)
+            .map(|weight| weight.trailing_zeros())
+            .min()
+    }
+
     /// Returns a vec with the Id's and weight off all Nodes present at level 'depth' which has
     /// a LSB matching the given lsb.
     pub fn nodes_with_lsb_at_level(&self, depth: &Depth, lsb: u32) -> Vec<(Id, u128)> {
@@ -259,11 +274,11 @@ impl fmt::Debug for NWArena {
 
 #[derive(Clone, Debug)]
 pub struct PWCArenaLevel {
-    level: HashMap<Id, PWCount, BuildHasherDefault<AHasher>>,
+    level: HashMap<Id, PWCount, BuildHasherDefault<FixedHasher>>,
 }
 
 impl Deref for PWCArenaLevel {
-    type Target = HashMap<Id, PWCount, BuildHasherDefault<AHasher>>;
+    type Target = HashMap<Id, PWCount, BuildHasherDefault<FixedHasher>>;
 
     fn deref(&self) -> &Self::Target {
         &self.level
@@ -278,7 +293,7 @@ impl DerefMut for PWCArenaLevel {
 }
 
 impl PWCArenaLevel {
-    pub fn new_from(level: HashMap<Id, PWCount, BuildHasherDefault<AHasher>>) -> Self {
+    pub fn new_from(level: HashMap<Id, PWCount, BuildHasherDefault<FixedHasher>>) -> Self {
         Self {
             level,
         }
@@ -1,12 +1,12 @@
 // #[cfg(feature = "unstable")]
 // use std::collections::TryReserveError;
 
-use ahash::AHasher;
+use crate::FixedHasher;
 use std::collections::BTreeMap;
-use std::collections::hash_map::{Entry, Keys};
-use std::collections::hash_map;
-use std::collections::hash_map::Iter;
-use std::collections::HashMap;
+use hashbrown::hash_map::{Entry, Keys};
+use hashbrown::hash_map;
+use hashbrown::hash_map::Iter;
+use hashbrown::HashMap;
 use std::fmt;
 use std::hash::BuildHasherDefault;
 use std::iter::Filter;
@@ -35,7 +35,7 @@ use super::super::PathCount;
 pub struct WDLevel<W> {
     /// Depth of level, optional to set.
     depth: Option<Depth>,
-    dists: HashMap<NodeId, W, BuildHasherDefault<AHasher>>,
+    dists: HashMap<NodeId, W, BuildHasherDefault<FixedHasher>>,
 }
 
 impl<W> WDLevel<W> {
@@ -67,7 +67,7 @@ impl<W> WDLevel<W> {
         self.dists.insert(node, distribution);
     }
 
-    pub fn entry(&mut self, node: NodeId) -> Entry<NodeId, W> {
+    pub fn entry(&mut self, node: NodeId) -> Entry<'_, NodeId, W, BuildHasherDefault<FixedHasher>> {
         self.dists.entry(node)
     }
 
@@ -319,7 +319,7 @@ impl<'a, W> FromIterator<(&'a NodeId, W)> for WDLevel<W> {
         level.extend(
             iter.into_iter()
             .map(|(id, w)| (id.clone(), w))
-            .collect::<HashMap<NodeId, W, BuildHasherDefault<AHasher>>>()
+            .collect::<HashMap<NodeId, W, BuildHasherDefault<FixedHasher>>>()
         );
         Self {
             depth: None,
@@ -136,7 +136,10 @@ impl NcWDistribution for WDCountV2 {
             .values()
             .map(|v| *v as usize)
             .fold((0, false),
-            |(sum, overflow), u| sum.overflowing_add(u) )
+            |(sum, overflow), u| {
+                let (sum, overflowed) = sum.overflowing_add(u);
+                (sum, overflow || overflowed)
+            })
     }
 }
 
@@ -262,7 +265,7 @@ mod test {
             dist: Some([(0, 1), (2, 52), (3, 20), (4, 42), (5, 15), (151, 20_000)].iter().cloned().collect()),
         };
         let actual = dist.total_number_of_paths_overflowing();
-        let expected = 20_130;
+        let expected = (20_130, false);
         assert_eq!(actual, expected);
     }
 
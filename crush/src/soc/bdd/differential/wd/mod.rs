@@ -114,7 +114,7 @@ impl Shard {
             };
 
             // Fill 'fill': Centurion for current depth
-            for node_id in self.levels[current_depth].get_nodes().keys() {
+            for node_id in self.levels[current_depth].iter_nodes().map(|(id, _)| id) {
                 // Calculate weights
                 let distribution = self.calculate_distribution_for_node(node_id, current_depth,
                                                                         step, prev);
@@ -271,7 +271,7 @@ impl Shard {
         // Init "base case": Go `step` down, and see if an 1-edge or more was traversed,
         // set distribution accordingly
         let mut base_case: WDLevel<W> =
-            self.levels[base_case_index].get_nodes().keys()
+            self.levels[base_case_index].iter_nodes().map(|(id, _)| id)
                 .map(|id| {
                     (*id, bc_distribution(DepBoolFinder::new(*id,
                                                              base_case_index,
@@ -324,7 +324,7 @@ impl Shard {
         debug_assert_eq!(p_depth - centurion_depth, step.get());
 
         let mut c_level: WDLevel<W> = WDLevel::new(Some(centurion_depth));
-        for c_id in self.levels[centurion_depth].get_nodes().keys() {
+        for c_id in self.levels[centurion_depth].iter_nodes().map(|(id, _)| id) {
 
             let distribution = self.calculate_distribution_for_node(c_id,
                                                                     centurion_depth,
@@ -358,7 +358,7 @@ impl Shard {
         debug_assert_eq!(c_depth + cm_step.clone().get() + mpc_step.clone().get(), p_depth);
 
         let mut m_level: WDLevel<W> = WDLevel::new(Some(member_depth));
-        for c_id in self.levels[c_depth].get_nodes().keys() {
+        for c_id in self.levels[c_depth].iter_nodes().map(|(id, _)| id) {
             // We want to know the intersection of dependencies between Centurion and Member
             // Therefore, for each node in the Centurion, we first step down to the dependencies
             // in Member, and then onwards to the dependencies in previous Centurion reachable from
@@ -459,8 +459,8 @@ impl Shard {
         // ===================================================================================
         // Part 1): Filling base case
         let top: WDLevel<W> = self.levels[active_area.start]
-            .get_nodes()
-            .keys()
+            .iter_nodes()
+            .map(|(id, _)| id)
             .map(|id| (id.clone(), factory.new_trivial(id)))
             .collect();
 
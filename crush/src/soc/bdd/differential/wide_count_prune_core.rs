@@ -69,7 +69,7 @@ impl Bdd {
                     count.lowest_non_zero_weight() >= lew
                 })
                 .map(|(id, count)| (id, count))
-                .collect::<HashMap<&Id, &PWCount, BuildHasherDefault<AHasher>>>();
+                .collect::<HashMap<&Id, &PWCount, BuildHasherDefault<FixedHasher>>>();
 
             let mut sorted_marked: BTreeMap<PathCount, Vec<&Id>> = BTreeMap::new();
             for (id, pwc) in marked.iter() {
@@ -93,12 +93,21 @@ impl Bdd {
             progress.set_message("Pruning: Deleting nodes");
             self.delete_nodes_from_level_until(complexity_target, delete, widest.0, step, &mut loop_logger);
             let size_after = self.get_size();
-            assert!(size_after < size_before, "No nodes were deleted, we risk an infinite loop now, aborting!");
+            if size_after >= size_before {
+                // No nodes were deleted, and none will be in the next loop either. Stop above the
+                // target rather than looping forever, it's up to the caller to check the size.
+                progress.println("Pruning: no nodes left to delete, stopping above the target");
+                prune_logger.register_loop_rec(loop_logger.finalize(size_after));
+                break;
+            }
             progress.inc((size_before - size_after) as u64);
             prune_logger.register_loop_rec(loop_logger.finalize(self.get_size()));
 
             //Done
         }
+        // The deletions leave dead ends and orphans outside of the levels they were done in
+        let reduced = self.reduce();
+        progress.inc(reduced.removed_nodes() as u64);
         progress.finish_and_clear();
         librarian.record(
             prune_logger.finalize(self.get_size())
@@ -0,0 +1,145 @@
+//! Validated mutation of the nodes and edges of an existing `Bdd`.
+//!
+//! `add_nodes_to_level` and `connect_nodes_from_spec` are meant for `build_bdd_from_spec`: they
+//! take the ids of a spec, check nothing and leave `next_id` to the caller. The methods of this
+//! module are meant for any other code adding nodes and edges to a `Bdd`: the ids of new nodes are
+//! allocated by the `Bdd`, and an edge is only set if both nodes exist and the child is in the
+//! level directly below the parent. Nothing is modified when an `Error` is returned.
+//!
+//! The source and sink levels always hold a single node, so nodes can only be added to or
+//! removed from the levels in between.
+//!
+//! These methods don't keep the `Bdd` reduced: a new node is an orphan until an edge points to
+//! it, and a dead end until it has an outgoing edge. Use `remove_orphans_start` and
+//! `remove_all_dead_ends_start` once done.
+
+use std::io::{Error, ErrorKind};
+
+use crate::soc::{Id, node::Node, store::NodeStore};
+
+use super::Bdd;
+
+impl<S: NodeStore> Bdd<S> {
+    /// Return the index of the level holding the node `id`, or `None` if there is no such node.
+    ///
+    /// This goes through the levels one by one.
+    pub fn find_node_level(&self, id: Id) -> Option<usize> {
+        self.levels.iter().position(|level| level.get_node(&id).is_some())
+    }
+
+    /// Add a node without edges to the level `level_index` and return its newly allocated id.
+    ///
+    /// Return an `Error` if the level is the source, the sink or doesn't exist.
+    pub fn new_node(&mut self, level_index: usize) -> Result<Id, Error> {
+        self.new_node_with_edges(level_index, None, None)
+    }
+
+    /// Add a node to the level `level_index`, with its 0-edge and 1-edge pointing to `e0` and `e1`,
+    /// and return its newly allocated id.
+    ///
+    /// Return an `Error` if the level is the source, the sink or doesn't exist, or if `e0` or `e1`
+    /// is not a node of the level below.
+    pub fn new_node_with_edges(
+        &mut self,
+        level_index: usize,
+        e0: Option<Id>,
+        e1: Option<Id>,
+    ) -> Result<Id, Error> {
+        if level_index == 0 || level_index >= self.get_sink_level_index() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("cannot add a node to level {}, only to the levels strictly between the source and the sink", level_index),
+            ));
+        }
+        for child in e0.iter().chain(e1.iter()) {
+            self.check_child(level_index, *child)?;
+        }
+        self.next_id += 1;
+        let id = Id::new(self.next_id * 10000 + *self.id);
+        self.levels[level_index].add_edged_node(id, e0, e1);
+        Ok(id)
+    }
+
+    /// Point the 0-edge (`edge` false) or the 1-edge (`edge` true) of the node `parent` to the
+    /// node `child`, replacing the previous edge if any.
+    ///
+    /// Return an `Error` if `parent` doesn't exist or `child` is not a node of the level directly
+    /// below the one of `parent`.
+    pub fn connect(&mut self, parent: Id, child: Id, edge: bool) -> Result<(), Error> {
+        let level_index = self.parent_level(parent)?;
+        self.check_child(level_index, child)?;
+        let node = self.levels[level_index].get_mut_nodes().get_mut(&parent).unwrap();
+        if edge {
+            node.connect_e1(child);
+        } else {
+            node.connect_e0(child);
+        }
+        Ok(())
+    }
+
+    /// Remove the 0-edge (`edge` false) or the 1-edge (`edge` true) of the node `parent`, and
+    /// return the node it pointed to if any.
+    ///
+    /// Return an `Error` if `parent` doesn't exist.
+    pub fn disconnect(&mut self, parent: Id, edge: bool) -> Result<Option<Id>, Error> {
+        let level_index = self.parent_level(parent)?;
+        let node = self.levels[level_index].get_mut_nodes().get_mut(&parent).unwrap();
+        let child = if edge { node.get_e1() } else { node.get_e0() };
+        if edge {
+            node.disconnect_e1();
+        } else {
+            node.disconnect_e0();
+        }
+        Ok(child)
+    }
+
+    /// Remove the node `id` and the edges pointing to it, and return it.
+    ///
+    /// Return an `Error` if the node doesn't exist or is the source or the sink.
+    pub fn remove_node(&mut self, id: Id) -> Result<Node, Error> {
+        let level_index = self.find_node_level(id).ok_or_else(|| not_found(id))?;
+        if level_index == 0 || level_index == self.get_sink_level_index() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("node {} is the source or the sink and cannot be removed", id),
+            ));
+        }
+        for (_, node) in self.levels[level_index - 1].iter_mut_nodes() {
+            if node.get_e0() == Some(id) {
+                node.disconnect_e0();
+            }
+            if node.get_e1() == Some(id) {
+                node.disconnect_e1();
+            }
+        }
+        Ok(self.levels[level_index].get_mut_nodes().remove(&id).unwrap())
+    }
+
+    /// Return the index of the level of `parent`, or an `Error` if it doesn't exist or is in the
+    /// sink level, which has no level below.
+    fn parent_level(&self, parent: Id) -> Result<usize, Error> {
+        let level_index = self.find_node_level(parent).ok_or_else(|| not_found(parent))?;
+        if level_index == self.get_sink_level_index() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("node {} is the sink, which has no outgoing edges", parent),
+            ));
+        }
+        Ok(level_index)
+    }
+
+    /// Return an `Error` if `child` is not a node of the level below `level_index`.
+    fn check_child(&self, level_index: usize, child: Id) -> Result<(), Error> {
+        match self.levels.get(level_index + 1) {
+            Some(below) if below.get_node(&child).is_some() => Ok(()),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("node {} is not in level {}, directly below the parent", child, level_index + 1),
+            )),
+        }
+    }
+}
+
+fn not_found(id: Id) -> Error {
+    Error::new(ErrorKind::NotFound, format!("node {} not present in bdd", id))
+}
@@ -0,0 +1,197 @@
+//! Removal of the paths of a `Bdd` whose weight exceeds a bound.
+//!
+//! The weight of a path is the number of 1-edges it takes in a set of designated levels, e.g. the
+//! active S-boxes of a differential or linear trail. A node may be on paths both above and below
+//! the bound, so removing nodes isn't enough: a node is split by the weight budget left to the
+//! paths reaching it. The budget of a node is capped by the largest weight of its paths to the
+//! sink, such that a node is only split when some of its paths to the sink have to be removed for
+//! some of the paths reaching it. Nodes representing the same function are then merged again.
+
+use alloc::{vec, vec::Vec};
+use num_bigint::BigUint;
+
+use crate::{AHashMap, AHashSet};
+use crate::soc::{Id, node::Node, store::NodeStore};
+
+use super::Bdd;
+
+/// The number of nodes of each level before and after `Bdd::prune_above_weight`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WeightPruneStats {
+    /// The number of nodes of each level before the pruning.
+    pub nodes_before: Vec<usize>,
+    /// The number of nodes of each level after the pruning.
+    pub nodes_after: Vec<usize>,
+    /// The number of paths before the pruning.
+    pub paths_before: BigUint,
+    /// The number of paths after the pruning.
+    pub paths_after: BigUint,
+}
+
+impl WeightPruneStats {
+    /// Return the number of nodes removed, the levels where nodes were split counting as none.
+    pub fn removed_nodes(&self) -> usize {
+        self.nodes_before
+            .iter()
+            .zip(self.nodes_after.iter())
+            .map(|(before, after)| before.saturating_sub(*after))
+            .sum()
+    }
+
+    /// Return the number of nodes added by splitting, the levels where nodes were removed counting
+    /// as none.
+    pub fn split_nodes(&self) -> usize {
+        self.nodes_before
+            .iter()
+            .zip(self.nodes_after.iter())
+            .map(|(before, after)| after.saturating_sub(*before))
+            .sum()
+    }
+}
+
+impl<S: NodeStore> Bdd<S> {
+    /// Remove every path of the `Bdd` taking more than `max_weight` 1-edges in the levels of
+    /// `active_levels` (the indices of the levels, from 0 at the source), see the `prune` module
+    /// documentation, and return the number of nodes and paths before and after.
+    ///
+    /// The edges are expected to only lead to the level just below (see
+    /// `normalize_jumping_edges`). If no path is within the bound, only the source and the sink
+    /// are left, unconnected.
+    pub fn prune_above_weight(&mut self, max_weight: usize, active_levels: &[usize]) -> WeightPruneStats {
+        let mut stats = WeightPruneStats {
+            nodes_before: self.levels.iter().map(|level| level.get_nodes_len()).collect(),
+            paths_before: self.count_paths(),
+            ..Default::default()
+        };
+        if self.levels.len() < 2 {
+            stats.nodes_after = stats.nodes_before.clone();
+            stats.paths_after = stats.paths_before.clone();
+            return stats;
+        }
+        let sink_level_index = self.levels.len() - 1;
+        let mut active = vec![false; sink_level_index];
+        for level_index in active_levels.iter().filter(|level_index| **level_index < sink_level_index) {
+            active[*level_index] = true;
+        }
+
+        // The lowest and highest weights of the paths from each node to the sink
+        let mut bounds: AHashMap<Id, (usize, usize)> = AHashMap::default();
+        for (id, _) in self.levels[sink_level_index].iter_nodes() {
+            bounds.insert(*id, (0, 0));
+        }
+        for (level_index, level) in self.levels[..sink_level_index].iter().enumerate().rev() {
+            for (id, node) in level.iter_nodes() {
+                let node_bounds = edges(node)
+                    .filter_map(|(edge, child)| {
+                        let cost = (edge && active[level_index]) as usize;
+                        bounds.get(&child).map(|(low, high)| (low + cost, high + cost))
+                    })
+                    .fold(None, |acc: Option<(usize, usize)>, (low, high)| match acc {
+                        Some((acc_low, acc_high)) => Some((acc_low.min(low), acc_high.max(high))),
+                        None => Some((low, high)),
+                    });
+                if let Some(node_bounds) = node_bounds {
+                    bounds.insert(*id, node_bounds);
+                }
+            }
+        }
+
+        // The nodes of each level as (old node, budget left, new node), and their new edges
+        let source = *self.levels[0].iter_nodes().next().unwrap().0;
+        let sink = *self.levels[sink_level_index].iter_nodes().next().unwrap().0;
+        let mut states: Vec<Vec<(Id, usize, Id)>> = vec![Vec::new(); self.levels.len()];
+        let mut new_edges: Vec<Vec<(Option<Id>, Option<Id>)>> = vec![Vec::new(); self.levels.len()];
+        match bounds.get(&source) {
+            Some((low, high)) if *low <= max_weight => {
+                states[0].push((source, max_weight.min(*high), source));
+                self.split_by_budget(&active, &bounds, &mut states, &mut new_edges);
+            }
+            _ => {
+                states[0].push((source, 0, source));
+                new_edges[0].push((None, None));
+                states[sink_level_index].push((sink, 0, sink));
+            }
+        }
+        new_edges[sink_level_index] = vec![(None, None); states[sink_level_index].len()];
+
+        // Merge the nodes representing the same function, from the bottom
+        let mut merged: AHashMap<Id, Id> = AHashMap::default();
+        for level_index in (0..self.levels.len()).rev() {
+            let mut known_functions: AHashMap<(Option<Id>, Option<Id>), Id> = AHashMap::default();
+            let mut store = S::with_capacity(states[level_index].len());
+            for ((_, _, id), (e0, e1)) in states[level_index].iter().zip(new_edges[level_index].iter()) {
+                let function = (e0.map(|e0| merged.get(&e0).copied().unwrap_or(e0)),
+                                e1.map(|e1| merged.get(&e1).copied().unwrap_or(e1)));
+                match known_functions.get(&function) {
+                    Some(existing) if level_index != 0 && level_index != sink_level_index => {
+                        merged.insert(*id, *existing);
+                    }
+                    _ => {
+                        known_functions.insert(function, *id);
+                        store.insert(*id, Node::with_edges(function.0, function.1));
+                    }
+                }
+            }
+            self.levels[level_index].replace_nodes(store);
+        }
+
+        stats.nodes_after = self.levels.iter().map(|level| level.get_nodes_len()).collect();
+        stats.paths_after = self.count_paths();
+        stats
+    }
+
+    /// Fill the levels of `states` below the source, and the edges of the levels above the sink in
+    /// `new_edges`, following the edges within the budget of each node.
+    fn split_by_budget(
+        &mut self,
+        active: &[bool],
+        bounds: &AHashMap<Id, (usize, usize)>,
+        states: &mut [Vec<(Id, usize, Id)>],
+        new_edges: &mut [Vec<(Option<Id>, Option<Id>)>],
+    ) {
+        for level_index in 0..self.levels.len() - 1 {
+            let mut next: AHashMap<(Id, usize), Id> = AHashMap::default();
+            let mut reused: AHashSet<Id> = AHashSet::default();
+            for state_index in 0..states[level_index].len() {
+                let (old, budget, _) = states[level_index][state_index];
+                let node = self.levels[level_index].get_node(&old).unwrap().clone();
+                let mut children = (None, None);
+                for (edge, child) in edges(&node) {
+                    let cost = (edge && active[level_index]) as usize;
+                    let (low, high) = match bounds.get(&child) {
+                        Some((low, high)) if cost + low <= budget => (*low, *high),
+                        _ => continue,
+                    };
+                    debug_assert!(low <= budget - cost);
+                    let key = (child, (budget - cost).min(high));
+                    let new_child = match next.get(&key) {
+                        Some(new_child) => *new_child,
+                        None => {
+                            // The first state of a node keeps its id
+                            let new_child = if reused.insert(child) {
+                                child
+                            } else {
+                                self.next_id += 1;
+                                Id::new(self.next_id * 10000 + *self.id)
+                            };
+                            next.insert(key, new_child);
+                            states[level_index + 1].push((child, key.1, new_child));
+                            new_child
+                        }
+                    };
+                    if edge {
+                        children.1 = Some(new_child);
+                    } else {
+                        children.0 = Some(new_child);
+                    }
+                }
+                new_edges[level_index].push(children);
+            }
+        }
+    }
+}
+
+/// The edges of `node`, as (false for the 0-edge, child).
+fn edges(node: &Node) -> impl Iterator<Item = (bool, Id)> {
+    node.get_e0().map(|e0| (false, e0)).into_iter().chain(node.get_e1().map(|e1| (true, e1)))
+}
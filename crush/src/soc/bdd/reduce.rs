@@ -0,0 +1,135 @@
+//! Reduction of a `Bdd`: removal of the nodes on no path from the source to the sink, and merging
+//! of the nodes representing the same function.
+//!
+//! The operations of `Bdd` keep it reduced as they go, but only from the levels they touched and
+//! short circuited (see `merge_equals_node_start`), and the deletions of the pruning leave dead
+//! ends and orphans behind in other levels. `Bdd::reduce` goes through the whole `Bdd` once:
+//! - from the sink up, the edges to missing nodes are disconnected, the nodes left without edges
+//!   are removed (dead ends), and the nodes of a level with the same edges are merged;
+//! - from the source down, the nodes no longer reached are removed (orphans).
+//!
+//! As the edges only lead to the level just below, a node with both edges to the same child is
+//! not redundant here, and is kept. The source and the sink are always kept, the source being
+//! left without edges if there is no path left.
+
+use alloc::vec::Vec;
+use crate::{AHashMap, AHashSet};
+use crate::soc::{Id, node::Node, store::NodeStore};
+
+use super::Bdd;
+
+/// The number of nodes of each level before and after `Bdd::reduce`, and what was removed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReduceStats {
+    /// The number of nodes of each level before the reduction.
+    pub nodes_before: Vec<usize>,
+    /// The number of nodes of each level after the reduction.
+    pub nodes_after: Vec<usize>,
+    /// The number of nodes removed as they lead to no node.
+    pub dead_ends: usize,
+    /// The number of nodes removed as no node leads to them.
+    pub orphans: usize,
+    /// The number of nodes merged into another one of the same level.
+    pub merged: usize,
+}
+
+impl ReduceStats {
+    /// Return the number of nodes removed.
+    pub fn removed_nodes(&self) -> usize {
+        self.dead_ends + self.orphans + self.merged
+    }
+
+    /// Return the number of nodes before the reduction.
+    pub fn size_before(&self) -> usize {
+        self.nodes_before.iter().sum()
+    }
+
+    /// Return the number of nodes after the reduction.
+    pub fn size_after(&self) -> usize {
+        self.nodes_after.iter().sum()
+    }
+}
+
+impl<S: NodeStore> Bdd<S> {
+    /// Remove the dead ends and orphans of every level and merge the nodes representing the same
+    /// function, see the `reduce` module documentation, and return the number of nodes before
+    /// and after.
+    ///
+    /// The set of solutions and the number of paths are unchanged. Of merged nodes, the one with
+    /// the lowest id is kept.
+    pub fn reduce(&mut self) -> ReduceStats {
+        let mut stats = ReduceStats {
+            nodes_before: self.levels.iter().map(|level| level.get_nodes_len()).collect(),
+            ..Default::default()
+        };
+        if self.levels.len() < 2 {
+            stats.nodes_after = stats.nodes_before.clone();
+            return stats;
+        }
+        let sink_level_index = self.levels.len() - 1;
+
+        // The nodes of the level below merged into another one
+        let mut merged: AHashMap<Id, Id> = AHashMap::default();
+        for level_index in (0..sink_level_index).rev() {
+            let below = &self.levels[level_index + 1];
+            let child = |edge: Option<Id>| {
+                edge.map(|id| merged.get(&id).copied().unwrap_or(id))
+                    .filter(|id| below.get_node(id).is_some())
+            };
+            let mut nodes: Vec<(Id, Option<Id>, Option<Id>)> = self.levels[level_index]
+                .iter_nodes()
+                .map(|(id, node)| (*id, child(node.get_e0()), child(node.get_e1())))
+                .collect();
+            nodes.sort_by_key(|(id, _, _)| *id);
+
+            let mut level_merged: AHashMap<Id, Id> = AHashMap::default();
+            let mut known_functions: AHashMap<(Option<Id>, Option<Id>), Id> = AHashMap::default();
+            let mut store = S::with_capacity(nodes.len());
+            for (id, e0, e1) in nodes {
+                if level_index == 0 {
+                    store.insert(id, Node::with_edges(e0, e1));
+                } else if e0.is_none() && e1.is_none() {
+                    stats.dead_ends += 1;
+                } else if let Some(existing) = known_functions.get(&(e0, e1)) {
+                    level_merged.insert(id, *existing);
+                    stats.merged += 1;
+                } else {
+                    known_functions.insert((e0, e1), id);
+                    store.insert(id, Node::with_edges(e0, e1));
+                }
+            }
+            self.levels[level_index].replace_nodes(store);
+            merged = level_merged;
+        }
+
+        stats.orphans = self.remove_all_orphans();
+        stats.nodes_after = self.levels.iter().map(|level| level.get_nodes_len()).collect();
+        stats
+    }
+
+    /// Remove the nodes which no node of the level above leads to, from the source down to the
+    /// sink (excluded), and return their number. Unlike `remove_orphans_start`, this goes through
+    /// all the levels.
+    pub(super) fn remove_all_orphans(&mut self) -> usize {
+        let mut removed = 0;
+        // The nodes of the current level reached from the level above
+        let mut reached: AHashSet<Id> = AHashSet::default();
+        for level_index in 0..self.levels.len() - 1 {
+            if level_index > 0 {
+                let orphans: AHashSet<Id> = self.levels[level_index]
+                    .iter_nodes()
+                    .map(|(id, _)| *id)
+                    .filter(|id| !reached.contains(id))
+                    .collect();
+                removed += orphans.len();
+                self.levels[level_index].remove_nodes_from_set(&orphans);
+            }
+            reached.clear();
+            for (_, node) in self.levels[level_index].iter_nodes() {
+                reached.extend(node.get_e0());
+                reached.extend(node.get_e1());
+            }
+        }
+        removed
+    }
+}
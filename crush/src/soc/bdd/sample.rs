@@ -0,0 +1,137 @@
+//! Uniform random sampling of the solutions of a `Bdd`.
+//!
+//! A path is drawn from top to bottom, each edge being taken with a probability proportional to
+//! the number of paths from its child to the sink, such that every path is equally likely. A
+//! random solution of the linear equations of the path is then drawn, each free variable being
+//! uniform. Every path consistent with the linear dependencies between the lhs has the same number
+//! of solutions (see the `count` module), the solutions are therefore uniform over the ones
+//! accepted by the `Bdd`. An inconsistent path is rejected and another one drawn, which is only
+//! slow if few paths are consistent.
+
+use num_bigint::BigUint;
+use rand::Rng;
+use vob::Vob;
+
+use crate::AHashMap;
+use crate::soc::{Id, store::NodeStore};
+
+use super::{count::linear_dependencies, Bdd};
+
+impl<S: NodeStore> Bdd<S> {
+    /// Return a solution of the `Bdd` drawn uniformly at random among the assignments of the
+    /// variables it accepts (see `accepts`), or `None` if it accepts none.
+    pub fn sample_solution<R: Rng>(&self, rng: &mut R) -> Option<Vob> {
+        self.sample_solutions(rng, 1).pop()
+    }
+
+    /// Return `count` solutions of the `Bdd` drawn independently and uniformly at random, see
+    /// `sample_solution`. The path counts are computed once for all the samples.
+    pub fn sample_solutions<R: Rng>(&self, rng: &mut R, count: usize) -> Vec<Vob> {
+        if self.levels.len() < 2 {
+            return Vec::new();
+        }
+        let sink_level_index = self.levels.len() - 1;
+        let nvar = self.levels[0].get_lhs().len();
+        let lhss: Vec<Vob> = self.levels[..sink_level_index].iter().map(|level| level.get_lhs()).collect();
+        let (dependencies, _) = linear_dependencies(&lhss);
+        if !dependencies.is_empty() && self.count_solutions(nvar) == BigUint::from(0u8) {
+            return Vec::new();
+        }
+
+        // The number of paths from each node to the sink
+        let mut paths: AHashMap<Id, BigUint> = AHashMap::default();
+        for (id, _) in self.levels[sink_level_index].iter_nodes() {
+            paths.insert(*id, BigUint::from(1u8));
+        }
+        for level in self.levels[..sink_level_index].iter().rev() {
+            for (id, node) in level.iter_nodes() {
+                let mut node_paths = BigUint::from(0u8);
+                for child in node.get_e0().into_iter().chain(node.get_e1()) {
+                    if let Some(child_paths) = paths.get(&child) {
+                        node_paths += child_paths;
+                    }
+                }
+                paths.insert(*id, node_paths);
+            }
+        }
+        let source = match self.levels[0].iter_nodes().next() {
+            Some((id, _)) if paths[id] != BigUint::from(0u8) => *id,
+            _ => return Vec::new(),
+        };
+
+        let mut samples = Vec::with_capacity(count);
+        while samples.len() < count {
+            let mut current = source;
+            let mut path = Vec::with_capacity(sink_level_index);
+            for level_index in 0..sink_level_index {
+                let node = self.levels[level_index].get_node(&current).unwrap();
+                let paths_of = |edge: Option<Id>| {
+                    edge.and_then(|child| paths.get(&child)).cloned().unwrap_or_default()
+                };
+                let paths0 = paths_of(node.get_e0());
+                let total = paths0.clone() + paths_of(node.get_e1());
+                let edge = random_below(rng, &total) >= paths0;
+                current = if edge { node.get_e1() } else { node.get_e0() }.unwrap();
+                path.push(edge);
+            }
+            if let Some(solution) = random_solution(rng, &lhss, &path, nvar) {
+                samples.push(solution);
+            }
+        }
+        samples
+    }
+}
+
+/// Return a random solution of the linear equations `lhss[i] = rhss[i]` over `nvar` variables,
+/// uniform over the solutions, or `None` if they are inconsistent.
+fn random_solution<R: Rng>(rng: &mut R, lhss: &[Vob], rhss: &[bool], nvar: usize) -> Option<Vob> {
+    // The echelon basis by pivot, the highest variable of each row
+    let mut basis: AHashMap<usize, (Vob, bool)> = AHashMap::default();
+    for (lhs, rhs) in lhss.iter().zip(rhss) {
+        let (mut row, mut value) = (lhs.clone(), *rhs);
+        loop {
+            match row.iter_set_bits(..).last() {
+                Some(pivot) => match basis.get(&pivot) {
+                    Some((basis_row, basis_value)) => {
+                        row.xor(basis_row);
+                        value ^= basis_value;
+                    }
+                    None => {
+                        basis.insert(pivot, (row, value));
+                        break;
+                    }
+                },
+                None if value => return None,
+                None => break,
+            }
+        }
+    }
+    // The free variables are random, the pivots are set from the lowest, each row only involving
+    // its pivot and lower variables.
+    let mut solution: Vob = (0..nvar).map(|_| rng.gen::<bool>()).collect();
+    let mut pivots: Vec<&usize> = basis.keys().collect();
+    pivots.sort_unstable();
+    for pivot in pivots {
+        let (row, value) = &basis[pivot];
+        let others = row.iter_set_bits(..*pivot).fold(false, |parity, var| parity ^ solution[var]);
+        solution.set(*pivot, value ^ others);
+    }
+    Some(solution)
+}
+
+/// Return a random `BigUint` uniform in `[0, bound)`, `bound` being positive.
+fn random_below<R: Rng>(rng: &mut R, bound: &BigUint) -> BigUint {
+    let bits = bound.bits();
+    let mut bytes = vec![0u8; bits.div_ceil(8) as usize];
+    loop {
+        rng.fill_bytes(&mut bytes);
+        if !bits.is_multiple_of(8) {
+            let last = bytes.len() - 1;
+            bytes[last] &= (1u8 << (bits % 8)) - 1;
+        }
+        let value = BigUint::from_bytes_le(&bytes);
+        if &value < bound {
+            return value;
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Dynamic reordering of the levels of a `Bdd` by sifting.
+//!
+//! The size of a `Bdd` heavily depends on the order of its levels, and the order inherited from the
+//! description of a cipher is often a bad one for an intermediate `Bdd`. Sifting moves each level in
+//! turn through all the positions, by swapping it with its neighbours (see `Bdd::swap`), and leaves
+//! it where the `Bdd` was the smallest. The levels are sifted from the widest to the narrowest, as
+//! the widest are the most likely to be misplaced.
+//!
+//! Moving a level away from its best position can make the `Bdd` blow up, so a level stops moving
+//! in a direction once the `Bdd` grows beyond `max_growth` times the smallest size found so far.
+//!
+//! Only the order of the levels changes: the `Bdd` still represents the same set of solutions, and
+//! each level keeps its LHS. The edges are expected to only lead to the level just below (see
+//! `normalize_jumping_edges`).
+//!
+//! Code driving its own reordering should use `Bdd::swap_adjacent_levels`, which checks these
+//! expectations and returns how the number of nodes changed, rather than `Bdd::swap`.
+
+use std::io::{Error, ErrorKind};
+
+use crate::soc::store::NodeStore;
+
+use super::Bdd;
+
+/// The default `max_growth` of `Bdd::sift_levels_with_growth`.
+pub const SIFT_MAX_GROWTH: f64 = 1.2;
+
+/// The number of nodes of a `Bdd` before and after `Bdd::sift_levels`, and the number of swaps it
+/// took.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SiftStats {
+    pub nodes_before: usize,
+    pub nodes_after: usize,
+    pub swaps: usize,
+}
+
+impl SiftStats {
+    /// Return the number of nodes saved by the sifting.
+    pub fn saved_nodes(&self) -> usize {
+        self.nodes_before.saturating_sub(self.nodes_after)
+    }
+}
+
+/// The number of nodes of the two levels exchanged by `Bdd::swap_adjacent_levels`, before and
+/// after the exchange. `above` is the level which was at the index given, and is now below.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LevelSwap {
+    pub above_before: usize,
+    pub below_before: usize,
+    pub above_after: usize,
+    pub below_after: usize,
+}
+
+impl LevelSwap {
+    /// Return the change in the number of nodes at the upper of the two indexes.
+    pub fn delta_above(&self) -> isize {
+        self.above_after as isize - self.above_before as isize
+    }
+
+    /// Return the change in the number of nodes at the lower of the two indexes.
+    pub fn delta_below(&self) -> isize {
+        self.below_after as isize - self.below_before as isize
+    }
+
+    /// Return the change in the number of nodes of the `Bdd`.
+    pub fn delta(&self) -> isize {
+        self.delta_above() + self.delta_below()
+    }
+}
+
+impl<S: NodeStore> Bdd<S> {
+    /// Exchange the levels `index` and `index + 1`, and return the number of nodes they hold
+    /// before and after.
+    ///
+    /// The set of solutions of the `Bdd` is unchanged, the LHS's of the two levels are exchanged,
+    /// and no other level is modified. The nodes of the level `index` keep their ids, and the ones
+    /// of the level `index + 1` are replaced by new ones, at most twice as many as there are nodes
+    /// at `index`. Two nodes of the new level never represent the same function, but the levels
+    /// above aren't merged again, as in `Bdd::swap`.
+    ///
+    /// This takes a time linear in the number of nodes of the two levels.
+    ///
+    /// Return an `Error`, without modifying the `Bdd`, if one of the levels is the sink or doesn't
+    /// exist, or if an edge of one of them doesn't lead to the level directly below.
+    pub fn swap_adjacent_levels(&mut self, index: usize) -> Result<LevelSwap, Error> {
+        let sink = self.get_sink_level_index();
+        if index + 1 >= sink {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Levels {} and {} can't be swapped, the sink is level {}", index, index + 1, sink),
+            ));
+        }
+        for level in index..index + 2 {
+            let below = &self.levels[level + 1];
+            let jumping = self.levels[level]
+                .iter_nodes()
+                .flat_map(|(id, node)| [node.get_e0(), node.get_e1()].map(|edge| (*id, edge)))
+                .find(|(_, edge)| matches!(edge, Some(child) if below.get_node(child).is_none()));
+            if let Some((parent, Some(child))) = jumping {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("The edge from {} at level {} to {} doesn't lead to the level below", parent, level, child),
+                ));
+            }
+        }
+        let mut swap = LevelSwap {
+            above_before: self.levels[index].get_nodes_len(),
+            below_before: self.levels[index + 1].get_nodes_len(),
+            ..LevelSwap::default()
+        };
+        self.swap(index, index + 1);
+        swap.above_after = self.levels[index].get_nodes_len();
+        swap.below_after = self.levels[index + 1].get_nodes_len();
+        Ok(swap)
+    }
+
+    /// Reorder the levels of the `Bdd` by sifting, with the growth bound `SIFT_MAX_GROWTH`, see
+    /// the `sift` module documentation.
+    pub fn sift_levels(&mut self) -> SiftStats {
+        self.sift_levels_with_growth(SIFT_MAX_GROWTH)
+    }
+
+    /// Reorder the levels of the `Bdd` by sifting, a level no longer moving in a direction once the
+    /// `Bdd` grows beyond `max_growth` (at least 1) times the smallest size found so far. See the
+    /// `sift` module documentation.
+    pub fn sift_levels_with_growth(&mut self, max_growth: f64) -> SiftStats {
+        let nodes_before = self.get_size();
+        let mut stats = SiftStats { nodes_before, nodes_after: nodes_before, swaps: 0 };
+        // The sink can't be moved
+        let movable = self.get_sink_level_index();
+        if movable < 2 {
+            return stats;
+        }
+        // The original index of the level at each position, to find a level once others moved
+        let mut origin: Vec<usize> = (0..movable).collect();
+        let mut order: Vec<usize> = (0..movable).collect();
+        order.sort_by_key(|level| core::cmp::Reverse(self.levels[*level].get_nodes_len()));
+        for level in order {
+            let position = origin.iter().position(|o| *o == level).unwrap();
+            stats.swaps += self.sift_level(position, movable, max_growth.max(1.0), &mut origin);
+        }
+        stats.nodes_after = self.get_size();
+        stats
+    }
+
+    /// Move the level at `position` down to the last of the `movable` levels, then up to the
+    /// first, and then to where the `Bdd` was the smallest, see `sift_levels_with_growth`.
+    /// Return the number of swaps.
+    fn sift_level(&mut self, position: usize, movable: usize, max_growth: f64, origin: &mut [usize]) -> usize {
+        let mut best = (self.get_size(), position);
+        let mut current = position;
+        let mut swaps = 0;
+        let mut swap = |bdd: &mut Self, above: usize| {
+            bdd.swap(above, above + 1);
+            origin.swap(above, above + 1);
+            swaps += 1;
+        };
+        let too_big = |size: usize, best: usize| size as f64 > best as f64 * max_growth;
+
+        while current + 1 < movable {
+            swap(self, current);
+            current += 1;
+            let size = self.get_size();
+            if size < best.0 {
+                best = (size, current);
+            }
+            if too_big(size, best.0) {
+                break;
+            }
+        }
+        // Back to the start, the sizes on the way are already known
+        while current > position {
+            swap(self, current - 1);
+            current -= 1;
+        }
+        while current > 0 {
+            swap(self, current - 1);
+            current -= 1;
+            let size = self.get_size();
+            if size < best.0 {
+                best = (size, current);
+            }
+            if too_big(size, best.0) {
+                break;
+            }
+        }
+        while current < best.1 {
+            swap(self, current);
+            current += 1;
+        }
+        swaps
+    }
+}
@@ -0,0 +1,65 @@
+//! Transfer matrices between the successive levels of a `Bdd`.
+//!
+//! The transfer matrices of a level are the adjacency matrices of its 0-edges and 1-edges towards
+//! the level below: the entry (i, j) is set if the edge of the i-th node of the level points to
+//! the j-th node of the level below. The nodes of a level are numbered from 0 by increasing id.
+//!
+//! Counting the paths, or their distribution over the edge types, amounts to multiplying these
+//! matrices, which is why they are meant to be exported (see `io::print_transfer_matrices_to_dir`)
+//! and studied in numerical tools. They are sparse, each row having at most one entry per matrix,
+//! so only the set entries are kept.
+
+use alloc::vec::Vec;
+use crate::soc::{Id, store::NodeStore};
+
+use super::Bdd;
+
+/// The transfer matrices from a level of a `Bdd` to the next one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferMatrices {
+    /// Number of nodes of the level, the number of rows.
+    pub rows: usize,
+    /// Number of nodes of the level below, the number of columns.
+    pub columns: usize,
+    /// Set entries (row, column) of the matrix of the 0-edges, sorted by row.
+    pub e0: Vec<(usize, usize)>,
+    /// Set entries (row, column) of the matrix of the 1-edges, sorted by row.
+    pub e1: Vec<(usize, usize)>,
+}
+
+impl<S: NodeStore> Bdd<S> {
+    /// Return the transfer matrices of each level to the next one, from the top.
+    ///
+    /// An edge to a node absent from the level below is left out.
+    pub fn transfer_matrices(&self) -> Vec<TransferMatrices> {
+        let sorted_ids = |level_index: usize| {
+            let mut ids: Vec<Id> = self.levels[level_index].iter_nodes().map(|(id, _)| *id).collect();
+            ids.sort_unstable();
+            ids
+        };
+        let mut matrices = Vec::with_capacity(self.levels.len().saturating_sub(1));
+        let mut ids = sorted_ids(0);
+        for level_index in 0..self.levels.len().saturating_sub(1) {
+            let ids_below = sorted_ids(level_index + 1);
+            let column = |edge: Option<Id>| edge.and_then(|id| ids_below.binary_search(&id).ok());
+            let mut matrix = TransferMatrices {
+                rows: ids.len(),
+                columns: ids_below.len(),
+                e0: Vec::new(),
+                e1: Vec::new(),
+            };
+            for (row, id) in ids.iter().enumerate() {
+                let node = self.levels[level_index].get_node(id).unwrap();
+                if let Some(column) = column(node.get_e0()) {
+                    matrix.e0.push((row, column));
+                }
+                if let Some(column) = column(node.get_e1()) {
+                    matrix.e1.push((row, column));
+                }
+            }
+            matrices.push(matrix);
+            ids = ids_below;
+        }
+        matrices
+    }
+}
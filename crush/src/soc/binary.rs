@@ -0,0 +1,170 @@
+//! Compact binary format of a `System`, to save and restore snapshots of a system being solved
+//! much faster than with the textual .bdd format.
+//!
+//! Unlike the .bdd format, a snapshot is exact: the ids of the nodes, the `next_id` of the bdds and
+//! the `LinBank` are kept as they are, so the restored system is in the very same state.
+//!
+//! All the integers are written as 64 bits little endian words:
+//!
+//! - the header, the bytes `BINARY_MAGIC` followed by `BINARY_VERSION`,
+//! - `nvar`, the number of bdds and the number of `LinEq` of the `LinBank`,
+//! - for each bdd, by increasing id: its id, `next_id` and number of levels, then for each level
+//!   its lhs and number of nodes, followed by each node by increasing id as its id, 0-edge and
+//!   1-edge, 0 meaning no edge,
+//! - for each `LinEq`, its lhs and rhs (0 or 1).
+//!
+//! A lhs is written as its number of set bits followed by the set bits.
+//!
+//! The functions read and write the given reader and writer word by word, they should be
+//! buffered (e.g. `BufReader` and `BufWriter`).
+
+use std::convert::TryFrom;
+use std::io::{self, Error, ErrorKind, Read, Write};
+
+use vob::Vob;
+
+use crate::soc::{
+    bdd::{Bdd, LinEq},
+    Id,
+    node::Node,
+    system::System};
+
+/// The bytes starting a binary snapshot.
+pub const BINARY_MAGIC: &[u8; 8] = b"CRUSHSYS";
+/// Version of the binary format written by this module.
+pub const BINARY_VERSION: u64 = 1;
+
+impl System {
+    /// Write a binary snapshot of the system to `writer`, see the module documentation.
+    pub fn serialize_binary<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(BINARY_MAGIC)?;
+        write_u64(writer, BINARY_VERSION)?;
+        write_u64(writer, self.get_nvar() as u64)?;
+        write_u64(writer, self.iter_bdds().len() as u64)?;
+        write_u64(writer, self.get_lin_bank_size() as u64)?;
+        let mut ids: Vec<Id> = self.iter_bdds().map(|(id, _)| *id).collect();
+        ids.sort_unstable();
+        for id in ids {
+            write_bdd(writer, &self.get_bdd(id).unwrap().borrow())?;
+        }
+        for lin_eq in self.iter_lin_eqs() {
+            write_lhs(writer, &lin_eq.get_lhs())?;
+            write_u64(writer, lin_eq.get_rhs() as u64)?;
+        }
+        Ok(())
+    }
+
+    /// Read a binary snapshot written by `serialize_binary` from `reader`.
+    ///
+    /// Return an `Error` of kind `ErrorKind::Unsupported` if it was written by a later version of
+    /// the format, and of kind `ErrorKind::InvalidData` if it is not a snapshot or is malformed.
+    pub fn deserialize_binary<R: Read>(reader: &mut R) -> io::Result<System> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != BINARY_MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a binary snapshot of a system"));
+        }
+        let version = read_u64(reader)?;
+        if version > BINARY_VERSION {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("binary format version {} is newer than the supported {}", version, BINARY_VERSION),
+            ));
+        }
+        let nvar = read_usize(reader)?;
+        let nbdds = read_usize(reader)?;
+        let nlin_eqs = read_usize(reader)?;
+        let mut system = System::new();
+        system.set_nvar(nvar);
+        for _ in 0..nbdds {
+            system.push_bdd(read_bdd(reader, nvar)?)?;
+        }
+        let mut lin_eqs = Vec::new();
+        for _ in 0..nlin_eqs {
+            let lhs = read_lhs(reader, nvar)?;
+            lin_eqs.push(LinEq::new(lhs, read_u64(reader)? != 0));
+        }
+        system.set_lin_eqs(lin_eqs);
+        Ok(system)
+    }
+}
+
+fn write_bdd<W: Write>(writer: &mut W, bdd: &Bdd) -> io::Result<()> {
+    write_u64(writer, *bdd.get_id() as u64)?;
+    write_u64(writer, bdd.get_next_id() as u64)?;
+    write_u64(writer, bdd.get_levels_size() as u64)?;
+    for level in bdd.iter_levels() {
+        write_lhs(writer, &level.get_lhs())?;
+        write_u64(writer, level.get_nodes_len() as u64)?;
+        let mut nodes: Vec<_> = level.iter_nodes().collect();
+        nodes.sort_unstable_by_key(|(id, _)| **id);
+        for (id, node) in nodes {
+            write_u64(writer, **id as u64)?;
+            write_u64(writer, node.get_e0().map_or(0, |e0| *e0) as u64)?;
+            write_u64(writer, node.get_e1().map_or(0, |e1| *e1) as u64)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_bdd<R: Read>(reader: &mut R, nvar: usize) -> io::Result<Bdd> {
+    let mut bdd = Bdd::new();
+    bdd.set_id(Id::new(read_usize(reader)?));
+    bdd.set_next_id(read_usize(reader)?);
+    let nlevels = read_usize(reader)?;
+    if nlevels == 0 {
+        return Err(Error::new(ErrorKind::InvalidData, format!("bdd {} has no level", bdd.get_id())));
+    }
+    for _ in 0..nlevels {
+        let lhs = read_lhs(reader, nvar)?;
+        let nnodes = read_usize(reader)?;
+        let mut nodes = Vec::new();
+        for _ in 0..nnodes {
+            let id = Id::new(read_usize(reader)?);
+            let edge = |e: usize| if e != 0 { Some(Id::new(e)) } else { None };
+            let e0 = edge(read_usize(reader)?);
+            let e1 = edge(read_usize(reader)?);
+            nodes.push((id, Node::with_edges(e0, e1)));
+        }
+        bdd.add_level_with_nodes(lhs, nodes);
+    }
+    Ok(bdd)
+}
+
+fn write_lhs<W: Write>(writer: &mut W, lhs: &Vob) -> io::Result<()> {
+    write_u64(writer, lhs.iter_set_bits(..).count() as u64)?;
+    for var in lhs.iter_set_bits(..) {
+        write_u64(writer, var as u64)?;
+    }
+    Ok(())
+}
+
+fn read_lhs<R: Read>(reader: &mut R, nvar: usize) -> io::Result<Vob> {
+    let mut lhs = Vob::from_elem(nvar, false);
+    for _ in 0..read_usize(reader)? {
+        let var = read_usize(reader)?;
+        if var >= nvar {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("variable {} out of the {} variables of the system", var, nvar),
+            ));
+        }
+        lhs.set(var, true);
+    }
+    Ok(lhs)
+}
+
+fn write_u64<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_usize<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let value = read_u64(reader)?;
+    usize::try_from(value).map_err(|_| Error::new(ErrorKind::InvalidData, format!("{} overflows usize", value)))
+}
@@ -0,0 +1,224 @@
+//! Export of a `System` to the DIMACS CNF format read by SAT solvers and model counters (e.g.
+//! CryptoMiniSat, Kissat, ApproxMC), to cross-check the solutions found by this library.
+//!
+//! The variable `i` of the system is the DIMACS variable `i + 1`. The Tseitin encoding adds:
+//! - for each lhs of more than one variable, the chain of variables of its partial XORs;
+//! - for each node, a variable true iff the node is on the path of the assignment, i.e. the source
+//!   or a node reached from a node on the path through the edge of the value of its lhs;
+//! - for each of these edges, a variable true iff the edge is taken.
+//!
+//! The sources and sinks are asserted, as are the linear equations of the `LinBank`. All the added
+//! variables are defined by the variables of the system, so the CNF has exactly as many models as
+//! the system has solutions. The variables of the system are listed in a `c ind` line, for the
+//! model counters projecting on them.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use vob::Vob;
+
+use crate::AHashMap;
+use crate::soc::{Id, system::System};
+
+/// The meaning of the DIMACS variables of the CNF written by `System::to_dimacs`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DimacsMapping {
+    /// The number of variables of the system, the DIMACS variables `1..=nvar`.
+    pub nvar: usize,
+    /// The literal of the lhs of each level, by bdd and level. A negative literal is a negated
+    /// variable, as in the DIMACS format.
+    pub levels: BTreeMap<(Id, usize), i64>,
+    /// The variable of each node, by bdd and node.
+    pub nodes: BTreeMap<(Id, Id), i64>,
+}
+
+impl DimacsMapping {
+    /// Write the mapping to writer, one variable per line: `x<i> <var>` for the variables of the
+    /// system, `lhs <bdd> <level> <literal>` for the lhs of the levels and `node <bdd> <node> <var>`
+    /// for the nodes.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for var in 0..self.nvar {
+            writeln!(writer, "x{} {}", var, var + 1)?;
+        }
+        for ((bdd, level), literal) in self.levels.iter() {
+            writeln!(writer, "lhs {} {} {}", bdd, level, literal)?;
+        }
+        for ((bdd, node), var) in self.nodes.iter() {
+            writeln!(writer, "node {} {} {}", bdd, node, var)?;
+        }
+        Ok(())
+    }
+}
+
+/// A formula in conjunctive normal form, as a list of clauses of DIMACS literals: the variable
+/// `v` is the literal `v` and its negation `-v`, from 1.
+///
+/// The XORs are Tseitin encoded once, their literal being reused afterwards.
+#[derive(Debug, Default, Clone)]
+pub struct Cnf {
+    nvars: i64,
+    clauses: Vec<Vec<i64>>,
+    xors: AHashMap<Vec<usize>, i64>,
+}
+
+impl Cnf {
+    /// Return an empty `Cnf` over the variables `1..=nvars`.
+    pub fn new(nvars: usize) -> Cnf {
+        Cnf { nvars: nvars as i64, ..Default::default() }
+    }
+
+    /// Return the number of variables.
+    pub fn get_nvars(&self) -> usize {
+        self.nvars as usize
+    }
+
+    /// Return the clauses.
+    pub fn get_clauses(&self) -> &[Vec<i64>] {
+        &self.clauses
+    }
+
+    /// Return the clauses, consuming the `Cnf`.
+    pub fn into_clauses(self) -> Vec<Vec<i64>> {
+        self.clauses
+    }
+
+    /// Return a new variable.
+    pub fn new_var(&mut self) -> i64 {
+        self.nvars += 1;
+        self.nvars
+    }
+
+    /// Add a clause, the empty clause making the `Cnf` unsatisfiable.
+    pub fn add_clause(&mut self, clause: Vec<i64>) {
+        self.clauses.push(clause);
+    }
+
+    /// Add the clauses of the linear equation `lhs = rhs`, the variable `i` of `lhs` being the
+    /// DIMACS variable `i + 1`.
+    pub fn add_linear_equation(&mut self, lhs: &Vob, rhs: bool) {
+        match self.xor(lhs.iter_set_bits(..).collect()) {
+            Some(literal) if rhs => self.clauses.push(vec![literal]),
+            Some(literal) => self.clauses.push(vec![-literal]),
+            None if rhs => self.clauses.push(vec![]),
+            None => {}
+        }
+    }
+
+    /// Return the literal of the XOR of `vars`, the variable `i` being the DIMACS variable `i + 1`,
+    /// encoding it if it wasn't yet. The XOR of no variable has no literal, its value being 0.
+    pub fn xor(&mut self, vars: Vec<usize>) -> Option<i64> {
+        let (first, rest) = vars.split_first()?;
+        if let Some(literal) = self.xors.get(&vars) {
+            return Some(*literal);
+        }
+        let mut literal = *first as i64 + 1;
+        for var in rest {
+            let (a, b, c) = (literal, *var as i64 + 1, self.new_var());
+            // c = a ^ b
+            self.clauses.extend(vec![vec![-a, -b, -c], vec![a, b, -c], vec![a, -b, c], vec![-a, b, c]]);
+            literal = c;
+        }
+        self.xors.insert(vars, literal);
+        Some(literal)
+    }
+
+    /// Write the `Cnf` in the DIMACS format to writer, after the comment lines `comments`.
+    pub fn write_dimacs<W: Write>(&self, writer: &mut W, comments: &[String]) -> io::Result<()> {
+        for comment in comments {
+            writeln!(writer, "c {}", comment)?;
+        }
+        writeln!(writer, "p cnf {} {}", self.nvars, self.clauses.len())?;
+        for clause in self.clauses.iter() {
+            for literal in clause {
+                write!(writer, "{} ", literal)?;
+            }
+            writeln!(writer, "0")?;
+        }
+        Ok(())
+    }
+
+    /// Return the variable of `parent` and `literal`, encoding it if needed.
+    fn and(&mut self, parent: i64, literal: Option<i64>) -> i64 {
+        match literal {
+            None => parent,
+            Some(literal) => {
+                let and = self.new_var();
+                self.clauses.push(vec![-and, parent]);
+                self.clauses.push(vec![-and, literal]);
+                self.clauses.push(vec![and, -parent, -literal]);
+                and
+            }
+        }
+    }
+}
+
+impl System {
+    /// Return the Tseitin encoding of the `System`, see the `dimacs` module documentation, and the
+    /// meaning of its variables.
+    pub fn to_cnf(&self) -> (Cnf, DimacsMapping) {
+        let mut cnf = Cnf::new(self.get_nvar());
+        let mut mapping = DimacsMapping { nvar: self.get_nvar(), ..Default::default() };
+        let mut ids: Vec<Id> = self.iter_bdds().map(|(id, _)| *id).collect();
+        ids.sort_unstable();
+        for id in ids {
+            let bdd = self.get_bdd(id).unwrap().borrow();
+            let levels: Vec<_> = bdd.iter_levels().collect();
+            // The edges taken to each node, as the variables of their parent and the value of the
+            // lhs of the parent.
+            let mut incoming: AHashMap<Id, Vec<(i64, Option<i64>)>> = AHashMap::default();
+            for (level_index, level) in levels.iter().enumerate() {
+                let xor = cnf.xor(level.iter_set_lhs().collect());
+                if let Some(literal) = xor {
+                    mapping.levels.insert((id, level_index), literal);
+                }
+                let mut nodes: Vec<_> = level.iter_nodes().collect();
+                nodes.sort_unstable_by_key(|(node_id, _)| **node_id);
+                for (node_id, node) in nodes {
+                    let var = cnf.new_var();
+                    mapping.nodes.insert((id, *node_id), var);
+                    let parents = incoming.remove(node_id).unwrap_or_default();
+                    if level_index == 0 {
+                        cnf.clauses.push(vec![var]);
+                    } else {
+                        // var is true iff one of the edges to the node is taken
+                        let edges: Vec<i64> = parents.into_iter()
+                            .map(|(parent, value)| cnf.and(parent, value))
+                            .collect();
+                        cnf.clauses.push(std::iter::once(-var).chain(edges.iter().copied()).collect());
+                        cnf.clauses.extend(edges.iter().map(|edge| vec![var, -edge]));
+                    }
+                    if level_index == levels.len() - 1 {
+                        cnf.clauses.push(vec![var]);
+                    }
+                    if let Some(e0) = node.get_e0() {
+                        incoming.entry(e0).or_default().push((var, xor.map(|literal| -literal)));
+                    }
+                    if let Some(e1) = node.get_e1() {
+                        // The lhs of no variable is always 0
+                        if xor.is_some() {
+                            incoming.entry(e1).or_default().push((var, xor));
+                        }
+                    }
+                }
+            }
+        }
+        for lin_eq in self.iter_lin_eqs() {
+            cnf.add_linear_equation(&lin_eq.get_lhs(), lin_eq.get_rhs());
+        }
+        (cnf, mapping)
+    }
+
+    /// Write the Tseitin encoding of the `System` (see `to_cnf`) in the DIMACS format to writer,
+    /// and return the meaning of its variables.
+    pub fn to_dimacs<W: Write>(&self, writer: &mut W) -> io::Result<DimacsMapping> {
+        let (cnf, mapping) = self.to_cnf();
+        let nbdds = self.iter_bdds().len();
+        let mut comments = vec![format!("system of {} variables and {} bdds", self.get_nvar(), nbdds)];
+        if self.get_nvar() > 0 {
+            let vars: Vec<String> = (1..=self.get_nvar()).map(|var| var.to_string()).collect();
+            comments.push(format!("ind {} 0", vars.join(" ")));
+        }
+        cnf.write_dimacs(writer, &comments)?;
+        Ok(mapping)
+    }
+}
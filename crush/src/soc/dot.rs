@@ -0,0 +1,330 @@
+//! The .dot language representation of bdds and systems, for visualization with GraphViz.
+//!
+//! The representation is returned as a `String` by `Bdd::to_dot` and `System::to_dot`, such that
+//! it can be built without touching the file system, e.g. in a browser. The `io` module writes it
+//! to files and pipes it to GraphViz.
+//!
+//! Each bdd of a system is drawn in its own cluster, with its levels from top to bottom. The levels
+//! of different bdds involving a same variable are linked by a dashed edge labelled with the shared
+//! variables, such that the dependencies between the bdds stand out. For each variable, the levels
+//! involving it are chained by increasing bdd id, so the number of these edges stays linear in the
+//! size of the system.
+
+use std::io::{self, Write};
+use std::ops::Range;
+
+use num_bigint::BigUint;
+
+use crate::{AHashMap, AHashSet};
+use crate::soc::{bdd::Bdd, system::System, Id};
+
+/// Options of the .dot output of a bdd, to annotate the graph with the accepted paths, i.e. the
+/// paths from the source to the sink, and to draw only a part of it. By default, the whole graph is
+/// drawn with the nodes and edges only.
+///
+/// Drawing a part of a bdd is the only way to inspect a bdd of hundreds of thousands of nodes, which
+/// GraphViz can't lay out. The path counts and weights are still the ones of the whole bdd.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DotOptions {
+    /// Label each node with the number of accepted paths going through it.
+    pub path_counts: bool,
+    /// Colour each edge by the share of the accepted paths going through it, from light yellow
+    /// (none) to dark red (all), such that the bottlenecks of the bdd stand out.
+    pub edge_weights: bool,
+    /// Only draw the levels in this range, e.g. `20..60`. The edges to the levels out of the range
+    /// are left out.
+    pub levels: Option<Range<usize>>,
+    /// Only draw the nodes reachable from this node, itself included.
+    pub from_node: Option<Id>,
+}
+
+/// Write .dot language representation of the given system into `writer`, see the module
+/// documentation.
+pub(crate) fn write_system_dot<W: Write>(system: &System, writer: &mut W) -> io::Result<()> {
+    use std::collections::BTreeMap;
+
+    /// A level of a bdd of the system, as the id of the bdd and the index of the level.
+    type LevelRef = (Id, usize);
+
+    let mut ids: Vec<Id> = system.iter_bdds().map(|(id, _)| *id).collect();
+    ids.sort_unstable();
+
+    writeln!(writer, "digraph \"SoC\" {{")?;
+    writeln!(writer, "center = true;")?;
+    writeln!(writer, "edge [dir = none];")?; // No arrowheads on the arrows
+
+    // For each variable, the (bdd, level) involving it
+    let mut involving: BTreeMap<usize, Vec<LevelRef>> = BTreeMap::new();
+    for id in ids.iter() {
+        let shard = system.get_bdd(*id).unwrap().borrow();
+        let sink_index = shard.get_sink_level_index();
+        writeln!(writer, "subgraph \"cluster_{}\" {{", **id)?;
+        writeln!(writer, "label = \"Shard {}\";", **id)?;
+
+        // The levels, as an invisible chain of plain text nodes on the left
+        writeln!(writer, "{{ node [shape = plaintext];")?;
+        writeln!(writer, "edge [style = invis];")?;
+        for (i, level) in shard.iter_levels().enumerate().take(sink_index) {
+            writeln!(writer, "\"{}.{}\" [label = \"{}. {}\"];", **id, i, i, lhs_label(level.iter_set_lhs()))?;
+            for var in level.iter_set_lhs() {
+                involving.entry(var).or_default().push((*id, i));
+            }
+        }
+        writeln!(writer, "\"{}.sink\" [style = invis];", **id)?;
+        for i in 0..sink_index {
+            write!(writer, "\"{}.{}\" -> ", **id, i)?;
+        }
+        writeln!(writer, "\"{}.sink\";\n}}", **id)?;
+
+        // The nodes, each level on the rank of its label
+        for (i, level) in shard.iter_levels().enumerate() {
+            if i == sink_index {
+                write!(writer, "{{ rank = same; \"{}.sink\"; ", **id)?;
+                for (node_id, _) in level.iter_nodes() {
+                    write!(writer, "\"{}\" [label = \"T\"; shape = box]; ", **node_id)?;
+                }
+            } else {
+                write!(writer, "{{ rank = same; \"{}.{}\"; ", **id, i)?;
+                for (node_id, _) in level.iter_nodes() {
+                    write!(writer, "\"{}\" [label = \"\"; shape = point; width = 0.06]; ", **node_id)?;
+                }
+            }
+            writeln!(writer, "}}")?;
+        }
+        for level in shard.iter_levels() {
+            for (node_id, node) in level.iter_nodes() {
+                if let Some(e0) = node.get_e0() {
+                    writeln!(writer, "\"{}\" -> \"{}\" [style = dashed];", **node_id, *e0)?;
+                }
+                if let Some(e1) = node.get_e1() {
+                    writeln!(writer, "\"{}\" -> \"{}\";", **node_id, *e1)?;
+                }
+            }
+        }
+        writeln!(writer, "}}")?; // Cluster done
+    }
+
+    // The dependencies between the bdds, the shared variables of each pair of levels linked
+    let mut dependencies: BTreeMap<(LevelRef, LevelRef), Vec<usize>> = BTreeMap::new();
+    for (var, levels) in involving.iter() {
+        for pair in levels.windows(2) {
+            if pair[0].0 != pair[1].0 {
+                dependencies.entry((pair[0], pair[1])).or_default().push(*var);
+            }
+        }
+    }
+    for (((bdd_a, level_a), (bdd_b, level_b)), vars) in dependencies.iter() {
+        let vars: Vec<String> = vars.iter().map(|var| format!("x{}", var)).collect();
+        writeln!(writer, "\"{}.{}\" -> \"{}.{}\" [style = dashed; constraint = false; color = gray; label = \"{}\"];",
+                 **bdd_a, level_a, **bdd_b, level_b, vars.join(", "))?;
+    }
+    writeln!(writer, "}}")
+}
+
+/// Return the label of a level from the variables of its lhs, e.g. "x1 + x2", "0" if none.
+pub(crate) fn lhs_label<I: Iterator<Item = usize>>(vars: I) -> String {
+    let vars: Vec<String> = vars.map(|var| format!("x{}", var)).collect();
+    if vars.is_empty() {
+        "0".to_string()
+    } else {
+        vars.join(" + ")
+    }
+}
+
+/// Number of accepted paths from the source to each node and from each node to the sink, counted
+/// by the `count` module of `Bdd`.
+pub(crate) struct PathCounts {
+    from_source: AHashMap<Id, BigUint>,
+    to_sink: AHashMap<Id, BigUint>,
+    /// Number of accepted paths of the bdd.
+    pub(crate) total: BigUint,
+}
+
+impl PathCounts {
+    pub(crate) fn new(shard: &Bdd) -> PathCounts {
+        let from_source = shard.paths_from_source();
+        let to_sink = shard.weighted_paths_to_sink(|_, _| BigUint::from(1u8));
+        let total = shard.iter_levels().next().unwrap().iter_nodes()
+            .map(|(id, _)| &to_sink[id])
+            .sum();
+        PathCounts { from_source, to_sink, total }
+    }
+
+    /// Return the number of accepted paths going through the node `id`.
+    pub(crate) fn through_node(&self, id: &Id) -> BigUint {
+        self.from_source.get(id).cloned().unwrap_or_default() * self.to_sink.get(id).cloned().unwrap_or_default()
+    }
+
+    /// Return the number of accepted paths going through the edge from `parent` to `child`.
+    pub(crate) fn through_edge(&self, parent: &Id, child: &Id) -> BigUint {
+        self.from_source.get(parent).cloned().unwrap_or_default() * self.to_sink.get(child).cloned().unwrap_or_default()
+    }
+
+    /// Return the colour, from 1 to 9, of the edge from `parent` to `child` in the `ylorrd9`
+    /// scheme of GraphViz, proportional to the share of the accepted paths going through it.
+    fn edge_colour(&self, parent: &Id, child: &Id) -> usize {
+        if self.total == BigUint::default() {
+            return 1;
+        }
+        let share = self.through_edge(parent, child) * 8u32 / &self.total;
+        1 + share.to_u32_digits().first().copied().unwrap_or(0) as usize
+    }
+}
+
+/// Write .dot language representation of the given shard into `writer`, annotated according to
+/// options.
+pub(crate) fn write_bdd_dot<W: Write>(shard: &Bdd, writer: &mut W, options: &DotOptions) -> io::Result<()> {
+    // Setup
+    let num_levels = shard.iter_levels().count();
+    let counts = if options.path_counts || options.edge_weights {
+        Some(PathCounts::new(shard))
+    } else {
+        None
+    };
+    let window = options.levels.clone().unwrap_or(0..num_levels);
+    let reachable = options.from_node.map(|from| {
+        let mut reachable: AHashSet<Id> = AHashSet::default();
+        reachable.insert(from);
+        for level in shard.iter_levels() {
+            for (id, node) in level.iter_nodes() {
+                if reachable.contains(id) {
+                    reachable.extend(node.get_e0().into_iter().chain(node.get_e1()));
+                }
+            }
+        }
+        reachable
+    });
+    let is_drawn = |level_index: usize, id: &Id| {
+        window.contains(&level_index) && reachable.as_ref().is_none_or(|reachable| reachable.contains(id))
+    };
+    let sink = shard.iter_levels().last().unwrap().iter_nodes().last().unwrap().0;
+    let sink_drawn = is_drawn(num_levels - 1, sink);
+
+    // Metadata:
+    writeln!(writer, "digraph \"DD\" {{")?; // I believe DD is just an ID.
+    writeln!(writer, "center = true;")?;
+    writeln!(writer, "edge [dir = none];")?; // No arrowheads on the arrows
+
+    // Writing the LHS of the graph
+    writeln!(writer, "{{ node [shape = plaintext];")?; // No "bubble" around the algebraic expression
+    writeln!(writer, "edge [style = invis];")?; // Draw no edges
+    writeln!(writer, "\"CONST NODES\" [style = invis];")?; // End node? Invisible
+
+    for (i,level) in shard.iter_levels().enumerate() {
+        if i == num_levels - 1 { // Skip terminal lvl
+            break;
+        }
+        if !window.contains(&i) {
+            continue;
+        }
+        write!(writer, "\"{}. ",i)?; // Line/row number
+        if level.iter_set_lhs().count() == 0 { // No variable is set
+            write!(writer, "0")?;
+        } else {
+            for (j, bit) in level.iter_set_lhs().enumerate() {
+                if j > 0 {
+                    write!(writer, " + ")?;
+                }
+                write!(writer, "x{}", bit)?;
+            }
+        }
+        write!(writer, "\" -> ")?;
+    }
+    writeln!(writer, "\"CONST NODES\";\n}}")?;
+
+    // Writing the RHS of the graph
+    for (i,level) in shard.iter_levels().enumerate() {
+        if i == num_levels - 1 { // Skip terminal lvl
+            break;
+        }
+        if !window.contains(&i) {
+            continue;
+        }
+        write!(writer, "{{ rank = same; ")?; // Tell GraphViz that these are on the same level
+        write!(writer, "\"{}. ", i)?; // Line/row/"rank" number
+
+        // I'm a bit unsure of the purpose of this if-else. I understand what it does, but not why.
+        // Theory: Links these to the rank above w/same "ID"? Printed dot file both support and object
+        // to this theory, and hard to find something in the GV doc.
+        if level.iter_set_lhs().count() == 0 { // No variable is set
+            write!(writer, "0")?;
+        } else {
+            for (j,bit) in level.iter_set_lhs().enumerate() {
+                if j > 0 {
+                    write!(writer, " + ")?;
+                }
+                write!(writer, "x{}", bit)?;
+            }
+        }
+        writeln!(writer, "\";")?;
+
+        // Add node to rank. (In GraphViz: level == rank)
+        for (id,_) in level.iter_nodes().filter(|(id, _)| is_drawn(i, id)) {
+            match counts.as_ref().filter(|_| options.path_counts) {
+                Some(counts) => {
+                    writeln!(writer, "\"{}\" [label = \"{}\"; shape = ellipse; fontsize = 10];",
+                             *id, counts.through_node(id))?;
+                }
+                // Remove the ID by setting label = "", and reducing drawing size by making the node shape to a point.
+                None => writeln!(writer, "\"{}\" [label = \"\"; shape = point; width = 0.06];", *id)?,
+            }
+        }
+        writeln!(writer, "}}")?; // Rank (/level) done
+    }
+
+    // Add terminal node, set node shape to box
+    if sink_drawn {
+        writeln!(writer, "{{ rank = same; \"CONST NODES\";")?; //
+        writeln!(writer, "{{ node [shape = box]; \"{}\";", **sink)?;
+        writeln!(writer, "}}")?;
+        writeln!(writer, "}}")?;
+    }
+
+    // Add edges between relevant nodes, including correct style
+    if options.edge_weights {
+        writeln!(writer, "edge [colorscheme = ylorrd9];")?;
+    }
+    let colour = |parent: &Id, child: &Id| {
+        counts.as_ref().filter(|_| options.edge_weights).map(|counts| counts.edge_colour(parent, child))
+    };
+    for (i,level) in shard.iter_levels().enumerate() {
+        for (id,node) in level.iter_nodes().filter(|(id, _)| is_drawn(i, id)) {
+            if let Some(e0) = node.get_e0().filter(|e0| is_drawn(i + 1, e0)) {
+                match colour(id, &e0) {
+                    Some(c) => writeln!(writer, "\"{}\" -> \"{}\" [style = dashed; color = {}];",*id,*e0,c)?,
+                    None => writeln!(writer, "\"{}\" -> \"{}\" [style = dashed];",*id,*e0)?,
+                }
+            }
+            if let Some(e1) = node.get_e1().filter(|e1| is_drawn(i + 1, e1)) {
+                match colour(id, &e1) {
+                    Some(c) => writeln!(writer, "\"{}\" -> \"{}\" [color = {}];",*id,*e1,c)?,
+                    None => writeln!(writer, "\"{}\" -> \"{}\";",*id,*e1)?,
+                }
+            }
+        }
+    }
+    // Label the terminal node as the True node
+    if sink_drawn {
+        writeln!(writer, "\"{}\" [label = \"T\"];", **sink)?;
+    }
+    writeln!(writer, "}}")
+}
+
+impl Bdd {
+    /// Return the .dot language representation of the `Bdd`, annotated according to options.
+    pub fn to_dot(&self, options: &DotOptions) -> String {
+        let mut dot = Vec::new();
+        write_bdd_dot(self, &mut dot, options).expect("Writing to a Vec doesn't fail");
+        String::from_utf8(dot).expect("The .dot output is UTF-8")
+    }
+}
+
+impl System {
+    /// Return the .dot language representation of the `System`, see the `dot` module
+    /// documentation.
+    pub fn to_dot(&self) -> String {
+        let mut dot = Vec::new();
+        write_system_dot(self, &mut dot).expect("Writing to a Vec doesn't fail");
+        String::from_utf8(dot).expect("The .dot output is UTF-8")
+    }
+}
@@ -0,0 +1,85 @@
+//! The error of the `soc` APIs reading, parsing, building and writing systems, telling which file,
+//! line or bdd failed.
+//!
+//! The streaming APIs built on `std::io` (e.g. `parse::SpecReader`) keep returning `io::Error`, the
+//! `SocError` being wrapped inside: `SocError::from` unwraps it back, and `io::Error::from` wraps
+//! a `SocError` with the kind `ErrorKind::InvalidData` (or the kind of the underlying `io::Error`).
+
+use core::fmt::{self, Display};
+use std::error;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::soc::Id;
+
+/// An error of the `soc` APIs, see the module documentation.
+#[derive(Debug)]
+pub enum SocError {
+    /// Reading or writing failed, on the file at `path` if known.
+    Io { path: Option<PathBuf>, error: io::Error },
+    /// The input is not in the .bdd format at the line `line` (from 1), of the file at `path` if
+    /// known.
+    Parse { path: Option<PathBuf>, line: usize, message: String },
+    /// The bdd `bdd` can't be part of the system.
+    Bdd { bdd: Id, message: String },
+}
+
+impl SocError {
+    /// Return a `SocError::Parse` at line `line` of an unknown file.
+    pub fn parse(line: usize, message: &str) -> SocError {
+        SocError::Parse { path: None, line, message: message.to_string() }
+    }
+
+    /// Set the file of the error to path, if it isn't known yet.
+    pub fn in_file(mut self, path: &Path) -> SocError {
+        match &mut self {
+            SocError::Io { path: file @ None, .. } | SocError::Parse { path: file @ None, .. } => {
+                *file = Some(path.to_path_buf())
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+impl Display for SocError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SocError::Io { path: Some(path), error } => write!(f, "{}: {}", path.display(), error),
+            SocError::Io { path: None, error } => write!(f, "{}", error),
+            SocError::Parse { path: Some(path), line, message } => {
+                write!(f, "{}:{}: {}", path.display(), line, message)
+            }
+            SocError::Parse { path: None, line, message } => write!(f, "line {}: {}", line, message),
+            SocError::Bdd { bdd, message } => write!(f, "bdd {}: {}", bdd, message),
+        }
+    }
+}
+
+impl error::Error for SocError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SocError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SocError {
+    fn from(error: io::Error) -> SocError {
+        if error.get_ref().is_some_and(|inner| inner.is::<SocError>()) {
+            return *error.into_inner().unwrap().downcast::<SocError>().unwrap();
+        }
+        SocError::Io { path: None, error }
+    }
+}
+
+impl From<SocError> for io::Error {
+    fn from(error: SocError) -> io::Error {
+        let kind = match &error {
+            SocError::Io { error, .. } => error.kind(),
+            _ => ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, error)
+    }
+}
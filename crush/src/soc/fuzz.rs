@@ -0,0 +1,422 @@
+//! Fuzzing and differential-testing entry points for the core operations on a `System`.
+//!
+//! `check_operations` decodes a small random `System` and a sequence of operations (restrict,
+//! swap, add, join, reduce) from raw bytes, applies them and after each operation checks:
+//! - the structural invariants of every `Bdd` (see `check_invariants`),
+//! - that the set of solutions of the `System`, computed by brute force over all the
+//!   assignments of its variables, is the one expected from the operations applied so far.
+//!
+//! Any violation panics, which is what fuzzers look for. The systems are kept small (at most 8
+//! variables) so that the brute force stays cheap. The entry points are used by the targets of
+//! the `fuzz` directory (cargo-fuzz) and by the tests below with pseudo-random inputs.
+//!
+//! Only available with the `fuzzing` feature.
+
+use vob::Vob;
+
+use crate::soc::{
+    bdd::Bdd,
+    system::System,
+    utils::{build_system_from_spec, BddSpec, LevelSpec, NodeSpec, SystemSpec},
+    Id,
+};
+
+/// Maximum number of variables of the systems built from bytes.
+pub const MAX_NVAR: usize = 8;
+
+/// Maximum number of levels (sink excluded) of the bdds built from bytes.
+pub const MAX_LEVELS: usize = 4;
+
+/// Maximum number of operations decoded from the bytes.
+pub const MAX_OPERATIONS: usize = 64;
+
+/// Reads the bytes of a fuzzing input, yielding zeros once they are exhausted so that every
+/// input decodes to something.
+struct ByteSource<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteSource<'a> {
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn byte(&mut self) -> u8 {
+        match self.data.split_first() {
+            Some((b, rest)) => {
+                self.data = rest;
+                *b
+            }
+            None => 0,
+        }
+    }
+
+    /// Return a value in `0..bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        self.byte() as usize % bound
+    }
+
+    fn bit(&mut self) -> bool {
+        self.byte() & 1 == 1
+    }
+
+    /// Return a `Vob` of size `nvar` with at least one bit set.
+    fn lhs(&mut self, nvar: usize) -> Vob {
+        let mut lhs = Vob::from_elem(nvar, false);
+        let mut bits = self.byte() as usize | (self.byte() as usize) << 8;
+        if bits & ((1 << nvar) - 1) == 0 {
+            bits = 1 << self.below(nvar);
+        }
+        for i in 0..nvar {
+            lhs.set(i, bits >> i & 1 == 1);
+        }
+        lhs
+    }
+}
+
+/// An operation applied to the `System` under test.
+#[derive(Debug, Clone)]
+pub enum Operation {
+    /// Fix a linear combination of the variables (`System::fix`).
+    Restrict(Vec<usize>, bool),
+    /// Swap two adjacent levels of a bdd (`System::swap`).
+    Swap(Id, usize),
+    /// Add a level to another level below it (`System::add`).
+    Add(Id, usize, usize),
+    /// Join two bdds (`System::join_bdds`).
+    Join(Id, Id),
+    /// Absorb the linear equations of a bdd into the `LinBank` (`System::scan_absorb_lin_eqs`).
+    Reduce(Id),
+}
+
+/// Build a small random `System` from `data`, with at least one solution.
+///
+/// Every bdd is a complete tree over up to `MAX_LEVELS` random non-zero lhs, whose leaves are
+/// connected to the sink according to random bits. A random assignment is drawn first and the
+/// leaves it reaches are always connected, so the system always has that solution.
+pub fn system_from_bytes(data: &[u8]) -> System {
+    build_system(&mut ByteSource { data })
+}
+
+fn build_system(src: &mut ByteSource) -> System {
+    let nvar = 1 + src.below(MAX_NVAR);
+    let n_bdds = 1 + src.below(3);
+    let mut solution = Vob::from_elem(nvar, false);
+    for i in 0..nvar {
+        solution.set(i, src.bit());
+    }
+    let bdds = (0..n_bdds)
+        .map(|bdd_index| {
+            let n_levels = 1 + src.below(MAX_LEVELS);
+            let lhs: Vec<Vob> = (0..n_levels).map(|_| src.lhs(nvar)).collect();
+            // Index of the leaf edge reached by `solution`
+            let forced = lhs.iter().fold(0, |acc, lhs| {
+                let value = lhs
+                    .iter_set_bits(..)
+                    .fold(false, |acc, var| acc ^ solution[var]);
+                acc << 1 | value as usize
+            });
+            let sink = 1 << n_levels;
+            let mut levels = Vec::with_capacity(n_levels + 1);
+            for (depth, lhs) in lhs.iter().enumerate() {
+                let first = 1 << depth;
+                let nodes = (0..first)
+                    .map(|p| {
+                        let (e0, e1) = if depth + 1 < n_levels {
+                            (2 * first + 2 * p, 2 * first + 2 * p + 1)
+                        } else {
+                            let mut leaf = |edge: usize| {
+                                if 2 * p + edge == forced || src.bit() {
+                                    sink
+                                } else {
+                                    0
+                                }
+                            };
+                            (leaf(0), leaf(1))
+                        };
+                        NodeSpec::new(Id::new(first + p), Id::new(e0), Id::new(e1))
+                    })
+                    .collect();
+                let vars = lhs.iter_set_bits(..).map(|var| var as i64).collect();
+                levels.push(LevelSpec::new(vars, nodes));
+            }
+            levels.push(LevelSpec::new(
+                vec![],
+                vec![NodeSpec::new(Id::new(sink), Id::new(0), Id::new(0))],
+            ));
+            BddSpec::new(Id::new(bdd_index), levels)
+        })
+        .collect();
+    let system = build_system_from_spec(SystemSpec::new(nvar, bdds))
+        .expect("the lhs are drawn over the nvar variables");
+    let ids: Vec<Id> = system.iter_bdds().map(|(id, _)| *id).collect();
+    for id in ids {
+        let mut bdd = system.get_bdd(id).unwrap().borrow_mut();
+        let last = bdd.get_sink_level_index() - 1;
+        bdd.remove_all_dead_ends_start(last);
+        if last > 0 {
+            bdd.remove_orphans_start(1);
+        }
+        bdd.merge_equals_node_start(last);
+    }
+    system
+}
+
+/// Decode the next operation to apply to `system` from `src`. Return `None` if the operation
+/// decoded is not applicable to the current `System`.
+fn next_operation(src: &mut ByteSource, system: &System) -> Option<Operation> {
+    let mut ids: Vec<Id> = system.iter_bdds().map(|(id, _)| *id).collect();
+    ids.sort();
+    if ids.is_empty() {
+        return None;
+    }
+    let id = ids[src.below(ids.len())];
+    let sink_level_index = system.get_bdd(id).unwrap().borrow().get_sink_level_index();
+    match src.below(5) {
+        0 => {
+            let lhs = src.lhs(system.get_nvar()).iter_set_bits(..).collect();
+            Some(Operation::Restrict(lhs, src.bit()))
+        }
+        1 if sink_level_index >= 2 => Some(Operation::Swap(id, src.below(sink_level_index - 1))),
+        2 if sink_level_index >= 2 => {
+            let above = src.below(sink_level_index - 1);
+            let below = above + 1 + src.below(sink_level_index - above - 1);
+            Some(Operation::Add(id, above, below))
+        }
+        3 if ids.len() >= 2 => {
+            let other = ids[src.below(ids.len())];
+            if other == id {
+                None
+            } else {
+                Some(Operation::Join(id, other))
+            }
+        }
+        4 => Some(Operation::Reduce(id)),
+        _ => None,
+    }
+}
+
+/// Return, for every assignment of the `nvar` variables (the assignment `a` sets the variable
+/// `i` to the bit `i` of `a`), whether it is a solution of `system`.
+pub fn brute_force_solutions(system: &System) -> Vec<bool> {
+    let nvar = system.get_nvar();
+    (0..1usize << nvar)
+        .map(|a| {
+            let mut assignment = Vob::from_elem(nvar, false);
+            for i in 0..nvar {
+                assignment.set(i, a >> i & 1 == 1);
+            }
+            system.is_solution(&assignment)
+        })
+        .collect()
+}
+
+/// Check the structural invariants of `bdd`, panicking with a description of the first one
+/// violated:
+/// - the sink level holds a single node without edges,
+/// - the source level holds a single node,
+/// - every other node has at least one edge, and every edge points to a node of the level
+///   just below,
+/// - every node below the source is pointed to by a node of the level just above.
+pub fn check_invariants(bdd: &Bdd) {
+    let levels: Vec<_> = bdd.iter_levels().collect();
+    let sink = levels.last().expect("bdd without any level");
+    assert_eq!(
+        sink.get_nodes_len(),
+        1,
+        "sink level of bdd {} has several nodes",
+        *bdd.get_id()
+    );
+    let (_, sink_node) = sink.iter_nodes().next().unwrap();
+    assert!(
+        sink_node.get_e0().is_none() && sink_node.get_e1().is_none(),
+        "sink of bdd {} has outgoing edges",
+        *bdd.get_id()
+    );
+    assert_eq!(
+        levels[0].get_nodes_len(),
+        1,
+        "source level of bdd {} has several nodes",
+        *bdd.get_id()
+    );
+    for (depth, pair) in levels.windows(2).enumerate() {
+        let (above, below) = (pair[0], pair[1]);
+        let mut pointed = Vec::new();
+        for (id, node) in above.iter_nodes() {
+            let edges: Vec<Id> = node.get_e0().into_iter().chain(node.get_e1()).collect();
+            assert!(
+                !edges.is_empty(),
+                "node {} at level {} of bdd {} is a dead end",
+                **id,
+                depth,
+                *bdd.get_id()
+            );
+            for edge in edges {
+                assert!(
+                    below.get_node(&edge).is_some(),
+                    "node {} at level {} of bdd {} points to {} which is not in the level below",
+                    **id,
+                    depth,
+                    *bdd.get_id(),
+                    *edge
+                );
+                pointed.push(edge);
+            }
+        }
+        for (id, _) in below.iter_nodes() {
+            assert!(
+                pointed.contains(id),
+                "node {} at level {} of bdd {} is an orphan",
+                **id,
+                depth + 1,
+                *bdd.get_id()
+            );
+        }
+    }
+}
+
+/// Check the invariants of every bdd of `system` and that its solutions are `expected`.
+fn check_system(system: &System, expected: &[bool], operation: Option<&Operation>) {
+    for (_, bdd) in system.iter_bdds() {
+        check_invariants(&bdd.borrow());
+    }
+    assert!(
+        brute_force_solutions(system) == expected,
+        "the solutions of the system changed after {:?}",
+        operation
+    );
+}
+
+/// Fuzzing entry point: build a `System` from `data`, then apply the operations decoded from the
+/// rest of `data`, checking the invariants and the solutions after each of them.
+///
+/// Swap, add, join and reduce must keep the solutions unchanged, a restriction must keep exactly
+/// the solutions satisfying the equation fixed. Restrictions leaving no solution are skipped, as
+/// the operations on a `System` without solutions are allowed to panic.
+pub fn check_operations(data: &[u8]) {
+    let mut src = ByteSource { data };
+    let mut system = build_system(&mut src);
+    let mut expected = brute_force_solutions(&system);
+    check_system(&system, &expected, None);
+    for _ in 0..MAX_OPERATIONS {
+        if src.is_empty() {
+            return;
+        }
+        let operation = match next_operation(&mut src, &system) {
+            Some(operation) => operation,
+            None => continue,
+        };
+        match &operation {
+            Operation::Restrict(lhs, rhs) => {
+                let restricted: Vec<bool> = expected
+                    .iter()
+                    .enumerate()
+                    .map(|(a, &sol)| {
+                        sol && lhs.iter().fold(false, |acc, var| acc ^ (a >> var & 1 == 1)) == *rhs
+                    })
+                    .collect();
+                if !restricted.contains(&true) {
+                    continue;
+                }
+                // An equation dependent on the LinBank is refused and leaves the system unchanged
+                if system.fix(lhs.clone(), *rhs).is_ok() {
+                    expected = restricted;
+                }
+            }
+            Operation::Swap(id, above) => system.swap(*id, *above, above + 1).unwrap(),
+            Operation::Add(id, above, below) => system.add(*id, *above, *below).unwrap(),
+            Operation::Join(id, other) => {
+                system.join_bdds(*id, *other).unwrap();
+            }
+            Operation::Reduce(id) => {
+                system.scan_absorb_lin_eqs(*id).unwrap();
+            }
+        }
+        check_system(&system, &expected, Some(&operation));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Pseudo-random bytes from a xorshift generator, to run the entry points without a fuzzer.
+    fn pseudo_random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 32) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn built_systems_have_a_solution() {
+        for seed in 0..200 {
+            let system = system_from_bytes(&pseudo_random_bytes(seed, 64));
+            assert!(brute_force_solutions(&system).contains(&true));
+        }
+    }
+
+    #[test]
+    fn random_operation_sequences() {
+        for seed in 0..500 {
+            check_operations(&pseudo_random_bytes(seed, 256));
+        }
+    }
+
+    #[test]
+    fn empty_input() {
+        check_operations(&[]);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_operations_match_sequential() {
+        for seed in 0..100 {
+            let system = system_from_bytes(&pseudo_random_bytes(seed, 64));
+            for (_, bdd) in system.iter_bdds() {
+                let bdd = bdd.borrow();
+                let nlevels = bdd.get_levels_size() - 1;
+                for above in 0..nlevels {
+                    for below in above + 1..nlevels {
+                        let (mut sequential, mut parallel) = (bdd.clone(), bdd.clone());
+                        if below == above + 1 {
+                            sequential.swap(above, below);
+                            parallel.par_swap(above, below);
+                            check_invariants(&parallel);
+                            assert_eq!(sequential.fingerprint(), parallel.fingerprint());
+                        }
+                        let (mut sequential, mut parallel) = (bdd.clone(), bdd.clone());
+                        sequential.add(above, below);
+                        parallel.par_add(above, below);
+                        check_invariants(&parallel);
+                        assert_eq!(sequential.fingerprint(), parallel.fingerprint());
+                        // Reducing after removing nodes, which leaves dead ends behind. The ids
+                        // of the new nodes may differ, so both start from the sequential result
+                        let mut removed: Vec<Id> = sequential.iter_levels().nth(below).unwrap()
+                            .iter_nodes().map(|(id, _)| *id).collect();
+                        removed.sort();
+                        for id in removed.into_iter().step_by(2) {
+                            sequential.remove_node(id).unwrap();
+                        }
+                        let mut parallel = sequential.clone();
+                        assert_eq!(sequential.reduce(), parallel.par_reduce());
+                        assert_eq!(sequential.fingerprint(), parallel.fingerprint());
+                        let ids = |bdd: &Bdd| -> Vec<Vec<Id>> {
+                            bdd.iter_levels().map(|level| {
+                                let mut ids: Vec<Id> = level.iter_nodes().map(|(id, _)| *id).collect();
+                                ids.sort();
+                                ids
+                            }).collect()
+                        };
+                        assert_eq!(ids(&sequential), ids(&parallel));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,347 @@
+//! Module providing the file and process I/O around systems of bdds: parsing systems from .bdd
+//! files, printing systems to .bdd files, saving and restoring binary snapshots of systems (see the
+//! `binary` module), bdds and systems to .dot format for visualization (see the `dot` module) and
+//! the transfer matrices of bdds to Matrix Market files.
+//!
+//! The rest of `soc` only works on in-memory structures, this is the only module touching the
+//! file system or spawning processes.
+//!
+//! Only available with the `io` feature. The .dot output and drawing with GraphViz also require the
+//! `draw` feature. Files are parsed with `parse::SpecReader`, one line at a time.
+//!
+//! Every function returns a `SocError` telling the path of the file which can't be read or
+//! written, none of them panics on a failed I/O.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "draw")]
+use std::process::Child;
+
+#[cfg(feature = "draw")]
+pub use crate::soc::dot::DotOptions;
+#[cfg(feature = "draw")]
+use crate::soc::dot::{write_bdd_dot, write_system_dot};
+use crate::soc::{
+    anf::read_anf,
+    bdd::Bdd,
+    dimacs::DimacsMapping,
+    error::SocError,
+    parse::{build_system_from_reader, SpecReader},
+    system::System,
+    utils::SystemSpec};
+
+/// Return a SystemSpec from the parsing of a .bdd file using the correct format
+///
+/// Will return a `SocError` telling the path, and the line if the file is malformed.
+pub fn parse_system_spec_from_file(path: &PathBuf) -> Result<SystemSpec, SocError> {
+    File::open(path)
+        .and_then(|file| SpecReader::new(BufReader::new(file)))
+        .and_then(SpecReader::read_system_spec)
+        .map_err(|e| SocError::from(e).in_file(path))
+}
+
+/// Return a SystemSpec from the parsing of a file in the ANF format, see the `anf` module.
+///
+/// Will return a `SocError` telling the path, and the line if the file is malformed.
+pub fn parse_system_spec_from_anf_file(path: &PathBuf) -> Result<SystemSpec, SocError> {
+    File::open(path)
+        .map_err(SocError::from)
+        .and_then(|file| read_anf(BufReader::new(file)))
+        .map_err(|e| e.in_file(path))
+}
+
+/// Build the system of a .bdd file, each bdd being built as soon as it is parsed, such that the
+/// whole specification is never held in memory (see `parse::build_system_from_reader`).
+///
+/// Will return a `SocError` telling the path, and the line if the file is malformed.
+pub fn build_system_from_file(path: &Path) -> Result<System, SocError> {
+    File::open(path)
+        .and_then(|file| build_system_from_reader(BufReader::new(file)))
+        .map_err(|e| SocError::from(e).in_file(path))
+}
+
+/// Create the file at path and write it with `write`, returning a `SocError` telling the path if
+/// it can't be written.
+fn write_file<F>(path: &Path, write: F) -> Result<(), SocError>
+    where F: FnOnce(&mut BufWriter<File>) -> io::Result<()>
+{
+    File::create(path)
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            write(&mut writer)?;
+            writer.flush()
+        })
+        .map_err(|e| SocError::from(e).in_file(path))
+}
+
+/// Write `.dot` language representation of the given bdd to a file at path
+///
+/// Will return a `SocError` telling the path if the file can't be written.
+#[cfg(feature = "draw")]
+pub fn print_bdd_to_dot_format(bdd: &Bdd, path: &Path) -> Result<(), SocError> {
+    print_bdd_to_dot_format_with_options(bdd, path, &DotOptions::default())
+}
+
+/// Write `.dot` language representation of the given bdd to a file at path, annotated according to
+/// options.
+///
+/// Will return a `SocError` telling the path if the file can't be written.
+#[cfg(feature = "draw")]
+pub fn print_bdd_to_dot_format_with_options(bdd: &Bdd, path: &Path, options: &DotOptions) -> Result<(), SocError> {
+    write_file(path, |writer| write_bdd_dot(bdd, writer, options))
+}
+
+/// Write `.dot` language representation of the given system to a file at path, see the `dot`
+/// module.
+///
+/// Will return a `SocError` telling the path if the file can't be written.
+#[cfg(feature = "draw")]
+pub fn print_system_to_dot_format(system: &System, path: &Path) -> Result<(), SocError> {
+    write_file(path, |writer| write_system_dot(system, writer))
+}
+
+/// Write .bdd representation of a bdd to a writer
+fn print_bdd_to_file_format<W: Write>(bdd: &Bdd, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "{} {}",*bdd.get_id(),bdd.iter_levels().count())?;
+    for level in bdd.iter_levels() {
+        for (i,bit) in level.iter_set_lhs().enumerate(){
+            if i != 0 {
+                write!(writer,"+")?;
+            }
+            write!(writer,"{}",bit)?;
+        }
+        write!(writer,":")?;
+        for (id,node) in level.iter_nodes() {
+            let e0 = match node.get_e0(){
+                Some(e0) => *e0,
+                None => 0,
+            };
+            let e1 = match node.get_e1(){
+                Some(e1) => *e1,
+                None => 0,
+            };
+            write!(writer,"({};{},{})",*id,e0,e1)?;
+        }
+        writeln!(writer,"|")?;
+    }
+    writeln!(writer,"---")
+}
+
+/// Write .bdd representation of a system to a file at path
+///
+/// Will return a `SocError` telling the path if the file can't be written.
+pub fn print_system_to_file(system: &System, path: &PathBuf) -> Result<(), SocError> {
+    write_system(system, path).map_err(|e| SocError::from(e).in_file(path))
+}
+
+fn write_system(system: &System, path: &PathBuf) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer,"{} {}",system.get_nvar(),system.iter_bdds().len())?;
+    let mut ids = Vec::new();
+    for bdd in system.iter_bdds() {
+        ids.push(bdd.0);
+    }
+    ids.sort();
+    for id in ids {
+        print_bdd_to_file_format(&system.get_bdd(*id).unwrap().borrow(), &mut writer)?;
+    }
+    writer.flush()
+}
+
+/// Write a checkpoint of a system being solved to a file at path, in the .bdd format.
+///
+/// Unlike `print_system_to_file`, the linear equations already absorbed in the `LinBank` are kept:
+/// each of them is written as a bdd with a single level, following the bdds of the system. Solving
+/// the system parsed back from the file therefore gives the same solutions as solving the system
+/// itself, the single level bdds being absorbed again as soon as the solving starts.
+///
+/// Will return a `SocError` telling the path if the file can't be written.
+pub fn print_checkpoint_to_file(system: &System, path: &PathBuf) -> Result<(), SocError> {
+    write_checkpoint(system, path).map_err(|e| SocError::from(e).in_file(path))
+}
+
+fn write_checkpoint(system: &System, path: &PathBuf) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let n_bdds = system.iter_bdds().len() + system.get_lin_bank_size();
+    writeln!(writer, "{} {}", system.get_nvar(), n_bdds)?;
+    let mut ids: Vec<_> = system.iter_bdds().map(|bdd| *bdd.0).collect();
+    ids.sort();
+    for id in ids.iter() {
+        print_bdd_to_file_format(&system.get_bdd(*id).unwrap().borrow(), &mut writer)?;
+    }
+    let next_id = ids.last().map_or(0, |id| **id + 1);
+    for (i, lin_eq) in system.iter_lin_eqs().enumerate() {
+        writeln!(writer, "{} 2", next_id + i)?;
+        for (j, bit) in lin_eq.get_lhs().iter_set_bits(..).enumerate() {
+            if j != 0 {
+                write!(writer, "+")?;
+            }
+            write!(writer, "{}", bit)?;
+        }
+        // Node 1 only has the edge of the rhs, pointing to the sink node 2.
+        let (e0, e1) = if lin_eq.get_rhs() { (0, 2) } else { (2, 0) };
+        writeln!(writer, ":(1;{},{})|", e0, e1)?;
+        writeln!(writer, ":(2;0,0)|")?;
+        writeln!(writer, "---")?;
+    }
+    writer.flush()
+}
+
+/// Write the CNF of a system to a file at path in the DIMACS format (see `System::to_dimacs`), and
+/// its variable mapping (see `DimacsMapping::write`) next to it, with the extension `map`.
+///
+/// Will return a `SocError` telling the path if a file can't be written.
+pub fn print_system_to_dimacs_file(system: &System, path: &PathBuf) -> Result<DimacsMapping, SocError> {
+    let mut writer = BufWriter::new(File::create(path).map_err(|e| SocError::from(e).in_file(path))?);
+    let mapping = system.to_dimacs(&mut writer)
+        .and_then(|mapping| writer.flush().map(|_| mapping))
+        .map_err(|e| SocError::from(e).in_file(path))?;
+    let map_path = path.with_extension("map");
+    File::create(&map_path)
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            mapping.write(&mut writer)?;
+            writer.flush()
+        })
+        .map_err(|e| SocError::from(e).in_file(&map_path))?;
+    Ok(mapping)
+}
+
+/// Save a binary snapshot of `system` to a file at path, see `System::serialize_binary`.
+///
+/// Will return a `SocError` telling the path if the file can't be written.
+pub fn save_snapshot_to_file(system: &System, path: &Path) -> Result<(), SocError> {
+    write_file(path, |writer| system.serialize_binary(writer))
+}
+
+/// Restore the system of a binary snapshot saved at path, see `System::deserialize_binary`.
+///
+/// Will return a `SocError` telling the path if the file can't be read or isn't a snapshot.
+pub fn load_snapshot_from_file(path: &Path) -> Result<System, SocError> {
+    File::open(path)
+        .and_then(|file| System::deserialize_binary(&mut BufReader::new(file)))
+        .map_err(|e| SocError::from(e).in_file(path))
+}
+
+/// Write the transfer matrices of each level of `bdd` to the next one (see
+/// `Bdd::transfer_matrices`) in the directory at path, creating it if needed.
+///
+/// Each matrix is written to its own file in the Matrix Market coordinate format, which most
+/// numerical tools read (e.g. `scipy.io.mmread`): `level_{i}_e0.mtx` and `level_{i}_e1.mtx` for the
+/// 0-edges and 1-edges from the level `i`. The indices are 1-based, as required by the format.
+///
+/// Will return a `SocError` telling the path of the directory or file which can't be written.
+pub fn print_transfer_matrices_to_dir(bdd: &Bdd, path: &Path) -> Result<(), SocError> {
+    fs::create_dir_all(path).map_err(|e| SocError::from(e).in_file(path))?;
+    for (level_index, matrices) in bdd.transfer_matrices().iter().enumerate() {
+        for (edge, entries) in [(0, &matrices.e0), (1, &matrices.e1)] {
+            write_file(&path.join(format!("level_{}_e{}.mtx", level_index, edge)), |writer| {
+                writeln!(writer, "%%MatrixMarket matrix coordinate pattern general")?;
+                writeln!(writer, "% bdd {}, {}-edges from level {} to level {}", bdd.get_id(), edge, level_index, level_index + 1)?;
+                writeln!(writer, "{} {} {}", matrices.rows, matrices.columns, entries.len())?;
+                for (row, column) in entries.iter() {
+                    writeln!(writer, "{} {}", row + 1, column + 1)?;
+                }
+                Ok(())
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Output formats GraphViz can draw a shard to, see `draw_shard`.
+#[cfg(feature = "draw")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Pdf,
+    /// Vector format, viewable in a browser and embeddable in notebooks and web pages.
+    Svg,
+    /// Raster format, small shards only as the size of the image grows with the shard.
+    Png,
+}
+
+#[cfg(feature = "draw")]
+impl OutputFormat {
+    /// Return the extension of the files of this format, which is also the name GraphViz knows
+    /// it by.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Pdf => "pdf",
+            OutputFormat::Svg => "svg",
+            OutputFormat::Png => "png",
+        }
+    }
+}
+
+/// Draw a graph representation of the Shard, using GraphViz.
+/// The output format is PDF, see `draw_shard`.
+#[cfg(feature = "draw")]
+pub fn draw_shard_as_pdf(shard: &Bdd, path: &Path) -> Result<Child, SocError> {
+    draw_shard(shard, path, OutputFormat::Pdf)
+}
+
+/// Draw a graph representation of the Shard in the given format, using GraphViz. The extension of
+/// path is replaced by the one of the format.
+///
+/// It is possible to use another function to instead output the dot-file of the shard. This allows
+/// the user to draw using GraphViz as desired. This function is intended as a easy-to-use way
+/// of generating snapshots of state. However, be mindful that GraphViz may use quite some time to
+/// finish drawing the shard, even after this function returns the handle to the GraphViz process.
+/// It is therefore *highly* recommended to always  use`.wait` on the handle to ensure that the
+/// drawing process is complete, before exiting the main thread!
+/// By returning the child handle, the caller is now free to decide when to wait for GraphViz to
+/// finish drawing.
+/// ---
+/// **NOTE:** Requires that `GraphViz` is installed! Returns a `SocError` if its `dot` command
+/// can't be run, or the shard can't be piped to it.
+/// Tested on a Windows with Graphviz 3.0.0
+///
+/// **WARNING!** The resulting output file may be very large!
+/// **WARNING** Failing to wait on the child process may lead to the failure of drawing the shard
+/// to file.
+/// **NOTE 1:** "Large" shards will take time to write to file. Patience is advised.
+/// **NOTE 2:** When opening the pdf based on a "large" shard, it may initially appear empty.
+/// When this is the case, it may be because it takes some time to load, or that you are viewing an
+/// empty part of the drawing. Scrolling or zooming in/out may help.
+/// ("Large" is hard to quantify, but my test file is only slightly more than 2mb large, yet took
+/// many minutes for GraphViz to write to file. (Output size is about 6mb, GraphViz spent about
+/// 30 min to draw...)).
+/// **NOTE 3:** SVG is usually the lighter option for large shards, as viewers render it lazily. A
+/// PNG of a large shard may exceed the maximum image size of GraphViz, which then scales it down.
+#[cfg(feature = "draw")]
+pub fn draw_shard(shard: &Bdd, path: &Path, format: OutputFormat) -> Result<Child, SocError> {
+    draw_shard_with_options(shard, path, format, &DotOptions::default())
+}
+
+/// Same as `draw_shard`, with the graph annotated according to options.
+#[cfg(feature = "draw")]
+pub fn draw_shard_with_options(shard: &Bdd, path: &Path, format: OutputFormat, options: &DotOptions)
+                               -> Result<Child, SocError> {
+    use std::ffi::OsString;
+    use std::process::{Command, Stdio};
+
+    let path = path.with_extension(format.extension());
+    let mut out_path = OsString::from("-o");
+    out_path.push(path.as_os_str());
+
+    let mut dot = Command::new("dot")
+        .arg(format!("-T{}", format.extension()))
+        .arg(out_path)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| SocError::from(io::Error::new(e.kind(), format!(
+            "Failed to run the dot command of GraphViz to draw the shard to {}: {}",
+            format.extension().to_uppercase(), e))))?;
+
+    if let Some(child_in) = dot.stdin.take() {
+        let mut writer = BufWriter::new(child_in);
+        write_bdd_dot(shard, &mut writer, options)
+            .and_then(|_| writer.flush())
+            .map_err(|e| SocError::from(e).in_file(&path))?;
+        // Child stdin is dropped, closing the child stdin's underlying file handle. This will
+        // essentially give an "EOF" to GraphViz, making it no longer wait on user input and thus
+        // start processing/drawing the given data.
+    }
+    Ok(dot)
+}
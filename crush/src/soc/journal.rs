@@ -0,0 +1,129 @@
+//! Append-only journal of the operations applied to a `System` while solving it, used to recover
+//! from a crash.
+//!
+//! Every entry holds a sequence number, the fingerprint of the `System` right after the operation
+//! (see `System::fingerprint`) and a description of the operation, one entry per line:
+//!
+//! ```text
+//! seq fingerprint(16 hex digits) operation
+//! ```
+//!
+//! Checkpoints written with `Journal::checkpoint` are recorded as entries too. As a checkpoint
+//! parsed back has the same fingerprint as the `System` it was written from, `resume_point`
+//! tells after a crash which entry the last checkpoint reflects: the operations up to it don't
+//! have to be done again, and the journal can be continued from there with `Journal::append`.
+//!
+//! Only available with the `io` feature.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use crate::soc::{io::print_checkpoint_to_file, system::System};
+
+/// An entry of the journal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub seq: usize,
+    pub fingerprint: u64,
+    pub operation: String,
+}
+
+impl JournalEntry {
+    /// Parse an entry from a line of the journal, return `None` if the line is malformed.
+    fn parse(line: &str) -> Option<JournalEntry> {
+        let mut parts = line.splitn(3, ' ');
+        let seq = parts.next()?.parse().ok()?;
+        let fingerprint = u64::from_str_radix(parts.next()?, 16).ok()?;
+        let operation = parts.next()?.to_string();
+        Some(JournalEntry { seq, fingerprint, operation })
+    }
+}
+
+impl fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:016x} {}", self.seq, self.fingerprint, self.operation)
+    }
+}
+
+/// An open journal, each recorded entry is written and synced to the file before returning.
+pub struct Journal {
+    file: File,
+    next_seq: usize,
+}
+
+impl Journal {
+    /// Create a new journal at path, replacing any existing file.
+    pub fn create(path: &PathBuf) -> io::Result<Journal> {
+        Ok(Journal { file: File::create(path)?, next_seq: 0 })
+    }
+
+    /// Open the journal at path to append new entries, numbered from `next_seq`. Typically
+    /// `next_seq` is the one following the entry returned by `resume_point`.
+    pub fn append(path: &PathBuf, next_seq: usize) -> io::Result<Journal> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Journal { file, next_seq })
+    }
+
+    /// Return the sequence number of the next entry.
+    pub fn next_seq(&self) -> usize {
+        self.next_seq
+    }
+
+    /// Record that `operation` was applied, `system` being the result.
+    pub fn record(&mut self, operation: &str, system: &System) -> io::Result<JournalEntry> {
+        let entry = JournalEntry {
+            seq: self.next_seq,
+            fingerprint: system.fingerprint(),
+            operation: operation.to_string(),
+        };
+        writeln!(self.file, "{}", entry)?;
+        self.file.sync_data()?;
+        self.next_seq += 1;
+        Ok(entry)
+    }
+
+    /// Write a checkpoint of `system` at path (see `io::print_checkpoint_to_file`) and record it.
+    ///
+    /// The checkpoint is written next to path first and then renamed, such that a crash while
+    /// writing it leaves the previous checkpoint untouched.
+    pub fn checkpoint(&mut self, system: &System, path: &PathBuf) -> io::Result<JournalEntry> {
+        let mut tmp_path = path.clone();
+        tmp_path.set_extension("tmp");
+        print_checkpoint_to_file(system, &tmp_path)?;
+        fs::rename(&tmp_path, path)?;
+        self.record(&format!("checkpoint {}", path.display()), system)
+    }
+}
+
+/// Read all the entries of the journal at path.
+///
+/// A malformed last line is ignored, as it is what a crash while writing an entry leaves behind.
+/// A malformed line anywhere else gives an `Error`.
+pub fn read_journal(path: &PathBuf) -> io::Result<Vec<JournalEntry>> {
+    let lines = BufReader::new(File::open(path)?)
+        .lines()
+        .collect::<io::Result<Vec<String>>>()?;
+    let mut entries = Vec::with_capacity(lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        match JournalEntry::parse(line) {
+            Some(entry) => entries.push(entry),
+            None if i + 1 == lines.len() => break,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("malformed journal entry at line {}", i + 1),
+                ))
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Return the last entry of `entries` whose fingerprint is the one of `system`, i.e. the last
+/// operation already reflected in `system`, or `None` if `system` matches no entry.
+pub fn resume_point<'a>(entries: &'a [JournalEntry], system: &System) -> Option<&'a JournalEntry> {
+    let fingerprint = system.fingerprint();
+    entries.iter().rev().find(|entry| entry.fingerprint == fingerprint)
+}
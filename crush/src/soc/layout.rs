@@ -0,0 +1,145 @@
+//! A layout of a shard, to draw it without GraphViz, e.g. in a browser.
+//!
+//! The levels are drawn from top to bottom, the last one being the sink. The nodes of each level
+//! are ordered from left to right by the barycenter heuristic: the source level is ordered by id,
+//! and every following level by the mean position of the parents of its nodes, which keeps most
+//! of the edges short and uncrossed. A visualizer only has to scale the positions to its canvas,
+//! e.g. drawing the node of position `p` of a level of `n` nodes at `(p + 0.5) / n` of the width.
+//!
+//! The nodes and edges carry the number of accepted paths going through them, as `DotOptions`
+//! does for the .dot output, such that the bottlenecks can be highlighted. `ShardLayout::to_json`
+//! returns the layout as JSON, the path counts being decimal strings as they may exceed the
+//! integers of JavaScript.
+
+use std::fmt::Write;
+
+use num_bigint::BigUint;
+
+use crate::AHashMap;
+use crate::reporting::json_string;
+use crate::soc::{bdd::Bdd, dot::{lhs_label, PathCounts}, Id};
+
+/// A level of a `ShardLayout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelLayout {
+    /// The variables of the lhs, increasing.
+    pub lhs: Vec<usize>,
+    /// The label of the level, e.g. "x1 + x2", or "T" for the sink.
+    pub label: String,
+    /// The number of nodes.
+    pub width: usize,
+}
+
+/// A node of a `ShardLayout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeLayout {
+    pub id: Id,
+    /// The index of the level of the node.
+    pub level: usize,
+    /// The position of the node in its level, from 0 on the left.
+    pub position: usize,
+    /// The number of accepted paths going through the node.
+    pub paths: BigUint,
+}
+
+/// An edge of a `ShardLayout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdgeLayout {
+    pub from: Id,
+    pub to: Id,
+    /// The value of the lhs of the level of `from` along the edge.
+    pub value: bool,
+    /// The number of accepted paths going through the edge.
+    pub paths: BigUint,
+}
+
+/// The layout of a shard, see the `layout` module documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardLayout {
+    /// The id of the shard.
+    pub id: Id,
+    /// The number of accepted paths of the shard.
+    pub paths: BigUint,
+    pub levels: Vec<LevelLayout>,
+    /// The nodes, level after level, each level from left to right.
+    pub nodes: Vec<NodeLayout>,
+    /// The edges, by node in the order of `nodes`, the edge 0 first.
+    pub edges: Vec<EdgeLayout>,
+}
+
+impl ShardLayout {
+    /// Return the layout as a JSON object, e.g. for a source of id 10000 on a level `x0`:
+    ///
+    /// ```text
+    /// {"id":0,"paths":"1","levels":[{"lhs":[0],"label":"x0","width":1},...],
+    ///  "nodes":[{"id":10000,"level":0,"position":0,"paths":"1"},...],
+    ///  "edges":[{"from":10000,"to":20000,"value":true,"paths":"1"},...]}
+    /// ```
+    ///
+    /// without the line breaks.
+    pub fn to_json(&self) -> String {
+        let mut json = format!("{{\"id\":{},\"paths\":\"{}\",\"levels\":[", self.id, self.paths);
+        for (i, level) in self.levels.iter().enumerate() {
+            let lhs: Vec<String> = level.lhs.iter().map(usize::to_string).collect();
+            write!(json, "{}{{\"lhs\":[{}],\"label\":{},\"width\":{}}}",
+                   separator(i), lhs.join(","), json_string(&level.label), level.width).unwrap();
+        }
+        json.push_str("],\"nodes\":[");
+        for (i, node) in self.nodes.iter().enumerate() {
+            write!(json, "{}{{\"id\":{},\"level\":{},\"position\":{},\"paths\":\"{}\"}}",
+                   separator(i), node.id, node.level, node.position, node.paths).unwrap();
+        }
+        json.push_str("],\"edges\":[");
+        for (i, edge) in self.edges.iter().enumerate() {
+            write!(json, "{}{{\"from\":{},\"to\":{},\"value\":{},\"paths\":\"{}\"}}",
+                   separator(i), edge.from, edge.to, edge.value, edge.paths).unwrap();
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+fn separator(index: usize) -> &'static str {
+    if index == 0 { "" } else { "," }
+}
+
+impl Bdd {
+    /// Return the layout of the `Bdd`, see the `layout` module documentation.
+    pub fn layout(&self) -> ShardLayout {
+        let counts = PathCounts::new(self);
+        let sink_level_index = self.get_sink_level_index();
+        let mut levels = Vec::new();
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        // The positions of the parents of the nodes of the current level
+        let mut parents: AHashMap<Id, Vec<usize>> = AHashMap::default();
+        for (level_index, level) in self.iter_levels().enumerate() {
+            let lhs: Vec<usize> = level.iter_set_lhs().collect();
+            let label = if level_index == sink_level_index { "T".to_string() } else { lhs_label(lhs.iter().copied()) };
+            let mut ordered: Vec<_> = level.iter_nodes()
+                .map(|(id, node)| (barycenter(parents.get(id)), *id, node))
+                .collect();
+            ordered.sort_unstable_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            levels.push(LevelLayout { lhs, label, width: ordered.len() });
+            parents.clear();
+            for (position, (_, id, node)) in ordered.into_iter().enumerate() {
+                nodes.push(NodeLayout { id, level: level_index, position, paths: counts.through_node(&id) });
+                for (value, child) in [(false, node.get_e0()), (true, node.get_e1())] {
+                    if let Some(child) = child {
+                        parents.entry(child).or_default().push(position);
+                        edges.push(EdgeLayout { from: id, to: child, value, paths: counts.through_edge(&id, &child) });
+                    }
+                }
+            }
+        }
+        ShardLayout { id: self.get_id(), paths: counts.total, levels, nodes, edges }
+    }
+}
+
+/// Return the mean of `positions`, 0 if none, e.g. for the source.
+fn barycenter(positions: Option<&Vec<usize>>) -> f64 {
+    match positions {
+        Some(positions) if !positions.is_empty() => positions.iter().sum::<usize>() as f64 / positions.len() as f64,
+        _ => 0.0,
+    }
+}
@@ -6,24 +6,29 @@
 //!
 //! x1 + x3 + x5 in a 7 variables system would be stored as [0101010]
 //!
-//! The nodes are a stored as a `AHasmap` of `Node` with the `Id` of a node as its key.
-//! All ids are supposed to be unique in the entirity of the system. The Hashmap uses
-//! AHash as its default hasher for speedup over SipHash.
+//! The nodes are stored in a `NodeStore`, with the `Id` of a node as its key. The default store
+//! is a Hashmap using AHash as its hasher for speedup over SipHash, see the `store` module.
+//! All ids are supposed to be unique in the entirity of the system.
+//!
+//! The store is shared between the clones of a level, and copied on the first change of the nodes
+//! of one of them. Cloning a `Bdd` or a `System` thus only copies the levels changed afterwards.
 
 extern crate vob;
 
-use std::collections::hash_map::{Iter, IterMut};
-use std::fmt;
+use core::fmt;
+use alloc::{sync::Arc, vec::Vec};
 
 use vob::{IterSetBits, Vob};
 
 use crate::{AHashMap, AHashSet};
 use crate::soc::{Id, node::Node};
+use crate::soc::store::{HashNodeStore, NodeStore};
 
 /// A level inside a Binary Decision Diagram
 #[derive(Default, Clone)]
-pub struct Level {
-    nodes: AHashMap<Id, Node>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Level<S: NodeStore = HashNodeStore> {
+    nodes: Arc<S>,
     lhs: Vob,
 }
 
@@ -32,6 +37,13 @@ impl Level {
     pub fn new() -> Level {
         Default::default()
     }
+}
+
+impl<S: NodeStore> Level<S> {
+    /// Construct a new Level with default parameters, storing its nodes in `S`
+    pub fn with_store() -> Level<S> {
+        Default::default()
+    }
 
     /// Set `lhs` to a `Vob` of size `var_len` with all the bits specified
     /// in `vars` equals to `true`.
@@ -82,7 +94,7 @@ impl Level {
     /// lhs = [010111001] -> vec![1,3,4,5,8].iter()
     /// ```
     #[inline]
-    pub fn iter_set_lhs(&self) -> IterSetBits<usize> {
+    pub fn iter_set_lhs(&self) -> IterSetBits<'_, usize> {
         self.lhs.iter_set_bits(0..self.lhs.len())
     }
 
@@ -97,26 +109,39 @@ impl Level {
 
     /// Return an `Iterator` over `nodes`.
     #[inline]
-    pub fn iter_nodes(&self) -> Iter<Id, Node> {
+    pub fn iter_nodes(&self) -> S::Iter<'_> {
         self.nodes.iter()
     }
 
     /// Return an `Iterator` over `nodes`.
     #[inline]
-    pub fn iter_mut_nodes(&mut self) -> IterMut<Id, Node> {
-        self.nodes.iter_mut()
+    pub fn iter_mut_nodes(&mut self) -> S::IterMut<'_> {
+        self.nodes_mut().iter_mut()
     }
 
-    /// Get ref to the map of nodes
+    /// Get ref to the store of nodes
     #[inline]
-    pub fn get_nodes(&self) -> &AHashMap<Id, Node> {
+    pub fn get_nodes(&self) -> &S {
         &self.nodes
     }
 
-    /// Get a mutable ref to the map of nodes
+    /// Get a mutable ref to the store of nodes
+    #[inline]
+    pub fn get_mut_nodes(&mut self) -> &mut S {
+        self.nodes_mut()
+    }
+
+    /// Return true if the store of nodes is shared with a clone of the level, in which case it
+    /// will be copied on the next change of the nodes.
     #[inline]
-    pub fn get_mut_nodes(&mut self) -> &mut AHashMap<Id, Node> {
-        &mut self.nodes
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.nodes) > 1
+    }
+
+    /// Return a mutable ref to the store of nodes, copying it first if it is shared.
+    #[inline]
+    fn nodes_mut(&mut self) -> &mut S {
+        Arc::make_mut(&mut self.nodes)
     }
 
     /// Returns a reference to the node
@@ -133,21 +158,35 @@ impl Level {
     /// Add a new `node` in the level with its `id` set at `n_id` and edges set to e0 and e1.
     pub fn add_edged_node(&mut self, n_id: Id, e0: Option<Id>, e1: Option<Id>) {
         let n = Node::with_edges(e0, e1);
-        self.nodes.insert(n_id, n);
+        self.nodes_mut().insert(n_id, n);
     }
 
     /// Add a new `node` in the level with its `id` set at `n_id` and edges set to `None`.
     pub fn add_new_node(&mut self, n_id: Id) {
         let n = Node::new();
-        self.nodes.insert(n_id, n);
+        self.nodes_mut().insert(n_id, n);
+    }
+
+    /// Add all the (id, node) pairs yielded by `nodes` to the level, reserving room for them
+    /// beforehand.
+    ///
+    /// Like `add_edged_node`, nothing is checked: the ids are expected to be unique and the edges
+    /// to point to nodes of the next level.
+    pub fn extend_nodes<I: IntoIterator<Item = (Id, Node)>>(&mut self, nodes: I) {
+        let nodes = nodes.into_iter();
+        let store = self.nodes_mut();
+        store.reserve(nodes.size_hint().0);
+        for (id, node) in nodes {
+            store.insert(id, node);
+        }
     }
 
-    /// Replace `nodes` by the given `AHashMap` of nodes and resize it to reduce
+    /// Replace `nodes` by the given store of nodes and resize it to reduce
     /// its memory footprint. We assume that no node will be insert after
     /// replacing the nodes hence the shrinking.
-    pub fn replace_nodes(&mut self, nodes: AHashMap<Id, Node>) {
-        self.nodes = nodes;
-        self.nodes.shrink_to_fit();
+    pub fn replace_nodes(&mut self, nodes: S) {
+        self.nodes = Arc::new(nodes);
+        self.nodes_mut().shrink_to_fit();
     }
 
     /// Remove any node not present in parents and insert in parents the edges of the remaining nodes
@@ -174,19 +213,19 @@ impl Level {
     /// Remove all nodes which ids are in the keys of the provided map
     pub fn remove_nodes_from_map(&mut self, map: &AHashMap<Id, Id>) {
         map.keys().for_each(|key| {
-            self.nodes.remove(key);
+            self.nodes_mut().remove(key);
         });
     }
 
     /// Remove all nodes which ids are in the provided set
     pub fn remove_nodes_from_set(&mut self, map: &AHashSet<Id>) {
         map.iter().for_each(|key| {
-            self.nodes.remove(key);
+            self.nodes_mut().remove(key);
         });
     }
     /// Remove a single node who has the provided Id
     pub fn remove_node(&mut self, to_remove: Id) {
-        self.nodes.remove(&to_remove);
+        self.nodes_mut().remove(&to_remove);
     }
 
     /// Check if `nodes` has at least one node with `e0` pointing to a valid `node` and one node
@@ -216,7 +255,7 @@ impl Level {
 
     /// Flip the edges of all nodes in the level.
     pub fn flip_edges(&mut self) {
-        self.nodes.iter_mut().for_each(|node| {
+        self.nodes_mut().iter_mut().for_each(|node| {
             node.1.flip_edges();
         });
     }
@@ -226,16 +265,14 @@ impl Level {
     /// We can then simply grab the node, look at its edges and then
     /// delete the level
     pub fn pop_source(&mut self) -> Node {
-        self.nodes
-            .drain()
-            .map(|(_, n)| n)
-            .collect::<Vec<Node>>()
-            .pop()
-            .unwrap()
+        let id = *self.nodes.iter().next().unwrap().0;
+        let source = self.nodes_mut().remove(&id).unwrap();
+        self.nodes = Arc::default();
+        source
     }
 }
 
-impl fmt::Debug for Level {
+impl<S: NodeStore> fmt::Debug for Level<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "lhs {:?}", self.lhs)?;
         if self.nodes.is_empty() {
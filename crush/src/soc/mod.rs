@@ -1,70 +1,58 @@
 //! Module providing the structures to represent a system of equation represented by a set of
 //! binary decision diagram (Bdd) and exposing the apis to absorb all the linear dependencies
 //! inside to solve it.
+//!
+//! The structures and algorithms (`bdd`, `system`, `utils`, as well as the visualization data of
+//! `dot` and `layout`) only work in memory. Everything reading or writing files and spawning
+//! processes lives in the `io` module, and the parser of the .bdd format in the `parse` module,
+//! behind the features of the same name (see Cargo.toml).
+//!
+//! Without the `std` feature, only the bdds (`bdd`, `store`) are built, on `alloc`: the systems
+//! report their errors as `std::io::Error`.
 
-use std::fmt::{self, Display};
-use std::ops::Deref;
+use core::fmt::{self, Display};
+use core::ops::Deref;
 
+#[cfg(feature = "std")]
+pub use error::SocError;
 pub use node::Node;
 
+#[cfg(feature = "std")]
+pub mod anf;
 pub mod bdd;
+#[cfg(feature = "std")]
+pub mod binary;
+#[cfg(feature = "std")]
+pub mod dimacs;
+#[cfg(feature = "std")]
+pub mod dot;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzz;
+#[cfg(feature = "io")]
+pub mod io;
+#[cfg(feature = "io")]
+pub mod journal;
+#[cfg(feature = "std")]
+pub mod layout;
 mod level;
 mod node;
+#[cfg(feature = "parse")]
+pub mod parse;
+#[cfg(feature = "io")]
+pub mod repl;
+#[cfg(feature = "io")]
+pub mod session;
+#[cfg(feature = "std")]
+pub mod stats;
+pub mod store;
+#[cfg(feature = "std")]
 pub mod system;
+#[cfg(feature = "std")]
 pub mod utils;
-#[macro_export]
-/// Macro to generate bdds :
-///
-/// ### Example :
-///
-/// ```
-/// # #[macro_use] extern crate crush;
-/// use nom;
-/// use crush::soc::Id;
-///
-/// let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;0,4);(3;4,0)]);("0+2",[(4;0,0)])]);
-/// ```
-/// will create bdd with 5 variable lhs, id 0 with 3 levels defined by lhs equations and arrays of nodes.
-///
-/// ! Depends on the "nom" crate. (See Cargo.toml)
-macro_rules! bdd {
-    (
-        $nvar:expr;
-        $id:expr; [
-            $((
-                $lhs:expr,[
-                    $((
-                        $id_node:expr;
-                        $e0:expr,
-                        $e1:expr
-                    ));*
-                ]
-            ))
-            ;*
-        ]
-    ) => {
-        $crate::soc::utils::build_bdd_from_spec(&mut $crate::soc::utils::BddSpec::new(Id::new($id),
-        [$($crate::soc::utils::LevelSpec::new($crate::soc::utils::vars(nom::types::CompleteStr(&$lhs)).expect("wrong format for lhs").1, [
-            $($crate::soc::utils::NodeSpec::new(Id::new($id_node), Id::new($e0), Id::new($e1)))
-            ,*].to_vec()))
-        ,*].to_vec()),$nvar);
-    }
-}
-
-#[macro_export]
-/// Macro to generate systems :
-///
-/// ### Example :
-///
-/// ```text.
-/// let system = system![bdd, bdd_2]?;
-/// ```
-/// will return a Result containing a System and fail if the bdds provided have a different nvar
-macro_rules! system {
-    [$($bdd:expr),*] => {
-        $crate::soc::system::System::from_elem(vec![$($bdd),*])
-    };
-}
+#[cfg(feature = "std")]
+pub mod validate;
 
 /// Custom type wrapping `usize` used for the ids of `node` inside a `Bdd` and
 /// ids of `Bdd` inside a `System`. This is purely use for type safety and allows
@@ -73,6 +61,8 @@ macro_rules! system {
 /// needs to occur).
 
 #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Id {
     val: usize,
 }
@@ -98,5 +88,5 @@ impl Display for Id {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "parse"))]
 mod test;
@@ -12,6 +12,7 @@ use crate::soc::Id;
 
 /// A Node inside a Binary Decision Diagram
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
     e0: Option<Id>,
     e1: Option<Id>,
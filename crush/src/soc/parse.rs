@@ -0,0 +1,309 @@
+//! Parser of the .bdd format into the specifications of the `utils` module, built with nom.
+//!
+//! `parse_system_spec` parses a whole system held in memory. `SpecReader` parses it from any
+//! `BufRead` with the same grammar, one line at a time, yielding the levels of each bdd as they
+//! are read: `build_system_from_reader` builds each level as soon as it is parsed, such that
+//! neither the input nor the specifications of whole bdds are ever held in memory.
+//!
+//! Only available with the `parse` feature.
+
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+use nom::digit;
+use nom::IResult;
+use nom::types::CompleteStr;
+
+use crate::metrics;
+use crate::soc::{
+    error::SocError,
+    Id,
+    system::System,
+    utils::{BddBuilder, BddSpec, LevelSpec, NodeSpec, SystemSpec}};
+
+named!(i64 <CompleteStr, i64>,
+ws!(
+    map_res!(digit,|CompleteStr(s)| FromStr::from_str(s))
+));
+
+named!(usize <CompleteStr, usize>,
+ws!(
+    map_res!(digit,|CompleteStr(s)| FromStr::from_str(s))
+));
+
+named!(line_break <CompleteStr,Option<CompleteStr>>,
+    opt!(alt!(tag!("\n")|tag!("\r\n")))
+);
+
+named!(minus_one <CompleteStr, i64>,
+ws!(
+    map_res!(
+        recognize!(
+            do_parse!(
+                opt!(tag!("-")) >>
+                digit >>
+                ()
+            )
+        ),
+    |CompleteStr(s)| FromStr::from_str(s))
+));
+
+named!(parameters<CompleteStr, (usize,usize)>,
+    do_parse!(
+        a: usize >>
+        b: usize >>
+        (a,b)
+));
+
+named!(var<CompleteStr,i64>,
+    do_parse!(   
+        opt!(alt!(char!('+')))>>
+        a: alt!(i64 | minus_one)>>
+        (a)
+));
+
+named!(pub vars<CompleteStr, Vec<i64>>,
+    many0!(
+        var
+));
+
+named!(lhs<CompleteStr,Vec<i64>>,
+    do_parse!(
+        a:vars>>
+        (a)
+));
+
+named!(node<CompleteStr,NodeSpec>,
+    do_parse!(
+        char!('(')>>
+        id: usize >>
+        char!(';')>>
+        e0: usize >>
+        char!(',')>>
+        e1: usize >>
+        char!(')')>>
+        (NodeSpec::new(Id::new(id), Id::new(e0), Id::new(e1)))
+    )
+);
+
+named!(rhs<CompleteStr,Vec<NodeSpec>>,
+    many0!(
+        node
+));
+
+named!(level<CompleteStr,LevelSpec>,
+    do_parse!(
+        a:lhs>>
+        char!(':')>>
+        b:rhs>>
+        char!('|')>>
+        line_break>>
+        (LevelSpec::new(a, b))
+));
+
+named!(levels<CompleteStr,Vec<LevelSpec>>,
+    many0!(
+        level
+));
+
+named!(bdd<CompleteStr,BddSpec>,
+    do_parse!(
+        param: parameters>>
+        line_break>>
+        levels: levels>>
+        tag!("---")>>
+        line_break>>
+        (BddSpec::new(Id::new(param.0), levels))
+));
+
+named!(bdds<CompleteStr,Vec<BddSpec>>,
+    many0!(
+        bdd
+));
+
+named!(full_parser<CompleteStr,SystemSpec>,
+    do_parse!(
+        params:parameters>>
+        line_break>>
+        bdds:bdds>>
+        (SystemSpec::new(params.0,bdds))
+    )
+);
+
+/// Return a SystemSpec from the parsing of the content of a .bdd file using the correct format
+///
+/// Will return a `SocError::Parse` at the first line which can't be parsed, i.e. the line of the
+/// parameters of the system or the first line of the first bdd which can't be parsed.
+pub fn parse_system_spec(input: &str) -> Result<SystemSpec, SocError> {
+    match full_parser(CompleteStr(input)) {
+        Ok((rest, spec)) if rest.trim().is_empty() => Ok(spec),
+        Ok((rest, _)) => {
+            let line = input[..input.len() - rest.trim_start().len()].matches('\n').count() + 1;
+            Err(SocError::parse(line, "expected a bdd: its id and its number of levels, its levels and ---"))
+        }
+        Err(_) => Err(SocError::parse(1, "expected the number of variables and the number of bdds")),
+    }
+}
+
+/// Parse the whole of `line` with `parser`, return `None` if it fails or leaves anything but
+/// whitespace.
+fn parse_line<T>(parser: fn(CompleteStr) -> IResult<CompleteStr, T>, line: &str) -> Option<T> {
+    match parser(CompleteStr(line)) {
+        Ok((rest, value)) if rest.trim().is_empty() => Some(value),
+        _ => None,
+    }
+}
+
+/// Streaming parser of the .bdd format, reading a system from a `BufRead` one line at a time with
+/// the grammar of `parse_system_spec`. The bdds are read with `next_bdd`, then their levels with
+/// `next_level`, such that only the line being parsed is held in memory. Empty lines are skipped.
+pub struct SpecReader<R: BufRead> {
+    reader: R,
+    line: String,
+    line_number: usize,
+    nvar: usize,
+    nbdds: usize,
+    /// Whether the header of a bdd was read but not its `---` yet.
+    in_bdd: bool,
+}
+
+impl<R: BufRead> SpecReader<R> {
+    /// Read the header of the system from `reader` and return a `SpecReader` positioned on its
+    /// first bdd.
+    ///
+    /// Return an `Error` of kind `ErrorKind::InvalidData` if the header is malformed.
+    pub fn new(reader: R) -> io::Result<SpecReader<R>> {
+        let mut spec_reader = SpecReader { reader, line: String::new(), line_number: 0, nvar: 0, nbdds: 0, in_bdd: false };
+        let header = match spec_reader.next_line()? {
+            true => parse_line(parameters, &spec_reader.line),
+            false => None,
+        };
+        let (nvar, nbdds) = header.ok_or_else(|| spec_reader.invalid("expected the number of variables and the number of bdds"))?;
+        spec_reader.nvar = nvar;
+        spec_reader.nbdds = nbdds;
+        Ok(spec_reader)
+    }
+
+    /// Return the number of variables of the system.
+    pub fn get_nvar(&self) -> usize {
+        self.nvar
+    }
+
+    /// Return the number of bdds announced by the header.
+    pub fn get_nbdds(&self) -> usize {
+        self.nbdds
+    }
+
+    /// Read the header of the next bdd, skipping the levels of the current one which weren't read,
+    /// and return its id and its number of levels, or `None` once the input is exhausted. Its
+    /// levels are then read with `next_level`.
+    ///
+    /// Return an `Error` of kind `ErrorKind::InvalidData` if the header is malformed.
+    pub fn next_bdd(&mut self) -> io::Result<Option<(Id, usize)>> {
+        while self.next_level()?.is_some() {}
+        if !self.next_line()? {
+            return Ok(None)
+        }
+        let (id, nlevels) = parse_line(parameters, &self.line)
+            .ok_or_else(|| self.invalid("expected the id of a bdd and its number of levels"))?;
+        self.in_bdd = true;
+        Ok(Some((Id::new(id), nlevels)))
+    }
+
+    /// Read the next level of the current bdd, return `None` once its `---` is read.
+    ///
+    /// Return an `Error` of kind `ErrorKind::InvalidData` if the level is malformed or if the
+    /// input ends before the `---`.
+    pub fn next_level(&mut self) -> io::Result<Option<LevelSpec>> {
+        if !self.in_bdd {
+            return Ok(None)
+        }
+        if !self.next_line()? {
+            return Err(self.invalid("truncated bdd, missing ---"));
+        }
+        if self.line.trim() == "---" {
+            self.in_bdd = false;
+            return Ok(None)
+        }
+        parse_line(level, self.line.trim())
+            .map(Some)
+            .ok_or_else(|| self.invalid("expected a level: its lhs, then its nodes (id;e0,e1) between : and |"))
+    }
+
+    /// Read the next bdd as a whole, return `None` once the input is exhausted.
+    ///
+    /// Return an `Error` of kind `ErrorKind::InvalidData` if the bdd is malformed or truncated.
+    pub fn read_bdd(&mut self) -> io::Result<Option<BddSpec>> {
+        let (id, nlevels) = match self.next_bdd()? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let mut levels = Vec::with_capacity(nlevels);
+        while let Some(level) = self.next_level()? {
+            levels.push(level);
+        }
+        Ok(Some(BddSpec::new(id, levels)))
+    }
+
+    /// Read every remaining bdd into a `SystemSpec`.
+    pub fn read_system_spec(mut self) -> io::Result<SystemSpec> {
+        let mut bdds = Vec::with_capacity(self.nbdds);
+        while let Some(bdd) = self.read_bdd()? {
+            bdds.push(bdd);
+        }
+        Ok(SystemSpec::new(self.nvar, bdds))
+    }
+
+    /// Read the next non empty line into `line`, return false at the end of the input.
+    fn next_line(&mut self) -> io::Result<bool> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(false)
+            }
+            self.line_number += 1;
+            if !self.line.trim().is_empty() {
+                return Ok(true)
+            }
+        }
+    }
+
+    fn invalid(&self, message: &str) -> io::Error {
+        SocError::parse(self.line_number, message).into()
+    }
+}
+
+impl<R: BufRead> Iterator for SpecReader<R> {
+    type Item = io::Result<BddSpec>;
+
+    fn next(&mut self) -> Option<io::Result<BddSpec>> {
+        self.read_bdd().transpose()
+    }
+}
+
+/// Read a system in the .bdd format from `reader` and build it, each level of a `Bdd` being built
+/// as soon as it is read (see `SpecReader::next_level` and `utils::BddBuilder`), such that
+/// neither the input nor the spec of a whole bdd is held in memory.
+///
+/// Unlike `utils::build_system_from_spec`, the bdds can't be renumbered once some are built: an
+/// `Error` of kind `ErrorKind::AlreadyExists` is returned if two bdds share an id.
+///
+/// The building is profiled as the `system.build` phase (see `metrics::phase`).
+pub fn build_system_from_reader<R: BufRead>(reader: R) -> io::Result<System> {
+    let _build = metrics::phase("system.build");
+    let mut specs = SpecReader::new(reader)?;
+    let mut system = System::new();
+    system.set_nvar(specs.get_nvar());
+    let mut size = 0;
+    while let Some((id, _)) = specs.next_bdd()? {
+        let mut builder = BddBuilder::new(id, specs.get_nvar());
+        while let Some(mut level) = specs.next_level()? {
+            builder.add_level(&mut level)?;
+        }
+        let bdd = builder.build();
+        size += bdd.get_size();
+        metrics::observe_nodes(size);
+        system.push_bdd(bdd)?;
+    }
+    Ok(system)
+}
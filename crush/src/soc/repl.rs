@@ -0,0 +1,228 @@
+//! The commands of the `socs-cli` binary, to explore a system interactively without writing a
+//! Rust main for each experiment.
+//!
+//! A `Repl` holds the system being explored and executes one command per line:
+//!
+//! - `load FILE`, read a system from a .bdd file, or from a binary snapshot if the extension is
+//!   `snap` (see `io::load_snapshot_from_file`),
+//! - `save FILE`, write the system to a .bdd file as a checkpoint, keeping the `LinBank` (see
+//!   `io::print_checkpoint_to_file`), or to a binary snapshot if the extension is `snap`,
+//! - `stats`, print the statistics of the system and of each of its bdds (see `System::stats`),
+//! - `deps`, list the linear dependencies among the levels, numbered from 0, each as the levels
+//!   `bdd:level` whose LHS's sum to zero (see `System::lhs_dependencies`),
+//! - `absorb N`, resolve the dependency numbered `N` by `deps`, joining the bdds involved (see
+//!   `System::try_absorb`),
+//! - `prune BDD WEIGHT [LEVEL...]`, remove the paths of the bdd of id `BDD` taking more than
+//!   `WEIGHT` 1-edges in the levels `LEVEL`, all the levels if none is given (see
+//!   `Bdd::prune_above_weight`),
+//! - `fix VAR 0|1`, fix a variable (see `System::fix_variable`),
+//! - `draw BDD FILE`, write the bdd of id `BDD` to a .dot file, or draw it with GraphViz if the
+//!   extension is `pdf`, `svg` or `png` (with the `draw` feature only),
+//! - `help`, list the commands, and `quit` (or `exit`).
+//!
+//! Empty lines and lines starting with `#` are ignored, such that a script of commands can be
+//! piped to the binary.
+
+use std::io::{self, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::soc::{io as soc_io, system::System, Id};
+
+const HELP: &str = "\
+load FILE                  read a system from a .bdd file, or a .snap snapshot
+save FILE                  write the system to a .bdd checkpoint, or a .snap snapshot
+stats                      print the statistics of the system
+deps                       list the linear dependencies among the levels
+absorb N                   resolve the dependency numbered N by deps
+prune BDD WEIGHT [LEVEL..] remove the paths of a bdd above a weight in the levels
+fix VAR 0|1                fix a variable
+draw BDD FILE              write a bdd to a .dot file, or draw it to a .pdf, .svg or .png
+help                       list the commands
+quit                       leave";
+
+/// The state of an interactive exploration, see the `repl` module documentation.
+#[derive(Debug, Default)]
+pub struct Repl {
+    system: Option<System>,
+}
+
+impl Repl {
+    /// Create a `Repl` without system, to be loaded with `load`.
+    pub fn new() -> Repl {
+        Repl::default()
+    }
+
+    /// Create a `Repl` exploring `system`.
+    pub fn with_system(system: System) -> Repl {
+        Repl { system: Some(system) }
+    }
+
+    /// Return the system being explored, if one was loaded.
+    pub fn system(&self) -> Option<&System> {
+        self.system.as_ref()
+    }
+
+    /// Execute the command of `line`, writing its output to `out`, and return false if it asks to
+    /// leave.
+    ///
+    /// Returns an `Error` of kind `ErrorKind::InvalidInput` if the command is unknown, its
+    /// arguments malformed, or it needs a system and none is loaded, and the `Error` of the
+    /// operation if it fails. The system is left untouched by a failed command.
+    pub fn execute(&mut self, line: &str, out: &mut dyn Write) -> io::Result<bool> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) if !command.starts_with('#') => command,
+            _ => return Ok(true),
+        };
+        let args: Vec<&str> = words.collect();
+        match (command, args.as_slice()) {
+            ("load", [path]) => {
+                let path = PathBuf::from(path);
+                let system = if is_snapshot(&path) {
+                    soc_io::load_snapshot_from_file(&path)?
+                } else {
+                    soc_io::build_system_from_file(&path)?
+                };
+                writeln!(out, "loaded {} bdds of {} variables", system.iter_bdds().len(), system.get_nvar())?;
+                self.system = Some(system);
+            }
+            ("save", [path]) => {
+                let path = PathBuf::from(path);
+                let system = self.loaded()?;
+                if is_snapshot(&path) {
+                    soc_io::save_snapshot_to_file(system, &path)?;
+                } else {
+                    soc_io::print_checkpoint_to_file(system, &path)?;
+                }
+                writeln!(out, "saved to {}", path.display())?;
+            }
+            ("stats", []) => {
+                let stats = self.loaded()?.stats();
+                writeln!(out, "{}", stats)?;
+                for bdd in stats.bdds.iter() {
+                    writeln!(out, "bdd {}: {} nodes, {} levels, max width {}, average width {:.1}",
+                             bdd.id, bdd.nodes, bdd.levels, bdd.max_width, bdd.avg_width)?;
+                }
+            }
+            ("deps", []) => {
+                let dependencies = self.loaded()?.lhs_dependencies();
+                if dependencies.is_empty() {
+                    writeln!(out, "no linear dependency")?;
+                }
+                for (n, dependency) in dependencies.iter().enumerate() {
+                    let levels: Vec<String> = dependency.iter().map(|(id, level)| format!("{}:{}", id, level)).collect();
+                    writeln!(out, "{}: {}", n, levels.join(" "))?;
+                }
+            }
+            ("absorb", [n]) => {
+                let n: usize = parse(n, "dependency number")?;
+                let system = self.loaded()?;
+                let dependency = system.lhs_dependencies().into_iter().nth(n).ok_or_else(|| {
+                    Error::new(ErrorKind::InvalidInput, format!("no dependency {}, see deps", n))
+                })?;
+                if let [(bdd_id, level)] = dependency.as_slice() {
+                    // A level of LHS zero is absorbed on its own
+                    self.loaded_mut()?.absorb(*bdd_id, *level, false)?;
+                    writeln!(out, "absorbed level {} of bdd {}", level, bdd_id)?;
+                    return Ok(true);
+                }
+                let (bdds, levels) = join_order(system, &dependency)?;
+                let trial = system.try_absorb(&bdds, &levels)?;
+                writeln!(out, "absorbed in bdd {}: {} nodes before, {} after, {} at the peak",
+                         trial.bdd(), trial.nodes_before(), trial.nodes_after(), trial.peak_nodes())?;
+                self.system = Some(trial.into_system());
+            }
+            ("prune", [bdd_id, weight, levels @ ..]) => {
+                let bdd_id = Id::new(parse(bdd_id, "bdd id")?);
+                let weight: usize = parse(weight, "weight")?;
+                let mut active = levels.iter().map(|level| parse(level, "level")).collect::<io::Result<Vec<usize>>>()?;
+                let system = self.loaded_mut()?;
+                if active.is_empty() {
+                    active = (0..system.get_bdd(bdd_id)?.borrow().get_sink_level_index()).collect();
+                }
+                let stats = system.modify_bdd(bdd_id, "prune", |bdd| bdd.prune_above_weight(weight, &active))?;
+                writeln!(out, "pruned bdd {}: {} nodes removed, {} split, {} paths left out of {}",
+                         bdd_id, stats.removed_nodes(), stats.split_nodes(), stats.paths_after, stats.paths_before)?;
+            }
+            ("fix", [var, value]) => {
+                let var: usize = parse(var, "variable")?;
+                let value = match *value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(Error::new(ErrorKind::InvalidInput, format!("malformed value {}, expected 0 or 1", value))),
+                };
+                self.loaded_mut()?.fix_variable(var, value)?;
+                writeln!(out, "fixed x{} to {}", var, value as u8)?;
+            }
+            #[cfg(feature = "draw")]
+            ("draw", [bdd_id, path]) => {
+                let bdd_id = Id::new(parse(bdd_id, "bdd id")?);
+                let path = PathBuf::from(path);
+                let bdd = self.loaded()?.get_bdd(bdd_id)?.borrow();
+                let format = match path.extension().and_then(|extension| extension.to_str()) {
+                    Some("pdf") => Some(soc_io::OutputFormat::Pdf),
+                    Some("svg") => Some(soc_io::OutputFormat::Svg),
+                    Some("png") => Some(soc_io::OutputFormat::Png),
+                    _ => None,
+                };
+                match format {
+                    Some(format) => {
+                        soc_io::draw_shard(&bdd, &path, format)?.wait()?;
+                    }
+                    None => soc_io::print_bdd_to_dot_format(&bdd, &path)?,
+                }
+                writeln!(out, "drew bdd {} to {}", bdd_id, path.display())?;
+            }
+            ("help", []) => writeln!(out, "{}", HELP)?,
+            ("quit", []) | ("exit", []) => return Ok(false),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("unknown command or wrong arguments: {}, see help", line.trim()),
+                ))
+            }
+        }
+        Ok(true)
+    }
+
+    fn loaded(&self) -> io::Result<&System> {
+        self.system.as_ref().ok_or_else(no_system)
+    }
+
+    fn loaded_mut(&mut self) -> io::Result<&mut System> {
+        self.system.as_mut().ok_or_else(no_system)
+    }
+}
+
+/// Return the bdds of `dependency` in the order to join them, and the indexes of its levels in the
+/// joined bdd, as expected by `System::try_absorb`.
+fn join_order(system: &System, dependency: &[(Id, usize)]) -> io::Result<(Vec<Id>, Vec<usize>)> {
+    let mut bdds: Vec<Id> = dependency.iter().map(|(id, _)| *id).collect();
+    bdds.sort_unstable();
+    bdds.dedup();
+    // The levels of each bdd follow the ones of the bdds joined before it, sinks excluded
+    let mut offsets = Vec::with_capacity(bdds.len());
+    let mut offset = 0;
+    for id in bdds.iter() {
+        offsets.push(offset);
+        offset += system.get_bdd(*id)?.borrow().get_sink_level_index();
+    }
+    let mut levels: Vec<usize> = dependency
+        .iter()
+        .map(|(id, level)| offsets[bdds.binary_search(id).unwrap()] + level)
+        .collect();
+    levels.sort_unstable();
+    Ok((bdds, levels))
+}
+
+fn is_snapshot(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "snap")
+}
+
+fn parse<T: std::str::FromStr>(word: &str, what: &str) -> io::Result<T> {
+    word.parse().map_err(|_| Error::new(ErrorKind::InvalidInput, format!("malformed {}: {}", what, word)))
+}
+
+fn no_system() -> Error {
+    Error::new(ErrorKind::InvalidInput, "no system loaded, see load")
+}
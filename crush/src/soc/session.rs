@@ -0,0 +1,204 @@
+//! Search sessions: everything about a long running solving kept together in a directory, such
+//! that it can be stopped, continued and inspected over several runs.
+//!
+//! A session directory holds:
+//!
+//! - `session`, the manifest: the version of the layout, the fingerprint of the input system
+//!   (see `System::fingerprint`), the configuration of the solving as key/value pairs and whether
+//!   the session is finalized,
+//! - `input.bdd`, the input system, written as a checkpoint to keep its `LinBank`,
+//! - `journal`, the journal of the solving (see the `journal` module),
+//! - `checkpoint.bdd`, the last checkpoint of the system, written along the journal,
+//! - `results`, the results found so far, one per line.
+//!
+//! A session is created with `Session::open`, continued in a later run with
+//! `Session::continue_from`, which gives back the system to solve from the last checkpoint, and
+//! closed with `Session::finalize` once the results are complete.
+//!
+//! Only available with the `io` feature.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::soc::{io::{build_system_from_file, print_checkpoint_to_file}, system::System};
+
+/// Version of the layout of the session directories written by this module.
+pub const SESSION_VERSION: u32 = 1;
+
+const MANIFEST: &str = "session";
+const INPUT: &str = "input.bdd";
+const JOURNAL: &str = "journal";
+const CHECKPOINT: &str = "checkpoint.bdd";
+const RESULTS: &str = "results";
+
+/// The manifest of a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub version: u32,
+    /// Fingerprint of the input system.
+    pub fingerprint: u64,
+    /// Configuration of the solving, e.g. the strategy used. The keys can't hold whitespaces.
+    pub config: BTreeMap<String, String>,
+    /// True once the results are complete.
+    pub finalized: bool,
+}
+
+impl Manifest {
+    /// Read the manifest of the session at dir.
+    ///
+    /// Return an `Error` of kind `ErrorKind::Unsupported` if it was written by a later version.
+    pub fn read(dir: &Path) -> io::Result<Manifest> {
+        let invalid = |line: &str| {
+            Error::new(ErrorKind::InvalidData, format!("malformed session manifest line: {}", line))
+        };
+        let mut lines = BufReader::new(File::open(dir.join(MANIFEST))?).lines();
+        let first = lines.next().unwrap_or_else(|| Ok(String::new()))?;
+        let version = first
+            .strip_prefix("crush-session ")
+            .and_then(|version| version.parse().ok())
+            .ok_or_else(|| invalid(&first))?;
+        if version > SESSION_VERSION {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("session version {} is newer than the supported {}", version, SESSION_VERSION),
+            ));
+        }
+        let mut manifest = Manifest { version, fingerprint: 0, config: BTreeMap::new(), finalized: false };
+        for line in lines {
+            let line = line?;
+            let mut parts = line.splitn(3, ' ');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some("fingerprint"), Some(fingerprint), None) => {
+                    manifest.fingerprint =
+                        u64::from_str_radix(fingerprint, 16).map_err(|_| invalid(&line))?;
+                }
+                (Some("state"), Some(state), None) => manifest.finalized = state == "finalized",
+                (Some("config"), Some(key), value) => {
+                    manifest.config.insert(key.to_string(), value.unwrap_or("").to_string());
+                }
+                _ => return Err(invalid(&line)),
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Write the manifest of the session at dir, through a temporary file renamed afterwards such
+    /// that a crash leaves the previous manifest untouched.
+    fn write(&self, dir: &Path) -> io::Result<()> {
+        let tmp_path = dir.join(format!("{}.tmp", MANIFEST));
+        let mut file = File::create(&tmp_path)?;
+        writeln!(file, "crush-session {}", self.version)?;
+        writeln!(file, "fingerprint {:016x}", self.fingerprint)?;
+        writeln!(file, "state {}", if self.finalized { "finalized" } else { "open" })?;
+        for (key, value) in self.config.iter() {
+            writeln!(file, "config {} {}", key, value)?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp_path, dir.join(MANIFEST))
+    }
+}
+
+/// An open session, see the module documentation.
+pub struct Session {
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+impl Session {
+    /// Create a new session at dir for solving `system` with the given configuration, creating
+    /// the directory if needed.
+    ///
+    /// Return an `Error` of kind `ErrorKind::AlreadyExists` if dir already holds a session.
+    pub fn open(dir: &Path, system: &System, config: BTreeMap<String, String>) -> io::Result<Session> {
+        fs::create_dir_all(dir)?;
+        if Self::exists(dir) {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} already holds a session", dir.display()),
+            ));
+        }
+        print_checkpoint_to_file(system, &dir.join(INPUT))?;
+        let manifest = Manifest {
+            version: SESSION_VERSION,
+            fingerprint: system.fingerprint(),
+            config,
+            finalized: false,
+        };
+        manifest.write(dir)?;
+        Ok(Session { dir: dir.to_path_buf(), manifest })
+    }
+
+    /// Return true if dir holds a session.
+    pub fn exists(dir: &Path) -> bool {
+        dir.join(MANIFEST).exists()
+    }
+
+    /// Continue the session at dir, returning it along with the system to solve: the last
+    /// checkpoint if any, the input system otherwise.
+    ///
+    /// Return an `Error` if the session is finalized, if a system can't be read, or if the input
+    /// system doesn't match the fingerprint of the manifest.
+    pub fn continue_from(dir: &Path) -> io::Result<(Session, System)> {
+        let manifest = Manifest::read(dir)?;
+        if manifest.finalized {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("the session at {} is finalized", dir.display()),
+            ));
+        }
+        let input = build_system_from_file(&dir.join(INPUT))?;
+        if input.fingerprint() != manifest.fingerprint {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "the input system doesn't match the fingerprint of the session",
+            ));
+        }
+        let checkpoint = dir.join(CHECKPOINT);
+        let system = if checkpoint.exists() {
+            build_system_from_file(&checkpoint)?
+        } else {
+            input
+        };
+        Ok((Session { dir: dir.to_path_buf(), manifest }, system))
+    }
+
+    /// Return the manifest of the session.
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Return the path of the journal of the session.
+    pub fn journal_path(&self) -> PathBuf {
+        self.dir.join(JOURNAL)
+    }
+
+    /// Return the path of the checkpoints of the session.
+    pub fn checkpoint_path(&self) -> PathBuf {
+        self.dir.join(CHECKPOINT)
+    }
+
+    /// Append `result` to the results of the session. It must hold on a single line.
+    pub fn add_result(&mut self, result: &str) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(self.dir.join(RESULTS))?;
+        writeln!(file, "{}", result)?;
+        file.sync_data()
+    }
+
+    /// Return the results of the session so far.
+    pub fn results(&self) -> io::Result<Vec<String>> {
+        match File::open(self.dir.join(RESULTS)) {
+            Ok(file) => BufReader::new(file).lines().collect(),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Mark the session as finalized, its results being complete. It can't be continued anymore.
+    pub fn finalize(mut self) -> io::Result<Manifest> {
+        self.manifest.finalized = true;
+        self.manifest.write(&self.dir)?;
+        Ok(self.manifest)
+    }
+}
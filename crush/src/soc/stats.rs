@@ -0,0 +1,199 @@
+//! Aggregate statistics of a `System`.
+//!
+//! Reports, heuristics and command line tools all need a few numbers about a system (how many
+//! nodes, how wide the bdds are, which variables are the most shared, ...). `System::stats`
+//! computes them all at once, such that each consumer doesn't have to walk the system again.
+//!
+//! The sink level of a `Bdd` holds no equation, it is left out of the level counts and widths.
+//!
+//! The statistics can be sent to `reporting` as a snapshot, see `reporting::stats_snapshot`, e.g.
+//! at regular intervals of a solving to follow the shape of the system as it goes.
+//!
+//! `System::join_scores` ranks the bdds by their expected contribution to the blowup of the joins,
+//! to choose which ones to join first or to leave for last.
+
+use core::fmt;
+
+use vob::Vob;
+
+use crate::soc::{Id, bdd::Bdd, system::System};
+
+/// Statistics of a single `Bdd` (shard) of a `System`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BddStats {
+    pub id: Id,
+    /// Number of nodes, the sink included.
+    pub nodes: usize,
+    /// Number of levels, the sink excluded.
+    pub levels: usize,
+    /// Number of nodes of the widest level.
+    pub max_width: usize,
+    /// Average number of nodes per level.
+    pub avg_width: f64,
+    /// Number of nodes of each level, from the source.
+    pub level_widths: Vec<usize>,
+    /// Number of variables of the lhs of each level, from the source.
+    pub lhs_densities: Vec<usize>,
+    /// Number of edges not leading to the level just below, see `Bdd::jumping_edges`.
+    pub jumping_edges: usize,
+}
+
+impl BddStats {
+    /// Compute the statistics of `bdd`.
+    pub fn new(bdd: &Bdd) -> BddStats {
+        let levels = bdd.get_levels_size().saturating_sub(1);
+        let level_widths: Vec<usize> = bdd.iter_levels().take(levels).map(|level| level.get_nodes_len()).collect();
+        let total_width: usize = level_widths.iter().sum();
+        BddStats {
+            id: bdd.get_id(),
+            nodes: bdd.get_size(),
+            levels,
+            max_width: level_widths.iter().copied().max().unwrap_or(0),
+            avg_width: if levels == 0 { 0.0 } else { total_width as f64 / levels as f64 },
+            lhs_densities: bdd.iter_levels().take(levels).map(|level| level.iter_set_lhs().count()).collect(),
+            level_widths,
+            jumping_edges: bdd.jumping_edges().len(),
+        }
+    }
+}
+
+/// Statistics of a `System`, see `System::stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemStats {
+    pub nvar: usize,
+    /// Number of nodes of all the bdds.
+    pub total_nodes: usize,
+    /// Number of levels of all the bdds, the sinks excluded.
+    pub total_levels: usize,
+    /// Number of linear equations in the `LinBank`.
+    pub lin_eqs: usize,
+    /// Statistics of each bdd, sorted by id.
+    pub bdds: Vec<BddStats>,
+    /// For each variable, the number of levels whose lhs contains it.
+    pub var_occurrences: Vec<usize>,
+    /// Rank of the lhs of all the levels of the bdds.
+    pub lhs_rank: usize,
+}
+
+impl SystemStats {
+    /// Return the statistics of the bdd with the most nodes, `None` if the system has no bdd.
+    pub fn biggest_bdd(&self) -> Option<&BddStats> {
+        self.bdds.iter().max_by_key(|bdd| bdd.nodes)
+    }
+
+    /// Return the largest width of all the bdds.
+    pub fn max_width(&self) -> usize {
+        self.bdds.iter().map(|bdd| bdd.max_width).max().unwrap_or(0)
+    }
+
+    /// Return the number of linear dependencies between the levels of the bdds, each of them
+    /// requiring some joins to be resolved.
+    pub fn dependencies(&self) -> usize {
+        self.total_levels - self.lhs_rank
+    }
+
+    /// Return the number of variables in the lhs of at least one level.
+    pub fn active_vars(&self) -> usize {
+        self.var_occurrences.iter().filter(|occurrences| **occurrences > 0).count()
+    }
+
+    /// Return the number of jumping edges of all the bdds.
+    pub fn jumping_edges(&self) -> usize {
+        self.bdds.iter().map(|bdd| bdd.jumping_edges).sum()
+    }
+}
+
+impl fmt::Display for SystemStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} variables ({} active), {} bdds, {} linear equations found",
+                 self.nvar, self.active_vars(), self.bdds.len(), self.lin_eqs)?;
+        writeln!(f, "{} nodes, {} levels, widest level has {} nodes, {} jumping edges",
+                 self.total_nodes, self.total_levels, self.max_width(), self.jumping_edges())?;
+        if let Some(biggest) = self.biggest_bdd() {
+            writeln!(f, "biggest bdd {} has {} nodes", biggest.id, biggest.nodes)?;
+        }
+        write!(f, "lhs rank {}, {} linear dependencies", self.lhs_rank, self.dependencies())
+    }
+}
+
+/// Expected contribution of a `Bdd` to the blowup of the joins, see `System::join_scores`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinScore {
+    pub id: Id,
+    /// Number of variables of the bdd also used by other bdds.
+    pub shared_vars: usize,
+    /// Sum over the levels of their width times the number of their variables shared with other
+    /// bdds.
+    pub score: f64,
+}
+
+impl System {
+    /// Compute the statistics of the system.
+    pub fn stats(&self) -> SystemStats {
+        let nvar = self.get_nvar();
+        let mut ids: Vec<Id> = self.iter_bdds().map(|(id, _)| *id).collect();
+        ids.sort_unstable();
+        let mut bdds = Vec::with_capacity(ids.len());
+        for id in ids {
+            bdds.push(BddStats::new(&self.get_bdd(id).unwrap().borrow()));
+        }
+        let (lhs, _) = self.lhs_matrix();
+        let mut var_occurrences = vec![0; nvar];
+        for row in lhs.iter_rows() {
+            for var in row.iter_set_bits(..) {
+                var_occurrences[var] += 1;
+            }
+        }
+        SystemStats {
+            nvar,
+            total_nodes: bdds.iter().map(|bdd| bdd.nodes).sum(),
+            total_levels: bdds.iter().map(|bdd| bdd.levels).sum(),
+            lin_eqs: self.get_lin_bank_size(),
+            bdds,
+            var_occurrences,
+            lhs_rank: self.lhs_rank(),
+        }
+    }
+
+    /// Score each bdd by its expected contribution to the blowup of the joins, and return the
+    /// scores from the highest to the lowest.
+    ///
+    /// A level whose lhs has no variable used by another bdd never takes part in a linear
+    /// dependency requiring a join, and the wider a level, the more nodes a join at this level creates.
+    /// The score of a bdd is therefore the sum over its levels of their width times their number of
+    /// shared variables. Ties are broken by id.
+    pub fn join_scores(&self) -> Vec<JoinScore> {
+        let nvar = self.get_nvar();
+        let mut bdds_vars = Vec::new();
+        let mut var_bdds = vec![0usize; nvar];
+        for (id, bdd) in self.iter_bdds() {
+            let bdd = bdd.borrow();
+            let mut vars = Vob::from_elem(nvar, false);
+            for level in bdd.iter_levels().take(bdd.get_levels_size().saturating_sub(1)) {
+                for var in level.iter_set_lhs() {
+                    vars.set(var, true);
+                }
+            }
+            for var in vars.iter_set_bits(..) {
+                var_bdds[var] += 1;
+            }
+            bdds_vars.push((*id, vars));
+        }
+        let mut scores: Vec<JoinScore> = bdds_vars.into_iter().map(|(id, vars)| {
+            let bdd = self.get_bdd(id).unwrap().borrow();
+            let score = bdd.iter_levels().take(bdd.get_levels_size().saturating_sub(1))
+                .map(|level| {
+                    let shared = level.iter_set_lhs().filter(|var| var_bdds[*var] > 1).count();
+                    (level.get_nodes_len() * shared) as f64
+                })
+                .sum();
+            JoinScore {
+                id,
+                shared_vars: vars.iter_set_bits(..).filter(|var| var_bdds[*var] > 1).count(),
+                score,
+            }
+        }).collect();
+        scores.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap().then(a.id.cmp(&b.id)));
+        scores
+    }
+}
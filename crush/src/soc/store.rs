@@ -0,0 +1,351 @@
+//! Storage of the nodes of a level.
+//!
+//! The algorithms of a `Bdd` only access the nodes of its levels through the `NodeStore` trait,
+//! such that the storage can be swapped without touching them. `Level` and `Bdd` take the store as
+//! a type parameter, which defaults to `HashNodeStore`.
+//!
+//! A store maps the `Id` of a node to the `Node` itself. The ids are unique within a `System`,
+//! see the `Bdd` module documentation, and a store must never hold two nodes with the same id.
+//!
+//! Two stores are provided:
+//! - `HashNodeStore`, a hash map from the ids to the nodes;
+//! - `ArenaNodeStore`, which keeps the nodes side by side in a `Vec` and only hashes the ids to
+//!   find their slot. Going through the nodes of a level is then a scan of contiguous memory, and
+//!   a node can also be reached by its `NodeHandle`, without hashing its id.
+
+use alloc::vec::Vec;
+use core::{convert::TryFrom, iter, slice};
+use hashbrown::hash_map;
+
+use crate::AHashMap;
+use crate::soc::{Id, node::Node};
+
+/// The default store, a hash map using AHash as its hasher.
+pub type HashNodeStore = AHashMap<Id, Node>;
+
+/// A map from the ids of the nodes of a level to the nodes.
+pub trait NodeStore: Default + Clone {
+    /// Iterator over the (id, node) pairs of the store, in no particular order.
+    type Iter<'a>: Iterator<Item = (&'a Id, &'a Node)> where Self: 'a;
+    /// Mutable iterator over the (id, node) pairs of the store, in no particular order.
+    type IterMut<'a>: Iterator<Item = (&'a Id, &'a mut Node)> where Self: 'a;
+
+    /// Construct an empty store, with room for at least `capacity` nodes.
+    fn with_capacity(capacity: usize) -> Self;
+
+    /// Return a reference to the node with the given id, if any.
+    fn get(&self, id: &Id) -> Option<&Node>;
+
+    /// Return a mutable reference to the node with the given id, if any.
+    fn get_mut(&mut self, id: &Id) -> Option<&mut Node>;
+
+    /// Insert `node` with the given id, returning the node previously stored with that id.
+    fn insert(&mut self, id: Id, node: Node) -> Option<Node>;
+
+    /// Remove the node with the given id, returning it.
+    fn remove(&mut self, id: &Id) -> Option<Node>;
+
+    /// Return true if the store holds a node with the given id.
+    fn contains(&self, id: &Id) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Return the number of nodes of the store.
+    fn len(&self) -> usize;
+
+    /// Return true if the store holds no node.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return an iterator over the (id, node) pairs of the store.
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// Return a mutable iterator over the (id, node) pairs of the store.
+    fn iter_mut(&mut self) -> Self::IterMut<'_>;
+
+    /// Reserve room for at least `additional` more nodes, such that they can be inserted without
+    /// growing the store on the way.
+    fn reserve(&mut self, _additional: usize) {}
+
+    /// Release any memory which is not needed to hold the current nodes. Called when no more
+    /// nodes are expected to be inserted.
+    fn shrink_to_fit(&mut self) {}
+}
+
+impl NodeStore for HashNodeStore {
+    type Iter<'a> = hash_map::Iter<'a, Id, Node>;
+    type IterMut<'a> = hash_map::IterMut<'a, Id, Node>;
+
+    #[inline]
+    fn with_capacity(capacity: usize) -> Self {
+        AHashMap::with_capacity_and_hasher(capacity, Default::default())
+    }
+
+    #[inline]
+    fn get(&self, id: &Id) -> Option<&Node> {
+        AHashMap::get(self, id)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, id: &Id) -> Option<&mut Node> {
+        AHashMap::get_mut(self, id)
+    }
+
+    #[inline]
+    fn insert(&mut self, id: Id, node: Node) -> Option<Node> {
+        AHashMap::insert(self, id, node)
+    }
+
+    #[inline]
+    fn remove(&mut self, id: &Id) -> Option<Node> {
+        AHashMap::remove(self, id)
+    }
+
+    #[inline]
+    fn contains(&self, id: &Id) -> bool {
+        self.contains_key(id)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        AHashMap::len(self)
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> {
+        AHashMap::iter(self)
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        AHashMap::iter_mut(self)
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        AHashMap::reserve(self, additional)
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        AHashMap::shrink_to_fit(self)
+    }
+}
+
+/// The position of a node in an `ArenaNodeStore`, which stays valid as long as the node is in
+/// the store and the store isn't compacted (see `ArenaNodeStore::shrink_to_fit`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeHandle {
+    index: u32,
+    generation: u32,
+}
+
+/// A slot of an `ArenaNodeStore`, vacant when its generation is odd. The generation is bumped
+/// each time the slot is vacated or filled, such that the handles to a removed node are stale.
+#[derive(Debug, Clone)]
+struct Slot {
+    id: Id,
+    node: Node,
+    generation: u32,
+}
+
+impl Slot {
+    #[inline]
+    fn is_vacant(&self) -> bool {
+        self.generation % 2 == 1
+    }
+}
+
+/// A store keeping the nodes contiguously in a `Vec`, see the module documentation.
+///
+/// The slots of the removed nodes are reused by the next insertions, and the nodes are only moved
+/// when the store is compacted by `shrink_to_fit`, which the levels do once they are rebuilt.
+///
+/// The edges still name the nodes by their ids, so the store keeps a map from the ids to the slots
+/// on top of the slots themselves, and takes more memory per node than a `HashNodeStore`.
+#[derive(Debug, Clone, Default)]
+pub struct ArenaNodeStore {
+    slots: Vec<Slot>,
+    /// The indexes of the vacant slots.
+    vacant: Vec<u32>,
+    /// The index of the slot of each node.
+    index: AHashMap<Id, u32>,
+}
+
+impl ArenaNodeStore {
+    /// Return the handle of the node with the given id, if any.
+    pub fn handle(&self, id: &Id) -> Option<NodeHandle> {
+        self.index.get(id).map(|index| NodeHandle { index: *index, generation: self.slots[*index as usize].generation })
+    }
+
+    /// Return the id and node of `handle`, or `None` if the handle is stale.
+    pub fn get_by_handle(&self, handle: NodeHandle) -> Option<(&Id, &Node)> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation && !slot.is_vacant())
+            .map(|slot| (&slot.id, &slot.node))
+    }
+
+    /// Return the id and a mutable reference to the node of `handle`, or `None` if the handle is
+    /// stale.
+    pub fn get_mut_by_handle(&mut self, handle: NodeHandle) -> Option<(&Id, &mut Node)> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation && !slot.is_vacant())
+            .map(|slot| (&slot.id, &mut slot.node))
+    }
+
+    /// Return the number of slots, vacant or not.
+    #[inline]
+    pub fn capacity_used(&self) -> usize {
+        self.slots.len()
+    }
+}
+
+/// Iterator over the nodes of an `ArenaNodeStore`, in the order of their slots.
+pub struct ArenaIter<'a> {
+    slots: slice::Iter<'a, Slot>,
+}
+
+impl<'a> Iterator for ArenaIter<'a> {
+    type Item = (&'a Id, &'a Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.slots.find(|slot| !slot.is_vacant()).map(|slot| (&slot.id, &slot.node))
+    }
+}
+
+/// Mutable iterator over the nodes of an `ArenaNodeStore`, in the order of their slots.
+pub struct ArenaIterMut<'a> {
+    slots: slice::IterMut<'a, Slot>,
+}
+
+impl<'a> Iterator for ArenaIterMut<'a> {
+    type Item = (&'a Id, &'a mut Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.slots.find(|slot| !slot.is_vacant()).map(|slot| (&slot.id, &mut slot.node))
+    }
+}
+
+impl NodeStore for ArenaNodeStore {
+    type Iter<'a> = ArenaIter<'a>;
+    type IterMut<'a> = ArenaIterMut<'a>;
+
+    fn with_capacity(capacity: usize) -> Self {
+        ArenaNodeStore {
+            slots: Vec::with_capacity(capacity),
+            vacant: Vec::new(),
+            index: AHashMap::with_capacity_and_hasher(capacity, Default::default()),
+        }
+    }
+
+    #[inline]
+    fn get(&self, id: &Id) -> Option<&Node> {
+        self.index.get(id).map(|index| &self.slots[*index as usize].node)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, id: &Id) -> Option<&mut Node> {
+        let slots = &mut self.slots;
+        self.index.get(id).map(move |index| &mut slots[*index as usize].node)
+    }
+
+    fn insert(&mut self, id: Id, node: Node) -> Option<Node> {
+        if let Some(index) = self.index.get(&id) {
+            return Some(core::mem::replace(&mut self.slots[*index as usize].node, node));
+        }
+        let index = match self.vacant.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                *slot = Slot { id, node, generation: slot.generation.wrapping_add(1) };
+                index
+            }
+            None => {
+                let index = u32::try_from(self.slots.len()).expect("An ArenaNodeStore holds at most 2^32 nodes");
+                self.slots.push(Slot { id, node, generation: 0 });
+                index
+            }
+        };
+        self.index.insert(id, index);
+        None
+    }
+
+    fn remove(&mut self, id: &Id) -> Option<Node> {
+        let index = self.index.remove(id)?;
+        let slot = &mut self.slots[index as usize];
+        slot.generation = slot.generation.wrapping_add(1);
+        self.vacant.push(index);
+        Some(core::mem::take(&mut slot.node))
+    }
+
+    #[inline]
+    fn contains(&self, id: &Id) -> bool {
+        self.index.contains_key(id)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> {
+        ArenaIter { slots: self.slots.iter() }
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        ArenaIterMut { slots: self.slots.iter_mut() }
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        let additional = additional.saturating_sub(self.vacant.len());
+        self.slots.reserve(additional);
+        self.index.reserve(additional);
+    }
+
+    /// Move the nodes to the front of the slots, in the order of their slots, and release the
+    /// memory of the vacant slots. This invalidates the handles to the moved nodes.
+    fn shrink_to_fit(&mut self) {
+        if !self.vacant.is_empty() {
+            self.slots.retain(|slot| !slot.is_vacant());
+            self.vacant.clear();
+            for (index, slot) in self.slots.iter_mut().enumerate() {
+                if self.index.insert(slot.id, index as u32) != Some(index as u32) {
+                    slot.generation = slot.generation.wrapping_add(2);
+                }
+            }
+        }
+        self.slots.shrink_to_fit();
+        self.vacant.shrink_to_fit();
+        self.index.shrink_to_fit();
+    }
+}
+
+impl iter::FromIterator<(Id, Node)> for ArenaNodeStore {
+    fn from_iter<I: IntoIterator<Item = (Id, Node)>>(nodes: I) -> Self {
+        let mut store = ArenaNodeStore::default();
+        for (id, node) in nodes {
+            store.insert(id, node);
+        }
+        store
+    }
+}
+
+/// An `ArenaNodeStore` is serialized as a map, as a `HashNodeStore` is.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ArenaNodeStore {
+    fn serialize<Ser: serde::Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ArenaNodeStore {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let nodes: HashNodeStore = serde::Deserialize::deserialize(deserializer)?;
+        Ok(nodes.into_iter().collect())
+    }
+}
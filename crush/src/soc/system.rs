@@ -2,12 +2,15 @@
 //! as key), a `LinBank` holding the `LinEq` found during the resolution and an `nvar` which
 //! indicate the total number of variables present initially in the system of equations.
 //!
-//! This object will be mutated through it's different methods (fix, drop, add, swap, absorb, scan)
+//! This object will be mutated through it's different methods (fix, drop, add, swap, absorb, scan,
+//! projection)
 //! in order to remove all the linear dependencies among the levels of the different `Bdd`s so
-//! the solutions to the system of equations it represents can be extracted.
+//! the solutions to the system of equations it represents can be extracted. These mutations can be
+//! rolled back within a transaction, see the `undo` module. The rank of the LHS's of the levels is
+//! tracked along these mutations, see the `lhs` module.
 
-use std::cell::RefCell;
-use std::fmt;
+use core::cell::RefCell;
+use core::fmt;
 use std::io::{self, Error, ErrorKind};
 use std::result::Result;
 
@@ -16,18 +19,37 @@ use vob::Vob;
 use crate::AHashMap;
 use crate::algebra;
 use crate::soc::{
-    bdd::{Bdd, LinEq},
+    bdd::{Bdd, Fingerprinter, LevelSwap, LinEq, PathCursor, ReduceStats, SiftStats},
+    utils::{build_bdd_from_spec, BddSpec, LevelSpec, NodeSpec},
     Id,
 };
 
-/// Cloning a SoC should only happen when the SoC is of a sensible size!
+pub use undo::Savepoint;
+
+mod lhs;
+mod undo;
+
+/// A solution of a `System`: the value of each variable, `None` for a free variable, every value
+/// of which gives a solution.
+pub type Solution = Vec<Option<bool>>;
+
+/// Cloning a SoC is cheap: the clones share the nodes of their levels until they change them,
+/// see the `level` module. Each level changed in one of them is then copied though.
 #[derive(Clone)]
 /// A system of Bdds providing a number of methods to interact safely with the Bdds it contains
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct System {
     bdds: AHashMap<Id, RefCell<Bdd>>,
     nvar: usize,
     lin_bank: LinBank,
+    /// Above the id of any `Bdd` ever pushed, including the ones since joined or popped
+    #[cfg_attr(feature = "serde", serde(default))]
+    next_bdd_id: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    undo: Option<undo::UndoLog>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    lhs: RefCell<lhs::LhsBasis>,
 }
 
 /// `LinBank` is the structure holding the valid linear equations
@@ -75,10 +97,66 @@ pub struct System {
 /// pushing is cancelled
 
 #[derive(Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct LinBank {
     lin_eqs: Vec<LinEq>,
 }
 
+/// A linear dependency resolved on a copy of a `System`, see `System::try_absorb`.
+///
+/// Dropping the trial discards the copy, and only the levels it changed were copied.
+pub struct AbsorbTrial {
+    system: System,
+    bdd: Id,
+    nodes_before: usize,
+    nodes_after: usize,
+    peak_nodes: usize,
+}
+
+impl AbsorbTrial {
+    /// Return the id of the `Bdd` the dependency was resolved in, i.e. the join of the `Bdd`s of
+    /// the dependency.
+    #[inline]
+    pub fn bdd(&self) -> Id {
+        self.bdd
+    }
+
+    /// Return the number of nodes of the `Bdd`s of the dependency before resolving it.
+    #[inline]
+    pub fn nodes_before(&self) -> usize {
+        self.nodes_before
+    }
+
+    /// Return the number of nodes of `bdd` once the dependency is resolved.
+    #[inline]
+    pub fn nodes_after(&self) -> usize {
+        self.nodes_after
+    }
+
+    /// Return the highest number of nodes of `bdd` on the way.
+    #[inline]
+    pub fn peak_nodes(&self) -> usize {
+        self.peak_nodes
+    }
+
+    /// Return the ratio of `nodes_after` to `nodes_before`.
+    pub fn growth(&self) -> f64 {
+        self.nodes_after as f64 / self.nodes_before.max(1) as f64
+    }
+
+    /// Return the `System` with the dependency resolved.
+    #[inline]
+    pub fn system(&self) -> &System {
+        &self.system
+    }
+
+    /// Return the `System` with the dependency resolved, to keep it.
+    #[inline]
+    pub fn into_system(self) -> System {
+        self.system
+    }
+}
+
 impl System {
     /// Construct a new System with default parameters
     pub fn new() -> System {
@@ -104,7 +182,9 @@ impl System {
 
     /// Set `nvar` of the `System`
     pub fn set_nvar(&mut self, nvar: usize) {
+        self.record("set_nvar", Some(&[]), false);
         self.nvar = nvar;
+        self.invalidate_lhs();
     }
 
     /// Get `nvar` of the `System`
@@ -129,10 +209,23 @@ impl System {
                 "A Bdd with the same id is already in the system",
             ));
         }
-        self.bdds.insert(bdd.get_id(), RefCell::new(bdd));
+        let bdd_id = bdd.get_id();
+        self.record("push_bdd", Some(&[bdd_id]), false);
+        self.bdds.insert(bdd_id, RefCell::new(bdd));
+        self.insert_lhs(bdd_id);
+        self.next_bdd_id = self.next_bdd_id.max(*bdd_id + 1);
         Ok(())
     }
 
+    /// Build a `Bdd` from `levels` under an id no `Bdd` of the `System` ever had, push it and
+    /// return its id.
+    fn push_new_bdd(&mut self, levels: Vec<LevelSpec>) -> Result<Id, Error> {
+        let bdd_id = Id::new(self.next_bdd_id);
+        let mut spec = BddSpec::new(bdd_id, levels);
+        self.push_bdd(build_bdd_from_spec(&mut spec, self.nvar))?;
+        Ok(bdd_id)
+    }
+
     /// Return a reference to the `Bdd` which `id` is equal to `bdd_id`.
     ///
     /// Will return an `Error` if there is no `Bdd` matching this condition.
@@ -151,6 +244,7 @@ impl System {
     ///
     /// Will return an `Error` if one `Id` in `ids` doesn't match any `Bdd` in the `system`.
     pub fn split(&mut self, ids: &[Id]) -> Result<System, Error> {
+        self.record("split", Some(ids), false);
         let mut bdds = Vec::with_capacity(ids.len());
         for id in ids {
             bdds.push(self.pop_bdd(*id)?);
@@ -165,6 +259,10 @@ impl System {
     ///
     /// Will return an error if one of the `Bdd` has a different `nvar` from the `System`.
     pub fn merge(&mut self, system: &mut System) -> Result<(), Error> {
+        if self.in_transaction() {
+            let ids: Vec<Id> = self.bdds.keys().chain(system.bdds.keys()).copied().collect();
+            self.record("merge", Some(&ids), true);
+        }
         for bdd in system.drain_bdds() {
             // TODO -> error handling should take into account middle crash and rollback system to its initial state
             // to avoid half merging if one bdd have a different nvar
@@ -192,6 +290,9 @@ impl System {
                 "bdd_1_id is equal to bdd_2_id",
             ));
         }
+        self.get_bdd(bdd_1_id)?;
+        self.get_bdd(bdd_2_id)?;
+        self.record("join_bdds", Some(&[bdd_1_id, bdd_2_id]), false);
         let bdd_1 = self.get_bdd(bdd_1_id)?;
         let bdd_2 = self.get_bdd(bdd_2_id)?;
         let sink_level_id = bdd_1.borrow().get_sink_level_index();
@@ -213,6 +314,31 @@ impl System {
         level_index_above: usize,
         level_index_below: usize,
     ) -> Result<(), Error> {
+        self.check_swap(bdd_id, level_index_above, level_index_below)?;
+        self.record("swap", Some(&[bdd_id]), false);
+        self.get_bdd(bdd_id)?.borrow_mut().swap(level_index_above, level_index_below);
+        Ok(())
+    }
+
+    /// Parallel version of `swap`, see `Bdd::par_swap`. The work runs in the current rayon thread
+    /// pool.
+    ///
+    /// Returns the same `Error`s as `swap`.
+    #[cfg(feature = "parallel")]
+    pub fn par_swap(
+        &mut self,
+        bdd_id: Id,
+        level_index_above: usize,
+        level_index_below: usize,
+    ) -> Result<(), Error> {
+        self.check_swap(bdd_id, level_index_above, level_index_below)?;
+        self.record("swap", Some(&[bdd_id]), false);
+        self.get_bdd(bdd_id)?.borrow_mut().par_swap(level_index_above, level_index_below);
+        Ok(())
+    }
+
+    /// Check the arguments of `swap`.
+    fn check_swap(&self, bdd_id: Id, level_index_above: usize, level_index_below: usize) -> Result<(), Error> {
         if level_index_below != level_index_above + 1 {
             return Err(Error::new(
                 ErrorKind::InvalidData,
@@ -223,10 +349,19 @@ impl System {
         if level_index_below >= bdd.borrow().get_sink_level_index() {
             return Err(Error::new(ErrorKind::InvalidData, "Out of range of levels"));
         }
-        bdd.borrow_mut().swap(level_index_above, level_index_below);
         Ok(())
     }
 
+    /// Exchange the levels `index` and `index + 1` of the `Bdd` of id `bdd_id`, see
+    /// `Bdd::swap_adjacent_levels`.
+    ///
+    /// Returns an `Error` if `bdd_id` is not found in the `System`, or if the `Bdd` refuses the swap.
+    pub fn swap_adjacent_levels(&mut self, bdd_id: Id, index: usize) -> Result<LevelSwap, Error> {
+        self.get_bdd(bdd_id)?;
+        self.record("swap_adjacent_levels", Some(&[bdd_id]), false);
+        self.get_bdd(bdd_id)?.borrow_mut().swap_adjacent_levels(index)
+    }
+
     /// Performs a `add` operation on the `Bdd` with the `id` specified between the 2 level indexes given.
     ///
     /// Returns an `Error` if `level_index_above` is not directly above `level_index_below`, if
@@ -237,6 +372,31 @@ impl System {
         level_index_above: usize,
         level_index_below: usize,
     ) -> Result<(), Error> {
+        self.check_add(bdd_id, level_index_above, level_index_below)?;
+        self.record("add", Some(&[bdd_id]), false);
+        self.get_bdd(bdd_id)?.borrow_mut().add(level_index_above, level_index_below);
+        Ok(())
+    }
+
+    /// Parallel version of `add`, see `Bdd::par_add`. The work runs in the current rayon thread
+    /// pool.
+    ///
+    /// Returns the same `Error`s as `add`.
+    #[cfg(feature = "parallel")]
+    pub fn par_add(
+        &mut self,
+        bdd_id: Id,
+        level_index_above: usize,
+        level_index_below: usize,
+    ) -> Result<(), Error> {
+        self.check_add(bdd_id, level_index_above, level_index_below)?;
+        self.record("add", Some(&[bdd_id]), false);
+        self.get_bdd(bdd_id)?.borrow_mut().par_add(level_index_above, level_index_below);
+        Ok(())
+    }
+
+    /// Check the arguments of `add`.
+    fn check_add(&self, bdd_id: Id, level_index_above: usize, level_index_below: usize) -> Result<(), Error> {
         if level_index_above >= level_index_below {
             return Err(Error::new(
                 ErrorKind::InvalidData,
@@ -254,7 +414,6 @@ impl System {
                 ),
             ));
         }
-        bdd.borrow_mut().add(level_index_above, level_index_below);
         Ok(())
     }
 
@@ -274,10 +433,59 @@ impl System {
                 ),
             ));
         }
+        self.record("absorb", Some(&[bdd_id]), false);
+        let bdd = self.get_bdd(bdd_id)?;
+        let lhs = bdd.borrow().get_lhs_level(level_index);
         bdd.borrow_mut().absorb(level_index, edge);
+        self.remove_lhs(&[lhs]);
         Ok(())
     }
 
+    /// Resolve the linear dependency of the levels `levels` of the join of `bdds`, as the default
+    /// `Solver::resolve` does, on a clone of the `System`, and return the clone along with the
+    /// number of nodes before, after and at the peak. `self` is left untouched.
+    ///
+    /// The `Bdd`s are joined in the order of `bdds`, and `levels` are the indexes of the levels of
+    /// the dependency in the joined `Bdd`, in increasing order, as returned by
+    /// `Dependency::best_join_order`. As the clone shares the levels of `self` until they change,
+    /// only the levels touched by the resolution are copied.
+    ///
+    /// Returns an `Error` if a `Bdd` of `bdds` is not found in the `System`, or if there are less
+    /// than two `levels`, not in increasing order or out of the range of the joined `Bdd`.
+    pub fn try_absorb(&self, bdds: &[Id], levels: &[usize]) -> Result<AbsorbTrial, Error> {
+        if levels.len() < 2 || levels.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("A dependency needs at least two levels in increasing order, got {:?}", levels),
+            ));
+        }
+        let root = *bdds.first().ok_or_else(|| Error::new(ErrorKind::InvalidInput, "No Bdd to join"))?;
+        let mut nodes_before = 0;
+        for id in bdds {
+            nodes_before += self.get_bdd(*id)?.borrow().get_size();
+        }
+        let mut system = self.clone();
+        system.undo = None;
+        for id in bdds.iter().skip(1) {
+            system.join_bdds(root, *id)?;
+        }
+        let size = |system: &System| system.bdds[&root].borrow().get_size();
+        let mut peak_nodes = size(&system);
+        for i in (0..levels.len() - 1).rev() {
+            for j in (levels[i] + 1..levels[i + 1]).rev() {
+                system.swap(root, j, j + 1)?;
+            }
+            system.add(root, levels[i], levels[i] + 1)?;
+            if i != 0 {
+                system.swap(root, levels[i], levels[i] + 1)?;
+            }
+            peak_nodes = peak_nodes.max(size(&system));
+        }
+        system.absorb(root, levels[0] + 1, false)?;
+        let nodes_after = size(&system);
+        Ok(AbsorbTrial { system, bdd: root, nodes_before, nodes_after, peak_nodes })
+    }
+
     /// Performs a `drop` operation on the `Bdd` with the `id` specified on `level_index`.
     ///
     /// Returns an `Error` if `level_index` is out of the range of the levels the `Bdd`, or
@@ -294,10 +502,44 @@ impl System {
                 ),
             ));
         }
+        self.record("drop", Some(&[bdd_id]), false);
+        let bdd = self.get_bdd(bdd_id)?;
+        let lhs = bdd.borrow().get_lhs_level(level_index);
         bdd.borrow_mut().drop(level_index);
+        self.remove_lhs(&[lhs]);
         Ok(())
     }
 
+    /// Reduce the `Bdd` of id `bdd_id`, see `Bdd::reduce`.
+    ///
+    /// Returns an `Error` if `bdd_id` is not found in the `System`.
+    pub fn reduce(&mut self, bdd_id: Id) -> Result<ReduceStats, Error> {
+        self.get_bdd(bdd_id)?;
+        self.record("reduce", Some(&[bdd_id]), false);
+        Ok(self.get_bdd(bdd_id)?.borrow_mut().reduce())
+    }
+
+    /// Reorder the levels of the `Bdd` with the `id` specified by sifting, see `Bdd::sift_levels`.
+    ///
+    /// Returns an `Error` if `bdd_id` is not found in the `System`.
+    pub fn sift(&mut self, bdd_id: Id) -> Result<SiftStats, Error> {
+        self.get_bdd(bdd_id)?;
+        self.record("sift", Some(&[bdd_id]), false);
+        Ok(self.get_bdd(bdd_id)?.borrow_mut().sift_levels())
+    }
+
+    /// Reorder by sifting the levels of every `Bdd` of more than `threshold` nodes, see
+    /// `Bdd::sift_levels`, and return their ids along with the result of the sifting.
+    pub fn sift_above(&mut self, threshold: usize) -> Vec<(Id, SiftStats)> {
+        self.record("sift_above", None, false);
+        let mut sifted: Vec<(Id, SiftStats)> = self.bdds.iter()
+            .filter(|(_, bdd)| bdd.borrow().get_size() > threshold)
+            .map(|(id, bdd)| (*id, bdd.borrow_mut().sift_levels()))
+            .collect();
+        sifted.sort_by_key(|(id, _)| *id);
+        sifted
+    }
+
     /// Fix the of a linear combination of variables in the `System` by adding a new LinEq to the LinBank.
     ///
     /// `lhs` contain all the variable of the left hand side of the equation
@@ -315,6 +557,7 @@ impl System {
             lhs_as_vob.set(*var, true);
         }
         let lin_eq = LinEq::new(lhs_as_vob, rhs);
+        self.record("fix", None, true);
         match self.push_lin_eq_to_lin_bank(lin_eq) {
             Some(_) => Ok(()),
             None => Err(Error::new(
@@ -324,17 +567,143 @@ impl System {
         }
     }
 
+    /// Fix the variable `var` to `value` in the `System`, substituting `value` for `var` in the
+    /// LHS's of all the `Bdd`s: `var` is removed from the LHS's containing it, and their edges
+    /// are flipped if `value` is true. The equation is kept in the `LinBank`, see `System::fix`.
+    ///
+    /// Returns an `Error` if `var` is out of the range of the variables of the `System`, or if the
+    /// `LinBank` already determines `var`.
+    pub fn fix_variable(&mut self, var: usize, value: bool) -> Result<(), Error> {
+        if var >= self.nvar {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Out of range of variables : trying to fix {}, nvar is {}", var, self.nvar),
+            ));
+        }
+        self.fix(vec![var], value)
+    }
+
+    /// Project the variables `vars` out of the `System`, such that its solutions are those of the
+    /// other variables extended by some value of `vars`.
+    ///
+    /// For each variable, the `Bdd`s with a level whose LHS contains it are joined (into the one
+    /// of the lowest id), the topmost of these levels is added to the others, and the level left
+    /// with the variable is dropped. As the variable appears in no other level, the value of the
+    /// LHS of the dropped level can be anything.
+    ///
+    /// Returns an `Error` if a variable of `vars` is out of the range of the variables of the
+    /// `System` or appears in the `LinBank`, in which case the `System` is left untouched.
+    pub fn project_out(&mut self, vars: &[usize]) -> Result<(), Error> {
+        for var in vars {
+            if *var >= self.nvar {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Out of range of variables : trying to project out {}, nvar is {}", var, self.nvar),
+                ));
+            }
+            if self.lin_bank.lin_eqs.iter().any(|lin_eq| lin_eq.get_lhs().get(*var) == Some(true)) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Can't project out {}, it appears in the LinBank", var),
+                ));
+            }
+        }
+        for var in vars {
+            let mut ids: Vec<Id> = self.bdds
+                .iter()
+                .filter(|(_, bdd)| bdd.borrow().get_lhs().iter().any(|lhs| lhs.get(*var) == Some(true)))
+                .map(|(id, _)| *id)
+                .collect();
+            ids.sort_unstable();
+            let root = match ids.first() {
+                Some(root) => *root,
+                None => continue,
+            };
+            for id in ids.iter().skip(1) {
+                self.join_bdds(root, *id)?;
+            }
+            // Adding the topmost level moves it just above the level added to, still the topmost
+            loop {
+                let levels: Vec<usize> = self.bdds[&root]
+                    .borrow()
+                    .get_lhs()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, lhs)| lhs.get(*var) == Some(true))
+                    .map(|(index, _)| index)
+                    .collect();
+                if levels.len() < 2 {
+                    if let Some(level) = levels.first() {
+                        self.drop(root, *level)?;
+                    }
+                    break;
+                }
+                self.add(root, levels[0], levels[1])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Restrict the solutions of the `System` to those satisfying `vars[0] + vars[1] + ... = rhs`,
+    /// by pushing a `Bdd` of one level of LHS `vars` whose source only has its `rhs` edge to the
+    /// sink. A variable appearing twice in `vars` cancels out. Return the id of the new `Bdd`.
+    ///
+    /// The new `Bdd` gets an id no `Bdd` of the `System` ever had, including the ones joined into
+    /// another one, as the ids of the nodes depend on it (see the `bdd` module).
+    ///
+    /// Returns an `Error` if a variable of `vars` is out of the range of the variables of the
+    /// `System`, or if the LHS is zero.
+    pub fn add_linear_constraint(&mut self, vars: &[usize], rhs: bool) -> Result<Id, Error> {
+        if let Some(var) = vars.iter().find(|var| **var >= self.nvar) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Out of range of variables : trying to constrain {}, nvar is {}", var, self.nvar),
+            ));
+        }
+        let mut lhs = Vob::from_elem(self.nvar, false);
+        for var in vars {
+            let set = lhs[*var];
+            lhs.set(*var, !set);
+        }
+        if lhs.iter_set_bits(..).next().is_none() {
+            return Err(Error::new(ErrorKind::InvalidInput, "The LHS of the constraint is zero"));
+        }
+        // The source (node 1) leads to the sink (node 2) along its `rhs` edge only
+        let (e0, e1) = if rhs { (Id::new(0), Id::new(2)) } else { (Id::new(2), Id::new(0)) };
+        self.push_new_bdd(vec![
+            LevelSpec::new(lhs.iter_set_bits(..).map(|var| var as i64).collect(), vec![NodeSpec::new(Id::new(1), e0, e1)]),
+            LevelSpec::new(Vec::new(), vec![NodeSpec::new(Id::new(2), Id::new(0), Id::new(0))]),
+        ])
+    }
+
     /// Scan the `Bdd` of `bdd_id` for `LinEq` and push the `LinEq`s found to the `LinBank`
     ///
     /// Returns the number of `LinEq` correctly absorbed or an `Error` if `bdd_id` is not in the
     /// `System`.
     pub fn scan_absorb_lin_eqs(&mut self, bdd_id: Id) -> Result<usize, io::Error> {
         let mut absorbed = 0;
+        self.get_bdd(bdd_id)?;
+        self.record("scan_absorb_lin_eqs", None, true);
         let bdd = self.get_bdd(bdd_id)?;
         let mut lin_eqs = bdd.borrow_mut().scan_absorb_lin_eq();
-        for lin_eq in lin_eqs.drain(..) {
-            if self.push_lin_eq_to_lin_bank(lin_eq).is_some() {
-                absorbed += 1;
+        // The LHS's of the absorbed levels, as substituted by the equations before them: each
+        // leaves the tracked span when the `LinBank` takes it as is, see the `lhs` module
+        let mut removed: Vec<Vob> = lin_eqs.iter().map(|lin_eq| lin_eq.get_lhs()).collect();
+        for (i, lin_eq) in lin_eqs.drain(..).enumerate() {
+            match self.push_lin_eq_to_lin_bank(lin_eq) {
+                Some(eq) => {
+                    absorbed += 1;
+                    let lhs = eq.get_lhs();
+                    if !lhs.iter_set_bits(..).eq(removed[i].iter_set_bits(..)) {
+                        self.invalidate_lhs();
+                    }
+                    let var = eq.get_lhs_max_set_bit().unwrap();
+                    for other in removed.iter_mut().skip(i + 1).filter(|other| other.get(var) == Some(true)) {
+                        other.xor(&lhs);
+                    }
+                }
+                None if removed[i].iter_set_bits(..).next().is_some() => self.invalidate_lhs(),
+                None => {}
             }
         }
         Ok(absorbed)
@@ -349,6 +718,7 @@ impl System {
         match self.lin_bank.push_lin_eq(lin_eq) {
             Some(eq) => {
                 let var = eq.get_lhs_max_set_bit().unwrap();
+                self.lhs.get_mut().substitute(&eq.get_lhs(), self.nvar);
                 for bdd in self.bdds.iter_mut() {
                     bdd.1.borrow_mut().replace_var_in_bdd(var, &eq);
                 }
@@ -358,6 +728,14 @@ impl System {
         }
     }
 
+    /// Give the nodes of every `Bdd` dense ids, see `Bdd::renumber`.
+    pub fn renumber(&mut self) {
+        self.record("renumber", None, false);
+        for bdd in self.bdds.values() {
+            bdd.borrow_mut().renumber();
+        }
+    }
+
     /// Get the number of nodes inside the `System`.
     pub fn get_size(&self) -> usize {
         self.bdds
@@ -366,12 +744,14 @@ impl System {
     }
 
     /// Iterate over the `bdds` of the `System`.
-    pub fn iter_bdds(&self) -> std::collections::hash_map::Iter<Id, RefCell<Bdd>> {
+    pub fn iter_bdds(&self) -> hashbrown::hash_map::Iter<'_, Id, RefCell<Bdd>> {
         self.bdds.iter()
     }
 
     /// Drain over the `bdds` of the `System`.
-    pub fn drain_bdds(&mut self) -> std::collections::hash_map::Drain<Id, RefCell<Bdd>> {
+    pub fn drain_bdds(&mut self) -> hashbrown::hash_map::Drain<'_, Id, RefCell<Bdd>> {
+        self.record("drain_bdds", None, false);
+        self.lhs.get_mut().clear();
         self.bdds.drain()
     }
 
@@ -379,8 +759,13 @@ impl System {
     ///
     /// Return an Error if `bdd_id` is not in the `System`.
     pub fn pop_bdd(&mut self, bdd_id: Id) -> Result<Bdd, io::Error> {
+        self.record("pop_bdd", Some(&[bdd_id]), false);
         match self.bdds.remove(&bdd_id) {
-            Some(bdd_ref) => Ok(bdd_ref.into_inner()),
+            Some(bdd_ref) => {
+                let bdd = bdd_ref.into_inner();
+                self.remove_lhs(&bdd.get_lhs());
+                Ok(bdd)
+            }
             None => Err(Error::new(
                 ErrorKind::InvalidData,
                 format!("id {} not present in system", *bdd_id),
@@ -397,53 +782,165 @@ impl System {
         system_lhs
     }
 
+    /// Return the lhs of the levels of all the `Bdd`s stacked as the rows of a `Matrix` of `nvar`
+    /// columns, along with the id of the `Bdd` and the index of the level of each row.
+    ///
+    /// The `Bdd`s are taken by increasing id and their levels from the top, the sinks excluded,
+    /// such that the rows of a `Bdd` are contiguous. A row of
+    /// `algebra::extract_linear_dependencies` applied to the matrix is a set of rows, the levels
+    /// to add together to resolve the dependency being found through the index.
+    pub fn lhs_matrix(&self) -> (algebra::Matrix, Vec<(Id, usize)>) {
+        let mut ids: Vec<Id> = self.bdds.keys().copied().collect();
+        ids.sort_unstable();
+        let mut rows = Vec::new();
+        let mut index = Vec::new();
+        for id in ids {
+            let bdd = self.bdds[&id].borrow();
+            for (level_index, level) in bdd.iter_levels().take(bdd.get_levels_size() - 1).enumerate() {
+                let mut lhs = level.get_lhs();
+                lhs.resize(self.nvar, false);
+                rows.push(lhs);
+                index.push((id, level_index));
+            }
+        }
+        (matrix![rows], index)
+    }
+
     /// Return the solutions to the `System` using the `LinBank` and the paths in the
     /// remaining BDDs. If multiple BDDs are still in the system it will join all of them to
     /// find the solutions.
     ///
     /// Will use the `algebra::solve_linear_system` to find the different solutions.
-    pub fn calculate_solutions(&mut self) -> Vec<Vec<Option<bool>>> {
-        let keys: Vec<Id> = self.bdds.keys().cloned().collect();
-        let remaining_id = match keys.len() {
+    pub fn calculate_solutions(&mut self) -> Vec<Solution> {
+        let remaining_id = match self.join_remaining_bdds() {
+            Some(id) => id,
             // everything in linbank
-            0 => {
-                let lhs = self.lin_bank.get_lhs();
-                let rhs = self.lin_bank.get_rhs();
-                return vec![algebra::solve_linear_system(matrix![lhs], rhs)];
-            }
-            // only one BDD left
-            1 => keys[0],
-            // multiple BDD, join everything first
-            _ => {
-                for key in 1..keys.len() {
-                    self.join_bdds(keys[0], keys[key]).unwrap();
-                }
-                keys[0]
-            }
+            None => return vec![self.solve_with_path(Vec::new())],
         };
         let paths = self
             .get_bdd(remaining_id)
             .unwrap()
             .borrow()
             .get_all_valid_path();
-        let mut solutions = Vec::new();
-        for path in paths {
-            let mut lin_bank = self.lin_bank.clone();
-            for eq in path {
-                lin_bank.push_lin_eq(eq);
+        paths
+            .into_iter()
+            .map(|path| self.solve_with_path(path))
+            .collect()
+    }
+
+    /// Resumable version of `calculate_solutions`: return at most `limit` solutions following
+    /// the position given by `cursor`, along with a cursor pointing past the last returned solution.
+    ///
+    /// The remaining BDDs are joined in increasing order of their `Id`, such that two copies of the
+    /// same `System` enumerate their solutions in the same order. A cursor may therefore be stored,
+    /// or sent to another worker holding a copy of the `System`, to continue the enumeration there.
+    ///
+    /// Returns an `Error` if the cursor was not produced by this `System`.
+    pub fn calculate_solutions_from(
+        &mut self,
+        cursor: &PathCursor,
+        limit: usize,
+    ) -> Result<(Vec<Solution>, PathCursor), Error> {
+        let remaining_id = match self.join_remaining_bdds() {
+            Some(id) => id,
+            None => {
+                // everything in linbank, there is exactly one solution, enumerated by the start
+                if limit == 0 {
+                    return Ok((Vec::new(), cursor.clone()));
+                }
+                if *cursor != PathCursor::start() {
+                    return Ok((Vec::new(), PathCursor::exhausted()));
+                }
+                return Ok((
+                    vec![self.solve_with_path(Vec::new())],
+                    PathCursor::exhausted(),
+                ));
             }
-            solutions.push(algebra::solve_linear_system(
-                matrix![lin_bank.get_lhs()],
-                lin_bank.get_rhs(),
-            ));
+        };
+        let (paths, next) = self
+            .get_bdd(remaining_id)?
+            .borrow()
+            .valid_paths_from(cursor, limit)?;
+        let solutions = paths
+            .into_iter()
+            .map(|path| self.solve_with_path(path))
+            .collect();
+        Ok((solutions, next))
+    }
+
+    /// Join all the `Bdd`s of the `System` into the one with the lowest `Id`, and return that `Id`.
+    ///
+    /// Returns `None` if there are no `Bdd`s left in the `System`.
+    fn join_remaining_bdds(&mut self) -> Option<Id> {
+        let mut keys: Vec<Id> = self.bdds.keys().cloned().collect();
+        keys.sort();
+        let root = *keys.first()?;
+        for key in keys.iter().skip(1) {
+            self.join_bdds(root, *key).unwrap();
         }
-        solutions
+        Some(root)
+    }
+
+    /// Solve the `LinBank` extended with the equations of `path`.
+    fn solve_with_path(&self, path: Vec<LinEq>) -> Solution {
+        let mut lin_bank = self.lin_bank.clone();
+        for eq in path {
+            lin_bank.push_lin_eq(eq);
+        }
+        algebra::solve_linear_system(matrix![lin_bank.get_lhs()], lin_bank.get_rhs())
+    }
+
+    /// Return true if the given assignment of the `nvar` variables satisfies the `LinBank` and is
+    /// accepted by every `Bdd` of the `System` (see `Bdd::accepts`).
+    pub fn is_solution(&self, assignment: &Vob) -> bool {
+        self.lin_bank.lin_eqs.iter().all(|lin_eq| {
+            lin_eq
+                .get_lhs()
+                .iter_set_bits(..)
+                .fold(false, |acc, var| acc ^ assignment.get(var).unwrap_or(false))
+                == lin_eq.get_rhs()
+        }) && self.bdds.values().all(|bdd| bdd.borrow().accepts(assignment))
     }
 
     /// Return the number of `LinEq` in the `LinBank`.
     pub fn get_lin_bank_size(&self) -> usize {
         self.lin_bank.lin_eqs.len()
     }
+
+    /// Return a fingerprint of the `System`, combining the fingerprints of its `Bdd`s and of the
+    /// `LinEq`s of its `LinBank` (see `Bdd::fingerprint` and `LinEq::fingerprint`) regardless of
+    /// their order.
+    ///
+    /// A `LinEq` counts as the single level `Bdd` holding it, so a `System` written with
+    /// `io::print_checkpoint_to_file` and parsed back has the same fingerprint as the original.
+    pub fn fingerprint(&self) -> u64 {
+        let mut parts: Vec<u64> = self
+            .bdds
+            .values()
+            .map(|bdd| bdd.borrow().fingerprint())
+            .chain(self.lin_bank.lin_eqs.iter().map(|lin_eq| lin_eq.fingerprint()))
+            .collect();
+        parts.sort_unstable();
+        let mut fingerprinter = Fingerprinter::new();
+        fingerprinter.write(self.nvar as u64);
+        for part in parts {
+            fingerprinter.write(part);
+        }
+        fingerprinter.finish()
+    }
+
+    /// Return an iterator over the `LinEq` of the `LinBank`.
+    pub fn iter_lin_eqs(&self) -> core::slice::Iter<'_, LinEq> {
+        self.lin_bank.lin_eqs.iter()
+    }
+
+    /// Replace the `LinEq` of the `LinBank` by `lin_eqs`, as they are, without absorbing them in
+    /// the bdds. They must come from the `LinBank` of a system in the same state, e.g. a snapshot
+    /// of it (see the `binary` module).
+    pub(crate) fn set_lin_eqs(&mut self, lin_eqs: Vec<LinEq>) {
+        self.record("set_lin_eqs", Some(&[]), true);
+        self.lin_bank.lin_eqs = lin_eqs;
+    }
 }
 
 impl fmt::Debug for System {
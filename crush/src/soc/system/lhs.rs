@@ -0,0 +1,261 @@
+//! Incremental tracking of the rank of the LHS's of the levels of a `System`, such that the
+//! solvers can tell how many linear dependencies are left without a Gaussian elimination of all
+//! the LHS's after each operation.
+//!
+//! The `System` keeps a basis of the span of its LHS's, in echelon form: at most one row of the
+//! basis per highest set bit. The operations which only move the LHS's around or replace a LHS by
+//! a sum of LHS's leave the span unchanged, and don't touch the basis: joins, swaps, adds, the
+//! absorption of a level of LHS zero, reductions and siftings. The other ones update the basis in
+//! place:
+//!
+//! - pushing a `Bdd` reduces each new LHS by the basis, a XOR of a row per row of the basis at
+//!   most, and adds it to the basis if it is independent;
+//! - substituting a variable by a linear equation pushed to the `LinBank` (`System::fix`,
+//!   `System::scan_absorb_lin_eqs`) maps the span along the equation onto the LHS's without the
+//!   variable: only the rows of the basis with the variable change, and are reduced again. The
+//!   LHS of a level absorbed by `System::scan_absorb_lin_eqs` is mapped to zero by the
+//!   substitution of its own equation, and leaves the span with it;
+//! - removing a LHS (drops, absorptions and removals of `Bdd`s) intersects the span with the
+//!   LHS's without a variable of the removed LHS which no other LHS holds, if there is one, as
+//!   after the resolution of an independency. Finding it takes a scan of the other LHS's, but no
+//!   elimination. Removing a `Bdd` of no levels, as the solvers do once its last level is
+//!   absorbed, leaves the span unchanged.
+//!
+//! The removals of a LHS with no variable of its own, the changes of `nvar`, the rollbacks and
+//! the changes through `System::modify_bdd` invalidate the basis, which is rebuilt on the next
+//! query.
+//!
+//! The `Bdd`s modified through `System::get_bdd` are not tracked: changing a LHS that way must be
+//! followed by `System::invalidate_lhs`.
+
+use core::cell::RefCell;
+
+use vob::Vob;
+
+use crate::algebra::{Bits, LhsBits};
+use crate::soc::Id;
+
+use super::System;
+
+/// A basis of the span of the LHS's of a `System`, see the `lhs` module documentation.
+#[derive(Debug, Default, Clone)]
+pub(super) struct LhsBasis {
+    /// The rows of the basis by their highest set bit, or `None` until rebuilt.
+    pivots: Option<Vec<Option<Bits>>>,
+    rank: usize,
+}
+
+impl LhsBasis {
+    /// Forget the basis, to be rebuilt on the next query.
+    pub(super) fn invalidate(&mut self) {
+        self.pivots = None;
+        self.rank = 0;
+    }
+
+    /// Forget all the LHS's, the `System` being left with none.
+    pub(super) fn clear(&mut self) {
+        if let Some(pivots) = self.pivots.as_mut() {
+            pivots.iter_mut().for_each(|pivot| *pivot = None);
+            self.rank = 0;
+        }
+    }
+
+    /// Add `lhs`, of `nvar` bits, to the span, if the basis is up to date.
+    pub(super) fn insert(&mut self, lhs: &Vob, nvar: usize) {
+        if self.pivots.is_some() {
+            self.insert_row(to_bits(lhs, nvar));
+        }
+    }
+
+    /// Substitute the highest variable of `lhs`, of `nvar` bits, by the others in the span: each
+    /// row of the basis with the variable is added `lhs` and reduced again.
+    pub(super) fn substitute(&mut self, lhs: &Vob, nvar: usize) {
+        let pivots = match self.pivots.as_mut() {
+            Some(pivots) => pivots,
+            None => return,
+        };
+        let lhs = to_bits(lhs, nvar);
+        let var = match lhs.max_set_bit() {
+            Some(var) => var,
+            None => return,
+        };
+        let touched: Vec<Bits> = pivots.iter_mut()
+            .filter(|pivot| pivot.as_ref().is_some_and(|row| row.get_bit(var)))
+            .filter_map(Option::take)
+            .collect();
+        self.rank -= touched.len();
+        for mut row in touched {
+            row.xor_assign(&lhs);
+            self.insert_row(row);
+        }
+    }
+
+    /// Intersect the span with the LHS's where `var` is 0, after the removal of the only LHS
+    /// holding `var`. The row of the basis with `var` of the lowest highest bit is added to the
+    /// others with `var`, which keeps their highest bit, and leaves the basis.
+    pub(super) fn remove_var(&mut self, var: usize) {
+        let pivots = match self.pivots.as_mut() {
+            Some(pivots) => pivots,
+            None => return,
+        };
+        let mut with_var = pivots.iter_mut()
+            .filter(|pivot| pivot.as_ref().is_some_and(|row| row.get_bit(var)));
+        let removed = match with_var.next().and_then(Option::take) {
+            Some(removed) => removed,
+            None => return,
+        };
+        for row in with_var.flatten() {
+            row.xor_assign(&removed);
+        }
+        self.rank -= 1;
+    }
+
+    /// Reduce `row` by the basis, and add it to the basis if it is independent.
+    fn insert_row(&mut self, mut row: Bits) {
+        let pivots = match self.pivots.as_mut() {
+            Some(pivots) => pivots,
+            None => return,
+        };
+        while let Some(bit) = row.max_set_bit() {
+            match &pivots[bit] {
+                Some(pivot) => row.xor_assign(pivot),
+                None => {
+                    pivots[bit] = Some(row);
+                    self.rank += 1;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Return `lhs` resized to `nvar` bits, as a row of the basis.
+fn to_bits(lhs: &Vob, nvar: usize) -> Bits {
+    let mut lhs = lhs.clone();
+    lhs.resize(nvar, false);
+    Bits::from_vob(&lhs)
+}
+
+impl System {
+    /// Return the rank of the LHS's of the levels of all the `Bdd`s, the sinks excluded: the
+    /// number of levels left once all the linear dependencies are absorbed. See the `lhs` module
+    /// documentation.
+    pub fn lhs_rank(&self) -> usize {
+        self.up_to_date_lhs().borrow().rank
+    }
+
+    /// Return the number of levels of all the `Bdd`s, the sinks excluded.
+    pub fn lhs_rows(&self) -> usize {
+        self.bdds.values().map(|bdd| bdd.borrow().get_sink_level_index()).sum()
+    }
+
+    /// Return the number of independent linear dependencies among the LHS's of the levels, the
+    /// number of levels to absorb before none is left.
+    pub fn lhs_dependency_count(&self) -> usize {
+        self.lhs_rows() - self.lhs_rank()
+    }
+
+    /// Return a basis of the linear dependencies among the LHS's of the levels, each as the
+    /// levels whose LHS's sum to zero, given by the id of their `Bdd` and their index. A level of
+    /// LHS zero is a dependency on its own.
+    ///
+    /// The dependencies are only extracted (see `algebra::extract_linear_dependencies`) if
+    /// `lhs_dependency_count` tells there are some.
+    pub fn lhs_dependencies(&self) -> Vec<Vec<(Id, usize)>> {
+        if self.lhs_dependency_count() == 0 {
+            return Vec::new();
+        }
+        let (lhs, index) = self.lhs_matrix();
+        crate::algebra::extract_linear_dependencies(lhs)
+            .iter_rows()
+            .map(|dependency| dependency.iter_set_bits(..).map(|row| index[row]).collect())
+            .collect()
+    }
+
+    /// Forget the tracked rank of the LHS's, after changing a LHS through `System::get_bdd`. See
+    /// the `lhs` module documentation.
+    pub fn invalidate_lhs(&mut self) {
+        self.lhs.get_mut().invalidate();
+    }
+
+    /// Return the basis of the LHS's, rebuilt if it was invalidated.
+    fn up_to_date_lhs(&self) -> &RefCell<LhsBasis> {
+        if self.lhs.borrow().pivots.is_none() {
+            let mut basis = LhsBasis { pivots: Some(vec![None; self.nvar]), rank: 0 };
+            for bdd in self.bdds.values() {
+                for lhs in bdd.borrow().get_lhs() {
+                    basis.insert(&lhs, self.nvar);
+                }
+            }
+            *self.lhs.borrow_mut() = basis;
+        }
+        &self.lhs
+    }
+
+    /// Add the LHS's of the levels of the `Bdd` of id `bdd_id`, just pushed, to the tracked basis.
+    pub(super) fn insert_lhs(&mut self, bdd_id: Id) {
+        if let Some(bdd) = self.bdds.get(&bdd_id) {
+            for lhs in bdd.borrow().get_lhs() {
+                self.lhs.get_mut().insert(&lhs, self.nvar);
+            }
+        }
+    }
+
+    /// Take the LHS's `removed`, just removed from the `System`, out of the tracked basis, one
+    /// after the other. See the `lhs` module documentation.
+    pub(super) fn remove_lhs(&mut self, removed: &[Vob]) {
+        let nonzero: Vec<&Vob> = removed.iter().filter(|lhs| lhs.iter_set_bits(..).next().is_some()).collect();
+        if nonzero.is_empty() || self.lhs.get_mut().pivots.is_none() {
+            return;
+        }
+        // The number of LHS's holding each variable, the ones left to remove included
+        let mut holders = vec![0usize; self.nvar];
+        let left = self.bdds.values().flat_map(|bdd| bdd.borrow().get_lhs());
+        for lhs in left.chain(nonzero.iter().map(|lhs| (*lhs).clone())) {
+            lhs.iter_set_bits(..).for_each(|var| holders[var] += 1);
+        }
+        let basis = self.lhs.get_mut();
+        for lhs in nonzero {
+            lhs.iter_set_bits(..).for_each(|var| holders[var] -= 1);
+            match lhs.iter_set_bits(..).find(|var| holders[*var] == 0) {
+                Some(var) => basis.remove_var(var),
+                None => {
+                    basis.invalidate();
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::soc::system::System;
+    use crate::solver::lhs::{DefaultSolver, LevelDependency};
+    use crate::solver::{Dependency, Solver};
+    use crate::system;
+
+    #[test]
+    fn solver_step_keeps_basis() {
+        // x0 + x1 + x2 is the sum of the first two levels, x3 = 0 once it is 0, and x1 = 1
+        let mut system = system!(4; 0; [("0+1",[(1;2,3)]);("2",[(2;4,0);(3;0,4)]);("",[(4;0,0)])];
+                                    1; [("0+1+2",[(1;2,3)]);("3",[(2;4,0);(3;4,4)]);("",[(4;0,0)])];
+                                    2; [("1",[(1;0,2)]);("",[(2;0,0)])]).unwrap();
+        let check = |system: &System, rank: usize| {
+            assert!(system.lhs.borrow().pivots.is_some());
+            assert_eq!(rank, system.lhs.borrow().rank);
+            assert_eq!(crate::algebra::rank(&system.lhs_matrix().0), rank);
+        };
+        assert_eq!(1, system.lhs_dependency_count());
+        check(&system, 4);
+
+        let join_order = DefaultSolver::pick_best_dep(LevelDependency::extract(&system));
+        DefaultSolver.resolve(&mut system, join_order).unwrap();
+        check(&system, 4);
+        // x3 and x1 are substituted, and the Bdd of x1 left with no level is removed
+        DefaultSolver::absorb_all_equations(&mut system).unwrap();
+        assert_eq!(1, system.iter_bdds().count());
+        check(&system, 2);
+        assert_eq!(0, system.lhs_dependency_count());
+    }
+}
@@ -0,0 +1,174 @@
+//! Undo log of a `System`, such that a sequence of operations can be rolled back, e.g. when a
+//! speculative resolution fails or turns out too costly.
+//!
+//! Once a transaction is started with `System::begin`, every operation of the `System` which
+//! modifies it first records the state of what it is about to modify: the `Bdd`s it touches (or
+//! their absence), and the `LinBank` and `nvar` if it may change them. `System::rollback` restores
+//! these states from the latest to the savepoint, while `System::commit` keeps the operations. As
+//! a clone of a `Bdd` shares its levels until they change (see the `level` module), recording a
+//! `Bdd` only costs a copy of the levels the operation changes afterwards.
+//!
+//! Transactions nest: a savepoint must be committed or rolled back before the ones taken before
+//! it, and the operations committed in a nested transaction are still rolled back by the outer
+//! one. `System::transaction` wraps a closure, rolling back when it returns an `Err`.
+//!
+//! The `Bdd`s modified through `System::get_bdd` are not recorded, use `System::modify_bdd`
+//! instead to apply e.g. a pruning to a `Bdd` within a transaction.
+
+use std::io::Error;
+
+use crate::soc::{bdd::Bdd, Id};
+
+use super::{LinBank, System};
+
+/// A point of the undo log of a `System` to roll back to, see `System::begin`.
+#[derive(Debug)]
+#[must_use = "a transaction is only closed by committing or rolling back its savepoint"]
+pub struct Savepoint {
+    index: usize,
+    depth: usize,
+}
+
+/// The operations recorded since the outermost savepoint.
+#[derive(Debug, Default, Clone)]
+pub(super) struct UndoLog {
+    entries: Vec<UndoEntry>,
+    depth: usize,
+}
+
+/// The state of what an operation was about to modify.
+#[derive(Debug, Clone)]
+struct UndoEntry {
+    operation: String,
+    bdds: Vec<(Id, Option<Bdd>)>,
+    lin_bank: Option<LinBank>,
+    nvar: usize,
+}
+
+impl System {
+    /// Start a transaction, or a nested one if one is already started, and return the savepoint
+    /// to close it with, see the `undo` module documentation.
+    pub fn begin(&mut self) -> Savepoint {
+        let log = self.undo.get_or_insert_with(UndoLog::default);
+        log.depth += 1;
+        Savepoint { index: log.entries.len(), depth: log.depth }
+    }
+
+    /// Keep the operations done since `savepoint`, closing its transaction. They can still be
+    /// rolled back by an outer transaction.
+    ///
+    /// Panics if `savepoint` isn't the one of the innermost transaction.
+    pub fn commit(&mut self, savepoint: Savepoint) {
+        let log = self.innermost(&savepoint);
+        log.depth -= 1;
+        if log.depth == 0 {
+            self.undo = None;
+        }
+    }
+
+    /// Undo the operations done since `savepoint`, closing its transaction, and return their
+    /// descriptions, the latest first.
+    ///
+    /// Panics if `savepoint` isn't the one of the innermost transaction.
+    pub fn rollback(&mut self, savepoint: Savepoint) -> Vec<String> {
+        let log = self.innermost(&savepoint);
+        let entries = log.entries.split_off(savepoint.index);
+        log.depth -= 1;
+        if log.depth == 0 {
+            self.undo = None;
+        }
+        if !entries.is_empty() {
+            self.invalidate_lhs();
+        }
+        let mut undone = Vec::with_capacity(entries.len());
+        for entry in entries.into_iter().rev() {
+            for (id, bdd) in entry.bdds {
+                match bdd {
+                    Some(bdd) => self.bdds.insert(id, bdd.into()),
+                    None => self.bdds.remove(&id),
+                };
+            }
+            if let Some(lin_bank) = entry.lin_bank {
+                self.lin_bank = lin_bank;
+            }
+            self.nvar = entry.nvar;
+            undone.push(entry.operation);
+        }
+        undone
+    }
+
+    /// Run `operations` in a transaction, committed if they return `Ok` and rolled back if they
+    /// return `Err`.
+    pub fn transaction<T, E, F>(&mut self, operations: F) -> Result<T, E>
+        where F: FnOnce(&mut System) -> Result<T, E>
+    {
+        let savepoint = self.begin();
+        let result = operations(self);
+        match result {
+            Ok(_) => self.commit(savepoint),
+            Err(_) => {
+                self.rollback(savepoint);
+            },
+        }
+        result
+    }
+
+    /// Return true if a transaction is started.
+    #[inline]
+    pub fn in_transaction(&self) -> bool {
+        self.undo.is_some()
+    }
+
+    /// Return the descriptions of the operations recorded since the outermost savepoint, the
+    /// earliest first.
+    pub fn logged_operations(&self) -> Vec<&str> {
+        self.undo
+            .iter()
+            .flat_map(|log| log.entries.iter())
+            .map(|entry| entry.operation.as_str())
+            .collect()
+    }
+
+    /// Apply `modify` to the `Bdd` of id `bdd_id`, recording it as `operation` if a transaction
+    /// is started, and return its result. The tracked rank of the LHS's is invalidated, see the
+    /// `lhs` module.
+    ///
+    /// Returns an `Error` if `bdd_id` is not found in the `System`.
+    pub fn modify_bdd<T, F>(&mut self, bdd_id: Id, operation: &str, modify: F) -> Result<T, Error>
+        where F: FnOnce(&mut Bdd) -> T
+    {
+        self.get_bdd(bdd_id)?;
+        self.record(operation, Some(&[bdd_id]), false);
+        self.invalidate_lhs();
+        Ok(modify(&mut self.get_bdd(bdd_id)?.borrow_mut()))
+    }
+
+    /// Record the state of the `Bdd`s of `bdds` (all of them if `None`), and of the `LinBank`
+    /// if `lin_bank`, before `operation` modifies them. Does nothing out of a transaction.
+    pub(super) fn record(&mut self, operation: &str, bdds: Option<&[Id]>, lin_bank: bool) {
+        if self.undo.is_none() {
+            return;
+        }
+        let ids: Vec<Id> = match bdds {
+            Some(ids) => ids.to_vec(),
+            None => self.bdds.keys().copied().collect(),
+        };
+        let entry = UndoEntry {
+            operation: format!("{} {:?}", operation, ids.iter().map(|id| **id).collect::<Vec<usize>>()),
+            bdds: ids.iter().map(|id| (*id, self.bdds.get(id).map(|bdd| bdd.borrow().clone()))).collect(),
+            lin_bank: if lin_bank { Some(self.lin_bank.clone()) } else { None },
+            nvar: self.nvar,
+        };
+        if let Some(log) = self.undo.as_mut() {
+            log.entries.push(entry);
+        }
+    }
+
+    /// Return the undo log, checking that `savepoint` is the one of the innermost transaction.
+    fn innermost(&mut self, savepoint: &Savepoint) -> &mut UndoLog {
+        match self.undo.as_mut() {
+            Some(log) if log.depth == savepoint.depth && log.entries.len() >= savepoint.index => log,
+            _ => panic!("A savepoint must be closed after the ones taken after it, and only once"),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::io::Error;
 
-use crate::soc::{Id, utils};
+use crate::{bdd, system};
+use crate::soc::{bdd::PathCursor, Id, utils};
 
 #[test]
 fn swap_test() {
@@ -13,6 +14,53 @@ fn swap_test() {
     assert_eq!(bdd, save);
 }
 
+#[test]
+fn swap_adjacent_levels_test() -> Result<(), Error> {
+    use std::io::ErrorKind;
+    use vob::Vob;
+    use crate::soc::{bdd::{Bdd, LevelSwap}, node::Node};
+    let mut bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let expected_result = bdd!(5;0;[("1+2",[(1;2,3)]);("0+4",[(2;5,4);(3;0,4)]);("3+2",[(4;6,0);(5;0,6)]);("",[(6;0,0)])]);
+    let swap = bdd.swap_adjacent_levels(1)?;
+    assert_eq!(bdd, expected_result);
+    assert_eq!(LevelSwap { above_before: 2, below_before: 2, above_after: 2, below_after: 2 }, swap);
+    assert_eq!(ErrorKind::InvalidInput, bdd.swap_adjacent_levels(2).unwrap_err().kind());
+    assert_eq!(bdd, expected_result);
+
+    // (x0 = x2) and (x1 = x3), bringing x2 next to x0 saves three nodes
+    let mut bdd = bdd!(4;0;[("0",[(1;2,3)]);("1",[(2;4,5);(3;6,7)]);("2",[(4;8,0);(5;9,0);(6;0,8);(7;0,9)]);("3",[(8;10,0);(9;0,10)]);("",[(10;0,0)])]);
+    let swap = bdd.swap_adjacent_levels(1)?;
+    assert_eq!((0, -3, -3), (swap.delta_above(), swap.delta_below(), swap.delta()));
+    assert_eq!(7, bdd.get_size());
+    assert_eq!(bdd.count_paths(), 4_usize.into());
+
+    let mut system = system![bdd]?;
+    assert_eq!(3, system.swap_adjacent_levels(Id::new(0), 1)?.delta());
+    assert!(system.swap_adjacent_levels(Id::new(0), 3).is_err());
+
+    // The 1-edge of the source jumps over the level below
+    let id = |k: usize| Id::new(k * 10000);
+    let lhs = |var: Option<usize>| {
+        let mut lhs = Vob::from_elem(3, false);
+        if let Some(var) = var {
+            lhs.set(var, true);
+        }
+        lhs
+    };
+    let mut bdd = Bdd::new();
+    bdd.add_level_with_nodes(lhs(Some(0)), vec![(id(1), Node::with_edges(Some(id(2)), Some(id(3))))]);
+    bdd.add_level_with_nodes(lhs(Some(1)), vec![(id(2), Node::with_edges(Some(id(3)), None))]);
+    bdd.add_level_with_nodes(lhs(Some(2)), vec![(id(3), Node::with_edges(Some(id(4)), None))]);
+    bdd.add_level_with_nodes(lhs(None), vec![(id(4), Node::new())]);
+    bdd.set_next_id(5);
+    let save = bdd.clone();
+    assert_eq!(ErrorKind::InvalidData, bdd.swap_adjacent_levels(0).unwrap_err().kind());
+    assert_eq!(bdd, save);
+    assert!(bdd.swap_adjacent_levels(1).is_ok());
+    assert!(system.swap_adjacent_levels(Id::new(1), 1).is_err());
+    Ok(())
+}
+
 #[test]
 fn add_test() {
     let mut bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
@@ -72,6 +120,160 @@ fn count_path_test() {
     assert_eq!(bdd.count_paths(), 0_usize.into());
 }
 
+#[test]
+fn count_solutions_test() {
+    use num_bigint::BigUint;
+    use vob::Vob;
+
+    let brute_force = |bdd: &crate::soc::bdd::Bdd, nvar: usize| -> BigUint {
+        let accepted = (0..1_usize << nvar)
+            .filter(|value| bdd.accepts(&(0..nvar).map(|var| (value >> var) & 1 == 1).collect::<Vob>()))
+            .count();
+        accepted.into()
+    };
+
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    assert_eq!(bdd.count_solutions(5), brute_force(&bdd, 5));
+    assert_eq!(bdd.count_solutions(5), 12_usize.into());
+
+    // The lhs of the third level is the XOR of the first two
+    let bdd = bdd!(3;0;[("0+1",[(1;2,3)]);("1+2",[(2;4,0);(3;0,4)]);("0+2",[(4;5,5)]);("",[(5;0,0)])]);
+    assert_eq!(bdd.count_paths(), 4_usize.into());
+    assert_eq!(bdd.count_solutions(3), brute_force(&bdd, 3));
+    assert_eq!(bdd.count_solutions(3), 4_usize.into());
+
+    // A null lhs only keeps its 0-edge
+    let bdd = bdd!(2;0;[("0",[(1;2,2)]);("",[(2;3,3)]);("",[(3;0,0)])]);
+    assert_eq!(bdd.count_solutions(2), brute_force(&bdd, 2));
+    assert_eq!(bdd.count_solutions(2), 4_usize.into());
+
+    let bdd = bdd!(5;0;[("",[(6;0,0)])]);
+    assert_eq!(bdd.count_solutions(5), 0_usize.into());
+}
+
+#[test]
+fn sample_solution_test() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+    // The lhs of the third level is the XOR of the first two: 4 solutions out of 8 assignments
+    let bdd = bdd!(3;0;[("0+1",[(1;2,3)]);("1+2",[(2;4,0);(3;0,4)]);("0+2",[(4;5,5)]);("",[(5;0,0)])]);
+    let samples = bdd.sample_solutions(&mut rng, 4000);
+    assert_eq!(samples.len(), 4000);
+    let mut frequencies = std::collections::HashMap::new();
+    for sample in samples {
+        assert!(bdd.accepts(&sample));
+        *frequencies.entry(sample).or_insert(0) += 1;
+    }
+    assert_eq!(frequencies.len(), 4);
+    assert!(frequencies.values().all(|frequency| (800..1200).contains(frequency)));
+
+    // Unbalanced subtrees: 3 paths, 12 solutions over 5 variables
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let mut frequencies = std::collections::HashMap::new();
+    for sample in bdd.sample_solutions(&mut rng, 6000) {
+        assert!(bdd.accepts(&sample));
+        *frequencies.entry(sample).or_insert(0) += 1;
+    }
+    assert_eq!(frequencies.len(), 12);
+    assert!(frequencies.values().all(|frequency| (350..650).contains(frequency)));
+
+    // No consistent path
+    let bdd = bdd!(2;0;[("0",[(1;0,2)]);("0",[(2;3,0)]);("",[(3;0,0)])]);
+    assert_eq!(bdd.sample_solution(&mut rng), None);
+}
+
+#[test]
+fn prune_above_weight_test() {
+    use vob::Vob;
+
+    let assignments = |nvar: usize| {
+        (0..1_usize << nvar).map(move |value| (0..nvar).map(|var| (value >> var) & 1 == 1).collect::<Vob>())
+    };
+    // The weight of the path selected by assignment, over the active levels
+    let weight = |lhss: &[Vob], active: &[usize], assignment: &Vob| {
+        active.iter()
+            .filter(|level| lhss[**level].iter_set_bits(..).fold(false, |parity, var| parity ^ assignment[var]))
+            .count()
+    };
+
+    let bdds = vec![
+        bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]),
+        bdd!(4;0;[("0",[(1;2,3)]);("1",[(2;4,5);(3;5,4)]);("2",[(4;6,6);(5;6,7)]);("3",[(6;8,8);(7;8,0)]);("",[(8;0,0)])]),
+    ];
+    for original in bdds {
+        let nvar = original.get_nvar_size();
+        let lhss = original.get_lhs();
+        for active in vec![vec![0, 1, 2], vec![1, 2], vec![0]] {
+            for max_weight in 0..4 {
+                let mut pruned = original.clone();
+                let stats = pruned.prune_above_weight(max_weight, &active);
+                assert!(pruned.jumping_edges().is_empty());
+                for assignment in assignments(nvar) {
+                    let expected = original.accepts(&assignment)
+                        && weight(&lhss, &active, &assignment) <= max_weight;
+                    assert_eq!(pruned.accepts(&assignment), expected);
+                }
+                assert_eq!(stats.paths_before, original.count_paths());
+                assert_eq!(stats.paths_after, pruned.count_paths());
+                assert_eq!(stats.nodes_after.iter().sum::<usize>(), pruned.get_size());
+            }
+        }
+    }
+
+    // The node 4 is reached with a weight of 0 and 1, only the first may take its 1-edge
+    let mut bdd = bdd!(3;0;[("0",[(1;2,3)]);("1",[(2;4,0);(3;4,0)]);("2",[(4;5,5)]);("",[(5;0,0)])]);
+    let stats = bdd.prune_above_weight(1, &[0, 2]);
+    assert_eq!(stats.paths_before, 4_usize.into());
+    assert_eq!(stats.paths_after, 3_usize.into());
+    assert_eq!(stats.nodes_after, vec![1, 2, 2, 1]);
+    assert_eq!(stats.split_nodes(), 1);
+    assert_eq!(stats.removed_nodes(), 0);
+
+    let stats = bdd.prune_above_weight(0, &[0, 2]);
+    assert_eq!(stats.paths_after, 1_usize.into());
+    assert_eq!(stats.nodes_after, vec![1, 1, 1, 1]);
+    assert_eq!(stats.removed_nodes(), 2);
+}
+
+#[test]
+fn count_weighted_paths_test() {
+    use num_bigint::BigUint;
+
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    assert_eq!(bdd.count_weighted_paths(|_, _| BigUint::from(1_u8)), bdd.count_paths());
+    // Each path weighted by 2^(number of 1-edges): 2 + 2 + 4
+    let weighted: BigUint = bdd.count_weighted_paths(|_, edge| BigUint::from(if edge { 2_u8 } else { 1 }));
+    assert_eq!(weighted, 8_usize.into());
+    // Probabilities of a level over independent lhs sum to one on a full bdd
+    let bdd = bdd!(2;0;[("0",[(1;2,3)]);("1",[(2;4,4);(3;4,4)]);("",[(4;0,0)])]);
+    let probability: f64 = bdd.count_weighted_paths(|level, edge| match (level, edge) {
+        (0, false) => 0.25,
+        (0, true) => 0.75,
+        _ => 0.5,
+    });
+    assert!((probability - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn weight_distribution_test() {
+    use num_bigint::BigUint;
+
+    // The 1-edges of the 3 paths by level: 001, 010 and 101
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let distribution = |active_area: std::ops::Range<usize>, step: usize| -> Vec<(usize, BigUint)> {
+        let distribution = bdd.weight_distribution(&active_area, step);
+        assert_eq!(distribution.values().sum::<BigUint>(), bdd.count_paths());
+        distribution.into_iter().collect()
+    };
+    assert_eq!(distribution(0..3, 1), vec![(1, 2_u8.into()), (2, 1_u8.into())]);
+    // The groups are the levels 0 and 1, and the level 2 alone
+    assert_eq!(distribution(0..3, 2), vec![(1, 2_u8.into()), (2, 1_u8.into())]);
+    assert_eq!(distribution(1..3, 2), vec![(1, 3_u8.into())]);
+    assert_eq!(distribution(0..1, 1), vec![(0, 2_u8.into()), (1, 1_u8.into())]);
+    assert_eq!(distribution(0..0, 1), vec![(0, 3_u8.into())]);
+}
+
 #[test]
 fn join_test() -> Result<(), Error> {
     let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
@@ -111,6 +313,199 @@ fn join_empty_bdd() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn sift_test() -> Result<(), Error> {
+    use vob::Vob;
+    // (x0 = x2) and (x1 = x3), which is the smallest with the levels of x0 and x2 adjacent
+    let bdd = bdd!(4;0;[("0",[(1;2,3)]);("1",[(2;4,5);(3;6,7)]);("2",[(4;8,0);(5;9,0);(6;0,8);(7;0,9)]);("3",[(8;10,0);(9;0,10)]);("",[(10;0,0)])]);
+    let mut sifted = bdd.clone();
+    let stats = sifted.sift_levels();
+    assert_eq!(10, stats.nodes_before);
+    assert_eq!(sifted.get_size(), stats.nodes_after);
+    assert!(stats.nodes_after < 10 && stats.swaps > 0);
+    assert_eq!(bdd.count_paths(), sifted.count_paths());
+    for assignment in 0..16_usize {
+        let assignment: Vob = (0..4).map(|var| assignment >> var & 1 == 1).collect();
+        assert_eq!(bdd.accepts(&assignment), sifted.accepts(&assignment));
+    }
+
+    // Sifting a sifted Bdd doesn't make it bigger
+    let again = sifted.sift_levels();
+    assert!(again.nodes_after <= again.nodes_before);
+
+    let mut system = system![bdd]?;
+    assert!(system.sift_above(10).is_empty());
+    let sifted = system.sift_above(5);
+    assert_eq!(vec![Id::new(0)], sifted.iter().map(|(id, _)| *id).collect::<Vec<Id>>());
+    assert_eq!(stats.nodes_after, sifted[0].1.nodes_after);
+    assert!(system.sift(Id::new(1)).is_err());
+    Ok(())
+}
+
+#[test]
+fn copy_on_write_test() -> Result<(), Error> {
+    use vob::Vob;
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let mut clone = bdd.clone();
+    assert_eq!((4, 4), (bdd.count_shared_levels(), clone.count_shared_levels()));
+    // Only the two swapped levels are copied
+    clone.swap(1, 2);
+    assert_eq!((2, 2), (bdd.count_shared_levels(), clone.count_shared_levels()));
+    drop(clone);
+    assert_eq!(0, bdd.count_shared_levels());
+
+    // x0 = x1 in the first Bdd, and the level x0 + x1 of the second one depends on both
+    let bdd_0 = bdd!(2;0;[("0",[(1;2,3)]);("1",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let bdd_1 = bdd!(2;1;[("0+1",[(5;6,6)]);("",[(6;0,0)])]);
+    let system = system![bdd_0, bdd_1]?;
+    let fingerprint = system.fingerprint();
+    let trial = system.try_absorb(&[Id::new(0), Id::new(1)], &[0, 1, 2])?;
+    assert_eq!((Id::new(0), 6, 4), (trial.bdd(), trial.nodes_before(), trial.nodes_after()));
+    assert!(trial.peak_nodes() >= trial.nodes_after());
+    assert_eq!(fingerprint, system.fingerprint());
+    assert!(system.get_bdd(Id::new(1)).is_ok() && trial.system().get_bdd(Id::new(1)).is_err());
+    for assignment in 0..4_usize {
+        let assignment: Vob = (0..2).map(|var| assignment >> var & 1 == 1).collect();
+        assert_eq!(system.is_solution(&assignment), trial.system().is_solution(&assignment));
+    }
+    let resolved = trial.into_system();
+    assert_eq!(vec![(Id::new(0), 2)], resolved.iter_bdds().map(|(id, bdd)| (*id, bdd.borrow().get_levels_size() - 1)).collect::<Vec<_>>());
+
+    assert!(system.try_absorb(&[Id::new(0), Id::new(1)], &[1, 0]).is_err());
+    assert!(system.try_absorb(&[Id::new(0), Id::new(2)], &[0, 1]).is_err());
+    Ok(())
+}
+
+#[test]
+fn fix_variable_and_project_out_test() -> Result<(), Error> {
+    use vob::Vob;
+    let bdd_0 = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_1 = bdd!(5;1;[("1+3",[(1;2,3)]);("4",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let system = system![bdd_0, bdd_1]?;
+    let assignments = || (0..32_usize).map(|assignment| (0..5).map(|var| assignment >> var & 1 == 1).collect::<Vob>());
+    let with = |assignment: &Vob, var: usize, value: bool| {
+        let mut assignment = assignment.clone();
+        assignment.set(var, value);
+        assignment
+    };
+
+    for (var, value) in [(2, true), (4, false), (0, true)].iter() {
+        let mut fixed = system.clone();
+        fixed.fix_variable(*var, *value)?;
+        assert!(fixed.iter_bdds().all(|(_, bdd)| bdd.borrow().get_lhs().iter().all(|lhs| !lhs[*var])));
+        for assignment in assignments() {
+            assert_eq!(assignment[*var] == *value && system.is_solution(&assignment), fixed.is_solution(&assignment));
+        }
+    }
+
+    for vars in [vec![2], vec![3], vec![0, 4]].iter() {
+        let mut projected = system.clone();
+        projected.project_out(vars)?;
+        for assignment in assignments() {
+            let mut extensions = vec![assignment.clone()];
+            for var in vars {
+                extensions = extensions.iter().flat_map(|a| vec![with(a, *var, false), with(a, *var, true)]).collect();
+            }
+            assert_eq!(extensions.iter().any(|a| system.is_solution(a)), projected.is_solution(&assignment));
+        }
+    }
+
+    let mut fixed = system.clone();
+    assert!(fixed.fix_variable(5, true).is_err());
+    fixed.fix_variable(2, true)?;
+    assert!(fixed.fix_variable(2, false).is_err());
+    let fingerprint = fixed.fingerprint();
+    assert!(fixed.project_out(&[3, 2]).is_err());
+    assert!(fixed.project_out(&[5]).is_err());
+    assert_eq!(fingerprint, fixed.fingerprint());
+    Ok(())
+}
+
+#[test]
+fn add_linear_constraint_test() -> Result<(), Error> {
+    use vob::Vob;
+    let bdd_0 = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_1 = bdd!(5;1;[("1+3",[(1;2,3)]);("4",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let original = system![bdd_0, bdd_1]?;
+    let mut system = original.clone();
+    system.join_bdds(Id::new(0), Id::new(1))?;
+    // Bdd 1 was joined into Bdd 0, but its nodes keep their ids
+    let id = system.add_linear_constraint(&[0, 3, 1, 1], true)?;
+    assert_eq!(Id::new(2), id);
+    assert_eq!(vec![vob![true, false, false, true, false]], system.get_bdd(id)?.borrow().get_lhs());
+    for assignment in 0..32_usize {
+        let assignment: Vob = (0..5).map(|var| assignment >> var & 1 == 1).collect();
+        let constrained = assignment[0] ^ assignment[3];
+        assert_eq!(original.is_solution(&assignment) && constrained, system.is_solution(&assignment));
+    }
+    // The constraint can be resolved with the rest of the System
+    system.join_bdds(Id::new(0), id)?;
+    assert_eq!(Id::new(3), system.add_linear_constraint(&[4], false)?);
+    // Nor is the id of a popped Bdd reused
+    system.pop_bdd(Id::new(3))?;
+    assert_eq!(Id::new(4), system.add_linear_constraint(&[4], false)?);
+
+    assert!(system.add_linear_constraint(&[5], true).is_err());
+    assert!(system.add_linear_constraint(&[2, 2], true).is_err());
+    assert!(system.add_linear_constraint(&[], false).is_err());
+    Ok(())
+}
+
+#[test]
+fn transaction_test() -> Result<(), Error> {
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_1 = bdd!(5;1;[("0+1",[(7;8,8)]);("",[(8;0,0)])]);
+    let mut system = system![bdd, bdd_1]?;
+    let fingerprint = system.fingerprint();
+
+    // Rolled back as the last operation fails
+    let result: Result<(), Error> = system.transaction(|system| {
+        system.swap(Id::new(0), 1, 2)?;
+        system.join_bdds(Id::new(0), Id::new(1))?;
+        system.fix(vec![3], true)?;
+        system.absorb(Id::new(0), 7, false)
+    });
+    assert!(result.is_err());
+    assert_eq!(fingerprint, system.fingerprint());
+    assert_eq!((2, 0), (system.iter_bdds().count(), system.get_lin_bank_size()));
+    assert!(!system.in_transaction());
+
+    // Nested transactions
+    let outer = system.begin();
+    system.swap(Id::new(0), 1, 2)?;
+    let swapped = system.fingerprint();
+    let inner = system.begin();
+    system.pop_bdd(Id::new(1))?;
+    system.modify_bdd(Id::new(0), "prune", |bdd| bdd.drop(0))?;
+    assert_eq!(vec!["swap [0]", "pop_bdd [1]", "prune [0]"], system.logged_operations());
+    assert_eq!(vec!["prune [0]".to_string(), "pop_bdd [1]".to_string()], system.rollback(inner));
+    assert_eq!(swapped, system.fingerprint());
+    let inner = system.begin();
+    system.fix(vec![0], false)?;
+    system.commit(inner);
+    assert_eq!(2, system.logged_operations().len());
+    assert_eq!(2, system.rollback(outer).len());
+    assert_eq!(fingerprint, system.fingerprint());
+
+    // Kept
+    system.transaction(|system| system.swap(Id::new(0), 1, 2))?;
+    assert_eq!(swapped, system.fingerprint());
+    assert!(system.logged_operations().is_empty());
+
+    // The parallel swaps and adds are recorded as the sequential ones
+    #[cfg(feature = "parallel")]
+    {
+        let savepoint = system.begin();
+        system.par_swap(Id::new(0), 1, 2)?;
+        system.par_add(Id::new(0), 0, 1)?;
+        assert!(system.par_swap(Id::new(0), 0, 2).is_err());
+        assert_eq!(vec!["swap [0]", "add [0]"], system.logged_operations());
+        system.rollback(savepoint);
+        assert_eq!(swapped, system.fingerprint());
+    }
+    Ok(())
+}
+
 #[test]
 fn fix_test() -> Result<(), Error> {
     let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
@@ -143,3 +538,1161 @@ fn test_equality() {
     ("0+4",[(40000;0,60000);(50000;60000,0)]);("",[(60000;0,0)])]);
     assert_eq!(bdd, same_bdd)
 }
+
+#[test]
+fn path_cursor_test() -> Result<(), Error> {
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;6,6);(5;6,0)]);("",[(6;0,0)])]);
+    let expected: Vec<Vec<bool>> = bdd
+        .get_all_valid_path()
+        .iter()
+        .map(|path| path.iter().map(|eq| eq.get_rhs()).collect())
+        .collect();
+
+    // Enumerate one path at a time, serializing the cursor in between
+    let mut cursor = PathCursor::start();
+    let mut actual = Vec::new();
+    while !cursor.is_exhausted() {
+        let (paths, next) = bdd.valid_paths_from(&cursor, 1)?;
+        actual.extend(paths.iter().map(|path| path.iter().map(|eq| eq.get_rhs()).collect::<Vec<bool>>()));
+        cursor = next.to_string().parse()?;
+    }
+    assert_eq!(actual, expected);
+    assert_eq!(actual.len(), 5);
+
+    // A cursor from another Bdd is rejected
+    let other = bdd!(5;1;[("0+4",[(4;6,6)]);("",[(6;0,0)])]);
+    let (_, cursor) = bdd.valid_paths_from(&PathCursor::start(), 1)?;
+    assert!(other.valid_paths_from(&cursor, 1).is_err());
+    Ok(())
+}
+
+#[test]
+fn path_cursor_dead_branch_test() -> Result<(), Error> {
+    // An unreduced Bdd whose leftmost branch dies at node 4, before the sink
+    let bdd = bdd!(3;0;[("0",[(1;2,3)]);("1",[(2;4,0);(3;5,6)]);("2",[(4;0,0);(5;7,0);(6;0,7)]);("",[(7;0,0)])]);
+    let mut cursor = PathCursor::start();
+    let mut actual = Vec::new();
+    while !cursor.is_exhausted() {
+        let (paths, next) = bdd.valid_paths_from(&cursor, 1)?;
+        actual.extend(paths.iter().map(|path| path.iter().map(|eq| eq.get_rhs()).collect::<Vec<bool>>()));
+        cursor = next;
+    }
+    assert_eq!(actual, vec![vec![true, false, false], vec![true, true, true]]);
+    assert_eq!(bdd.count_solutions(3), actual.len().into());
+    Ok(())
+}
+
+#[test]
+fn solutions_cursor_test() -> Result<(), Error> {
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let mut system = system![bdd]?;
+    let expected = system.clone().calculate_solutions();
+    let (first, cursor) = system.calculate_solutions_from(&PathCursor::start(), 2)?;
+    let (rest, cursor) = system.calculate_solutions_from(&cursor, 10)?;
+    assert!(cursor.is_exhausted());
+    assert_eq!([first, rest].concat(), expected);
+
+    // Without bdd, the single solution is followed by an exhausted cursor, whatever the cursor
+    let mut system = crate::soc::system::System::new();
+    system.set_nvar(2);
+    let (solutions, cursor) = system.calculate_solutions_from(&PathCursor::start(), 10)?;
+    assert_eq!((1, true), (solutions.len(), cursor.is_exhausted()));
+    let (solutions, cursor) = system.calculate_solutions_from(&"after:01".parse()?, 10)?;
+    assert!(solutions.is_empty() && cursor.is_exhausted());
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "io")]
+fn checkpoint_test() -> Result<(), Error> {
+    use crate::soc::{fuzz::brute_force_solutions, io};
+
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let mut system = system![bdd]?;
+    system.fix(vec![0, 3], true)?;
+    system.scan_absorb_lin_eqs(Id::new(0))?;
+    assert!(system.get_lin_bank_size() > 0);
+
+    let path = std::env::temp_dir().join(format!("crush_checkpoint_test_{}.bdd", std::process::id()));
+    io::print_checkpoint_to_file(&system, &path)?;
+    let restored = utils::build_system_from_spec(io::parse_system_spec_from_file(&path)?)?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(
+        restored.iter_bdds().len(),
+        system.iter_bdds().len() + system.get_lin_bank_size()
+    );
+    assert_eq!(brute_force_solutions(&restored), brute_force_solutions(&system));
+    assert_eq!(restored.fingerprint(), system.fingerprint());
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "io")]
+fn journal_test() -> Result<(), Error> {
+    use std::io::Write;
+    use crate::soc::{io, journal::{self, Journal}};
+
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let mut system = system![bdd]?;
+    let dir = std::env::temp_dir();
+    let journal_path = dir.join(format!("crush_journal_test_{}.log", std::process::id()));
+    let checkpoint_path = dir.join(format!("crush_journal_test_{}.bdd", std::process::id()));
+
+    let mut journal = Journal::create(&journal_path)?;
+    journal.record("start", &system)?;
+    system.fix(vec![0, 3], true)?;
+    journal.record("fix", &system)?;
+    let checkpoint = journal.checkpoint(&system, &checkpoint_path)?;
+    system.swap(Id::new(0), 0, 1)?;
+    journal.record("swap", &system)?;
+    // A crash while writing an entry leaves a partial line behind
+    write!(std::fs::OpenOptions::new().append(true).open(&journal_path)?, "3 12ab")?;
+
+    let entries = journal::read_journal(&journal_path)?;
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[2], checkpoint);
+    let restored = utils::build_system_from_spec(io::parse_system_spec_from_file(&checkpoint_path)?)?;
+    assert_eq!(journal::resume_point(&entries, &restored), Some(&checkpoint));
+    assert_eq!(journal::resume_point(&entries, &system), Some(&entries[3]));
+
+    std::fs::remove_file(&journal_path)?;
+    std::fs::remove_file(&checkpoint_path)?;
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "io")]
+fn repl_test() -> Result<(), Error> {
+    use std::io::ErrorKind;
+    use vob::Vob;
+    use crate::soc::{io, repl::Repl};
+
+    let bdd_0 = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_1 = bdd!(5;1;[("1+3",[(1;2,3)]);("4",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let system = system![bdd_0, bdd_1]?;
+    let dir = std::env::temp_dir();
+    let input_path = dir.join(format!("crush_repl_test_{}.bdd", std::process::id()));
+    let output_path = dir.join(format!("crush_repl_test_{}_out.snap", std::process::id()));
+    io::print_system_to_file(&system, &input_path)?;
+
+    let mut repl = Repl::new();
+    let mut run = |line: &str| -> Result<String, Error> {
+        let mut out = Vec::new();
+        assert!(repl.execute(line, &mut out)?);
+        Ok(String::from_utf8(out).unwrap())
+    };
+    assert_eq!(ErrorKind::InvalidInput, run("stats").unwrap_err().kind());
+    assert_eq!("loaded 2 bdds of 5 variables\n", run(&format!("load {}", input_path.display()))?);
+    assert!(run("stats")?.contains("lhs rank 4, 1 linear dependencies"));
+    assert_eq!("0: 0:0 0:1 1:0\n", run("deps")?);
+    assert!(run("absorb 0")?.starts_with("absorbed in bdd 0"));
+    assert_eq!("no linear dependency\n", run("deps")?);
+    assert_eq!(ErrorKind::InvalidInput, run("absorb 0").unwrap_err().kind());
+    assert!(run("prune 0 1")?.starts_with("pruned bdd 0"));
+    assert_eq!("", run("# a comment")?);
+    run(&format!("save {}", output_path.display()))?;
+    for line in ["frobnicate", "prune 0", "fix 1 2", "absorb x"].iter() {
+        assert_eq!(ErrorKind::InvalidInput, run(line).unwrap_err().kind());
+    }
+    assert!(!repl.execute("quit", &mut Vec::new())?);
+
+    // The dependency was resolved and the paths above weight 1 pruned, on the saved system too
+    let explored = repl.system().unwrap();
+    let saved = io::load_snapshot_from_file(&output_path)?;
+    assert_eq!(explored.fingerprint(), saved.fingerprint());
+    for assignment in 0..32_usize {
+        let assignment: Vob = (0..5).map(|var| assignment >> var & 1 == 1).collect();
+        if saved.is_solution(&assignment) {
+            assert!(system.is_solution(&assignment));
+        }
+    }
+
+    std::fs::remove_file(&input_path)?;
+    std::fs::remove_file(&output_path)?;
+    Ok(())
+}
+
+#[test]
+fn renumber_test() {
+    let mut bdd = bdd!(5;3;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    bdd.swap(1, 2);
+    bdd.swap(0, 1);
+    let before = bdd.clone();
+    bdd.renumber();
+    assert_eq!(bdd.fingerprint(), before.fingerprint());
+    let node_ids = |bdd: &crate::soc::bdd::Bdd| {
+        let mut ids: Vec<usize> = bdd
+            .iter_levels()
+            .flat_map(|level| level.iter_nodes().map(|(id, _)| **id))
+            .collect();
+        ids.sort_unstable();
+        ids
+    };
+    let expected: Vec<usize> = (1..=bdd.get_size()).map(|k| k * 10000 + 3).collect();
+    assert_eq!(node_ids(&bdd), expected);
+
+    // The nodes created afterwards don't reuse an id
+    bdd.swap(1, 2);
+    let mut ids = node_ids(&bdd);
+    ids.dedup();
+    assert_eq!(ids.len(), bdd.get_size());
+}
+
+#[test]
+fn edit_test() {
+    use std::io::ErrorKind;
+    let mut bdd = bdd!(5;3;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let id = |k: usize| Id::new(k * 10000 + 3);
+    let solutions = bdd.count_paths();
+    assert_eq!(bdd.find_node_level(id(1)), Some(0));
+    assert_eq!(bdd.find_node_level(id(5)), Some(2));
+    assert_eq!(bdd.find_node_level(id(7)), None);
+
+    // The source and sink levels can't get new nodes, and children must be in the level below
+    assert_eq!(bdd.new_node(0).unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(bdd.new_node(3).unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(bdd.new_node(4).unwrap_err().kind(), ErrorKind::InvalidInput);
+    let size = bdd.get_size();
+    assert!(bdd.new_node_with_edges(1, Some(id(6)), None).is_err());
+    assert_eq!(bdd.get_size(), size);
+
+    // A new node only changes the solutions once reachable
+    let new = bdd.new_node_with_edges(1, None, Some(id(5))).unwrap();
+    assert_eq!(bdd.find_node_level(new), Some(1));
+    assert_eq!(bdd.get_size(), size + 1);
+    assert_eq!(bdd.connect(id(3), new, false).unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(bdd.connect(id(7), id(4), false).unwrap_err().kind(), ErrorKind::NotFound);
+    assert_eq!(bdd.connect(id(6), id(4), false).unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(bdd.count_paths(), solutions);
+    bdd.connect(id(1), new, true).unwrap();
+    assert_eq!(bdd.count_paths(), solutions);
+    assert_eq!(bdd.disconnect(id(1), false).unwrap(), Some(id(2)));
+    assert_eq!(bdd.disconnect(id(1), false).unwrap(), None);
+
+    // Removing a node removes the edges pointing to it
+    assert!(bdd.remove_node(id(6)).is_err());
+    bdd.remove_node(new).unwrap();
+    assert_eq!(bdd.find_node_level(new), None);
+    assert_eq!(bdd.iter_levels().next().unwrap().get_node(&id(1)).unwrap().get_e1(), None);
+}
+
+#[test]
+fn add_level_with_nodes_test() {
+    use vob::Vob;
+    use crate::soc::{bdd::Bdd, node::Node};
+    let spec = bdd!(3;2;[("0+1",[(1;2,3)]);("2",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let id = |k: usize| Id::new(k * 10000 + 2);
+    let lhs = |vars: &[usize]| {
+        let mut lhs = Vob::from_elem(3, false);
+        for var in vars {
+            lhs.set(*var, true);
+        }
+        lhs
+    };
+    let mut bdd = Bdd::new();
+    bdd.set_id(Id::new(2));
+    bdd.add_level_with_nodes(lhs(&[0, 1]), vec![(id(1), Node::with_edges(Some(id(2)), Some(id(3))))]);
+    bdd.add_level_with_nodes(lhs(&[2]), vec![
+        (id(2), Node::with_edges(Some(id(4)), None)),
+        (id(3), Node::with_edges(None, Some(id(4)))),
+    ]);
+    bdd.add_level_with_nodes(lhs(&[]), vec![(id(4), Node::new())]);
+    bdd.set_next_id(5);
+    assert_eq!(bdd.get_size(), spec.get_size());
+    assert_eq!(bdd.fingerprint(), spec.fingerprint());
+}
+
+/// The allocator of the tests, counting the bytes allocated by each thread such that a test can
+/// measure the memory held by what it builds, see `allocated_by`.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATED: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+}
+
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let _ = ALLOCATED.try_with(|bytes| bytes.set(bytes.get() + layout.size() as isize));
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        let _ = ALLOCATED.try_with(|bytes| bytes.set(bytes.get() - layout.size() as isize));
+        std::alloc::System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Run `f` and return its result along with the number of bytes it allocated and didn't free.
+fn allocated_by<T, F: FnOnce() -> T>(f: F) -> (T, usize) {
+    let before = ALLOCATED.with(|bytes| bytes.get());
+    let result = f();
+    (result, (ALLOCATED.with(|bytes| bytes.get()) - before) as usize)
+}
+
+#[test]
+fn store_bytes_per_node() {
+    use crate::metrics::BYTES_PER_NODE;
+    use crate::soc::{node::Node, store::{ArenaNodeStore, HashNodeStore, NodeStore}};
+
+    fn bytes_per_node<S: NodeStore>(nodes: usize) -> usize {
+        let (store, bytes) = allocated_by(|| {
+            let mut store = S::default();
+            for k in 0..nodes {
+                store.insert(Id::new(k), Node::with_edges(Some(Id::new(k + 1)), None));
+            }
+            store.shrink_to_fit();
+            store
+        });
+        assert_eq!(nodes, store.len());
+        bytes / nodes
+    }
+
+    // As many nodes as a map of 2^14 buckets holds at its maximal load factor, as assumed by
+    // `BYTES_PER_NODE`, but for the control bytes of the last group of the map
+    let nodes = 14 * 1024;
+    let hash = bytes_per_node::<HashNodeStore>(nodes);
+    let arena = bytes_per_node::<ArenaNodeStore>(nodes);
+    assert!(hash <= BYTES_PER_NODE + 1, "{} bytes per node in a HashNodeStore", hash);
+    // The arena holds both the nodes with their ids and a map from the ids to the slots
+    assert!(hash < arena, "{} bytes per node in a HashNodeStore, {} in an ArenaNodeStore", hash, arena);
+}
+
+#[test]
+fn arena_store_test() {
+    use crate::soc::{bdd::Bdd, node::Node, store::{ArenaNodeStore, HashNodeStore, NodeStore}};
+    let (a, b, c) = (Id::new(10000), Id::new(20000), Id::new(30000));
+    let mut store = ArenaNodeStore::default();
+    assert!(store.insert(a, Node::with_edges(Some(b), None)).is_none());
+    assert!(store.insert(b, Node::new()).is_none());
+    let handle = store.handle(&b).unwrap();
+    assert_eq!(Some(&b), store.get_by_handle(handle).map(|(id, _)| id));
+    assert!(store.remove(&b).is_some());
+    assert!(store.get_by_handle(handle).is_none());
+    // The slot of b is reused, but not its handle
+    assert!(store.insert(c, Node::new()).is_none());
+    assert_eq!((2, 2), (store.len(), store.capacity_used()));
+    assert!(store.get_by_handle(handle).is_none());
+    assert_eq!(Some(b), store.insert(a, Node::with_edges(None, Some(c))).unwrap().get_e0());
+    // Compacting moves c to the slot of a
+    store.remove(&a);
+    let handle = store.handle(&c).unwrap();
+    store.shrink_to_fit();
+    assert_eq!((1, 1), (store.len(), store.capacity_used()));
+    assert!(store.get_by_handle(handle).is_none());
+    assert!(store.get_by_handle(store.handle(&c).unwrap()).is_some());
+    assert_eq!(vec![c], store.iter().map(|(id, _)| *id).collect::<Vec<Id>>());
+
+    // The same operations give the same Bdd in both stores
+    let mut hash = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let mut arena: Bdd<ArenaNodeStore> = hash.clone().into_store();
+    assert_eq!(hash.fingerprint(), arena.fingerprint());
+    hash.swap(1, 2);
+    arena.swap(1, 2);
+    hash.add(0, 2);
+    arena.add(0, 2);
+    assert_eq!(hash.reduce().nodes_after, arena.reduce().nodes_after);
+    assert_eq!(hash.sift_levels().nodes_after, arena.sift_levels().nodes_after);
+    assert_eq!(hash.count_paths(), arena.count_paths());
+    assert_eq!(hash.fingerprint(), arena.fingerprint());
+    // The ids allocated depend on the order the nodes are visited in, which differs between stores
+    let next_id = arena.get_next_id();
+    let back: Bdd<HashNodeStore> = arena.into_store();
+    assert_eq!(hash.fingerprint(), back.fingerprint());
+    assert_eq!(next_id, back.get_next_id());
+}
+
+#[test]
+fn normalize_jumping_edges_test() {
+    use vob::Vob;
+    use crate::soc::{bdd::Bdd, node::Node};
+    let id = |k: usize| Id::new(k * 10000);
+    let lhs = |var: Option<usize>| {
+        let mut lhs = Vob::from_elem(3, false);
+        if let Some(var) = var {
+            lhs.set(var, true);
+        }
+        lhs
+    };
+    // The 1-edge of the source jumps over the two levels in between to the sink
+    let mut bdd = Bdd::new();
+    bdd.add_level_with_nodes(lhs(Some(0)), vec![(id(1), Node::with_edges(Some(id(2)), Some(id(4))))]);
+    bdd.add_level_with_nodes(lhs(Some(1)), vec![(id(2), Node::with_edges(Some(id(3)), Some(id(3))))]);
+    bdd.add_level_with_nodes(lhs(Some(2)), vec![(id(3), Node::with_edges(Some(id(4)), None))]);
+    bdd.add_level_with_nodes(lhs(None), vec![(id(4), Node::new())]);
+    bdd.set_next_id(5);
+    assert_eq!(bdd.jumping_edges(), vec![(0, id(1), id(4))]);
+    bdd.normalize_jumping_edges();
+    assert_eq!(bdd.jumping_edges(), vec![]);
+    assert_eq!(bdd.count_paths(), 6_usize.into());
+    assert_eq!(bdd.get_size(), 6);
+
+    // The spec builder normalizes, including the jumps over the level just above the sink
+    let built = bdd!(3;0;[("0",[(1;2,4)]);("1",[(2;3,3)]);("2",[(3;4,0)]);("",[(4;0,0)])]);
+    assert_eq!(built.jumping_edges(), vec![]);
+    assert_eq!(built.fingerprint(), bdd.fingerprint());
+    let built = bdd!(3;0;[("0",[(1;2,3)]);("1",[(2;4,0)]);("2",[(3;4,4)]);("",[(4;0,0)])]);
+    assert_eq!(built.jumping_edges(), vec![]);
+    assert_eq!(built.count_paths(), 6_usize.into());
+}
+
+#[test]
+fn reduce_test() -> Result<(), Error> {
+    use vob::Vob;
+    use crate::soc::{bdd::Bdd, node::Node};
+    let id = |k: usize| Id::new(k * 10000);
+    let lhs = |var: Option<usize>| {
+        let mut lhs = Vob::from_elem(3, false);
+        if let Some(var) = var {
+            lhs.set(var, true);
+        }
+        lhs
+    };
+    // 3 is a copy of 2, 5 only leads to a missing node and nothing leads to 6
+    let mut bdd = Bdd::new();
+    bdd.add_level_with_nodes(lhs(Some(0)), vec![(id(1), Node::with_edges(Some(id(2)), Some(id(3))))]);
+    bdd.add_level_with_nodes(lhs(Some(1)), vec![
+        (id(2), Node::with_edges(Some(id(4)), Some(id(5)))),
+        (id(3), Node::with_edges(Some(id(4)), Some(id(5)))),
+    ]);
+    bdd.add_level_with_nodes(lhs(Some(2)), vec![
+        (id(4), Node::with_edges(Some(id(7)), None)),
+        (id(5), Node::with_edges(Some(id(9)), None)),
+        (id(6), Node::with_edges(Some(id(7)), Some(id(7)))),
+    ]);
+    bdd.add_level_with_nodes(lhs(None), vec![(id(7), Node::new())]);
+    bdd.set_next_id(10);
+    let before = bdd.clone();
+    let stats = bdd.reduce();
+    assert_eq!((1, 1, 1), (stats.dead_ends, stats.orphans, stats.merged));
+    assert_eq!((vec![1, 2, 3, 1], vec![1, 1, 1, 1]), (stats.nodes_before.clone(), stats.nodes_after.clone()));
+    assert_eq!((7, 4, 3), (stats.size_before(), stats.size_after(), stats.removed_nodes()));
+    assert_eq!(bdd.count_paths(), 2_usize.into());
+    for assignment in 0..8_usize {
+        let assignment: Vob = (0..3).map(|var| assignment >> var & 1 == 1).collect();
+        assert_eq!(before.accepts(&assignment), bdd.accepts(&assignment));
+    }
+    let expected_result = bdd!(3;0;[("0",[(1;2,2)]);("1",[(2;4,0)]);("2",[(4;7,0)]);("",[(7;0,0)])]);
+    assert_eq!(expected_result.fingerprint(), bdd.fingerprint());
+    assert!(bdd.validate().is_valid());
+
+    // Reducing again changes nothing
+    assert_eq!(0, bdd.reduce().removed_nodes());
+    let mut system = system![bdd]?;
+    assert_eq!(0, system.reduce(Id::new(0))?.removed_nodes());
+    assert!(system.reduce(Id::new(1)).is_err());
+    Ok(())
+}
+
+#[test]
+fn validate_test() -> Result<(), Error> {
+    use vob::Vob;
+    use crate::soc::{bdd::Bdd, node::Node, validate::Issue};
+    let id = |k: usize| Id::new(k * 10000);
+    let lhs = |var: Option<usize>| {
+        let mut lhs = Vob::from_elem(3, false);
+        if let Some(var) = var {
+            lhs.set(var, true);
+        }
+        lhs
+    };
+    let valid = bdd!(3;0;[("0",[(1;2,3)]);("1",[(2;4,0);(3;4,4)]);("2",[(4;5,5)]);("",[(5;0,0)])]);
+    assert!(valid.validate().is_valid());
+
+    // A dangling 1-edge on the source, two unreachable nodes of which one is a dead end, and two
+    // levels on x1
+    let mut bdd = Bdd::new();
+    bdd.add_level_with_nodes(lhs(Some(0)), vec![(id(1), Node::with_edges(Some(id(2)), Some(id(9))))]);
+    bdd.add_level_with_nodes(
+        lhs(Some(1)),
+        vec![
+            (id(2), Node::with_edges(Some(id(4)), None)),
+            (id(3), Node::with_edges(Some(id(4)), Some(id(4)))),
+            (id(5), Node::new()),
+        ],
+    );
+    bdd.add_level_with_nodes(lhs(Some(1)), vec![(id(4), Node::with_edges(Some(id(6)), Some(id(6))))]);
+    bdd.add_level_with_nodes(lhs(None), vec![(id(6), Node::new())]);
+    let bdd_id = bdd.get_id();
+    let report = bdd.validate();
+    assert!(!report.is_valid());
+    assert_eq!(
+        report.issues,
+        vec![
+            Issue::DanglingEdge { bdd: bdd_id, level: 0, parent: id(1), child: id(9) },
+            Issue::UnreachableNode { bdd: bdd_id, level: 1, node: id(3) },
+            Issue::UnreachableNode { bdd: bdd_id, level: 1, node: id(5) },
+            Issue::DeadEnd { bdd: bdd_id, level: 1, node: id(5) },
+            Issue::DuplicateLhs { bdd: bdd_id, level: 2, first: 1 },
+        ]
+    );
+
+    let mut system = system![valid]?;
+    assert!(system.validate().is_valid());
+    system.set_nvar(4);
+    let report = system.validate();
+    assert_eq!(report.issues.len(), 4);
+    assert_eq!(report.issues[0], Issue::NvarMismatch { bdd: Id::new(0), level: 0, len: 3, nvar: 4 });
+    Ok(())
+}
+
+#[test]
+fn system_spec_round_trip_test() -> Result<(), Error> {
+    use crate::soc::utils::SystemSpec;
+
+    let bdd_0 = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_1 = bdd!(5;1;[("0+3",[(1;2,3)]);("1",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let mut system = system![bdd_0, bdd_1]?;
+    system.swap(Id::new(0), 0, 1)?;
+
+    let restored = utils::build_system_from_spec(SystemSpec::from_system(&system))?;
+    assert_eq!(restored.get_nvar(), system.get_nvar());
+    assert_eq!(restored.get_size(), system.get_size());
+    assert_eq!(restored.fingerprint(), system.fingerprint());
+
+    // The nodes are renumbered from 1 in each bdd
+    let bdd = restored.get_bdd(Id::new(0))?.borrow();
+    let max_id = bdd.iter_levels().flat_map(|level| level.iter_nodes().map(|(id, _)| **id)).max();
+    assert_eq!(max_id, Some(bdd.get_size() * 10000));
+    Ok(())
+}
+
+#[test]
+fn spec_transformation_test() -> Result<(), Error> {
+    use crate::soc::utils::{BddSpec, SystemSpec};
+
+    let round = bdd!(4;0;[("0+1",[(1;2,3)]);("2",[(2;4,0);(3;0,4)]);("3",[(4;0,5)]);("",[(5;0,0)])]);
+    let round = BddSpec::from_bdd(&round);
+    assert_eq!(round.max_var(), Some(3));
+
+    // Three rounds, each one using the last two variables of the previous one
+    let mut spec = SystemSpec::new(0, Vec::new());
+    spec.push_copies(&round, 3, 2);
+    assert_eq!(spec.get_nvar(), 8);
+    let ids: Vec<usize> = spec.iter_bdds().map(|bdd| *bdd.get_id()).collect();
+    assert_eq!(ids, vec![0, 1, 2]);
+    assert_eq!(spec.iter_bdds().map(|bdd| bdd.max_var()).collect::<Vec<_>>(), vec![Some(3), Some(5), Some(7)]);
+
+    let mut other = SystemSpec::new(2, vec![round.clone()]);
+    other.offset_vars(4);
+    assert_eq!(other.get_nvar(), 6);
+    spec.concat(other);
+    assert_eq!(spec.get_nvar(), 8);
+    assert_eq!(spec.next_bdd_id(), Id::new(4));
+
+    spec.relabel_vars(|var| 7 - var);
+    let system = utils::build_system_from_spec(spec)?;
+    assert_eq!(system.get_nvar(), 8);
+    assert_eq!(system.iter_bdds().len(), 4);
+    let lhs = system.get_bdd(Id::new(2))?.borrow().get_lhs_level(0);
+    assert_eq!(lhs.iter_set_bits(..).collect::<Vec<_>>(), vec![2, 3]);
+    Ok(())
+}
+
+#[test]
+fn stats_test() -> Result<(), Error> {
+    let bdd_0 = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_1 = bdd!(5;1;[("1+3",[(1;2,3)]);("4",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let system = system![bdd_0, bdd_1]?;
+    let stats = system.stats();
+    assert_eq!(stats.nvar, 5);
+    assert_eq!(stats.total_nodes, system.get_size());
+    assert_eq!(stats.total_levels, 5);
+    assert_eq!(stats.bdds.iter().map(|bdd| bdd.id).collect::<Vec<_>>(), vec![Id::new(0), Id::new(1)]);
+    assert_eq!(stats.bdds[0].max_width, 2);
+    assert_eq!(stats.bdds[1].avg_width, 1.5);
+    assert_eq!(stats.biggest_bdd().unwrap().id, Id::new(0));
+    assert_eq!(stats.var_occurrences, vec![1, 2, 2, 2, 2]);
+    // x1+x3 is the sum of x1+x2 and x3+x2
+    assert_eq!(stats.lhs_rank, 4);
+    assert_eq!(stats.dependencies(), 1);
+    assert_eq!(stats.bdds[0].level_widths, vec![1, 2, 2]);
+    assert_eq!(stats.bdds[1].lhs_densities, vec![2, 1]);
+    assert_eq!(stats.active_vars(), 5);
+    // The edges of the bdd! macro are normalized
+    assert_eq!(stats.jumping_edges(), 0);
+
+    // The 1-edge of the source jumps to the sink
+    use vob::Vob;
+    use crate::soc::{bdd::Bdd, node::Node, stats::BddStats};
+    let id = |k: usize| Id::new(k * 10000);
+    let lhs = |vars: &[usize]| vars.iter().fold(Vob::from_elem(3, false), |mut lhs, var| {
+        lhs.set(*var, true);
+        lhs
+    });
+    let mut bdd = Bdd::new();
+    bdd.add_level_with_nodes(lhs(&[0, 2]), vec![(id(1), Node::with_edges(Some(id(2)), Some(id(3))))]);
+    bdd.add_level_with_nodes(lhs(&[1]), vec![(id(2), Node::with_edges(Some(id(3)), Some(id(3))))]);
+    bdd.add_level_with_nodes(lhs(&[]), vec![(id(3), Node::new())]);
+    let stats = BddStats::new(&bdd);
+    assert_eq!((stats.level_widths, stats.lhs_densities, stats.jumping_edges), (vec![1, 1], vec![2, 1], 1));
+    Ok(())
+}
+
+#[test]
+fn join_scores_test() -> Result<(), Error> {
+    let bdd_0 = bdd!(6;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_1 = bdd!(6;1;[("1+3",[(1;2,3)]);("4",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let bdd_2 = bdd!(6;2;[("5",[(1;2,3)]);("",[(2;0,0);(3;0,0)])]);
+    let system = system![bdd_0, bdd_1, bdd_2]?;
+    let scores = system.join_scores();
+    assert_eq!(scores.iter().map(|score| score.id).collect::<Vec<_>>(), vec![Id::new(0), Id::new(1), Id::new(2)]);
+    // x1, x3 and x4 are shared by bdd 0 and 1, x5 is only used by bdd 2
+    assert_eq!(scores.iter().map(|score| score.shared_vars).collect::<Vec<_>>(), vec![3, 3, 0]);
+    assert_eq!(scores[0].score, 1.0 + 2.0 * 1.0 + 2.0 * 1.0);
+    assert_eq!(scores[1].score, 1.0 * 2.0 + 2.0 * 1.0);
+    assert_eq!(scores[2].score, 0.0);
+    Ok(())
+}
+
+#[test]
+fn lhs_matrix_test() -> Result<(), Error> {
+    let bdd_0 = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_1 = bdd!(5;1;[("1+3",[(1;2,3)]);("4",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let system = system![bdd_1, bdd_0]?;
+    let (lhs, index) = system.lhs_matrix();
+    assert_eq!(lhs.row_size(), 5);
+    assert_eq!(lhs.column_size(), 5);
+    assert_eq!(index, vec![(Id::new(0), 0), (Id::new(0), 1), (Id::new(0), 2), (Id::new(1), 0), (Id::new(1), 1)]);
+    assert_eq!(lhs.get_row(3).unwrap().iter_set_bits(..).collect::<Vec<_>>(), vec![1, 3]);
+
+    // x1+x3 = (x1+x2) + (x3+x2)
+    let dependencies = crate::algebra::extract_linear_dependencies(lhs);
+    assert_eq!(dependencies.row_size(), 1);
+    let levels: Vec<_> = dependencies.get_row(0).unwrap().iter_set_bits(..).map(|row| index[row]).collect();
+    assert_eq!(levels, vec![(Id::new(0), 0), (Id::new(0), 1), (Id::new(1), 0)]);
+    Ok(())
+}
+
+#[test]
+fn lhs_tracking_test() -> Result<(), Error> {
+    use crate::soc::system::System;
+    let bdd_0 = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_1 = bdd!(5;1;[("1+3",[(1;2,3)]);("4",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let mut system = system![bdd_0, bdd_1]?;
+    let check = |system: &System, rank: usize| {
+        assert_eq!(rank, system.lhs_rank());
+        assert_eq!(crate::algebra::rank(&system.lhs_matrix().0), rank);
+        assert_eq!(system.lhs_rows() - rank, system.lhs_dependency_count());
+    };
+    check(&system, 4);
+    assert_eq!(vec![vec![(Id::new(0), 0), (Id::new(0), 1), (Id::new(1), 0)]], system.lhs_dependencies());
+
+    // Resolving the dependency, as `Solver::resolve` does, leaves the rank unchanged
+    system.join_bdds(Id::new(0), Id::new(1))?;
+    system.swap(Id::new(0), 2, 3)?;
+    system.add(Id::new(0), 1, 2)?;
+    system.swap(Id::new(0), 1, 2)?;
+    system.add(Id::new(0), 0, 1)?;
+    check(&system, 4);
+    assert_eq!(vec![vec![(Id::new(0), 1)]], system.lhs_dependencies());
+    system.absorb(Id::new(0), 1, false)?;
+    check(&system, 4);
+    assert!(system.lhs_dependencies().is_empty());
+
+    // Pushing a Bdd adds its LHS's, dropping a level removes its LHS
+    system.push_bdd(bdd!(5;2;[("0+1+2",[(1;2,2)]);("",[(2;0,0)])]))?;
+    check(&system, 4);
+    system.drop(Id::new(0), 3)?;
+    check(&system, 4);
+    system.drop(Id::new(0), 0)?;
+    check(&system, 3);
+    let savepoint = system.begin();
+    system.pop_bdd(Id::new(2))?;
+    check(&system, 2);
+    system.rollback(savepoint);
+    check(&system, 3);
+    Ok(())
+}
+
+#[test]
+fn transfer_matrices_test() {
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let matrices = bdd.transfer_matrices();
+    assert_eq!(matrices.len(), 3);
+    assert_eq!((matrices[1].rows, matrices[1].columns), (2, 2));
+    assert_eq!(matrices[1].e0, vec![(0, 0), (1, 0)]);
+    assert_eq!(matrices[1].e1, vec![(0, 1)]);
+
+    // The number of paths is the sum of the entries of the product of the matrices
+    let mut paths = vec![1usize];
+    for matrix in matrices.iter() {
+        let mut below = vec![0; matrix.columns];
+        for (row, column) in matrix.e0.iter().chain(matrix.e1.iter()) {
+            below[*column] += paths[*row];
+        }
+        paths = below;
+    }
+    assert_eq!(paths.iter().sum::<usize>().to_string(), bdd.count_paths().to_string());
+}
+
+#[test]
+#[cfg(feature = "io")]
+fn print_transfer_matrices_test() -> Result<(), Error> {
+    use crate::soc::io;
+
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let dir = std::env::temp_dir().join(format!("crush_transfer_test_{}", std::process::id()));
+    io::print_transfer_matrices_to_dir(&bdd, &dir)?;
+    let content = std::fs::read_to_string(dir.join("level_1_e0.mtx"))?;
+    let files = std::fs::read_dir(&dir)?.count();
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(files, 6);
+    let lines: Vec<&str> = content.lines().filter(|line| !line.starts_with('%')).collect();
+    assert_eq!(lines, vec!["2 2 2", "1 1", "2 1"]);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "draw")]
+fn dot_options_test() -> Result<(), Error> {
+    use num_bigint::BigUint;
+
+    use crate::soc::io::{self, DotOptions};
+
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let path = std::env::temp_dir().join(format!("crush_dot_test_{}.dot", std::process::id()));
+    io::print_bdd_to_dot_format(&bdd, &path)?;
+    let plain = std::fs::read_to_string(&path)?;
+    let options = DotOptions { path_counts: true, edge_weights: true, ..Default::default() };
+    io::print_bdd_to_dot_format_with_options(&bdd, &path, &options)?;
+    let annotated = std::fs::read_to_string(&path)?;
+    assert_eq!(annotated, bdd.to_dot(&options));
+    let options = DotOptions { levels: Some(1..3), from_node: Some(Id::new(30000)), ..Default::default() };
+    io::print_bdd_to_dot_format_with_options(&bdd, &path, &options)?;
+    let windowed = std::fs::read_to_string(&path)?;
+    std::fs::remove_file(&path)?;
+    assert!(plain.contains("\"40000\" [label = \"\"; shape = point; width = 0.06];"));
+    assert!(!plain.contains("color"));
+    // 2 of the 3 paths go through the node 4, and through its 1-edge to the sink
+    assert!(annotated.contains("\"40000\" [label = \"2\"; shape = ellipse; fontsize = 10];"));
+    assert!(annotated.contains("\"50000\" [label = \"1\"; shape = ellipse; fontsize = 10];"));
+    assert!(annotated.contains("\"40000\" -> \"60000\" [color = 6];"));
+    assert!(annotated.contains("\"10000\" -> \"30000\" [color = 3];"));
+    assert!(annotated.contains("\"20000\" -> \"40000\" [style = dashed; color = 3];"));
+    // The counts are the ones of the count module: the paths through the nodes of a level add up
+    // to all the paths
+    let counts = crate::soc::dot::PathCounts::new(&bdd);
+    assert_eq!(counts.total, bdd.count_paths());
+    let through_level: BigUint = bdd.iter_levels().nth(1).unwrap().iter_nodes().map(|(id, _)| counts.through_node(id)).sum();
+    assert_eq!(through_level, bdd.count_paths());
+    // Only the levels 1 and 2 below the node 30000 are drawn
+    assert!(windowed.contains("\"1. x2 + x3\" -> \"2. x0 + x4\" -> \"CONST NODES\";"));
+    assert!(windowed.contains("\"30000\" -> \"40000\" [style = dashed];"));
+    for id in ["10000", "20000", "50000", "60000"].iter() {
+        assert!(!windowed.contains(&format!("\"{}\"", id)), "node {} is drawn", id);
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "draw")]
+fn system_dot_test() -> Result<(), Error> {
+    use crate::soc::io;
+
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_2 = bdd!(5;1;[("4+3",[(1;2,2)]);("",[(2;0,0)])]);
+    let system = system![bdd, bdd_2]?;
+    let path = std::env::temp_dir().join(format!("crush_system_dot_test_{}.dot", std::process::id()));
+    io::print_system_to_dot_format(&system, &path)?;
+    let dot = std::fs::read_to_string(&path)?;
+    std::fs::remove_file(&path)?;
+    assert!(dot.contains("subgraph \"cluster_0\" {"));
+    assert!(dot.contains("subgraph \"cluster_1\" {"));
+    assert!(dot.contains("\"1.0\" [label = \"0. x3 + x4\"];"));
+    assert!(dot.contains("\"10001\" -> \"20001\";"));
+    // x3 is shared with the level 1 of the bdd 0, x4 with its level 2, x2 only within the bdd 0
+    let dependencies: Vec<&str> = dot.lines().filter(|line| line.contains("constraint = false")).collect();
+    assert_eq!(dependencies, vec![
+        "\"0.1\" -> \"1.0\" [style = dashed; constraint = false; color = gray; label = \"x3\"];",
+        "\"0.2\" -> \"1.0\" [style = dashed; constraint = false; color = gray; label = \"x4\"];",
+    ]);
+    Ok(())
+}
+
+#[test]
+fn layout_test() -> Result<(), Error> {
+    use num_bigint::BigUint;
+    use crate::soc::layout::{EdgeLayout, NodeLayout};
+
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let layout = bdd.layout();
+    assert_eq!(BigUint::from(3u8), layout.paths);
+    let labels: Vec<&str> = layout.levels.iter().map(|level| level.label.as_str()).collect();
+    assert_eq!(vec!["x1 + x2", "x2 + x3", "x0 + x4", "T"], labels);
+    // The node 5 is only below the node 2, on the left, so it is placed left of the node 4
+    let positions: Vec<(usize, usize, usize)> = layout.nodes.iter().map(|node| (*node.id, node.level, node.position)).collect();
+    assert_eq!(vec![(10000, 0, 0), (20000, 1, 0), (30000, 1, 1), (50000, 2, 0), (40000, 2, 1), (60000, 3, 0)], positions);
+    assert_eq!(NodeLayout { id: Id::new(40000), level: 2, position: 1, paths: BigUint::from(2u8) }, layout.nodes[4]);
+    assert_eq!(7, layout.edges.len());
+    assert_eq!(EdgeLayout { from: Id::new(10000), to: Id::new(20000), value: false, paths: BigUint::from(2u8) }, layout.edges[0]);
+
+    let json: serde_json::Value = serde_json::from_str(&layout.to_json()).unwrap();
+    assert_eq!(json["paths"], "3");
+    assert_eq!(json["levels"][1], serde_json::json!({"lhs": [2, 3], "label": "x2 + x3", "width": 2}));
+    assert_eq!(json["nodes"][3], serde_json::json!({"id": 50000, "level": 2, "position": 0, "paths": "1"}));
+    assert_eq!(json["edges"][5], serde_json::json!({"from": 50000, "to": 60000, "value": false, "paths": "1"}));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "io")]
+fn session_test() -> Result<(), Error> {
+    use std::collections::BTreeMap;
+    use std::io::ErrorKind;
+    use crate::soc::{journal::{self, Journal}, session::{Manifest, Session}};
+
+    let bdd = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let mut system = system![bdd]?;
+    let dir = std::env::temp_dir().join(format!("crush_session_test_{}", std::process::id()));
+    let mut config = BTreeMap::new();
+    config.insert("strategy".to_string(), "no drop".to_string());
+    let session = Session::open(&dir, &system, config.clone())?;
+    assert_eq!(Session::open(&dir, &system, config.clone()).err().map(|e| e.kind()), Some(ErrorKind::AlreadyExists));
+
+    // Solving is journaled in the session, then stops after a checkpoint
+    let mut journal = Journal::create(&session.journal_path())?;
+    journal.record("start", &system)?;
+    system.swap(Id::new(0), 0, 1)?;
+    journal.checkpoint(&system, &session.checkpoint_path())?;
+
+    let (mut session, restored) = Session::continue_from(&dir)?;
+    assert_eq!(session.manifest().config, config);
+    assert_eq!(restored.fingerprint(), system.fingerprint());
+    let entries = journal::read_journal(&session.journal_path())?;
+    assert_eq!(journal::resume_point(&entries, &restored).map(|entry| entry.seq), Some(1));
+
+    session.add_result("0101-")?;
+    session.add_result("1100-")?;
+    assert_eq!(session.results()?, vec!["0101-", "1100-"]);
+    let manifest = session.finalize()?;
+    assert!(manifest.finalized);
+    assert_eq!(Manifest::read(&dir)?, manifest);
+    assert!(Session::continue_from(&dir).is_err());
+
+    // A session written by a later version isn't continued
+    let content = std::fs::read_to_string(dir.join("session"))?;
+    std::fs::write(dir.join("session"), content.replace("crush-session 1", "crush-session 99"))?;
+    let kind = Manifest::read(&dir).err().map(|e| e.kind());
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(kind, Some(ErrorKind::Unsupported));
+    Ok(())
+}
+
+#[test]
+fn spec_reader_test() -> Result<(), Error> {
+    use std::io::ErrorKind;
+    use crate::soc::parse::{build_system_from_reader, parse_system_spec, SpecReader};
+
+    let input = "5 2\n0 4\n1+2:(1;2,3)|\n3+2+-1:(2;4,5)(3;4,0)|\n0+4:(4;0,6)(5;6,0)|\n:(6;0,0)|\n---\n\n1 3\n0+3:(1;2,3)|\n1:(2;4,0)(3;0,4)|\n:(4;0,0)|\n---\n";
+    let mut reader = SpecReader::new(input.as_bytes())?;
+    assert_eq!((reader.get_nvar(), reader.get_nbdds()), (5, 2));
+    assert_eq!(reader.read_bdd()?.map(|bdd| bdd.get_id()), Some(Id::new(0)));
+    assert_eq!(reader.map(|bdd| bdd.map(|bdd| bdd.get_id())).collect::<Result<Vec<_>, _>>()?, vec![Id::new(1)]);
+
+    // The levels are read one at a time, the ones left unread being skipped
+    let mut reader = SpecReader::new(input.as_bytes())?;
+    assert_eq!(reader.next_bdd()?, Some((Id::new(0), 4)));
+    assert!(reader.next_level()?.is_some());
+    assert_eq!(reader.next_bdd()?, Some((Id::new(1), 3)));
+    let mut nlevels = 0;
+    while reader.next_level()?.is_some() {
+        nlevels += 1;
+    }
+    assert_eq!(nlevels, 3);
+    assert_eq!(reader.next_bdd()?, None);
+
+    // Same system as the nom parser
+    let streamed = build_system_from_reader(input.as_bytes())?;
+    let parsed = utils::build_system_from_spec(parse_system_spec(input)?)?;
+    assert_eq!(streamed.get_size(), parsed.get_size());
+    assert_eq!(streamed.fingerprint(), parsed.fingerprint());
+
+    let truncated = &input[..input.find("---").unwrap()];
+    let error = build_system_from_reader(truncated.as_bytes()).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    let malformed = input.replace("(3;4,0)", "(3;4)");
+    let error = SpecReader::new(malformed.as_bytes())?.read_system_spec().err().unwrap();
+    assert_eq!((error.kind(), error.to_string()),
+               (ErrorKind::InvalidData, "line 4: expected a level: its lhs, then its nodes (id;e0,e1) between : and |".to_string()));
+    let beyond = input.replace("5 2\n", "4 2\n");
+    let error = build_system_from_reader(beyond.as_bytes()).err().unwrap();
+    assert_eq!(error.to_string(), "bdd 0: variable 4 is beyond the 4 variables of the system");
+    let duplicated = input.replace("\n1 3\n", "\n0 3\n");
+    let error = build_system_from_reader(duplicated.as_bytes()).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "io")]
+fn soc_error_test() -> Result<(), Error> {
+    use std::io::ErrorKind;
+    use crate::soc::{io, parse::parse_system_spec, utils::SystemSpec, SocError};
+
+    let input = "5 2\n0 2\n1+2:(1;2,3)|\n:(2;0,0)|\n---\n1 2\n0+3:(1;2,3)\n:(2;0,0)|\n---\n";
+    match parse_system_spec(input) {
+        Err(SocError::Parse { path: None, line: 6, .. }) => {}
+        result => panic!("expected a parse error on line 6, got {:?}", result),
+    }
+
+    // The reader of the file tells the path and the line
+    let path = std::env::temp_dir().join(format!("crush_soc_error_test_{}.bdd", std::process::id()));
+    std::fs::write(&path, input.replace("(2;0,0)|\n---\n1", "(2;0)|\n---\n1"))?;
+    let error = io::parse_system_spec_from_file(&path).err().unwrap();
+    std::fs::remove_file(&path)?;
+    assert_eq!(error.to_string(),
+               format!("{}:4: expected a level: its lhs, then its nodes (id;e0,e1) between : and |", path.display()));
+    let error = io::parse_system_spec_from_file(&path).err().unwrap();
+    assert!(matches!(&error, SocError::Io { path: Some(p), .. } if *p == path));
+    assert_eq!(Error::from(error).kind(), ErrorKind::NotFound);
+
+    // A variable beyond nvar is reported instead of panicking
+    let spec = parse_system_spec("3 1\n0 2\n1+4:(1;2,3)|\n:(2;0,0)|\n---\n")?;
+    let error = utils::build_system_from_spec(spec.clone()).err().unwrap();
+    assert_eq!(error.to_string(), "bdd 0: variable 4 is beyond the 3 variables of the system");
+    let bdds = spec.iter_bdds().cloned().collect();
+    assert!(utils::build_system_from_spec(SystemSpec::new(5, bdds)).is_ok());
+    Ok(())
+}
+
+#[test]
+fn anf_test() -> Result<(), Error> {
+    use crate::soc::{anf::parse_anf, fuzz::brute_force_solutions, SocError};
+
+    let table = [0, 1, 1, 2, 3, 3, 0, 2];
+    let input = "# A non bijective S-box\nsbox s 3 2 0 1 1 2 3 3 0 2\n\ns(x0, x1 + 1, x2) = (x3, x4 + x0) # applied once\nx3 = x2 + 1\n";
+    let spec = parse_anf(input)?;
+    assert_eq!((spec.get_nvar(), spec.iter_bdds().count()), (5, 2));
+    let system = utils::build_system_from_spec(spec)?;
+    assert!(system.validate().is_valid());
+    let bit = |a: usize, var: usize| (a >> var) & 1;
+    let expected: Vec<bool> = (0..1 << 5)
+        .map(|a| {
+            let entry = table[bit(a, 0) << 2 | (bit(a, 1) ^ 1) << 1 | bit(a, 2)];
+            entry >> 1 == bit(a, 3) && entry & 1 == bit(a, 4) ^ bit(a, 0) && bit(a, 3) ^ bit(a, 2) == 1
+        })
+        .collect();
+    assert_eq!(brute_force_solutions(&system), expected);
+
+    let line_of = |input: &str| match parse_anf(input) {
+        Err(SocError::Parse { line, .. }) => line,
+        result => panic!("expected a parse error, got {:?}", result),
+    };
+    assert_eq!(line_of("sbox s 2 1 0 1 1\n"), 1);
+    assert_eq!(line_of("sbox s 1 1 0 1\n\nt(x0) = (x1)\n"), 3);
+    assert_eq!(line_of("sbox s 1 1 0 1\ns(x0) = (x1, x2)\n"), 2);
+    assert_eq!(line_of("sbox s 1 1 0 1\ns(x0 + x0) = (x1)\n"), 2);
+    assert_eq!(line_of("x0 + y1 = 0\n"), 1);
+    Ok(())
+}
+
+#[test]
+fn dump_test() -> Result<(), Error> {
+    use std::io::ErrorKind;
+    use vob::Vob;
+    use crate::soc::utils::{build_bdd_from_spec, read_buddy, read_dddmp};
+
+    // x0 and x1, the root being the last node
+    let mut spec = read_buddy("2 2\n0 1 \n3 1 0 1\n2 0 0 3\n".as_bytes(), Id::new(0))?;
+    assert_eq!(build_bdd_from_spec(&mut spec, 2), bdd!(2;0;[("0",[(1;0,2)]);("1",[(2;0,3)]);("",[(3;0,0)])]));
+    // x0 xor x1, with x1 first in the order
+    let mut spec = read_buddy("3 2\n1 0 \n2 0 0 1\n3 0 1 0\n4 1 2 3\n".as_bytes(), Id::new(1))?;
+    assert_eq!(build_bdd_from_spec(&mut spec, 2), bdd!(2;1;[("1",[(1;2,3)]);("0",[(2;0,4);(3;4,0)]);("",[(4;0,0)])]));
+    // x0 and x2, the edge skipping x1 being normalized
+    let mut spec = read_buddy("2 3\n0 1 2 \n3 2 0 1\n2 0 0 3\n".as_bytes(), Id::new(0))?;
+    let bdd = build_bdd_from_spec(&mut spec, 3);
+    for x in 0..8_usize {
+        let assignment: Vob = (0..3).map(|i| (x >> i) & 1 == 1).collect();
+        assert_eq!(x & 0b101 == 0b101, bdd.accepts(&assignment));
+    }
+    let mut spec = read_buddy("0 0 1\n".as_bytes(), Id::new(0))?;
+    assert_eq!(build_bdd_from_spec(&mut spec, 2), bdd!(2;0;[("",[(1;0,0)])]));
+    assert_eq!(ErrorKind::InvalidData, read_buddy("0 0 0\n".as_bytes(), Id::new(0)).unwrap_err().kind());
+    let unordered = read_buddy("2 2\n1 0 \n3 1 0 1\n2 0 0 3\n".as_bytes(), Id::new(0)).unwrap_err();
+    assert_eq!("bdd 0: node 3 isn't after its parent in the order", unordered.to_string());
+    let malformed = read_buddy("2 2\n0 1 \n3 1 0\n2 0 0 3\n".as_bytes(), Id::new(0)).unwrap_err();
+    assert_eq!("line 3: expected a node id var low high", malformed.to_string());
+
+    // x3 xor x5 and its negation, through complemented edges
+    let dddmp = ".ver DDDMP-2.0\n.mode A\n.varinfo 0\n.nnodes 3\n.nvars 6\n.nsuppvars 2\n.ids 3 5\n.permids 3 5\n\
+                 .nroots 2\n.rootids -3 3\n.nodes\n1 T 1 0 0\n2 5 1 1 -1\n3 3 0 2 -2\n.end\n";
+    let mut specs = read_dddmp(dddmp.as_bytes())?;
+    assert_eq!(2, specs.len());
+    assert_eq!(build_bdd_from_spec(&mut specs[0], 6), bdd!(6;0;[("3",[(1;2,3)]);("5",[(2;0,4);(3;4,0)]);("",[(4;0,0)])]));
+    assert_eq!(build_bdd_from_spec(&mut specs[1], 6), bdd!(6;1;[("3",[(1;3,2)]);("5",[(2;0,4);(3;4,0)]);("",[(4;0,0)])]));
+    let binary = dddmp.replace(".mode A", ".mode B");
+    assert_eq!(ErrorKind::Unsupported, read_dddmp(binary.as_bytes()).unwrap_err().kind());
+    let truncated = &dddmp[..dddmp.find(".end").unwrap()];
+    assert_eq!(ErrorKind::InvalidData, read_dddmp(truncated.as_bytes()).unwrap_err().kind());
+    Ok(())
+}
+
+#[test]
+fn write_dddmp_test() -> Result<(), Error> {
+    use std::io::ErrorKind;
+    use vob::Vob;
+    use crate::soc::utils::{build_bdd_from_spec, read_dddmp, write_dddmp};
+
+    let and = bdd!(2;0;[("0",[(1;0,2)]);("1",[(2;0,3)]);("",[(3;0,0)])]);
+    let mut dump = Vec::new();
+    write_dddmp(&and, &mut dump)?;
+    let dump = String::from_utf8(dump).unwrap();
+    assert!(dump.ends_with(".ids 0 1\n.permids 0 1\n.nroots 1\n.rootids 3\n.nodes\n1 T 1 0 0\n2 1 1 1 -1\n3 0 0 2 -1\n.end\n"));
+
+    // The missing then edges are complemented away, and the order of the levels is kept
+    let xor = bdd!(3;1;[("1",[(1;2,3)]);("",[(2;4,4)]);("0",[(3;0,5);(4;5,0)]);("",[(5;0,0)])]);
+    let mut dump = Vec::new();
+    write_dddmp(&xor, &mut dump)?;
+    let mut specs = read_dddmp(dump.as_slice())?;
+    assert_eq!(1, specs.len());
+    let read = build_bdd_from_spec(&mut specs[0], 3);
+    assert_eq!(vec![1, 0], read.iter_levels().flat_map(|level| level.iter_set_lhs()).collect::<Vec<_>>());
+    for x in 0..8_usize {
+        let assignment: Vob = (0..3).map(|i| (x >> i) & 1 == 1).collect();
+        assert_eq!(xor.accepts(&assignment), read.accepts(&assignment));
+    }
+
+    let mut dump = Vec::new();
+    let linear = bdd!(3;0;[("0+1",[(1;2,0)]);("",[(2;0,0)])]);
+    assert_eq!(ErrorKind::Unsupported, write_dddmp(&linear, &mut dump).unwrap_err().kind());
+    let repeated = bdd!(3;0;[("0",[(1;2,2)]);("0",[(2;3,0)]);("",[(3;0,0)])]);
+    assert_eq!(ErrorKind::Unsupported, write_dddmp(&repeated, &mut dump).unwrap_err().kind());
+    Ok(())
+}
+
+#[test]
+fn dimacs_test() -> Result<(), Error> {
+    use vob::Vob;
+    use crate::AHashMap;
+    use crate::soc::fuzz::brute_force_solutions;
+
+    // Whether the assignment of the variables of the system satisfies the CNF, the variables added
+    // by the encoding being set by unit propagation.
+    let satisfies = |dimacs: &str, assignment: &Vob| {
+        let clauses: Vec<Vec<i64>> = dimacs.lines()
+            .filter(|line| !line.starts_with('c') && !line.starts_with('p'))
+            .map(|line| line.split_whitespace().map(|literal| literal.parse().unwrap()).filter(|literal| *literal != 0))
+            .map(|clause| clause.collect())
+            .collect();
+        let mut values: AHashMap<i64, bool> =
+            assignment.iter().enumerate().map(|(var, value)| (var as i64 + 1, value)).collect();
+        loop {
+            let mut propagated = false;
+            for clause in clauses.iter() {
+                let value = |literal: &i64| values.get(&literal.abs()).map(|value| *value == (*literal > 0));
+                if clause.iter().any(|literal| value(literal) == Some(true)) {
+                    continue;
+                }
+                let unassigned: Vec<i64> = clause.iter().copied().filter(|literal| value(literal).is_none()).collect();
+                match unassigned.as_slice() {
+                    [] => return false,
+                    [literal] => {
+                        values.insert(literal.abs(), *literal > 0);
+                        propagated = true;
+                    }
+                    _ => {}
+                }
+            }
+            if !propagated {
+                let satisfied = |literal: &i64| values.get(&literal.abs()) == Some(&(*literal > 0));
+                return clauses.iter().all(|clause| clause.iter().any(satisfied));
+            }
+        }
+    };
+
+    let bdd_0 = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_1 = bdd!(5;1;[("0+3",[(1;2,3)]);("1",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let mut system = system![bdd_0, bdd_1]?;
+    system.fix(vec![0, 3], true)?;
+    system.scan_absorb_lin_eqs(Id::new(0))?;
+    assert!(system.get_lin_bank_size() > 0);
+    let mut dimacs = Vec::new();
+    let mapping = system.to_dimacs(&mut dimacs)?;
+    let dimacs = String::from_utf8(dimacs).unwrap();
+    let (cnf, _) = system.to_cnf();
+    assert!(dimacs.contains(&format!("p cnf {} {}\n", cnf.get_nvars(), cnf.get_clauses().len())));
+    assert_eq!(mapping.nodes.len(), system.get_size());
+
+    let expected = brute_force_solutions(&system);
+    assert!(expected.iter().any(|solution| *solution));
+    for (a, solution) in expected.into_iter().enumerate() {
+        let assignment: Vob = (0..5).map(|var| (a >> var) & 1 == 1).collect();
+        assert_eq!(satisfies(&dimacs, &assignment), solution, "assignment {:05b}", a);
+    }
+    Ok(())
+}
+
+#[test]
+fn binary_test() -> Result<(), Error> {
+    use std::io::ErrorKind;
+    use crate::soc::{binary::BINARY_VERSION, system::System};
+
+    let bdd_0 = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_1 = bdd!(5;1;[("0+3",[(1;2,3)]);("1",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let mut system = system![bdd_0, bdd_1]?;
+    system.fix(vec![0, 3], true)?;
+    system.scan_absorb_lin_eqs(Id::new(0))?;
+    assert!(system.get_lin_bank_size() > 0);
+
+    let mut bytes = Vec::new();
+    system.serialize_binary(&mut bytes)?;
+    let restored = System::deserialize_binary(&mut bytes.as_slice())?;
+    assert_eq!(restored.get_nvar(), system.get_nvar());
+    assert_eq!(restored.get_lin_bank_size(), system.get_lin_bank_size());
+    assert_eq!(restored.fingerprint(), system.fingerprint());
+    assert_eq!(restored.get_bdd(Id::new(0))?.borrow().get_next_id(), system.get_bdd(Id::new(0))?.borrow().get_next_id());
+    // The snapshot is exact, the ids of the nodes included
+    let mut restored_bytes = Vec::new();
+    restored.serialize_binary(&mut restored_bytes)?;
+    assert_eq!(restored_bytes, bytes);
+    #[cfg(feature = "io")]
+    {
+        let path = std::env::temp_dir().join(format!("crush_binary_test_{}.bin", std::process::id()));
+        crate::soc::io::save_snapshot_to_file(&system, &path)?;
+        let loaded = crate::soc::io::load_snapshot_from_file(&path);
+        std::fs::remove_file(&path)?;
+        assert_eq!(loaded?.fingerprint(), system.fingerprint());
+        // A missing snapshot is an error telling its path, not a panic
+        match crate::soc::io::load_snapshot_from_file(&path) {
+            Err(crate::soc::SocError::Io { path: Some(missing), error }) => {
+                assert_eq!(missing, path);
+                assert_eq!(error.kind(), ErrorKind::NotFound);
+            }
+            other => panic!("expected an Io error, got {:?}", other.map(|system| system.fingerprint())),
+        }
+    }
+
+    let error = System::deserialize_binary(&mut &bytes[1..]).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    let error = System::deserialize_binary(&mut &bytes[..bytes.len() - 1]).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    bytes[8..16].copy_from_slice(&(BINARY_VERSION + 1).to_le_bytes());
+    let error = System::deserialize_binary(&mut bytes.as_slice()).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serde_test() -> Result<(), Error> {
+    use crate::soc::{system::System, utils::SystemSpec};
+
+    let bdd_0 = bdd!(5;0;[("1+2",[(1;2,3)]);("3+2",[(2;4,5);(3;4,0)]);("0+4",[(4;0,6);(5;6,0)]);("",[(6;0,0)])]);
+    let bdd_1 = bdd!(5;1;[("0+3",[(1;2,3)]);("1",[(2;4,0);(3;0,4)]);("",[(4;0,0)])]);
+    let mut system = system![bdd_0, bdd_1]?;
+    system.fix(vec![0, 3], true)?;
+    system.scan_absorb_lin_eqs(Id::new(0))?;
+
+    let json = serde_json::to_string(&system)?;
+    let restored: System = serde_json::from_str(&json)?;
+    assert_eq!(restored.get_lin_bank_size(), system.get_lin_bank_size());
+    assert_eq!(restored.fingerprint(), system.fingerprint());
+
+    let spec = SystemSpec::from_system(&system);
+    let restored: SystemSpec = serde_json::from_str(&serde_json::to_string(&spec)?)?;
+    assert_eq!(utils::build_system_from_spec(restored)?.fingerprint(), utils::build_system_from_spec(spec)?.fingerprint());
+    Ok(())
+}
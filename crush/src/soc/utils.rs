@@ -1,24 +1,31 @@
-//! Module providing a set of tools to create `System` of bdds from file,
-//! print a Bdd to .dot format for visualization, print systems to .bdd format
-//! and needed structures for it.
+//! Module providing a set of tools to create `System` of bdds from specifications and needed
+//! structures for it.
+//!
+//! Parsing specifications from the .bdd format is done in the `parse` module, reading and writing
+//! files in the `io` module. A `Bdd` is built from a whole `BddSpec` by `build_bdd_from_spec`, or
+//! level by level by a `BddBuilder` as the levels are parsed. The dumps of other bdd libraries are
+//! read into `BddSpec`s, and bdds written for them, by the `dump` module.
 
 use std::collections::HashSet;
-use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Write};
-use std::path::PathBuf;
-use std::process::Child;
-use std::str::FromStr;
 
-use nom::digit;
-use nom::types::CompleteStr;
+use vob::Vob;
 
+use crate::{metrics, AHashMap};
 use crate::soc::{
     bdd::Bdd,
+    error::SocError,
     Id,
+    node::Node,
+    store::NodeStore,
     system::System};
 
+pub use dump::{read_buddy, read_dddmp, write_dddmp};
+
+pub mod dump;
+
 /// A specification of a `Node` inside a Bdd
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeSpec {
     id:Id,
     e0:Id,
@@ -50,6 +57,7 @@ impl NodeSpec {
 /// later remove when creating the `System`. A vec![1,2,4] as `lhs` means
 /// the equations is x1 + x2 + x4.
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LevelSpec {
     lhs:Vec<i64>,
     rhs:Vec<NodeSpec>
@@ -86,10 +94,22 @@ impl LevelSpec {
         self.rhs.iter_mut().map(|node| node.flip_edge()).collect()
     }
 
+    /// Replace each variable `x` of `lhs` by `f(x)`, keeping the `-1`.
+    pub fn relabel_vars<F: Fn(usize) -> usize>(&mut self, f: F) {
+        for var in self.lhs.iter_mut().filter(|var| **var >= 0) {
+            *var = f(*var as usize) as i64;
+        }
+    }
+
+    /// Return the largest variable of `lhs`, if any.
+    fn max_var(&self) -> Option<usize> {
+        self.lhs.iter().filter(|var| **var >= 0).max().map(|var| *var as usize)
+    }
 }
 
 /// A specification of Bdd
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BddSpec {
     id: Id,
     levels:Vec<LevelSpec>,
@@ -103,10 +123,67 @@ impl BddSpec {
              levels
          }
      }
+
+    /// Return the `BddSpec` of `bdd`, the inverse of `build_bdd_from_spec`.
+    ///
+    /// The nodes are numbered from 1, level by level and by increasing id within a level, as done
+    /// by `Bdd::renumber`, so the spec doesn't depend on the ids the nodes got while solving. An
+    /// edge to a node absent from the `Bdd` is considered as removed, as in
+    /// `Bdd::remove_all_dead_ends_start`.
+    pub fn from_bdd<S: NodeStore>(bdd: &Bdd<S>) -> BddSpec {
+        let mut spec_ids = AHashMap::default();
+        let mut levels_ids = Vec::with_capacity(bdd.get_levels_size());
+        for level in bdd.iter_levels() {
+            let mut ids: Vec<Id> = level.iter_nodes().map(|(id, _)| *id).collect();
+            ids.sort_unstable();
+            for id in ids.iter() {
+                spec_ids.insert(*id, Id::new(spec_ids.len() + 1));
+            }
+            levels_ids.push(ids);
+        }
+        let edge = |e: Option<Id>| e.and_then(|e| spec_ids.get(&e).copied()).unwrap_or(Id::new(0));
+        let levels = bdd.iter_levels().zip(levels_ids.iter()).map(|(level, ids)| {
+            let lhs = level.iter_set_lhs().map(|var| var as i64).collect();
+            let rhs = ids.iter().map(|id| {
+                let node = level.get_node(id).unwrap();
+                NodeSpec::new(spec_ids[id], edge(node.get_e0()), edge(node.get_e1()))
+            }).collect();
+            LevelSpec::new(lhs, rhs)
+        }).collect();
+        BddSpec::new(bdd.get_id(), levels)
+    }
+
+    /// Return the id of the bdd.
+    pub fn get_id(&self) -> Id {
+        self.id
+    }
+
+    /// Set the id of the bdd.
+    pub fn set_id(&mut self, id: Id) {
+        self.id = id;
+    }
+
+    /// Replace each variable `x` of the levels by `f(x)`, see `LevelSpec::relabel_vars`.
+    pub fn relabel_vars<F: Fn(usize) -> usize>(&mut self, f: F) {
+        for level in self.levels.iter_mut() {
+            level.relabel_vars(&f);
+        }
+    }
+
+    /// Add `offset` to each variable of the levels, e.g. to get the same bdd in a later round.
+    pub fn offset_vars(&mut self, offset: usize) {
+        self.relabel_vars(|var| var + offset);
+    }
+
+    /// Return the largest variable of the levels, if any.
+    pub fn max_var(&self) -> Option<usize> {
+        self.levels.iter().filter_map(|level| level.max_var()).max()
+    }
 }
 
 /// A specification of a system of Bdd
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SystemSpec {
     nvar:usize,
     bdds:Vec<BddSpec>,
@@ -120,6 +197,95 @@ impl SystemSpec {
             bdds
         }
     }
+
+    /// Return the `SystemSpec` of `system`, the inverse of `build_system_from_spec`, with the bdds
+    /// sorted by id (see `BddSpec::from_bdd`).
+    ///
+    /// Like `io::print_system_to_file`, only the bdds are kept: the linear equations already
+    /// absorbed in the `LinBank` are not part of the spec.
+    pub fn from_system(system: &System) -> SystemSpec {
+        let mut ids: Vec<Id> = system.iter_bdds().map(|(id, _)| *id).collect();
+        ids.sort_unstable();
+        let bdds = ids.iter()
+            .map(|id| BddSpec::from_bdd(&system.get_bdd(*id).unwrap().borrow()))
+            .collect();
+        SystemSpec::new(system.get_nvar(), bdds)
+    }
+
+    /// Return the number of variables of the system.
+    pub fn get_nvar(&self) -> usize {
+        self.nvar
+    }
+
+    /// Return an iterator over the bdds of the system.
+    pub fn iter_bdds(&self) -> std::slice::Iter<'_, BddSpec> {
+        self.bdds.iter()
+    }
+
+    /// Return the id following the largest id of the bdds, 0 if there is none.
+    pub fn next_bdd_id(&self) -> Id {
+        Id::new(self.bdds.iter().map(|bdd| *bdd.id + 1).max().unwrap_or(0))
+    }
+
+    /// Push `bdd` to the system, increasing `nvar` if needed to cover its variables.
+    ///
+    /// The id of `bdd` is kept as is, see `build_system_from_spec` for what happens when two bdds
+    /// share an id.
+    pub fn push_bdd(&mut self, bdd: BddSpec) {
+        if let Some(var) = bdd.max_var() {
+            self.nvar = self.nvar.max(var + 1);
+        }
+        self.bdds.push(bdd);
+    }
+
+    /// Replace each variable `x` of the bdds by `f(x)`, increasing `nvar` if needed to cover the
+    /// new variables.
+    pub fn relabel_vars<F: Fn(usize) -> usize>(&mut self, f: F) {
+        for bdd in self.bdds.iter_mut() {
+            bdd.relabel_vars(&f);
+        }
+        if let Some(var) = self.bdds.iter().filter_map(|bdd| bdd.max_var()).max() {
+            self.nvar = self.nvar.max(var + 1);
+        }
+    }
+
+    /// Add `offset` to each variable of the bdds, increasing `nvar` by as much.
+    pub fn offset_vars(&mut self, offset: usize) {
+        let nvar = self.nvar;
+        self.relabel_vars(|var| var + offset);
+        self.nvar = nvar + offset;
+    }
+
+    /// Add `offset` to the id of each bdd.
+    ///
+    /// The ids of the nodes of a `Bdd` are built from its id and must stay unique in the `System`,
+    /// see the `Bdd` module documentation, so the ids are expected to stay below 10000.
+    pub fn offset_ids(&mut self, offset: usize) {
+        for bdd in self.bdds.iter_mut() {
+            bdd.id = Id::new(*bdd.id + offset);
+        }
+    }
+
+    /// Append the bdds of `other` to the system, their ids being offset such that they follow the
+    /// ones of the system. `nvar` becomes the largest of both, the variables are shared.
+    pub fn concat(&mut self, mut other: SystemSpec) {
+        other.offset_ids(*self.next_bdd_id());
+        self.nvar = self.nvar.max(other.nvar);
+        self.bdds.append(&mut other.bdds);
+    }
+
+    /// Push `n` copies of `bdd`, the variables of the `i`-th copy being offset by `i*var_offset`,
+    /// as done to assemble the shards of the successive rounds of a cipher. The copies get the
+    /// ids following the ones of the system.
+    pub fn push_copies(&mut self, bdd: &BddSpec, n: usize, var_offset: usize) {
+        let first_id = *self.next_bdd_id();
+        for i in 0..n {
+            let mut copy = bdd.clone();
+            copy.id = Id::new(first_id + i);
+            copy.offset_vars(i * var_offset);
+            self.push_bdd(copy);
+        }
+    }
 }
 
 /// From a `SystemSpec` build a `System` following the specifications.
@@ -127,399 +293,111 @@ impl SystemSpec {
 /// We create an empty `System` with the `nvar` set to the spec and 
 /// push to it every `Bdd` created using the spec.
 /// If some Id of Bdds in the spec are not unique their order is used as Id
-pub fn build_system_from_spec(mut spec: SystemSpec) -> System {
+///
+/// Will return a `SocError::Bdd` if a bdd involves a variable beyond the `nvar` of the spec.
+///
+/// The building is profiled as the `system.build` phase (see `metrics::phase`).
+pub fn build_system_from_spec(mut spec: SystemSpec) -> Result<System, SocError> {
+    let _build = metrics::phase("system.build");
     let mut system = System::new();
-    system.set_nvar(spec.nvar as usize);
+    system.set_nvar(spec.nvar);
     let ids:HashSet<Id> = spec.bdds.iter().map(|bdd| bdd.id).collect();
     let nbr_bdd = spec.bdds.len();
+    let mut size = 0;
     for (i,bdd_spec) in spec.bdds.iter_mut().enumerate(){
          if ids.len() != nbr_bdd {
             bdd_spec.id = Id::new(i);
         }
-        system.push_bdd(build_bdd_from_spec(bdd_spec,spec.nvar as usize)).expect("No reason to crash since we are using the nvar of the system
-        to set the one of the Bdds we are pushing");
+        check_vars(bdd_spec, spec.nvar)?;
+        let bdd = build_bdd_from_spec(bdd_spec,spec.nvar);
+        size += bdd.get_size();
+        metrics::observe_nodes(size);
+        system.push_bdd(bdd).map_err(|e| SocError::Bdd { bdd: bdd_spec.id, message: e.to_string() })?;
+    }
+    Ok(system)
+}
+
+/// Return a `SocError::Bdd` if `spec` involves a variable beyond `nvar`, which
+/// `build_bdd_from_spec` can't build.
+pub fn check_vars(spec: &BddSpec, nvar: usize) -> Result<(), SocError> {
+    match spec.max_var() {
+        Some(var) if var >= nvar => Err(SocError::Bdd {
+            bdd: spec.id,
+            message: format!("variable {} is beyond the {} variables of the system", var, nvar),
+        }),
+        _ => Ok(()),
     }
-    system
 }
 
 /// From a `BddSpec` and a `nvar` build a `Bdd` following the specifications.
 /// 
 /// We create an empty `Bdd`, set its `id` according to the spec then create all the levels
-/// (removing the `-1` from the `lhs` beforehand) without connecting the nodes.
-/// 
-/// Once all the level have been created we connect all the nodes to each other following the
-/// `e0` and `e1` specs. All the id of the nodes are then reset to initialize `next_id` of the
-/// `Bdd`. Finally we remove any jumping edges by calling `add_same_edge_node_at_level` on all the
-/// levels of the `Bdd`.
-/// WARNING! There is an unconfirmed case which indicates that the removal of jumping edges does NOT
-/// work as intended! This will be investigated when I get the time.
-// FIXME, the case referred to is the original PRINCE or LowMC S-box used in our differential
-// experiments. It was built from a .bdd file, and we did had to change the .bdd to not include
-// jumping edges b/c they caused us trouble. I was not aware of the fact that this fn is supposed
-// to handle jumping edges at the time, otherwise I would have looked into it then. Maybe it's a
-// too early short-circuit again?
-// Of course, it may also have been some other error on our part, which is why this case is
-// "unconfirmed".
+/// (removing the `-1` from the `lhs` beforehand), each with all its nodes already connected
+/// following the `e0` and `e1` specs. `next_id` of the `Bdd` is then set past the largest id of
+/// the spec. Finally we remove any jumping edges with `Bdd::normalize_jumping_edges`. See
+/// `BddBuilder` to build the levels one at a time.
+///
+/// Will panic if the spec involves a variable beyond `nvar`, see `check_vars`.
 pub fn build_bdd_from_spec(spec: &mut BddSpec, nvar: usize) -> Bdd {
-    let mut bdd = Bdd::new();
-    bdd.set_id(spec.id);
-    let next_id = spec.levels.iter().fold(0,|last_id,level|
-    {
-        let level_id =level.rhs.iter().fold(0,|last_id_level,node| {
-            if *node.id > last_id_level {
-                *node.id
-            } else {
-                last_id_level
-            }
-        });
-        if level_id>last_id {
-            level_id
-        } else {
-            last_id
-        }
-    });
-    for (i,level_spec) in spec.levels.iter_mut().enumerate(){
-        level_spec.remove_minus_one();
-        bdd.add_level();
-        bdd.set_lhs_level(i,level_spec.lhs.iter().map(|i| *i as usize).collect(),nvar);
-        bdd.add_nodes_to_level(i,level_spec.rhs.iter().map(|node| node.id).collect());
-    }
-    bdd.set_next_id(next_id+1);
-    for level_spec in spec.levels.iter(){
-        for node_spec in level_spec.rhs.iter(){
-            if *node_spec.e0 != 0 {
-                bdd.connect_nodes_from_spec(node_spec.id, node_spec.e0, 0);
-            }
-            if *node_spec.e1 != 0 {
-                bdd.connect_nodes_from_spec(node_spec.id, node_spec.e1, 1);
-            }
-        }
-    }
-    if spec.levels.len() > 2 {
-        for i in 1..spec.levels.len()-2 {
-            bdd.add_same_edges_node_at_level(i);
-        }
-    }
-    bdd
-}
-
-
-named!(i64 <CompleteStr, i64>,
-ws!(
-    map_res!(digit,|CompleteStr(s)| FromStr::from_str(s))
-));
-
-named!(usize <CompleteStr, usize>,
-ws!(
-    map_res!(digit,|CompleteStr(s)| FromStr::from_str(s))
-));
-
-named!(line_break <CompleteStr,Option<CompleteStr>>,
-    opt!(alt!(tag!("\n")|tag!("\r\n")))
-);
-
-named!(minus_one <CompleteStr, i64>,
-ws!(
-    map_res!(
-        recognize!(
-            do_parse!(
-                opt!(tag!("-")) >>
-                digit >>
-                ()
-            )
-        ),
-    |CompleteStr(s)| FromStr::from_str(s))
-));
-
-named!(parameters<CompleteStr, (usize,usize)>,
-    do_parse!(
-        a: usize >>
-        b: usize >>
-        (a,b)
-));
-
-named!(var<CompleteStr,i64>,
-    do_parse!(   
-        opt!(alt!(char!('+')))>>
-        a: alt!(i64 | minus_one)>>
-        (a)
-));
-
-//pub because we use it in the bdd! macro
-named!(pub vars<CompleteStr, Vec<i64>>,
-    many0!(
-        var
-));
-
-named!(lhs<CompleteStr,Vec<i64>>,
-    do_parse!(
-        a:vars>>
-        (a)
-));
-
-named!(node<CompleteStr,NodeSpec>,
-    do_parse!(
-        char!('(')>>
-        id: usize >>
-        char!(';')>>
-        e0: usize >>
-        char!(',')>>
-        e1: usize >>
-        char!(')')>>
-        (NodeSpec::new(Id::new(id), Id::new(e0), Id::new(e1)))
-    )
-);
-
-named!(rhs<CompleteStr,Vec<NodeSpec>>,
-    many0!(
-        node
-));
-
-named!(level<CompleteStr,LevelSpec>,
-    do_parse!(
-        a:lhs>>
-        char!(':')>>
-        b:rhs>>
-        char!('|')>>
-        line_break>>
-        (LevelSpec::new(a, b))
-));
-
-named!(levels<CompleteStr,Vec<LevelSpec>>,
-    many0!(
-        level
-));
-
-named!(bdd<CompleteStr,BddSpec>,
-    do_parse!(
-        param: parameters>>
-        line_break>>
-        levels: levels>>
-        tag!("---")>>
-        line_break>>
-        (BddSpec::new(Id::new(param.0 as usize), levels))
-));
-
-named!(bdds<CompleteStr,Vec<BddSpec>>,
-    many0!(
-        bdd
-));
-
-named!(full_parser<CompleteStr,SystemSpec>,
-    do_parse!(
-        params:parameters>>
-        line_break>>
-        bdds:bdds>>
-        (SystemSpec::new(params.0,bdds))
-    )
-);
-
-/// Return a SystemSpec from the parsing of a .bdd file using the correct format
-pub fn parse_system_spec_from_file(path: &PathBuf) -> SystemSpec {
-    let file = File::open(path).unwrap();
-    let mut file_content = String::new();
-    BufReader::new(file).read_to_string(&mut file_content).unwrap();
-    let result = full_parser(CompleteStr(&file_content)).expect("Parsing file");
-    result.1
-}
-
-/// Write `.dot` language representation of the given bdd to a file at path
-pub fn print_bdd_to_dot_format(bdd: &Bdd, path:&PathBuf) {
-    let write_file = File::create(path).unwrap();
-    let mut writer = BufWriter::new(&write_file);
-
-    to_dot_format(&bdd, &mut writer);
-
-    writer.flush().expect("Failed to write to file");
-}
-
-/// Write .bdd representation of a bdd to a Buffered write of a file
-fn print_bdd_to_file_format(bdd: &Bdd,writer: &mut BufWriter<&File>){
-    writeln!(writer, "{} {}",*bdd.get_id(),bdd.iter_levels().count()).unwrap();
-    for level in bdd.iter_levels() {
-        for (i,bit) in level.iter_set_lhs().enumerate(){
-            if i != 0 {
-                write!(writer,"+").unwrap();
-            }
-            write!(writer,"{}",bit).unwrap();
-        }
-        write!(writer,":").unwrap();
-        for (id,node) in level.iter_nodes() {
-            let e0 = match node.get_e0(){
-                Some(e0) => *e0,
-                None => 0,
-            };
-            let e1 = match node.get_e1(){
-                Some(e1) => *e1,
-                None => 0,
-            };
-            write!(writer,"({};{},{})",*id,e0,e1).unwrap();
-        }
-        writeln!(writer,"|").unwrap();
+    let mut builder = BddBuilder::new(spec.id, nvar);
+    for level_spec in spec.levels.iter_mut() {
+        builder.add_level(level_spec).expect("The variables of the spec are below nvar");
     }
-    writeln!(writer,"---").unwrap();
+    builder.build()
 }
 
-/// Write .bdd representation of a system to a file at path
-pub fn print_system_to_file(system: &System, path: &PathBuf){
-    let write_file = File::create(path).unwrap();
-    let mut writer = BufWriter::new(&write_file);
-    writeln!(writer,"{} {}",system.get_nvar(),system.iter_bdds().len()).unwrap();
-    let mut ids = Vec::new();
-    for bdd in system.iter_bdds() {
-        ids.push(bdd.0);
-}
-    ids.sort();
-    for id in ids {
-        print_bdd_to_file_format(&system.get_bdd(*id).unwrap().borrow(), &mut writer);
-    }
+/// Builder of a `Bdd` from the `LevelSpec`s of its levels, added one at a time from the top as
+/// done by `build_bdd_from_spec`, such that a `Bdd` can be built while its levels are parsed (see
+/// `parse::build_system_from_reader`) without holding its whole `BddSpec`.
+pub struct BddBuilder {
+    bdd: Bdd,
+    nvar: usize,
+    /// The largest id of the nodes of the levels added so far.
+    last_id: usize,
 }
 
-/// Draw a graph representation of the Shard, using GraphViz.
-/// The output format is PDF.
-///
-/// It is possible to use another function to instead output the dot-file of the shard. This allows
-/// the user to draw using GraphViz as desired. This function is intended as a easy-to-use way
-/// of generating snapshots of state. However, be mindful that GraphViz may use quite some time to
-/// finish drawing the shard, even after this function returns the handle to the GraphViz process.
-/// It is therefore *highly* recommended to always  use`.wait` on the handle to ensure that the
-/// drawing process is complete, before exiting the main thread!
-/// By returning the child handle, the caller is now free to decide when to wait for GraphViz to
-/// finish drawing.
-/// ---
-/// **NOTE:** Requires that `GraphViz` is installed!
-/// Tested on a Windows with Graphviz 3.0.0
-///
-/// **WARNING!** The resulting output file may be very large!
-/// **WARNING** Failing to wait on the child process may lead to the failure of drawing the shard
-/// to file.
-/// **NOTE 1:** "Large" shards will take time to write to file. Patience is advised.
-/// **NOTE 2:** When opening the pdf based on a "large" shard, it may initially appear empty.
-/// When this is the case, it may be because it takes some time to load, or that you are viewing an
-/// empty part of the drawing. Scrolling or zooming in/out may help.
-/// ("Large" is hard to quantify, but my test file is only slightly more than 2mb large, yet took
-/// many minutes for GraphViz to write to file. (Output size is about 6mb, GraphViz spent about
-/// 30 min to draw...)).
-pub fn draw_shard_as_pdf(shard: &Bdd, path:&PathBuf) -> Child {
-    use std::process::{Command, Stdio};
-
-    let mut args = vec!["-Tpdf",];
-    let mut path = path.clone();
-    path.set_extension("pdf");
-
-    let out_path = format!("-o{}", path.as_os_str().to_str().unwrap());
-    args.push(&out_path);
-
-
-    let mut dot = Command::new("dot")
-        .args(&args)
-        .stdin(Stdio::piped())
-        .spawn()
-        .expect("failed to draw the shard to PDF.");
-
-    {
-        let child_in = dot.stdin.take().expect("Child stdin not captured");
-        let mut writer = BufWriter::new(child_in);
-
-        to_dot_format(&shard, &mut writer);
-        writer.flush().unwrap();
-        // Child stdin is dropped, closing the child stdin's underlying file handle. This will
-        // essentially give an "EOF" to GraphViz, making it no longer wait on user input and thus
-        // start processing/drawing the given data.
+impl BddBuilder {
+    /// Start building the `Bdd` of id `id` over `nvar` variables.
+    pub fn new(id: Id, nvar: usize) -> BddBuilder {
+        let mut bdd = Bdd::new();
+        bdd.set_id(id);
+        BddBuilder { bdd, nvar, last_id: 0 }
     }
-    dot
-}
 
-/// Write .dot language representation of the given shard into `writer`.
-fn to_dot_format<W: Write> (shard: &Bdd, writer: &mut BufWriter<W>) {
-    // Setup
-    let num_levels = shard.iter_levels().count();
-
-    // Metadata:
-    writeln!(writer, "digraph \"DD\" {{").unwrap(); // I believe DD is just an ID.
-    writeln!(writer, "center = true;").unwrap();
-    writeln!(writer, "edge [dir = none];").unwrap(); // No arrowheads on the arrows
-
-    // Writing the LHS of the graph
-    writeln!(writer, "{{ node [shape = plaintext];").unwrap(); // No "bubble" around the algebraic expression
-    writeln!(writer, "edge [style = invis];").unwrap(); // Draw no edges
-    writeln!(writer, "\"CONST NODES\" [style = invis];").unwrap(); // End node? Invisible
-
-    for (i,level) in shard.iter_levels().enumerate() {
-        write!(writer, "\"{}. ",i).unwrap(); // Line/row number
-        if level.iter_set_lhs().count() == 0 { // No variable is set
-            write!(writer, "0").unwrap();
-        } else {
-            for (j, bit) in level.iter_set_lhs().enumerate() {
-                if j > 0 {
-                    write!(writer, " + ").unwrap();
-                }
-                write!(writer, "x{}", bit).unwrap();
-            }
+    /// Add the level of `spec` below the levels added so far, with its nodes connected following
+    /// their `e0` and `e1` specs to the nodes of the next levels. The `-1` are removed from its
+    /// `lhs` beforehand (see `LevelSpec::remove_minus_one`).
+    ///
+    /// Will return a `SocError::Bdd` if the level involves a variable beyond `nvar`, as
+    /// `check_vars` does.
+    pub fn add_level(&mut self, spec: &mut LevelSpec) -> Result<(), SocError> {
+        let bdd_id = *self.bdd.get_id();
+        if let Some(var) = spec.max_var().filter(|var| *var >= self.nvar) {
+            return Err(SocError::Bdd {
+                bdd: self.bdd.get_id(),
+                message: format!("variable {} is beyond the {} variables of the system", var, self.nvar),
+            });
         }
-        write!(writer, "\" -> ").unwrap();
-        if i == num_levels - 2 { // Skip terminal lvl + started at index 0 ==> -2 ?
-            break;
+        spec.remove_minus_one();
+        let node_id = |spec_id:Id| Id::new(*spec_id*10000 + bdd_id);
+        let edge = |spec_id:Id| if *spec_id != 0 {Some(node_id(spec_id))} else {None};
+        let mut lhs = Vob::from_elem(self.nvar, false);
+        for var in spec.lhs.iter().map(|i| *i as usize) {
+            // a variable appearing twice cancels out, as in Level::set_lhs
+            let set = lhs[var];
+            lhs.set(var, !set);
         }
+        self.last_id = spec.rhs.iter().map(|node| *node.id).fold(self.last_id, usize::max);
+        self.bdd.add_level_with_nodes(lhs, spec.rhs.iter().map(|node_spec|
+            (node_id(node_spec.id), Node::with_edges(edge(node_spec.e0), edge(node_spec.e1)))));
+        Ok(())
     }
-    writeln!(writer, "\"CONST NODES\";\n}}").unwrap();
-
-    // Writing the RHS of the graph
-    for (i,level) in shard.iter_levels().enumerate() {
-        write!(writer, "{{ rank = same; ").unwrap(); // Tell GraphViz that these are on the same level
-        write!(writer, "\"{}. ", i).unwrap(); // Line/row/"rank" number
-
-        // I'm a bit unsure of the purpose of this if-else. I understand what it does, but not why.
-        // Theory: Links these to the rank above w/same "ID"? Printed dot file both support and object
-        // to this theory, and hard to find something in the GV doc.
-        if level.iter_set_lhs().count() == 0 { // No variable is set
-            write!(writer, "0").unwrap();
-        } else {
-            for (j,bit) in level.iter_set_lhs().enumerate() {
-                if j > 0 {
-                    write!(writer, " + ").unwrap();
-                }
-                write!(writer, "x{}", bit).unwrap();
-            }
-        }
-        writeln!(writer, "\";").unwrap();
-
-        // Add node to rank. (In GraphViz: level == rank)
-        for (id,_) in level.iter_nodes(){
-            // Remove the ID by setting label = "", and reducing drawing size by making the node shape to a point.
-            writeln!(writer, "\"{}\" [label = \"\"; shape = point; width = 0.06];", *id).unwrap();
-        }
-        writeln!(writer, "}}").unwrap(); // Rank (/level) done
 
-        if i == num_levels - 2 { // Skip terminal lvl + started at index 0 ==> -2 ?
-            break;
-        }
+    /// Return the `Bdd`, its `next_id` set past the largest id of the levels and its jumping edges
+    /// removed with `Bdd::normalize_jumping_edges`.
+    pub fn build(mut self) -> Bdd {
+        self.bdd.set_next_id(self.last_id + 1);
+        self.bdd.normalize_jumping_edges();
+        self.bdd
     }
-
-    // Add terminal node, set node shape to box
-    writeln!(writer, "{{ rank = same; \"CONST NODES\";").unwrap(); //
-    writeln!(writer, "{{ node [shape = box]; \"{}\";", *shard.iter_levels().last().unwrap()
-        .iter_nodes().last().unwrap()
-        .0).unwrap();
-    writeln!(writer, "}}").unwrap();
-    writeln!(writer, "}}").unwrap();
-
-    // Add edges between relevant nodes, including correct style
-    for level in shard.iter_levels() {
-        for (id,node) in level.iter_nodes() {
-            if let Some(e0) = node.get_e0() {
-                writeln!(writer, "\"{}\" -> \"{}\" [style = dashed];",*id,*e0).unwrap();
-            }
-            if let Some(e1) = node.get_e1() {
-                writeln!(writer, "\"{}\" -> \"{}\";",*id,*e1).unwrap();
-            }
-        }
-    }
-    // Label the terminal node as the True node
-    writeln!(writer, "\"{}\" [label = \"T\"];", *shard.iter_levels().last().unwrap()
-        .iter_nodes().last().unwrap()
-        .0).unwrap();
-    writeln!(writer, "}}").unwrap();
 }
-
-
@@ -0,0 +1,417 @@
+//! Readers of the dumps of other bdd libraries, such that the Shards they build can be imported as
+//! `BddSpec`s:
+//! - `read_buddy` reads the format of `bdd_save` of BuDDy;
+//! - `read_dddmp` reads the text mode of the DDDMP format of CUDD (`Dddmp_cuddBddStore` and
+//!   `Dddmp_cuddBddArrayStore`), with its complemented edges.
+//!
+//! Conversely, `write_dddmp` writes a `Bdd` in the text mode of the DDDMP format, to be loaded by
+//! the tools built on CUDD (`Dddmp_cuddBddLoad`). Only the `Bdd`s whose levels each test a single
+//! variable are functions of the kind of these libraries, e.g. the Shards read by this module.
+//!
+//! Sylvan only serializes to a binary format, which isn't read. Its bdds (or those of any library)
+//! can still be imported by walking their nodes into a `DumpGraph`, which is what both readers do.
+//!
+//! A bdd of these libraries is a function of its variables, each node testing one variable and the
+//! variables following the same order along any path. Its `BddSpec` has one level per variable it
+//! depends on, whose lhs is that variable alone, in the order of the library, followed by the sink
+//! level: a path to the constant 1 leads to the sink, while an edge to the constant 0 is no edge.
+//! The variables of the library keep their index, `BddSpec::relabel_vars` mapping them to the
+//! variables of a system. An edge skipping variables of the order becomes a jumping edge, which
+//! `build_bdd_from_spec` normalizes.
+
+use std::collections::HashSet;
+use std::io::{self, BufRead, Error, ErrorKind, Write};
+
+use crate::AHashMap;
+use crate::soc::{bdd::Bdd, error::SocError, Id, store::NodeStore};
+use crate::soc::utils::{BddSpec, LevelSpec, NodeSpec};
+
+/// An edge of a `DumpGraph` to the node `node`, computing its negation if `complemented`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DumpEdge {
+    pub node: usize,
+    pub complemented: bool,
+}
+
+impl DumpEdge {
+    /// Return the edge to `node`, complemented if `complemented`.
+    pub fn new(node: usize, complemented: bool) -> DumpEdge {
+        DumpEdge { node, complemented }
+    }
+
+    /// Return the regular edge to `node`.
+    pub fn regular(node: usize) -> DumpEdge {
+        DumpEdge::new(node, false)
+    }
+
+    /// Return the edge `self` complemented once more by `complemented`.
+    fn complement(self, complemented: bool) -> DumpEdge {
+        DumpEdge::new(self.node, self.complemented ^ complemented)
+    }
+}
+
+/// A node of a `DumpGraph`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpNode {
+    /// The constant 0 or 1.
+    Constant(bool),
+    /// A node testing `var`, leading to `low` if it is 0 and to `high` otherwise.
+    Var { var: usize, low: DumpEdge, high: DumpEdge },
+}
+
+/// The nodes of the bdds of another library along with its order of the variables, from which the
+/// `BddSpec` of each of these bdds is made, see the module documentation.
+#[derive(Debug, Default, Clone)]
+pub struct DumpGraph {
+    nodes: AHashMap<usize, DumpNode>,
+    positions: AHashMap<usize, usize>,
+}
+
+impl DumpGraph {
+    /// Return an empty `DumpGraph`, whose variables are in their natural order.
+    pub fn new() -> DumpGraph {
+        DumpGraph::default()
+    }
+
+    /// Add the node of id `id`, replacing the previous one of that id if any.
+    pub fn add_node(&mut self, id: usize, node: DumpNode) {
+        self.nodes.insert(id, node);
+    }
+
+    /// Return the node of id `id`, if any.
+    pub fn get_node(&self, id: usize) -> Option<&DumpNode> {
+        self.nodes.get(&id)
+    }
+
+    /// Set the position of `var` in the order of the variables, which defaults to `var`.
+    pub fn set_position(&mut self, var: usize, position: usize) {
+        self.positions.insert(var, position);
+    }
+
+    /// Return the position of `var` in the order of the variables.
+    pub fn get_position(&self, var: usize) -> usize {
+        self.positions.get(&var).copied().unwrap_or(var)
+    }
+
+    /// Return the `BddSpec` of id `id` of the bdd starting at `root`. The nodes are numbered from
+    /// 1, level by level.
+    ///
+    /// Return a `SocError::Bdd` if `root` leads to a missing node or to a node whose variable isn't
+    /// after the one of its parent in the order, or if the bdd is the constant 0, which no
+    /// `BddSpec` represents.
+    pub fn bdd_spec(&self, id: Id, root: DumpEdge) -> Result<BddSpec, SocError> {
+        let error = |message: String| SocError::Bdd { bdd: id, message };
+        // The nodes testing a variable reached from root, by position of their variable, with
+        // their edges. A node reached through complemented and regular edges is two nodes.
+        type Edges = (DumpEdge, Option<DumpEdge>, Option<DumpEdge>);
+        let mut levels: AHashMap<usize, Vec<Edges>> = AHashMap::default();
+        let mut seen = HashSet::new();
+        let mut stack = Vec::new();
+        // The edges to the constant 0 are removed and those to 1 are kept, as edges to the sink
+        let mut child = |edge: DumpEdge, parent: Option<usize>, stack: &mut Vec<DumpEdge>| {
+            match self.nodes.get(&edge.node) {
+                None => Err(error(format!("node {} is missing", edge.node))),
+                Some(DumpNode::Constant(value)) => Ok((*value ^ edge.complemented).then_some(edge)),
+                Some(DumpNode::Var { var, .. }) => {
+                    let position = self.get_position(*var);
+                    if parent.is_some_and(|parent| position <= parent) {
+                        return Err(error(format!("node {} isn't after its parent in the order", edge.node)));
+                    }
+                    if seen.insert(edge) {
+                        stack.push(edge);
+                    }
+                    Ok(Some(edge))
+                }
+            }
+        };
+        if child(root, None, &mut stack)?.is_none() {
+            return Err(error("the bdd is the constant 0".to_string()));
+        }
+        while let Some(edge) = stack.pop() {
+            if let Some(DumpNode::Var { var, low, high }) = self.nodes.get(&edge.node) {
+                let position = self.get_position(*var);
+                let low = child(low.complement(edge.complemented), Some(position), &mut stack)?;
+                let high = child(high.complement(edge.complemented), Some(position), &mut stack)?;
+                levels.entry(position).or_default().push((edge, low, high));
+            }
+        }
+        let mut positions: Vec<usize> = levels.keys().copied().collect();
+        positions.sort_unstable();
+        let mut spec_ids = AHashMap::default();
+        for position in positions.iter() {
+            let nodes = levels.get_mut(position).unwrap();
+            nodes.sort_unstable();
+            for (edge, _, _) in nodes.iter() {
+                spec_ids.insert(*edge, Id::new(spec_ids.len() + 1));
+            }
+        }
+        let sink = Id::new(spec_ids.len() + 1);
+        let spec_id = |edge: Option<DumpEdge>| match edge {
+            None => Id::new(0),
+            Some(edge) => spec_ids.get(&edge).copied().unwrap_or(sink),
+        };
+        let mut spec_levels: Vec<LevelSpec> = positions.iter().map(|position| {
+            let nodes = &levels[position];
+            let var = match self.nodes[&nodes[0].0.node] {
+                DumpNode::Var { var, .. } => var as i64,
+                DumpNode::Constant(_) => unreachable!("Only the nodes testing a variable have a level"),
+            };
+            let rhs = nodes.iter()
+                .map(|(edge, low, high)| NodeSpec::new(spec_ids[edge], spec_id(*low), spec_id(*high)))
+                .collect();
+            LevelSpec::new(vec![var], rhs)
+        }).collect();
+        spec_levels.push(LevelSpec::new(vec![], vec![NodeSpec::new(sink, Id::new(0), Id::new(0))]));
+        Ok(BddSpec::new(id, spec_levels))
+    }
+}
+
+/// Read the bdd written by `bdd_save` of BuDDy from `reader`, as the `BddSpec` of id `id`.
+///
+/// The dump starts with its number of nodes and of variables, followed by the position of each
+/// variable in the order and then by the nodes, `id var low high`, the root being the last one.
+/// The ids 0 and 1 are the constants.
+///
+/// Return an `Error` of kind `ErrorKind::InvalidData` if the dump is malformed or is the constant
+/// 0 (see `DumpGraph::bdd_spec`).
+pub fn read_buddy<R: BufRead>(reader: R, id: Id) -> io::Result<BddSpec> {
+    let mut lines = DumpLines::new(reader);
+    let mut graph = DumpGraph::new();
+    graph.add_node(0, DumpNode::Constant(false));
+    graph.add_node(1, DumpNode::Constant(true));
+    if !lines.next_line()? {
+        return Err(lines.invalid("missing header"));
+    }
+    let header = lines.integers::<usize>()?;
+    let (nnodes, nvars) = match header[..] {
+        [0, 0, root] => return Ok(graph.bdd_spec(id, DumpEdge::regular(root))?),
+        [nnodes, nvars] => (nnodes, nvars),
+        _ => return Err(lines.invalid("expected the number of nodes and of variables")),
+    };
+    if nvars > 0 {
+        if !lines.next_line()? {
+            return Err(lines.invalid("missing order of the variables"));
+        }
+        let positions = lines.integers::<usize>()?;
+        if positions.len() != nvars {
+            return Err(lines.invalid("expected the position of each variable"));
+        }
+        for (var, position) in positions.into_iter().enumerate() {
+            graph.set_position(var, position);
+        }
+    }
+    let mut root = None;
+    for _ in 0..nnodes {
+        if !lines.next_line()? {
+            return Err(lines.invalid("truncated dump, missing nodes"));
+        }
+        match lines.integers::<usize>()?[..] {
+            [node, var, low, high] if var < nvars => {
+                graph.add_node(node, DumpNode::Var { var, low: DumpEdge::regular(low), high: DumpEdge::regular(high) });
+                root = Some(node);
+            }
+            _ => return Err(lines.invalid("expected a node id var low high")),
+        }
+    }
+    let root = root.ok_or_else(|| lines.invalid("expected at least one node"))?;
+    Ok(graph.bdd_spec(id, DumpEdge::regular(root))?)
+}
+
+/// Read the bdds written in the text mode of the DDDMP format of CUDD from `reader`, as one
+/// `BddSpec` per root, of ids from 0 in the order of `.rootids`.
+///
+/// The header lines start with a `.`, e.g. `.nroots` and `.rootids`, and the nodes, `id [info] var
+/// then else`, are listed between `.nodes` and `.end`. A negative id is a complemented edge, and
+/// the node whose both edges are 0 is the constant 1. The variable of a node is an index in `.ids`,
+/// the variables the bdds depend on, its position being the one of the same index in `.permids`.
+/// Without `.ids`, the indices are the variables, and without `.permids` they are in the order of
+/// the indices.
+///
+/// Return an `Error` of kind `ErrorKind::Unsupported` if the dump is in binary mode or of an ADD,
+/// and of kind `ErrorKind::InvalidData` if it is malformed or one of its bdds is the constant 0.
+pub fn read_dddmp<R: BufRead>(reader: R) -> io::Result<Vec<BddSpec>> {
+    let mut lines = DumpLines::new(reader);
+    let (mut ids, mut permids, mut roots) = (None, None, None);
+    let mut nroots = None;
+    loop {
+        if !lines.next_line()? {
+            return Err(lines.invalid("truncated dump, missing .nodes"));
+        }
+        let line = lines.line.trim();
+        let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match key {
+            ".mode" if value.trim() != "A" => {
+                return Err(Error::new(ErrorKind::Unsupported, "Only the text mode of DDDMP is supported"));
+            }
+            ".add" => return Err(Error::new(ErrorKind::Unsupported, "Only the DDDMP dumps of bdds are supported")),
+            ".ids" => ids = Some(lines.integers_of::<usize>(value)?),
+            ".permids" => permids = Some(lines.integers_of::<usize>(value)?),
+            ".nroots" => nroots = Some(lines.integers_of::<usize>(value)?),
+            ".rootids" => roots = Some(lines.integers_of::<i64>(value)?),
+            ".nodes" => break,
+            _ if key.starts_with('.') => {}
+            _ => return Err(lines.invalid("expected a header line")),
+        }
+    }
+    let roots = roots.ok_or_else(|| lines.invalid("missing .rootids"))?;
+    if nroots.is_some_and(|nroots| nroots != [roots.len()]) {
+        return Err(lines.invalid(".nroots doesn't match .rootids"));
+    }
+    let mut graph = DumpGraph::new();
+    let nindices = ids.as_ref().map_or(0, Vec::len).max(permids.as_ref().map_or(0, Vec::len));
+    for index in 0..nindices {
+        let var = ids.as_ref().and_then(|ids| ids.get(index)).copied().unwrap_or(index);
+        let position = permids.as_ref().and_then(|permids| permids.get(index)).copied().unwrap_or(index);
+        graph.set_position(var, position);
+    }
+    let edge = |id: i64| DumpEdge::new(id.unsigned_abs() as usize, id < 0);
+    loop {
+        if !lines.next_line()? {
+            return Err(lines.invalid("truncated dump, missing .end"));
+        }
+        if lines.line.trim() == ".end" {
+            break;
+        }
+        let tokens: Vec<&str> = lines.line.split_whitespace().collect();
+        let parse = |token: &str| token.parse::<i64>().ok();
+        let node = match tokens[..] {
+            [id, .., var, then, other] if tokens.len() <= 5 => (parse(id), parse(var), parse(then), parse(other)),
+            _ => (None, None, None, None),
+        };
+        match node {
+            (Some(id), _, Some(0), Some(0)) if id > 0 => graph.add_node(id as usize, DumpNode::Constant(true)),
+            (Some(id), Some(var), Some(then), Some(other)) if id > 0 && var >= 0 => {
+                let var = var as usize;
+                let var = ids.as_ref().map_or(Some(var), |ids| ids.get(var).copied())
+                    .ok_or_else(|| lines.invalid("variable beyond .ids"))?;
+                graph.add_node(id as usize, DumpNode::Var { var, low: edge(other), high: edge(then) });
+            }
+            _ => return Err(lines.invalid("expected a node id [info] var then else")),
+        }
+    }
+    roots.into_iter().enumerate()
+        .map(|(index, root)| Ok(graph.bdd_spec(Id::new(index), edge(root))?))
+        .collect()
+}
+
+/// Write `bdd` in the text mode of the DDDMP format to writer, with one root per node of its first
+/// level, see the module documentation.
+///
+/// The sink level is the constant 1, a missing edge being the complemented edge to it, i.e. the
+/// constant 0. Each other level tests its variable, a level of empty lhs being skipped through the
+/// 0 edges of its nodes. The nodes are numbered from the constant up, such that the children of a
+/// node are written before it, and only the else edges are complemented, as CUDD requires. The
+/// position of a variable in `.permids` is the rank of its level.
+///
+/// Return an `Error` of kind `ErrorKind::Unsupported` if a level but the last tests several
+/// variables, or a variable already tested by a level above it.
+pub fn write_dddmp<S: NodeStore, W: Write>(bdd: &Bdd<S>, writer: &mut W) -> io::Result<()> {
+    let levels: Vec<_> = bdd.iter_levels().collect();
+    let last = levels.len().saturating_sub(1);
+    // The variable tested by each level but the last, if any
+    let mut vars: Vec<Option<usize>> = Vec::with_capacity(last);
+    for (index, level) in levels[..last].iter().enumerate() {
+        match level.iter_set_lhs().collect::<Vec<_>>()[..] {
+            [] => vars.push(None),
+            [var] if !vars.contains(&Some(var)) => vars.push(Some(var)),
+            _ => return Err(Error::new(ErrorKind::Unsupported,
+                                       format!("Level {} doesn't test a single new variable", index))),
+        }
+    }
+    let mut ids: Vec<usize> = vars.iter().flatten().copied().collect();
+    ids.sort_unstable();
+    let support: AHashMap<usize, usize> = ids.iter().enumerate().map(|(index, var)| (*var, index)).collect();
+    let position: AHashMap<usize, usize> = vars.iter().flatten().enumerate().map(|(rank, var)| (*var, rank)).collect();
+
+    // The edge of DDDMP computing the function of each node, and the nodes written, as id, variable,
+    // then edge and else edge
+    let mut edges: AHashMap<Id, DumpEdge> = AHashMap::default();
+    let mut nodes: Vec<(usize, usize, DumpEdge, DumpEdge)> = Vec::new();
+    for (index, level) in levels.iter().enumerate().rev() {
+        let mut node_ids: Vec<Id> = level.iter_nodes().map(|(id, _)| *id).collect();
+        node_ids.sort_unstable();
+        for id in node_ids {
+            let node = level.get_node(&id).unwrap();
+            let edge = |e: Option<Id>| e.and_then(|e| edges.get(&e).copied()).unwrap_or(DumpEdge::new(1, true));
+            let function = match vars.get(index) {
+                None => DumpEdge::regular(1),
+                Some(None) => edge(node.get_e0()),
+                Some(Some(var)) => {
+                    let (low, high) = (edge(node.get_e0()), edge(node.get_e1()));
+                    // A complemented then edge is written as the complement of the negated node
+                    let complemented = high.complemented;
+                    let dddmp_id = nodes.len() + 2;
+                    nodes.push((dddmp_id, *var, high.complement(complemented), low.complement(complemented)));
+                    DumpEdge::new(dddmp_id, complemented)
+                }
+            };
+            edges.insert(id, function);
+        }
+    }
+    let mut roots: Vec<Id> = levels.first().map_or(Vec::new(), |level| level.iter_nodes().map(|(id, _)| *id).collect());
+    roots.sort_unstable();
+
+    let signed = |edge: DumpEdge| if edge.complemented { -(edge.node as i64) } else { edge.node as i64 };
+    let list = |values: Vec<String>| values.iter().map(|value| format!(" {}", value)).collect::<String>();
+    writeln!(writer, ".ver DDDMP-2.0")?;
+    writeln!(writer, ".mode A")?;
+    writeln!(writer, ".varinfo 0")?;
+    writeln!(writer, ".dd bdd_{}", bdd.get_id())?;
+    writeln!(writer, ".nnodes {}", nodes.len() + 1)?;
+    writeln!(writer, ".nvars {}", levels.first().map_or(0, |level| level.get_lhs().len()))?;
+    writeln!(writer, ".nsuppvars {}", ids.len())?;
+    writeln!(writer, ".ids{}", list(ids.iter().map(|var| var.to_string()).collect()))?;
+    writeln!(writer, ".permids{}", list(ids.iter().map(|var| position[var].to_string()).collect()))?;
+    writeln!(writer, ".nroots {}", roots.len())?;
+    writeln!(writer, ".rootids{}", list(roots.iter().map(|root| signed(edges[root]).to_string()).collect()))?;
+    writeln!(writer, ".nodes")?;
+    writeln!(writer, "1 T 1 0 0")?;
+    for (id, var, then, other) in nodes {
+        writeln!(writer, "{} {} {} {} {}", id, var, support[&var], signed(then), signed(other))?;
+    }
+    writeln!(writer, ".end")?;
+    Ok(())
+}
+
+/// The non empty lines of a dump, with their number for the errors.
+struct DumpLines<R: BufRead> {
+    reader: R,
+    line: String,
+    line_number: usize,
+}
+
+impl<R: BufRead> DumpLines<R> {
+    fn new(reader: R) -> DumpLines<R> {
+        DumpLines { reader, line: String::new(), line_number: 0 }
+    }
+
+    /// Read the next non empty line into `line`, return false at the end of the input.
+    fn next_line(&mut self) -> io::Result<bool> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(false)
+            }
+            self.line_number += 1;
+            if !self.line.trim().is_empty() {
+                return Ok(true)
+            }
+        }
+    }
+
+    /// Parse `line` as whitespace separated integers.
+    fn integers<T: std::str::FromStr>(&self) -> io::Result<Vec<T>> {
+        self.integers_of(&self.line)
+    }
+
+    /// Parse `value`, part of `line`, as whitespace separated integers.
+    fn integers_of<T: std::str::FromStr>(&self, value: &str) -> io::Result<Vec<T>> {
+        value.split_whitespace()
+            .map(|integer| integer.parse::<T>().map_err(|_| self.invalid("expected integers")))
+            .collect()
+    }
+
+    fn invalid(&self, message: &str) -> Error {
+        SocError::parse(self.line_number, message).into()
+    }
+}
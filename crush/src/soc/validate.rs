@@ -0,0 +1,214 @@
+//! Structural validation of a `Bdd` or a `System`, to reject a malformed input (e.g. a
+//! hand-written .bdd file) upfront with a description of what is wrong, instead of panicking deep
+//! inside a solver.
+//!
+//! `Bdd::validate` and `System::validate` check everything and return a `ValidationReport` listing
+//! all the issues found, each telling the bdd, level and node concerned.
+
+use core::fmt::{self, Display};
+
+use crate::{AHashMap, AHashSet};
+use crate::soc::{bdd::Bdd, Id, Node, store::NodeStore, system::System};
+
+/// An issue found by `Bdd::validate` or `System::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// The bdd has no level.
+    NoLevel { bdd: Id },
+    /// The source level doesn't hold a single node.
+    SourceLevel { bdd: Id, nodes: usize },
+    /// The sink level doesn't hold a single node.
+    SinkLevel { bdd: Id, nodes: usize },
+    /// The sink has an outgoing edge.
+    SinkEdge { bdd: Id, sink: Id },
+    /// The node `child` doesn't exist in the bdd.
+    DanglingEdge { bdd: Id, level: usize, parent: Id, child: Id },
+    /// The node `child` isn't in the level just below its parent.
+    JumpingEdge { bdd: Id, level: usize, parent: Id, child: Id },
+    /// The node `node` can't be reached from the source.
+    UnreachableNode { bdd: Id, level: usize, node: Id },
+    /// No path leads from the node `node` to the sink.
+    DeadEnd { bdd: Id, level: usize, node: Id },
+    /// The level `level` has the same lhs as the level `first` above it.
+    DuplicateLhs { bdd: Id, level: usize, first: usize },
+    /// The lhs of the level `level` is over `len` variables, while the system has `nvar` variables.
+    NvarMismatch { bdd: Id, level: usize, len: usize, nvar: usize },
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Issue::NoLevel { bdd } => write!(f, "bdd {}: no level", bdd),
+            Issue::SourceLevel { bdd, nodes } => {
+                write!(f, "bdd {}: the source level holds {} nodes instead of 1", bdd, nodes)
+            }
+            Issue::SinkLevel { bdd, nodes } => {
+                write!(f, "bdd {}: the sink level holds {} nodes instead of 1", bdd, nodes)
+            }
+            Issue::SinkEdge { bdd, sink } => write!(f, "bdd {}: the sink {} has an outgoing edge", bdd, sink),
+            Issue::DanglingEdge { bdd, level, parent, child } => write!(
+                f,
+                "bdd {}, level {}: node {} points to {} which doesn't exist",
+                bdd, level, parent, child
+            ),
+            Issue::JumpingEdge { bdd, level, parent, child } => write!(
+                f,
+                "bdd {}, level {}: node {} points to {} which is not in the level below",
+                bdd, level, parent, child
+            ),
+            Issue::UnreachableNode { bdd, level, node } => {
+                write!(f, "bdd {}, level {}: node {} can't be reached from the source", bdd, level, node)
+            }
+            Issue::DeadEnd { bdd, level, node } => {
+                write!(f, "bdd {}, level {}: no path leads from node {} to the sink", bdd, level, node)
+            }
+            Issue::DuplicateLhs { bdd, level, first } => {
+                write!(f, "bdd {}, level {}: same lhs as the level {}", bdd, level, first)
+            }
+            Issue::NvarMismatch { bdd, level, len, nvar } => write!(
+                f,
+                "bdd {}, level {}: lhs over {} variables in a system of {} variables",
+                bdd, level, len, nvar
+            ),
+        }
+    }
+}
+
+/// The issues found by `Bdd::validate` or `System::validate`, by bdd and from the top.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    /// Return true if no issue was found.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_valid() {
+            return write!(f, "no issue found");
+        }
+        writeln!(f, "{} issues found:", self.issues.len())?;
+        for issue in self.issues.iter() {
+            writeln!(f, "- {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: NodeStore> Bdd<S> {
+    /// Check the structure of the `Bdd`, see `Issue` for what is checked.
+    pub fn validate(&self) -> ValidationReport {
+        let bdd = self.get_id();
+        let mut issues = Vec::new();
+        let levels: Vec<_> = self.iter_levels().collect();
+        let (source, sink) = match (levels.first(), levels.last()) {
+            (Some(source), Some(sink)) => (source, sink),
+            _ => {
+                issues.push(Issue::NoLevel { bdd });
+                return ValidationReport { issues };
+            }
+        };
+        if source.get_nodes_len() != 1 && levels.len() > 1 {
+            issues.push(Issue::SourceLevel { bdd, nodes: source.get_nodes_len() });
+        }
+        if sink.get_nodes_len() != 1 {
+            issues.push(Issue::SinkLevel { bdd, nodes: sink.get_nodes_len() });
+        }
+        for (id, node) in sink.iter_nodes() {
+            if node.get_e0().is_some() || node.get_e1().is_some() {
+                issues.push(Issue::SinkEdge { bdd, sink: *id });
+            }
+        }
+
+        let mut level_of: AHashMap<Id, usize> = AHashMap::default();
+        for (level_index, level) in levels.iter().enumerate() {
+            for (id, _) in level.iter_nodes() {
+                level_of.insert(*id, level_index);
+            }
+        }
+        let children = |node: &Node| node.get_e0().into_iter().chain(node.get_e1());
+        for (level_index, level) in levels.iter().enumerate().take(levels.len() - 1) {
+            let mut nodes: Vec<_> = level.iter_nodes().collect();
+            nodes.sort_unstable_by_key(|(id, _)| **id);
+            for (id, node) in nodes {
+                for child in children(node) {
+                    match level_of.get(&child) {
+                        None => issues.push(Issue::DanglingEdge { bdd, level: level_index, parent: *id, child }),
+                        Some(child_level) if *child_level != level_index + 1 => {
+                            issues.push(Issue::JumpingEdge { bdd, level: level_index, parent: *id, child })
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        // Reachability from the source, then co-reachability from the sink, following any edge
+        let mut reachable: AHashSet<Id> = source.iter_nodes().map(|(id, _)| *id).collect();
+        for level in levels.iter() {
+            for (id, node) in level.iter_nodes() {
+                if reachable.contains(id) {
+                    reachable.extend(children(node));
+                }
+            }
+        }
+        let mut to_sink: AHashSet<Id> = sink.iter_nodes().map(|(id, _)| *id).collect();
+        for level in levels.iter().rev().skip(1) {
+            for (id, node) in level.iter_nodes() {
+                if children(node).any(|child| to_sink.contains(&child)) {
+                    to_sink.insert(*id);
+                }
+            }
+        }
+        for (level_index, level) in levels.iter().enumerate() {
+            let mut ids: Vec<Id> = level.iter_nodes().map(|(id, _)| *id).collect();
+            ids.sort_unstable();
+            for id in ids {
+                if !reachable.contains(&id) {
+                    issues.push(Issue::UnreachableNode { bdd, level: level_index, node: id });
+                }
+                if !to_sink.contains(&id) {
+                    issues.push(Issue::DeadEnd { bdd, level: level_index, node: id });
+                }
+            }
+        }
+
+        let mut first_with_lhs = AHashMap::default();
+        for (level_index, level) in levels.iter().enumerate().take(levels.len() - 1) {
+            let lhs: Vec<usize> = level.iter_set_lhs().collect();
+            if let Some(first) = first_with_lhs.get(&lhs) {
+                issues.push(Issue::DuplicateLhs { bdd, level: level_index, first: *first });
+            } else {
+                first_with_lhs.insert(lhs, level_index);
+            }
+        }
+        ValidationReport { issues }
+    }
+}
+
+impl System {
+    /// Check the structure of every `Bdd` of the `System` (see `Bdd::validate`), and that their
+    /// lhs are over the `nvar` variables of the `System`.
+    pub fn validate(&self) -> ValidationReport {
+        let nvar = self.get_nvar();
+        let mut ids: Vec<Id> = self.iter_bdds().map(|(id, _)| *id).collect();
+        ids.sort_unstable();
+        let mut issues = Vec::new();
+        for id in ids {
+            let bdd = self.get_bdd(id).unwrap().borrow();
+            for (level_index, level) in bdd.iter_levels().enumerate() {
+                let len = level.get_lhs().len();
+                if len != nvar {
+                    issues.push(Issue::NvarMismatch { bdd: id, level: level_index, len, nvar });
+                }
+            }
+            issues.extend(bdd.validate().issues);
+        }
+        ValidationReport { issues }
+    }
+}
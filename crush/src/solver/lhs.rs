@@ -0,0 +1,103 @@
+//! A `Dependency` ready to be solved with, built on `System::lhs_dependencies`, such that a
+//! `System` can be solved without writing a strategy first: `DefaultSolver` resolves the
+//! `LevelDependency`s with the default methods of `Solver`, the cheapest first. The bindings of the
+//! other languages solve with it.
+//!
+//! The cost of a dependency is the number of nodes of the bdds it involves, which are joined into
+//! the one of lowest id before its levels are added together and absorbed.
+
+use std::io::Error;
+
+use crate::soc::{system::System, Id};
+use crate::solver::{Dependency, Solver};
+
+/// A linear dependency among the levels of a `System`, see the module documentation.
+#[derive(Clone, Debug)]
+pub struct LevelDependency {
+    /// The bdds involved, by increasing id, i.e. in the order they are joined.
+    bdds: Vec<Id>,
+    /// The index of the levels of the dependency in the joined bdd, increasing.
+    levels: Vec<usize>,
+    /// The number of nodes of the bdds involved.
+    nodes: usize,
+}
+
+impl LevelDependency {
+    /// Return the `LevelDependency` of the levels `dependency`, given by the id of their `Bdd` and
+    /// their index, as returned by `System::lhs_dependencies`.
+    pub fn new(system: &System, dependency: &[(Id, usize)]) -> Result<LevelDependency, Error> {
+        let mut bdds: Vec<Id> = dependency.iter().map(|(id, _)| *id).collect();
+        bdds.sort_unstable();
+        bdds.dedup();
+        // The levels of each bdd follow the ones of the bdds joined before it, sinks excluded
+        let mut offsets = Vec::with_capacity(bdds.len());
+        let (mut offset, mut nodes) = (0, 0);
+        for id in bdds.iter() {
+            let bdd = system.get_bdd(*id)?.borrow();
+            offsets.push(offset);
+            offset += bdd.get_sink_level_index();
+            nodes += bdd.get_size();
+        }
+        let mut levels: Vec<usize> = dependency.iter()
+            .map(|(id, level)| offsets[bdds.binary_search(id).unwrap()] + level)
+            .collect();
+        levels.sort_unstable();
+        Ok(LevelDependency { bdds, levels, nodes })
+    }
+}
+
+impl Dependency for LevelDependency {
+    fn minimize_distance(&self) -> usize {
+        self.nodes
+    }
+
+    fn best_join_order(&self) -> (Vec<Id>, Vec<usize>) {
+        (self.bdds.clone(), self.levels.clone())
+    }
+
+    /// The levels of LHS zero are absorbed on their own by the solver, so only the dependencies of
+    /// several levels are extracted.
+    fn extract(system: &System) -> Vec<Self> {
+        system.lhs_dependencies().iter()
+            .filter(|dependency| dependency.len() > 1)
+            .map(|dependency| LevelDependency::new(system, dependency).expect("The bdds of a dependency are in the system"))
+            .collect()
+    }
+}
+
+/// The `Solver` of the default methods, reporting through `reporting` and `metrics` only.
+#[derive(Clone, Debug, Default)]
+pub struct DefaultSolver;
+
+impl Solver for DefaultSolver {}
+
+#[cfg(test)]
+mod test {
+    use vob::Vob;
+
+    use crate::soc::bdd::PathCursor;
+    use crate::system;
+
+    use super::*;
+
+    #[test]
+    fn solve_dependency() {
+        // x2 = x0 + x1 and x0 + x1 = 0, the LHS x0 + x1 being shared
+        let mut system = system!(3; 0; [("0+1",[(1;2,3)]);("2",[(2;4,0);(3;0,4)]);("",[(4;0,0)])];
+                                    1; [("0+1",[(1;2,0)]);("0",[(2;3,3)]);("",[(3;0,0)])]).unwrap();
+        let original = system.clone();
+        let dependencies = LevelDependency::extract(&system);
+        assert_eq!(1, dependencies.len());
+        assert_eq!((vec![Id::new(0), Id::new(1)], vec![0, 2]), dependencies[0].best_join_order());
+        DefaultSolver.solve::<LevelDependency>(&mut system).unwrap();
+        assert!(LevelDependency::extract(&system).is_empty());
+        let (solutions, _) = system.calculate_solutions_from(&PathCursor::start(), usize::MAX).unwrap();
+        // The free variables are counted for both of their values
+        let count: usize = solutions.iter().map(|solution| 1 << solution.iter().filter(|x| x.is_none()).count()).sum();
+        assert_eq!(2, count);
+        for solution in solutions {
+            let assignment: Vob = solution.iter().map(|x| x.unwrap_or(false)).collect();
+            assert!(original.is_solution(&assignment));
+        }
+    }
+}
@@ -1,10 +1,98 @@
-//! Provide the traits to create solving strategies using the apis of `soc::System`.
+//! Provide the traits to create solving strategies using the apis of `soc::System`, and a default
+//! strategy in the `lhs` module.
 
 
 use std::io::Error;
 use std::result::Result;
 
-use crate::soc::{Id, system::System};
+use crate::{interrupt, metrics, reporting};
+use crate::reporting::Event;
+use crate::soc::{Id, stats::SystemStats, system::System};
+
+pub mod lhs;
+
+/// The source of the records of the solvers, see `reporting`.
+const SOURCE: &str = "crush.solver";
+
+/// Report the state of `system` as gauges: `solver.bdds_remaining`, `solver.nodes_remaining`,
+/// `solver.lin_eqs`, `solver.biggest_bdd` (number of nodes of the biggest `Bdd`),
+/// `solver.max_width` and `solver.dependencies`.
+///
+/// The number of nodes is also reported with `metrics::observe_nodes`, for the memory profiling
+/// of the running phases.
+///
+/// Return the `SystemStats` the gauges were taken from, for the caller to display them.
+pub fn report_system_metrics(system: &System) -> SystemStats {
+    let stats = system.stats();
+    metrics::observe_nodes(stats.total_nodes);
+    metrics::gauge("solver.bdds_remaining", stats.bdds.len() as f64);
+    metrics::gauge("solver.nodes_remaining", stats.total_nodes as f64);
+    metrics::gauge("solver.lin_eqs", stats.lin_eqs as f64);
+    let max_size = stats.biggest_bdd().map_or(0, |bdd| bdd.nodes);
+    metrics::gauge("solver.biggest_bdd", max_size as f64);
+    metrics::gauge("solver.max_width", stats.max_width() as f64);
+    metrics::gauge("solver.dependencies", stats.dependencies() as f64);
+    stats
+}
+
+/// Absorb the linear equations found in the `Bdd` `bdd_id` (see `System::scan_absorb_lin_eqs`),
+/// reporting it with `Event::Absorbed` if any. Return the number of levels absorbed.
+fn scan_absorb_reported(system: &mut System, bdd_id: Id) -> usize {
+    let bdd_size = |system: &System| system.get_bdd(bdd_id).map_or(0, |bdd| bdd.borrow().get_size());
+    let nodes_before = bdd_size(system);
+    let levels = system
+        .scan_absorb_lin_eqs(bdd_id)
+        .expect("shouldn't crash when absorbing lin eq");
+    if levels > 0 {
+        reporting::report(SOURCE, Event::Absorbed {
+            bdd: bdd_id,
+            levels,
+            nodes_before,
+            nodes_after: bdd_size(system),
+        });
+    }
+    levels
+}
+
+/// Sift the levels of every `Bdd` of `system` of more than `threshold` nodes, if any threshold (see
+/// `System::sift_above`), reporting each with `Event::Sifted`.
+fn sift_reported(system: &mut System, threshold: Option<usize>) {
+    let threshold = match threshold {
+        Some(threshold) => threshold,
+        None => return,
+    };
+    for (bdd, stats) in system.sift_above(threshold) {
+        metrics::counter("solver.sifted", 1);
+        reporting::report(SOURCE, Event::Sifted {
+            bdd,
+            swaps: stats.swaps,
+            nodes_before: stats.nodes_before,
+            nodes_after: stats.nodes_after,
+        });
+    }
+}
+
+/// Extract the `Dependency`s of `system`, skipping the extraction when the tracked rank of its
+/// LHS's tells there are none (see `System::lhs_dependency_count`).
+fn extract_dependencies<D: Dependency>(system: &System) -> Vec<D> {
+    if system.lhs_dependency_count() == 0 {
+        return Vec::new();
+    }
+    D::extract(system)
+}
+
+/// Return the ids of the `Bdd`s of `system`, increasing, such that they are absorbed in the same
+/// order from one run to the next.
+fn sorted_ids(system: &System) -> Vec<Id> {
+    let mut ids = system.iter_bdds().map(|(id, _)| *id).collect::<Vec<Id>>();
+    ids.sort_unstable();
+    ids
+}
+
+/// Report that the `Bdd`s of `join_order` are about to be joined, with `Event::JoinStarted`.
+fn report_join(system: &System, join_order: &(Vec<Id>, Vec<usize>)) {
+    reporting::report(SOURCE, Event::JoinStarted { bdds: join_order.0.clone(), nodes: system.get_size() });
+}
 
 /// Describe a dependency inside a `System` of `Bdd`. A `Dependency`
 /// is defined as a collection of levels in a `System` which can be add to create a
@@ -19,7 +107,8 @@ pub trait Dependency: Sized {
     /// Return the order in which the `Bdd`s involved in the `Dependency` should be joined,
     /// and the index of the levels to add to create a 0-level in the resulting `Bdd`.
     fn best_join_order(&self) -> (Vec<Id>, Vec<usize>);
-    /// Extract all the `Dependency` in a given `System`
+    /// Extract all the `Dependency` in a given `System`. The solvers only call it when
+    /// `System::lhs_dependency_count` tells there are some.
     fn extract(system: &System) -> Vec<Self>;
 }
 
@@ -52,26 +141,45 @@ pub trait Independency: Sized {
 ///
 /// - resolve which is a way to specify how will a given `Dependency` be remove from the `System`
 ///
-/// - feedback which provide ongoing information to the user during the solving
+/// - feedback which provide ongoing information to the user during the solving, by default
+///   as metrics (see `report_system_metrics`)
 ///
 /// - solve which act as an entry point and will call the other methods in a loop
-/// until all `Dependency` have been removed
+///   until all `Dependency` have been removed
 ///
 /// We provide default implementations for all of those methods.
 pub trait Solver {
     /// Remove every linear dependency in a `System` using absorbtion and return the solutions
+    ///
+    /// If an interruption is requested (see `interrupt`), return an `Error` of kind
+    /// `ErrorKind::Interrupted` before resolving the next `Dependency`.
+    ///
+    /// Each join, resolution, absorption and sifting is reported (see `reporting`), as well as the
+    /// memory use after each `Dependency`. The `Bdd`s beyond `sift_threshold` are sifted after the
+    /// absorptions following each `Dependency`.
     fn solve<T: Dependency>(
         &mut self,
         system: &mut System,
     ) -> Result<Vec<Vec<Option<bool>>>, Error> {
         Self::absorb_all_equations(system)?;
-        let mut deps = T::extract(system);
+        let mut deps = extract_dependencies::<T>(system);
         while !deps.is_empty() {
-            Self::resolve(self, system, Self::pick_best_dep(deps))?;
+            interrupt::check()?;
+            let join = metrics::phase("solver.join");
+            let join_order = Self::pick_best_dep(deps);
+            let root = join_order.0[0];
+            report_join(system, &join_order);
+            metrics::time("solver.resolve", || Self::resolve(self, system, join_order))?;
+            metrics::counter("solver.dependencies_resolved", 1);
+            let nodes = system.get_bdd(root).map_or(0, |bdd| bdd.borrow().get_size());
+            reporting::report(SOURCE, Event::DependencyResolved { bdd: root, nodes });
             Self::feedback(self, system);
             Self::absorb_all_equations(system)?;
+            sift_reported(system, self.sift_threshold());
             Self::feedback(self, system);
-            deps = T::extract(system);
+            reporting::memory_snapshot(SOURCE, system.get_size());
+            drop(join);
+            deps = extract_dependencies::<T>(system);
         }
         Ok(system.calculate_solutions())
     }
@@ -99,21 +207,14 @@ pub trait Solver {
     /// the most easy way of getting them is to make them a field of your `Solver` and updating
     /// the fields during the solving.
     fn feedback(&self, system: &System) {
-        print!("\x1Bc");
-        println!(
-            "{} bdds remaining\n{} total nodes remaining\ntotal linear equations found {}",
-            system.iter_bdds().len(),
-            system.get_size(),
-            system.get_lin_bank_size()
-        );
-        let max_size = system.iter_bdds().fold(0, |size, bdd| {
-            if bdd.1.borrow().get_size() > size {
-                bdd.1.borrow().get_size()
-            } else {
-                size
-            }
-        });
-        println!("biggest bdd has {} nodes", max_size);
+        report_system_metrics(system);
+    }
+
+    /// The number of nodes beyond which a `Bdd` has its levels reordered by sifting (see
+    /// `Bdd::sift_levels`) once a `Dependency` is resolved, or `None` (the default) to never
+    /// reorder them.
+    fn sift_threshold(&self) -> Option<usize> {
+        None
     }
 
     /// Describe the way a `Dependency` should be resolved.
@@ -132,6 +233,7 @@ pub trait Solver {
                 .join_bdds(*bdd_root_id, *key)
                 .expect("should not crash when joining");
         }
+        metrics::observe_nodes(system.get_size());
         for i in (0..join_order.1.len() - 1).rev() {
             for j in (join_order.1[i] + 1..join_order.1[i + 1]).rev() {
                 system.swap(*bdd_root_id, j, j + 1)?;
@@ -148,21 +250,15 @@ pub trait Solver {
 
     /// Go through all BDDs and check for equation to absorb
     /// until there are no left. If when absorbing a BDD is reduced to
-    /// its sink then we remove it from the system
+    /// its sink then we remove it from the system. Each absorption is reported with
+    /// `reporting::Event::Absorbed`.
     fn absorb_all_equations(system: &mut System) -> Result<(), Error> {
         let mut absorbed = true;
         while absorbed {
             absorbed = false;
-            let ids = system
-                .iter_bdds()
-                .map(|bdd| *bdd.0)
-                .collect::<Vec<Id>>();
+            let ids = sorted_ids(system);
             for id in ids.iter() {
-                if system
-                    .scan_absorb_lin_eqs(*id)
-                    .expect("shouldn't crash when absorbing lin eq")
-                    > 0
-                {
+                if scan_absorb_reported(system, *id) > 0 {
                     absorbed = true;
                 }
             }
@@ -193,11 +289,12 @@ pub trait Solver {
 ///
 /// - indep_resolver which is a way to specify how will a given `Independency` be remove from the `System`
 ///
-/// - feedback which provide ongoing information to the user during the solving
+/// - feedback which provide ongoing information to the user during the solving, by default
+///   as metrics (see `report_system_metrics`)
 ///
 /// - solve which act as an entry point and will call the other methods in a loop
-/// until all `Dependency` have been removed. Solve will also be responsible for choosing if it
-/// should resolve the best `Dependency` or the best `Independency` next.
+///   until all `Dependency` have been removed. Solve will also be responsible for choosing if it
+///   should resolve the best `Dependency` or the best `Independency` next.
 ///
 /// We provide default implementations for all of those methods.
 pub trait DroppingSolver {
@@ -210,26 +307,50 @@ pub trait DroppingSolver {
     /// Not all possible drop have to be made as the purpose of dropping is only to make absorbing the
     /// dependencies faster, so we exit and get the solutions as soon as no dependencies are left
     /// in the `System`.
+    ///
+    /// If an interruption is requested (see `interrupt`), return an `Error` of kind
+    /// `ErrorKind::Interrupted` before resolving the next `Dependency` or `Independency`.
+    ///
+    /// Each join, resolution, drop, absorption and sifting is reported (see `reporting`), as well as
+    /// the memory use after each `Dependency` or `Independency`. The `Bdd`s beyond `sift_threshold`
+    /// are sifted after the absorptions following each of them.
     fn solve<D: Dependency, I: Independency>(
         &mut self,
         system: &mut System,
         forbid_dropping: Option<&[usize]>,
     ) -> Result<Vec<Vec<Option<bool>>>, Error> {
         Self::absorb_all_equations(system)?;
-        let mut deps = D::extract(system);
+        let mut deps = extract_dependencies::<D>(system);
         let mut indeps = I::extract(system, forbid_dropping);
         while !deps.is_empty() {
+            interrupt::check()?;
             let (id_dep, min_distance_dep) = Self::pick_best_dep(&deps);
             let (id_indep, min_distance_indep) = Self::pick_best_indep(&indeps);
+            let join = metrics::phase("solver.join");
             if min_distance_indep < min_distance_dep {
-                Self::indep_resolver(self, system, indeps[id_indep].best_join_order())?;
+                let join_order = indeps[id_indep].best_join_order();
+                let root = join_order.0[0];
+                report_join(system, &join_order);
+                metrics::time("solver.drop", || Self::indep_resolver(self, system, join_order))?;
+                metrics::counter("solver.independencies_dropped", 1);
+                let nodes = system.get_bdd(root).map_or(0, |bdd| bdd.borrow().get_size());
+                reporting::report(SOURCE, Event::IndependencyDropped { bdd: root, nodes });
             } else {
-                Self::dep_resolver(self, system, deps[id_dep].best_join_order())?;
+                let join_order = deps[id_dep].best_join_order();
+                let root = join_order.0[0];
+                report_join(system, &join_order);
+                metrics::time("solver.resolve", || Self::dep_resolver(self, system, join_order))?;
+                metrics::counter("solver.dependencies_resolved", 1);
+                let nodes = system.get_bdd(root).map_or(0, |bdd| bdd.borrow().get_size());
+                reporting::report(SOURCE, Event::DependencyResolved { bdd: root, nodes });
             }
             Self::feedback(self, system);
             Self::absorb_all_equations(system)?;
+            sift_reported(system, self.sift_threshold());
             Self::feedback(self, system);
-            deps = D::extract(system);
+            reporting::memory_snapshot(SOURCE, system.get_size());
+            drop(join);
+            deps = extract_dependencies::<D>(system);
             indeps = I::extract(system, forbid_dropping);
         }
         Ok(system.calculate_solutions())
@@ -255,6 +376,7 @@ pub trait DroppingSolver {
                 .join_bdds(*bdd_root_id, *key)
                 .expect("should not crash when joining");
         }
+        metrics::observe_nodes(system.get_size());
         for i in 0..join_order.1.len() - 1 {
             system.add(*bdd_root_id, join_order.1[i], join_order.1[i + 1])?;
             system.swap(*bdd_root_id, join_order.1[i + 1] - 1, join_order.1[i + 1])?;
@@ -281,6 +403,7 @@ pub trait DroppingSolver {
                 .join_bdds(*bdd_root_id, *key)
                 .expect("should not crash when joining");
         }
+        metrics::observe_nodes(system.get_size());
         for i in (0..join_order.1.len() - 1).rev() {
             for j in (join_order.1[i] + 1..join_order.1[i + 1]).rev() {
                 system.swap(*bdd_root_id, j, j + 1)?;
@@ -289,7 +412,7 @@ pub trait DroppingSolver {
             if i != 0 {
                 system.swap(*bdd_root_id, join_order.1[i], join_order.1[i] + 1)?;
             }
-            Self::feedback(&self, system);
+            Self::feedback(self, system);
         }
         system.absorb(*bdd_root_id, join_order.1[0] + 1, false)?;
         Ok(())
@@ -328,21 +451,15 @@ pub trait DroppingSolver {
 
     /// Go through all BDDs and check for equation to absorb
     /// until there are no left. If when absorbing a BDD is reduced to
-    /// its sink then we remove it from the system
+    /// its sink then we remove it from the system. Each absorption is reported with
+    /// `reporting::Event::Absorbed`.
     fn absorb_all_equations(system: &mut System) -> Result<(), Error> {
         let mut absorbed = true;
         while absorbed {
             absorbed = false;
-            let ids = system
-                .iter_bdds()
-                .map(|bdd| *bdd.0)
-                .collect::<Vec<Id>>();
+            let ids = sorted_ids(system);
             for id in ids.iter() {
-                if system
-                    .scan_absorb_lin_eqs(*id)
-                    .expect("shouldn't crash when absorbing lin eq")
-                    > 0
-                {
+                if scan_absorb_reported(system, *id) > 0 {
                     absorbed = true;
                 }
             }
@@ -361,20 +478,13 @@ pub trait DroppingSolver {
     /// the most easy way of getting them is to make them a field of your `DroppingSolver` and updating
     /// the fields during the solving.
     fn feedback(&self, system: &System) {
-        print!("\x1Bc");
-        println!(
-            "{} bdds remaining\n{} total nodes remaining\ntotal linear equations found {}",
-            system.iter_bdds().len(),
-            system.get_size(),
-            system.get_lin_bank_size(),
-        );
-        let max_size = system.iter_bdds().fold(0, |size, bdd| {
-            if bdd.1.borrow().get_size() > size {
-                bdd.1.borrow().get_size()
-            } else {
-                size
-            }
-        });
-        println!("biggest bdd has {} nodes", max_size);
+        report_system_metrics(system);
+    }
+
+    /// The number of nodes beyond which a `Bdd` has its levels reordered by sifting (see
+    /// `Bdd::sift_levels`) once a `Dependency` or an `Independency` is resolved, or `None` (the
+    /// default) to never reorder them.
+    fn sift_threshold(&self) -> Option<usize> {
+        None
     }
 }
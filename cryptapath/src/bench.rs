@@ -0,0 +1,121 @@
+//! Standard benchmark scenarios.
+//!
+//! Each scenario builds a system of BDDs with one call, always the same one, such that
+//! performance regressions in the operations of crush (join, count, ...) can be measured
+//! consistently. The random values used (plaintext, key, S-box tables, linear combinations) come
+//! from a seeded generator and never from `rand::thread_rng`.
+//!
+//! The cipher scenarios are fixed with a plaintext/ciphertext pair encrypted under a key, so the
+//! systems have at least one solution and can also be used to benchmark the strategies.
+
+use crate::bit::Bit;
+use crate::rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use crate::sbox::Sbox;
+use crate::targets::{
+    build_system_cipher, fix_system_values_cipher, lowmc::LowMC, present80::Present80, Cipher,
+};
+use crush::soc::{system::System, utils::*};
+
+/// Seed used by the scenarios taking no seed.
+pub const DEFAULT_SEED: u64 = 0x5eed;
+
+/// Number of rounds of the PRESENT scenario.
+pub const PRESENT_ROUNDS: usize = 4;
+
+/// Number of rounds of the LowMC scenario, the instance has a 64 bits block, an 80 bits key and
+/// a single S-box per round.
+pub const LOWMC_ROUNDS: usize = 8;
+
+/// Build the system of 4 rounds of PRESENT-80 fixed with a plaintext/ciphertext pair.
+pub fn present80_4_rounds() -> System {
+    build_fixed_cipher_system(&Present80::new(PRESENT_ROUNDS), DEFAULT_SEED)
+}
+
+/// Build the system of the small LowMC instance (see `LOWMC_ROUNDS`) fixed with a
+/// plaintext/ciphertext pair.
+pub fn lowmc_small() -> System {
+    build_fixed_cipher_system(&LowMC::new(LOWMC_ROUNDS, 64, 80, 1), DEFAULT_SEED)
+}
+
+/// Build a random system of `n_bdds` BDDs over `nvar` input variables.
+///
+/// Every BDD is a random 4-bit S-box whose input bits are random linear combinations of the
+/// input variables and of the outputs of the previous S-boxes, and whose output bits are fresh
+/// variables. The system therefore has `nvar + 4 * n_bdds` variables and always has solutions.
+/// The same `seed` always gives the same system.
+pub fn random_system(nvar: usize, n_bdds: usize, seed: u64) -> System {
+    assert!(nvar > 0, "a random system needs at least one input variable");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut sbox = Sbox::new(4, 4, (0..16).collect(), nvar);
+    for _ in 0..n_bdds {
+        let mut table: Vec<u8> = (0..16).collect();
+        table.shuffle(&mut rng);
+        sbox = Sbox::replace_existing_sbox(4, 4, table, sbox);
+        let n_available = sbox.next_var_id();
+        let in_bits = (0..4)
+            .map(|_| {
+                let mut bit = Bit::from_variable_id(rng.gen_range(0, n_available));
+                for _ in 0..rng.gen_range(0, 3) {
+                    bit ^= Bit::from_variable_id(rng.gen_range(0, n_available));
+                }
+                if rng.gen() {
+                    bit ^= bit!(true);
+                }
+                bit
+            })
+            .collect();
+        sbox.apply(in_bits);
+    }
+    let nvar = sbox.next_var_id();
+    build_system_from_spec(SystemSpec::new(nvar, sbox.bdds())).expect("the sbox bdds are over the variables of the system")
+}
+
+/// Return the system of the scenario with the given name, or `None` if it doesn't exist.
+/// Supported: present80-4, lowmc-small, random-small, random-medium, random-large
+pub fn build_scenario_by_name(name: &str) -> Option<System> {
+    match name {
+        "present80-4" => Some(present80_4_rounds()),
+        "lowmc-small" => Some(lowmc_small()),
+        "random-small" => Some(random_system(16, 8, DEFAULT_SEED)),
+        "random-medium" => Some(random_system(32, 32, DEFAULT_SEED)),
+        "random-large" => Some(random_system(64, 128, DEFAULT_SEED)),
+        _ => None,
+    }
+}
+
+/// Build the system of `cipher` and fix it with a plaintext and the ciphertext obtained by
+/// encrypting it under a key, both drawn from a generator seeded with `seed`.
+fn build_fixed_cipher_system(cipher: &dyn Cipher, seed: u64) -> System {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut seeded_bits = |len: usize| -> Vec<Bit> {
+        (0..len).map(|_| Bit::from_value(rng.gen())).collect()
+    };
+    let plaintext = seeded_bits(cipher.message_length());
+    let key = seeded_bits(cipher.key_length());
+    let ciphertext = cipher.encrypt(plaintext.clone(), key);
+    let (input, output, mut system) = build_system_cipher(cipher);
+    fix_system_values_cipher(&mut system, &plaintext, &ciphertext, &input, &output);
+    system
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn random_system_is_deterministic() {
+        let system = random_system(16, 8, 42);
+        assert_eq!(system.get_nvar(), 16 + 4 * 8);
+        assert_eq!(system.iter_bdds().len(), 8);
+        let other = random_system(16, 8, 42);
+        for (id, bdd) in system.iter_bdds() {
+            assert_eq!(*bdd.borrow(), *other.get_bdd(*id).unwrap().borrow());
+        }
+    }
+
+    #[test]
+    fn scenario_by_name() {
+        assert!(build_scenario_by_name("present80-4").is_some());
+        assert!(build_scenario_by_name("not-a-scenario").is_none());
+    }
+}
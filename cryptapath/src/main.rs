@@ -8,17 +8,27 @@ extern crate structopt_derive;
 
 #[macro_use]
 pub mod bit;
+pub mod bench;
+pub mod modular_addition;
 pub mod options;
 pub mod sbox;
 pub mod strategy;
 pub mod targets;
 
+use crush::soc::io::*;
+use crush::soc::session::Session;
+use crush::soc::system::System;
 use crush::soc::utils::*;
 use options::CryptaPathOptions;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Instant;
 use structopt::StructOpt;
 use targets::*;
 
 fn main() {
+    #[cfg(feature = "interrupt")]
+    crush::interrupt::install_handler().expect("failed to install the Ctrl-C handler");
     match CryptaPathOptions::from_args() {
         CryptaPathOptions::Cipher {
             cipher_name,
@@ -27,6 +37,7 @@ fn main() {
             key,
             out,
             strategy,
+            journal,
         } => {
             let cipher = match build_cipher_by_name(cipher_name.as_ref(), rounds) {
                 Some(c) => c,
@@ -83,14 +94,19 @@ fn main() {
                 fix_system_values_cipher(&mut system, &plaintext, &ciphertext, &input, &output);
             }
             if let Some(path) = out {
-                print_system_to_file(&system, &path);
+                system.renumber();
+                print_system_to_file(&system, &path).expect("failed to write the system");
             }
             let forbid_dropping: Vec<usize> = (0..cipher.key_length()).collect();
+            let recovery = journal.map(|path| {
+                strategy::Recovery::start(&path, &system).expect("failed to start the journal")
+            });
             let mut sols = match strategy {
                 Some(name) => match strategy::execute_strategy_by_name(
                         name.as_ref(),
                         &mut system,
                         Some(&forbid_dropping),
+                        recovery,
                     ) {
                         Some(sols) => sols,
                         None => {
@@ -100,7 +116,8 @@ fn main() {
                     }
                 ,
                 None => {
-                    strategy::execute_strategy_by_name("no_drop", &mut system, None).unwrap()
+                    strategy::execute_strategy_by_name("no_drop", &mut system, None, recovery)
+                        .unwrap()
                 }
             };
             for sol in sols.iter_mut() {
@@ -196,13 +213,15 @@ fn main() {
                 None => fix_system_values_sponge(hash.as_ref(), &mut system, &hash_value, &output),
             }
             if let Some(path) = out {
-                print_system_to_file(&system, &path);
+                system.renumber();
+                print_system_to_file(&system, &path).expect("failed to write the system");
             }
             let forbid_dropping: Vec<usize> = (0..hash.message_length()).collect();
             let mut sols = strategy::execute_strategy_by_name(
                 "drop",
                 &mut system,
                 Some(&forbid_dropping),
+                None,
             )
             .unwrap();
             for sol in sols.iter_mut() {
@@ -243,10 +262,93 @@ fn main() {
             println!("ciphertext : {}", bit::bits_to_hex_string(ciphertext));
             println!("key : {}", bit::bits_to_binary_string(key));
         }
-        CryptaPathOptions::FromFile { file } => {
-            let specs = parse_system_spec_from_file(&file);
-            let mut system = build_system_from_spec(specs);
-            strategy::execute_strategy_by_name("no_drop", &mut system, None).unwrap();
+        CryptaPathOptions::Bench { scenario, strategy } => {
+            let start = Instant::now();
+            let mut system = match bench::build_scenario_by_name(scenario.as_ref()) {
+                Some(s) => s,
+                None => {
+                    println!("Scenario not supported. Check --help for supported scenarios.");
+                    return;
+                }
+            };
+            println!("built {} in {:?}", scenario, start.elapsed());
+            println!("{}", system.stats());
+            let scores = system.join_scores();
+            let hardest: Vec<String> = scores
+                .iter()
+                .take(5)
+                .map(|score| format!("{} ({})", score.id, score.score))
+                .collect();
+            println!("highest join scores: {}", hardest.join(", "));
+            let start = Instant::now();
+            let strategy = strategy.unwrap_or_else(|| "no_drop".to_string());
+            match strategy::execute_strategy_by_name(strategy.as_ref(), &mut system, None, None) {
+                Some(sols) => println!(
+                    "solved {} in {:?}, {} solution(s)",
+                    scenario,
+                    start.elapsed(),
+                    sols.len()
+                ),
+                None => println!("Strategy not supported. Check --help for supported strategies."),
+            }
+            print!("{}", crush::metrics::snapshot());
+        }
+        CryptaPathOptions::FromFile { file, journal } => {
+            let mut system = read_system(&file);
+            let recovery = journal.map(|path| {
+                if path.exists() {
+                    strategy::Recovery::resume(&path, &system)
+                        .expect("failed to resume from the journal")
+                } else {
+                    strategy::Recovery::start(&path, &system).expect("failed to start the journal")
+                }
+            });
+            strategy::execute_strategy_by_name("no_drop", &mut system, None, recovery).unwrap();
+        }
+        CryptaPathOptions::Session { dir, file, strategy } => {
+            let (session, mut system) = if Session::exists(&dir) {
+                Session::continue_from(&dir).expect("failed to continue the session")
+            } else {
+                let file = file.expect("a source file is required to open a new session");
+                let system = read_system(&file);
+                let mut config = BTreeMap::new();
+                config.insert("source".to_string(), file.display().to_string());
+                config.insert("strategy".to_string(), strategy.unwrap_or_else(|| "no_drop".to_string()));
+                (Session::open(&dir, &system, config).expect("failed to open the session"), system)
+            };
+            let strategy = session.manifest().config["strategy"].clone();
+            let recovery = strategy::Recovery::for_session(&session, &system)
+                .expect("failed to journal the session");
+            let sols = match strategy::execute_strategy_by_name(strategy.as_ref(), &mut system, None, Some(recovery)) {
+                Some(sols) => sols,
+                None => {
+                    println!("Strategy not supported. Check --help for supported strategies.");
+                    return;
+                }
+            };
+            let mut session = session;
+            for sol in sols.iter() {
+                let sol: String = sol.iter().map(|var| match var {
+                    Some(true) => '1',
+                    Some(false) => '0',
+                    None => '-',
+                }).collect();
+                println!("solution : {}", sol);
+                session.add_result(&sol).expect("failed to record a solution");
+            }
+            session.finalize().expect("failed to finalize the session");
+            println!("session finalized, the solutions are in {}", dir.join("results").display());
         }
     }
 }
+
+/// Read the system at path, exiting with the issues found if it is malformed.
+fn read_system(path: &PathBuf) -> System {
+    let system = build_system_from_file(path).expect("failed to read the system");
+    let report = system.validate();
+    if !report.is_valid() {
+        eprint!("{} is malformed, {}", path.display(), report);
+        std::process::exit(1);
+    }
+    system
+}
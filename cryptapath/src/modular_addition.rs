@@ -0,0 +1,113 @@
+//! Addition modulo 2^n of two words, as used by ARX ciphers.
+//!
+//! The sum of two words is linear in the input bits and the carry bits, while each carry bit is
+//! a non-linear function of the bits below it. The carries are therefore computed by S-Boxes:
+//! the carry out of the least significant bit is the AND of the two input bits, and every other
+//! carry is the majority of the two input bits and the incoming carry. When applied to words
+//! containing variables this produces one fresh variable (and one BDD) per carry bit, that is
+//! n-1 of them for a word of n bits. When applied to constant words the result is the constant
+//! sum, which is useful to validate the implementation of a cipher with test vectors.
+//!
+//! Words are represented as in the rest of CryptaPath: a Vec<Bit> with the most significant bit
+//! first.
+
+use crate::bit::Bit;
+use crate::sbox::Sbox;
+
+const AND_TABLE: [u8; 4] = [0, 0, 0, 1];
+const MAJ_TABLE: [u8; 8] = [0, 0, 0, 1, 0, 1, 1, 1];
+
+/// Computes additions modulo 2^n, and holds the S-Box producing the carry bits.
+/// next_var_id is the id of the first variable to be made by the carries.
+/// The S-Box is the majority, and the carry out of the least significant bit applies the AND
+/// table through it, such that all the carries share its variables and BDDs.
+pub struct ModularAdder {
+    sbox: Sbox,
+}
+
+impl ModularAdder {
+    pub fn new(next_var_id: usize) -> Self {
+        ModularAdder {
+            sbox: Sbox::new(3, 1, MAJ_TABLE.to_vec(), next_var_id),
+        }
+    }
+
+    /// Return a + b mod 2^n, where n is the length of the two words.
+    pub fn add(&self, a: &[Bit], b: &[Bit]) -> Vec<Bit> {
+        assert_eq!(a.len(), b.len());
+        let n = a.len();
+        let mut out_bits = vec![bit!(false); n];
+        if n == 0 {
+            return out_bits;
+        }
+        out_bits[n - 1] = a[n - 1].clone() ^ b[n - 1].clone();
+        if n == 1 {
+            return out_bits;
+        }
+        let mut carry = self.carry_lsb(&a[n - 1], &b[n - 1]);
+        for i in (0..n - 1).rev() {
+            out_bits[i] = a[i].clone() ^ b[i].clone() ^ carry.clone();
+            if i > 0 {
+                carry = self
+                    .sbox
+                    .apply(vec![a[i].clone(), b[i].clone(), carry])
+                    .pop()
+                    .unwrap();
+            }
+        }
+        out_bits
+    }
+
+    /// The carry out of the least significant bit, i.e. a AND b.
+    fn carry_lsb(&self, a: &Bit, b: &Bit) -> Bit {
+        self.sbox
+            .apply_table(vec![a.clone(), b.clone()], &AND_TABLE)
+            .pop()
+            .unwrap()
+    }
+
+    /// The S-Box holding the BDDs made by the carries so far.
+    pub fn sbox(&self) -> Sbox {
+        self.sbox.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bit;
+    use crate::modular_addition::ModularAdder;
+
+    #[test]
+    fn validate_add() {
+        let adder = ModularAdder::new(0);
+        for (a, b, sum) in [
+            ("0000", "0000", "0000"),
+            ("ffff", "0001", "0000"),
+            ("6574", "694c", "cec0"),
+            ("8000", "8000", "0000"),
+            ("1234", "edcb", "ffff"),
+            ("7fff", "7fff", "fffe"),
+        ]
+        .iter()
+        {
+            let a = bit::bits_from_hex_string(a);
+            let b = bit::bits_from_hex_string(b);
+            assert_eq!(*sum, bit::bits_to_hex_string(adder.add(&a, &b)));
+        }
+        // Constant words make no variables
+        assert_eq!(0, adder.sbox().next_var_id());
+    }
+
+    #[test]
+    fn carries_make_variables() {
+        let adder = ModularAdder::new(8);
+        let a = (0..4).map(bit::Bit::from_variable_id).collect::<Vec<_>>();
+        let b = (4..8).map(bit::Bit::from_variable_id).collect::<Vec<_>>();
+        let sum = adder.add(&a, &b);
+        // One carry for each bit but the most significant one
+        assert_eq!(11, adder.sbox().next_var_id());
+        assert_eq!(3, adder.sbox().bdds().len());
+        // The least significant bit of the sum has no carry in
+        assert_eq!(a[3].clone() ^ b[3].clone(), sum[3]);
+    }
+}
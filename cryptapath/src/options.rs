@@ -11,7 +11,11 @@ pub enum CryptaPathOptions {
     Cipher {
         #[structopt(short = "c", long = "cipher")]
         ///Name of the target cipher. Currently supported: 
-        ///skinny64128, skinny128128, lowmc64, lowmc128, lowmc256, miniaes2x2, miniaes4x4, present80, prince, prince-core, des
+        ///skinny64128, skinny128128, lowmc64, lowmc128, lowmc256, miniaes2x2, miniaes4x4, present80, prince, prince-core, des,
+        ///speck3264, speck4872, speck4896, speck6496, speck64128, speck9696, speck96144, speck128128, speck128192, speck128256,
+        ///simon3264, simon4872, simon4896, simon6496, simon64128, simon9696, simon96144, simon128128, simon128192, simon128256,
+        ///simeck3264, simeck4896, simeck64128, katan32, katan48, katan64,
+        ///ktantan32, ktantan48, ktantan64
         cipher_name: String,
         #[structopt(short = "r", long = "rounds")]
         ///The number of rounds to run on the cipher
@@ -40,6 +44,11 @@ pub enum CryptaPathOptions {
         /// Choose the strategy when trying to solve.
         /// Available choices: "drop" "no_drop", default: "no_drop"
         strategy: Option<String>,
+        #[structopt(short = "j", long = "journal", parse(from_os_str))]
+        /// If provided, journal the solving at the provided path and write a checkpoint of the
+        /// system next to it regularly (with the extension .checkpoint.bdd). After a crash, the
+        /// solving can be resumed by running from-file on the checkpoint with the same journal.
+        journal: Option<PathBuf>,
     },
     #[structopt(name = "sponge")]
     Sponge {
@@ -84,16 +93,49 @@ pub enum CryptaPathOptions {
     MakeParam {
         #[structopt(short = "c", long = "cipher")]
         ///Name of the target cipher. Currently supported: 
-        ///skinny64128, skinny128128, lowmc64, lowmc128, lowmc256, miniaes2x2, miniaes4x4, present80, prince, prince-core, des
+        ///skinny64128, skinny128128, lowmc64, lowmc128, lowmc256, miniaes2x2, miniaes4x4, present80, prince, prince-core, des,
+        ///speck3264, speck4872, speck4896, speck6496, speck64128, speck9696, speck96144, speck128128, speck128192, speck128256,
+        ///simon3264, simon4872, simon4896, simon6496, simon64128, simon9696, simon96144, simon128128, simon128192, simon128256,
+        ///simeck3264, simeck4896, simeck64128, katan32, katan48, katan64,
+        ///ktantan32, ktantan48, ktantan64
         cipher: String,
         #[structopt(short = "r", long = "rounds")]
         ///The number of rounds to run on the cipher
         rounds: usize,
     },
+    #[structopt(name = "bench")]
+    Bench {
+        #[structopt(short = "s", long = "scenario")]
+        ///Name of the benchmark scenario. Currently supported:
+        ///present80-4, lowmc-small, random-small, random-medium, random-large
+        scenario: String,
+        #[structopt(long = "strategy")]
+        /// Choose the strategy when solving the scenario.
+        /// Available choices: "drop" "no_drop", default: "no_drop"
+        strategy: Option<String>,
+    },
     #[structopt(name = "from-file")]
     FromFile {
         #[structopt(short = "f", long = "file", parse(from_os_str))]
         /// The source bdd file
-        file: PathBuf
+        file: PathBuf,
+        #[structopt(short = "j", long = "journal", parse(from_os_str))]
+        /// If provided, journal the solving at the provided path. If the journal exists, the
+        /// source file should be its last checkpoint and the solving resumes from there.
+        journal: Option<PathBuf>,
+    },
+    #[structopt(name = "session")]
+    Session {
+        #[structopt(short = "d", long = "dir", parse(from_os_str))]
+        /// The session directory. If it holds a session, the solving continues from its last
+        /// checkpoint, otherwise a new session is opened for the source bdd file.
+        dir: PathBuf,
+        #[structopt(short = "f", long = "file", parse(from_os_str))]
+        /// The source bdd file, required to open a new session
+        file: Option<PathBuf>,
+        #[structopt(long = "strategy")]
+        /// Choose the strategy when opening a new session, a continued session keeps its own.
+        /// Available choices: "drop" "no_drop", default: "no_drop"
+        strategy: Option<String>,
     }
 }
\ No newline at end of file
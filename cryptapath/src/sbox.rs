@@ -65,7 +65,7 @@ impl Sbox {
 
     /// Produce a deterministic output of constant bits by using the
     /// lookup table.
-    fn sbox_fixed_output(&self, in_bits: Vec<Bit>) -> Vec<Bit> {
+    fn sbox_fixed_output(&self, in_bits: Vec<Bit>, table: &[u8]) -> Vec<Bit> {
         let in_value = usize::from_str_radix(
             in_bits
                 .iter()
@@ -75,8 +75,8 @@ impl Sbox {
             2,
         )
         .unwrap();
-        assert!(in_value < (1 << self.in_size));
-        let out_value = self.table[in_value];
+        assert!(in_value < table.len());
+        let out_value = table[in_value];
         let mut out_bits = Vec::with_capacity(self.out_size);
         for i in 0..self.out_size {
             match out_value >> (self.out_size - i - 1) & 0x01 {
@@ -92,8 +92,17 @@ impl Sbox {
     /// or new set of Bit containing new Variable if at least one of them
     /// is not constant. In that case also produce and store a BDD.
     pub fn apply(&self, in_bits: Vec<Bit>) -> Vec<Bit> {
+        assert_eq!(self.in_size, in_bits.len());
+        self.apply_table(in_bits, &self.table)
+    }
+
+    /// As apply, but with the lookup table `table` of in_bits.len() bits to out_size bits
+    /// instead of the one of the S-Box. The variables and the BDDs are still the ones of the
+    /// S-Box, so a cipher with a few small tables can share a single S-Box.
+    pub fn apply_table(&self, in_bits: Vec<Bit>, table: &[u8]) -> Vec<Bit> {
+        assert_eq!(1 << in_bits.len(), table.len());
         if in_bits.iter().find(|bit| bit.vars().next().is_some()).is_none() {
-            self.sbox_fixed_output(in_bits)
+            self.sbox_fixed_output(in_bits, table)
         } else {
             let mut out_bits = Vec::with_capacity(self.out_size);
             for i in self.next_var_id.get()..self.next_var_id.get() + self.out_size {
@@ -102,7 +111,7 @@ impl Sbox {
             self.next_var_id.set(self.next_var_id.get() + self.out_size);
             self.bdds
                 .borrow_mut()
-                .push(buid_bdd_spec(in_bits, out_bits.clone(), &self.table));
+                .push(buid_bdd_spec(in_bits, out_bits.clone(), table));
             out_bits
         }
     }
@@ -3,15 +3,47 @@
 
 
 use std::cell::Cell;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process;
 use std::result::Result;
 
+use vob::Vob;
+
 use crush::{
-    algebra,
-    soc::{Id, system::System},
-    solver::{Dependency, DroppingSolver, Independency, Solver},
+    algebra, interrupt, metrics,
+    soc::{
+        io::print_checkpoint_to_file,
+        journal::{self, Journal},
+        session::Session,
+        Id,
+        system::System,
+    },
+    solver::{report_system_metrics, Dependency, DroppingSolver, Independency, Solver},
 };
 
+/// Group the levels whose bits are set in `row` by `Bdd`, `index` giving the `Bdd` and the level of
+/// each bit (see `System::lhs_matrix`).
+fn involved_bdds(system: &System, index: &[(Id, usize)], row: &Vob) -> Vec<InvolvedBdd> {
+    let mut involved_bdds: Vec<InvolvedBdd> = Vec::new();
+    for bit in row.iter_set_bits(..) {
+        let (id, level) = index[bit];
+        match involved_bdds.last_mut() {
+            // the rows of a bdd are contiguous in the matrix
+            Some(involved) if involved.id == id => involved.involved_levels.push(level),
+            _ => {
+                let bdd = system.get_bdd(id).unwrap().borrow();
+                let mut levels: Vec<usize> =
+                    bdd.iter_levels().map(|level| level.get_nodes_len()).collect();
+                // Removes the sink since iter_levels doesn't skip the last
+                levels.pop();
+                involved_bdds.push(InvolvedBdd::new(id, levels, bdd.get_size(), vec![level]));
+            }
+        }
+    }
+    involved_bdds
+}
+
 /// Describe the informations about a `Bdd` involved in a `NodeRankedDependency` or a `NodeRankedIndependency`.
 #[derive(Clone, Debug)]
 pub struct InvolvedBdd {
@@ -192,61 +224,14 @@ impl Dependency for NodeRankedDependency {
 
     /// Build the linear dependencies of the system.
     fn extract(system: &System) -> Vec<NodeRankedDependency> {
-        let mut deps = Vec::new();
-        let mut id_lhs = system.get_system_lhs();
-        let mut lhs_concat = Vec::new();
-        let mut id_levels_size = Vec::new();
-        for bdd in id_lhs.iter_mut() {
-            let mut levels = Vec::new();
-            let total_size;
-            {
-                let bdd_object = system.get_bdd(bdd.0).unwrap().borrow();
-                bdd_object
-                    .iter_levels()
-                    .for_each(|level| levels.push(level.get_nodes_len()));
-                total_size = bdd_object.get_size();
-            }
-            // Removes the sink since iter_levels doesn't skip the last
-            levels.pop();
-            id_levels_size.push((bdd.0, levels, total_size));
-            lhs_concat.append(&mut bdd.1);
-        }
-        let lin_dep = algebra::extract_linear_dependencies(matrix![lhs_concat]);
-
-        for m_row in lin_dep.iter_rows() {
-            let mut involved_bdds = Vec::new();
-            let mut id_levels_size_iter = id_levels_size.iter();
-            let mut bdd = id_levels_size_iter.next().unwrap();
-            let mut bdd_start_range = 0;
-            let mut bdd_end_range = bdd.1.len() - 1;
-            let mut involved = Vec::new();
-            for bit in m_row.iter_set_bits(..) {
-                // for each bit (a bit is a level involved in the dep) :
-                // check if the bit is in the range of the bdd (between its first and its last level)
-                // if it is -> add it in the involved
-                // if it is not -> update the range by proceeding to the next BDD, if the involved bdd wasn't
-                // empty push it to the bdds of the dep
-                // when all the row has been processed the bdds make one dep
-                if bit <= bdd_end_range {
-                    involved.push(bit - bdd_start_range);
-                } else {
-                    if !involved.is_empty() {
-                        involved_bdds.push(InvolvedBdd::new(bdd.0, bdd.1.clone(), bdd.2, involved));
-                        involved = Vec::new();
-                    }
-                    while bit > bdd_end_range {
-                        bdd = id_levels_size_iter.next().unwrap();
-                        let len = bdd.1.len();
-                        bdd_start_range = bdd_end_range + 1;
-                        bdd_end_range += len;
-                    }
-                    involved.push(bit - bdd_start_range);
-                }
-            }
-            involved_bdds.push(InvolvedBdd::new(bdd.0, bdd.1.clone(), bdd.2, involved));
-            deps.push(NodeRankedDependency { involved_bdds });
-        }
-        deps
+        let (lhs, index) = system.lhs_matrix();
+        let lin_dep = algebra::extract_linear_dependencies(lhs);
+        lin_dep
+            .iter_rows()
+            .map(|m_row| NodeRankedDependency {
+                involved_bdds: involved_bdds(system, &index, m_row),
+            })
+            .collect()
     }
 }
 
@@ -301,6 +286,7 @@ pub struct UpwardSolver {
     remaining: usize,
     solved: usize,
     max_reached: Cell<usize>,
+    recovery: Option<Recovery>,
 }
 
 impl UpwardSolver {
@@ -308,16 +294,30 @@ impl UpwardSolver {
         Default::default()
     }
 
+    /// Construct an `UpwardSolver` journaling its operations to `recovery`.
+    pub fn with_recovery(recovery: Recovery) -> UpwardSolver {
+        UpwardSolver {
+            recovery: Some(recovery),
+            ..Default::default()
+        }
+    }
+
     pub fn improved_solve(&mut self, system: &mut System) -> Result<Vec<Vec<Option<bool>>>, Error> {
         Self::absorb_all_equations(system)?;
         let mut deps = NodeRankedDependency::extract(system);
         self.remaining = deps.len();
         while !deps.is_empty() {
+            interrupt::check()?;
             deps = find_best_bdd_pattern_dep(&deps);
+            let join = metrics::phase("solver.join");
             Self::resolve(self, system, Self::pick_best_dep(deps))?;
             self.solved += 1;
             Self::feedback(self, system);
             Self::absorb_all_equations(system)?;
+            drop(join);
+            if let Some(recovery) = self.recovery.as_mut() {
+                recovery.record(&format!("resolve {}", self.solved), system)?;
+            }
             deps = NodeRankedDependency::extract(system);
             self.remaining = deps.len();
             Self::feedback(self, system);
@@ -328,28 +328,20 @@ impl UpwardSolver {
 
 impl Solver for UpwardSolver {
     fn feedback(&self, system: &System) {
+        let stats = report_system_metrics(system);
         print!("\x1Bc");
         println!(
             "{} bdds remaining\n{} total nodes remaining\ntotal linear equations found {}\nsolved dependencies {}, {} remaining",
-            system.iter_bdds().len(),
-            system.get_size(),
-            system.get_lin_bank_size(),
+            stats.bdds.len(),
+            stats.total_nodes,
+            stats.lin_eqs,
             self.solved,
             self.remaining,
         );
-        let max_size = system.iter_bdds().fold(0, |size, bdd| {
-            if bdd.1.borrow().get_size() > size {
-                bdd.1.borrow().get_size()
-            } else {
-                size
-            }
-        });
+        let max_size = stats.biggest_bdd().map_or(0, |bdd| bdd.nodes);
         println!("biggest bdd has {} nodes", max_size);
-        let total_nodes = system
-            .iter_bdds()
-            .fold(0, |acc, bdd| acc + bdd.1.borrow().get_size());
-        if total_nodes > self.max_reached.get() {
-            self.max_reached.set(total_nodes);
+        if stats.total_nodes > self.max_reached.get() {
+            self.max_reached.set(stats.total_nodes);
         }
         println!(
             "max node reach 2**{}",
@@ -456,25 +448,8 @@ impl Independency for NodeRankedIndependency {
     /// of the entire system. Each independency therefore describe all the levels containing a specific variable.
     fn extract(system: &System, limit: Option<&[usize]>) -> Vec<NodeRankedIndependency> {
         let mut indeps = Vec::new();
-        let mut id_lhs = system.get_system_lhs();
-        let mut lhs_concat = Vec::new();
-        let mut id_levels_size = Vec::new();
-        for mut bdd in id_lhs.drain(..) {
-            let mut levels = Vec::new();
-            let total_size;
-            {
-                let bdd_object = system.get_bdd(bdd.0).unwrap().borrow();
-                bdd_object
-                    .iter_levels()
-                    .for_each(|level| levels.push(level.get_nodes_len()));
-                total_size = bdd_object.get_size();
-            }
-            //Removes the sink
-            levels.pop();
-            id_levels_size.push((bdd.0, levels, total_size));
-            lhs_concat.append(&mut bdd.1);
-        }
-        let lin_indep = algebra::transpose(&matrix![lhs_concat]);
+        let (lhs, index) = system.lhs_matrix();
+        let lin_indep = algebra::transpose(&lhs);
         for (var, m_row) in lin_indep.iter_rows().enumerate() {
             if limit.is_some() && limit.unwrap().contains(&var) {
                 continue;
@@ -482,36 +457,7 @@ impl Independency for NodeRankedIndependency {
             if m_row.iter_set_bits(..).next().is_none() {
                 continue;
             }
-            let mut involved_bdds = Vec::new();
-            let mut id_levels_size_iter = id_levels_size.iter();
-            let mut bdd = id_levels_size_iter.next().unwrap();
-            let mut bdd_start_range = 0;
-            let mut bdd_end_range = bdd.1.len() - 1;
-            let mut involved = Vec::new();
-            for bit in m_row.iter_set_bits(..) {
-                // for each bit (a bit is a level involved in the dep) :
-                // check if the bit is in the range of the bdd (between its first and its last level)
-                // if it is -> add it in the involved
-                // if it is not -> update the range by proceeding to the next BDD, if the involved bdd wasn't
-                // empty push it to the bdds of the dep
-                // when all the row has been processed the bdds make one dep
-                if bit <= bdd_end_range {
-                    involved.push(bit - bdd_start_range);
-                } else {
-                    if !involved.is_empty() {
-                        involved_bdds.push(InvolvedBdd::new(bdd.0, bdd.1.clone(), bdd.2, involved));
-                        involved = Vec::new();
-                    }
-                    while bit > bdd_end_range {
-                        bdd = id_levels_size_iter.next().unwrap();
-                        let len = bdd.1.len();
-                        bdd_start_range = bdd_end_range + 1;
-                        bdd_end_range += len;
-                    }
-                    involved.push(bit - bdd_start_range);
-                }
-            }
-            involved_bdds.push(InvolvedBdd::new(bdd.0, bdd.1.clone(), bdd.2, involved));
+            let involved_bdds = involved_bdds(system, &index, m_row);
             if involved_bdds.len() == 1 {
                 indeps.push(NodeRankedIndependency { involved_bdds });
             }
@@ -526,6 +472,7 @@ pub struct UpwardDroppingSolver {
     solved: usize,
     dropped: usize,
     max_reached: Cell<usize>,
+    recovery: Option<Recovery>,
 }
 
 impl UpwardDroppingSolver {
@@ -533,6 +480,14 @@ impl UpwardDroppingSolver {
         Default::default()
     }
 
+    /// Construct an `UpwardDroppingSolver` journaling its operations to `recovery`.
+    pub fn with_recovery(recovery: Recovery) -> UpwardDroppingSolver {
+        UpwardDroppingSolver {
+            recovery: Some(recovery),
+            ..Default::default()
+        }
+    }
+
     pub fn improved_solve(
         &mut self,
         system: &mut System,
@@ -543,19 +498,27 @@ impl UpwardDroppingSolver {
         let mut indeps = NodeRankedIndependency::extract(system, forbid_dropping);
         self.remaining = deps.len();
         while !deps.is_empty() {
+            interrupt::check()?;
             deps = find_best_bdd_pattern_dep(&deps);
             let (id_dep, min_distance_dep) = Self::pick_best_dep(&deps);
             let (id_indep, min_distance_indep) = Self::pick_best_indep(&indeps);
-            if min_distance_indep < min_distance_dep {
+            let join = metrics::phase("solver.join");
+            let operation = if min_distance_indep < min_distance_dep {
                 Self::indep_resolver(self, system, indeps[id_indep].best_join_order())?;
                 self.dropped += 1;
+                format!("drop {}", self.dropped)
             } else {
                 Self::dep_resolver(self, system, deps[id_dep].best_join_order())?;
                 self.solved += 1;
-            }
+                format!("resolve {}", self.solved)
+            };
 
             Self::feedback(self, system);
             Self::absorb_all_equations(system)?;
+            drop(join);
+            if let Some(recovery) = self.recovery.as_mut() {
+                recovery.record(&operation, system)?;
+            }
             deps = NodeRankedDependency::extract(system);
             indeps = NodeRankedIndependency::extract(system, forbid_dropping);
             self.remaining = deps.len();
@@ -567,31 +530,23 @@ impl UpwardDroppingSolver {
 
 impl DroppingSolver for UpwardDroppingSolver {
     fn feedback(&self, system: &System) {
+        let stats = report_system_metrics(system);
         print!( "\x1Bc");
         println!(
             
             "{} bdds remaining\n{} total nodes remaining\ntotal linear equations found {}\nsolved dependencies {}, {} remaining\ndropped variables {}",
-            system.iter_bdds().len(),
-            system.get_size(),
-            system.get_lin_bank_size(),
+            stats.bdds.len(),
+            stats.total_nodes,
+            stats.lin_eqs,
             self.solved,
             self.remaining,
             self.dropped
         )
         ;
-        let max_size = system.iter_bdds().fold(0, |size, bdd| {
-            if bdd.1.borrow().get_size() > size {
-                bdd.1.borrow().get_size()
-            } else {
-                size
-            }
-        });
+        let max_size = stats.biggest_bdd().map_or(0, |bdd| bdd.nodes);
         println!( "biggest bdd has {} nodes", max_size);
-        let total_nodes = system
-            .iter_bdds()
-            .fold(0, |acc, bdd| acc + bdd.1.borrow().get_size());
-        if total_nodes > self.max_reached.get() {
-            self.max_reached.set(total_nodes);
+        if stats.total_nodes > self.max_reached.get() {
+            self.max_reached.set(stats.total_nodes);
         }
         println!(
             "max node reach 2**{}",
@@ -600,20 +555,166 @@ impl DroppingSolver for UpwardDroppingSolver {
     }
 }
 
+/// Solve `system` with the strategy `name`, journaling the operations to `recovery` if provided.
+/// Return `None` if the strategy doesn't exist.
 pub fn execute_strategy_by_name(
     name: &str,
     system: &mut System,
     forbid_dropping: Option<&[usize]>,
+    recovery: Option<Recovery>,
 ) -> Option<Vec<Vec<Option<bool>>>> {
     match name {
         "no_drop" => {
-            let mut solver = UpwardSolver::new();
-            Some(solver.improved_solve(system).unwrap())
+            let mut solver = match recovery {
+                Some(recovery) => UpwardSolver::with_recovery(recovery),
+                None => UpwardSolver::new(),
+            };
+            let result = solver.improved_solve(system);
+            Some(solutions_or_checkpoint(result, system, || {
+                format!("solved dependencies {}, {} remaining", solver.solved, solver.remaining)
+            }))
         }
         "drop" => {
-            let mut solver = UpwardDroppingSolver::new();
-            Some(solver.improved_solve(system, forbid_dropping).unwrap())
+            let mut solver = match recovery {
+                Some(recovery) => UpwardDroppingSolver::with_recovery(recovery),
+                None => UpwardDroppingSolver::new(),
+            };
+            let result = solver.improved_solve(system, forbid_dropping);
+            Some(solutions_or_checkpoint(result, system, || {
+                format!(
+                    "solved dependencies {}, {} remaining\ndropped variables {}",
+                    solver.solved, solver.remaining, solver.dropped
+                )
+            }))
         }
         _ => None,
     }
 }
+
+/// Path of the checkpoint written when solving is interrupted. The checkpoint can be solved
+/// with the `from-file` command to resume.
+pub const CHECKPOINT_PATH: &str = "interrupted.bdd";
+
+/// Return the solutions if the solving went through. If it was interrupted, print the progress
+/// made so far, write a checkpoint of `system` at `CHECKPOINT_PATH` and exit.
+fn solutions_or_checkpoint<F: FnOnce() -> String>(
+    result: Result<Vec<Vec<Option<bool>>>, Error>,
+    system: &System,
+    progress: F,
+) -> Vec<Vec<Option<bool>>> {
+    match result {
+        Ok(sols) => sols,
+        Err(e) if e.kind() == ErrorKind::Interrupted => {
+            println!("solving interrupted\n{}", progress());
+            println!("{}", system.stats());
+            print_checkpoint_to_file(system, &PathBuf::from(CHECKPOINT_PATH))
+                .expect("failed to write the checkpoint");
+            println!(
+                "checkpoint written to {}, solve it with the from-file command to resume",
+                CHECKPOINT_PATH
+            );
+            process::exit(interrupt::EXIT_CODE);
+        }
+        Err(e) => panic!("solving failed: {}", e),
+    }
+}
+
+/// Number of operations recorded in the journal between two checkpoints.
+pub const CHECKPOINT_INTERVAL: usize = 10;
+
+/// Journal of the operations of a solving, with a checkpoint of the system written every
+/// `CHECKPOINT_INTERVAL` operations, such that solving can resume from the last checkpoint after
+/// a crash (see `crush::soc::journal`).
+///
+/// The checkpoint is written next to the journal, with the extension `checkpoint.bdd`, or in the
+/// session directory when journaling a session (see `crush::soc::session`).
+pub struct Recovery {
+    journal: Journal,
+    checkpoint: PathBuf,
+    since_checkpoint: usize,
+}
+
+impl Recovery {
+    /// Start a new journal at path for solving `system`.
+    pub fn start(path: &PathBuf, system: &System) -> Result<Recovery, Error> {
+        Self::start_with_checkpoint(path, Self::checkpoint_path(path), system)
+    }
+
+    /// Continue the journal at path for solving `system`, parsed from the last checkpoint.
+    ///
+    /// Return an `Error` if `system` is not reflected by any entry of the journal, i.e. it isn't
+    /// a checkpoint written while journaling there.
+    pub fn resume(path: &PathBuf, system: &System) -> Result<Recovery, Error> {
+        Self::resume_with_checkpoint(path, Self::checkpoint_path(path), system)
+    }
+
+    /// Journal the solving of `system` in `session`, starting its journal or continuing it if
+    /// `system` was given back by `Session::continue_from`.
+    pub fn for_session(session: &Session, system: &System) -> Result<Recovery, Error> {
+        let path = session.journal_path();
+        if path.exists() {
+            Self::resume_with_checkpoint(&path, session.checkpoint_path(), system)
+        } else {
+            Self::start_with_checkpoint(&path, session.checkpoint_path(), system)
+        }
+    }
+
+    fn start_with_checkpoint(
+        path: &PathBuf,
+        checkpoint: PathBuf,
+        system: &System,
+    ) -> Result<Recovery, Error> {
+        let mut journal = Journal::create(path)?;
+        journal.record("start", system)?;
+        Ok(Recovery {
+            journal,
+            checkpoint,
+            since_checkpoint: 0,
+        })
+    }
+
+    fn resume_with_checkpoint(
+        path: &PathBuf,
+        checkpoint: PathBuf,
+        system: &System,
+    ) -> Result<Recovery, Error> {
+        let entries = journal::read_journal(path)?;
+        let point = journal::resume_point(&entries, system).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                "the system doesn't match any checkpoint of the journal",
+            )
+        })?;
+        let last_seq = entries.last().map_or(0, |entry| entry.seq);
+        println!(
+            "resuming after operation {} ({}), {} later operation(s) to do again",
+            point.seq,
+            point.operation,
+            last_seq - point.seq
+        );
+        let mut journal = Journal::append(path, last_seq + 1)?;
+        journal.record(&format!("resume {}", point.seq), system)?;
+        Ok(Recovery {
+            journal,
+            checkpoint,
+            since_checkpoint: 0,
+        })
+    }
+
+    /// Return the path of the checkpoints written along the journal at path.
+    pub fn checkpoint_path(path: &Path) -> PathBuf {
+        path.with_extension("checkpoint.bdd")
+    }
+
+    /// Record that `operation` was applied, `system` being the result, and write a checkpoint
+    /// if `CHECKPOINT_INTERVAL` operations were recorded since the last one.
+    fn record(&mut self, operation: &str, system: &System) -> Result<(), Error> {
+        self.journal.record(operation, system)?;
+        self.since_checkpoint += 1;
+        if self.since_checkpoint >= CHECKPOINT_INTERVAL {
+            self.journal.checkpoint(system, &self.checkpoint)?;
+            self.since_checkpoint = 0;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+use crate::bit::Bit;
+use crate::sbox::Sbox;
+use crate::targets::simon::and_sbox;
+use crate::targets::Cipher;
+
+/// The irregular update sequence, one bit per round.
+const IR: &str = "11111110001101010101111011001100101001000100011000111100001000010100000111110011111101010001010100110000110011101111101110100101011010011100110110001011101101111001011011010111001001001101000111000100111101000011101011000001011001000000110111000000010010";
+
+pub struct Katan {
+    n_rounds: usize,
+    message_length: usize,
+    key_length: usize,
+    /// Length of the two registers L1 and L2
+    l1_length: usize,
+    l2_length: usize,
+    /// Taps of L1 used by fa, x1..x5 in the specification
+    x: [usize; 5],
+    /// Taps of L2 used by fb, y1..y6 in the specification
+    y: [usize; 6],
+    /// Number of times the registers are clocked per round
+    clocks: usize,
+    ir: Vec<bool>,
+    /// KTANTAN, whose key bits of each round are chosen by the round counter
+    ktantan: bool,
+    sbox: Sbox,
+}
+
+impl Katan {
+    /// KATAN with a block of block_size bits, either 32, 48 or 64. The full cipher has 254 rounds.
+    ///
+    /// Each clock of the registers computes three ANDs, and the nonlinear ones (those not involving
+    /// the irregular update bit) are made into tiny S-Boxes of their own, so a full KATAN64 yields
+    /// several thousands of shards.
+    pub fn new(block_size: usize, n_rounds: usize) -> Self {
+        assert!(
+            n_rounds <= IR.len(),
+            "KATAN has at most {} rounds",
+            IR.len()
+        );
+        let (l1_length, l2_length, x, y, clocks) = match block_size {
+            32 => (13, 19, [12, 7, 8, 5, 3], [18, 7, 12, 10, 8, 3], 1),
+            48 => (19, 29, [18, 12, 15, 7, 6], [28, 19, 21, 13, 15, 6], 2),
+            64 => (25, 39, [24, 15, 20, 11, 9], [38, 25, 33, 21, 14, 9], 3),
+            _ => panic!("KATAN{} is not a standard parameter set", block_size),
+        };
+        let key_length = 80;
+        Katan {
+            n_rounds,
+            message_length: block_size,
+            key_length,
+            l1_length,
+            l2_length,
+            x,
+            y,
+            clocks,
+            ir: IR.chars().map(|c| c == '1').collect(),
+            ktantan: false,
+            sbox: and_sbox(block_size + key_length),
+        }
+    }
+
+    /// KTANTAN with a block of block_size bits, either 32, 48 or 64. It is KATAN with a burnt-in
+    /// key: the two key bits of each round are picked among the 80 bits of the key by the round
+    /// counter, instead of coming from an LFSR.
+    pub fn ktantan(block_size: usize, n_rounds: usize) -> Self {
+        Katan {
+            ktantan: true,
+            ..Katan::new(block_size, n_rounds)
+        }
+    }
+
+    /// The key schedule is a linear LFSR, giving the two key bits (ka, kb) of each round, or for
+    /// KTANTAN the selection of ktantan_key_bits.
+    /// Key bit k_i is the i-th least significant bit of the key.
+    fn make_round_keys(&self, key: Vec<Bit>) -> Vec<(Bit, Bit)> {
+        assert_eq!(key.len(), self.key_length);
+        let mut k = key.into_iter().rev().collect::<Vec<Bit>>();
+        if self.ktantan {
+            return ktantan_key_bits(self.n_rounds)
+                .into_iter()
+                .map(|(a, b)| (k[a].clone(), k[b].clone()))
+                .collect();
+        }
+        for i in 80..2 * self.n_rounds {
+            let bit = k[i - 80].clone() ^ k[i - 61].clone() ^ k[i - 50].clone() ^ k[i - 13].clone();
+            k.push(bit);
+        }
+        (0..self.n_rounds)
+            .map(|round| (k[2 * round].clone(), k[2 * round + 1].clone()))
+            .collect()
+    }
+
+    fn and(&self, a: &Bit, b: &Bit) -> Bit {
+        self.sbox.apply(vec![a.clone(), b.clone()]).pop().unwrap()
+    }
+}
+
+/// The indices of the key bits (ka, kb) of each round of KTANTAN. The key is made of the five words
+/// w_j = k_{16j+15}..k_{16j}, from each of which the round counter T7..T0 selects the bit a_j of
+/// index T7T6T5T4. Then ka is a_0 if T3 = T2 = 0 and a_{1+T1T0} otherwise, and kb is a_4 if
+/// T3 = 0 and T2 = 1 and a_{3-T1T0} otherwise.
+fn ktantan_key_bits(n_rounds: usize) -> Vec<(usize, usize)> {
+    // The counter is the LFSR whose bit T7 is the irregular update bit, clocked before each round
+    let mut counter: usize = 0xff;
+    (0..n_rounds)
+        .map(|round| {
+            let feedback = (counter >> 7) ^ (counter >> 6) ^ (counter >> 4) ^ (counter >> 2);
+            counter = ((counter << 1) | (feedback & 1)) & 0xff;
+            debug_assert_eq!(IR.as_bytes()[round] == b'1', counter >> 7 == 1);
+            let (index, select) = (counter >> 4, counter & 3);
+            let word_a = if counter & 0xc == 0 { 0 } else { 1 + select };
+            let word_b = if counter & 0xc == 0x4 { 4 } else { 3 - select };
+            (16 * word_a + index, 16 * word_b + index)
+        })
+        .collect()
+}
+
+impl Cipher for Katan {
+    /// Registers are indexed as in the specification, L2 holding the least significant bits of the
+    /// plaintext, and bit 0 of each register being the one receiving the feedback.
+    fn encrypt(&self, in_bits: Vec<Bit>, key_bits: Vec<Bit>) -> Vec<Bit> {
+        assert_eq!(in_bits.len(), self.message_length);
+        let round_keys = self.make_round_keys(key_bits);
+        let mut state = in_bits.into_iter().rev().collect::<Vec<Bit>>();
+        let mut l1 = state.split_off(self.l2_length);
+        let mut l2 = state;
+        let (x, y) = (self.x, self.y);
+        for (round, (ka, kb)) in round_keys.into_iter().enumerate() {
+            for _ in 0..self.clocks {
+                let mut fa = l1[x[0]].clone()
+                    ^ l1[x[1]].clone()
+                    ^ self.and(&l1[x[2]], &l1[x[3]])
+                    ^ ka.clone();
+                if self.ir[round] {
+                    fa ^= l1[x[4]].clone();
+                }
+                let fb = l2[y[0]].clone()
+                    ^ l2[y[1]].clone()
+                    ^ self.and(&l2[y[2]], &l2[y[3]])
+                    ^ self.and(&l2[y[4]], &l2[y[5]])
+                    ^ kb.clone();
+                l1.pop();
+                l1.insert(0, fb);
+                l2.pop();
+                l2.insert(0, fa);
+            }
+        }
+        debug_assert_eq!(l1.len(), self.l1_length);
+        l2.append(&mut l1);
+        l2.into_iter().rev().collect()
+    }
+
+    fn message_length(&self) -> usize {
+        self.message_length
+    }
+
+    fn key_length(&self) -> usize {
+        self.key_length
+    }
+
+    fn n_rounds(&self) -> usize {
+        self.n_rounds
+    }
+
+    fn sbox(&self) -> Sbox {
+        self.sbox.clone()
+    }
+}
+
+// from https://www.iacr.org/archive/ches2009/57470271/57470271.pdf
+#[cfg(test)]
+mod test {
+    use crate::bit;
+    use crate::targets::{katan::Katan, Cipher};
+
+    #[test]
+    fn validate_encrypt() {
+        let ones = "ffffffffffffffffffff";
+        let zeros = "00000000000000000000";
+
+        let katan = Katan::new(32, 254);
+        let ciphertext = katan.encrypt(
+            bit::bits_from_hex_string("00000000"),
+            bit::bits_from_hex_string(ones),
+        );
+        assert_eq!("7e1ff945", bit::bits_to_hex_string(ciphertext));
+        let ciphertext = katan.encrypt(
+            bit::bits_from_hex_string("ffffffff"),
+            bit::bits_from_hex_string(zeros),
+        );
+        assert_eq!("432e61da", bit::bits_to_hex_string(ciphertext));
+
+        let katan = Katan::new(48, 254);
+        let ciphertext = katan.encrypt(
+            bit::bits_from_hex_string("000000000000"),
+            bit::bits_from_hex_string(ones),
+        );
+        assert_eq!("4b7efcfb8659", bit::bits_to_hex_string(ciphertext));
+        let ciphertext = katan.encrypt(
+            bit::bits_from_hex_string("ffffffffffff"),
+            bit::bits_from_hex_string(zeros),
+        );
+        assert_eq!("a4bd196d0b85", bit::bits_to_hex_string(ciphertext));
+
+        let katan = Katan::new(64, 254);
+        let ciphertext = katan.encrypt(
+            bit::bits_from_hex_string("0000000000000000"),
+            bit::bits_from_hex_string(ones),
+        );
+        assert_eq!("21f2e99c0fab828a", bit::bits_to_hex_string(ciphertext));
+        let ciphertext = katan.encrypt(
+            bit::bits_from_hex_string("ffffffffffffffff"),
+            bit::bits_from_hex_string(zeros),
+        );
+        assert_eq!("c956100dbeb64ba8", bit::bits_to_hex_string(ciphertext));
+    }
+
+    #[test]
+    fn validate_encrypt_ktantan() {
+        // Under the all-ones key, every key bit of KTANTAN is 1, and under the zero key it is
+        // KATAN
+        let ones = "ffffffffffffffffffff";
+        let zeros = "00000000000000000000";
+        for (block_size, ciphertext_ones, ciphertext_zeros) in [
+            (32, "22ea3988", "432e61da"),
+            (48, "936d0fa33a05", "a4bd196d0b85"),
+            (64, "c02de05bfa194b16", "c956100dbeb64ba8"),
+        ]
+        .iter()
+        {
+            let katan = Katan::ktantan(*block_size, 254);
+            let ciphertext = katan.encrypt(
+                bit::bits_from_hex_string(&"0".repeat(block_size / 4)),
+                bit::bits_from_hex_string(ones),
+            );
+            assert_eq!(*ciphertext_ones, bit::bits_to_hex_string(ciphertext));
+            let ciphertext = katan.encrypt(
+                bit::bits_from_hex_string(&"f".repeat(block_size / 4)),
+                bit::bits_from_hex_string(zeros),
+            );
+            assert_eq!(*ciphertext_zeros, bit::bits_to_hex_string(ciphertext));
+        }
+    }
+
+    #[test]
+    fn ktantan_round_function() {
+        // The counter starts at 0xfe, selecting bit 15 of w3 and w1, then 0xfc and 0xf8 select
+        // bit 15 of w1 and w3
+        assert_eq!(
+            vec![(63, 31), (31, 63), (31, 63), (15, 47), (14, 14)],
+            super::ktantan_key_bits(5)
+        );
+        // k_32 is the last key bit to be used, in round 218
+        let key_bits = super::ktantan_key_bits(254);
+        assert_eq!(
+            Some(218),
+            key_bits.iter().position(|(a, b)| *a == 32 || *b == 32)
+        );
+
+        // One, two and all the rounds under a key of distinct words
+        let key = "0123456789abcdef0123";
+        for (block_size, plaintext, ciphertexts) in [
+            (32, "89abcdef", ["135f9bde", "26bf37bc", "6f038f14"]),
+            (
+                48,
+                "89abcdef0123",
+                ["26af37bc048c", "9abcbef01232", "508446b935ed"],
+            ),
+            (
+                64,
+                "89abcdef01234567",
+                ["4d5e6ff8091a2b39", "6af37fc048d159cf", "5cef88d6d8d6da75"],
+            ),
+        ]
+        .iter()
+        {
+            for (n_rounds, ciphertext) in [1, 2, 254].iter().zip(ciphertexts.iter()) {
+                let katan = Katan::ktantan(*block_size, *n_rounds);
+                let encrypted = katan.encrypt(
+                    bit::bits_from_hex_string(plaintext),
+                    bit::bits_from_hex_string(key),
+                );
+                assert_eq!(*ciphertext, bit::bits_to_hex_string(encrypted));
+            }
+        }
+    }
+
+    #[test]
+    fn reduced_rounds_system() {
+        // Two nonlinear ANDs in fb and one in fa per clock, KATAN48 clocks twice per round.
+        let katan = Katan::new(48, 10);
+        let (_, _, system) = crate::targets::build_system_cipher(&katan);
+        assert_eq!(system.iter_bdds().count(), 10 * 2 * 3);
+    }
+}
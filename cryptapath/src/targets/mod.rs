@@ -1,22 +1,30 @@
 pub mod des;
+pub mod katan;
 pub mod keccak;
 pub mod lowmc;
 pub mod miniaes2x2;
 pub mod miniaes4x4;
 pub mod present80;
 pub mod prince;
+pub mod simeck;
+pub mod simon;
 pub mod skinny128;
 pub mod skinny64;
+pub mod speck;
 
 use des::DES;
+use katan::Katan;
 use keccak::Keccak;
 use lowmc::LowMC;
 use miniaes2x2::MiniAES2x2;
 use miniaes4x4::MiniAES4x4;
 use present80::Present80;
 use prince::Prince;
+use simeck::Simeck;
+use simon::Simon;
 use skinny128::Skinny128;
 use skinny64::Skinny64;
+use speck::Speck;
 
 use crate::bit::{self, Bit, *};
 use crate::sbox::Sbox;
@@ -63,7 +71,7 @@ pub fn build_system_sponge(hash: &dyn SpongeHash) -> (Vec<Bit>, System) {
         hash.message_length() + (hash.state_length() * hash.n_rounds()) * n_state,
         bdds,
     );
-    (output, build_system_from_spec(system_spec))
+    (output, build_system_from_spec(system_spec).expect("the sbox bdds are over the variables of the system"))
 }
 
 pub fn build_system_cipher(cipher: &dyn Cipher) -> (Vec<Bit>, Vec<Bit>, System) {
@@ -79,7 +87,7 @@ pub fn build_system_cipher(cipher: &dyn Cipher) -> (Vec<Bit>, Vec<Bit>, System)
     let mut sbox = cipher.sbox();
     let bdds = sbox.bdds();
     let system_spec = SystemSpec::new(sbox.next_var_id(), bdds);
-    (message_bits, output, build_system_from_spec(system_spec))
+    (message_bits, output, build_system_from_spec(system_spec).expect("the sbox bdds are over the variables of the system"))
 }
 
 pub fn get_random_sponge_output(hash: &dyn SpongeHash) -> Vec<Bit> {
@@ -280,6 +288,35 @@ pub fn build_cipher_by_name(name: &str, rounds: usize) -> Option<Box<dyn Cipher>
         "prince" => Some(Box::new(Prince::new(rounds, true))),
         "prince-core" => Some(Box::new(Prince::new(rounds, false))),
         "des" => Some(Box::new(DES::new(rounds))),
+        "katan32" => Some(Box::new(Katan::new(32, rounds))),
+        "katan48" => Some(Box::new(Katan::new(48, rounds))),
+        "katan64" => Some(Box::new(Katan::new(64, rounds))),
+        "ktantan32" => Some(Box::new(Katan::ktantan(32, rounds))),
+        "ktantan48" => Some(Box::new(Katan::ktantan(48, rounds))),
+        "ktantan64" => Some(Box::new(Katan::ktantan(64, rounds))),
+        "speck3264" => Some(Box::new(Speck::new(32, 64, rounds))),
+        "speck4872" => Some(Box::new(Speck::new(48, 72, rounds))),
+        "speck4896" => Some(Box::new(Speck::new(48, 96, rounds))),
+        "speck6496" => Some(Box::new(Speck::new(64, 96, rounds))),
+        "speck64128" => Some(Box::new(Speck::new(64, 128, rounds))),
+        "speck9696" => Some(Box::new(Speck::new(96, 96, rounds))),
+        "speck96144" => Some(Box::new(Speck::new(96, 144, rounds))),
+        "speck128128" => Some(Box::new(Speck::new(128, 128, rounds))),
+        "speck128192" => Some(Box::new(Speck::new(128, 192, rounds))),
+        "speck128256" => Some(Box::new(Speck::new(128, 256, rounds))),
+        "simon3264" => Some(Box::new(Simon::new(32, 64, rounds))),
+        "simon4872" => Some(Box::new(Simon::new(48, 72, rounds))),
+        "simon4896" => Some(Box::new(Simon::new(48, 96, rounds))),
+        "simon6496" => Some(Box::new(Simon::new(64, 96, rounds))),
+        "simon64128" => Some(Box::new(Simon::new(64, 128, rounds))),
+        "simon9696" => Some(Box::new(Simon::new(96, 96, rounds))),
+        "simon96144" => Some(Box::new(Simon::new(96, 144, rounds))),
+        "simon128128" => Some(Box::new(Simon::new(128, 128, rounds))),
+        "simon128192" => Some(Box::new(Simon::new(128, 192, rounds))),
+        "simon128256" => Some(Box::new(Simon::new(128, 256, rounds))),
+        "simeck3264" => Some(Box::new(Simeck::new(32, rounds))),
+        "simeck4896" => Some(Box::new(Simeck::new(48, rounds))),
+        "simeck64128" => Some(Box::new(Simeck::new(64, rounds))),
         _ => None,
     }
 }
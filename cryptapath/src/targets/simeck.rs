@@ -0,0 +1,143 @@
+use crate::bit::{Bit, *};
+use crate::sbox::Sbox;
+use crate::targets::simon::{and_rx, and_sbox};
+use crate::targets::Cipher;
+
+pub struct Simeck {
+    n_rounds: usize,
+    message_length: usize,
+    key_length: usize,
+    word_size: usize,
+    sequence: u64,
+    sbox: Sbox,
+}
+
+impl Simeck {
+    /// Simeck with a block of block_size bits, and a key of twice that size.
+    /// Supported block sizes are 32, 48 and 64, see full_rounds.
+    pub fn new(block_size: usize, n_rounds: usize) -> Self {
+        assert!(
+            Simeck::full_rounds(block_size).is_some(),
+            "Simeck{} is not a standard parameter set",
+            block_size
+        );
+        // The constants of the key schedule, generated by LFSRs, least significant bit first.
+        let sequence = if block_size == 64 { 0x938BCA3083F } else { 0x9A42BB1F };
+        Simeck {
+            n_rounds,
+            message_length: block_size,
+            key_length: 2 * block_size,
+            word_size: block_size / 2,
+            sequence,
+            sbox: and_sbox(3 * block_size),
+        }
+    }
+
+    /// The number of rounds of the full cipher, or None if the block size is not a standard one.
+    pub fn full_rounds(block_size: usize) -> Option<usize> {
+        match block_size {
+            32 => Some(32),
+            48 => Some(36),
+            64 => Some(44),
+            _ => None,
+        }
+    }
+
+    /// Simeck uses its round function in the key schedule, which therefore makes variables as well.
+    /// The key is given as the words (t_2, t_1, t_0, k_0), as in the test vectors of the designers.
+    fn make_round_keys(&self, key: Vec<Bit>) -> Vec<Vec<Bit>> {
+        assert_eq!(key.len(), self.key_length);
+        let mut words = key
+            .chunks(self.word_size)
+            .rev()
+            .map(|word| word.to_vec())
+            .collect::<Vec<Vec<Bit>>>();
+        let mut round_keys = Vec::with_capacity(self.n_rounds);
+        let mut sequence = self.sequence;
+        for i in 0..self.n_rounds {
+            round_keys.push(words[0].clone());
+            if i + 1 == self.n_rounds {
+                break;
+            }
+            // c ^ z_i, with c = 2^n - 4
+            let mut constant = vec![bit!(true); self.word_size];
+            constant[self.word_size - 2] = bit!(false);
+            constant[self.word_size - 1] = bit!(sequence & 1 == 1);
+            sequence >>= 1;
+            let f = and_rx(&self.sbox, &words[1], 0, 5, 1);
+            let t = bit_vector_xoring(bit_vector_xoring(words[0].clone(), f), constant);
+            words.remove(0);
+            words.push(t);
+        }
+        round_keys
+    }
+}
+
+impl Cipher for Simeck {
+    fn encrypt(&self, in_bits: Vec<Bit>, key_bits: Vec<Bit>) -> Vec<Bit> {
+        assert_eq!(in_bits.len(), self.message_length);
+        let round_keys = self.make_round_keys(key_bits);
+        let mut x = in_bits[..self.word_size].to_vec();
+        let mut y = in_bits[self.word_size..].to_vec();
+        for round_key in round_keys {
+            let f = and_rx(&self.sbox, &x, 0, 5, 1);
+            let new_x = bit_vector_xoring(bit_vector_xoring(y, f), round_key);
+            y = x;
+            x = new_x;
+        }
+        x.append(&mut y);
+        x
+    }
+
+    fn message_length(&self) -> usize {
+        self.message_length
+    }
+
+    fn key_length(&self) -> usize {
+        self.key_length
+    }
+
+    fn n_rounds(&self) -> usize {
+        self.n_rounds
+    }
+
+    fn sbox(&self) -> Sbox {
+        self.sbox.clone()
+    }
+}
+
+// from https://eprint.iacr.org/2015/612.pdf
+#[cfg(test)]
+mod test {
+    use crate::bit;
+    use crate::targets::{simeck::Simeck, Cipher};
+
+    #[test]
+    fn validate_encrypt() {
+        let simeck = Simeck::new(32, 32);
+        let message = bit::bits_from_hex_string("65656877");
+        let key = bit::bits_from_hex_string("1918111009080100");
+        let ciphertext = simeck.encrypt(message, key);
+        assert_eq!("770d2c76", bit::bits_to_hex_string(ciphertext));
+
+        let simeck = Simeck::new(48, 36);
+        let message = bit::bits_from_hex_string("72696320646e");
+        let key = bit::bits_from_hex_string("1a19181211100a0908020100");
+        let ciphertext = simeck.encrypt(message, key);
+        assert_eq!("f3cf25e33b36", bit::bits_to_hex_string(ciphertext));
+
+        let simeck = Simeck::new(64, 44);
+        let message = bit::bits_from_hex_string("656b696c20646e75");
+        let key = bit::bits_from_hex_string("1b1a1918131211100b0a090803020100");
+        let ciphertext = simeck.encrypt(message, key);
+        assert_eq!("45ce69025f7ab7ed", bit::bits_to_hex_string(ciphertext));
+    }
+
+    #[test]
+    fn reduced_rounds_system() {
+        // One AND per bit of the word and per round, in both the key schedule and the encryption.
+        let simeck = Simeck::new(32, 4);
+        let (_, _, system) = crate::targets::build_system_cipher(&simeck);
+        assert_eq!(system.iter_bdds().count(), (4 + 3) * 16);
+    }
+}
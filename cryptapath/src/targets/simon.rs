@@ -0,0 +1,234 @@
+use crate::bit::{Bit, *};
+use crate::sbox::Sbox;
+use crate::targets::Cipher;
+
+const Z: [&str; 5] = [
+    "11111010001001010110000111001101111101000100101011000011100110",
+    "10001110111110010011000010110101000111011111001001100001011010",
+    "10101111011100000011010010011000101000010001111110010110110011",
+    "11011011101011000110010111100000010010001010011100110100001111",
+    "11010001111001101011011000100000010111000011001010010011101111",
+];
+
+pub struct Simon {
+    n_rounds: usize,
+    message_length: usize,
+    key_length: usize,
+    word_size: usize,
+    key_words: usize,
+    z: Vec<bool>,
+    sbox: Sbox,
+}
+
+impl Simon {
+    /// Simon with a block of block_size bits and a key of key_size bits. All the standard
+    /// parameter sets are supported, see full_rounds for the list.
+    pub fn new(block_size: usize, key_size: usize, n_rounds: usize) -> Self {
+        let (_, z) = Simon::parameters(block_size, key_size).unwrap_or_else(|| {
+            panic!("Simon{}/{} is not a standard parameter set", block_size, key_size)
+        });
+        let word_size = block_size / 2;
+        Simon {
+            n_rounds,
+            message_length: block_size,
+            key_length: key_size,
+            word_size,
+            key_words: key_size / word_size,
+            z: Z[z].chars().map(|c| c == '1').collect(),
+            sbox: and_sbox(block_size + key_size),
+        }
+    }
+
+    /// The number of rounds of the full cipher, or None if the block and key sizes do not
+    /// form a standard parameter set.
+    pub fn full_rounds(block_size: usize, key_size: usize) -> Option<usize> {
+        Simon::parameters(block_size, key_size).map(|(rounds, _)| rounds)
+    }
+
+    /// The number of rounds and the index of the constant sequence z used by the key schedule.
+    fn parameters(block_size: usize, key_size: usize) -> Option<(usize, usize)> {
+        match (block_size, key_size) {
+            (32, 64) => Some((32, 0)),
+            (48, 72) => Some((36, 0)),
+            (48, 96) => Some((36, 1)),
+            (64, 96) => Some((42, 2)),
+            (64, 128) => Some((44, 3)),
+            (96, 96) => Some((52, 2)),
+            (96, 144) => Some((54, 3)),
+            (128, 128) => Some((68, 2)),
+            (128, 192) => Some((69, 3)),
+            (128, 256) => Some((72, 4)),
+            _ => None,
+        }
+    }
+
+    /// The key schedule is linear, so it does not make any variables.
+    /// The key is given as the words (k_{m-1}, ..., k_0), as in the test vectors of the designers.
+    fn make_round_keys(&self, key: Vec<Bit>) -> Vec<Vec<Bit>> {
+        assert_eq!(key.len(), self.key_length);
+        let mut round_keys = key
+            .chunks(self.word_size)
+            .rev()
+            .map(|word| word.to_vec())
+            .collect::<Vec<Vec<Bit>>>();
+        let m = self.key_words;
+        for i in 0..self.n_rounds.saturating_sub(m) {
+            let mut tmp = ror(round_keys[i + m - 1].clone(), 3);
+            if m == 4 {
+                tmp = bit_vector_xoring(tmp, round_keys[i + 1].clone());
+            }
+            tmp = bit_vector_xoring(tmp.clone(), ror(tmp, 1));
+            // c ^ z_i, with c = 2^n - 4
+            let mut constant = vec![bit!(true); self.word_size];
+            constant[self.word_size - 2] = bit!(false);
+            constant[self.word_size - 1] = bit!(self.z[i % 62]);
+            round_keys.push(bit_vector_xoring(
+                bit_vector_xoring(round_keys[i].clone(), tmp),
+                constant,
+            ));
+        }
+        round_keys.truncate(self.n_rounds);
+        round_keys
+    }
+}
+
+impl Cipher for Simon {
+    fn encrypt(&self, in_bits: Vec<Bit>, key_bits: Vec<Bit>) -> Vec<Bit> {
+        assert_eq!(in_bits.len(), self.message_length);
+        let round_keys = self.make_round_keys(key_bits);
+        let mut x = in_bits[..self.word_size].to_vec();
+        let mut y = in_bits[self.word_size..].to_vec();
+        for round_key in round_keys {
+            let f = and_rx(&self.sbox, &x, 1, 8, 2);
+            let new_x = bit_vector_xoring(bit_vector_xoring(y, f), round_key);
+            y = x;
+            x = new_x;
+        }
+        x.append(&mut y);
+        x
+    }
+
+    fn message_length(&self) -> usize {
+        self.message_length
+    }
+
+    fn key_length(&self) -> usize {
+        self.key_length
+    }
+
+    fn n_rounds(&self) -> usize {
+        self.n_rounds
+    }
+
+    fn sbox(&self) -> Sbox {
+        self.sbox.clone()
+    }
+}
+
+/// The S-Box computing the AND of two bits.
+pub(crate) fn and_sbox(next_var_id: usize) -> Sbox {
+    Sbox::new(2, 1, vec![0, 0, 0, 1], next_var_id)
+}
+
+/// The non-linear function of Simon-like ciphers: (S^a x & S^b x) ^ S^c x, where S^r is a left
+/// rotation by r. Each bit of the AND is a S-Box application of its own, while the rotations and
+/// the XOR are linear and only end up in the left hand sides of the BDDs.
+pub(crate) fn and_rx(sbox: &Sbox, x: &[Bit], a: usize, b: usize, c: usize) -> Vec<Bit> {
+    let xa = rol(x.to_vec(), a);
+    let xb = rol(x.to_vec(), b);
+    let and = xa
+        .into_iter()
+        .zip(xb)
+        .map(|(bit_a, bit_b)| sbox.apply(vec![bit_a, bit_b]).pop().unwrap())
+        .collect();
+    bit_vector_xoring(and, rol(x.to_vec(), c))
+}
+
+/// Left rotation of a word, with the most significant bit first.
+pub(crate) fn rol(mut word: Vec<Bit>, r: usize) -> Vec<Bit> {
+    word.rotate_left(r);
+    word
+}
+
+/// Right rotation of a word, with the most significant bit first.
+pub(crate) fn ror(mut word: Vec<Bit>, r: usize) -> Vec<Bit> {
+    word.rotate_right(r);
+    word
+}
+
+// from https://eprint.iacr.org/2013/404.pdf
+#[cfg(test)]
+mod test {
+    use crate::bit;
+    use crate::targets::{simon::Simon, Cipher};
+
+    fn check(block_size: usize, key_size: usize, key: &str, message: &str, expected: &str) {
+        let simon = Simon::new(
+            block_size,
+            key_size,
+            Simon::full_rounds(block_size, key_size).unwrap(),
+        );
+        let message = bit::bits_from_hex_string(message);
+        let key = bit::bits_from_hex_string(key);
+        let ciphertext = simon.encrypt(message, key);
+        assert_eq!(expected, bit::bits_to_hex_string(ciphertext));
+    }
+
+    #[test]
+    fn validate_encrypt() {
+        check(32, 64, "1918111009080100", "65656877", "c69be9bb");
+        check(48, 72, "1211100a0908020100", "6120676e696c", "dae5ac292cac");
+        check(48, 96, "1a19181211100a0908020100", "72696320646e", "6e06a5acf156");
+        check(64, 96, "131211100b0a090803020100", "6f7220676e696c63", "5ca2e27f111a8fc8");
+        check(
+            64,
+            128,
+            "1b1a1918131211100b0a090803020100",
+            "656b696c20646e75",
+            "44c8fc20b9dfa07a",
+        );
+        check(
+            96,
+            96,
+            "0d0c0b0a0908050403020100",
+            "2072616c6c69702065687420",
+            "602807a462b469063d8ff082",
+        );
+        check(
+            96,
+            144,
+            "1514131211100d0c0b0a0908050403020100",
+            "74616874207473756420666f",
+            "ecad1c6c451e3f59c5db1ae9",
+        );
+        check(
+            128,
+            128,
+            "0f0e0d0c0b0a09080706050403020100",
+            "63736564207372656c6c657661727420",
+            "49681b1e1e54fe3f65aa832af84e0bbc",
+        );
+        check(
+            128,
+            192,
+            "17161514131211100f0e0d0c0b0a09080706050403020100",
+            "206572656874206e6568772065626972",
+            "c4ac61effcdc0d4f6c9c8d6e2597b85b",
+        );
+        check(
+            128,
+            256,
+            "1f1e1d1c1b1a191817161514131211100f0e0d0c0b0a09080706050403020100",
+            "74206e69206d6f6f6d69732061207369",
+            "8d2b5579afc8a3a03bf72a87efe7b868",
+        );
+    }
+
+    #[test]
+    fn reduced_rounds_system() {
+        // One AND per bit of the word and per round, the key schedule is linear.
+        let simon = Simon::new(32, 64, 5);
+        let (_, _, system) = crate::targets::build_system_cipher(&simon);
+        assert_eq!(system.iter_bdds().count(), 5 * 16);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::bit::{self, Bit, *};
+use crate::modular_addition::ModularAdder;
+use crate::sbox::Sbox;
+use crate::targets::Cipher;
+
+pub struct Speck {
+    n_rounds: usize,
+    message_length: usize,
+    key_length: usize,
+    word_size: usize,
+    key_words: usize,
+    alpha: usize,
+    beta: usize,
+    adder: ModularAdder,
+}
+
+impl Speck {
+    /// Speck with a block of block_size bits and a key of key_size bits. All the standard
+    /// parameter sets are supported, see full_rounds for the list.
+    pub fn new(block_size: usize, key_size: usize, n_rounds: usize) -> Self {
+        assert!(
+            Speck::full_rounds(block_size, key_size).is_some(),
+            "Speck{}/{} is not a standard parameter set",
+            block_size,
+            key_size
+        );
+        let word_size = block_size / 2;
+        let (alpha, beta) = if word_size == 16 { (7, 2) } else { (8, 3) };
+        Speck {
+            n_rounds,
+            message_length: block_size,
+            key_length: key_size,
+            word_size,
+            key_words: key_size / word_size,
+            alpha,
+            beta,
+            adder: ModularAdder::new(block_size + key_size),
+        }
+    }
+
+    /// The number of rounds of the full cipher, or None if the block and key sizes do not
+    /// form a standard parameter set.
+    pub fn full_rounds(block_size: usize, key_size: usize) -> Option<usize> {
+        match (block_size, key_size) {
+            (32, 64) => Some(22),
+            (48, 72) => Some(22),
+            (48, 96) => Some(23),
+            (64, 96) => Some(26),
+            (64, 128) => Some(27),
+            (96, 96) => Some(28),
+            (96, 144) => Some(29),
+            (128, 128) => Some(32),
+            (128, 192) => Some(33),
+            (128, 256) => Some(34),
+            _ => None,
+        }
+    }
+
+    fn ror(&self, mut word: Vec<Bit>, r: usize) -> Vec<Bit> {
+        word.rotate_right(r);
+        word
+    }
+
+    fn rol(&self, mut word: Vec<Bit>, r: usize) -> Vec<Bit> {
+        word.rotate_left(r);
+        word
+    }
+
+    /// The round function, applied on (x, y) with the round key k.
+    fn round(&self, x: Vec<Bit>, y: Vec<Bit>, k: Vec<Bit>) -> (Vec<Bit>, Vec<Bit>) {
+        let x = bit_vector_xoring(self.adder.add(&self.ror(x, self.alpha), &y), k);
+        let y = bit_vector_xoring(self.rol(y, self.beta), x.clone());
+        (x, y)
+    }
+
+    /// The key is given as the words (l_{m-2}, ..., l_0, k_0), with the first round key k_0
+    /// last, as in the test vectors of the designers.
+    fn make_round_keys(&self, key: Vec<Bit>) -> Vec<Vec<Bit>> {
+        assert_eq!(key.len(), self.key_length);
+        let mut words = key
+            .chunks(self.word_size)
+            .map(|word| word.to_vec())
+            .collect::<Vec<Vec<Bit>>>();
+        let mut k = words.pop().unwrap();
+        let mut l = words.into_iter().rev().collect::<Vec<Vec<Bit>>>();
+        let mut round_keys = Vec::with_capacity(self.n_rounds);
+        for i in 0..self.n_rounds {
+            round_keys.push(k.clone());
+            if i + 1 == self.n_rounds {
+                break;
+            }
+            let counter = bit::bits_from_binary_string(&format!("{:0w$b}", i, w = self.word_size));
+            let (new_l, new_k) = self.round(l[i].clone(), k, counter);
+            l.push(new_l);
+            k = new_k;
+        }
+        debug_assert_eq!(l.len(), self.key_words - 1 + self.n_rounds.saturating_sub(1));
+        round_keys
+    }
+}
+
+impl Cipher for Speck {
+    fn encrypt(&self, in_bits: Vec<Bit>, key_bits: Vec<Bit>) -> Vec<Bit> {
+        assert_eq!(in_bits.len(), self.message_length);
+        let round_keys = self.make_round_keys(key_bits);
+        let mut x = in_bits[..self.word_size].to_vec();
+        let mut y = in_bits[self.word_size..].to_vec();
+        for round_key in round_keys {
+            let (new_x, new_y) = self.round(x, y, round_key);
+            x = new_x;
+            y = new_y;
+        }
+        x.append(&mut y);
+        x
+    }
+
+    fn message_length(&self) -> usize {
+        self.message_length
+    }
+
+    fn key_length(&self) -> usize {
+        self.key_length
+    }
+
+    fn n_rounds(&self) -> usize {
+        self.n_rounds
+    }
+
+    fn sbox(&self) -> Sbox {
+        self.adder.sbox()
+    }
+}
+
+// from https://eprint.iacr.org/2013/404.pdf
+#[cfg(test)]
+mod test {
+    use crush::soc::bdd::differential::{PPFactory, StyledProgressBar};
+    use pathfinder::ciphers::speck;
+    use pathfinder::diff_solver::{SPFactory, SolverConfig, SolverResult};
+
+    use crate::bit;
+    use crate::targets::{speck::Speck, Cipher};
+
+    fn check(block_size: usize, key_size: usize, key: &str, message: &str, expected: &str) {
+        let speck = Speck::new(
+            block_size,
+            key_size,
+            Speck::full_rounds(block_size, key_size).unwrap(),
+        );
+        let message = bit::bits_from_hex_string(message);
+        let key = bit::bits_from_hex_string(key);
+        let ciphertext = speck.encrypt(message, key);
+        assert_eq!(expected, bit::bits_to_hex_string(ciphertext));
+    }
+
+    #[test]
+    fn validate_encrypt() {
+        check(32, 64, "1918111009080100", "6574694c", "a86842f2");
+        check(48, 72, "1211100a0908020100", "20796c6c6172", "c049a5385adc");
+        check(48, 96, "1a19181211100a0908020100", "6d2073696874", "735e10b6445d");
+        check(64, 96, "131211100b0a090803020100", "74614620736e6165", "9f7952ec4175946c");
+        check(
+            64,
+            128,
+            "1b1a1918131211100b0a090803020100",
+            "3b7265747475432d",
+            "8c6fa548454e028b",
+        );
+        check(
+            96,
+            96,
+            "0d0c0b0a0908050403020100",
+            "65776f68202c656761737520",
+            "9e4d09ab717862bdde8f79aa",
+        );
+        check(
+            96,
+            144,
+            "1514131211100d0c0b0a0908050403020100",
+            "656d6974206e69202c726576",
+            "2bf31072228a7ae440252ee6",
+        );
+        check(
+            128,
+            128,
+            "0f0e0d0c0b0a09080706050403020100",
+            "6c617669757165207469206564616d20",
+            "a65d9851797832657860fedf5c570d18",
+        );
+        check(
+            128,
+            192,
+            "17161514131211100f0e0d0c0b0a09080706050403020100",
+            "726148206665696843206f7420746e65",
+            "1be4cf3a13135566f9bc185de03c1886",
+        );
+        check(
+            128,
+            256,
+            "1f1e1d1c1b1a191817161514131211100f0e0d0c0b0a09080706050403020100",
+            "65736f6874206e49202e72656e6f6f70",
+            "4109010405c0f53e4eeeb48d9c188f43",
+        );
+    }
+
+    #[test]
+    fn sampled_trail_weights() {
+        // A sanity check of the trail of optimal_trail_weights, starting with (0x0040, 0x0000)
+        // and going through (0x8000, 0x8000), (0x8100, 0x8102) and (0x8000, 0x840a), of weights
+        // 0, 1 and 3 over 1, 2 and 3 rounds: the pairs following it over the first rounds must be
+        // about a fraction 2^-weight of all the pairs.
+        let trail = [(0x8000, 0x8000), (0x8100, 0x8102), (0x8000, 0x840a)];
+        let key = bit::bits_from_hex_string("1918111009080100");
+        let nr_pairs = 1 << 10;
+        for (i, (weight, delta_out)) in [0, 1, 3].iter().zip(trail.iter()).enumerate() {
+            let speck = Speck::new(32, 64, i + 1);
+            let encrypt = |x: u32, y: u32| {
+                let message = bit::bits_from_hex_string(&format!("{:04x}{:04x}", x, y));
+                let ciphertext = bit::bits_to_hex_string(speck.encrypt(message, key.clone()));
+                u32::from_str_radix(&ciphertext, 16).unwrap()
+            };
+            let followed = (0..nr_pairs)
+                .filter(|i| {
+                    let (x, y) = ((i * 0x9e37) & 0xffff, (i * 0x7f4b + 0x1234) & 0xffff);
+                    encrypt(x, y) ^ encrypt(x ^ 0x0040, y) == delta_out.0 << 16 | delta_out.1
+                })
+                .count();
+            let measured = -(followed as f64 / nr_pairs as f64).log2();
+            assert_eq!(*weight, measured.round() as u32);
+        }
+    }
+
+    /// A progress factory of the solver of pathfinder reporting nothing.
+    #[derive(Debug, Clone)]
+    struct Silent;
+
+    impl StyledProgressBar for Silent {
+        fn inc(&self, _delta: u64) {}
+        fn set_message(&self, _msg: &str) {}
+        fn finish_with_message(&self, _msg: &str) {}
+        fn finish_and_clear(&self) {}
+        fn println(&self, _msg: &str) {}
+    }
+
+    impl SPFactory for Silent {
+        type ProgressBar = Silent;
+
+        fn new_solve_progress(&self, _len: u64) -> Silent {
+            Silent
+        }
+    }
+
+    impl PPFactory for Silent {
+        type ProgressBar = Silent;
+
+        fn new_progress_bar(&self, _len: u64) -> Silent {
+            Silent
+        }
+    }
+
+    #[test]
+    fn optimal_trail_weights() {
+        // The differential model of Speck32 solved by the SimpleSolver of pathfinder must be the
+        // one of this Speck32
+        let key = [0x1918, 0x1110, 0x0908, 0x0100];
+        let key_bits = bit::bits_from_hex_string("1918111009080100");
+        for nr_rounds in 1..4 {
+            let speck = Speck::new(32, 64, nr_rounds);
+            let model = speck::Speck::new(nr_rounds);
+            for (x, y) in [(0x6574, 0x694c), (0x0000, 0x0000), (0xffff, 0x0040)].iter() {
+                let message = bit::bits_from_hex_string(&format!("{:04x}{:04x}", x, y));
+                let ciphertext = bit::bits_to_hex_string(speck.encrypt(message, key_bits.clone()));
+                let (cx, cy) = model.encrypt((*x, *y), key);
+                assert_eq!(format!("{:04x}{:04x}", cx, cy), ciphertext);
+            }
+        }
+
+        // The optimal differential trails of Speck32 over 2 and 3 rounds weigh 1 and 3 (Biryukov
+        // and Velichkov, "Automatic Search for Differential Trails in ARX Ciphers", 2014)
+        for (nr_rounds, soft_limit, weight) in [(2, 1 << 12, 1), (3, 1 << 14, 3)].iter() {
+            let config = SolverConfig::new().with_soft_limit(*soft_limit);
+            let mut solver =
+                speck::make_speck_solver(&speck::Speck::new(*nr_rounds), Silent, config);
+            solver.run();
+            match solver.finalize() {
+                SolverResult::ProvedOptimal { weight: found, .. } => assert_eq!(*weight, found),
+                _ => panic!("The solving over {} rounds wasn't complete", nr_rounds),
+            }
+        }
+    }
+
+    #[test]
+    fn reduced_rounds_system() {
+        // Each round makes one carry variable per bit of the word, except for the most
+        // significant one, in both the key schedule and the encryption.
+        let speck = Speck::new(32, 64, 3);
+        let (_, _, system) = crate::targets::build_system_cipher(&speck);
+        assert_eq!(system.iter_bdds().count(), (3 + 2) * 15);
+    }
+}
@@ -0,0 +1,168 @@
+//! The ASCON permutation, see "Ascon v1.2: Lightweight Authenticated Encryption and Hashing"
+//! (Dobraunig et al., Journal of Cryptology 2021) and NIST SP 800-232.
+//!
+//! The state is made of five 64 bit words x0, ..., x4. A round adds a round constant to x2, applies
+//! the 5 bit S-box to each of the 64 columns of bits, x0 being the most significant bit of its
+//! input, and then the linear diffusion layer to each word. The S-box at position j therefore
+//! reads the bits `5 * j` to `5 * j + 5` of the state, bit `5 * j + 4 - k` being bit j of xk. The
+//! round constants are the round keys, such that `encrypt` computes the permutation (the key being
+//! ignored).
+
+use std::io::{Error, ErrorKind};
+
+use vob::Vob;
+
+use crush::algebra::Matrix;
+
+use crate::code_gen::cipher::{Cipher, SBox};
+
+/// The S-box of ASCON.
+const S: [usize; 32] = [
+    0x04, 0x0b, 0x1f, 0x14, 0x1a, 0x15, 0x09, 0x02, 0x1b, 0x05, 0x08, 0x12, 0x1d, 0x03, 0x06, 0x1c,
+    0x1e, 0x13, 0x07, 0x0e, 0x00, 0x0d, 0x11, 0x18, 0x10, 0x0c, 0x01, 0x19, 0x16, 0x0a, 0x0f, 0x17,
+];
+
+/// The rotations of the linear diffusion layer of each word.
+const ROTATIONS: [(usize, usize); 5] = [(19, 28), (61, 39), (1, 6), (10, 17), (7, 41)];
+
+/// The number of rounds of the full permutation.
+pub const MAX_ROUNDS: usize = 12;
+
+/// The ASCON permutation, over its last `nr_rounds` rounds, e.g. 12, 8 or 6 as in the standard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ascon {
+    nr_rounds: usize,
+}
+
+impl Ascon {
+    /// Returns an `Error` unless `nr_rounds` is from 1 to `MAX_ROUNDS`.
+    pub fn new(nr_rounds: usize) -> Result<Ascon, Error> {
+        if nr_rounds == 0 || nr_rounds > MAX_ROUNDS {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                                  format!("ASCON has from 1 to {} rounds, not {}", MAX_ROUNDS, nr_rounds)));
+        }
+        Ok(Ascon { nr_rounds })
+    }
+
+    /// The state bit holding bit `bit` of the word `word`.
+    #[inline]
+    pub fn state_bit(word: usize, bit: usize) -> usize {
+        5 * bit + 4 - word
+    }
+}
+
+impl Cipher for Ascon {
+    fn name(&self) -> String {
+        format!("ASCON-p{}", self.nr_rounds)
+    }
+
+    fn block_size(&self) -> usize {
+        320
+    }
+
+    fn nr_rounds(&self) -> usize {
+        self.nr_rounds
+    }
+
+    fn num_sboxes(&self, _round: usize) -> usize {
+        64
+    }
+
+    fn sbox(&self, _round: usize, _pos: usize) -> SBox {
+        SBox::new(S.to_vec(), 5, 5).unwrap()
+    }
+
+    /// The linear diffusion layer: xk is XORed with its rotations to the right.
+    fn linear_layer(&self, _round: usize) -> Matrix {
+        let mut rows = vec![Vob::from_elem(320, false); 320];
+        for (word, (r1, r2)) in ROTATIONS.iter().enumerate() {
+            for bit in 0..64 {
+                let row = &mut rows[Ascon::state_bit(word, bit)];
+                for from in [bit, (bit + r1) % 64, (bit + r2) % 64].iter() {
+                    row.set(Ascon::state_bit(word, *from), true);
+                }
+            }
+        }
+        Matrix::from_rows(rows)
+    }
+
+    /// The round constants, added to x2.
+    fn round_keys(&self, _key: &Vob) -> Vec<Vob> {
+        (MAX_ROUNDS - self.nr_rounds..MAX_ROUNDS)
+            .map(|round| {
+                let constant = ((0xf - round) << 4) | round;
+                (0..320)
+                    .map(|i| i % 5 == 2 && (constant >> (i / 5)) & 1 == 1)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crush::algebra;
+    use crush::soc::Id;
+
+    use crate::code_gen::cipher::{make_cipher_soc, TrailKind};
+    use crate::diff_solver::lat;
+
+    use super::*;
+
+    #[test]
+    fn ascon_rounds() {
+        assert!(Ascon::new(0).is_err());
+        assert!(Ascon::new(13).is_err());
+        let ascon = Ascon::new(6).unwrap();
+        assert_eq!("ASCON-p6", ascon.name());
+        // The last 6 round constants, from 0x96 to 0x4b
+        let constants: Vec<usize> = ascon.round_keys(&Vob::new()).iter()
+            .map(|key| (0..64).fold(0, |c, bit| c | ((key[Ascon::state_bit(2, bit)] as usize) << bit)))
+            .collect();
+        assert_eq!(vec![0x96, 0x87, 0x78, 0x69, 0x5a, 0x4b], constants);
+    }
+
+    #[test]
+    fn ascon_encrypt() {
+        let to_state = |words: [u64; 5]| -> Vob {
+            (0..320).map(|i| (words[4 - i % 5] >> (i / 5)) & 1 == 1).collect()
+        };
+        // The initial state of ASCON-Hash, from its IV
+        let state = to_state([0x00400c0000000100, 0, 0, 0, 0]);
+        let expected = to_state([0xee9398aadb67f03d, 0x8bb21831c60f1002, 0xb48a92db98d5da62,
+                                 0x43189921b8f8e3e8, 0x348fa5c9d525e140]);
+        assert_eq!(expected, Ascon::new(12).unwrap().encrypt(&state, &Vob::new()));
+    }
+
+    #[test]
+    fn ascon_sbox() {
+        let sbox = Ascon::new(1).unwrap().sbox(0, 0);
+        let ddt = sbox.ddt();
+        assert_eq!(8, (1..32).flat_map(|a| ddt[a].iter()).max().copied().unwrap());
+        let lat = lat(sbox.table(), 5, 5);
+        assert_eq!(8, (1..32).flat_map(|a| lat[a].iter()).max().copied().unwrap());
+    }
+
+    #[test]
+    fn ascon_linear_layer() {
+        let layer = Ascon::new(1).unwrap().linear_layer(0);
+        assert!(algebra::inverse(&layer).is_some());
+        // Bit 0 of x0 reaches the bits 0, 64 - 19 and 64 - 28 of x0
+        let from = Ascon::state_bit(0, 0);
+        let reached: Vec<usize> = layer.iter_rows().enumerate()
+            .filter(|(_, row)| row[from])
+            .map(|(i, _)| i)
+            .collect();
+        let mut expected: Vec<usize> = [0, 45, 36].iter().map(|bit| Ascon::state_bit(0, *bit)).collect();
+        expected.sort_unstable();
+        assert_eq!(expected, reached);
+    }
+
+    #[test]
+    fn ascon_socs() {
+        let ascon = Ascon::new(2).unwrap();
+        let (soc, rounds) = make_cipher_soc(&ascon, TrailKind::Differential, 2).unwrap();
+        assert_eq!(320 * 3, soc.get_nvar());
+        assert_eq!((0..128).map(Id::new).collect::<Vec<_>>(), rounds.concat());
+    }
+}
@@ -0,0 +1,299 @@
+//! The DES block cipher, see FIPS 46-3, over its differential characteristics.
+//!
+//! A round maps the halves (L, R) of 32 bits to (R, L ⊕ P(S(E(R) ⊕ k))), where E expands R to 48
+//! bits, S is the layer of the eight S-boxes of 6 bits to 4 bits and P permutes the 32 bits. The
+//! S-boxes aren't bijective, so an S-box with a non-zero input difference may have a zero output
+//! difference, and counting the active S-boxes from their outputs means nothing. Their Shards are
+//! instead weighted (see `code_gen::sbox::weighted_generic_shard`): the weight of a trail of the SoC
+//! is the sum of the weights of its transitions in units of a precision, i.e. -log2 of the
+//! probability of the characteristic, each transition being rounded to the nearest unit. Over a
+//! single round, the characteristics (L, 0) of probability 1 weigh 0 and are taken for trivial. As
+//! DES isn't an SPN, it is described by its `SBoxHandler` and `LLHandler` rather than by a `Cipher`:
+//! - the initial state is L followed by R, each from the least significant bit;
+//! - the in block of each round is E(R), read by the S-boxes, followed by R and L, which pass
+//!   through the non-linear layer;
+//! - the linear layer maps the outputs of the S-boxes, R and L to the in block of L' = R and
+//!   R' = L ⊕ P(S(E(R))).
+//!
+//! The bits are numbered from the least significant one, bit i being bit 32 - i (or 48 - i) of FIPS
+//! 46-3, so the S-box at position j is S<sub>8 - j</sub>. The round keys don't change the
+//! differences and aren't modelled.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::Error;
+
+use vob::Vob;
+
+use crush::soc::Id;
+use crush::soc::bdd::differential::PPFactory;
+
+use crate::code_gen::{soc_gen, LLHandler, SBoxHandler};
+use crate::code_gen::cipher::{SBox, TrailKind};
+use crate::code_gen::gsf::GenericShard;
+use crate::code_gen::sbox;
+use crate::diff_solver::{SPFactory, SimpleSolver, SolverConfig};
+
+/// The number of bits of a half.
+const HALF_SIZE: usize = 32;
+/// The number of S-boxes of a round.
+const NUM_SBOXES: usize = 8;
+
+/// The S-boxes S<sub>1</sub> to S<sub>8</sub> of FIPS 46-3, by row and column.
+const SBOXES: [[[usize; 16]; 4]; NUM_SBOXES] = [
+    [[14, 4, 13, 1, 2, 15, 11, 8, 3, 10, 6, 12, 5, 9, 0, 7],
+     [0, 15, 7, 4, 14, 2, 13, 1, 10, 6, 12, 11, 9, 5, 3, 8],
+     [4, 1, 14, 8, 13, 6, 2, 11, 15, 12, 9, 7, 3, 10, 5, 0],
+     [15, 12, 8, 2, 4, 9, 1, 7, 5, 11, 3, 14, 10, 0, 6, 13]],
+    [[15, 1, 8, 14, 6, 11, 3, 4, 9, 7, 2, 13, 12, 0, 5, 10],
+     [3, 13, 4, 7, 15, 2, 8, 14, 12, 0, 1, 10, 6, 9, 11, 5],
+     [0, 14, 7, 11, 10, 4, 13, 1, 5, 8, 12, 6, 9, 3, 2, 15],
+     [13, 8, 10, 1, 3, 15, 4, 2, 11, 6, 7, 12, 0, 5, 14, 9]],
+    [[10, 0, 9, 14, 6, 3, 15, 5, 1, 13, 12, 7, 11, 4, 2, 8],
+     [13, 7, 0, 9, 3, 4, 6, 10, 2, 8, 5, 14, 12, 11, 15, 1],
+     [13, 6, 4, 9, 8, 15, 3, 0, 11, 1, 2, 12, 5, 10, 14, 7],
+     [1, 10, 13, 0, 6, 9, 8, 7, 4, 15, 14, 3, 11, 5, 2, 12]],
+    [[7, 13, 14, 3, 0, 6, 9, 10, 1, 2, 8, 5, 11, 12, 4, 15],
+     [13, 8, 11, 5, 6, 15, 0, 3, 4, 7, 2, 12, 1, 10, 14, 9],
+     [10, 6, 9, 0, 12, 11, 7, 13, 15, 1, 3, 14, 5, 2, 8, 4],
+     [3, 15, 0, 6, 10, 1, 13, 8, 9, 4, 5, 11, 12, 7, 2, 14]],
+    [[2, 12, 4, 1, 7, 10, 11, 6, 8, 5, 3, 15, 13, 0, 14, 9],
+     [14, 11, 2, 12, 4, 7, 13, 1, 5, 0, 15, 10, 3, 9, 8, 6],
+     [4, 2, 1, 11, 10, 13, 7, 8, 15, 9, 12, 5, 6, 3, 0, 14],
+     [11, 8, 12, 7, 1, 14, 2, 13, 6, 15, 0, 9, 10, 4, 5, 3]],
+    [[12, 1, 10, 15, 9, 2, 6, 8, 0, 13, 3, 4, 14, 7, 5, 11],
+     [10, 15, 4, 2, 7, 12, 9, 5, 6, 1, 13, 14, 0, 11, 3, 8],
+     [9, 14, 15, 5, 2, 8, 12, 3, 7, 0, 4, 10, 1, 13, 11, 6],
+     [4, 3, 2, 12, 9, 5, 15, 10, 11, 14, 1, 7, 6, 0, 8, 13]],
+    [[4, 11, 2, 14, 15, 0, 8, 13, 3, 12, 9, 7, 5, 10, 6, 1],
+     [13, 0, 11, 7, 4, 9, 1, 10, 14, 3, 5, 12, 2, 15, 8, 6],
+     [1, 4, 11, 13, 12, 3, 7, 14, 10, 15, 6, 8, 0, 5, 9, 2],
+     [6, 11, 13, 8, 1, 4, 10, 7, 9, 5, 0, 15, 14, 2, 3, 12]],
+    [[13, 2, 8, 4, 6, 15, 11, 1, 10, 9, 3, 14, 5, 0, 12, 7],
+     [1, 15, 13, 8, 10, 3, 7, 4, 12, 5, 6, 11, 0, 14, 9, 2],
+     [7, 11, 4, 1, 9, 12, 14, 2, 0, 6, 10, 13, 15, 3, 5, 8],
+     [2, 1, 14, 7, 4, 10, 8, 13, 15, 12, 9, 0, 3, 5, 6, 11]],
+];
+
+/// The expansion E of FIPS 46-3: bit i of E(R) is bit `EXPANSION[i]` of R.
+const EXPANSION: [usize; 48] = [
+    31, 0, 1, 2, 3, 4, 3, 4, 5, 6, 7, 8, 7, 8, 9, 10, 11, 12, 11, 12, 13, 14, 15, 16, 15, 16, 17, 18,
+    19, 20, 19, 20, 21, 22, 23, 24, 23, 24, 25, 26, 27, 28, 27, 28, 29, 30, 31, 0,
+];
+
+/// The permutation P of FIPS 46-3: bit i of P(x) is bit `PERMUTATION[i]` of x.
+const PERMUTATION: [usize; 32] = [
+    7, 28, 21, 10, 26, 2, 19, 13, 23, 29, 5, 0, 18, 8, 24, 30, 22, 1, 14, 27, 6, 9, 17, 31, 15, 4, 20,
+    3, 11, 12, 25, 16,
+];
+
+/// DES over `nr_rounds` rounds, 16 for the full cipher, with the weights of its transitions counted
+/// in units of a precision.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Des {
+    nr_rounds: usize,
+    precision: f64,
+    sboxes: Vec<SBox>,
+}
+
+impl Des {
+    /// DES over `nr_rounds` rounds, the weight of a transition of an S-box being counted in units of
+    /// `precision`, see `sbox::weighted_generic_shard`.
+    ///
+    /// Returns an `Error` as `sbox::check_precision` does.
+    pub fn new(nr_rounds: usize, precision: f64) -> Result<Des, Error> {
+        sbox::check_precision(TrailKind::Differential, precision)?;
+        let sboxes = (0..NUM_SBOXES)
+            .map(|pos| {
+                let rows = &SBOXES[NUM_SBOXES - 1 - pos];
+                // The outer bits of the input give the row, and the inner bits the column
+                let table = (0..64).map(|x| rows[(x >> 4 & 2) | (x & 1)][x >> 1 & 0xf]).collect();
+                SBox::new(table, 6, 4).expect("The S-boxes of DES are well formed")
+            })
+            .collect();
+        Ok(Des { nr_rounds, precision, sboxes })
+    }
+
+    #[inline]
+    pub fn nr_rounds(&self) -> usize {
+        self.nr_rounds
+    }
+
+    #[inline]
+    pub fn precision(&self) -> f64 {
+        self.precision
+    }
+
+    /// The S-box at `pos`, from the least significant bits of E(R).
+    #[inline]
+    pub fn sbox(&self, pos: usize) -> &SBox {
+        &self.sboxes[pos]
+    }
+
+    /// The in block of a round from the halves L and R.
+    fn in_block(left: &[Vob], right: &[Vob]) -> Vec<Vob> {
+        EXPANSION.iter().map(|i| right[*i].clone())
+            .chain(right.iter().cloned())
+            .chain(left.iter().cloned())
+            .collect()
+    }
+}
+
+impl SBoxHandler for Des {
+    fn num_sboxes(&self, _round: usize) -> usize {
+        NUM_SBOXES
+    }
+
+    fn sbox_size_in(&self, _round: usize, _pos: usize) -> usize {
+        6
+    }
+
+    fn sbox_size_out(&self, _round: usize, _pos: usize) -> usize {
+        4
+    }
+
+    fn bt_generic_shard(&self, _round: usize, pos: usize) -> GenericShard {
+        sbox::weighted_generic_shard(self.sbox(pos), TrailKind::Differential, self.precision)
+            .expect("The precision was checked by new")
+    }
+
+    fn sbox_weight_size(&self, _round: usize, pos: usize) -> usize {
+        sbox::weight_size(self.sbox(pos), TrailKind::Differential, self.precision)
+            .expect("The precision was checked by new")
+    }
+}
+
+impl LLHandler for Des {
+    fn block_size(&self, round: usize) -> usize {
+        if round == 0 { 2 * HALF_SIZE } else { EXPANSION.len() + 2 * HALF_SIZE }
+    }
+
+    /// From the outputs of the S-boxes, R and L, to the in block of L' = R and
+    /// R' = L ⊕ P(S(E(R))).
+    fn apply_linear_layer(&self, _round: usize, state: Vec<Vob>) -> Vec<Vob> {
+        let (outputs, rest) = state.split_at(HALF_SIZE);
+        let (right, left) = rest.split_at(HALF_SIZE);
+        let next_right: Vec<Vob> = PERMUTATION.iter().zip(left.iter())
+            .map(|(i, l)| {
+                let mut bit = outputs[*i].clone();
+                bit.xor(l);
+                bit
+            })
+            .collect();
+        Des::in_block(right, &next_right)
+    }
+
+    fn apply_initial_layer(&self, state: Vec<Vob>) -> Vec<Vob> {
+        let (left, right) = state.split_at(HALF_SIZE);
+        Des::in_block(left, right)
+    }
+}
+
+/// A `SimpleSolver` of the differential characteristics of `des`, the cohort of each Shard being
+/// the out bits of its S-box, such that the weight of a trail is the sum of the weights of its
+/// transitions in units of the precision of `des` (see the module documentation).
+pub fn make_des_solver<F>(des: &Des, progress: F, config: SolverConfig) -> SimpleSolver<F>
+    where
+        F: SPFactory + PPFactory + Clone + Debug,
+{
+    let (soc, rounds) = soc_gen::make_soc(des, des, des.nr_rounds());
+    let cohorts: HashMap<Id, Vec<Vob>> = rounds.iter()
+        .flat_map(|ids| ids.iter())
+        .map(|id| {
+            let shard = soc.get_bdd(*id).unwrap().borrow();
+            (*id, shard.get_lhs()[6..10].to_vec())
+        })
+        .collect();
+    SimpleSolver::new(soc, rounds, Id::new(0), cohorts, 2 * HALF_SIZE, progress, config)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::code_gen::fixture::Silent;
+    use crate::diff_solver::SolverResult;
+    use crate::diff_solver::post_processing_v5::extract_best_k_trails;
+
+    use super::*;
+
+    #[test]
+    fn des_sboxes() {
+        let des = Des::new(1, 1.0).unwrap();
+        // The first entries of S8 and S1, the input 1 being row 1
+        assert_eq!(&[13, 1, 2, 15], &des.sbox(0).table()[..4]);
+        assert_eq!(&[14, 0, 4, 15], &des.sbox(7).table()[..4]);
+        for pos in 0..NUM_SBOXES {
+            let ddt = des.sbox(pos).ddt();
+            // Each S-box has 4 outputs for each row and the differential uniformity of DES is 16
+            assert!((0..64).all(|x| des.sbox(pos).table().iter().filter(|y| **y == des.sbox(pos).table()[x]).count() == 4));
+            assert_eq!(16, (1..64).flat_map(|a| ddt[a].iter()).max().copied().unwrap());
+        }
+    }
+
+    /// The search of the differential characteristics of DES over `nr_rounds` rounds starting with
+    /// `delta_in` (L and R), at precision 1 and with the soft limit `soft_limit`.
+    fn search(nr_rounds: usize, soft_limit: usize, delta_in: (u32, u32)) -> SolverResult<Silent> {
+        let des = Des::new(nr_rounds, 1.0).unwrap();
+        let mut solver = make_des_solver(&des, Silent, SolverConfig::new().with_soft_limit(soft_limit));
+        let half = |h: u32| (0..HALF_SIZE).map(move |i| (h >> i) & 1 == 1);
+        solver.fix_input(&half(delta_in.0).chain(half(delta_in.1)).collect());
+        solver.run();
+        solver.finalize()
+    }
+
+    /// Minus the log2 of the probability of the best characteristic found by `result` over
+    /// `nr_rounds` rounds, from the DDTs of the S-boxes rather than from the rounded weights.
+    fn best_characteristic_weight(nr_rounds: usize, result: &SolverResult<Silent>) -> f64 {
+        let des = Des::new(nr_rounds, 1.0).unwrap();
+        let trail = extract_best_k_trails(result.run(), 1).unwrap().remove(0);
+        let bits = |from: usize, len: usize| (0..len).fold(0, |x, i| x | (trail.values[from + i] as usize) << i);
+        let (mut left, mut right) = (bits(0, HALF_SIZE), bits(HALF_SIZE, HALF_SIZE));
+        // The in bits of the first round follow the initial state, and the out bits of each S-box
+        // are followed by its weight variables
+        let mut pos = 2 * HALF_SIZE;
+        let mut weight = 0.0;
+        for _ in 0..nr_rounds {
+            let expanded = EXPANSION.iter().enumerate().fold(0, |x, (i, j)| x | (right >> j & 1) << i);
+            let mut outputs = 0;
+            for sbox_pos in 0..NUM_SBOXES {
+                let output = bits(pos, 4);
+                pos += 4 + des.sbox_weight_size(0, sbox_pos);
+                outputs |= output << (4 * sbox_pos);
+                let entry = des.sbox(sbox_pos).ddt()[expanded >> (6 * sbox_pos) & 0x3f][output];
+                weight -= (entry as f64 / 64.0).log2();
+            }
+            let f = PERMUTATION.iter().enumerate().fold(0, |x, (i, j)| x | (outputs >> j & 1) << i);
+            (left, right) = (right, left ^ f);
+        }
+        weight
+    }
+
+    #[test]
+    fn des_optimal_characteristics() {
+        // The optimal characteristics of DES over 1 to 3 rounds have the probabilities 1, 2^-2 and
+        // 2^-4 (Matsui, "On Correlation Between the Order of S-boxes and the Strength of DES",
+        // 1994). Over 1 round, (L, 0) weighs 0 and is trivial, so there is no other trail
+        assert_eq!("infeasible", search(1, 1 << 10, (0x400, 0)).name());
+        // Over 2 rounds, (0x400, 0) goes to (0, 0x400), then S-box 2 reads 8 of probability 16/64
+        let result = search(2, 1 << 10, (0x400, 0));
+        assert!(matches!(result, SolverResult::ProvedOptimal { weight: 2, .. }));
+        assert_eq!(2.0, best_characteristic_weight(2, &result));
+        // Over 3 rounds, the same transition, then the one of 0, then the same again
+        let result = search(3, 1 << 14, (0x200008, 0x400));
+        assert!(matches!(result, SolverResult::ProvedOptimal { weight: 4, .. }));
+        assert_eq!(4.0, best_characteristic_weight(3, &result));
+    }
+
+    #[test]
+    fn des_optimal_characteristic_4_rounds() {
+        // The optimal characteristic over 4 rounds has probability 2^-9.61 (Matsui, 1994), its
+        // transitions weighing 2.42, 0, 3 and 2 + 2.19. Rounded to the precision, they weigh 9
+        let result = search(4, 1 << 10, (0x2000401, 0x20));
+        let upper = match &result {
+            SolverResult::ProvedOptimal { weight, .. } => Some(*weight),
+            SolverResult::FeasibleFound { bounds, .. } => bounds.upper,
+            _ => None,
+        };
+        assert_eq!(Some(9), upper);
+        assert_eq!(9.61, (best_characteristic_weight(4, &result) * 100.0).round() / 100.0);
+    }
+}
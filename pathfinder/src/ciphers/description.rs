@@ -0,0 +1,298 @@
+//! SPN ciphers described in TOML and loaded at runtime, such that a new cipher can be tried without
+//! writing (or compiling) any Rust.
+//!
+//! A description gives the name, block size and number of rounds of the cipher, its S-box and its
+//! linear layer, the same for every round. The linear layer is either a bit permutation, bit i of
+//! the state moving to bit `permutation[i]`, or a matrix whose row i gives the bits XORed into bit
+//! i, as a string with a character `0` or `1` per bit. The S-box layer is complete unless
+//! `num_sboxes` is given. See `Cipher` for how the bits of the state are numbered. E.g. the toy
+//! cipher of two PRESENT S-boxes, with a permutation moving bit i to 2i mod 7:
+//!
+//! ```toml
+//! name = "toy"
+//! block_size = 8
+//! rounds = 4
+//!
+//! [sbox]
+//! table = [0xc, 0x5, 0x6, 0xb, 0x9, 0x0, 0xa, 0xd, 0x3, 0xe, 0xf, 0x8, 0x4, 0x7, 0x1, 0x2]
+//!
+//! [linear_layer]
+//! permutation = [0, 2, 4, 6, 1, 3, 5, 7]
+//! ```
+
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use toml::Value;
+use toml::value::Table;
+use vob::Vob;
+
+use crush::algebra::Matrix;
+
+use crate::code_gen::cipher::{Cipher, SBox};
+
+/// A cipher loaded from a description, see the module documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CipherDescription {
+    name: String,
+    block_size: usize,
+    nr_rounds: usize,
+    num_sboxes: usize,
+    sbox: SBox,
+    linear_layer: Matrix,
+}
+
+impl CipherDescription {
+    /// Load the description in the file at `path`.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// Load the description `toml`. Returns an `Error` of kind `InvalidData` if it isn't a valid
+    /// description, e.g. because of a missing or unknown key.
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        let root = match toml.parse::<Value>() {
+            Ok(Value::Table(root)) => root,
+            Ok(_) => return Err(invalid("Not a TOML document")),
+            Err(e) => return Err(invalid(format!("Not valid TOML: {}", e))),
+        };
+        check_keys(&root, "", &["name", "block_size", "rounds", "num_sboxes", "sbox", "linear_layer"])?;
+        let name = match root.get("name") {
+            Some(Value::String(name)) => name.clone(),
+            Some(_) => return Err(invalid("The name must be a string")),
+            None => return Err(invalid("Missing key 'name'")),
+        };
+        let block_size = get_usize(&root, "block_size")?;
+        let nr_rounds = get_usize(&root, "rounds")?;
+        if block_size == 0 {
+            return Err(invalid("The block size must be positive"));
+        }
+
+        let sbox_table = get_table(&root, "sbox")?;
+        check_keys(sbox_table, "sbox.", &["table", "size_out"])?;
+        let table = get_usizes(sbox_table, "table")?;
+        if table.len() < 2 || !table.len().is_power_of_two() {
+            return Err(invalid("The S-box table must have a power of two entries"));
+        }
+        let size_in = table.len().trailing_zeros() as usize;
+        let size_out = match sbox_table.get("size_out") {
+            Some(_) => get_usize(sbox_table, "size_out")?,
+            None => size_in,
+        };
+        if size_out != size_in {
+            return Err(invalid("The S-box must map as many bits as it reads"));
+        }
+        let sbox = SBox::new(table, size_in, size_out).map_err(|e| invalid(e.to_string()))?;
+        let num_sboxes = match root.get("num_sboxes") {
+            Some(_) => get_usize(&root, "num_sboxes")?,
+            None => block_size / size_in,
+        };
+        if num_sboxes * size_in > block_size {
+            return Err(invalid(format!("{} S-boxes of {} bits don't fit in {} bits",
+                                       num_sboxes, size_in, block_size)));
+        }
+
+        let layer_table = get_table(&root, "linear_layer")?;
+        check_keys(layer_table, "linear_layer.", &["permutation", "matrix"])?;
+        let linear_layer = match (layer_table.get("permutation"), layer_table.get("matrix")) {
+            (Some(_), None) => permutation_matrix(&get_usizes(layer_table, "permutation")?, block_size)?,
+            (None, Some(rows)) => bit_matrix(rows, block_size)?,
+            _ => return Err(invalid("The linear layer must be either a 'permutation' or a 'matrix'")),
+        };
+
+        Ok(CipherDescription {
+            name,
+            block_size,
+            nr_rounds,
+            num_sboxes,
+            sbox,
+            linear_layer,
+        })
+    }
+}
+
+impl Cipher for CipherDescription {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn nr_rounds(&self) -> usize {
+        self.nr_rounds
+    }
+
+    fn num_sboxes(&self, _round: usize) -> usize {
+        self.num_sboxes
+    }
+
+    fn sbox(&self, _round: usize, _pos: usize) -> SBox {
+        self.sbox.clone()
+    }
+
+    fn linear_layer(&self, _round: usize) -> Matrix {
+        self.linear_layer.clone()
+    }
+}
+
+fn invalid<E: Into<String>>(msg: E) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.into())
+}
+
+/// Returns an `Error` if `table` has a key outside of `known`, `prefix` being the path to `table`.
+fn check_keys(table: &Table, prefix: &str, known: &[&str]) -> Result<(), Error> {
+    match table.keys().find(|key| !known.contains(&key.as_str())) {
+        Some(key) => Err(invalid(format!("Unknown key '{}{}'", prefix, key))),
+        None => Ok(()),
+    }
+}
+
+fn get_table<'a>(table: &'a Table, key: &str) -> Result<&'a Table, Error> {
+    match table.get(key) {
+        Some(Value::Table(inner)) => Ok(inner),
+        Some(_) => Err(invalid(format!("'{}' must be a table", key))),
+        None => Err(invalid(format!("Missing table '{}'", key))),
+    }
+}
+
+fn to_usize(value: &Value, key: &str) -> Result<usize, Error> {
+    match value {
+        Value::Integer(i) if *i >= 0 => Ok(*i as usize),
+        _ => Err(invalid(format!("'{}' must be made of non-negative integers", key))),
+    }
+}
+
+fn get_usize(table: &Table, key: &str) -> Result<usize, Error> {
+    match table.get(key) {
+        Some(value) => to_usize(value, key),
+        None => Err(invalid(format!("Missing key '{}'", key))),
+    }
+}
+
+fn get_usizes(table: &Table, key: &str) -> Result<Vec<usize>, Error> {
+    match table.get(key) {
+        Some(Value::Array(values)) => values.iter().map(|value| to_usize(value, key)).collect(),
+        Some(_) => Err(invalid(format!("'{}' must be an array", key))),
+        None => Err(invalid(format!("Missing key '{}'", key))),
+    }
+}
+
+/// The matrix of the bit permutation moving bit i to bit `permutation[i]`.
+fn permutation_matrix(permutation: &[usize], block_size: usize) -> Result<Matrix, Error> {
+    if permutation.len() != block_size {
+        return Err(invalid(format!("The permutation has {} entries rather than {}",
+                                   permutation.len(), block_size)));
+    }
+    let mut rows = vec![Vob::from_elem(block_size, false); block_size];
+    for (from, to) in permutation.iter().enumerate() {
+        if *to >= block_size || rows[*to].iter_set_bits(..).next().is_some() {
+            return Err(invalid(format!("The permutation isn't a permutation of the {} bits", block_size)));
+        }
+        rows[*to].set(from, true);
+    }
+    Ok(Matrix::from_rows(rows))
+}
+
+/// The matrix given by `rows`, an array of strings of `0` and `1`.
+fn bit_matrix(rows: &Value, block_size: usize) -> Result<Matrix, Error> {
+    let rows = match rows {
+        Value::Array(rows) if rows.len() == block_size => rows,
+        _ => return Err(invalid(format!("The matrix must be an array of {} rows", block_size))),
+    };
+    let rows = rows.iter()
+        .map(|row| match row {
+            Value::String(row) if row.len() == block_size => row.chars()
+                .map(|c| match c {
+                    '0' => Ok(false),
+                    '1' => Ok(true),
+                    _ => Err(invalid(format!("Unexpected '{}' in a row of the matrix", c))),
+                })
+                .collect::<Result<Vob, Error>>(),
+            _ => Err(invalid(format!("The rows of the matrix must be strings of {} bits", block_size))),
+        })
+        .collect::<Result<Vec<Vob>, Error>>()?;
+    Ok(Matrix::from_rows(rows))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ciphers::gift::Gift;
+    use crate::code_gen::cipher::{make_cipher_soc, RoundOptions, RoundWindow, TrailKind};
+
+    use super::*;
+
+    const TOY: &str = r#"
+        name = "toy"
+        block_size = 8
+        rounds = 4
+
+        [sbox]
+        table = [0xc, 0x5, 0x6, 0xb, 0x9, 0x0, 0xa, 0xd, 0x3, 0xe, 0xf, 0x8, 0x4, 0x7, 0x1, 0x2]
+
+        [linear_layer]
+        permutation = [0, 2, 4, 6, 1, 3, 5, 7]
+    "#;
+
+    fn to_vob(x: usize, size: usize) -> Vob {
+        (0..size).map(|i| (x >> i) & 1 == 1).collect()
+    }
+
+    #[test]
+    fn load_toy() {
+        let toy = CipherDescription::from_toml(TOY).unwrap();
+        assert_eq!("toy", toy.name());
+        assert_eq!((8, 4, 2), (toy.block_size(), toy.nr_rounds(), toy.num_sboxes(0)));
+        assert_eq!(4, toy.sbox(0, 0).size_in());
+
+        // The same linear layer as a matrix
+        let rows: Vec<String> = (0..8)
+            .map(|to| (0..8).map(|from| if [0, 2, 4, 6, 1, 3, 5, 7][from] == to { '1' } else { '0' }).collect())
+            .collect();
+        let matrix = TOY.replace("permutation = [0, 2, 4, 6, 1, 3, 5, 7]", &format!("matrix = {:?}", rows));
+        assert_eq!(toy, CipherDescription::from_toml(&matrix).unwrap());
+
+        let (soc, _) = make_cipher_soc(&toy, TrailKind::Division, 2).unwrap();
+        assert_eq!(8 + 2 * 8, soc.get_nvar());
+    }
+
+    #[test]
+    fn load_gift() {
+        let gift = Gift::gift64(28);
+        let permutation: Vec<usize> = (0..64).map(|i| gift.permute_bit(i)).collect();
+        let toml = format!("name = 'GIFT-64'\nblock_size = 64\nrounds = 28\n\
+                            [sbox]\ntable = {:?}\n[linear_layer]\npermutation = {:?}\n",
+                           gift.sbox(0, 0).table(), permutation);
+        let loaded = CipherDescription::from_toml(&toml).unwrap();
+        // The description has no round keys
+        let keyless = (0..28).fold(RoundWindow::new(0..28).with_final_key_addition(false), |window, round| {
+            window.with_round_options(round, RoundOptions { key_addition: false, linear_layer: true })
+        });
+        let key = Vob::from_elem(128, false);
+        for x in [0, 1, 0x0123_4567_89ab_cdef, usize::MAX >> 1].iter() {
+            let plaintext = to_vob(*x, 64);
+            assert_eq!(gift.encrypt_rounds(&plaintext, &key, &keyless), loaded.encrypt(&plaintext, &Vob::new()));
+        }
+    }
+
+    #[test]
+    fn invalid_descriptions() {
+        let invalid = |from: &str, to: &str| {
+            let toml = TOY.replace(from, to);
+            let err = CipherDescription::from_toml(&toml).unwrap_err();
+            assert_eq!(ErrorKind::InvalidData, err.kind(), "{}", toml);
+        };
+        invalid("name = \"toy\"", "");
+        invalid("rounds = 4", "rounds = -4");
+        invalid("rounds = 4", "rounds = 4\nround_keys = 2");
+        invalid("[0, 2, 4, 6, 1, 3, 5, 7]", "[0, 2, 4, 6, 1, 3, 5, 5]");
+        invalid("[0, 2, 4, 6, 1, 3, 5, 7]", "[0, 2, 4, 6, 1, 3, 5]");
+        invalid("permutation", "matrix");
+        invalid("0xc, 0x5,", "0x1c, 0x5,");
+        invalid("0xc, 0x5,", "");
+        invalid("block_size = 8", "block_size = 8\nnum_sboxes = 3");
+        invalid("[sbox]", "[sbox");
+    }
+}
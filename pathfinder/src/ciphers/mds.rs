@@ -0,0 +1,302 @@
+//! The linear layers of AES-like ciphers, made from a matrix over GF(2^n) rather than written bit
+//! by bit.
+//!
+//! An `Mds` is a square matrix whose entries are elements of GF(2^n), given as integers of n bits
+//! (bit i being the coefficient of x^i), reduced by an irreducible polynomial of degree n, e.g.
+//! `0x11b` for the field of AES. `column_matrix` gives the binary matrix of its product with a
+//! column of cells of n bits, and `mix_columns` the one of its product with each column of a state
+//! of cells, which is the linear layer `Cipher::linear_layer` expects. The Shards of the SoCs are
+//! then made from it as for any other linear layer.
+//!
+//! As in `skinny`, cell i of a state of `rows` rows and `columns` columns is at row i / columns and
+//! column i % columns, and is made of the bits `i * n` to `(i + 1) * n`, from the least significant
+//! one. A layer such as ShiftRows moving cells around is given by `permute_cells`, and the two are
+//! composed with `Matrix::left_mul`, e.g. `mds.mix_columns(4).left_mul(&permute_cells(&SHIFT_ROWS, 8))`
+//! for ShiftRows followed by MixColumns.
+//!
+//! Despite its name, an `Mds` need not be MDS, see `is_mds`.
+
+use std::io::{Error, ErrorKind};
+
+use vob::Vob;
+
+use crush::algebra::Matrix;
+
+/// Returns the product of `a` and `b` in GF(2^`cell_size`) reduced by `modulus`.
+pub fn gf_mul(a: usize, b: usize, cell_size: usize, modulus: usize) -> usize {
+    let mut product = 0;
+    let mut a = a;
+    for i in 0..cell_size {
+        if (b >> i) & 1 == 1 {
+            product ^= a;
+        }
+        a <<= 1;
+        if (a >> cell_size) & 1 == 1 {
+            a ^= modulus;
+        }
+    }
+    product
+}
+
+/// A square matrix over GF(2^n), see the module documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mds {
+    entries: Vec<Vec<usize>>,
+    cell_size: usize,
+    modulus: usize,
+}
+
+impl Mds {
+    /// The matrix of `entries`, row by row, over GF(2^`cell_size`) reduced by `modulus`.
+    ///
+    /// Returns an `Error` of kind `ErrorKind::InvalidInput` if `entries` isn't a non-empty square
+    /// matrix of elements of the field, or if `modulus` isn't of degree `cell_size`.
+    pub fn new(entries: Vec<Vec<usize>>, cell_size: usize, modulus: usize) -> Result<Mds, Error> {
+        if cell_size == 0 || cell_size >= usize::BITS as usize || modulus >> cell_size != 1 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Expected a modulus of degree {}, found {:#x}", cell_size, modulus)));
+        }
+        if entries.is_empty() || entries.iter().any(|row| row.len() != entries.len()) {
+            return Err(Error::new(ErrorKind::InvalidInput, "Expected a non-empty square matrix"));
+        }
+        if let Some(entry) = entries.iter().flatten().find(|entry| **entry >> cell_size != 0) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The entry {:#x} isn't an element of GF(2^{})", entry, cell_size)));
+        }
+        Ok(Mds { entries, cell_size, modulus })
+    }
+
+    /// The MixColumns of AES, over GF(2^8) reduced by x^8 + x^4 + x^3 + x + 1.
+    pub fn aes() -> Mds {
+        let entries = vec![vec![2, 3, 1, 1], vec![1, 2, 3, 1], vec![1, 1, 2, 3], vec![3, 1, 1, 2]];
+        Mds::new(entries, 8, 0x11b).unwrap()
+    }
+
+    /// The number of rows (and columns) of the matrix.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The number of bits of a cell, n.
+    #[inline]
+    pub fn cell_size(&self) -> usize {
+        self.cell_size
+    }
+
+    #[inline]
+    pub fn entries(&self) -> &[Vec<usize>] {
+        &self.entries
+    }
+
+    /// The product of the matrix with the column of cells `column`.
+    pub fn apply(&self, column: &[usize]) -> Vec<usize> {
+        assert_eq!(self.size(), column.len());
+        self.entries.iter()
+            .map(|row| row.iter().zip(column)
+                .fold(0, |y, (entry, x)| y ^ gf_mul(*entry, *x, self.cell_size, self.modulus)))
+            .collect()
+    }
+
+    /// The binary matrix of the product with a column of `size` cells, cell i being made of the
+    /// bits `i * n` to `(i + 1) * n`.
+    pub fn column_matrix(&self) -> Matrix {
+        let n = self.cell_size;
+        let mut matrix = Matrix::new(self.size() * n, self.size() * n);
+        for (i, row) in self.entries.iter().enumerate() {
+            for (j, entry) in row.iter().enumerate() {
+                // Bit t of cell j goes to the bits of entry * x^t in cell i
+                for t in 0..n {
+                    let image = gf_mul(*entry, 1 << t, n, self.modulus);
+                    for s in (0..n).filter(|s| (image >> s) & 1 == 1) {
+                        matrix.set(i * n + s, j * n + t, true);
+                    }
+                }
+            }
+        }
+        matrix
+    }
+
+    /// The binary matrix of the product with each of the `nr_columns` columns of a state of `size`
+    /// rows, see the module documentation for the numbering of the cells.
+    pub fn mix_columns(&self, nr_columns: usize) -> Matrix {
+        let n = self.cell_size;
+        let column_matrix = self.column_matrix();
+        let block_size = self.size() * nr_columns * n;
+        let mut matrix = Matrix::new(block_size, block_size);
+        // Bit s of row i of the column is bit s of cell i * nr_columns + column of the state
+        let bit = |column: usize, k: usize| ((k / n) * nr_columns + column) * n + k % n;
+        for column in 0..nr_columns {
+            for (k, row) in column_matrix.iter_rows().enumerate() {
+                for l in row.iter_set_bits(..) {
+                    matrix.set(bit(column, k), bit(column, l), true);
+                }
+            }
+        }
+        matrix
+    }
+
+    /// Whether the matrix is MDS, i.e. every square submatrix is invertible, such that a non-zero
+    /// column and its product have at least `size + 1` non-zero cells between them. This goes
+    /// through all the square submatrices and is meant for small matrices.
+    pub fn is_mds(&self) -> bool {
+        let size = self.size();
+        (1..1_usize << size).all(|rows| {
+            let k = rows.count_ones();
+            (1..1_usize << size)
+                .filter(|columns| columns.count_ones() == k)
+                .all(|columns| {
+                    let submatrix: Vec<Vec<usize>> = (0..size).filter(|i| (rows >> i) & 1 == 1)
+                        .map(|i| (0..size).filter(|j| (columns >> j) & 1 == 1).map(|j| self.entries[i][j]).collect())
+                        .collect();
+                    self.is_invertible(submatrix)
+                })
+        })
+    }
+
+    /// Whether the square `matrix` over the field is invertible, by Gaussian elimination.
+    fn is_invertible(&self, mut matrix: Vec<Vec<usize>>) -> bool {
+        let size = matrix.len();
+        for column in 0..size {
+            let pivot = match (column..size).find(|row| matrix[*row][column] != 0) {
+                Some(pivot) => pivot,
+                None => return false,
+            };
+            matrix.swap(column, pivot);
+            let inverse = self.inverse(matrix[column][column]);
+            let pivot_row = matrix[column].clone();
+            for row in matrix.iter_mut().skip(column + 1) {
+                let factor = gf_mul(row[column], inverse, self.cell_size, self.modulus);
+                for (entry, pivot) in row.iter_mut().zip(pivot_row.iter()).skip(column) {
+                    *entry ^= gf_mul(factor, *pivot, self.cell_size, self.modulus);
+                }
+            }
+        }
+        true
+    }
+
+    /// The inverse of the non-zero `a` in the field, by exhaustive search.
+    fn inverse(&self, a: usize) -> usize {
+        (1..1 << self.cell_size)
+            .find(|b| gf_mul(a, *b, self.cell_size, self.modulus) == 1)
+            .expect("The modulus is irreducible")
+    }
+}
+
+/// The binary matrix moving cell `permutation[i]` of a state of cells of `cell_size` bits to cell
+/// i, e.g. ShiftRows.
+pub fn permute_cells(permutation: &[usize], cell_size: usize) -> Matrix {
+    let block_size = permutation.len() * cell_size;
+    let rows = permutation.iter()
+        .flat_map(|from| (0..cell_size).map(move |bit| from * cell_size + bit))
+        .map(|from| {
+            let mut row = Vob::from_elem(block_size, false);
+            row.set(from, true);
+            row
+        })
+        .collect();
+    Matrix::from_rows(rows)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::code_gen::cipher::{make_cipher_soc, Cipher, Granularity, SBox, TrailKind};
+
+    use super::*;
+
+    fn to_vob(cells: &[usize], cell_size: usize) -> Vob {
+        cells.iter().flat_map(|x| (0..cell_size).map(move |i| (x >> i) & 1 == 1)).collect()
+    }
+
+    fn from_vob(state: &Vob, cell_size: usize) -> Vec<usize> {
+        (0..state.len() / cell_size)
+            .map(|cell| (0..cell_size).fold(0, |x, i| x | ((state[cell * cell_size + i] as usize) << i)))
+            .collect()
+    }
+
+    #[test]
+    fn aes_mix_columns() {
+        let aes = Mds::aes();
+        assert_eq!(0xc1, gf_mul(0x57, 0x83, 8, 0x11b));
+        // A test vector of MixColumns
+        assert_eq!(vec![0x8e, 0x4d, 0xa1, 0xbc], aes.apply(&[0xdb, 0x13, 0x53, 0x45]));
+        let column = aes.column_matrix();
+        assert_eq!(vec![0x8e, 0x4d, 0xa1, 0xbc],
+                   from_vob(&column.mul_vob(&to_vob(&[0xdb, 0x13, 0x53, 0x45], 8)), 8));
+        assert!(aes.is_mds());
+
+        // Each column of the state is mixed on its own
+        let state: Vec<usize> = (0..16).map(|i| (i * 37 + 11) % 256).collect();
+        let mixed = from_vob(&aes.mix_columns(4).mul_vob(&to_vob(&state, 8)), 8);
+        for c in 0..4 {
+            let column: Vec<usize> = (0..4).map(|r| state[4 * r + c]).collect();
+            let expected = aes.apply(&column);
+            assert_eq!(expected, (0..4).map(|r| mixed[4 * r + c]).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn mds_checks() {
+        assert!(Mds::new(vec![vec![1, 2], vec![3]], 4, 0x13).is_err());
+        assert!(Mds::new(vec![vec![1, 0x10], vec![1, 1]], 4, 0x13).is_err());
+        assert!(Mds::new(vec![vec![1]], 4, 0x11b).is_err());
+        // The MixColumns of SKINNY is binary and not MDS
+        let skinny = vec![vec![1, 0, 1, 1], vec![1, 0, 0, 0], vec![0, 1, 1, 0], vec![1, 0, 1, 0]];
+        assert!(!Mds::new(skinny, 4, 0x13).unwrap().is_mds());
+        assert!(!Mds::new(vec![vec![1, 1], vec![1, 1]], 4, 0x13).unwrap().is_mds());
+
+        // Moving the cells of a row of a state of 2 by 2 cells
+        let shift = permute_cells(&[0, 1, 3, 2], 4);
+        assert_eq!(vec![1, 2, 4, 3], from_vob(&shift.mul_vob(&to_vob(&[1, 2, 3, 4], 4)), 4));
+    }
+
+    /// An AES-like cipher of 2 by 2 cells of 4 bits, whose MixColumns is MDS of branch number 3.
+    struct Mini;
+
+    impl Cipher for Mini {
+        fn name(&self) -> String {
+            "mini".to_string()
+        }
+
+        fn block_size(&self) -> usize {
+            16
+        }
+
+        fn nr_rounds(&self) -> usize {
+            4
+        }
+
+        fn sbox(&self, _round: usize, _pos: usize) -> SBox {
+            let present = vec![0xc, 0x5, 0x6, 0xb, 0x9, 0x0, 0xa, 0xd, 0x3, 0xe, 0xf, 0x8, 0x4, 0x7, 0x1, 0x2];
+            SBox::new(present, 4, 4).unwrap()
+        }
+
+        fn linear_layer(&self, _round: usize) -> Matrix {
+            let mds = Mds::new(vec![vec![1, 2], vec![2, 1]], 4, 0x13).unwrap();
+            mds.mix_columns(2).left_mul(&permute_cells(&[0, 1, 3, 2], 4))
+        }
+
+        fn granularity(&self) -> Granularity {
+            Granularity::Word
+        }
+    }
+
+    #[test]
+    fn mds_cipher() {
+        let mds = Mds::new(vec![vec![1, 2], vec![2, 1]], 4, 0x13).unwrap();
+        assert!(mds.is_mds());
+        // The branch number of an MDS matrix of 2 rows is 3
+        let active = |cells: &[usize]| cells.iter().filter(|x| **x != 0).count();
+        for x in 1..256 {
+            let column = [x & 0xf, x >> 4];
+            assert!(active(&column) + active(&mds.apply(&column)) >= 3);
+        }
+
+        // A variable per cell
+        let (soc, rounds) = make_cipher_soc(&Mini, TrailKind::Differential, 2).unwrap();
+        assert_eq!(4 + 2 * 4, soc.get_nvar());
+        assert_eq!(4, rounds[0].len());
+    }
+}
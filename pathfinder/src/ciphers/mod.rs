@@ -0,0 +1,15 @@
+//! Ciphers ready for the search of their trails: the SPNs are described by
+//! `code_gen::cipher::Cipher`, or loaded at runtime from a description (see `description`), the
+//! ARX ciphers and the Feistel cipher DES by their `SBoxHandler` and `LLHandler`. The linear
+//! layers of AES-like ciphers are made from their MDS matrix by `mds`. The S-box of PRINCE, with
+//! its inverse, is in `prince`.
+
+pub mod ascon;
+pub mod des;
+pub mod description;
+pub mod gift;
+pub mod mds;
+pub mod prince;
+pub mod simon;
+pub mod skinny;
+pub mod speck;
@@ -0,0 +1,112 @@
+//! The S-box of the PRINCE block cipher, see "PRINCE – A Low-latency Block Cipher for Pervasive
+//! Computing Applications" (Borghoff et al., ASIACRYPT 2012).
+//!
+//! PRINCE applies its S-box in the first half of its rounds and the inverse S-box in the second
+//! half, `SbMock` describing the S-box layers of such a half and half SoC to the post-processing.
+
+use std::convert::TryFrom;
+
+use crate::code_gen::cipher::SBox;
+use crate::code_gen::gsf::GenericShard;
+use crate::code_gen::SBoxHandler;
+use crate::diff_solver::post_processing_v5::BaseTable;
+
+/// The S-box of PRINCE.
+const S: [usize; 16] = [0xb, 0xf, 0x3, 0x2, 0xa, 0xc, 0x9, 0x1, 0x6, 0x7, 0x8, 0x0, 0xe, 0x5, 0xd, 0x4];
+
+/// The number of S-boxes of a layer, on the 64 bits of the state.
+const NUM_SBOXES: usize = 16;
+
+/// Return the S-box of PRINCE.
+pub fn sbox() -> SBox {
+    SBox::new(S.to_vec(), 4, 4).unwrap()
+}
+
+/// Return the inverse of the S-box of PRINCE.
+pub fn inverse_sbox() -> SBox {
+    let mut inverse = vec![0; S.len()];
+    for (x, y) in S.iter().enumerate() {
+        inverse[*y] = x;
+    }
+    SBox::new(inverse, 4, 4).unwrap()
+}
+
+/// Return the DDT of the S-box of PRINCE.
+pub fn ddt_raw() -> Vec<Vec<usize>> {
+    sbox().ddt()
+}
+
+/// Return the DDT of the inverse S-box of PRINCE.
+pub fn ddt_inverse_raw() -> Vec<Vec<usize>> {
+    inverse_sbox().ddt()
+}
+
+/// The S-box layers of PRINCE: the S-box in the first `forward_rounds` rounds and the inverse
+/// S-box in the following ones.
+#[derive(Debug, Clone)]
+pub struct SbMock {
+    forward_rounds: usize,
+    forward: BaseTable,
+    inverse: BaseTable,
+}
+
+impl SbMock {
+    /// The S-box layers of the 6 rounds of PRINCE-core around its middle, 3 with the S-box and 3
+    /// with its inverse.
+    pub fn new() -> SbMock {
+        SbMock::with_forward_rounds(3)
+    }
+
+    /// The S-box layers of PRINCE, the S-box being applied in the first `forward_rounds` rounds.
+    pub fn with_forward_rounds(forward_rounds: usize) -> SbMock {
+        SbMock {
+            forward_rounds,
+            forward: BaseTable::try_from(ddt_raw()).unwrap(),
+            inverse: BaseTable::try_from(ddt_inverse_raw()).unwrap(),
+        }
+    }
+}
+
+impl Default for SbMock {
+    fn default() -> Self {
+        SbMock::new()
+    }
+}
+
+impl SBoxHandler for SbMock {
+    fn num_sboxes(&self, _round: usize) -> usize {
+        NUM_SBOXES
+    }
+
+    fn sbox_size_in(&self, _round: usize, _pos: usize) -> usize {
+        4
+    }
+
+    fn sbox_size_out(&self, _round: usize, _pos: usize) -> usize {
+        4
+    }
+
+    fn bt_generic_shard(&self, round: usize, _pos: usize) -> GenericShard {
+        let table = if round < self.forward_rounds { &self.forward } else { &self.inverse };
+        GenericShard::new(table, 4, 4)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prince_sbox() {
+        let (sbox, inverse) = (sbox(), inverse_sbox());
+        assert!((0..16).all(|x| inverse.apply(sbox.apply(x)) == x));
+        // The differential uniformity of the S-box of PRINCE is 4
+        let ddt = ddt_raw();
+        assert_eq!(16, ddt[0][0]);
+        assert_eq!(4, ddt.iter().skip(1).flatten().copied().max().unwrap());
+        // The DDT of the inverse is the transposed DDT
+        let inverse_ddt = ddt_inverse_raw();
+        assert!((0..16).all(|a| (0..16).all(|b| ddt[a][b] == inverse_ddt[b][a])));
+        assert_eq!(16, SbMock::new().num_sboxes(0));
+    }
+}
@@ -0,0 +1,156 @@
+//! The Simon32/64 block cipher, see "The SIMON and SPECK Families of Lightweight Block Ciphers"
+//! (Beaulieu et al., 2013).
+//!
+//! A round maps the words (x, y) of 16 bits to (y ⊕ f(x) ⊕ k, x), where
+//! f(x) = ((x ⋘ 1) & (x ⋘ 8)) ⊕ (x ⋘ 2). The bitwise AND is the only non-linear part, and each of
+//! its bits is a Shard of two in bits and one out bit (see `code_gen::arx::and_shard`). The two
+//! inputs of an AND share their bits with other ANDs, which the Shards of the ANDs don't account
+//! for: a trail of the SoC may have a lower weight than the actual one. As Simon isn't an SPN, it
+//! is described by its `SBoxHandler` and `LLHandler` rather than by a `Cipher`:
+//! - the initial state is x followed by y, each from the least significant bit;
+//! - the in block of each round is bit i of x ⋘ 1 and of x ⋘ 8 for each i, read by the ANDs,
+//!   followed by x and y, which pass through the non-linear layer;
+//! - the linear layer maps the ANDs, x and y to the in block of the next round.
+//!
+//! The round keys don't change the differences, and are only used by `encrypt`.
+
+use vob::Vob;
+
+use crate::code_gen::{LLHandler, SBoxHandler};
+use crate::code_gen::arx::and_shard;
+use crate::code_gen::gsf::GenericShard;
+
+/// The number of bits of a word.
+const WORD_SIZE: usize = 16;
+
+/// The constant sequence z0 of the key schedule.
+const Z0: u64 = 0b11111010001001010110000111001101111101000100101011000011100110;
+
+/// Simon32/64 over `nr_rounds` rounds, 32 for the full cipher.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Simon {
+    nr_rounds: usize,
+}
+
+impl Simon {
+    pub fn new(nr_rounds: usize) -> Simon {
+        Simon { nr_rounds }
+    }
+
+    #[inline]
+    pub fn nr_rounds(&self) -> usize {
+        self.nr_rounds
+    }
+
+    /// The round keys of `key`, given as the words (k3, k2, k1, k0) as in the test vectors of the
+    /// designers.
+    pub fn round_keys(&self, key: [u16; 4]) -> Vec<u16> {
+        let mut k = vec![key[3], key[2], key[1], key[0]];
+        for i in 0..self.nr_rounds.saturating_sub(4) {
+            let mut tmp = k[i + 3].rotate_right(3) ^ k[i + 1];
+            tmp ^= tmp.rotate_right(1);
+            let z = ((Z0 >> (61 - i % 62)) & 1) as u16;
+            k.push(!k[i] ^ tmp ^ z ^ 3);
+        }
+        k.truncate(self.nr_rounds);
+        k
+    }
+
+    /// Encrypt the words (`x`, `y`) under `key`, see `round_keys`.
+    pub fn encrypt(&self, (mut x, mut y): (u16, u16), key: [u16; 4]) -> (u16, u16) {
+        for k in self.round_keys(key) {
+            let f = (x.rotate_left(1) & x.rotate_left(8)) ^ x.rotate_left(2);
+            let next = y ^ f ^ k;
+            y = x;
+            x = next;
+        }
+        (x, y)
+    }
+
+    /// The in block of a round from the words x and y.
+    fn in_block(x: &[Vob], y: &[Vob]) -> Vec<Vob> {
+        let rotated = |i: usize, r: usize| x[(i + WORD_SIZE - r) % WORD_SIZE].clone();
+        (0..WORD_SIZE)
+            .flat_map(|i| vec![rotated(i, 1), rotated(i, 8)])
+            .chain(x.iter().cloned())
+            .chain(y.iter().cloned())
+            .collect()
+    }
+}
+
+impl SBoxHandler for Simon {
+    fn num_sboxes(&self, _round: usize) -> usize {
+        WORD_SIZE
+    }
+
+    fn sbox_size_in(&self, _round: usize, _pos: usize) -> usize {
+        2
+    }
+
+    fn sbox_size_out(&self, _round: usize, _pos: usize) -> usize {
+        1
+    }
+
+    fn bt_generic_shard(&self, _round: usize, _pos: usize) -> GenericShard {
+        and_shard()
+    }
+}
+
+impl LLHandler for Simon {
+    fn block_size(&self, round: usize) -> usize {
+        if round == 0 { 2 * WORD_SIZE } else { 4 * WORD_SIZE }
+    }
+
+    /// From the ANDs, x and y, to the in block of x' = y ⊕ f(x) and y' = x.
+    fn apply_linear_layer(&self, _round: usize, state: Vec<Vob>) -> Vec<Vob> {
+        let (and, rest) = state.split_at(WORD_SIZE);
+        let (x, y) = rest.split_at(WORD_SIZE);
+        let next_x: Vec<Vob> = (0..WORD_SIZE)
+            .map(|i| {
+                let mut bit = y[i].clone();
+                bit.xor(&and[i]);
+                bit.xor(&x[(i + WORD_SIZE - 2) % WORD_SIZE]);
+                bit
+            })
+            .collect();
+        Simon::in_block(&next_x, x)
+    }
+
+    fn apply_initial_layer(&self, state: Vec<Vob>) -> Vec<Vob> {
+        let (x, y) = state.split_at(WORD_SIZE);
+        Simon::in_block(x, y)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crush::soc::Id;
+
+    use crate::code_gen::soc_gen;
+
+    use super::*;
+
+    #[test]
+    fn simon_test_vector() {
+        let simon = Simon::new(32);
+        assert_eq!((0xc69b, 0xe9bb), simon.encrypt((0x6565, 0x6877), [0x1918, 0x1110, 0x0908, 0x0100]));
+    }
+
+    #[test]
+    fn simon_soc() {
+        let simon = Simon::new(2);
+        let (soc, rounds) = soc_gen::make_soc(&simon, &simon, 2);
+        assert_eq!(32 + 2 * 16, soc.get_nvar());
+        assert_eq!((0..32).map(Id::new).collect::<Vec<_>>(), rounds.concat());
+
+        // The AND of bit 0 of the first round reads bits 15 and 8 of x
+        let shard = soc.get_bdd(Id::new(0)).unwrap().borrow();
+        let lhss: Vec<Vec<usize>> = shard.iter_levels().take(3).map(|level| level.iter_set_lhs().collect()).collect();
+        assert_eq!(vec![vec![15], vec![8], vec![32]], lhss);
+        // In the second round, bit 1 of x' ⋘ 1 is bit 0 of x', i.e. bit 0 of y, of the first AND
+        // and bit 14 of x
+        let shard = soc.get_bdd(Id::new(17)).unwrap().borrow();
+        let lhs: Vec<usize> = shard.iter_levels().next().unwrap().iter_set_lhs().collect();
+        assert_eq!(vec![14, 16, 32], lhs);
+    }
+}
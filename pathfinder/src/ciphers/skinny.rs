@@ -0,0 +1,261 @@
+//! The SKINNY-64 and SKINNY-128 block ciphers, see "The SKINNY Family of Block Ciphers and Its
+//! Low-Latency Variant MANTIS" (Beierle et al., CRYPTO 2016).
+//!
+//! The state is a 4x4 array of cells of 4 (SKINNY-64) or 8 (SKINNY-128) bits, cell i being at row
+//! i / 4 and column i % 4 and made of the bits `i * cell_size` to `(i + 1) * cell_size`, from the
+//! least significant one. A round applies SubCells, AddConstants, AddRoundTweakey, ShiftRows and
+//! MixColumns. The constants and the tweakey don't change the differences (in the single key
+//! setting), the masks or the division property, and are not modelled in the SoCs. They make the
+//! round keys of `encrypt`, the key being the tweakey TK1 of SKINNY-64-64 and SKINNY-128-128: as
+//! they are added right after SubCells, the ones of a round go through ShiftRows and MixColumns
+//! into the round key of the next round, the one of the first round being zero.
+
+use vob::Vob;
+
+use crush::algebra::Matrix;
+
+use crate::code_gen::cipher::{Cipher, SBox};
+
+/// The 4 bit S-box of SKINNY-64.
+const S4: [usize; 16] = [0xc, 0x6, 0x9, 0x0, 0x1, 0xa, 0x2, 0xb, 0x3, 0x8, 0x5, 0xd, 0x4, 0xe, 0x7, 0xf];
+
+/// ShiftRows, the cell moved to cell i being the cell `SHIFT_ROWS[i]`.
+const SHIFT_ROWS: [usize; 16] = [0, 1, 2, 3, 7, 4, 5, 6, 10, 11, 8, 9, 13, 14, 15, 12];
+
+/// MixColumns, row r of a column of the output being the XOR of the rows `MIX_COLUMNS[r]` of the
+/// column of the input.
+const MIX_COLUMNS: [&[usize]; 4] = [&[0, 2, 3], &[0], &[1, 2], &[0, 2]];
+
+/// The tweakey permutation, the cell moved to cell i being the cell `TWEAKEY_PERMUTATION[i]`.
+const TWEAKEY_PERMUTATION: [usize; 16] = [9, 15, 8, 13, 10, 14, 12, 11, 0, 1, 2, 3, 4, 5, 6, 7];
+
+/// The SKINNY block cipher with 64 or 128 bits of state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skinny {
+    cell_size: usize,
+    nr_rounds: usize,
+}
+
+impl Skinny {
+    /// SKINNY-64, over `nr_rounds` rounds.
+    pub fn skinny64(nr_rounds: usize) -> Skinny {
+        Skinny { cell_size: 4, nr_rounds }
+    }
+
+    /// SKINNY-128, over `nr_rounds` rounds.
+    pub fn skinny128(nr_rounds: usize) -> Skinny {
+        Skinny { cell_size: 8, nr_rounds }
+    }
+
+    #[inline]
+    pub fn cell_size(&self) -> usize {
+        self.cell_size
+    }
+}
+
+impl Cipher for Skinny {
+    fn name(&self) -> String {
+        format!("SKINNY-{}", 16 * self.cell_size)
+    }
+
+    fn block_size(&self) -> usize {
+        16 * self.cell_size
+    }
+
+    fn nr_rounds(&self) -> usize {
+        self.nr_rounds
+    }
+
+    fn num_sboxes(&self, _round: usize) -> usize {
+        16
+    }
+
+    fn sbox(&self, _round: usize, _pos: usize) -> SBox {
+        let table = match self.cell_size {
+            4 => S4.to_vec(),
+            _ => (0..256).map(s8).collect(),
+        };
+        SBox::new(table, self.cell_size, self.cell_size).unwrap()
+    }
+
+    /// ShiftRows followed by MixColumns.
+    fn linear_layer(&self, _round: usize) -> Matrix {
+        let block_size = self.block_size();
+        let mut rows = Vec::with_capacity(block_size);
+        for cell in 0..16 {
+            let (row, column) = (cell / 4, cell % 4);
+            for bit in 0..self.cell_size {
+                let mut matrix_row = Vob::from_elem(block_size, false);
+                for from_row in MIX_COLUMNS[row] {
+                    let from_cell = SHIFT_ROWS[4 * from_row + column];
+                    matrix_row.set(from_cell * self.cell_size + bit, true);
+                }
+                rows.push(matrix_row);
+            }
+        }
+        Matrix::from_rows(rows)
+    }
+
+    fn key_size(&self) -> usize {
+        self.block_size()
+    }
+
+    /// The constants and the round tweakeys, the first two rows of TK1 in each round, moved
+    /// through the linear layer, see the module documentation.
+    fn round_keys(&self, key: &Vob) -> Vec<Vob> {
+        let (block_size, cell_size) = (self.block_size(), self.cell_size);
+        let linear_layer = self.linear_layer(0);
+        let mut tweakey = key.clone();
+        let mut round_keys = vec![Vob::from_elem(block_size, false)];
+        let mut rc = 0;
+        for _ in 0..self.nr_rounds {
+            rc = ((rc << 1) & 0x3f) | (((rc >> 5) ^ (rc >> 4) ^ 1) & 1);
+            // The constants of cells 0, 4 and 8
+            let constants = [rc & 0xf, rc >> 4, 0x2];
+            let round_key: Vob = (0..block_size)
+                .map(|i| {
+                    let (cell, bit) = (i / cell_size, i % cell_size);
+                    let constant = cell % 4 == 0 && cell < 12 && (constants[cell / 4] >> bit) & 1 == 1;
+                    constant ^ (cell < 8 && tweakey[i])
+                })
+                .collect();
+            round_keys.push(linear_layer.iter_rows()
+                .map(|row| row.iter_set_bits(..).fold(false, |bit, j| bit ^ round_key[j]))
+                .collect());
+            tweakey = (0..block_size)
+                .map(|i| tweakey[TWEAKEY_PERMUTATION[i / cell_size] * cell_size + i % cell_size])
+                .collect();
+        }
+        round_keys
+    }
+}
+
+/// The 8 bit S-box of SKINNY-128, from its definition as four iterations of a NOR based function,
+/// each followed by a bit permutation.
+fn s8(x: usize) -> usize {
+    let bit = |x: usize, i: usize| (x >> i) & 1;
+    let nor = |x: usize| {
+        let x = x ^ ((1 ^ (bit(x, 7) | bit(x, 6))) << 4);
+        x ^ (1 ^ (bit(x, 3) | bit(x, 2)))
+    };
+    // The bit of x moved to bit 7, 6, ..., 0
+    let permute = |x: usize, from: [usize; 8]| {
+        from.iter().enumerate().fold(0, |y, (k, i)| y | (bit(x, *i) << (7 - k)))
+    };
+    let mut x = x;
+    for _ in 0..3 {
+        x = permute(nor(x), [2, 1, 7, 6, 4, 0, 3, 5]);
+    }
+    permute(nor(x), [7, 6, 5, 4, 3, 1, 2, 0])
+}
+
+#[cfg(test)]
+mod test {
+    use crush::algebra;
+    use crush::soc::Id;
+
+    use crate::code_gen::cipher::{make_cipher_soc, make_weighted_solver, RoundWindow, TrailKind};
+    use crate::code_gen::fixture::Silent;
+    use crate::diff_solver::{SolverConfig, SolverResult, Verbosity};
+
+    use super::*;
+
+    /// The state or key of SKINNY-64 written as `x`, the most significant nibble being cell 0.
+    fn from_hex(x: u64) -> Vob {
+        (0..64).map(|i| (x >> (60 - 4 * (i / 4) + i % 4)) & 1 == 1).collect()
+    }
+
+    fn cells(state: &Vob, cell_size: usize) -> Vec<usize> {
+        (0..16)
+            .map(|cell| (0..cell_size).fold(0, |x, i| x | ((state[cell * cell_size + i] as usize) << i)))
+            .collect()
+    }
+
+    #[test]
+    fn skinny_sboxes() {
+        let s8: Vec<usize> = Skinny::skinny128(1).sbox(0, 0).table().to_vec();
+        assert_eq!(vec![0x65, 0x4c, 0x6a, 0x42, 0x4b, 0x63, 0x43, 0x6b], s8[..8].to_vec());
+        assert_eq!(0xff, s8[0xff]);
+        let mut sorted = s8.clone();
+        sorted.sort_unstable();
+        assert_eq!((0..256).collect::<Vec<_>>(), sorted);
+
+        // The differential uniformity of both S-boxes
+        let ddt = Skinny::skinny64(1).sbox(0, 0).ddt();
+        assert_eq!(4, (1..16).flat_map(|a| ddt[a].iter()).max().copied().unwrap());
+        let ddt = Skinny::skinny128(1).sbox(0, 0).ddt();
+        assert_eq!(64, (1..256).flat_map(|a| ddt[a].iter()).max().copied().unwrap());
+    }
+
+    #[test]
+    fn skinny_linear_layer() {
+        for skinny in [Skinny::skinny64(1), Skinny::skinny128(1)].iter() {
+            let layer = skinny.linear_layer(0);
+            assert!(algebra::inverse(&layer).is_some());
+            // A difference in cell 0 reaches cells 0, 4 and 12, one in cell 5 is shifted to cell 6,
+            // then only reaches cell 10.
+            for (cell, reached) in [(0, vec![0, 4, 12]), (5, vec![10])].iter() {
+                let mut state = Vob::from_elem(skinny.block_size(), false);
+                state.set(cell * skinny.cell_size(), true);
+                let out: Vob = layer.iter_rows()
+                    .map(|row| row.iter_set_bits(..).any(|j| state[j]))
+                    .collect();
+                let active: Vec<usize> = cells(&out, skinny.cell_size()).iter().enumerate()
+                    .filter(|(_, x)| **x != 0)
+                    .map(|(cell, x)| {
+                        assert_eq!(1, *x);
+                        cell
+                    })
+                    .collect();
+                assert_eq!(reached, &active);
+            }
+        }
+    }
+
+    #[test]
+    fn skinny_encrypt() {
+        // The test vector of SKINNY-64-64 (Beierle et al., 2016)
+        let skinny = Skinny::skinny64(32);
+        assert_eq!(from_hex(0xbb39_dfb2_429b_8ac7),
+                   skinny.encrypt(&from_hex(0x0603_4f95_7724_d19d), &from_hex(0xf526_9826_fc68_1238)));
+
+        // Without the constants, S4(0) = 0xc in every cell, which MixColumns cancels in rows 2 and 3
+        let state = Vob::from_elem(64, false);
+        let window = RoundWindow::new(0..1).with_final_key_addition(false);
+        let out = cells(&skinny.encrypt_rounds(&state, &state, &window), 4);
+        assert_eq!(vec![0xc; 4], out[..4].to_vec());
+        assert_eq!(vec![0xc; 4], out[4..8].to_vec());
+        assert_eq!(vec![0; 4], out[8..12].to_vec());
+        assert_eq!(vec![0; 4], out[12..].to_vec());
+    }
+
+    #[test]
+    fn skinny_socs() {
+        let skinny = Skinny::skinny64(2);
+        for kind in [TrailKind::Differential, TrailKind::Linear].iter() {
+            let (soc, rounds) = make_cipher_soc(&skinny, *kind, 2).unwrap();
+            assert_eq!(64 * 3, soc.get_nvar());
+            assert_eq!((0..32).map(Id::new).collect::<Vec<_>>(), rounds.concat());
+        }
+        // MixColumns is not a bit permutation
+        assert!(make_cipher_soc(&skinny, TrailKind::Division, 2).is_err());
+    }
+
+    #[test]
+    fn skinny_optimal_weights() {
+        // The optimal differential trails of SKINNY-64 over 1 to 3 rounds weigh 2, 4 and 10, e.g.
+        // the ones starting with 0x2 in cell 15. The weights of S4 are 2 and 3, so the weights
+        // are exact at precision 1
+        for (nr_rounds, expected) in [(1, 2), (2, 4), (3, 10)].iter() {
+            let config = SolverConfig::new().with_verbosity(Verbosity::Quiet);
+            let mut solver = make_weighted_solver(&Skinny::skinny64(*nr_rounds), TrailKind::Differential,
+                                                  &RoundWindow::new(0..*nr_rounds), 1.0, Silent, config).unwrap();
+            solver.fix_input(&(0..64).map(|i| i == 61).collect());
+            solver.run();
+            match solver.finalize() {
+                SolverResult::ProvedOptimal { weight, .. } => assert_eq!(*expected, weight),
+                _ => panic!("The solving over {} rounds wasn't complete", nr_rounds),
+            }
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Support for ARX primitives, whose non-linear parts are modular additions (Speck) or bitwise ANDs
+//! (Simon) rather than S-boxes.
+//!
+//! The differentials of the addition modulo 2<sup>n</sup> are the ones of Lipmaa and Moriai
+//! ("Efficient Algorithms for Computing Differential Properties of Addition", FSE 2001): the
+//! differential (α, β → γ) is possible iff bit i of α ^ β ^ γ equals bit i - 1 of β whenever the
+//! bits i - 1 of α, β and γ are equal (bit -1 being 0), and its weight is the number of bits below
+//! the most significant one where α, β and γ aren't all equal. Going from the least significant
+//! bit, whether a bit is constrained only depends on the bits just below, so the Shard of the
+//! addition is a carry chain with a handful of nodes per level, as long as the levels of each bit
+//! of α, β and γ are next to each other: `modular_addition_shard`.
+//!
+//! The weight of a trail as counted by the `SimpleSolver` is its number of active groups of out
+//! bits, so a single Shard for the whole addition would weigh 1 as soon as it is active. Instead,
+//! the addition is split per bit with f = α ⊕ β ⊕ γ ⊕ (β ≪ 1), bit 0 of f being always 0: the
+//! condition above is that bit i + 1 of f is 0 whenever the bits i of α, β and γ are equal. Bit i
+//! below the most significant one is then a Shard reading α<sub>i</sub>, β<sub>i</sub>,
+//! β<sub>i - 1</sub> and f<sub>i</sub>, of which γ<sub>i</sub> = α<sub>i</sub> ⊕ β<sub>i</sub> ⊕
+//! β<sub>i - 1</sub> ⊕ f<sub>i</sub>, whose out bits w<sub>i</sub>, set iff the bits i aren't all
+//! equal, and f<sub>i + 1</sub> are active iff bit i weighs: `addition_bit_shard`. The most
+//! significant bit of γ is linear in the bits of α, β and f, and needs no Shard.
+
+use std::collections::BTreeMap;
+
+use crush::soc::Id;
+use crush::soc::utils::{BddSpec, LevelSpec, NodeSpec};
+
+use crate::code_gen::gsf::GenericShard;
+use crate::diff_solver::post_processing_v5::BaseTable;
+
+/// What the bits below tell about the current bit of a differential of the modular addition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Carry {
+    /// The bits below of α, β and γ are all equal to the given value: the parity of the current
+    /// bits of α, β and γ must be that value.
+    Equal(bool),
+    /// The bits below of α, β and γ are not all equal: the current bits are free.
+    Free,
+}
+
+impl Carry {
+    fn after(a: bool, b: bool, c: bool) -> Carry {
+        if a == b && b == c { Carry::Equal(a) } else { Carry::Free }
+    }
+}
+
+/// The Shard of the differentials of the addition modulo 2<sup>`word_size`</sup>, see the module
+/// documentation. The in bits are the ones of α followed by the ones of β, and the out bits the
+/// ones of γ, each from the least significant bit. The levels are ordered by bit: α<sub>i</sub>,
+/// β<sub>i</sub> and γ<sub>i</sub> are the levels 3i, 3i + 1 and 3i + 2.
+pub fn modular_addition_shard(word_size: usize) -> GenericShard {
+    assert!(word_size > 0);
+    // The nodes of each level by their state: the carry, then the bits of α and β of the current
+    // bit read so far.
+    let mut levels: Vec<BTreeMap<(Carry, Vec<bool>), usize>> = vec![BTreeMap::new(); 3 * word_size];
+    let mut edges: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+    let sink_id = 1;
+    let mut next_id = 2;
+    levels[0].insert((Carry::Equal(false), Vec::new()), next_id);
+    next_id += 1;
+
+    for depth in 0..3 * word_size {
+        let nodes: Vec<((Carry, Vec<bool>), usize)> = levels[depth].iter()
+            .map(|(state, id)| (state.clone(), *id))
+            .collect();
+        for ((carry, read), id) in nodes {
+            let mut children = [0; 2];
+            for (edge, child) in children.iter_mut().enumerate() {
+                let bit = edge == 1;
+                let next_state = if read.len() < 2 {
+                    let mut read = read.clone();
+                    read.push(bit);
+                    (carry, read)
+                } else {
+                    let (a, b, c) = (read[0], read[1], bit);
+                    if let Carry::Equal(parity) = carry {
+                        if a ^ b ^ c != parity {
+                            continue;
+                        }
+                    }
+                    (Carry::after(a, b, c), Vec::new())
+                };
+                *child = if depth + 1 == 3 * word_size {
+                    sink_id
+                } else {
+                    *levels[depth + 1].entry(next_state).or_insert_with(|| {
+                        next_id += 1;
+                        next_id - 1
+                    })
+                };
+            }
+            edges.insert(id, (children[0], children[1]));
+        }
+    }
+
+    let mut level_specs: Vec<LevelSpec> = levels.iter()
+        .map(|level| {
+            let rhs = level.values()
+                .map(|id| {
+                    let (e0, e1) = edges[id];
+                    NodeSpec::new(Id::new(*id), Id::new(e0), Id::new(e1))
+                })
+                .collect();
+            LevelSpec::new(vec![], rhs)
+        })
+        .collect();
+    level_specs.push(LevelSpec::new(vec![], vec![NodeSpec::new(Id::new(sink_id), Id::new(0), Id::new(0))]));
+
+    let sink_depth = 3 * word_size;
+    let mut shard = crush::soc::utils::build_bdd_from_spec(&mut BddSpec::new(Id::new(0), level_specs), 1);
+    shard.remove_all_dead_ends_start(sink_depth - 1);
+    shard.remove_orphans_start(1);
+    shard.merge_equals_node_start(sink_depth - 1);
+
+    let order = (0..word_size).map(|i| 3 * i)
+        .chain((0..word_size).map(|i| 3 * i + 1))
+        .chain((0..word_size).map(|i| 3 * i + 2))
+        .collect();
+    GenericShard::with_levels(shard, 2 * word_size, word_size, order)
+}
+
+/// The weight of the differential (`alpha`, `beta` → `gamma`) of the addition modulo
+/// 2<sup>`word_size`</sup>, i.e. minus the log2 of its probability, or `None` if it is impossible.
+pub fn xdp_add_weight(alpha: u64, beta: u64, gamma: u64, word_size: usize) -> Option<u32> {
+    let mask = if word_size >= 64 { u64::MAX } else { (1 << word_size) - 1 };
+    let (alpha, beta, gamma) = (alpha & mask, beta & mask, gamma & mask);
+    // Bit i is set iff the bits i of the three words are equal
+    let eq = |x: u64, y: u64, z: u64| !(x ^ y) & !(x ^ z) & mask;
+    let shifted_eq = eq(alpha << 1, beta << 1, gamma << 1);
+    if shifted_eq & (alpha ^ beta ^ gamma ^ (beta << 1)) & mask != 0 {
+        return None;
+    }
+    Some((!eq(alpha, beta, gamma) & (mask >> 1)).count_ones())
+}
+
+/// The Shard of bit `bit` of the differentials of the modular addition, split per bit as in the
+/// module documentation, for a bit below the most significant one. The in bits are
+/// α<sub>i</sub>, β<sub>i</sub>, β<sub>i - 1</sub> and f<sub>i</sub>, but for bit 0 where the
+/// last two are 0 and left out. The out bits are w<sub>i</sub>, set iff α<sub>i</sub>,
+/// β<sub>i</sub> and γ<sub>i</sub> aren't all equal, and f<sub>i + 1</sub>, which may only be set
+/// along with w<sub>i</sub>.
+pub fn addition_bit_shard(bit: usize) -> GenericShard {
+    let size_in = if bit == 0 { 2 } else { 4 };
+    let rows = (0..1usize << size_in)
+        .map(|input| {
+            let (a, b) = (input & 1, (input >> 1) & 1);
+            let c = a ^ b ^ ((input >> 2) & 1) ^ (input >> 3);
+            if a == b && b == c { vec![1, 0, 0, 0] } else { vec![0, 1, 0, 1] }
+        })
+        .collect();
+    let table = BaseTable::new(rows).expect("The table is not empty");
+    GenericShard::new(&table, size_in, 2)
+}
+
+/// The Shard of the differentials of the AND of two bits, the in bits being the two inputs.
+pub fn and_shard() -> GenericShard {
+    let table = BaseTable::new(vec![vec![4, 0], vec![2, 2], vec![2, 2], vec![2, 2]])
+        .expect("The table is not empty");
+    GenericShard::new(&table, 2, 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn modular_addition_transitions() {
+        for word_size in 1..=4 {
+            let shard = modular_addition_shard(word_size);
+            let mut transitions = shard.transitions();
+            transitions.sort_unstable();
+
+            let mask = (1 << word_size) - 1;
+            let mut expected = Vec::new();
+            for input in 0..1usize << (2 * word_size) {
+                let (alpha, beta) = (input & mask, input >> word_size);
+                for gamma in 0..1usize << word_size {
+                    // The differential is possible iff some pair follows it
+                    let possible = (0..1usize << (2 * word_size)).any(|pair| {
+                        let (x, y) = (pair & mask, pair >> word_size);
+                        ((x + y) ^ ((x ^ alpha) + (y ^ beta))) & mask == gamma
+                    });
+                    assert_eq!(possible, xdp_add_weight(alpha as u64, beta as u64, gamma as u64, word_size).is_some());
+                    if possible {
+                        expected.push((input, gamma));
+                    }
+                }
+            }
+            assert_eq!(expected, transitions);
+        }
+    }
+
+    #[test]
+    fn xdp_add_weights() {
+        // The weight is exact: the number of pairs following each differential over 3 bits
+        let word_size = 3;
+        for alpha in 0..8u64 {
+            for beta in 0..8u64 {
+                for gamma in 0..8u64 {
+                    let pairs = (0..64u64)
+                        .filter(|pair| {
+                            let (x, y) = (pair & 7, pair >> 3);
+                            ((x + y) ^ ((x ^ alpha) + (y ^ beta))) & 7 == gamma
+                        })
+                        .count() as u32;
+                    match xdp_add_weight(alpha, beta, gamma, word_size) {
+                        Some(weight) => assert_eq!(64 >> weight, pairs),
+                        None => assert_eq!(0, pairs),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn addition_bit_weights() {
+        // Chaining the Shards of the bits gives the differentials of the addition, the number of
+        // active Shards being their weight
+        let word_size = 4;
+        let shards: Vec<Vec<(usize, usize)>> = (0..word_size - 1).map(|bit| addition_bit_shard(bit).transitions()).collect();
+        let bit = |x: u64, i: usize| ((x >> i) & 1) as usize;
+        for alpha in 0..16u64 {
+            for beta in 0..16u64 {
+                for gamma in 0..16u64 {
+                    let f = alpha ^ beta ^ gamma ^ (beta << 1);
+                    let mut weight = Some(0);
+                    for (i, transitions) in shards.iter().enumerate() {
+                        let input = if i == 0 {
+                            bit(alpha, 0) | bit(beta, 0) << 1
+                        } else {
+                            bit(alpha, i) | bit(beta, i) << 1 | bit(beta, i - 1) << 2 | bit(f, i) << 3
+                        };
+                        let active = transitions.iter().any(|&(a, b)| a == input && b & 1 == 1);
+                        if !transitions.contains(&(input, active as usize | bit(f, i + 1) << 1)) {
+                            weight = None;
+                        } else if active {
+                            weight = weight.map(|w| w + 1);
+                        }
+                    }
+                    // The Shards read f rather than γ, which is found back from it but for the
+                    // bits 0 of f, which must be 0, and the most significant bit of γ
+                    if f & 1 == 1 {
+                        weight = None;
+                    }
+                    assert_eq!(xdp_add_weight(alpha, beta, gamma, word_size), weight);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn and_transitions() {
+        let mut transitions = and_shard().transitions();
+        transitions.sort_unstable();
+        assert_eq!(vec![(0, 0), (1, 0), (1, 1), (2, 0), (2, 1), (3, 0), (3, 1)], transitions);
+    }
+}
@@ -0,0 +1,121 @@
+//! The C99 target: the state is a `uint8_t` array, and the sources only need the standard library.
+//! E.g. `cc -O2 -o verify verify.c`.
+
+use crush::algebra::Matrix;
+use vob::Vob;
+
+use super::{Backend, DEFAULT_SAMPLES, SBoxCall, join, join_bits, xor, xored_bits};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CBackend;
+
+impl Backend for CBackend {
+    fn preamble(&self, comment: &[String], block_size: usize, nr_rounds: usize) -> String {
+        format!("/* {} */\n#include <stdint.h>\n#include <stdio.h>\n#include <stdlib.h>\n#include <string.h>\n\
+                 #include <time.h>\n\n#define BLOCK_SIZE {}\n#define ROUNDS {}\n\n",
+                comment.join("\n * "), block_size, nr_rounds)
+    }
+
+    fn sbox_table(&self, index: usize, table: &[usize]) -> String {
+        format!("static const uint32_t SBOX_{}[{}] = {{{}}};\n", index, table.len(), join(table))
+    }
+
+    fn round(&self, round: usize, sboxes: &[SBoxCall], linear_layer: &Matrix) -> String {
+        let mut out = format!("static void round_{}(uint8_t *s, const uint8_t *k) {{\n    uint8_t t[BLOCK_SIZE];\n",
+                              round);
+        if !sboxes.is_empty() {
+            out.push_str("    uint32_t x;\n");
+        }
+        out.push_str("    for (int i = 0; i < BLOCK_SIZE; i++) {\n        s[i] ^= k[i];\n    }\n");
+        for sbox in sboxes {
+            let read: Vec<String> = (0..sbox.size_in)
+                .map(|i| format!("(uint32_t) s[{}] << {}", sbox.start + i, i))
+                .collect();
+            out.push_str(&format!("    x = SBOX_{}[{}];\n", sbox.table, read.join(" | ")));
+            for i in 0..sbox.size_out {
+                out.push_str(&format!("    s[{}] = x >> {} & 1;\n", sbox.start + i, i));
+            }
+        }
+        for (i, bits) in xored_bits(linear_layer).iter().enumerate() {
+            out.push_str(&format!("    t[{}] = {};\n", i, xor(bits)));
+        }
+        out.push_str("    memcpy(s, t, BLOCK_SIZE);\n}\n\n");
+        out
+    }
+
+    fn round_table(&self, nr_rounds: usize) -> String {
+        let rounds: Vec<String> = (0..nr_rounds).map(|round| format!("round_{}", round)).collect();
+        format!("static void (*const ROUND[ROUNDS])(uint8_t *, const uint8_t *) = {{{}}};\n\n", rounds.join(", "))
+    }
+
+    fn encrypt(&self) -> String {
+        "void encrypt(uint8_t *s, const uint8_t keys[ROUNDS][BLOCK_SIZE]) {\n    \
+         for (int r = 0; r < ROUNDS; r++) {\n        ROUND[r](s, keys[r]);\n    }\n}\n".to_string()
+    }
+
+    fn bit_arrays(&self, name: &str, bits: &[Vob]) -> String {
+        let rows: Vec<String> = bits.iter().map(|row| format!("    {{{}}}", join_bits(row))).collect();
+        format!("static const uint8_t {}[{}][BLOCK_SIZE] = {{\n{}\n}};\n\n", name, bits.len(), rows.join(",\n"))
+    }
+
+    fn verifier_main(&self, fixed_keys: bool) -> String {
+        let round_key = if fixed_keys { "memcpy(k, KEYS[r], BLOCK_SIZE);" } else { "random_bits(k);" };
+        format!("#define DEFAULT_SAMPLES {}ULL
+
+static uint64_t rng_state;
+
+/* xorshift64* */
+static uint64_t next_random(void) {{
+    rng_state ^= rng_state >> 12;
+    rng_state ^= rng_state << 25;
+    rng_state ^= rng_state >> 27;
+    return rng_state * 0x2545F4914F6CDD1DULL;
+}}
+
+static void random_bits(uint8_t *s) {{
+    for (int i = 0; i < BLOCK_SIZE; i++) {{
+        s[i] = next_random() >> 63;
+    }}
+}}
+
+static int has_difference(const uint8_t *a, const uint8_t *b, const uint8_t *difference) {{
+    for (int i = 0; i < BLOCK_SIZE; i++) {{
+        if ((a[i] ^ b[i]) != difference[i]) {{
+            return 0;
+        }}
+    }}
+    return 1;
+}}
+
+int main(int argc, char **argv) {{
+    uint64_t samples = argc > 1 ? strtoull(argv[1], NULL, 10) : DEFAULT_SAMPLES;
+    uint64_t trail = 0, differential = 0;
+    uint8_t a[BLOCK_SIZE], b[BLOCK_SIZE], k[BLOCK_SIZE];
+    rng_state = argc > 2 ? strtoull(argv[2], NULL, 10) : (uint64_t) time(NULL);
+    if (rng_state == 0) {{
+        rng_state = 1;
+    }}
+    for (uint64_t n = 0; n < samples; n++) {{
+        int follows = 1;
+        random_bits(a);
+        for (int i = 0; i < BLOCK_SIZE; i++) {{
+            b[i] = a[i] ^ DIFFERENCES[0][i];
+        }}
+        for (int r = 0; r < ROUNDS; r++) {{
+            {}
+            ROUND[r](a, k);
+            ROUND[r](b, k);
+            follows = follows && has_difference(a, b, DIFFERENCES[r + 1]);
+        }}
+        trail += follows;
+        differential += has_difference(a, b, DIFFERENCES[ROUNDS]);
+    }}
+    printf(\"trail: %llu of %llu pairs, probability %g\\n\", (unsigned long long) trail,
+           (unsigned long long) samples, (double) trail / samples);
+    printf(\"differential: %llu of %llu pairs, probability %g\\n\", (unsigned long long) differential,
+           (unsigned long long) samples, (double) differential / samples);
+    return 0;
+}}
+", DEFAULT_SAMPLES, round_key)
+    }
+}
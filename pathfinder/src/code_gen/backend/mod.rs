@@ -0,0 +1,293 @@
+//! Generation of source code implementing the rounds of a `Cipher`, for Rust test harnesses as well
+//! as for C verification pipelines. What is generated is the same for every language, and the
+//! `Backend` of each language only writes its pieces: the tables of the S-boxes, one function per
+//! round, the table `ROUND` of these functions, and so on.
+//!
+//! The state is an array of `BLOCK_SIZE` bytes, one per bit, numbered as in `Cipher`. A round is the
+//! one of `Cipher::encrypt`: the addition of its round key, the S-box layer, then the linear layer.
+//! Two sources are generated:
+//! - `cipher_source`: the rounds, and the function `encrypt` applying them under given round keys;
+//! - `verifier_source`: a standalone program checking a differential trail experimentally. It
+//!   encrypts random pairs of plaintexts with the input difference of the trail, and counts the
+//!   pairs which follow the whole trail, and the ones which only have its output difference (i.e.
+//!   follow the differential). Without a key, the round keys are drawn at random for each pair (the
+//!   usual independent round keys assumption), otherwise they are the ones given by the key
+//!   schedule of the cipher. The program is run as `verify [samples] [seed]`, and the programs of
+//!   the different targets draw the same pairs for the same seed.
+
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+
+use crush::algebra::Matrix;
+use vob::Vob;
+
+use crate::code_gen::cipher::Cipher;
+
+pub mod c;
+pub mod rust;
+
+/// The number of pairs sampled when a verifier is run without arguments.
+pub const DEFAULT_SAMPLES: u64 = 1 << 20;
+
+/// An S-box of a round, as the functions of the rounds see it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SBoxCall {
+    /// The index of its table `SBOX_<table>`.
+    pub table: usize,
+    /// The first bit of the state it reads.
+    pub start: usize,
+    pub size_in: usize,
+    pub size_out: usize,
+}
+
+/// The pieces of the generated sources in a given language, see the module documentation. Each
+/// piece ends with an empty line.
+pub trait Backend {
+    /// The start of a source: `comment`, then the constants `BLOCK_SIZE` and `ROUNDS`.
+    fn preamble(&self, comment: &[String], block_size: usize, nr_rounds: usize) -> String;
+
+    /// The table `SBOX_<index>`.
+    fn sbox_table(&self, index: usize, table: &[usize]) -> String;
+
+    /// The function `round_<round>`, which maps the state in place given the round key.
+    fn round(&self, round: usize, sboxes: &[SBoxCall], linear_layer: &Matrix) -> String;
+
+    /// The table `ROUND` of the `nr_rounds` functions of the rounds.
+    fn round_table(&self, nr_rounds: usize) -> String;
+
+    /// The function `encrypt`, which applies the `ROUNDS` rounds to the state given their keys.
+    fn encrypt(&self) -> String;
+
+    /// The constant `name`: an array of states, `bits`.
+    fn bit_arrays(&self, name: &str, bits: &[Vob]) -> String;
+
+    /// The entry point of the verifier, given the constants `DIFFERENCES` and, if `fixed_keys`,
+    /// `KEYS`.
+    fn verifier_main(&self, fixed_keys: bool) -> String;
+}
+
+/// The languages in which sources can be generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    C,
+    Rust,
+}
+
+impl Target {
+    pub fn backend(self) -> Box<dyn Backend> {
+        match self {
+            Target::C => Box::new(c::CBackend),
+            Target::Rust => Box::new(rust::RustBackend),
+        }
+    }
+
+    /// The usual extension of the sources of the target.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Target::C => "c",
+            Target::Rust => "rs",
+        }
+    }
+}
+
+/// Returns the source implementing the first `nr_rounds` rounds of `cipher`, see the module
+/// documentation.
+pub fn cipher_source<C: Cipher>(backend: &dyn Backend, cipher: &C, nr_rounds: usize) -> String {
+    let comment = vec![format!("The first {} rounds of {}.", nr_rounds, cipher.name()),
+                       "Generated by pathfinder::code_gen::backend.".to_string()];
+    let mut out = backend.preamble(&comment, cipher.block_size(), nr_rounds);
+    out.push_str(&rounds_source(backend, cipher, nr_rounds));
+    out.push_str(&backend.encrypt());
+    out
+}
+
+/// Returns the source of a program checking the trail whose differences before each round (and
+/// after the last one) are `differences`, see the module documentation. The trail covers the first
+/// `differences.len() - 1` rounds of `cipher`.
+///
+/// Returns an `Error` if there are fewer than two differences, or if any isn't the size of the
+/// state.
+pub fn verifier_source<C: Cipher>(backend: &dyn Backend,
+                                  cipher: &C,
+                                  differences: &[Vob],
+                                  key: Option<&Vob>) -> Result<String, Error> {
+    if differences.len() < 2 {
+        return Err(Error::new(ErrorKind::InvalidInput, "A trail has at least one round"));
+    }
+    let block_size = cipher.block_size();
+    if let Some(difference) = differences.iter().find(|difference| difference.len() != block_size) {
+        return Err(Error::new(ErrorKind::InvalidInput,
+                              format!("A difference has {} bits, but the state has {}", difference.len(), block_size)));
+    }
+    let nr_rounds = differences.len() - 1;
+
+    let comment = vec![format!("Experimental verification of a {} round differential trail of {}.",
+                               nr_rounds, cipher.name()),
+                       "Generated by pathfinder::code_gen::backend.".to_string(),
+                       "Usage: verify [samples] [seed]".to_string()];
+    let mut out = backend.preamble(&comment, block_size, nr_rounds);
+    out.push_str(&rounds_source(backend, cipher, nr_rounds));
+    out.push_str(&backend.bit_arrays("DIFFERENCES", differences));
+    if let Some(key) = key {
+        let mut round_keys = cipher.round_keys(key);
+        round_keys.resize(nr_rounds, Vob::from_elem(block_size, false));
+        round_keys.truncate(nr_rounds);
+        out.push_str(&backend.bit_arrays("KEYS", &round_keys));
+    }
+    out.push_str(&backend.verifier_main(key.is_some()));
+    Ok(out)
+}
+
+/// The tables of the distinct S-boxes, the functions of the rounds and their table.
+fn rounds_source<C: Cipher>(backend: &dyn Backend, cipher: &C, nr_rounds: usize) -> String {
+    let mut out = String::new();
+    let mut tables: HashMap<Vec<usize>, usize> = HashMap::new();
+    let mut rounds = Vec::with_capacity(nr_rounds);
+    for round in 0..nr_rounds {
+        let mut start = 0;
+        let mut sboxes = Vec::with_capacity(cipher.num_sboxes(round));
+        for pos in 0..cipher.num_sboxes(round) {
+            let sbox = cipher.sbox(round, pos);
+            let index = tables.len();
+            let table = *tables.entry(sbox.table().to_vec()).or_insert_with(|| {
+                out.push_str(&backend.sbox_table(index, sbox.table()));
+                index
+            });
+            sboxes.push(SBoxCall { table, start, size_in: sbox.size_in(), size_out: sbox.size_out() });
+            start += sbox.size_in();
+        }
+        rounds.push(backend.round(round, &sboxes, &cipher.linear_layer(round)));
+    }
+    out.push('\n');
+    out.push_str(&rounds.concat());
+    out.push_str(&backend.round_table(nr_rounds));
+    out
+}
+
+/// The indices of the bits XORed into each bit by `matrix`.
+fn xored_bits(matrix: &Matrix) -> Vec<Vec<usize>> {
+    matrix.iter_rows().map(|row| row.iter_set_bits(..).collect()).collect()
+}
+
+fn join(values: &[usize]) -> String {
+    values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+fn join_bits(bits: &Vob) -> String {
+    bits.iter().map(|bit| if bit { "1" } else { "0" }).collect::<Vec<_>>().join(", ")
+}
+
+/// The XOR of the bits `bits` of the state `s`.
+fn xor(bits: &[usize]) -> String {
+    if bits.is_empty() {
+        "0".to_string()
+    } else {
+        bits.iter().map(|bit| format!("s[{}]", bit)).collect::<Vec<_>>().join(" ^ ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::code_gen::cipher::SBox;
+
+    use super::*;
+
+    const PRESENT: [usize; 16] = [0xc, 0x5, 0x6, 0xb, 0x9, 0x0, 0xa, 0xd, 0x3, 0xe, 0xf, 0x8, 0x4, 0x7, 0x1, 0x2];
+
+    /// Two PRESENT S-boxes, and the swap of the two halves of the state.
+    struct Toy;
+
+    impl Cipher for Toy {
+        fn name(&self) -> String {
+            "toy".to_string()
+        }
+
+        fn block_size(&self) -> usize {
+            8
+        }
+
+        fn nr_rounds(&self) -> usize {
+            2
+        }
+
+        fn sbox(&self, _round: usize, _pos: usize) -> SBox {
+            SBox::new(PRESENT.to_vec(), 4, 4).unwrap()
+        }
+
+        fn linear_layer(&self, _round: usize) -> Matrix {
+            let mut rows = vec![Vob::from_elem(8, false); 8];
+            for (i, row) in rows.iter_mut().enumerate() {
+                row.set((i + 4) % 8, true);
+            }
+            Matrix::from_rows(rows)
+        }
+    }
+
+    fn to_vob(x: usize) -> Vob {
+        (0..8).map(|i| (x >> i) & 1 == 1).collect()
+    }
+
+    #[test]
+    fn c_sources() {
+        let backend = Target::C.backend();
+        let source = cipher_source(&*backend, &Toy, 2);
+        assert!(source.contains("#define BLOCK_SIZE 8\n#define ROUNDS 2\n"));
+        assert!(source.contains("static const uint32_t SBOX_0[16] = {12, 5, 6, 11, 9, 0, 10, 13, 3, 14, 15, 8, 4, 7, 1, 2};"));
+        // A single table for all the S-boxes
+        assert!(!source.contains("SBOX_1"));
+        assert!(source.contains("    x = SBOX_0[(uint32_t) s[4] << 0 | (uint32_t) s[5] << 1 | \
+                                 (uint32_t) s[6] << 2 | (uint32_t) s[7] << 3];"));
+        assert!(source.contains("    t[0] = s[4];"));
+        assert!(source.contains("= {round_0, round_1};"));
+        assert!(source.contains("void encrypt("));
+        assert!(!source.contains("main("));
+
+        // 0x1 -> 0x3 through the first S-box, then the halves are swapped
+        let trail = [to_vob(0x01), to_vob(0x30)];
+        let source = verifier_source(&*backend, &Toy, &trail, None).unwrap();
+        assert!(source.contains("#define ROUNDS 1\n"));
+        assert!(source.contains("    {1, 0, 0, 0, 0, 0, 0, 0},\n    {0, 0, 0, 0, 1, 1, 0, 0}\n"));
+        assert!(source.contains("random_bits(k);"));
+        assert!(!source.contains("KEYS"));
+        assert!(!source.contains("void encrypt("));
+
+        let source = verifier_source(&*backend, &Toy, &trail, Some(&Vob::new())).unwrap();
+        assert!(source.contains("static const uint8_t KEYS[1][BLOCK_SIZE] = {\n    {0, 0, 0, 0, 0, 0, 0, 0}\n};"));
+        assert!(source.contains("memcpy(k, KEYS[r], BLOCK_SIZE);"));
+    }
+
+    #[test]
+    fn rust_sources() {
+        let backend = Target::Rust.backend();
+        let source = cipher_source(&*backend, &Toy, 2);
+        assert!(source.contains("const BLOCK_SIZE: usize = 8;\nconst ROUNDS: usize = 2;\n"));
+        assert!(source.contains("static SBOX_0: [u32; 16] = [12, 5, 6, 11, 9, 0, 10, 13, 3, 14, 15, 8, 4, 7, 1, 2];"));
+        assert!(!source.contains("SBOX_1"));
+        assert!(source.contains("    let x = SBOX_0[(s[4] as usize) | (s[5] as usize) << 1 | \
+                                 (s[6] as usize) << 2 | (s[7] as usize) << 3];"));
+        assert!(source.contains("    t[0] = s[4];"));
+        assert!(source.contains("= [round_0, round_1];"));
+        assert!(source.contains("pub fn encrypt("));
+        assert!(!source.contains("fn main("));
+
+        let trail = [to_vob(0x01), to_vob(0x30)];
+        let source = verifier_source(&*backend, &Toy, &trail, None).unwrap();
+        assert!(source.contains("const ROUNDS: usize = 1;\n"));
+        assert!(source.contains("    [1, 0, 0, 0, 0, 0, 0, 0],\n    [0, 0, 0, 0, 1, 1, 0, 0],\n"));
+        assert!(source.contains("rng.bits()"));
+        assert!(!source.contains("KEYS"));
+
+        let source = verifier_source(&*backend, &Toy, &trail, Some(&Vob::new())).unwrap();
+        assert!(source.contains("static KEYS: [State; 1] = [\n    [0, 0, 0, 0, 0, 0, 0, 0],\n];"));
+        assert!(source.contains("KEYS[r]"));
+    }
+
+    #[test]
+    fn invalid_trails() {
+        for target in [Target::C, Target::Rust].iter() {
+            let backend = target.backend();
+            assert!(verifier_source(&*backend, &Toy, &[to_vob(1)], None).is_err());
+            assert!(verifier_source(&*backend, &Toy, &[to_vob(1), Vob::from_elem(4, false)], None).is_err());
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! The Rust target: the state is a `[u8; BLOCK_SIZE]`, and the sources only need the standard
+//! library. A cipher source can be `include!`d by a test harness, a verifier is compiled on its own,
+//! e.g. `rustc -O verify.rs`.
+
+use crush::algebra::Matrix;
+use vob::Vob;
+
+use super::{Backend, DEFAULT_SAMPLES, SBoxCall, join, join_bits, xor, xored_bits};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RustBackend;
+
+impl Backend for RustBackend {
+    fn preamble(&self, comment: &[String], block_size: usize, nr_rounds: usize) -> String {
+        let comment: Vec<String> = comment.iter().map(|line| format!("// {}\n", line)).collect();
+        format!("{}\nconst BLOCK_SIZE: usize = {};\nconst ROUNDS: usize = {};\n\ntype State = [u8; BLOCK_SIZE];\n\n",
+                comment.concat(), block_size, nr_rounds)
+    }
+
+    fn sbox_table(&self, index: usize, table: &[usize]) -> String {
+        format!("static SBOX_{}: [u32; {}] = [{}];\n", index, table.len(), join(table))
+    }
+
+    fn round(&self, round: usize, sboxes: &[SBoxCall], linear_layer: &Matrix) -> String {
+        let mut out = format!("fn round_{}(s: &mut State, k: &State) {{\n    let mut t = [0; BLOCK_SIZE];\n    \
+                               for (bit, key) in s.iter_mut().zip(k) {{\n        *bit ^= key;\n    }}\n",
+                              round);
+        for sbox in sboxes {
+            let read: Vec<String> = (0..sbox.size_in)
+                .map(|i| if i == 0 {
+                    format!("(s[{}] as usize)", sbox.start)
+                } else {
+                    format!("(s[{}] as usize) << {}", sbox.start + i, i)
+                })
+                .collect();
+            out.push_str(&format!("    let x = SBOX_{}[{}];\n", sbox.table, read.join(" | ")));
+            for i in 0..sbox.size_out {
+                out.push_str(&format!("    s[{}] = ((x >> {}) & 1) as u8;\n", sbox.start + i, i));
+            }
+        }
+        for (i, bits) in xored_bits(linear_layer).iter().enumerate() {
+            out.push_str(&format!("    t[{}] = {};\n", i, xor(bits)));
+        }
+        out.push_str("    *s = t;\n}\n\n");
+        out
+    }
+
+    fn round_table(&self, nr_rounds: usize) -> String {
+        let rounds: Vec<String> = (0..nr_rounds).map(|round| format!("round_{}", round)).collect();
+        format!("static ROUND: [fn(&mut State, &State); ROUNDS] = [{}];\n\n", rounds.join(", "))
+    }
+
+    fn encrypt(&self) -> String {
+        "pub fn encrypt(s: &mut State, keys: &[State; ROUNDS]) {\n    \
+         for (round, k) in ROUND.iter().zip(keys) {\n        round(s, k);\n    }\n}\n".to_string()
+    }
+
+    fn bit_arrays(&self, name: &str, bits: &[Vob]) -> String {
+        let rows: Vec<String> = bits.iter().map(|row| format!("    [{}],\n", join_bits(row))).collect();
+        format!("static {}: [State; {}] = [\n{}];\n\n", name, bits.len(), rows.concat())
+    }
+
+    fn verifier_main(&self, fixed_keys: bool) -> String {
+        let round_key = if fixed_keys { "KEYS[r]" } else { "rng.bits()" };
+        format!("const DEFAULT_SAMPLES: u64 = {};
+
+/// xorshift64*
+struct Rng(u64);
+
+impl Rng {{
+    fn next(&mut self) -> u64 {{
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }}
+
+    fn bits(&mut self) -> State {{
+        let mut s = [0; BLOCK_SIZE];
+        for bit in s.iter_mut() {{
+            *bit = (self.next() >> 63) as u8;
+        }}
+        s
+    }}
+}}
+
+fn has_difference(a: &State, b: &State, difference: &State) -> bool {{
+    a.iter().zip(b).zip(difference).all(|((a, b), difference)| a ^ b == *difference)
+}}
+
+fn main() {{
+    let args: Vec<String> = std::env::args().collect();
+    let samples: u64 = args.get(1)
+        .map_or(DEFAULT_SAMPLES, |arg| arg.parse().expect(\"The number of samples must be an integer\"));
+    let seed: u64 = args.get(2).map_or_else(
+        || std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs(),
+        |arg| arg.parse().expect(\"The seed must be an integer\"));
+    let mut rng = Rng(seed.max(1));
+    let (mut trail, mut differential) = (0u64, 0u64);
+    for _ in 0..samples {{
+        let mut a = rng.bits();
+        let mut b = a;
+        for (bit, difference) in b.iter_mut().zip(&DIFFERENCES[0]) {{
+            *bit ^= difference;
+        }}
+        let mut follows = true;
+        for r in 0..ROUNDS {{
+            let k = {};
+            ROUND[r](&mut a, &k);
+            ROUND[r](&mut b, &k);
+            follows = follows && has_difference(&a, &b, &DIFFERENCES[r + 1]);
+        }}
+        trail += follows as u64;
+        differential += has_difference(&a, &b, &DIFFERENCES[ROUNDS]) as u64;
+    }}
+    println!(\"trail: {{}} of {{}} pairs, probability {{}}\", trail, samples, trail as f64 / samples as f64);
+    println!(\"differential: {{}} of {{}} pairs, probability {{}}\", differential, samples,
+             differential as f64 / samples as f64);
+}}
+", DEFAULT_SAMPLES, round_key)
+    }
+}
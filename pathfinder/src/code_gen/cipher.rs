@@ -0,0 +1,868 @@
+//! A description of an SPN cipher, from which the SoCs of its differential, linear and division
+//! trails are made.
+//!
+//! A new cipher only needs to implement `Cipher`: its state size, its number of rounds, its
+//! S-boxes and its linear layers as matrices. `CipherHandler` then provides the `SBoxHandler`
+//! and `LLHandler` of the cipher for the kind of trail searched, and `make_cipher_soc` its SoC.
+//! A `RoundWindow` restricts them to some of the rounds, each made with its own options. A cipher
+//! describing its key schedule with `key_schedule` also gets the SoC of its related-key
+//! differential trails, see `make_cipher_related_key_soc`, and any cipher with bijective S-boxes
+//! the SoC of its boomerangs, see `make_cipher_boomerang_soc`. A cipher whose bit-level SoC is too
+//! large, e.g. a byte-oriented one, can ask for word-level Shards with `granularity`, for its
+//! truncated differential trails. `make_weighted_solver` counts the weights of the transitions of
+//! the S-boxes rather than the active S-boxes, see `sbox::weighted_generic_shard`.
+//!
+//! The bits of the state are numbered from 0. The S-box at position `pos` of a round reads the
+//! `size_in` bits following the ones read by the S-boxes before it, the first one being the least
+//! significant bit of its input, and writes its output to the same bits. The bits after the last
+//! S-box of an incomplete S-box layer are left unchanged.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::{Error, ErrorKind};
+use std::ops::Range;
+
+use vob::Vob;
+
+use crush::algebra::Matrix;
+use crush::soc::bdd::differential::PPFactory;
+use crush::soc::Id;
+use crush::soc::system::System;
+
+use crate::code_gen::{apply_matrix, LLHandler, SBoxHandler};
+use crate::code_gen::gsf::GenericShard;
+use crate::code_gen::{sbox, soc_gen};
+use crate::code_gen::truncated::truncate_handlers;
+use crate::diff_solver::{bct, make_boomerang_soc, make_division_soc, make_linear_soc, make_related_key_soc,
+                         switch_table, KeyScheduleHandler, Library, LibraryKey, SimpleSolver, SolverConfig,
+                         SPFactory};
+use crate::diff_solver::post_processing_v5::{BaseTable, Trail};
+
+/// An S-box given by its lookup table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SBox {
+    table: Vec<usize>,
+    size_in: usize,
+    size_out: usize,
+}
+
+impl SBox {
+    /// Returns an `Error` unless `table` has an entry of `size_out` bits for each input of
+    /// `size_in` bits.
+    pub fn new(table: Vec<usize>, size_in: usize, size_out: usize) -> Result<SBox, Error> {
+        if table.len() != 1 << size_in {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                                  format!("Expected {} entries in the table, found {}", 1 << size_in, table.len())));
+        }
+        if let Some(entry) = table.iter().find(|entry| **entry >= 1 << size_out) {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                                  format!("The entry {} doesn't fit in {} bits", entry, size_out)));
+        }
+        Ok(SBox { table, size_in, size_out })
+    }
+
+    #[inline]
+    pub fn size_in(&self) -> usize {
+        self.size_in
+    }
+
+    #[inline]
+    pub fn size_out(&self) -> usize {
+        self.size_out
+    }
+
+    #[inline]
+    pub fn table(&self) -> &[usize] {
+        &self.table
+    }
+
+    #[inline]
+    pub fn apply(&self, x: usize) -> usize {
+        self.table[x]
+    }
+
+    /// The DDT of the S-box, where the entry at (a, b) is the number of x such that
+    /// S(x) ^ S(x ^ a) = b.
+    pub fn ddt(&self) -> Vec<Vec<usize>> {
+        let mut ddt = vec![vec![0; 1 << self.size_out]; 1 << self.size_in];
+        for (a, row) in ddt.iter_mut().enumerate() {
+            for x in 0..self.table.len() {
+                row[self.table[x] ^ self.table[x ^ a]] += 1;
+            }
+        }
+        ddt
+    }
+}
+
+/// An SPN cipher: each round XORs a round key into the state (see `round_keys`), applies a layer
+/// of S-boxes and then a linear layer.
+pub trait Cipher {
+    fn name(&self) -> String;
+
+    /// The number of bits of the state.
+    fn block_size(&self) -> usize;
+
+    fn nr_rounds(&self) -> usize;
+
+    /// The number of S-boxes of the S-box layer of `round`. Defaults to a complete layer of the
+    /// S-box at position 0.
+    fn num_sboxes(&self, round: usize) -> usize {
+        self.block_size() / self.sbox(round, 0).size_in()
+    }
+
+    /// The S-box at position `pos` of the S-box layer of `round`. It must map as many bits as it
+    /// reads.
+    fn sbox(&self, round: usize, pos: usize) -> SBox;
+
+    /// The bits of the state read by each S-box of the S-box layer of `round`, in the order of
+    /// their positions.
+    fn sbox_inputs(&self, round: usize) -> Vec<Range<usize>> {
+        let mut start = 0;
+        (0..self.num_sboxes(round))
+            .map(|pos| {
+                let end = start + self.sbox(round, pos).size_in();
+                let inputs = start..end;
+                start = end;
+                inputs
+            })
+            .collect()
+    }
+
+    /// The linear layer following the S-box layer of `round`, as a square matrix where row i
+    /// gives the bits XORed into bit i.
+    fn linear_layer(&self, round: usize) -> Matrix;
+
+    /// The number of bits of the key.
+    fn key_size(&self) -> usize {
+        0
+    }
+
+    /// The key schedule: the round keys XORed into the state before the S-box layer of each
+    /// round, and after the last round if there is one more, given the key. Defaults to none, as
+    /// the round keys don't matter to the trails.
+    fn round_keys(&self, _key: &Vob) -> Vec<Vob> {
+        Vec::new()
+    }
+
+    /// The key schedule of `round` as a `KeyRound`, for the related-key trails, or `None` if it
+    /// isn't described. It must give the same round keys as `round_keys`. Defaults to none.
+    fn key_schedule(&self, _round: usize) -> Option<KeyRound> {
+        None
+    }
+
+    /// The granularity of the Shards of the SoCs of the cipher. Defaults to `Granularity::Bit`.
+    fn granularity(&self) -> Granularity {
+        Granularity::Bit
+    }
+
+    /// Encrypt `plaintext` under `key`, e.g. to check a trail experimentally.
+    fn encrypt(&self, plaintext: &Vob, key: &Vob) -> Vob {
+        self.encrypt_rounds(plaintext, key, &RoundWindow::new(0..self.nr_rounds()))
+    }
+
+    /// Encrypt `state` under `key` through the rounds of `window` only, as made by its options,
+    /// e.g. to append a round to a trail of the rounds before. The round key following the last
+    /// round of the window is XORed at the end if `window` asks for it and the key schedule gives
+    /// one.
+    fn encrypt_rounds(&self, state: &Vob, key: &Vob, window: &RoundWindow) -> Vob {
+        let round_keys = self.round_keys(key);
+        let mut state = state.clone();
+        for round in window.rounds() {
+            let options = window.options(round);
+            if let Some(round_key) = round_keys.get(round).filter(|_| options.key_addition) {
+                state.xor(round_key);
+            }
+            let mut start = 0;
+            for pos in 0..self.num_sboxes(round) {
+                let sbox = self.sbox(round, pos);
+                let x = (0..sbox.size_in()).fold(0, |x, i| x | ((state[start + i] as usize) << i));
+                let y = sbox.apply(x);
+                for i in 0..sbox.size_out() {
+                    state.set(start + i, (y >> i) & 1 == 1);
+                }
+                start += sbox.size_in();
+            }
+            if options.linear_layer {
+                state = self.linear_layer(round).iter_rows()
+                    .map(|row| row.iter_set_bits(..).fold(false, |bit, j| bit ^ state[j]))
+                    .collect();
+            }
+        }
+        if let Some(round_key) = round_keys.get(window.rounds().end).filter(|_| window.final_key_addition()) {
+            state.xor(round_key);
+        }
+        state
+    }
+}
+
+/// A round of the key schedule of a `Cipher`, acting on a key state initially equal to the key:
+/// the round key of the round is extracted from the key state, then the key state goes through a
+/// layer of S-boxes and a linear update. See `diff_solver::related_key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRound {
+    /// The round key as a matrix of `block_size` rows and `key_size` columns, where row i gives
+    /// the bits of the key state XORed into bit i of the round key.
+    pub round_key: Matrix,
+    /// The S-boxes applied to the key state once the round key is extracted, along with the bits
+    /// each one reads and writes. Their sizes must be multiples of the size of the S-boxes of the
+    /// state.
+    pub sboxes: Vec<(Range<usize>, SBox)>,
+    /// The linear update of the key state following its S-boxes, as a square matrix where row i
+    /// gives the bits XORed into bit i.
+    pub update: Matrix,
+}
+
+/// How a round of a `Cipher` is made, see `RoundWindow`. By default, a round has both its key
+/// addition and its linear layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundOptions {
+    /// Whether the round key is XORed into the state before the S-box layer. Keys don't matter to
+    /// the trails, only to `Cipher::encrypt_rounds`.
+    pub key_addition: bool,
+    /// Whether the linear layer follows the S-box layer, e.g. not in the last round of AES.
+    pub linear_layer: bool,
+}
+
+impl Default for RoundOptions {
+    fn default() -> Self {
+        RoundOptions { key_addition: true, linear_layer: true }
+    }
+}
+
+/// A window of consecutive rounds of a `Cipher`, with the options of each round, from which a SoC
+/// is made (see `make_window_soc`) or a state encrypted (see `Cipher::encrypt_rounds`). This allows
+/// for the usual tricks, such as searching the trails of the rounds r1..r2 only, omitting the last
+/// linear layer, or searching r - 1 rounds and appending a free round.
+///
+/// The SoC of a window always ends with the output of its last S-box layer: The linear layer of
+/// the last round only matters to the encryption.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundWindow {
+    rounds: Range<usize>,
+    options: HashMap<usize, RoundOptions>,
+    final_key_addition: bool,
+}
+
+impl RoundWindow {
+    /// The window of `rounds`, each round with the default options, and the round key following
+    /// the last round added.
+    pub fn new(rounds: Range<usize>) -> RoundWindow {
+        RoundWindow { rounds, options: HashMap::new(), final_key_addition: true }
+    }
+
+    /// Make `round` with `options`.
+    pub fn with_round_options(mut self, round: usize, options: RoundOptions) -> RoundWindow {
+        self.options.insert(round, options);
+        self
+    }
+
+    /// Omit the linear layer of the last round of the window.
+    pub fn omit_last_linear_layer(self) -> RoundWindow {
+        match self.rounds.end.checked_sub(1) {
+            Some(last) => {
+                let options = RoundOptions { linear_layer: false, ..self.options(last) };
+                self.with_round_options(last, options)
+            }
+            None => self,
+        }
+    }
+
+    /// Whether the round key following the last round of the window is added.
+    pub fn with_final_key_addition(mut self, add: bool) -> RoundWindow {
+        self.final_key_addition = add;
+        self
+    }
+
+    #[inline]
+    pub fn rounds(&self) -> Range<usize> {
+        self.rounds.clone()
+    }
+
+    /// The number of rounds of the window.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.rounds.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.rounds.is_empty()
+    }
+
+    /// The options of `round`, a round of the cipher.
+    pub fn options(&self, round: usize) -> RoundOptions {
+        self.options.get(&round).copied().unwrap_or_default()
+    }
+
+    #[inline]
+    pub fn final_key_addition(&self) -> bool {
+        self.final_key_addition
+    }
+}
+
+/// The kind of trails searched, telling which table the Shards of the S-boxes are based on and
+/// how the trails go through the linear layers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailKind {
+    /// Differential trails, from the DDTs.
+    Differential,
+    /// Linear trails, from the LATs, see `diff_solver::make_linear_soc`.
+    Linear,
+    /// Division trails, from the division trail tables, see `diff_solver::make_division_soc`.
+    Division,
+}
+
+/// The granularity of the Shards of the SoCs of a `Cipher`, see `Cipher::granularity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    /// A variable per bit of the state, and a Shard per S-box.
+    Bit,
+    /// A variable per word of the state, i.e. per S-box, telling whether it is active, as made by
+    /// `code_gen::truncated`. Only differential trails are searched at this granularity, which then
+    /// are truncated differential trails. It needs complete layers of bijective S-boxes, all of
+    /// the same size.
+    Word,
+}
+
+/// The `SBoxHandler` and `LLHandler` of a `Cipher`, for the trails of `kind`. Round r of the
+/// handlers is the round r of the window of the handler, by default all the rounds of the cipher.
+pub struct CipherHandler<'a, C: Cipher> {
+    cipher: &'a C,
+    kind: TrailKind,
+    window: RoundWindow,
+    /// The precision of the weight variables of the Shards, if any, see `with_weights`.
+    precision: Option<f64>,
+}
+
+impl<'a, C: Cipher> CipherHandler<'a, C> {
+    pub fn new(cipher: &'a C, kind: TrailKind) -> Self {
+        Self::for_window(cipher, kind, RoundWindow::new(0..cipher.nr_rounds()))
+    }
+
+    /// The handlers of the rounds of `window` only, made as its options tell.
+    pub fn for_window(cipher: &'a C, kind: TrailKind, window: RoundWindow) -> Self {
+        CipherHandler { cipher, kind, window, precision: None }
+    }
+
+    /// Follow the out bits of the Shards of the S-boxes with weight variables, counting their
+    /// weights in units of `precision`, see `sbox::weighted_generic_shard`.
+    ///
+    /// Returns an `Error` as `sbox::check_precision` does.
+    pub fn with_weights(mut self, precision: f64) -> Result<Self, Error> {
+        sbox::check_precision(self.kind, precision)?;
+        self.precision = Some(precision);
+        Ok(self)
+    }
+
+    /// The round of the cipher of round `round` of the handlers.
+    #[inline]
+    fn cipher_round(&self, round: usize) -> usize {
+        self.window.rounds().start + round
+    }
+}
+
+impl<'a, C: Cipher> SBoxHandler for CipherHandler<'a, C> {
+    fn num_sboxes(&self, round: usize) -> usize {
+        self.cipher.num_sboxes(self.cipher_round(round))
+    }
+
+    fn sbox_size_in(&self, round: usize, pos: usize) -> usize {
+        self.cipher.sbox(self.cipher_round(round), pos).size_in()
+    }
+
+    fn sbox_size_out(&self, round: usize, pos: usize) -> usize {
+        self.cipher.sbox(self.cipher_round(round), pos).size_out()
+    }
+
+    fn bt_generic_shard(&self, round: usize, pos: usize) -> GenericShard {
+        let sbox = self.cipher.sbox(self.cipher_round(round), pos);
+        match self.precision {
+            Some(precision) => sbox::weighted_generic_shard(&sbox, self.kind, precision)
+                .expect("The precision was checked by with_weights"),
+            None => sbox::generic_shard(&sbox, self.kind),
+        }
+    }
+
+    fn sbox_weight_size(&self, round: usize, pos: usize) -> usize {
+        match self.precision {
+            Some(precision) => sbox::weight_size(&self.cipher.sbox(self.cipher_round(round), pos), self.kind, precision)
+                .expect("The precision was checked by with_weights"),
+            None => 0,
+        }
+    }
+}
+
+impl<'a, C: Cipher> LLHandler for CipherHandler<'a, C> {
+    fn block_size(&self, _round: usize) -> usize {
+        self.cipher.block_size()
+    }
+
+    /// The linear layer of round r of the SoC is the one following the S-box layer of round r - 1
+    /// of the cipher, or none if that round omits it.
+    fn apply_linear_layer(&self, round: usize, state: Vec<Vob>) -> Vec<Vob> {
+        let previous = self.cipher_round(round - 1);
+        match self.window.options(previous).linear_layer {
+            true => apply_matrix(&self.cipher.linear_layer(previous), &state),
+            false => state,
+        }
+    }
+}
+
+impl<'a, C: Cipher> CipherHandler<'a, C> {
+    /// The key schedule of round `round` of the handlers, checked to be described by
+    /// `make_cipher_related_key_soc`.
+    fn key_round(&self, round: usize) -> KeyRound {
+        self.cipher.key_schedule(self.cipher_round(round)).expect("The key schedule of the round is described")
+    }
+}
+
+impl<'a, C: Cipher> KeyScheduleHandler for CipherHandler<'a, C> {
+    fn key_state_size(&self) -> usize {
+        self.cipher.key_size()
+    }
+
+    fn round_key(&self, round: usize, key_state: &[Vob]) -> Vec<Vob> {
+        apply_matrix(&self.key_round(round).round_key, key_state)
+    }
+
+    fn num_key_sboxes(&self, round: usize) -> usize {
+        self.key_round(round).sboxes.len()
+    }
+
+    fn key_sbox_bits(&self, round: usize, pos: usize) -> Range<usize> {
+        self.key_round(round).sboxes[pos].0.clone()
+    }
+
+    fn key_generic_shard(&self, round: usize, pos: usize) -> GenericShard {
+        sbox::generic_shard(&self.key_round(round).sboxes[pos].1, self.kind)
+    }
+
+    fn apply_key_update(&self, round: usize, key_state: Vec<Vob>) -> Vec<Vob> {
+        apply_matrix(&self.key_round(round).update, &key_state)
+    }
+}
+
+/// Make the SoC of the trails of `kind` over the first `nr_rounds` rounds of `cipher`.
+///
+/// Returns an `Error` if `nr_rounds` is 0, or if the linear layers don't suit `kind`, see
+/// `make_linear_soc` and `make_division_soc`.
+pub fn make_cipher_soc<C: Cipher>(cipher: &C, kind: TrailKind, nr_rounds: usize)
+                                  -> Result<(System, Vec<Vec<Id>>), Error> {
+    make_window_soc(cipher, kind, &RoundWindow::new(0..nr_rounds))
+}
+
+/// Make the SoC of the trails of `kind` over the rounds of `window` of `cipher`, made as its
+/// options tell. The Ids of the Shards are given round by round from the first round of the window.
+///
+/// At `Granularity::Word`, the SoC is the truncated SoC laid out as told by `code_gen::truncated`.
+///
+/// Returns an `Error` of kind `ErrorKind::InvalidInput` if the window is empty, and an `Error` if
+/// the linear layers don't suit `kind`, as for `make_cipher_soc`. At `Granularity::Word`, returns
+/// an `Error` of kind `ErrorKind::Unsupported` unless `kind` is `TrailKind::Differential`, and of
+/// kind `ErrorKind::InvalidInput` if the S-box layers don't suit it, see `truncate_handlers`.
+pub fn make_window_soc<C: Cipher>(cipher: &C, kind: TrailKind, window: &RoundWindow)
+                                  -> Result<(System, Vec<Vec<Id>>), Error> {
+    if window.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "Expected a window of at least one round"));
+    }
+    let handler = CipherHandler::for_window(cipher, kind, window.clone());
+    let nr_rounds = window.len();
+    match (kind, cipher.granularity()) {
+        (TrailKind::Differential, Granularity::Bit) => Ok(soc_gen::make_soc(&handler, &handler, nr_rounds)),
+        (TrailKind::Linear, Granularity::Bit) => make_linear_soc(&handler, &handler, nr_rounds),
+        (TrailKind::Division, Granularity::Bit) => make_division_soc(&handler, &handler, nr_rounds),
+        (TrailKind::Differential, Granularity::Word) => {
+            let (llh, sh) = truncate_handlers(&handler, &handler, nr_rounds)?;
+            Ok(soc_gen::make_soc(&llh, &sh, nr_rounds))
+        }
+        (_, Granularity::Word) => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("Word-level SoCs are made for differential trails only, not {:?} ones", kind))),
+    }
+}
+
+/// Make a `SimpleSolver` searching the trails of `kind` over the first `nr_rounds` rounds of
+/// `cipher`, the weight of a trail being its number of active S-boxes, with the strategy `config`.
+pub fn make_cipher_solver<C, F>(cipher: &C, kind: TrailKind, nr_rounds: usize, progress: F,
+                                config: SolverConfig) -> Result<SimpleSolver<F>, Error>
+    where
+        C: Cipher,
+        F: SPFactory + PPFactory + Clone + Debug,
+{
+    make_window_solver(cipher, kind, &RoundWindow::new(0..nr_rounds), progress, config)
+}
+
+/// As `make_cipher_solver`, over the rounds of `window` of `cipher`, see `make_window_soc`.
+pub fn make_window_solver<C, F>(cipher: &C, kind: TrailKind, window: &RoundWindow, progress: F,
+                                config: SolverConfig) -> Result<SimpleSolver<F>, Error>
+    where
+        C: Cipher,
+        F: SPFactory + PPFactory + Clone + Debug,
+{
+    let (soc, rounds) = make_window_soc(cipher, kind, window)?;
+    Ok(cipher_solver(cipher, window, soc, rounds, progress, config))
+}
+
+/// Make the SoC of the trails of `kind` over the rounds of `window` of `cipher` as
+/// `make_window_soc` does, but with the weights of the transitions of the S-boxes encoded by weight
+/// variables in units of `precision`, see `sbox::weighted_generic_shard`. The weight variables of
+/// an S-box follow its out bits.
+///
+/// Returns an `Error` of kind `ErrorKind::Unsupported` unless the granularity of `cipher` is
+/// `Granularity::Bit`, an `Error` as `sbox::check_precision` does, and an `Error` as
+/// `make_window_soc` does.
+pub fn make_weighted_window_soc<C: Cipher>(cipher: &C, kind: TrailKind, window: &RoundWindow, precision: f64)
+                                           -> Result<(System, Vec<Vec<Id>>), Error> {
+    if cipher.granularity() != Granularity::Bit {
+        return Err(Error::new(ErrorKind::Unsupported, "Weighted SoCs are made at the bit level only"));
+    }
+    if window.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "Expected a window of at least one round"));
+    }
+    let handler = CipherHandler::for_window(cipher, kind, window.clone()).with_weights(precision)?;
+    match kind {
+        TrailKind::Linear => make_linear_soc(&handler, &handler, window.len()),
+        _ => Ok(soc_gen::make_soc(&handler, &handler, window.len())),
+    }
+}
+
+/// As `make_window_solver`, the weight of a trail being the sum of the weights of its
+/// transitions in units of `precision` instead of its number of active S-boxes, see
+/// `make_weighted_window_soc`. A weight w found by the solver is thus a probability, or
+/// correlation, of about 2^-(w * `precision`).
+pub fn make_weighted_solver<C, F>(cipher: &C, kind: TrailKind, window: &RoundWindow, precision: f64,
+                                  progress: F, config: SolverConfig) -> Result<SimpleSolver<F>, Error>
+    where
+        C: Cipher,
+        F: SPFactory + PPFactory + Clone + Debug,
+{
+    let (soc, rounds) = make_weighted_window_soc(cipher, kind, window, precision)?;
+    Ok(cipher_solver(cipher, window, soc, rounds, progress, config))
+}
+
+/// Make the SoC of the related-key differential trails over the first `nr_rounds` rounds of
+/// `cipher`, see `diff_solver::related_key`. The Shards of each round are the ones of its S-boxes
+/// followed by the ones of the S-boxes of its key schedule.
+///
+/// Returns an `Error` of kind `ErrorKind::Unsupported` if the key schedule of one of the rounds
+/// isn't described or if the granularity of `cipher` isn't `Granularity::Bit`, and of kind `ErrorKind::InvalidInput` if `nr_rounds` is 0 or if the key
+/// schedule doesn't suit the sizes of the block and the key.
+pub fn make_cipher_related_key_soc<C: Cipher>(cipher: &C, nr_rounds: usize)
+                                              -> Result<(System, Vec<Vec<Id>>), Error> {
+    if cipher.granularity() != Granularity::Bit {
+        return Err(Error::new(ErrorKind::Unsupported, "Related-key SoCs are made at the bit level only"));
+    }
+    if let Some(round) = (0..nr_rounds).find(|round| cipher.key_schedule(*round).is_none()) {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("The key schedule of round {} of {} isn't described", round, cipher.name())));
+    }
+    for round in 0..nr_rounds {
+        let key_round = cipher.key_schedule(round).unwrap();
+        let (block_size, key_size) = (cipher.block_size(), cipher.key_size());
+        if key_round.round_key.row_size() != block_size || key_round.round_key.column_size() != key_size
+            || key_round.update.row_size() != key_size || key_round.update.column_size() != key_size {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("The key schedule of round {} doesn't suit a block of {} bits and a key of {} bits",
+                        round, block_size, key_size)));
+        }
+    }
+    let handler = CipherHandler::new(cipher, TrailKind::Differential);
+    make_related_key_soc(&handler, &handler, &handler, nr_rounds)
+}
+
+/// Make a `SimpleSolver` searching the related-key differential trails over the first `nr_rounds`
+/// rounds of `cipher`, the weight of a trail being its number of active S-boxes, of the state and
+/// of the key schedule alike, with the strategy `config`. `Master` holds the difference of the
+/// input followed by the one of the key, see `make_cipher_related_key_soc`.
+pub fn make_related_key_solver<C, F>(cipher: &C, nr_rounds: usize, progress: F, config: SolverConfig)
+                                     -> Result<SimpleSolver<F>, Error>
+    where
+        C: Cipher,
+        F: SPFactory + PPFactory + Clone + Debug,
+{
+    let (soc, rounds) = make_cipher_related_key_soc(cipher, nr_rounds)?;
+    // The S-boxes map as many bits as they read, so the second half of the LHSs of a Shard are
+    // the outputs of its S-box.
+    let cohorts: HashMap<Id, Vec<Vob>> = rounds.iter().flatten()
+        .map(|id| {
+            let lhs = soc.get_bdd(*id).unwrap().borrow().get_lhs();
+            let outputs = lhs[lhs.len() / 2..].to_vec();
+            (*id, outputs)
+        })
+        .collect();
+    Ok(SimpleSolver::new(soc, rounds, Id::new(0), cohorts, cipher.block_size() + cipher.key_size(), progress,
+                         config))
+}
+
+/// Make the SoC of the boomerangs over the first `nr_rounds` rounds of `cipher`, round
+/// `switch_round` being the switch, see `diff_solver::make_boomerang_soc`. The Shards of the
+/// switch are based on the `switch_table`s of its S-boxes.
+///
+/// Returns an `Error` of kind `ErrorKind::Unsupported` if the granularity of `cipher` isn't
+/// `Granularity::Bit`, and of kind `ErrorKind::InvalidInput` if an S-box of the switch round isn't
+/// bijective or if `switch_round` isn't one of the `nr_rounds` rounds.
+pub fn make_cipher_boomerang_soc<C: Cipher>(cipher: &C, switch_round: usize, nr_rounds: usize)
+                                            -> Result<(System, Vec<Vec<Id>>), Error> {
+    if cipher.granularity() != Granularity::Bit {
+        return Err(Error::new(ErrorKind::Unsupported, "Boomerang SoCs are made at the bit level only"));
+    }
+    let mut switch = Vec::new();
+    for pos in 0..cipher.num_sboxes(switch_round) {
+        let sbox = cipher.sbox(switch_round, pos);
+        let table = bct(sbox.table()).ok_or_else(|| Error::new(
+            ErrorKind::InvalidInput,
+            format!("The S-box {} of the switch round isn't bijective", pos)))?;
+        let base = BaseTable::new(switch_table(&table)).expect("The BCT of an SBox is never empty");
+        switch.push(GenericShard::new(&base, sbox.size_in(), sbox.size_out()));
+    }
+    let handler = CipherHandler::new(cipher, TrailKind::Differential);
+    make_boomerang_soc(&handler, &handler, switch_round, switch, nr_rounds)
+}
+
+/// Make a `SimpleSolver` searching the boomerangs over the first `nr_rounds` rounds of `cipher`,
+/// round `switch_round` being the switch, with the strategy `config`, see
+/// `make_cipher_boomerang_soc`. The weight of a trail is its number of active S-boxes, those of
+/// the switch included, and the one of its boomerang is given by `boomerang_weight`. The best
+/// boomerang is thus among the trails of the lowest weights, rather than necessarily the best one.
+pub fn make_boomerang_solver<C, F>(cipher: &C, switch_round: usize, nr_rounds: usize, progress: F,
+                                   config: SolverConfig) -> Result<SimpleSolver<F>, Error>
+    where
+        C: Cipher,
+        F: SPFactory + PPFactory + Clone + Debug,
+{
+    let (soc, rounds) = make_cipher_boomerang_soc(cipher, switch_round, nr_rounds)?;
+    Ok(cipher_solver(cipher, &RoundWindow::new(0..nr_rounds), soc, rounds, progress, config))
+}
+
+/// Returns the weight of the boomerang of `trail`, a trail found by the solver of
+/// `make_boomerang_solver` around round `switch_round` of `cipher`: -log2 p²q², where p and q are
+/// the probabilities of its upper and lower trails. That is twice its number of active S-boxes,
+/// those of the switch left out.
+pub fn boomerang_weight<C: Cipher>(cipher: &C, switch_round: usize, trail: &Trail) -> u32 {
+    let rounds = trail.rounds(cipher.block_size());
+    let active: usize = rounds.iter()
+        .skip(1)
+        .enumerate()
+        .filter(|(round, _)| *round != switch_round)
+        .map(|(round, output)| {
+            let mut start = 0;
+            (0..cipher.num_sboxes(round))
+                .filter(|pos| {
+                    let sbox = cipher.sbox(round, *pos);
+                    let mut bits = start..start + sbox.size_out();
+                    start += sbox.size_in();
+                    bits.any(|bit| output[bit])
+                })
+                .count()
+        })
+        .sum();
+    2 * active as u32
+}
+
+/// As `make_cipher_solver`, going through `library` (see `diff_solver::library`): a solving of the
+/// same cipher, kind, number of rounds and config stored in the library is resumed, and otherwise
+/// the SoC is loaded from the library, or made and stored in it. The solver stores its solving in
+/// the library once all Shards are joined, see `SimpleSolver::set_library`.
+///
+/// The key of the SoC is made of the name of the cipher, `nr_rounds` and `kind`, and the one of the
+/// solving adds `config`. Returns an `Error` if the library can't be read or written.
+pub fn make_cipher_solver_with_library<C, F>(cipher: &C, kind: TrailKind, nr_rounds: usize, progress: F,
+                                             config: SolverConfig, library: &Library)
+                                             -> Result<SimpleSolver<F>, Error>
+    where
+        C: Cipher,
+        F: SPFactory + PPFactory + Clone + Debug,
+{
+    let key = LibraryKey::new(&cipher.name(), nr_rounds).with_constraint(&format!("{:?}", kind));
+    let solved_key = key.clone().with_constraint(&config.to_string());
+    if let Some(solver) = library.load_solved(&solved_key, progress.clone(), config.clone())? {
+        return Ok(solver);
+    }
+    let (soc, rounds) = match library.load_soc(&key)? {
+        Some(entry) => entry,
+        None => {
+            let (soc, rounds) = make_cipher_soc(cipher, kind, nr_rounds)?;
+            library.store_soc(&key, &soc, &rounds)?;
+            (soc, rounds)
+        }
+    };
+    let mut solver = cipher_solver(cipher, &RoundWindow::new(0..nr_rounds), soc, rounds, progress, config);
+    solver.set_library(library.clone(), solved_key);
+    Ok(solver)
+}
+
+/// A `SimpleSolver` of `soc`, the SoC of `cipher` over `window` made by `make_window_soc` along
+/// with `rounds`.
+fn cipher_solver<C, F>(cipher: &C, window: &RoundWindow, soc: System, rounds: Vec<Vec<Id>>, progress: F,
+                       config: SolverConfig) -> SimpleSolver<F>
+    where
+        C: Cipher,
+        F: SPFactory + PPFactory + Clone + Debug,
+{
+    // The number of inputs and outputs of each Shard, and the size of Master, i.e. of the first
+    // input
+    let (sizes, block_size): (Vec<Vec<(usize, usize)>>, usize) = match cipher.granularity() {
+        Granularity::Bit => {
+            let sizes = rounds.iter().enumerate()
+                .map(|(r, ids)| (0..ids.len())
+                    .map(|pos| cipher.sbox(window.rounds().start + r, pos))
+                    .map(|sbox| (sbox.size_in(), sbox.size_out()))
+                    .collect())
+                .collect();
+            (sizes, cipher.block_size())
+        }
+        Granularity::Word => {
+            // The first round has a Shard per word
+            let handler = CipherHandler::for_window(cipher, TrailKind::Differential, window.clone());
+            let (_, sh) = truncate_handlers(&handler, &handler, rounds.len())
+                .expect("The SoC was made from the truncated handlers");
+            let sizes = rounds.iter().enumerate()
+                .map(|(r, ids)| (0..ids.len()).map(|pos| (sh.sbox_size_in(r, pos), sh.sbox_size_out(r, pos))).collect())
+                .collect();
+            (sizes, rounds[0].len())
+        }
+    };
+    // The cohort of a Shard is the output of its S-box, which is active iff the input is. At the
+    // word level, each output is the activity of an S-box. Weight variables, if any, follow the
+    // output and are left out, such that each of their groups counts as one more active S-box.
+    let cohorts: HashMap<Id, Vec<Vob>> = rounds.iter().enumerate()
+        .flat_map(|(r, ids)| ids.iter().enumerate().map(move |(pos, id)| (r, pos, *id)))
+        .map(|(r, pos, id)| {
+            let shard = soc.get_bdd(id).unwrap().borrow();
+            let (size_in, size_out) = sizes[r][pos];
+            let outputs = shard.get_lhs()[size_in..size_in + size_out].to_vec();
+            (id, outputs)
+        })
+        .collect();
+    SimpleSolver::new(soc, rounds, Id::new(0), cohorts, block_size, progress, config)
+}
+
+#[cfg(test)]
+mod test {
+    use crush::algebra;
+
+    use crate::code_gen::fixture::{to_vob, toy_solver, Silent, Toy, PRESENT};
+    use crate::diff_solver::SolverResult;
+
+    use super::*;
+
+    #[test]
+    fn sbox_tables() {
+        assert!(SBox::new(vec![0, 1, 2], 2, 2).is_err());
+        assert!(SBox::new(vec![0, 1, 2, 4], 2, 2).is_err());
+        let ddt = SBox::new(PRESENT.to_vec(), 4, 4).unwrap().ddt();
+        assert_eq!(16, ddt[0][0]);
+        assert!((0..16).all(|a| ddt[a].iter().sum::<usize>() == 16));
+        // The differential uniformity of PRESENT
+        assert_eq!(4, (1..16).flat_map(|a| ddt[a].iter()).max().copied().unwrap());
+    }
+
+    #[test]
+    fn encrypt_first_round() {
+        let toy = Toy;
+        // The first round of the cipher, by hand
+        let first_round = |x: usize, key: usize| {
+            let x = x ^ key;
+            let y = PRESENT[x & 0xf] | (PRESENT[x >> 4] << 4);
+            (0..8).fold(0, |z, i| z | (((y >> i) & 1) << if i == 7 { 7 } else { 2 * i % 7 }))
+        };
+        struct OneRound;
+        impl Cipher for OneRound {
+            fn name(&self) -> String { Toy.name() }
+            fn block_size(&self) -> usize { Toy.block_size() }
+            fn nr_rounds(&self) -> usize { 1 }
+            fn sbox(&self, round: usize, pos: usize) -> SBox { Toy.sbox(round, pos) }
+            fn linear_layer(&self, round: usize) -> Matrix { Toy.linear_layer(round) }
+            fn round_keys(&self, key: &Vob) -> Vec<Vob> { vec![key.clone()] }
+        }
+        for x in 0..256 {
+            assert_eq!(to_vob(first_round(x, 0x5a)), OneRound.encrypt(&to_vob(x), &to_vob(0x5a)));
+        }
+        // A pair of plaintexts differs after the rounds whatever the key
+        assert_ne!(toy.encrypt(&to_vob(1), &to_vob(3)), toy.encrypt(&to_vob(0), &to_vob(3)));
+    }
+
+    #[test]
+    fn cipher_round_window() {
+        let key = to_vob(0xa7);
+        let no_keys = (0..3).fold(RoundWindow::new(0..3).with_final_key_addition(false), |window, round| {
+            window.with_round_options(round, RoundOptions { key_addition: false, linear_layer: true })
+        });
+        let last_layer = Toy.linear_layer(2);
+        for x in 0..256 {
+            let x = to_vob(x);
+            // Two rounds, then a round appended
+            let two_rounds = Toy.encrypt_rounds(&x, &key, &RoundWindow::new(0..2).with_final_key_addition(false));
+            assert_eq!(Toy.encrypt(&x, &key), Toy.encrypt_rounds(&two_rounds, &key, &RoundWindow::new(2..3)));
+            // The round keys of the zero key are zero
+            assert_eq!(Toy.encrypt(&x, &Vob::from_elem(8, false)), Toy.encrypt_rounds(&x, &key, &no_keys));
+            let window = RoundWindow::new(0..3).with_final_key_addition(false);
+            let omitted = Toy.encrypt_rounds(&x, &key, &window.clone().omit_last_linear_layer());
+            let state: Vec<Vob> = omitted.iter().map(|bit| Vob::from_elem(1, bit)).collect();
+            let layered: Vob = apply_matrix(&last_layer, &state).iter().map(|bit| bit[0]).collect();
+            assert_eq!(Toy.encrypt_rounds(&x, &key, &window), layered);
+        }
+
+        // All the rounds of Toy are the same
+        let (soc, rounds) = make_window_soc(&Toy, TrailKind::Differential, &RoundWindow::new(1..3)).unwrap();
+        let (first, first_rounds) = make_cipher_soc(&Toy, TrailKind::Differential, 2).unwrap();
+        assert_eq!(first_rounds, rounds);
+        assert_eq!(first.get_nvar(), soc.get_nvar());
+        for id in rounds.iter().flatten() {
+            assert_eq!(first.get_bdd(*id).unwrap().borrow().get_lhs(), soc.get_bdd(*id).unwrap().borrow().get_lhs());
+        }
+        assert!(make_window_soc(&Toy, TrailKind::Differential, &RoundWindow::new(2..2)).is_err());
+
+        // Without its linear layer, the output of each S-box of the first round is the input of the
+        // same S-box of the second
+        let window = RoundWindow::new(0..2)
+            .with_round_options(0, RoundOptions { key_addition: true, linear_layer: false });
+        let (soc, rounds) = make_window_soc(&Toy, TrailKind::Differential, &window).unwrap();
+        for (first, second) in rounds[0].iter().zip(rounds[1].iter()) {
+            let outputs = soc.get_bdd(*first).unwrap().borrow().get_lhs()[4..].to_vec();
+            let inputs = soc.get_bdd(*second).unwrap().borrow().get_lhs()[..4].to_vec();
+            assert_eq!(outputs, inputs);
+        }
+        let mut solver = make_window_solver(&Toy, TrailKind::Differential, &window, Silent, SolverConfig::new()).unwrap();
+        solver.run();
+        assert!(matches!(solver.finalize(), SolverResult::ProvedOptimal { weight: 2, .. }));
+    }
+
+    #[test]
+    fn cipher_socs() {
+        let toy = Toy;
+        for kind in [TrailKind::Differential, TrailKind::Linear, TrailKind::Division].iter() {
+            let (soc, rounds) = make_cipher_soc(&toy, *kind, 2).unwrap();
+            assert_eq!(8 + 2 * 8, soc.get_nvar());
+            assert_eq!(vec![vec![Id::new(0), Id::new(1)], vec![Id::new(2), Id::new(3)]], rounds);
+        }
+        // The bit permutation is its own inverse transpose, the second round reads the output of
+        // the first through it for every kind of trail
+        let (soc, _) = make_cipher_soc(&toy, TrailKind::Linear, 2).unwrap();
+        let inputs: Vec<Vec<usize>> = soc.get_bdd(Id::new(2)).unwrap().borrow().iter_levels()
+            .take(4)
+            .map(|level| level.iter_set_lhs().collect())
+            .collect();
+        // Bits 0, 1, 2 and 3 of the state come from bits 0, 4, 1 and 5 of the S-box outputs
+        assert_eq!(vec![vec![8], vec![12], vec![9], vec![13]], inputs);
+        assert_eq!(algebra::transpose(&toy.linear_layer(0)).left_mul(&toy.linear_layer(0)),
+                   algebra::identity(8));
+    }
+
+    #[test]
+    fn cipher_solver() {
+        // A non-trivial trail has an active S-box in every round, and Toy has trails with a single
+        // one per round.
+        for kind in [TrailKind::Differential, TrailKind::Linear].iter() {
+            let mut solver = toy_solver(*kind, 3, SolverConfig::new());
+            solver.run();
+            match solver.finalize() {
+                SolverResult::ProvedOptimal { weight, .. } => assert_eq!(3, weight),
+                _ => panic!("The solving of {:?} trails wasn't complete", kind),
+            }
+        }
+    }
+
+}
@@ -0,0 +1,136 @@
+//! The fixtures shared by the tests of pathfinder: `Toy`, a small cipher whose trails are quickly
+//! solved, `Silent`, a progress factory reporting nothing, `toy_solver`, making the solver of
+//! the trails of `Toy`, and `ThisThread`, keeping the records of a test apart from the others.
+
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+
+use vob::Vob;
+
+use crush::algebra::{self, Matrix};
+use crush::reporting::{Record, Reporter};
+use crush::soc::bdd::differential::{PPFactory, StyledProgressBar};
+
+use crate::code_gen::cipher::{make_cipher_solver, Cipher, KeyRound, SBox, TrailKind};
+use crate::diff_solver::{SPFactory, SimpleSolver, SolverConfig};
+
+/// The S-box of PRESENT.
+pub(crate) const PRESENT: [usize; 16] = [0xc, 0x5, 0x6, 0xb, 0x9, 0x0, 0xa, 0xd, 0x3, 0xe, 0xf, 0x8, 0x4, 0x7, 0x1, 0x2];
+
+/// Two PRESENT S-boxes, the linear layer sending bit i to bit 2i mod 7 (bit 7 staying), and
+/// the round keys being the key rotated by the round.
+pub(crate) struct Toy;
+
+impl Cipher for Toy {
+    fn name(&self) -> String {
+        "toy".to_string()
+    }
+
+    fn block_size(&self) -> usize {
+        8
+    }
+
+    fn nr_rounds(&self) -> usize {
+        3
+    }
+
+    fn sbox(&self, _round: usize, _pos: usize) -> SBox {
+        SBox::new(PRESENT.to_vec(), 4, 4).unwrap()
+    }
+
+    fn linear_layer(&self, _round: usize) -> Matrix {
+        let mut rows = vec![Vob::from_elem(8, false); 8];
+        for i in 0..8 {
+            let to = if i == 7 { 7 } else { 2 * i % 7 };
+            rows[to].set(i, true);
+        }
+        Matrix::from_rows(rows)
+    }
+
+    fn key_size(&self) -> usize {
+        8
+    }
+
+    fn round_keys(&self, key: &Vob) -> Vec<Vob> {
+        (0..=self.nr_rounds())
+            .map(|round| (0..8).map(|i| key[(i + round) % 8]).collect())
+            .collect()
+    }
+
+    fn key_schedule(&self, _round: usize) -> Option<KeyRound> {
+        // Rotating the key state by one bit each round
+        let mut update = Matrix::new(8, 8);
+        for i in 0..8 {
+            update.set(i, (i + 1) % 8, true);
+        }
+        Some(KeyRound { round_key: algebra::identity(8), sboxes: Vec::new(), update })
+    }
+}
+
+/// The state of `Toy` of value `x`, bit i being the bit i of `x`.
+pub(crate) fn to_vob(x: usize) -> Vob {
+    (0..8).map(|i| (x >> i) & 1 == 1).collect()
+}
+
+/// A progress factory reporting nothing.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Silent;
+
+impl StyledProgressBar for Silent {
+    fn inc(&self, _delta: u64) {}
+    fn set_message(&self, _msg: &str) {}
+    fn finish_with_message(&self, _msg: &str) {}
+    fn finish_and_clear(&self) {}
+    fn println(&self, _msg: &str) {}
+}
+
+impl SPFactory for Silent {
+    type ProgressBar = Silent;
+
+    fn new_solve_progress(&self, _len: u64) -> Silent {
+        Silent
+    }
+}
+
+impl PPFactory for Silent {
+    type ProgressBar = Silent;
+
+    fn new_progress_bar(&self, _len: u64) -> Silent {
+        Silent
+    }
+}
+
+/// The solver of the trails of `kind` over the first `nr_rounds` rounds of `Toy`, with the strategy
+/// `config`, see `make_cipher_solver`.
+pub(crate) fn toy_solver(kind: TrailKind, nr_rounds: usize, config: SolverConfig) -> SimpleSolver<Silent> {
+    make_cipher_solver(&Toy, kind, nr_rounds, Silent, config).unwrap()
+}
+
+/// Held by the tests installing a reporter with `crush::reporting::set_reporter`, such that they
+/// don't replace each other's.
+pub(crate) static REPORTER_LOCK: Mutex<()> = Mutex::new(());
+
+/// A reporter sending to `reporter` only the records reported from the thread which created it,
+/// the test running a solver, and not from the solvers of the tests running concurrently.
+pub(crate) struct ThisThread {
+    thread: ThreadId,
+    reporter: Arc<dyn Reporter>,
+}
+
+impl ThisThread {
+    pub(crate) fn new(reporter: Arc<dyn Reporter>) -> ThisThread {
+        ThisThread { thread: thread::current().id(), reporter }
+    }
+}
+
+impl Reporter for ThisThread {
+    fn report(&self, record: &Record) {
+        if thread::current().id() == self.thread {
+            self.reporter.report(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.reporter.flush();
+    }
+}
@@ -16,6 +16,8 @@ use crate::diff_solver::post_processing_v5::BaseTable;
 pub struct GenericShard {
     size_in: usize,
     size_out: usize,
+    /// The level of each in bit, followed by the level of each out bit.
+    levels: Vec<usize>,
     shard: Shard,
 }
 
@@ -25,10 +27,38 @@ impl GenericShard {
         Self {
             size_in,
             size_out,
+            levels: (0..size_in + size_out).collect(),
             shard: Self::make_generic_shard(table, size_in, size_out),
         }
     }
 
+    /// A generic shard from a `shard` whose levels aren't ordered as the in bits followed by the
+    /// out bits, `levels` giving the level of each in bit followed by the level of each out bit.
+    /// This allows for shards which would be too wide with the in bits first, such as the one of
+    /// a modular addition (see `code_gen::arx`).
+    pub fn with_levels(shard: Shard, size_in: usize, size_out: usize, levels: Vec<usize>) -> Self {
+        assert_eq!(size_in + size_out, levels.len());
+        assert_eq!(levels.len() + 1, shard.get_levels_size());
+        Self {
+            size_in,
+            size_out,
+            levels,
+            shard,
+        }
+    }
+
+    /// A generic shard accepting exactly the (in, out) pairs of `transitions`, ordered as by
+    /// `transitions`. This allows for tables too wide to be written out as a `BaseTable`, such as
+    /// the ones with weight variables (see `code_gen::sbox::weighted_generic_shard`).
+    pub fn from_transitions(transitions: &[(usize, usize)], size_in: usize, size_out: usize) -> Self {
+        Self {
+            size_in,
+            size_out,
+            levels: (0..size_in + size_out).collect(),
+            shard: Self::make_shard(transitions.iter().copied(), size_in, size_out),
+        }
+    }
+
     #[inline]
     pub fn size_in(&self) -> usize {
         self.size_in
@@ -39,6 +69,34 @@ impl GenericShard {
         self.size_out
     }
 
+    /// All the (in, out) pairs with a path in the Shard. Bit i of in (out) is the value of the
+    /// i'th in (out) bit, i.e. the same ordering as the rows (columns) of the BaseTable.
+    pub fn transitions(&self) -> Vec<(usize, usize)> {
+        let sink_depth = self.size_in + self.size_out;
+        let mut res = Vec::new();
+        let mut stack: Vec<(usize, Id, usize)> = self.shard.level(0).unwrap()
+            .iter_nodes()
+            .map(|(id, _)| (0, *id, 0))
+            .collect();
+
+        while let Some((depth, id, path)) = stack.pop() {
+            if depth == sink_depth {
+                let bits = self.levels.iter().enumerate()
+                    .fold(0, |bits, (i, level)| bits | (((path >> level) & 1) << i));
+                res.push((bits & ((1 << self.size_in) - 1), bits >> self.size_in));
+                continue;
+            }
+            let node = self.shard.level(depth).unwrap().get_node(&id).unwrap();
+            if let Some(e0) = node.get_e0() {
+                stack.push((depth + 1, e0, path));
+            }
+            if let Some(e1) = node.get_e1() {
+                stack.push((depth + 1, e1, path | (1 << depth)));
+            }
+        }
+        res
+    }
+
     // Note, caller must ensure that all LHSs are of valid (and equal) length.
     #[inline]
     pub fn into_specific<T>(mut self, in_lhss: &mut T, out_lhss: &mut T, id: Id) -> Shard
@@ -48,12 +106,12 @@ impl GenericShard {
         self.shard.set_id(id);
         // Update the LHSs for in bits
         for i in 0..self.size_in {
-            self.shard.set_lhs_level_from_vob(i, in_lhss.next().unwrap());
+            self.shard.set_lhs_level_from_vob(self.levels[i], in_lhss.next().unwrap());
         }
 
         // Update the LHSs for out bits
         for i in self.size_in..self.size_in+self.size_out {
-            self.shard.set_lhs_level_from_vob(i, out_lhss.next().unwrap());
+            self.shard.set_lhs_level_from_vob(self.levels[i], out_lhss.next().unwrap());
         }
 
         self.shard
@@ -62,6 +120,19 @@ impl GenericShard {
 
 
     fn make_generic_shard(table: &BaseTable, size_in: usize, size_out: usize) -> Shard {
+        // An in-value can yield an out-value if the entry at table[row idx][col idx] is non-zero
+        let transitions = (0..table.nr_of_rows())
+            .flat_map(|row_idx| table.row(row_idx).unwrap().iter().enumerate()
+                .filter(|(_, entry)| **entry != 0)
+                .map(move |(col_idx, _)| (row_idx, col_idx)));
+        Self::make_shard(transitions, size_in, size_out)
+    }
+
+    /// The shard whose paths are the (in, out) pairs of `transitions`.
+    fn make_shard<T>(transitions: T, size_in: usize, size_out: usize) -> Shard
+        where
+            T: Iterator<Item = (usize, usize)>,
+    {
         // === A note on the "nodes", and how they work: ===
         // A node here is based on the NodeSpec struct from crush::utils, which roughly is
         // (my_id; e0_id, e1_id). e0 (e1) is the child node at the end of the 0-(1-)edge. A '0' represents
@@ -110,8 +181,6 @@ impl GenericShard {
         // =========== Fill Remaining Levels ====================
         // Row index yields the in-path, which should now be present in the node_arena
         // Column index yields the out-path, and needs to be connected to the corresponding in-path.
-        // As per usual for a BaseTable (DDT, LAT, etc), an in-path can yield an out-path if the entry
-        // at table[row idx][col idx] is non-zero.
 
         // The end node of an in-path will be the start node of an corresponding out-path.
         // See notes on fn rev() for the idea of how to find the "start node" for the output path.
@@ -121,120 +190,109 @@ impl GenericShard {
         let sink_depth = size_in + size_out;
 
 
-        for row_idx in 0..table.nr_of_rows() {
-            let row = table.row(row_idx).unwrap();
-
-            for col_idx in 0..row.len() {
-                let is_connected = row.get(col_idx).unwrap();
-
-                // The entry is 0, in-value CANNOT yield out-value => continue
-                if *is_connected == 0 {
-                    continue;
-                }
-
-                // OBS, remember that lsb is rightmost now.
-                // (I skip using rev() so that I don't have to deal with the surplus leading 0's).
-                let out_path = format!("{:0>w$b}", col_idx, w = size_out);
-
-                let mut child_depth = size_in + 1; // FIXME off by one?
-
-                // Path to walk
-                let mut edges = out_path.chars().rev();
-                // Start node
-                let mut parent_id = offset + Self::rev_nr_bits(row_idx, size_in);
-                let mut parent_node = node_arena.get_mut(&parent_id).unwrap();
-
-                // The current edge will be used in several places
-                let mut current_edge = edges.next().unwrap()
+        for (row_idx, col_idx) in transitions {
+            // OBS, remember that lsb is rightmost now.
+            // (I skip using rev() so that I don't have to deal with the surplus leading 0's).
+            let out_path = format!("{:0>w$b}", col_idx, w = size_out);
+
+            let mut child_depth = size_in + 1; // FIXME off by one?
+
+            // Path to walk
+            let mut edges = out_path.chars().rev();
+            // Start node
+            let mut parent_id = offset + Self::rev_nr_bits(row_idx, size_in);
+            let mut parent_node = node_arena.get_mut(&parent_id).unwrap();
+
+            // The current edge will be used in several places
+            let mut current_edge = edges.next().unwrap()
+                .to_digit(2).unwrap();
+
+            // Get child of start node
+            let mut child_id = match current_edge {
+                0 => parent_node.0,
+                1 => parent_node.1,
+                _ => panic!("Somehow a radix 2 became non binary"),
+            };
+
+
+            // Walk the existing path for as long as it exists
+            while child_id != 0 {
+                // Update child depth
+                child_depth += 1;
+                // Update parent
+                parent_id = child_id;
+                parent_node = node_arena.get_mut(&parent_id).unwrap();
+                // Update edge
+                current_edge = edges.next()
+                    // Shouldn't be able to walk the path all the way to sink!
+                    .expect("Did we unexpectedly reach/pass the sink node?")
                     .to_digit(2).unwrap();
-
-                // Get child of start node
-                let mut child_id = match current_edge {
+                // Update child
+                child_id = match current_edge {
                     0 => parent_node.0,
                     1 => parent_node.1,
                     _ => panic!("Somehow a radix 2 became non binary"),
                 };
+            }
 
+            // ======= End of existing path =======
 
-                // Walk the existing path for as long as it exists
-                while child_id != 0 {
-                    // Update child depth
-                    child_depth += 1;
-                    // Update parent
-                    parent_id = child_id;
-                    parent_node = node_arena.get_mut(&parent_id).unwrap();
-                    // Update edge
-                    current_edge = edges.next()
-                        // Shouldn't be able to walk the path all the way to sink!
-                        .expect("Did we unexpectedly reach/pass the sink node?")
-                        .to_digit(2).unwrap();
-                    // Update child
-                    child_id = match current_edge {
-                        0 => parent_node.0,
-                        1 => parent_node.1,
-                        _ => panic!("Somehow a radix 2 became non binary"),
-                    };
-                }
-
-                // ======= End of existing path =======
-
-                // Building the remainder of the path:
-                // Current state: parent id != 0, but the child along current_edge is 0 => child_id = 0;
-                // => parent_node.current_edge = 0 => Need to make next child.
-
-                if child_depth == sink_depth {
-                    match current_edge {
-                        0 => parent_node.0 = sink_id,
-                        1 => parent_node.1 = sink_id,
-                        _ => panic!("Somehow a radix 2 became non binary"),
-                    };
-                    continue;
-                }
+            // Building the remainder of the path:
+            // Current state: parent id != 0, but the child along current_edge is 0 => child_id = 0;
+            // => parent_node.current_edge = 0 => Need to make next child.
 
-
-                // Insert id of next child into parent
+            if child_depth == sink_depth {
                 match current_edge {
-                    0 => parent_node.0 = next_child,
-                    1 => parent_node.1 = next_child,
+                    0 => parent_node.0 = sink_id,
+                    1 => parent_node.1 = sink_id,
                     _ => panic!("Somehow a radix 2 became non binary"),
                 };
+                continue;
+            }
+
 
-                // Make the rest
-                loop {
-                    match edges.next() {
-                        // We have another edge to create a child node at the end of:
-                        Some(e) => {
-                            // Update current edge
-                            current_edge = e.to_digit(2).unwrap();
-
-                            // "Make" the child
-                            match current_edge {
-                                0 => {
-                                    node_arena.insert(next_child, (next_child + 1, 0));
-                                    let lvel = level_arena.entry(child_depth).or_insert(Vec::new());
-                                    lvel.push(next_child);
-                                },
-                                1 => {
-                                    node_arena.insert(next_child, (0, next_child + 1));
-                                    let lvel = level_arena.entry(child_depth).or_insert(Vec::new());
-                                    lvel.push(next_child);
-                                },
-                                _ => panic!("Somehow a radix 2 became non binary"),
-                            };
-                            child_depth += 1;
-                            next_child += 1;
-                        },
-                        None => {
-                            // Last parent points to a non-existing child when it should point to sink
-                            // Connect parent to sink instead:
-                            let parent = node_arena.get_mut(&(next_child - 1)).unwrap();
-                            match current_edge {
-                                0 => parent.0 = sink_id,
-                                1 => parent.1 = sink_id,
-                                _ => panic!("Somehow a radix 2 became non binary"),
-                            };
-                            break;
-                        }
+            // Insert id of next child into parent
+            match current_edge {
+                0 => parent_node.0 = next_child,
+                1 => parent_node.1 = next_child,
+                _ => panic!("Somehow a radix 2 became non binary"),
+            };
+
+            // Make the rest
+            loop {
+                match edges.next() {
+                    // We have another edge to create a child node at the end of:
+                    Some(e) => {
+                        // Update current edge
+                        current_edge = e.to_digit(2).unwrap();
+
+                        // "Make" the child
+                        match current_edge {
+                            0 => {
+                                node_arena.insert(next_child, (next_child + 1, 0));
+                                let lvel = level_arena.entry(child_depth).or_insert(Vec::new());
+                                lvel.push(next_child);
+                            },
+                            1 => {
+                                node_arena.insert(next_child, (0, next_child + 1));
+                                let lvel = level_arena.entry(child_depth).or_insert(Vec::new());
+                                lvel.push(next_child);
+                            },
+                            _ => panic!("Somehow a radix 2 became non binary"),
+                        };
+                        child_depth += 1;
+                        next_child += 1;
+                    },
+                    None => {
+                        // Last parent points to a non-existing child when it should point to sink
+                        // Connect parent to sink instead:
+                        let parent = node_arena.get_mut(&(next_child - 1)).unwrap();
+                        match current_edge {
+                            0 => parent.0 = sink_id,
+                            1 => parent.1 = sink_id,
+                            _ => panic!("Somehow a radix 2 became non binary"),
+                        };
+                        break;
                     }
                 }
             }
@@ -302,36 +360,34 @@ impl GenericShard {
 mod test {
     use std::convert::TryFrom;
 
-    use crush::soc::{utils};
-
     use crate::ciphers::prince;
 
-    use super::BaseTable;
+    use super::{BaseTable, GenericShard};
+
+    /// Return the (in, out) pairs of the non zero entries of `ddt`.
+    fn transitions(ddt: &[Vec<usize>]) -> Vec<(usize, usize)> {
+        let mut transitions: Vec<(usize, usize)> = ddt.iter().enumerate()
+            .flat_map(|(a, row)| row.iter().enumerate().filter(|(_, n)| **n > 0).map(move |(b, _)| (a, b)))
+            .collect();
+        transitions.sort_unstable();
+        transitions
+    }
 
     #[test]
     fn test_generic_generator_using_prince() {
         let bt = BaseTable::try_from(prince::ddt_raw()).unwrap();
-        let actual = super::GenericShard::make_generic_shard(&bt, 4, 4);
-
-        let path_to_expected = &["SoCs", "DDTprinceS_generic.bdd"].iter().collect();
-        let sys_spec = utils::parse_system_spec_from_file(&path_to_expected);
-        let mut soc = utils::build_system_from_spec(sys_spec);
-        let expected = soc.drain_bdds().next().unwrap().1.into_inner();
-
-        assert_eq!(actual, expected);
+        let mut actual = GenericShard::new(&bt, 4, 4).transitions();
+        actual.sort_unstable();
+        assert_eq!(transitions(&prince::ddt_raw()), actual);
     }
 
     #[test]
     fn test_generic_generator_using_prince_inv() {
         let bt = BaseTable::try_from(prince::ddt_inverse_raw()).unwrap();
-        let actual = super::GenericShard::make_generic_shard(&bt, 4, 4);
-
-        let path_to_expected = &["SoCs", "DDTprinceSinv_generic.bdd"].iter().collect();
-        let sys_spec = utils::parse_system_spec_from_file(&path_to_expected);
-        let mut soc = utils::build_system_from_spec(sys_spec);
-        let expected = soc.drain_bdds().next().unwrap().1.into_inner();
-
-        assert_eq!(actual, expected);
+        let mut actual = GenericShard::new(&bt, 4, 4).transitions();
+        actual.sort_unstable();
+        let mut transposed: Vec<(usize, usize)> = transitions(&prince::ddt_raw()).into_iter().map(|(a, b)| (b, a)).collect();
+        transposed.sort_unstable();
+        assert_eq!(transposed, actual);
     }
 }
-
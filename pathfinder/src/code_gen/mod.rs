@@ -1,8 +1,17 @@
 use vob::Vob;
+use crush::algebra::Matrix;
 use crate::code_gen::gsf::GenericShard;
 
+pub mod arx;
+pub mod backend;
+pub mod cipher;
+#[cfg(test)]
+pub(crate) mod fixture;
+pub mod sbox;
 pub mod soc_gen;
 pub mod gsf;
+pub mod truncated;
+pub mod trail_table;
 
 
 pub trait SBoxHandler {
@@ -17,6 +26,13 @@ pub trait SBoxHandler {
     // itself shouldn't behave any different than any other generic shard, it helps remind us of the
     // context we're in. (We don't want something that isn't based on a base table ;) ).
     fn bt_generic_shard(&self, round: usize, pos: usize) -> GenericShard;
+
+    /// The number of weight variables following the out bits of the S-box at `pos` in its Shard,
+    /// see `sbox::weighted_generic_shard`. They are fresh variables, which aren't part of the state
+    /// going through the linear layer. Defaults to none.
+    fn sbox_weight_size(&self, _round: usize, _pos: usize) -> usize {
+        0
+    }
 }
 
 /// LinearLayerHandler
@@ -29,4 +45,25 @@ pub trait LLHandler {
     // non-linear layer call ever becomes needed.
     fn apply_linear_layer(&self, round: usize, state: Vec<Vob>) -> Vec<Vob>;
 
+    /// Transformation of the initial state into the in block of the first non-linear layer.
+    /// Defaults to the identity, but a Feistel cipher may f.ex. use it to expand one half of the
+    /// state, while keeping both halves around for the next round.
+    fn apply_initial_layer(&self, state: Vec<Vob>) -> Vec<Vob> {
+        state
+    }
+}
+
+/// Apply the linear map given by `matrix`, where row i gives the bits XORed into bit i, to a state
+/// of lhs.
+pub fn apply_matrix(matrix: &Matrix, state: &[Vob]) -> Vec<Vob> {
+    matrix.iter_rows()
+        .map(|row| {
+            let mut out = Vob::from_elem(state[0].len(), false);
+            for j in row.iter_set_bits(..) {
+                out.xor(&state[j]);
+            }
+            out
+        })
+        .collect()
 }
+pub mod sandwich;
@@ -0,0 +1,298 @@
+//! The search of boomerang distinguishers of a `Cipher` from solved trails.
+//!
+//! A boomerang, in the sandwich framework, splits the cipher in an upper part, a middle of a single
+//! switch round and a lower part. The upper and lower trails are searched separately, e.g. with
+//! `make_window_solver` over `Sandwich::upper_window` and `Sandwich::lower_window`, and `Sandwich`
+//! then connects them: an upper trail ends with the difference Δ into the S-boxes of the switch
+//! round, a lower trail starts from the difference ∇ out of these S-boxes (before the linear layer
+//! of the switch round), and the two are compatible if every S-box of the switch round connects its
+//! part of Δ to its part of ∇.
+//!
+//! The compatibility is checked on the SoC of the switch round, whose Shards are based on the BCT
+//! (see `diff_solver::make_boomerang_soc`), by checking that each Shard accepts Δ and ∇. The
+//! probability of a compatible pair of trails of probabilities p and q is estimated as p²q²r, where
+//! r is the product of the entries of the BCT connecting them, each divided by the size of its
+//! S-box. The weights of the `BoomerangCandidate`s are the -log2 of these probabilities.
+
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
+use vob::Vob;
+
+use crush::algebra::{self, Matrix};
+use crush::soc::Id;
+use crush::soc::system::System;
+
+use crate::code_gen::cipher::{Cipher, CipherHandler, RoundWindow, TrailKind};
+use crate::code_gen::gsf::GenericShard;
+use crate::code_gen::trail_table::{hex, trail_rounds, TrailTable};
+use crate::diff_solver::{bct, make_boomerang_soc};
+use crate::diff_solver::post_processing_v5::{BaseTable, Trail};
+
+/// A boomerang distinguisher made of an upper and a lower trail connected by the switch round,
+/// see the module documentation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoomerangCandidate {
+    /// The position of the upper trail among the upper trails given to `Sandwich::connect`.
+    pub upper: usize,
+    /// The position of the lower trail among the lower trails given to `Sandwich::connect`.
+    pub lower: usize,
+    /// The difference into the S-boxes of the switch round, Δ.
+    pub switch_input: Vob,
+    /// The difference out of the S-boxes of the switch round, ∇.
+    pub switch_output: Vob,
+    /// The weight of the upper trail, -log2 p.
+    pub upper_weight: f64,
+    /// The weight of the lower trail, -log2 q.
+    pub lower_weight: f64,
+    /// The weight of the switch, -log2 r.
+    pub switch_weight: f64,
+}
+
+impl BoomerangCandidate {
+    /// The weight of the boomerang, -log2 p²q²r.
+    pub fn weight(&self) -> f64 {
+        2.0 * self.upper_weight + 2.0 * self.lower_weight + self.switch_weight
+    }
+
+    /// The estimated probability of the boomerang, p²q²r.
+    pub fn probability(&self) -> f64 {
+        2_f64.powf(-self.weight())
+    }
+}
+
+impl fmt::Display for BoomerangCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "upper {}, lower {}: 0x{} -> 0x{}, weight 2 * {:.2} + 2 * {:.2} + {:.2} = {:.2}",
+               self.upper,
+               self.lower,
+               hex(&self.switch_input, self.switch_input.len()),
+               hex(&self.switch_output, self.switch_output.len()),
+               self.upper_weight,
+               self.lower_weight,
+               self.switch_weight,
+               self.weight())
+    }
+}
+
+/// The connection of the upper and lower trails of `cipher` around a switch round, see the module
+/// documentation.
+pub struct Sandwich<'a, C: Cipher> {
+    cipher: &'a C,
+    switch_round: usize,
+    /// The SoC of the switch round, whose first `block_size` variables are Δ followed by the
+    /// outputs of its S-boxes.
+    soc: System,
+    ids: Vec<Id>,
+    bcts: Vec<Vec<Vec<usize>>>,
+    /// The inverse of the linear layer of the switch round.
+    inverse: Matrix,
+}
+
+impl<'a, C: Cipher> Sandwich<'a, C> {
+    /// The sandwich of `cipher` around round `switch_round`.
+    ///
+    /// Returns an `Error` of kind `ErrorKind::InvalidInput` if there isn't a round before and
+    /// after the switch round, if an S-box of the switch round isn't bijective or if its linear
+    /// layer isn't invertible.
+    pub fn new(cipher: &'a C, switch_round: usize) -> Result<Self, Error> {
+        if switch_round == 0 || switch_round + 1 >= cipher.nr_rounds() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Expected a switch round between the first and the last of {} rounds, found {}",
+                        cipher.nr_rounds(), switch_round)));
+        }
+        let mut bcts = Vec::new();
+        let mut switch = Vec::new();
+        for pos in 0..cipher.num_sboxes(switch_round) {
+            let sbox = cipher.sbox(switch_round, pos);
+            let table = bct(sbox.table()).ok_or_else(|| Error::new(
+                ErrorKind::InvalidInput,
+                format!("The S-box {} of the switch round isn't bijective", pos)))?;
+            let base = BaseTable::new(table.clone()).expect("The BCT of an SBox is never empty");
+            switch.push(GenericShard::new(&base, sbox.size_in(), sbox.size_out()));
+            bcts.push(table);
+        }
+        let inverse = algebra::inverse(&cipher.linear_layer(switch_round)).ok_or_else(|| Error::new(
+            ErrorKind::InvalidInput,
+            "The linear layer of the switch round isn't invertible"))?;
+        let window = RoundWindow::new(switch_round..switch_round + 1);
+        let handler = CipherHandler::for_window(cipher, TrailKind::Differential, window);
+        let (soc, mut rounds) = make_boomerang_soc(&handler, &handler, 0, switch, 1)?;
+        Ok(Sandwich { cipher, switch_round, soc, ids: rounds.remove(0), bcts, inverse })
+    }
+
+    #[inline]
+    pub fn switch_round(&self) -> usize {
+        self.switch_round
+    }
+
+    /// The window of the upper trails of `nr_rounds` rounds, ending with the round before the
+    /// switch round.
+    pub fn upper_window(&self, nr_rounds: usize) -> RoundWindow {
+        RoundWindow::new(self.switch_round.saturating_sub(nr_rounds)..self.switch_round)
+    }
+
+    /// The window of the lower trails of `nr_rounds` rounds, starting with the round after the
+    /// switch round.
+    pub fn lower_window(&self, nr_rounds: usize) -> RoundWindow {
+        RoundWindow::new(self.switch_round + 1..self.switch_round + 1 + nr_rounds)
+    }
+
+    /// Connect each trail of `upper`, differential trails over an `upper_window`, to each trail of
+    /// `lower`, differential trails over a `lower_window`, as extracted by
+    /// `post_processing_v5::extract_best_k_trails`. Returns the compatible pairs as
+    /// `BoomerangCandidate`s by increasing weight.
+    ///
+    /// Returns an `Error` of kind `ErrorKind::InvalidInput` if a trail isn't a trail of such a
+    /// window.
+    pub fn connect(&self, upper: &[Trail], lower: &[Trail]) -> Result<Vec<BoomerangCandidate>, Error> {
+        let upper = upper.iter()
+            .map(|trail| self.switch_input(trail))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let lower = lower.iter()
+            .map(|trail| self.switch_output(trail))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut candidates = Vec::new();
+        for (u, (input, upper_weight)) in upper.iter().enumerate() {
+            for (l, (output, lower_weight)) in lower.iter().enumerate() {
+                if let Some(switch_weight) = self.switch_weight(input, output)? {
+                    candidates.push(BoomerangCandidate {
+                        upper: u,
+                        lower: l,
+                        switch_input: input.clone(),
+                        switch_output: output.clone(),
+                        upper_weight: *upper_weight,
+                        lower_weight: *lower_weight,
+                        switch_weight,
+                    });
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.weight().partial_cmp(&b.weight()).unwrap());
+        Ok(candidates)
+    }
+
+    /// Δ, the difference out of the upper trail `trail` through the linear layer of its last
+    /// round, and the weight of the trail.
+    fn switch_input(&self, trail: &Trail) -> Result<(Vob, f64), Error> {
+        let nr_rounds = trail_rounds(self.cipher, trail)?;
+        if nr_rounds > self.switch_round {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Expected an upper trail of at most {} rounds, found {}", self.switch_round, nr_rounds)));
+        }
+        let window = self.upper_window(nr_rounds);
+        let table = TrailTable::for_window(self.cipher, TrailKind::Differential, &window, trail)?;
+        let last = table.rounds().last().unwrap();
+        let input = self.cipher.linear_layer(self.switch_round - 1).mul_vob(&last.output);
+        Ok((input, table.weight().unwrap()))
+    }
+
+    /// ∇, the difference into the lower trail `trail` through the inverse of the linear layer of
+    /// the switch round, and the weight of the trail.
+    fn switch_output(&self, trail: &Trail) -> Result<(Vob, f64), Error> {
+        let nr_rounds = trail_rounds(self.cipher, trail)?;
+        let window = self.lower_window(nr_rounds);
+        let table = TrailTable::for_window(self.cipher, TrailKind::Differential, &window, trail)?;
+        let first = &table.rounds()[0];
+        Ok((self.inverse.mul_vob(&first.input), table.weight().unwrap()))
+    }
+
+    /// The weight of the switch from `input` to `output`, or `None` if the switch round doesn't
+    /// connect them.
+    fn switch_weight(&self, input: &Vob, output: &Vob) -> Result<Option<f64>, Error> {
+        // The variables of the SoC are the input, then the outputs of the S-boxes, in order. The
+        // bits after the last S-box are left unchanged.
+        let sbox_inputs = self.cipher.sbox_inputs(self.switch_round);
+        let covered = sbox_inputs.last().map_or(0, |bits| bits.end);
+        if (covered..input.len()).any(|bit| input[bit] != output[bit]) {
+            return Ok(None);
+        }
+        let mut assignment = input.clone();
+        for bits in sbox_inputs.iter() {
+            assignment.extend(output.iter().skip(bits.start).take(bits.len()));
+        }
+        for id in self.ids.iter() {
+            if !self.soc.get_bdd(*id)?.borrow().accepts(&assignment) {
+                return Ok(None);
+            }
+        }
+
+        let mut weight = 0.0;
+        for ((pos, bits), bct) in sbox_inputs.iter().enumerate().zip(self.bcts.iter()) {
+            let value = |state: &Vob| bits.clone().filter(|bit| state[*bit]).fold(0, |x, bit| x | (1 << (bit - bits.start)));
+            let size = self.cipher.sbox(self.switch_round, pos).table().len() as f64;
+            weight -= (bct[value(input)][value(output)] as f64 / size).log2();
+        }
+        Ok(Some(weight))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::code_gen::cipher::{make_window_solver, SBox};
+    use crate::code_gen::fixture::{Silent, Toy, PRESENT};
+    use crate::diff_solver::post_processing_v5::extract_best_k_trails;
+    use crate::diff_solver::SolverConfig;
+
+    use super::*;
+
+    #[test]
+    fn sandwich() {
+        let sandwich = Sandwich::new(&Toy, 1).unwrap();
+        let trails = |window: RoundWindow| {
+            let mut solver = make_window_solver(&Toy, TrailKind::Differential, &window, Silent, SolverConfig::new())
+                .unwrap();
+            solver.run();
+            extract_best_k_trails(solver.finalize().run(), 8).unwrap()
+        };
+        let upper = trails(sandwich.upper_window(1));
+        let lower = trails(sandwich.lower_window(1));
+        let candidates = sandwich.connect(&upper, &lower).unwrap();
+        assert!(!candidates.is_empty());
+
+        // By hand: Δ is the output of the upper trail through the linear layer, and ∇ the input of
+        // the lower trail through its inverse
+        let to = |i: usize| if i == 7 { 7 } else { 2 * i % 7 };
+        let linear_layer = |y: usize| (0..8).fold(0, |z, i| z | (((y >> i) & 1) << to(i)));
+        let inverse = |z: usize| (0..8).fold(0, |y, i| y | (((z >> to(i)) & 1) << i));
+        let from_vob = |vob: &Vob| vob.iter_set_bits(..).fold(0, |x, i| x | (1 << i));
+        let ddt = SBox::new(PRESENT.to_vec(), 4, 4).unwrap().ddt();
+        let bct = bct(&PRESENT).unwrap();
+        let weight = |table: &Vec<Vec<usize>>, a: usize, b: usize| {
+            -(table[a & 0xf][b & 0xf] as f64 / 16.0).log2() - (table[a >> 4][b >> 4] as f64 / 16.0).log2()
+        };
+        let mut expected = Vec::new();
+        for (u, upper) in upper.iter().enumerate() {
+            let rounds = upper.rounds(8);
+            let (a, b) = (from_vob(&rounds[0]), from_vob(&rounds[1]));
+            let delta = linear_layer(b);
+            for (l, lower) in lower.iter().enumerate() {
+                let rounds = lower.rounds(8);
+                let (c, d) = (from_vob(&rounds[0]), from_vob(&rounds[1]));
+                let nabla = inverse(c);
+                if bct[delta & 0xf][nabla & 0xf] > 0 && bct[delta >> 4][nabla >> 4] > 0 {
+                    expected.push((u, l, delta, nabla, 2.0 * weight(&ddt, a, b) + 2.0 * weight(&ddt, c, d)
+                        + weight(&bct, delta, nabla)));
+                }
+            }
+        }
+        assert_eq!(expected.len(), candidates.len());
+        for (u, l, delta, nabla, weight) in expected {
+            let candidate = candidates.iter().find(|candidate| candidate.upper == u && candidate.lower == l).unwrap();
+            assert_eq!(delta, from_vob(&candidate.switch_input));
+            assert_eq!(nabla, from_vob(&candidate.switch_output));
+            assert!((weight - candidate.weight()).abs() < 1e-9);
+        }
+        assert!(candidates.windows(2).all(|pair| pair[0].weight() <= pair[1].weight()));
+        assert!(candidates[0].probability() > 0.0);
+        assert!(candidates[0].to_string().starts_with("upper"));
+
+        // The switch needs rounds around it, and the upper trails must end before it
+        assert!(Sandwich::new(&Toy, 0).is_err());
+        assert!(Sandwich::new(&Toy, 2).is_err());
+        assert!(sandwich.connect(&trails(RoundWindow::new(0..2)), &lower).is_err());
+    }
+}
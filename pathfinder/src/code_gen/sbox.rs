@@ -0,0 +1,268 @@
+//! The analysis of an `SBox` from its lookup table, giving everything the Shards of its S-box
+//! layer are made of, such that no Shard is encoded by hand.
+//!
+//! For the trails of a `TrailKind`, `table` gives the table of the S-box (its DDT, LAT or division
+//! trail table), `transitions` its possible transitions along with their weights, `generic_shard`
+//! the `GenericShard` of the S-box and `bdd_spec` the `BddSpec` of that Shard, whose variables are
+//! the input bits of the S-box followed by its output bits, from the least significant ones.
+//! `CipherHandler` makes the Shards of the S-boxes of a `Cipher` with `generic_shard`.
+//!
+//! The solvers count the weight of a trail as its number of active S-boxes. `weighted_generic_shard`
+//! instead follows the out bits of the Shard with weight variables encoding the weight of each
+//! transition, rounded to a number of units of a given precision, such that the solvers count the
+//! weights of the transitions: the weight variables come in groups of the size of the output of the
+//! S-box, the output of an active S-box counting for its first unit and each further unit setting
+//! the first variable of one more group. An active transition thus weighs at least one unit.
+
+use std::io::{Error, ErrorKind};
+
+use vob::Vob;
+
+use crush::soc::Id;
+use crush::soc::utils::BddSpec;
+
+use crate::code_gen::cipher::{SBox, TrailKind};
+use crate::code_gen::gsf::GenericShard;
+use crate::diff_solver::{division_table, lat};
+use crate::diff_solver::post_processing_v5::BaseTable;
+
+/// A transition of an S-box, from an input difference (or mask, or division property) to an
+/// output one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub input: usize,
+    pub output: usize,
+    /// The entry of the table of the S-box at (input, output).
+    pub entry: usize,
+    /// The weight of the transition, see `weight`.
+    pub weight: Option<f64>,
+}
+
+/// The table of `sbox` for the trails of `kind`: its DDT, its LAT in absolute values (see
+/// `diff_solver::lat`) or its division trail table.
+pub fn table(sbox: &SBox, kind: TrailKind) -> Vec<Vec<usize>> {
+    match kind {
+        TrailKind::Differential => sbox.ddt(),
+        TrailKind::Linear => lat(sbox.table(), sbox.size_in(), sbox.size_out()),
+        TrailKind::Division => division_table(sbox.table(), sbox.size_in(), sbox.size_out()),
+    }
+}
+
+/// The weight of a transition of `sbox` for the trails of `kind` whose entry in the table is
+/// `entry`: -log2 of its probability for a differential, or of its absolute correlation for a
+/// linear approximation. Division trails have no weight.
+pub fn weight(sbox: &SBox, kind: TrailKind, entry: usize) -> Option<f64> {
+    let size_in = sbox.size_in() as i32;
+    match kind {
+        TrailKind::Differential => Some(-(entry as f64 / 2_f64.powi(size_in)).log2()),
+        TrailKind::Linear => Some(-(entry as f64 / 2_f64.powi(size_in - 1)).log2()),
+        TrailKind::Division => None,
+    }
+}
+
+/// The possible transitions of `sbox` for the trails of `kind`, i.e. the non-zero entries of its
+/// table, by increasing input and then output.
+pub fn transitions(sbox: &SBox, kind: TrailKind) -> Vec<Transition> {
+    table(sbox, kind).into_iter().enumerate()
+        .flat_map(|(input, row)| row.into_iter().enumerate()
+            .filter(|(_, entry)| *entry != 0)
+            .map(move |(output, entry)| (input, output, entry)))
+        .map(|(input, output, entry)| Transition { input, output, entry, weight: weight(sbox, kind, entry) })
+        .collect()
+}
+
+/// The `GenericShard` of `sbox` for the trails of `kind`, based on its table.
+pub fn generic_shard(sbox: &SBox, kind: TrailKind) -> GenericShard {
+    let table = BaseTable::new(table(sbox, kind)).expect("The table of an SBox is never empty");
+    GenericShard::new(&table, sbox.size_in(), sbox.size_out())
+}
+
+/// The number of weight variables of the Shard of `sbox` for the trails of `kind` made by
+/// `weighted_generic_shard` with `precision`.
+///
+/// Returns an `Error` as `weighted_generic_shard` does.
+pub fn weight_size(sbox: &SBox, kind: TrailKind, precision: f64) -> Result<usize, Error> {
+    let groups = weight_groups(sbox, kind, precision)?;
+    Ok(groups_size(sbox, &groups))
+}
+
+/// The `GenericShard` of `sbox` for the trails of `kind`, whose out bits are followed by the
+/// weight variables of each transition, its weight being counted in units of `precision`, see the
+/// module documentation. The weight of a trail found by a solver is then the number of units of its
+/// transitions, each rounded to the nearest.
+///
+/// Returns an `Error` of kind `ErrorKind::Unsupported` if `kind` is `TrailKind::Division`, as
+/// division trails have no weight, and of kind `ErrorKind::InvalidInput` unless `precision` is a
+/// positive number.
+pub fn weighted_generic_shard(sbox: &SBox, kind: TrailKind, precision: f64) -> Result<GenericShard, Error> {
+    let groups = weight_groups(sbox, kind, precision)?;
+    let weight_size = groups_size(sbox, &groups);
+    let transitions: Vec<(usize, usize)> = groups.into_iter()
+        .map(|(input, output, groups)| {
+            let weight = (0..groups).fold(0, |weight, group| weight | (1 << (group * sbox.size_out())));
+            (input, output | (weight << sbox.size_out()))
+        })
+        .collect();
+    Ok(GenericShard::from_transitions(&transitions, sbox.size_in(), sbox.size_out() + weight_size))
+}
+
+/// Check that the trails of `kind` can be weighted in units of `precision`, returning an `Error`
+/// as `weighted_generic_shard` does.
+pub fn check_precision(kind: TrailKind, precision: f64) -> Result<(), Error> {
+    if kind == TrailKind::Division {
+        return Err(Error::new(ErrorKind::Unsupported, "Division trails have no weight"));
+    }
+    if !(precision > 0.0 && precision.is_finite()) {
+        return Err(Error::new(ErrorKind::InvalidInput,
+                              format!("Expected a positive precision, found {}", precision)));
+    }
+    Ok(())
+}
+
+/// The transitions of `sbox` for the trails of `kind` along with their number of groups of weight
+/// variables set, see the module documentation.
+fn weight_groups(sbox: &SBox, kind: TrailKind, precision: f64) -> Result<Vec<(usize, usize, usize)>, Error> {
+    check_precision(kind, precision)?;
+    Ok(transitions(sbox, kind).into_iter()
+        .map(|transition| {
+            let units = (transition.weight.unwrap() / precision).round() as usize;
+            let active = (transition.output != 0) as usize;
+            (transition.input, transition.output, units.max(active) - active)
+        })
+        .collect())
+}
+
+/// The number of weight variables following the output of `sbox`, given the number of groups set
+/// by each transition.
+fn groups_size(sbox: &SBox, groups: &[(usize, usize, usize)]) -> usize {
+    groups.iter().map(|(_, _, groups)| *groups).max().unwrap_or(0) * sbox.size_out()
+}
+
+/// The `BddSpec` of the Shard of `sbox` for the trails of `kind`, over `size_in + size_out`
+/// variables: variable i is bit i of the input for i < `size_in`, and bit i - `size_in` of the
+/// output otherwise.
+pub fn bdd_spec(sbox: &SBox, kind: TrailKind) -> BddSpec {
+    let nvar = sbox.size_in() + sbox.size_out();
+    let units = |vars: std::ops::Range<usize>| vars
+        .map(|var| {
+            let mut lhs = Vob::from_elem(nvar, false);
+            lhs.set(var, true);
+            lhs
+        })
+        .collect::<Vec<_>>()
+        .into_iter();
+    let shard = generic_shard(sbox, kind)
+        .into_specific(&mut units(0..sbox.size_in()), &mut units(sbox.size_in()..nvar), Id::new(0));
+    BddSpec::from_bdd(&shard)
+}
+
+#[cfg(test)]
+mod test {
+    use crush::soc::utils::build_bdd_from_spec;
+
+    use crate::code_gen::cipher::{make_weighted_solver, make_weighted_window_soc, RoundWindow};
+    use crate::code_gen::fixture::{Silent, Toy, PRESENT};
+    use crate::diff_solver::{SolverConfig, SolverResult};
+
+    use super::*;
+
+    #[test]
+    fn present_transitions() {
+        let sbox = SBox::new(PRESENT.to_vec(), 4, 4).unwrap();
+        let ddt = sbox.ddt();
+        let differential = transitions(&sbox, TrailKind::Differential);
+        assert_eq!(ddt.iter().flatten().filter(|entry| **entry != 0).count(), differential.len());
+        assert_eq!(Transition { input: 0, output: 0, entry: 16, weight: Some(0.0) }, differential[0]);
+        // The differential uniformity of PRESENT is 4, and its linearity 8, i.e. its correlations
+        // are at most 1/2
+        assert!(differential[1..].iter().all(|t| t.input != 0 && t.weight.unwrap() >= 2.0));
+        let linear = transitions(&sbox, TrailKind::Linear);
+        assert_eq!(Some(0.0), linear[0].weight);
+        assert!(linear[1..].iter().all(|t| t.weight.unwrap() >= 1.0));
+        assert!(linear[1..].iter().any(|t| t.weight == Some(1.0)));
+        assert!(transitions(&sbox, TrailKind::Division).iter().all(|t| t.weight.is_none()));
+    }
+
+    #[test]
+    fn present_weighted_shard() {
+        let sbox = SBox::new(PRESENT.to_vec(), 4, 4).unwrap();
+        let ddt = sbox.ddt();
+        // The weights of PRESENT are 2 and 3, so at most two groups follow the output
+        assert_eq!(8, weight_size(&sbox, TrailKind::Differential, 1.0).unwrap());
+        assert_eq!(20, weight_size(&sbox, TrailKind::Differential, 0.5).unwrap());
+        for precision in [1.0, 0.5].iter() {
+            let shard = weighted_generic_shard(&sbox, TrailKind::Differential, *precision).unwrap();
+            assert_eq!(4 + weight_size(&sbox, TrailKind::Differential, *precision).unwrap(), shard.size_out());
+            let mut paths = shard.transitions();
+            paths.sort_unstable_by_key(|(a, b)| (*a, b & 0xf));
+            let expected: Vec<(usize, usize)> = transitions(&sbox, TrailKind::Differential).iter()
+                .map(|t| (t.input, t.output))
+                .collect();
+            assert_eq!(expected, paths.iter().map(|(a, b)| (*a, b & 0xf)).collect::<Vec<_>>());
+            for (a, b) in paths {
+                let units = (b & 0xf != 0) as u32 + (b >> 4).count_ones();
+                let weight = -(ddt[a][b & 0xf] as f64 / 16.0).log2();
+                assert_eq!((weight / precision).round() as u32, units);
+            }
+        }
+        assert_eq!(ErrorKind::Unsupported,
+                   weighted_generic_shard(&sbox, TrailKind::Division, 1.0).unwrap_err().kind());
+        assert_eq!(ErrorKind::InvalidInput, weight_size(&sbox, TrailKind::Linear, 0.0).unwrap_err().kind());
+    }
+
+    #[test]
+    fn present_bdd_spec() {
+        let sbox = SBox::new(PRESENT.to_vec(), 4, 4).unwrap();
+        for kind in [TrailKind::Differential, TrailKind::Linear].iter() {
+            let table = table(&sbox, *kind);
+            let shard = build_bdd_from_spec(&mut bdd_spec(&sbox, *kind), 8);
+            for (a, row) in table.iter().enumerate() {
+                for (b, entry) in row.iter().enumerate() {
+                    let assignment: Vob = (0..8).map(|i| ((a | (b << 4)) >> i) & 1 == 1).collect();
+                    assert_eq!(*entry != 0, shard.accepts(&assignment), "{:?} transition ({}, {})", kind, a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_solver() {
+        // The lowest weight of a trail in units of `precision`, by going through the differences
+        // round by round
+        let ddt = SBox::new(PRESENT.to_vec(), 4, 4).unwrap().ddt();
+        let linear_layer = |y: usize| (0..8).fold(0, |z, i| z | (((y >> i) & 1) << if i == 7 { 7 } else { 2 * i % 7 }));
+        let lowest = |precision: f64| {
+            let units = |a: usize, b: usize| (-(ddt[a][b] as f64 / 16.0).log2() / precision).round() as usize;
+            let mut best: Vec<usize> = (0..256).map(|x| if x == 0 { usize::MAX } else { 0 }).collect();
+            for _ in 0..3 {
+                let mut next = vec![usize::MAX; 256];
+                for (a, cost) in best.iter().enumerate().filter(|(_, cost)| **cost != usize::MAX) {
+                    for b in (0..256).filter(|b| ddt[a & 0xf][b & 0xf] > 0 && ddt[a >> 4][b >> 4] > 0) {
+                        let y = linear_layer(b);
+                        next[y] = next[y].min(cost + units(a & 0xf, b & 0xf) + units(a >> 4, b >> 4));
+                    }
+                }
+                best = next;
+            }
+            best.into_iter().min().unwrap()
+        };
+
+        let window = RoundWindow::new(0..3);
+        for precision in [1.0, 0.5].iter() {
+            let mut solver = make_weighted_solver(&Toy, TrailKind::Differential, &window, *precision, Silent,
+                                                  SolverConfig::new()).unwrap();
+            solver.run();
+            match solver.finalize() {
+                SolverResult::ProvedOptimal { weight, .. } => assert_eq!(lowest(*precision) as u32, weight),
+                _ => panic!("The solving with a precision of {} wasn't complete", precision),
+            }
+        }
+
+        let error = |kind: TrailKind, precision: f64| make_weighted_window_soc(&Toy, kind, &window, precision)
+            .unwrap_err()
+            .kind();
+        assert_eq!(ErrorKind::Unsupported, error(TrailKind::Division, 1.0));
+        assert_eq!(ErrorKind::InvalidInput, error(TrailKind::Linear, -1.0));
+        assert_eq!(ErrorKind::InvalidInput, error(TrailKind::Differential, f64::NAN));
+    }
+}
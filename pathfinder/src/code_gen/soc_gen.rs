@@ -51,7 +51,7 @@ pub fn make_soc<L, S>(llb: &L, sh: &S, nr_rounds: usize) -> (System, Vec<Vec<Id>
     // Apply round 0 linear layer,
     // 'inn' is input block to the non-linear layer.
     // let mut inn = llb.apply_linear_layer(0, initial);
-    let mut inn = initial;
+    let mut inn = llb.apply_initial_layer(initial);
 
     // Used by the simple_solver to ensure its invariants
     let mut rounds: Vec<Vec<Id>> = Vec::new();
@@ -83,6 +83,13 @@ pub fn make_soc<L, S>(llb: &L, sh: &S, nr_rounds: usize) -> (System, Vec<Vec<Id>
                 // Duplicate for the linear layer transformation
                 out.push(lhs_out);
             }
+            // The weight variables follow the out bits, but never go through the linear layer
+            for _ in 0..sh.sbox_weight_size(r, s) {
+                let mut lhs_weight = Vob::from_elem(nvar, false);
+                lhs_weight.set(next_var_id, true);
+                next_var_id += 1;
+                lhs_o.push(lhs_weight);
+            }
 
             // Create specific shard
             let shard = sh.bt_generic_shard(r, s)
@@ -118,9 +125,9 @@ fn count_nvar(llc: &dyn LLHandler, sc: &dyn SBoxHandler, nr_rounds: usize) -> us
     let mut n_vars = llc.block_size(0);
 
     for r in 0..nr_rounds {
-        // Add the count of out-bits for each S-box
+        // Add the count of out-bits and weight variables for each S-box
         for s in 0..sc.num_sboxes(r) {
-            n_vars += sc.sbox_size_out(r, s);
+            n_vars += sc.sbox_size_out(r, s) + sc.sbox_weight_size(r, s);
         }
     }
     n_vars
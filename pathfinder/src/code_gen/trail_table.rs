@@ -0,0 +1,280 @@
+//! The round by round rendering of a trail of a `Cipher`, as a plain text or a LaTeX table.
+//!
+//! Each row gives the difference (or mask) into the S-box layer of a round, the positions of the
+//! active S-boxes, the difference out of the S-box layer and the weight of the round, e.g.
+//!
+//! ```text
+//! round | input | active | output | weight
+//!     1 |  0x0b |      0 |   0x05 |   2.00
+//!     2 |  0x10 |      1 |   0x30 |   2.00
+//! total |       |        |        |   4.00
+//! ```
+//!
+//! The differences are printed in hex, the most significant nibble first, bit 0 of the state
+//! being the least significant bit.
+
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
+use vob::Vob;
+
+use crate::code_gen::cipher::{make_window_soc, Cipher, Granularity, RoundWindow, TrailKind};
+use crate::code_gen::sbox;
+use crate::diff_solver::post_processing_v5::Trail;
+
+/// A round of a `TrailTable`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrailRound {
+    /// The difference (or mask) into the S-box layer.
+    pub input: Vob,
+    /// The difference (or mask) out of the S-box layer.
+    pub output: Vob,
+    /// The positions of the active S-boxes, by increasing position.
+    pub active: Vec<usize>,
+    /// The weight of the round, i.e. the sum of -log2 of the probability (differential trails) or
+    /// of the absolute correlation (linear trails) of its active S-boxes. Division trails have
+    /// no weight.
+    pub weight: Option<f64>,
+}
+
+/// A trail of a `Cipher`, round by round, see the module documentation. `Display` renders it as a
+/// plain text table and `to_latex` as a LaTeX one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrailTable {
+    block_size: usize,
+    rounds: Vec<TrailRound>,
+}
+
+impl TrailTable {
+    /// The table of `trail`, a trail of `kind` of `cipher` as extracted by
+    /// `post_processing_v5::extract_best_k_trails`. The number of rounds is given by the number of
+    /// values of `trail`.
+    ///
+    /// Returns an `Error` of kind `ErrorKind::InvalidInput` if `trail` isn't a trail of the SoC of
+    /// `cipher` over some number of rounds, or an `Error` as for `for_window`.
+    pub fn new<C: Cipher>(cipher: &C, kind: TrailKind, trail: &Trail) -> Result<TrailTable, Error> {
+        check_granularity(cipher)?;
+        let nr_rounds = trail_rounds(cipher, trail)?;
+        Self::for_window(cipher, kind, &RoundWindow::new(0..nr_rounds), trail)
+    }
+
+    /// The table of `trail`, a trail of `kind` over the rounds of `window` of `cipher`, see
+    /// `code_gen::cipher::make_window_soc`. The rounds of the table are numbered from the first
+    /// round of the window.
+    ///
+    /// Returns an `Error` of kind `ErrorKind::InvalidInput` if `trail` isn't a trail of the SoC of
+    /// the window, of kind `ErrorKind::Unsupported` if the SoCs of `cipher` are word-level ones,
+    /// or an `Error` if the SoC cannot be made.
+    pub fn for_window<C: Cipher>(cipher: &C, kind: TrailKind, window: &RoundWindow, trail: &Trail)
+                                 -> Result<TrailTable, Error> {
+        check_granularity(cipher)?;
+        let block_size = cipher.block_size();
+        let nr_values = trail.values.len();
+        let nr_rounds = window.len();
+        let (soc, ids) = make_window_soc(cipher, kind, window)?;
+        if soc.get_nvar() != nr_values {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Expected {} values for {} rounds, found {}", soc.get_nvar(), nr_rounds, nr_values)));
+        }
+        let eval = |lhs: &Vob| lhs.iter_set_bits(..).filter(|var| trail.values[*var]).count() % 2 == 1;
+
+        let mut rounds = Vec::with_capacity(nr_rounds);
+        for (round, round_ids) in ids.iter().enumerate() {
+            let mut input = Vob::from_elem(block_size, false);
+            let mut output = Vob::from_elem(block_size, false);
+            let mut active = Vec::new();
+            let mut weight = Some(0.0);
+            let sbox_inputs = cipher.sbox_inputs(window.rounds().start + round);
+            for ((pos, id), bits) in round_ids.iter().enumerate().zip(sbox_inputs) {
+                let sbox = cipher.sbox(window.rounds().start + round, pos);
+                let lhs = soc.get_bdd(*id)?.borrow().get_lhs();
+                let (lhs_in, lhs_out) = lhs.split_at(sbox.size_in());
+                let mut a = 0;
+                for (i, lhs) in lhs_in.iter().enumerate() {
+                    let bit = eval(lhs);
+                    input.set(bits.start + i, bit);
+                    a |= (bit as usize) << i;
+                }
+                let mut b = 0;
+                for (i, lhs) in lhs_out.iter().take(sbox.size_out()).enumerate() {
+                    let bit = eval(lhs);
+                    output.set(bits.start + i, bit);
+                    b |= (bit as usize) << i;
+                }
+                if a == 0 && b == 0 {
+                    continue;
+                }
+                active.push(pos);
+                let entry = sbox::table(&sbox, kind)[a][b];
+                weight = weight.zip(sbox::weight(&sbox, kind, entry)).map(|(weight, w)| weight + w);
+            }
+            rounds.push(TrailRound { input, output, active, weight });
+        }
+        Ok(TrailTable { block_size, rounds })
+    }
+
+    #[inline]
+    pub fn rounds(&self) -> &[TrailRound] {
+        &self.rounds
+    }
+
+    /// The weight of the trail, i.e. the sum of the weights of its rounds.
+    pub fn weight(&self) -> Option<f64> {
+        self.rounds.iter().map(|round| round.weight).sum()
+    }
+
+    /// Render the table as a LaTeX `tabular`.
+    pub fn to_latex(&self) -> String {
+        let mut latex = String::from("\\begin{tabular}{rcccr}\n\\hline\n");
+        latex.push_str("Round & Input & Active S-boxes & Output & Weight \\\\\n\\hline\n");
+        for (round, row) in self.rounds.iter().enumerate() {
+            latex.push_str(&format!(
+                "{} & \\texttt{{{}}} & {} & \\texttt{{{}}} & {} \\\\\n",
+                round + 1,
+                hex(&row.input, self.block_size),
+                active_list(&row.active),
+                hex(&row.output, self.block_size),
+                weight_string(row.weight)));
+        }
+        latex.push_str(&format!("\\hline\nTotal & & & & {} \\\\\n\\hline\n\\end{{tabular}}\n",
+                                weight_string(self.weight())));
+        latex
+    }
+}
+
+/// The number of rounds of `trail`, a trail of `cipher` as extracted by
+/// `post_processing_v5::extract_best_k_trails`, given by its number of values.
+///
+/// Returns an `Error` of kind `ErrorKind::InvalidInput` if the values aren't at least two blocks.
+pub fn trail_rounds<C: Cipher>(cipher: &C, trail: &Trail) -> Result<usize, Error> {
+    let block_size = cipher.block_size();
+    let nr_values = trail.values.len();
+    if block_size == 0 || nr_values < 2 * block_size || !nr_values.is_multiple_of(block_size) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Expected the values of at least two blocks of {} bits, found {} values", block_size, nr_values)));
+    }
+    Ok(nr_values / block_size - 1)
+}
+
+/// The tables are made from bit-level trails only.
+fn check_granularity<C: Cipher>(cipher: &C) -> Result<(), Error> {
+    match cipher.granularity() {
+        Granularity::Bit => Ok(()),
+        Granularity::Word => Err(Error::new(ErrorKind::Unsupported, "Expected a trail of a bit-level SoC")),
+    }
+}
+
+/// The first `block_size` bits of `state` in hex, the most significant nibble first.
+pub(crate) fn hex(state: &Vob, block_size: usize) -> String {
+    let nibbles = block_size.div_ceil(4);
+    (0..nibbles).rev()
+        .map(|nibble| {
+            let value = (0..4)
+                .filter(|i| state.get(4 * nibble + i).unwrap_or(false))
+                .fold(0, |value, i| value | (1 << i));
+            std::char::from_digit(value, 16).unwrap()
+        })
+        .collect()
+}
+
+impl fmt::Display for TrailTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<[String; 5]> = self.rounds.iter().enumerate()
+            .map(|(round, row)| [
+                (round + 1).to_string(),
+                format!("0x{}", hex(&row.input, self.block_size)),
+                active_list(&row.active),
+                format!("0x{}", hex(&row.output, self.block_size)),
+                weight_string(row.weight),
+            ])
+            .chain(std::iter::once([
+                "total".to_string(), String::new(), String::new(), String::new(), weight_string(self.weight()),
+            ]))
+            .collect();
+        let header = ["round", "input", "active", "output", "weight"];
+        let widths: Vec<usize> = (0..header.len())
+            .map(|col| rows.iter().map(|row| row[col].len()).chain(std::iter::once(header[col].len())).max().unwrap())
+            .collect();
+        let line = |cells: Vec<&str>| cells.iter().zip(&widths)
+            .map(|(cell, width)| format!("{:>width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        writeln!(f, "{}", line(header.to_vec()))?;
+        for row in &rows {
+            writeln!(f, "{}", line(row.iter().map(|cell| cell.as_str()).collect()))?;
+        }
+        Ok(())
+    }
+}
+
+/// The positions of the active S-boxes, or "-" if there are none.
+fn active_list(active: &[usize]) -> String {
+    if active.is_empty() {
+        return "-".to_string();
+    }
+    active.iter().map(|pos| pos.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn weight_string(weight: Option<f64>) -> String {
+    match weight {
+        Some(weight) => format!("{:.2}", weight),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::code_gen::apply_matrix;
+    use crate::code_gen::fixture::{toy_solver, Toy};
+    use crate::diff_solver::post_processing_v5::extract_best_k_trails;
+    use crate::diff_solver::SolverConfig;
+
+    use super::*;
+
+    #[test]
+    fn trail_table() {
+        for kind in [TrailKind::Differential, TrailKind::Linear].iter() {
+            let mut solver = toy_solver(*kind, 3, SolverConfig::new());
+            solver.run();
+            let result = solver.finalize();
+            let trail = extract_best_k_trails(result.run(), 1).unwrap().pop().unwrap();
+            let table = TrailTable::new(&Toy, *kind, &trail).unwrap();
+            assert_eq!(3, table.rounds().len());
+            // A single active S-box per round, of weight at least 1
+            for round in table.rounds() {
+                assert_eq!(1, round.active.len());
+                assert!(round.weight.unwrap() >= 1.0);
+            }
+            let weight: f64 = table.rounds().iter().map(|round| round.weight.unwrap()).sum();
+            assert_eq!(Some(weight), table.weight());
+
+            let text = table.to_string();
+            assert_eq!(5, text.lines().count());
+            assert!(text.lines().last().unwrap().starts_with("total"));
+            let latex = table.to_latex();
+            assert!(latex.starts_with("\\begin{tabular}"));
+            assert!(latex.trim_end().ends_with("\\end{tabular}"));
+            assert_eq!(3, latex.lines().filter(|line| line.contains("\\texttt")).count());
+
+            if *kind == TrailKind::Differential {
+                let rounds = trail.rounds(8);
+                assert_eq!(rounds[0], table.rounds()[0].input);
+                for (r, round) in table.rounds().iter().enumerate() {
+                    assert_eq!(rounds[r + 1], round.output);
+                    // The PRESENT S-box has no transition of probability above 1/4
+                    assert!(round.weight.unwrap() >= 2.0);
+                    if r + 1 < 3 {
+                        let state: Vec<Vob> = round.output.iter().map(|bit| Vob::from_elem(1, bit)).collect();
+                        let next: Vob = apply_matrix(&Toy.linear_layer(r), &state).iter().map(|bit| bit[0]).collect();
+                        assert_eq!(next, table.rounds()[r + 1].input);
+                    }
+                }
+            }
+        }
+
+        let trail = Trail { weight: 0, values: Vob::from_elem(12, false) };
+        assert!(TrailTable::new(&Toy, TrailKind::Differential, &trail).is_err());
+    }
+}
@@ -0,0 +1,438 @@
+//! Truncated differentials, where the state is split into words and only the activity of each
+//! word (zero or non-zero difference) is kept track of.
+//!
+//! A truncated SoC has one variable per word, and is built by `soc_gen::make_soc` from the
+//! handlers returned by `truncate_handlers`, which are derived from the handlers of the bit-level
+//! SoC. The words are the S-boxes, which must all be bijective and of the same size. As a bijective
+//! S-box never changes the activity of its word, the S-box layers are folded into the linear
+//! layers, and the SoC is laid out as follows:
+//!
+//! - Round 0 has one Shard per word, which is the truncation of the first S-box layer, i.e. the
+//!   identity on the activity of the word.
+//! - Round r > 0 has one Shard per component of the r'th linear layer, where a component is a
+//!   smallest set of input words and output words such that the output words only depend on the
+//!   input words. The Shard is the truncation of the component, see `truncate_component`.
+//!
+//! Each output variable of a truncated SoC is then the activity of an S-box, and the weight of a
+//! trail (with a step of 1) is its number of active S-boxes.
+//!
+//! A `Cipher` is searched at the word level by returning `Granularity::Word` from
+//! `Cipher::granularity`, see `cipher::make_window_soc`.
+
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind};
+
+use vob::Vob;
+
+use crate::code_gen::{LLHandler, SBoxHandler};
+use crate::code_gen::gsf::GenericShard;
+use crate::diff_solver::post_processing_v5::BaseTable;
+
+/// Components with more input bits than this are not enumerated, see `truncate_component`.
+const MAX_ENUMERATED_BITS: usize = 20;
+
+/// Project `shard` to a truncated Shard over words of `word_size` bits. Bit i of the in (out) side
+/// of the truncated Shard is set if the i'th in (out) word of `shard` is active. The entries of the
+/// table of the truncated Shard count the transitions of `shard` with that activity pattern.
+pub fn truncate(shard: &GenericShard, word_size: usize) -> GenericShard {
+    assert_eq!(0, shard.size_in() % word_size);
+    assert_eq!(0, shard.size_out() % word_size);
+    let words_in = shard.size_in() / word_size;
+    let words_out = shard.size_out() / word_size;
+
+    let mut table = vec![vec![0; 1 << words_out]; 1 << words_in];
+    for (inn, out) in shard.transitions() {
+        table[activity(inn, word_size, words_in)][activity(out, word_size, words_out)] += 1;
+    }
+    make_shard(table, words_in, words_out)
+}
+
+/// The part of a linear layer where the words in `inn` are mapped to the words in `out`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Component {
+    inn: Vec<usize>,
+    out: Vec<usize>,
+}
+
+/// The truncated LLHandler, see the module doc for the layout of the state.
+pub struct TruncatedLlHandler {
+    nr_words: usize,
+    /// The components of linear layer r are at index r - 1.
+    components: Vec<Vec<Component>>,
+}
+
+impl LLHandler for TruncatedLlHandler {
+    fn block_size(&self, _round: usize) -> usize {
+        self.nr_words
+    }
+
+    fn apply_linear_layer(&self, round: usize, state: Vec<Vob>) -> Vec<Vob> {
+        // The output words of the previous round are in the order of its components, sort them
+        let natural = if round == 1 {
+            state
+        } else {
+            let mut natural = vec![None; self.nr_words];
+            let out_order = self.components[round - 2].iter().flat_map(|c| c.out.iter());
+            for (word, lhs) in out_order.zip(state) {
+                natural[*word] = Some(lhs);
+            }
+            natural.into_iter().map(|lhs| lhs.unwrap()).collect()
+        };
+
+        // Gather the input words of each component
+        self.components[round - 1].iter()
+            .flat_map(|c| c.inn.iter())
+            .map(|word| natural[*word].clone())
+            .collect()
+    }
+}
+
+/// The truncated SBoxHandler, see the module doc for the layout of the Shards.
+pub struct TruncatedSbHandler {
+    first: Vec<GenericShard>,
+    /// The Shards of round r are at index r - 1.
+    components: Vec<Vec<GenericShard>>,
+}
+
+impl SBoxHandler for TruncatedSbHandler {
+    fn num_sboxes(&self, round: usize) -> usize {
+        if round == 0 {
+            self.first.len()
+        } else {
+            self.components[round - 1].len()
+        }
+    }
+
+    fn sbox_size_in(&self, round: usize, pos: usize) -> usize {
+        self.bt_generic_shard(round, pos).size_in()
+    }
+
+    fn sbox_size_out(&self, round: usize, pos: usize) -> usize {
+        self.bt_generic_shard(round, pos).size_out()
+    }
+
+    fn bt_generic_shard(&self, round: usize, pos: usize) -> GenericShard {
+        if round == 0 {
+            self.first[pos].clone()
+        } else {
+            self.components[round - 1][pos].clone()
+        }
+    }
+}
+
+/// Derive the handlers of a truncated SoC of `nr_rounds` rounds from those of a bit-level SoC.
+///
+/// Returns an Error unless every S-box layer is complete, and all the S-boxes are bijective and of
+/// the same size.
+pub fn truncate_handlers<L, S>(llh: &L, sh: &S, nr_rounds: usize)
+    -> Result<(TruncatedLlHandler, TruncatedSbHandler), Error>
+    where
+        L: LLHandler,
+        S: SBoxHandler,
+{
+    let word_size = sh.sbox_size_in(0, 0);
+    let block_size = llh.block_size(0);
+    for r in 0..nr_rounds {
+        if llh.block_size(r + 1) != block_size || sh.num_sboxes(r) * word_size != block_size {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                                  "Truncated mode needs complete S-box layers of a fixed block size"));
+        }
+        for pos in 0..sh.num_sboxes(r) {
+            if sh.sbox_size_in(r, pos) != word_size || sh.sbox_size_out(r, pos) != word_size {
+                return Err(Error::new(ErrorKind::InvalidInput,
+                                      "Truncated mode needs all S-boxes to be of the same size"));
+            }
+            let truncated = truncate(&sh.bt_generic_shard(r, pos), word_size);
+            let mut transitions = truncated.transitions();
+            transitions.sort_unstable();
+            if transitions != vec![(0, 0), (1, 1)] {
+                return Err(Error::new(ErrorKind::InvalidInput,
+                                      "Truncated mode needs all S-boxes to be bijective"));
+            }
+        }
+    }
+    let nr_words = block_size / word_size;
+
+    let first = (0..nr_words)
+        .map(|pos| truncate(&sh.bt_generic_shard(0, pos), word_size))
+        .collect();
+
+    let mut components = Vec::with_capacity(nr_rounds.saturating_sub(1));
+    let mut shards = Vec::with_capacity(nr_rounds.saturating_sub(1));
+    for r in 1..nr_rounds {
+        let supports = llh.apply_linear_layer(r, unit_vectors(block_size));
+        let round_components = find_components(&supports, word_size);
+        shards.push(round_components.iter()
+            .map(|c| truncate_component(c, &supports, word_size))
+            .collect());
+        components.push(round_components);
+    }
+
+    Ok((TruncatedLlHandler { nr_words, components }, TruncatedSbHandler { first, components: shards }))
+}
+
+/// Find the components of a linear layer, given by the `supports` of its output bits.
+fn find_components(supports: &[Vob], word_size: usize) -> Vec<Component> {
+    let nr_words = supports.len() / word_size;
+    // The input words each output word depends on
+    let deps: Vec<BTreeSet<usize>> = (0..nr_words)
+        .map(|out| supports[out * word_size..(out + 1) * word_size].iter()
+            .flat_map(|support| support.iter_set_bits(..).map(|bit| bit / word_size))
+            .collect())
+        .collect();
+
+    let mut assigned = vec![false; nr_words];
+    let mut components = Vec::new();
+    for start in 0..nr_words {
+        if assigned[start] {
+            continue;
+        }
+        // Grow the component until it is closed in both directions
+        let mut out: BTreeSet<usize> = BTreeSet::new();
+        let mut inn: BTreeSet<usize> = deps[start].clone();
+        out.insert(start);
+        loop {
+            let more: BTreeSet<usize> = (0..nr_words)
+                .filter(|o| !out.contains(o) && !deps[*o].is_disjoint(&inn))
+                .collect();
+            if more.is_empty() {
+                break;
+            }
+            for o in more {
+                inn.extend(deps[o].iter());
+                out.insert(o);
+            }
+        }
+        for o in out.iter() {
+            assigned[*o] = true;
+        }
+        components.push(Component {
+            inn: inn.into_iter().collect(),
+            out: out.into_iter().collect(),
+        });
+    }
+    components
+}
+
+/// The truncated Shard of `component`, followed by the (bijective) S-boxes of its output words.
+///
+/// If the component has at most `MAX_ENUMERATED_BITS` input bits, all its inputs are enumerated and
+/// the Shard is exact. Otherwise, the only constraints kept are that an output word can only be
+/// active if one of the input words it depends on is, and that a non-zero input gives a non-zero
+/// output.
+fn truncate_component(component: &Component, supports: &[Vob], word_size: usize) -> GenericShard {
+    let words_in = component.inn.len();
+    let words_out = component.out.len();
+    let bits_in = words_in * word_size;
+
+    // The support of each output bit, over the input bits of the component
+    let local: Vec<usize> = component.out.iter()
+        .flat_map(|out| supports[out * word_size..(out + 1) * word_size].iter())
+        .map(|support| {
+            let mut local = 0;
+            for (i, word) in component.inn.iter().enumerate() {
+                for j in 0..word_size {
+                    if support.get(word * word_size + j).unwrap() {
+                        local |= 1 << (i * word_size + j);
+                    }
+                }
+            }
+            local
+        })
+        .collect();
+
+    let mut table = vec![vec![0; 1 << words_out]; 1 << words_in];
+    if bits_in <= MAX_ENUMERATED_BITS {
+        for x in 0..(1_usize << bits_in) {
+            let y = local.iter().enumerate()
+                .fold(0, |y, (i, support)| y | (((support & x).count_ones() as usize & 1) << i));
+            table[activity(x, word_size, words_in)][activity(y, word_size, words_out)] += 1;
+        }
+    } else {
+        let word_mask = (1 << word_size) - 1;
+        // The input words of the component each output word depends on
+        let deps: Vec<usize> = (0..words_out)
+            .map(|o| (0..words_in)
+                .filter(|i| local[o * word_size..(o + 1) * word_size].iter()
+                    .any(|support| (support >> (i * word_size)) & word_mask != 0))
+                .fold(0, |deps, i| deps | (1 << i)))
+            .collect();
+        for (a, row) in table.iter_mut().enumerate() {
+            for (b, entry) in row.iter_mut().enumerate() {
+                let reachable = (0..words_out).all(|o| (b >> o) & 1 == 0 || deps[o] & a != 0);
+                if reachable && (a == 0) == (b == 0) {
+                    *entry = 1;
+                }
+            }
+        }
+    }
+    make_shard(table, words_in, words_out)
+}
+
+/// Bit i of the result is set if word i of `value` is non-zero.
+fn activity(value: usize, word_size: usize, nr_words: usize) -> usize {
+    let word_mask = (1 << word_size) - 1;
+    (0..nr_words)
+        .filter(|i| (value >> (i * word_size)) & word_mask != 0)
+        .fold(0, |act, i| act | (1 << i))
+}
+
+fn unit_vectors(len: usize) -> Vec<Vob> {
+    (0..len)
+        .map(|i| {
+            let mut unit = Vob::from_elem(len, false);
+            unit.set(i, true);
+            unit
+        })
+        .collect()
+}
+
+fn make_shard(table: Vec<Vec<usize>>, size_in: usize, size_out: usize) -> GenericShard {
+    GenericShard::new(&BaseTable::try_from(table).unwrap(), size_in, size_out)
+}
+
+#[cfg(test)]
+mod test {
+    use crush::algebra::Matrix;
+
+    use crate::code_gen::cipher::{make_cipher_soc, make_cipher_solver, Cipher, Granularity, SBox, TrailKind};
+    use crate::code_gen::fixture::{Silent, PRESENT};
+    use crate::code_gen::trail_table::TrailTable;
+    use crate::diff_solver::post_processing_v5::extract_best_k_trails;
+    use crate::diff_solver::{SolverConfig, SolverResult};
+
+    use super::*;
+
+    fn present_ddt() -> Vec<Vec<usize>> {
+        let mut ddt = vec![vec![0; 16]; 16];
+        for x in 0..16 {
+            for a in 0..16 {
+                ddt[a][PRESENT[x] ^ PRESENT[x ^ a]] += 1;
+            }
+        }
+        ddt
+    }
+
+    #[test]
+    fn transitions_of_ddt() {
+        let ddt = present_ddt();
+        let mut expected: Vec<(usize, usize)> = (0..16)
+            .flat_map(|a| (0..16).map(move |b| (a, b)))
+            .filter(|(a, b)| ddt[*a][*b] != 0)
+            .collect();
+        let mut transitions = make_shard(ddt, 4, 4).transitions();
+
+        expected.sort_unstable();
+        transitions.sort_unstable();
+        assert_eq!(expected, transitions);
+    }
+
+    #[test]
+    fn truncate_sbox() {
+        let mut transitions = truncate(&make_shard(present_ddt(), 4, 4), 4).transitions();
+        transitions.sort_unstable();
+        assert_eq!(vec![(0, 0), (1, 1)], transitions);
+    }
+
+    #[test]
+    fn truncate_bit_permutation() {
+        // Two words of two bits, where the low bits go to the first word and the high bits to the
+        // second word. Output word j is bit j of each input word.
+        let mut supports = unit_vectors(4);
+        supports.swap(1, 2);
+        let components = find_components(&supports, 2);
+        assert_eq!(vec![Component { inn: vec![0, 1], out: vec![0, 1] }], components);
+
+        let mut transitions = truncate_component(&components[0], &supports, 2).transitions();
+        transitions.sort_unstable();
+        // Each input word puts one bit into each output word, so any non-zero input may activate
+        // any non-empty set of output words
+        assert_eq!(vec![(0, 0), (1, 1), (1, 2), (1, 3), (2, 1), (2, 2), (2, 3), (3, 1), (3, 2), (3, 3)],
+                   transitions);
+    }
+
+    /// Four PRESENT S-boxes, each word of the output of the linear layer being the XOR of the
+    /// three other words of its input, searched at the word level.
+    struct Words;
+
+    impl Cipher for Words {
+        fn name(&self) -> String {
+            "words".to_string()
+        }
+
+        fn block_size(&self) -> usize {
+            16
+        }
+
+        fn nr_rounds(&self) -> usize {
+            3
+        }
+
+        fn sbox(&self, _round: usize, _pos: usize) -> SBox {
+            SBox::new(PRESENT.to_vec(), 4, 4).unwrap()
+        }
+
+        fn linear_layer(&self, _round: usize) -> Matrix {
+            let mut matrix = Matrix::new(16, 16);
+            for out in 0..4 {
+                for inn in (0..4).filter(|inn| *inn != out) {
+                    for j in 0..4 {
+                        matrix.set(4 * out + j, 4 * inn + j, true);
+                    }
+                }
+            }
+            matrix
+        }
+
+        fn granularity(&self) -> Granularity {
+            Granularity::Word
+        }
+    }
+
+    #[test]
+    fn word_granularity() {
+        // A variable per word
+        let (soc, rounds) = make_cipher_soc(&Words, TrailKind::Differential, 3).unwrap();
+        assert_eq!(4 + 3 * 4, soc.get_nvar());
+        assert_eq!(4, rounds[0].len());
+
+        // The fewest active words of a trail, by going through the activity patterns round by round
+        let activity = |x: usize| (0..4).filter(|w| (x >> (4 * w)) & 0xf != 0).fold(0, |a, w| a | (1 << w));
+        let linear_layer = |x: usize| (0..4).fold(0, |y, out| {
+            let word = (0..4).filter(|inn| *inn != out).fold(0, |word, inn| word ^ ((x >> (4 * inn)) & 0xf));
+            y | (word << (4 * out))
+        });
+        let mut transitions = vec![vec![false; 16]; 16];
+        for x in 0..1 << 16 {
+            transitions[activity(x)][activity(linear_layer(x))] = true;
+        }
+        let mut best: Vec<usize> = (0..16).map(|a| if a == 0 { usize::MAX } else { (a as u32).count_ones() as usize })
+            .collect();
+        for _ in 1..3 {
+            best = (0..16)
+                .map(|b| (0..16)
+                    .filter(|a| best[*a] != usize::MAX && transitions[*a][b])
+                    .map(|a| best[a] + (b as u32).count_ones() as usize)
+                    .min()
+                    .unwrap_or(usize::MAX))
+                .collect();
+        }
+        let expected = best.into_iter().min().unwrap() as u32;
+        // The branch number of the linear layer is 4
+        assert_eq!(5, expected);
+
+        let mut solver = make_cipher_solver(&Words, TrailKind::Differential, 3, Silent, SolverConfig::new()).unwrap();
+        solver.run();
+        let result = solver.finalize();
+        match &result {
+            SolverResult::ProvedOptimal { weight, .. } => assert_eq!(expected, *weight),
+            _ => panic!("The solving of the truncated trails wasn't complete"),
+        }
+
+        // Only differential trails, and no bit-level tables of them
+        let trail = extract_best_k_trails(result.run(), 1).unwrap().pop().unwrap();
+        assert_eq!(ErrorKind::Unsupported, TrailTable::new(&Words, TrailKind::Differential, &trail).unwrap_err().kind());
+        assert_eq!(ErrorKind::Unsupported, make_cipher_soc(&Words, TrailKind::Linear, 3).unwrap_err().kind());
+    }
+}
@@ -0,0 +1,228 @@
+//! Support for boomerang trails.
+//!
+//! A boomerang splits the cipher in an upper part, a switch round and a lower part. An upper trail
+//! goes from the input difference to the input of the S-boxes of the switch round, and a lower
+//! trail goes from the output of these S-boxes to the output difference. The two trails are
+//! compatible if every S-box of the switch round connects them, which is given by its Boomerang
+//! Connectivity Table (BCT).
+//!
+//! The search for a pair of compatible trails is therefore the same as the search for a single
+//! differential trail, except that the Shards of the switch round are based on the BCT rather than
+//! on the DDT. The BCT connects any difference to a zero one though, so the switch tables leave
+//! these connections out, see `switch_table`. The probability of a boomerang whose upper and lower
+//! trails have probabilities p and q is then p²q², see `make_boomerang_soc`.
+//!
+//! Upper and lower trails solved separately are connected by `code_gen::sandwich::Sandwich`.
+
+use std::io::{Error, ErrorKind};
+
+use crush::soc::Id;
+use crush::soc::system::System;
+
+use crate::code_gen::{LLHandler, SBoxHandler};
+use crate::code_gen::gsf::GenericShard;
+use crate::code_gen::soc_gen;
+
+/// Returns the BCT of the bijective S-box given by `table`, or None if the S-box is not bijective.
+///
+/// The entry at (a, b) is the number of x such that
+/// S<sup>-1</sup>(S(x) ^ b) ^ S<sup>-1</sup>(S(x ^ a) ^ b) = a.
+pub fn bct(table: &[usize]) -> Option<Vec<Vec<usize>>> {
+    let size = table.len();
+    let mut inverse = vec![None; size];
+    for (x, y) in table.iter().enumerate() {
+        match inverse.get_mut(*y) {
+            Some(inv @ None) => *inv = Some(x),
+            _ => return None,
+        }
+    }
+    let inverse: Vec<usize> = inverse.into_iter().map(|x| x.unwrap()).collect();
+
+    let mut bct = vec![vec![0; size]; size];
+    for (a, row) in bct.iter_mut().enumerate() {
+        for (b, entry) in row.iter_mut().enumerate() {
+            *entry = (0..size)
+                .filter(|x| inverse[table[*x] ^ b] ^ inverse[table[x ^ a] ^ b] == a)
+                .count();
+        }
+    }
+    Some(bct)
+}
+
+/// Returns the table of an S-box of the switch of a boomerang, which is `bct` with the entries
+/// connecting a zero difference to a non-zero one set to 0.
+///
+/// The row and the column of the zero difference of a BCT are full: any difference into an S-box
+/// of the switch comes back from a zero difference out of it, and conversely. With them, the best
+/// boomerang would stop at the switch, its lower trail being zero, and the search would merely be
+/// the one of a differential trail. Without them, each S-box of the switch is active on both sides
+/// or on none, so a non-zero input difference makes both trails non-zero.
+pub fn switch_table(bct: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    bct.iter()
+        .enumerate()
+        .map(|(a, row)| row.iter()
+            .enumerate()
+            .map(|(b, entry)| if (a == 0) == (b == 0) { *entry } else { 0 })
+            .collect())
+        .collect()
+}
+
+/// An SBoxHandler where the Shards of the switch round are replaced by the given switch Shards,
+/// typically based on the BCT of the S-boxes. All other rounds are left to the wrapped handler.
+pub struct SwitchHandler<'a, S: SBoxHandler> {
+    inner: &'a S,
+    switch_round: usize,
+    switch: Vec<GenericShard>,
+}
+
+impl<'a, S: SBoxHandler> SwitchHandler<'a, S> {
+    /// There must be one switch Shard for each S-box of the switch round, of the same size.
+    pub fn new(inner: &'a S, switch_round: usize, switch: Vec<GenericShard>) -> Result<Self, Error> {
+        if switch.len() != inner.num_sboxes(switch_round) {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                                  "Expected one switch Shard per S-box of the switch round"));
+        }
+        for (pos, shard) in switch.iter().enumerate() {
+            if shard.size_in() != inner.sbox_size_in(switch_round, pos)
+                || shard.size_out() != inner.sbox_size_out(switch_round, pos) {
+                return Err(Error::new(ErrorKind::InvalidInput,
+                                      format!("Switch Shard {} does not match the size of the S-box", pos)));
+            }
+        }
+        Ok(Self { inner, switch_round, switch })
+    }
+}
+
+impl<'a, S: SBoxHandler> SBoxHandler for SwitchHandler<'a, S> {
+    fn num_sboxes(&self, round: usize) -> usize {
+        self.inner.num_sboxes(round)
+    }
+
+    fn sbox_size_in(&self, round: usize, pos: usize) -> usize {
+        self.inner.sbox_size_in(round, pos)
+    }
+
+    fn sbox_size_out(&self, round: usize, pos: usize) -> usize {
+        self.inner.sbox_size_out(round, pos)
+    }
+
+    fn bt_generic_shard(&self, round: usize, pos: usize) -> GenericShard {
+        if round == self.switch_round {
+            self.switch[pos].clone()
+        } else {
+            self.inner.bt_generic_shard(round, pos)
+        }
+    }
+
+    fn sbox_weight_size(&self, round: usize, pos: usize) -> usize {
+        if round == self.switch_round {
+            0
+        } else {
+            self.inner.sbox_weight_size(round, pos)
+        }
+    }
+}
+
+/// Make the SoC of a boomerang over `nr_rounds` rounds, where round `switch_round` is the switch.
+/// The rounds before the switch make up the upper part, and the rounds after it the lower part.
+///
+/// Solving the SoC then finds an upper and a lower trail connected by the switch, whose Shards
+/// should be based on the `switch_table`s of the S-boxes: with the BCTs themselves, the lower
+/// trail found may be zero. The weight of the boomerang, -log2 p²q², is twice the one of its upper
+/// and lower trails, the S-boxes of the switch left out, see `code_gen::cipher::boomerang_weight`.
+pub fn make_boomerang_soc<L, S>(llh: &L,
+                                sh: &S,
+                                switch_round: usize,
+                                switch: Vec<GenericShard>,
+                                nr_rounds: usize)
+                                -> Result<(System, Vec<Vec<Id>>), Error>
+    where
+        L: LLHandler,
+        S: SBoxHandler,
+{
+    if switch_round >= nr_rounds {
+        return Err(Error::new(ErrorKind::InvalidInput,
+                              "The switch round must be one of the rounds of the boomerang"));
+    }
+    let switch_handler = SwitchHandler::new(sh, switch_round, switch)?;
+    Ok(soc_gen::make_soc(llh, &switch_handler, nr_rounds))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::code_gen::cipher::{boomerang_weight, make_boomerang_solver};
+    use crate::code_gen::fixture::{Silent, Toy, PRESENT};
+    use crate::diff_solver::{SolverConfig, SolverResult};
+    use crate::diff_solver::post_processing_v5::extract_best_k_trails;
+
+    use super::*;
+
+    fn ddt(table: &[usize]) -> Vec<Vec<usize>> {
+        let mut ddt = vec![vec![0; table.len()]; table.len()];
+        for x in 0..table.len() {
+            for a in 0..table.len() {
+                ddt[a][table[x] ^ table[x ^ a]] += 1;
+            }
+        }
+        ddt
+    }
+
+    #[test]
+    fn bct_present() {
+        let bct = bct(&PRESENT).unwrap();
+        let ddt = ddt(&PRESENT);
+
+        for a in 0..16 {
+            // The first row and column are full
+            assert_eq!(16, bct[0][a]);
+            assert_eq!(16, bct[a][0]);
+            for b in 0..16 {
+                // The BCT is at least the DDT, and they agree on their parity
+                assert!(bct[a][b] >= ddt[a][b]);
+                assert_eq!(bct[a][b] % 2, ddt[a][b] % 2);
+            }
+        }
+        // The boomerang uniformity of PRESENT
+        assert_eq!(16, (1..16).flat_map(|a| (1..16).map(move |b| (a, b)))
+            .map(|(a, b)| bct[a][b])
+            .max().unwrap());
+    }
+
+    #[test]
+    fn bct_not_bijective() {
+        assert_eq!(None, bct(&[0, 1, 1, 2]));
+    }
+
+    #[test]
+    fn switch_table_present() {
+        let bct = bct(&PRESENT).unwrap();
+        let table = switch_table(&bct);
+        assert_eq!(16, table[0][0]);
+        for a in 1..16 {
+            assert_eq!(0, table[a][0]);
+            assert_eq!(0, table[0][a]);
+            assert_eq!(bct[a][1..], table[a][1..]);
+        }
+    }
+
+    #[test]
+    fn boomerang_toy() {
+        // Toy has trails with a single active S-box per round. Around a switch in the second
+        // round, the best boomerang has one before the switch and one after it, of weight 2 * 2.
+        let mut solver = make_boomerang_solver(&Toy, 1, 3, Silent, SolverConfig::new()).unwrap();
+        solver.run();
+        let result = solver.finalize();
+        // The weight counts the S-boxes of the switch too
+        match result {
+            SolverResult::ProvedOptimal { weight, .. } => assert_eq!(3, weight),
+            _ => panic!("The solving of the boomerangs wasn't complete"),
+        }
+        // The input, the outputs of the S-boxes before, at and after the switch: none is zero
+        for trail in extract_best_k_trails(result.run(), 4).unwrap() {
+            let rounds = trail.rounds(8);
+            assert_eq!(4, rounds.len());
+            assert!(rounds.iter().all(|round| round.iter_set_bits(..).next().is_some()), "{:?}", rounds);
+            assert_eq!(4, boomerang_weight(&Toy, 1, &trail));
+        }
+    }
+}
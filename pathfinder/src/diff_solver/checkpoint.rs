@@ -0,0 +1,212 @@
+//! Checkpoints of a `SimpleSolver`, such that a solving of several days can resume where it
+//! stopped after the process was killed or the machine rebooted.
+//!
+//! A checkpoint holds the whole state of the solving: the SoC, with `Master` as far as it was
+//! joined, absorbed and pruned, the Shards joined so far and the rounds done, the cohorts, the
+//! weight bounds and the lowest prune threshold. The solver uses no randomness, so there is no
+//! generator state to keep: a resumed solving gives the same result as an uninterrupted one. Only
+//! the history of the `Librarian` is not kept, it restarts at the resume.
+//!
+//! A checkpoint file starts with the bytes `CHECKPOINT_MAGIC` and `CHECKPOINT_VERSION`, followed by
+//! the state as 64 bits little endian words and the SoC as a binary snapshot (see
+//! `crush::soc::binary`). It is written to a temporary file renamed afterwards, such that a crash
+//! while writing leaves the previous checkpoint untouched.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::Path;
+
+use vob::Vob;
+
+use crush::soc::Id;
+use crush::soc::system::System;
+
+use super::meta::WeightBounds;
+
+/// The bytes starting a checkpoint of a `SimpleSolver`.
+pub const CHECKPOINT_MAGIC: &[u8; 8] = b"PFSOLVER";
+/// Version of the checkpoints written by this module.
+pub const CHECKPOINT_VERSION: u64 = 1;
+
+/// The state of a `SimpleSolver` besides its SoC.
+pub(super) struct SolverState {
+    pub(super) master_id: Id,
+    pub(super) master_block_size: usize,
+    pub(super) step: usize,
+    pub(super) cohorts: HashMap<Id, Vec<Vob>>,
+    pub(super) rounds: Vec<Vec<Id>>,
+    pub(super) joined_w_master: Vec<Id>,
+    /// Number of rounds whose Shards were all joined and whose bounds were updated.
+    pub(super) rounds_done: usize,
+    pub(super) bounds: WeightBounds,
+    pub(super) lowest_pruned: Option<u32>,
+}
+
+/// Write a checkpoint of `state` and `soc` at path.
+pub(super) fn write_checkpoint(path: &Path, state: &SolverState, soc: &System) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let file = File::create(&tmp_path)?;
+    let mut writer = BufWriter::new(&file);
+    writer.write_all(CHECKPOINT_MAGIC)?;
+    write_u64(&mut writer, CHECKPOINT_VERSION)?;
+    write_u64(&mut writer, *state.master_id as u64)?;
+    write_u64(&mut writer, state.master_block_size as u64)?;
+    write_u64(&mut writer, state.step as u64)?;
+    let mut cohorts: Vec<_> = state.cohorts.iter().collect();
+    cohorts.sort_unstable_by_key(|(id, _)| **id);
+    write_u64(&mut writer, cohorts.len() as u64)?;
+    for (id, lhss) in cohorts {
+        write_u64(&mut writer, **id as u64)?;
+        write_u64(&mut writer, lhss.len() as u64)?;
+        for lhs in lhss {
+            write_vob(&mut writer, lhs)?;
+        }
+    }
+    write_u64(&mut writer, state.rounds.len() as u64)?;
+    for round in state.rounds.iter() {
+        write_ids(&mut writer, round)?;
+    }
+    write_ids(&mut writer, &state.joined_w_master)?;
+    write_u64(&mut writer, state.rounds_done as u64)?;
+    for value in [state.bounds.lower, state.bounds.upper, state.lowest_pruned].iter() {
+        write_option(&mut writer, *value)?;
+    }
+    soc.serialize_binary(&mut writer)?;
+    writer.flush()?;
+    drop(writer);
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Read the checkpoint at path, written by `write_checkpoint`.
+///
+/// Return an `Error` of kind `ErrorKind::Unsupported` if it was written by a later version, and
+/// of kind `ErrorKind::InvalidData` if it is not a checkpoint or is malformed.
+pub(super) fn read_checkpoint(path: &Path) -> io::Result<(SolverState, System)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != CHECKPOINT_MAGIC {
+        return Err(Error::new(ErrorKind::InvalidData, "not a checkpoint of a SimpleSolver"));
+    }
+    let version = read_u64(&mut reader)?;
+    if version > CHECKPOINT_VERSION {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("checkpoint version {} is newer than the supported {}", version, CHECKPOINT_VERSION),
+        ));
+    }
+    let master_id = Id::new(read_usize(&mut reader)?);
+    let master_block_size = read_usize(&mut reader)?;
+    let step = read_usize(&mut reader)?;
+    let mut cohorts = HashMap::new();
+    for _ in 0..read_usize(&mut reader)? {
+        let id = Id::new(read_usize(&mut reader)?);
+        let mut lhss = Vec::new();
+        for _ in 0..read_usize(&mut reader)? {
+            lhss.push(read_vob(&mut reader)?);
+        }
+        cohorts.insert(id, lhss);
+    }
+    let mut rounds = Vec::new();
+    for _ in 0..read_usize(&mut reader)? {
+        rounds.push(read_ids(&mut reader)?);
+    }
+    let joined_w_master = read_ids(&mut reader)?;
+    let rounds_done = read_usize(&mut reader)?;
+    let bounds = WeightBounds { lower: read_option(&mut reader)?, upper: read_option(&mut reader)? };
+    let lowest_pruned = read_option(&mut reader)?;
+    let soc = System::deserialize_binary(&mut reader)?;
+    let state = SolverState {
+        master_id,
+        master_block_size,
+        step,
+        cohorts,
+        rounds,
+        joined_w_master,
+        rounds_done,
+        bounds,
+        lowest_pruned,
+    };
+    Ok((state, soc))
+}
+
+pub(super) fn write_ids<W: Write>(writer: &mut W, ids: &[Id]) -> io::Result<()> {
+    write_u64(writer, ids.len() as u64)?;
+    for id in ids {
+        write_u64(writer, **id as u64)?;
+    }
+    Ok(())
+}
+
+pub(super) fn read_ids<R: Read>(reader: &mut R) -> io::Result<Vec<Id>> {
+    let mut ids = Vec::new();
+    for _ in 0..read_usize(reader)? {
+        ids.push(Id::new(read_usize(reader)?));
+    }
+    Ok(ids)
+}
+
+/// Write a `Vob` as its length, its number of set bits and the set bits.
+fn write_vob<W: Write>(writer: &mut W, vob: &Vob) -> io::Result<()> {
+    write_u64(writer, vob.len() as u64)?;
+    write_u64(writer, vob.iter_set_bits(..).count() as u64)?;
+    for bit in vob.iter_set_bits(..) {
+        write_u64(writer, bit as u64)?;
+    }
+    Ok(())
+}
+
+fn read_vob<R: Read>(reader: &mut R) -> io::Result<Vob> {
+    let len = read_usize(reader)?;
+    let mut vob = Vob::from_elem(len, false);
+    for _ in 0..read_usize(reader)? {
+        let bit = read_usize(reader)?;
+        if bit >= len {
+            return Err(Error::new(ErrorKind::InvalidData, format!("bit {} out of a vob of length {}", bit, len)));
+        }
+        vob.set(bit, true);
+    }
+    Ok(vob)
+}
+
+/// Write an `Option<u32>` as 0 for `None`, or 1 followed by the value.
+fn write_option<W: Write>(writer: &mut W, value: Option<u32>) -> io::Result<()> {
+    match value {
+        Some(value) => {
+            write_u64(writer, 1)?;
+            write_u64(writer, value as u64)
+        }
+        None => write_u64(writer, 0),
+    }
+}
+
+fn read_option<R: Read>(reader: &mut R) -> io::Result<Option<u32>> {
+    if read_u64(reader)? == 0 {
+        return Ok(None);
+    }
+    let value = read_u64(reader)?;
+    u32::try_from(value).map(Some).map_err(|_| overflow(value))
+}
+
+pub(super) fn write_u64<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub(super) fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub(super) fn read_usize<R: Read>(reader: &mut R) -> io::Result<usize> {
+    let value = read_u64(reader)?;
+    usize::try_from(value).map_err(|_| overflow(value))
+}
+
+fn overflow(value: u64) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("{} is out of range", value))
+}
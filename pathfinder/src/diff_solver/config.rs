@@ -0,0 +1,273 @@
+//! The strategy of a `SimpleSolver`, such that experiments can compare strategies without code
+//! edits.
+//!
+//! A `SolverConfig` is built from `SolverConfig::new()`, which is the strategy the solver always
+//! used, by changing what is to be compared:
+//!
+//! ```
+//! use pathfinder::diff_solver::{JoinOrder, SolverConfig};
+//!
+//! let config = SolverConfig::new()
+//!     .with_soft_limit(1 << 20)
+//!     .with_prune_target(0.5)
+//!     .with_join_order(JoinOrder::SmallestProductFirst);
+//! assert_eq!(1 << 19, config.prune_limit());
+//! ```
+//!
+//! The config is given to `SimpleSolver::new`, or `SimpleSolver::resume_from_checkpoint` as it is
+//! not part of a checkpoint.
+//!
+//! A run is reproducible with `SolverConfig::deterministic`. The heuristics of the solver draw no
+//! randomness and break their ties by a fixed order (the join orders by the static order of the
+//! Shards, crush by the ids of the bdds), and the maps of crush iterate in the same order from one
+//! build to the next (see `crush::FixedHasher`). A deterministic run also swaps and adds the levels
+//! sequentially whatever the number of threads, as the parallel versions name the new nodes
+//! differently. The seed is kept in the config, to be reported with it (e.g. in a `RunResult`),
+//! and is the one a randomized heuristic has to draw from.
+
+use std::fmt;
+
+use crush::budget::MemoryBudget;
+
+use super::join_order::JoinOrder;
+
+/// Which of its records a `SimpleSolver` sends to `crush::reporting`. Each level reports what the
+/// previous one does, and more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Verbosity {
+    /// Only the start and end of the rounds, and what the memory budget reports.
+    Quiet,
+    /// Also the joins, absorptions and prunings, and the memory used after each join.
+    Normal,
+    /// Also the memory used after each absorption, and the reordering of the levels.
+    Verbose,
+}
+
+/// The strategy of a `SimpleSolver`, see the module documentation.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SolverConfig {
+    join_order: JoinOrder,
+    soft_limit: usize,
+    prune_target: f64,
+    hard_limit: Option<usize>,
+    reorder_levels: bool,
+    verbosity: Verbosity,
+    stats_interval: Option<usize>,
+    seed: Option<u64>,
+}
+
+impl SolverConfig {
+    /// Construct the default config: Shards joined in the static order, no pruning, no memory
+    /// budget, no reordering of the levels, `Verbosity::Normal`, no statistics snapshots and not
+    /// deterministic.
+    pub fn new() -> SolverConfig {
+        SolverConfig {
+            join_order: JoinOrder::Static,
+            soft_limit: usize::MAX,
+            prune_target: 1.0,
+            hard_limit: None,
+            reorder_levels: false,
+            verbosity: Verbosity::Normal,
+            stats_interval: None,
+            seed: None,
+        }
+    }
+
+    /// Join the Shards of a round in `order`, see `join_order`.
+    pub fn with_join_order(mut self, order: JoinOrder) -> SolverConfig {
+        self.join_order = order;
+        self
+    }
+
+    /// Prune `Master` when it grows beyond `limit` nodes after a join.
+    pub fn with_soft_limit(mut self, limit: usize) -> SolverConfig {
+        self.soft_limit = limit;
+        self
+    }
+
+    /// Prune `Master` down to the fraction `target` of the soft limit, rather than to the soft
+    /// limit itself, leaving room for the next joins before the next pruning. `target` is clamped
+    /// to ]0, 1].
+    pub fn with_prune_target(mut self, target: f64) -> SolverConfig {
+        self.prune_target = target.clamp(f64::MIN_POSITIVE, 1.0);
+        self
+    }
+
+    /// Keep the nodes of the SoC within `limit`, with a `MemoryBudget` of `limit` nodes, see
+    /// `SimpleSolver::set_memory_budget`.
+    pub fn with_hard_limit(mut self, limit: usize) -> SolverConfig {
+        self.hard_limit = Some(limit);
+        self
+    }
+
+    /// After the absorptions of each join, reorder the levels of `Master` above its active area
+    /// if it makes `Master` smaller. These levels are out of reach of the pruning, and are only
+    /// reordered pairwise, by swapping adjacent levels.
+    pub fn with_level_reordering(mut self, reorder: bool) -> SolverConfig {
+        self.reorder_levels = reorder;
+        self
+    }
+
+    /// Report the records of `verbosity`.
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> SolverConfig {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Report a snapshot of the statistics of the SoC every `joins` joins into `Master`, with
+    /// `crush::reporting::stats_snapshot`, whatever the verbosity. Computing the statistics goes
+    /// through the whole SoC, `joins` is at least 1.
+    pub fn with_stats_interval(mut self, joins: usize) -> SolverConfig {
+        self.stats_interval = Some(joins.max(1));
+        self
+    }
+
+    /// Make the run reproducible, keeping `seed` for the randomized heuristics, see the module
+    /// documentation.
+    pub fn deterministic(mut self, seed: u64) -> SolverConfig {
+        self.seed = Some(seed);
+        self
+    }
+
+    #[inline]
+    pub fn join_order(&self) -> &JoinOrder {
+        &self.join_order
+    }
+
+    #[inline]
+    pub fn soft_limit(&self) -> usize {
+        self.soft_limit
+    }
+
+    #[inline]
+    pub fn prune_target(&self) -> f64 {
+        self.prune_target
+    }
+
+    /// Return the number of nodes `Master` is pruned down to once beyond the soft limit.
+    pub fn prune_limit(&self) -> usize {
+        if self.prune_target >= 1.0 {
+            self.soft_limit
+        } else {
+            (self.soft_limit as f64 * self.prune_target) as usize
+        }
+    }
+
+    #[inline]
+    pub fn hard_limit(&self) -> Option<usize> {
+        self.hard_limit
+    }
+
+    /// Return the memory budget of the hard limit, if any.
+    pub fn memory_budget(&self) -> Option<MemoryBudget> {
+        self.hard_limit.map(MemoryBudget::new)
+    }
+
+    #[inline]
+    pub fn reorder_levels(&self) -> bool {
+        self.reorder_levels
+    }
+
+    #[inline]
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    #[inline]
+    pub fn stats_interval(&self) -> Option<usize> {
+        self.stats_interval
+    }
+
+    /// Return the seed of a deterministic run, `None` if the run isn't deterministic.
+    #[inline]
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    #[inline]
+    pub fn is_deterministic(&self) -> bool {
+        self.seed.is_some()
+    }
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig::new()
+    }
+}
+
+impl fmt::Display for SolverConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "join order: {}, soft limit: {}, prune target: {}, hard limit: {}, level reordering: {}, \
+                   verbosity: {:?}, stats interval: {}, seed: {}",
+               self.join_order, self.soft_limit, self.prune_target,
+               self.hard_limit.map_or("none".to_string(), |limit| limit.to_string()),
+               self.reorder_levels, self.verbosity,
+               self.stats_interval.map_or("none".to_string(), |joins| joins.to_string()),
+               self.seed.map_or("none".to_string(), |seed| seed.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crush::soc::dot::DotOptions;
+    use crush::soc::Id;
+
+    use crate::code_gen::cipher::TrailKind;
+    use crate::code_gen::fixture::toy_solver;
+    use crate::diff_solver::SolverResult;
+
+    use super::*;
+
+    #[test]
+    fn solver_config() {
+        // Only the way to the optimal trail changes with the strategy.
+        let configs = [
+            SolverConfig::new().with_join_order(JoinOrder::SmallestProductFirst),
+            SolverConfig::new().with_join_order(JoinOrder::FewestNewVariables).with_level_reordering(true),
+            SolverConfig::new().with_join_order(JoinOrder::Permutation((0..12).rev().map(Id::new).collect())),
+            SolverConfig::new().with_level_reordering(true).with_verbosity(Verbosity::Verbose),
+        ];
+        for config in configs.iter() {
+            let mut solver = toy_solver(TrailKind::Differential, 3, config.clone());
+            solver.run();
+            match solver.finalize() {
+                SolverResult::ProvedOptimal { weight, .. } => assert_eq!(3, weight),
+                _ => panic!("The solving with {} wasn't complete", config),
+            }
+        }
+
+        // Pruned down to 512 nodes, an optimal trail is still found, but no longer proved optimal.
+        let config = SolverConfig::new().with_soft_limit(1 << 10).with_prune_target(0.5)
+            .with_verbosity(Verbosity::Quiet);
+        assert_eq!(512, config.prune_limit());
+        let mut solver = toy_solver(TrailKind::Differential, 3, config);
+        solver.run();
+        let result = solver.finalize();
+        assert!(result.is_complete());
+        assert_eq!(Some(3), result.run().bounds.upper);
+        assert!(result.run().bounds.lower <= Some(3));
+    }
+
+    #[test]
+    fn solver_deterministic() {
+        // Whatever the number of threads, two deterministic runs end on the same Master, ids included.
+        let master = || {
+            let config = SolverConfig::new().with_join_order(JoinOrder::SmallestProductFirst).deterministic(42);
+            let mut solver = toy_solver(TrailKind::Differential, 3, config);
+            solver.set_num_threads(4).unwrap();
+            solver.run();
+            let run = solver.finalize().into_run();
+            let mut ids: Vec<Id> = run.master.iter_bdds().map(|(id, _)| *id).collect();
+            ids.sort_unstable();
+            ids.into_iter()
+                .map(|id| run.master.get_bdd(id).unwrap().borrow().to_dot(&DotOptions::default()))
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(master(), master());
+        assert_eq!(Some(42), SolverConfig::new().deterministic(42).seed());
+        assert!(SolverConfig::new().deterministic(42).to_string().ends_with("seed: 42"));
+    }
+}
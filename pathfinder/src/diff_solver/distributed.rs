@@ -0,0 +1,499 @@
+//! Distributed solving, where independent subtrees of the search are solved by worker processes,
+//! possibly on other machines, for the ciphers too large for one.
+//!
+//! The trails are split by the activity of (some of) the S-boxes of the first round: a `Subtree`
+//! fixes which of them are active, and a worker solves it with a `SimpleSolver` restricted to it
+//! by `SimpleSolver::fix_input_activity`. `first_round_subtrees` gives the subtrees of all the
+//! activity patterns of a set of S-boxes, which together cover all the trails.
+//!
+//! The `Coordinator` listens on a TCP socket, hands the subtrees out to the workers connecting to
+//! it (see `run_worker`) as they become idle, and aggregates their outcomes into the bounds of the
+//! whole search. A subtree whose worker disconnects before sending its outcome is handed out
+//! again. Only the kind and the weight bounds of the solving of a subtree are sent back, not the
+//! trails: the best trail is extracted by solving again the subtree of the best outcome.
+//!
+//! The protocol is a line of text per message:
+//! - the worker starts with `HELLO <version>`, `PROTOCOL_VERSION` being the current version;
+//! - the coordinator sends `SUBTREE <index> <start>..<end>:<active> ...`, `active` being 0 or 1,
+//!   or `DONE` once no subtree is left;
+//! - the worker answers a subtree with `OUTCOME <index> <kind> <lower> <upper>`, the kind being
+//!   the one of `OutcomeKind::name` and an unknown bound `-`.
+//!
+//! The workers build the SoC of the cipher themselves, the same as the one of the coordinator.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::io::{self, BufRead, BufReader, Error, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::Range;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crush::reporting::{self, Event};
+
+use super::meta::WeightBounds;
+use super::simple_solver::SolverResult;
+use super::SPFactory;
+
+/// Version of the protocol between the coordinator and the workers.
+pub const PROTOCOL_VERSION: u64 = 1;
+
+/// The source of the records of the coordinator, see `crush::reporting`.
+const SOURCE: &str = "pathfinder.distributed";
+
+/// How long the coordinator waits between two checks for new workers.
+const ACCEPT_POLL: Duration = Duration::from_millis(50);
+
+/// A part of the search: the trails whose first S-box layer has the activity of `activity`, for
+/// each range of input variables (the inputs of an S-box), whether one of them is non zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subtree {
+    pub index: usize,
+    pub activity: Vec<(Range<usize>, bool)>,
+}
+
+/// Return the subtrees of all the activity patterns of the S-boxes reading the input variables
+/// of `sboxes`, e.g. `Cipher::sbox_inputs(0)` or the first few of them. The S-box `i` is active
+/// in the subtree of index `index` if its bit `i` is set.
+///
+/// The number of subtrees doubles with each S-box: a few S-boxes are enough to keep many workers
+/// busy.
+pub fn first_round_subtrees(sboxes: &[Range<usize>]) -> Vec<Subtree> {
+    (0..1 << sboxes.len())
+        .map(|index: usize| Subtree {
+            index,
+            activity: sboxes.iter()
+                .enumerate()
+                .map(|(i, inputs)| (inputs.clone(), (index >> i) & 1 == 1))
+                .collect(),
+        })
+        .collect()
+}
+
+/// How the solving of a subtree ended, the kinds of `SolverResult`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutcomeKind {
+    ProvedOptimal,
+    FeasibleFound,
+    TimedOut,
+    OutOfBudget,
+    MemoryLimited,
+    Infeasible,
+}
+
+impl OutcomeKind {
+    const ALL: [OutcomeKind; 6] = [
+        OutcomeKind::ProvedOptimal,
+        OutcomeKind::FeasibleFound,
+        OutcomeKind::TimedOut,
+        OutcomeKind::OutOfBudget,
+        OutcomeKind::MemoryLimited,
+        OutcomeKind::Infeasible,
+    ];
+
+    /// Return the name of the kind, in snake case.
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutcomeKind::ProvedOptimal => "proved_optimal",
+            OutcomeKind::FeasibleFound => "feasible_found",
+            OutcomeKind::TimedOut => "timed_out",
+            OutcomeKind::OutOfBudget => "out_of_budget",
+            OutcomeKind::MemoryLimited => "memory_limited",
+            OutcomeKind::Infeasible => "infeasible",
+        }
+    }
+
+    /// Returns true if all Shards were joined, see `SolverResult::is_complete`.
+    pub fn is_complete(&self) -> bool {
+        !matches!(self, OutcomeKind::TimedOut | OutcomeKind::OutOfBudget)
+    }
+}
+
+/// The outcome of the solving of the subtree of index `index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubtreeOutcome {
+    pub index: usize,
+    pub kind: OutcomeKind,
+    /// The bounds on the weight of the best trail of the subtree.
+    pub bounds: WeightBounds,
+}
+
+impl SubtreeOutcome {
+    /// Return the outcome of `result`, the solving of the subtree of index `index`.
+    pub fn new<F: SPFactory + Debug>(index: usize, result: &SolverResult<F>) -> SubtreeOutcome {
+        let kind = match result {
+            SolverResult::ProvedOptimal { .. } => OutcomeKind::ProvedOptimal,
+            SolverResult::FeasibleFound { .. } => OutcomeKind::FeasibleFound,
+            SolverResult::TimedOut { .. } => OutcomeKind::TimedOut,
+            SolverResult::OutOfBudget { .. } => OutcomeKind::OutOfBudget,
+            SolverResult::MemoryLimited { .. } => OutcomeKind::MemoryLimited,
+            SolverResult::Infeasible { .. } => OutcomeKind::Infeasible,
+        };
+        SubtreeOutcome { index, kind, bounds: result.run().bounds }
+    }
+}
+
+/// The outcomes of all the subtrees of a distributed solving, and the bounds on the weight of
+/// the optimal trail they give.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistributedResult {
+    /// The outcomes, by index of their subtree.
+    pub outcomes: Vec<SubtreeOutcome>,
+    /// The upper bound is the best trail of all the subtrees, and the lower bound the lowest of
+    /// the lower bounds of the subtrees having trails, unknown if one of them is unknown.
+    pub bounds: WeightBounds,
+}
+
+impl DistributedResult {
+    /// Aggregate `outcomes`, the ones of all the subtrees.
+    pub fn new(mut outcomes: Vec<SubtreeOutcome>) -> DistributedResult {
+        outcomes.sort_by_key(|outcome| outcome.index);
+        let mut bounds = WeightBounds::default();
+        let with_trails: Vec<&SubtreeOutcome> = outcomes.iter()
+            .filter(|outcome| outcome.kind != OutcomeKind::Infeasible)
+            .collect();
+        if with_trails.iter().all(|outcome| outcome.bounds.lower.is_some()) {
+            if let Some(lower) = with_trails.iter().filter_map(|outcome| outcome.bounds.lower).min() {
+                bounds.raise_lower(lower);
+            }
+        }
+        if let Some(upper) = outcomes.iter().filter_map(|outcome| outcome.bounds.upper).min() {
+            bounds.lower_upper(upper);
+        }
+        DistributedResult { outcomes, bounds }
+    }
+
+    /// Returns true if all the subtrees were solved completely.
+    pub fn is_complete(&self) -> bool {
+        self.outcomes.iter().all(|outcome| outcome.kind.is_complete())
+    }
+
+    /// Returns the outcome of the subtree holding the best trail found, if any.
+    pub fn best(&self) -> Option<&SubtreeOutcome> {
+        self.outcomes.iter()
+            .filter(|outcome| outcome.bounds.upper.is_some())
+            .min_by_key(|outcome| outcome.bounds.upper)
+    }
+}
+
+impl Display for DistributedResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let solved = self.outcomes.iter().filter(|outcome| outcome.kind.is_complete()).count();
+        write!(f, "{} of {} subtrees solved. Weight bounds: {}", solved, self.outcomes.len(), self.bounds)
+    }
+}
+
+/// The subtrees left to hand out and the outcomes received, shared by the connections of the
+/// coordinator.
+struct Shared {
+    pending: VecDeque<Subtree>,
+    outcomes: Vec<SubtreeOutcome>,
+    total: usize,
+}
+
+impl Shared {
+    fn is_done(&self) -> bool {
+        self.outcomes.len() == self.total
+    }
+}
+
+/// Hands the subtrees out to the workers and aggregates their outcomes, see the module
+/// documentation.
+#[derive(Debug)]
+pub struct Coordinator {
+    listener: TcpListener,
+    subtrees: Vec<Subtree>,
+}
+
+impl Coordinator {
+    /// Listen at `addr` for the workers solving `subtrees`.
+    pub fn bind<A: ToSocketAddrs>(addr: A, subtrees: Vec<Subtree>) -> io::Result<Coordinator> {
+        Ok(Coordinator { listener: TcpListener::bind(addr)?, subtrees })
+    }
+
+    /// Returns the address the coordinator listens at, e.g. to find the port when bound to port 0.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Hand the subtrees out to the workers connecting, until all their outcomes are received,
+    /// and return them aggregated. The failure of a worker is reported as an `Event::Failure` with
+    /// `crush::reporting`, from the source `pathfinder.distributed`, and its subtree is handed out
+    /// again.
+    ///
+    /// This waits for as long as a subtree is left and no worker takes it.
+    pub fn run(self) -> io::Result<DistributedResult> {
+        let total = self.subtrees.len();
+        let shared = Arc::new((
+            Mutex::new(Shared { pending: self.subtrees.into(), outcomes: Vec::with_capacity(total), total }),
+            Condvar::new(),
+        ));
+        self.listener.set_nonblocking(true)?;
+        let mut connections = Vec::new();
+        while !shared.0.lock().unwrap().is_done() {
+            match self.listener.accept() {
+                Ok((stream, peer)) => {
+                    stream.set_nonblocking(false)?;
+                    let shared = shared.clone();
+                    connections.push(thread::spawn(move || {
+                        if let Err(e) = serve(stream, &shared) {
+                            reporting::report(SOURCE, Event::Failure {
+                                operation: format!("serve the worker at {}", peer),
+                                error: e.to_string(),
+                            });
+                        }
+                    }));
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL),
+                Err(e) => return Err(e),
+            }
+        }
+        for connection in connections {
+            let _ = connection.join();
+        }
+        let outcomes = std::mem::take(&mut shared.0.lock().unwrap().outcomes);
+        Ok(DistributedResult::new(outcomes))
+    }
+}
+
+/// Hand the subtrees out to the worker of `stream` until none is left, putting back the subtree
+/// it was solving if it fails.
+fn serve(stream: TcpStream, shared: &(Mutex<Shared>, Condvar)) -> io::Result<()> {
+    let (state, changed) = shared;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let hello = read_message(&mut reader)?;
+    if hello != format!("HELLO {}", PROTOCOL_VERSION) {
+        return Err(malformed(&hello));
+    }
+    loop {
+        let subtree = {
+            let mut state = state.lock().unwrap();
+            loop {
+                if let Some(subtree) = state.pending.pop_front() {
+                    break subtree;
+                }
+                if state.is_done() {
+                    return writeln!(writer, "DONE");
+                }
+                state = changed.wait(state).unwrap();
+            }
+        };
+        let outcome = writeln!(writer, "{}", encode_subtree(&subtree))
+            .and_then(|_| read_message(&mut reader))
+            .and_then(|message| decode_outcome(&message))
+            .and_then(|outcome| if outcome.index == subtree.index {
+                Ok(outcome)
+            } else {
+                Err(Error::new(ErrorKind::InvalidData,
+                               format!("outcome of subtree {} for subtree {}", outcome.index, subtree.index)))
+            });
+        let mut state = state.lock().unwrap();
+        match outcome {
+            Ok(outcome) => state.outcomes.push(outcome),
+            Err(e) => {
+                state.pending.push_back(subtree);
+                changed.notify_all();
+                return Err(e);
+            }
+        }
+        changed.notify_all();
+    }
+}
+
+/// Connect to the coordinator at `addr`, and solve the subtrees it hands out with `solve` until
+/// none is left. Returns the number of subtrees solved.
+pub fn run_worker<A, S>(addr: A, mut solve: S) -> io::Result<usize>
+    where
+        A: ToSocketAddrs,
+        S: FnMut(&Subtree) -> SubtreeOutcome,
+{
+    let stream = TcpStream::connect(addr)?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    writeln!(writer, "HELLO {}", PROTOCOL_VERSION)?;
+    let mut solved = 0;
+    loop {
+        let message = match read_message(&mut reader) {
+            Ok(message) => message,
+            // The coordinator stops listening once all the outcomes are received
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && solved == 0 => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        if message == "DONE" {
+            return Ok(solved);
+        }
+        let subtree = decode_subtree(&message)?;
+        let outcome = SubtreeOutcome { index: subtree.index, ..solve(&subtree) };
+        writeln!(writer, "{}", encode_outcome(&outcome))?;
+        solved += 1;
+    }
+}
+
+/// Read a message, without its end of line.
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "the connection was closed"));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+fn encode_subtree(subtree: &Subtree) -> String {
+    let mut message = format!("SUBTREE {}", subtree.index);
+    for (range, active) in subtree.activity.iter() {
+        message.push_str(&format!(" {}..{}:{}", range.start, range.end, *active as u8));
+    }
+    message
+}
+
+fn decode_subtree(message: &str) -> io::Result<Subtree> {
+    let mut words = message.split(' ');
+    if words.next() != Some("SUBTREE") {
+        return Err(malformed(message));
+    }
+    let index = parse(words.next(), message)?;
+    let activity = words
+        .map(|word| {
+            let (range, active) = word.split_once(':').ok_or_else(|| malformed(message))?;
+            let (start, end) = range.split_once("..").ok_or_else(|| malformed(message))?;
+            let active = match active {
+                "0" => false,
+                "1" => true,
+                _ => return Err(malformed(message)),
+            };
+            Ok((parse(Some(start), message)?..parse(Some(end), message)?, active))
+        })
+        .collect::<io::Result<_>>()?;
+    Ok(Subtree { index, activity })
+}
+
+fn encode_outcome(outcome: &SubtreeOutcome) -> String {
+    let bound = |bound: Option<u32>| bound.map_or("-".to_string(), |bound| bound.to_string());
+    format!("OUTCOME {} {} {} {}", outcome.index, outcome.kind.name(), bound(outcome.bounds.lower),
+            bound(outcome.bounds.upper))
+}
+
+fn decode_outcome(message: &str) -> io::Result<SubtreeOutcome> {
+    let words: Vec<&str> = message.split(' ').collect();
+    if words.len() != 5 || words[0] != "OUTCOME" {
+        return Err(malformed(message));
+    }
+    let kind = OutcomeKind::ALL.iter()
+        .find(|kind| kind.name() == words[2])
+        .copied()
+        .ok_or_else(|| malformed(message))?;
+    let bound = |word: &str| match word {
+        "-" => Ok(None),
+        _ => parse(Some(word), message).map(Some),
+    };
+    Ok(SubtreeOutcome {
+        index: parse(Some(words[1]), message)?,
+        kind,
+        bounds: WeightBounds { lower: bound(words[3])?, upper: bound(words[4])? },
+    })
+}
+
+fn parse<T: std::str::FromStr>(word: Option<&str>, message: &str) -> io::Result<T> {
+    word.and_then(|word| word.parse().ok()).ok_or_else(|| malformed(message))
+}
+
+fn malformed(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("malformed message: {:?}", message))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::code_gen::cipher::{Cipher, TrailKind};
+    use crate::code_gen::fixture::{toy_solver, Toy};
+    use crate::diff_solver::{SolverConfig, WeightBounds};
+
+    use super::*;
+
+    #[test]
+    fn subtrees() {
+        let subtrees = first_round_subtrees(&[0..4, 4..8]);
+        assert_eq!(4, subtrees.len());
+        assert_eq!(vec![(0..4, true), (4..8, false)], subtrees[1].activity);
+        for subtree in subtrees {
+            assert_eq!(subtree, decode_subtree(&encode_subtree(&subtree)).unwrap());
+        }
+        assert!(decode_subtree("SUBTREE 1 0..4:2").is_err());
+        assert!(decode_subtree("OUTCOME 1").is_err());
+    }
+
+    #[test]
+    fn aggregation() {
+        let outcome = |index, kind, lower, upper| {
+            SubtreeOutcome { index, kind, bounds: WeightBounds { lower, upper } }
+        };
+        let outcomes = vec![
+            outcome(2, OutcomeKind::FeasibleFound, Some(4), Some(6)),
+            outcome(0, OutcomeKind::Infeasible, None, None),
+            outcome(1, OutcomeKind::ProvedOptimal, Some(5), Some(5)),
+        ];
+        for outcome in outcomes.iter() {
+            assert_eq!(*outcome, decode_outcome(&encode_outcome(outcome)).unwrap());
+        }
+        let result = DistributedResult::new(outcomes.clone());
+        assert_eq!(WeightBounds { lower: Some(4), upper: Some(5) }, result.bounds);
+        assert_eq!(1, result.best().unwrap().index);
+        assert!(result.is_complete());
+
+        // A subtree which was stopped early and whose lower bound is unknown
+        let mut outcomes = outcomes;
+        outcomes.push(outcome(3, OutcomeKind::TimedOut, None, None));
+        let result = DistributedResult::new(outcomes);
+        assert_eq!(WeightBounds { lower: None, upper: Some(5) }, result.bounds);
+        assert!(!result.is_complete());
+    }
+
+    #[test]
+    fn coordinator() {
+        let subtrees = first_round_subtrees(&[0..4, 4..8, 8..12]);
+        let coordinator = Coordinator::bind("127.0.0.1:0", subtrees).unwrap();
+        let addr = coordinator.local_addr().unwrap();
+        // The weight of a subtree is its number of active S-boxes
+        let solve = |subtree: &Subtree| {
+            let weight = subtree.activity.iter().filter(|(_, active)| *active).count() as u32;
+            let (kind, bounds) = match weight {
+                0 => (OutcomeKind::Infeasible, WeightBounds::default()),
+                _ => (OutcomeKind::ProvedOptimal, WeightBounds { lower: Some(weight), upper: Some(weight) }),
+            };
+            SubtreeOutcome { index: subtree.index, kind, bounds }
+        };
+        // A worker failing after its hello, whose subtree goes to the others
+        let failing = TcpStream::connect(addr).unwrap();
+        writeln!(&failing, "HELLO {}", PROTOCOL_VERSION).unwrap();
+        let workers: Vec<_> = (0..2)
+            .map(|_| thread::spawn(move || run_worker(addr, solve).unwrap()))
+            .collect();
+        drop(failing);
+        let result = coordinator.run().unwrap();
+        let solved: usize = workers.into_iter().map(|worker| worker.join().unwrap()).sum();
+        assert_eq!(8, solved);
+        let indexes: Vec<usize> = result.outcomes.iter().map(|outcome| outcome.index).collect();
+        assert_eq!((0..8).collect::<Vec<_>>(), indexes);
+        assert_eq!(WeightBounds { lower: Some(1), upper: Some(1) }, result.bounds);
+    }
+
+    #[test]
+    fn distributed_solver() {
+        assert_eq!(vec![0..4, 4..8], Toy.sbox_inputs(0));
+        let coordinator = Coordinator::bind("127.0.0.1:0", first_round_subtrees(&Toy.sbox_inputs(0))).unwrap();
+        let addr = coordinator.local_addr().unwrap();
+        let worker = std::thread::spawn(move || run_worker(addr, |subtree| {
+            let mut solver = toy_solver(TrailKind::Differential, 3, SolverConfig::new());
+            solver.fix_input_activity(&subtree.activity);
+            solver.run();
+            SubtreeOutcome::new(subtree.index, &solver.finalize())
+        }).unwrap());
+        let result = coordinator.run().unwrap();
+        assert_eq!(4, worker.join().unwrap());
+        // Without an active S-box in the first round, only the trivial trail is left
+        assert_eq!(OutcomeKind::Infeasible, result.outcomes[0].kind);
+        assert!(result.is_complete());
+        assert_eq!(WeightBounds { lower: Some(3), upper: Some(3) }, result.bounds);
+    }
+}
@@ -0,0 +1,393 @@
+//! Integral distinguishers through the bit-based division property.
+//!
+//! A division trail through an S-box goes from u to v if the product of the output bits selected
+//! by v contains a monomial divisible by the monomial of the input bits selected by u. Division
+//! trails through a bit permutation are simply permuted, and key additions leave them unchanged.
+//! For ciphers whose linear layer is a bit permutation, the division trails are therefore exactly
+//! the paths of a SoC where the Shards of the S-boxes are based on their division trail table,
+//! see `division_table` and `make_division_soc`.
+//!
+//! Output bit j is balanced if there is no division trail from the input division vector to the
+//! unit vector e<sub>j</sub>. As with impossible differentials, the SoC must be solved without
+//! pruning, and the input division vector is best fixed before solving. The balanced bits are then
+//! the unit vectors which cannot reach the source of `Master`, see `balanced_bits`.
+//!
+//! `integral_distinguisher` does all of it for a `Cipher`: given the input bits taking all values,
+//! the input division vector, it returns the output bits balanced whatever the key.
+
+use std::fmt::Debug;
+use std::io::{Error, ErrorKind};
+use std::ops::Range;
+
+use vob::Vob;
+
+use crush::soc::bdd::Bdd;
+use crush::soc::bdd::differential::PPFactory;
+use crush::soc::Id;
+use crush::soc::system::System;
+
+use crate::code_gen::{LLHandler, SBoxHandler};
+use crate::code_gen::cipher::{make_cipher_solver, Cipher, TrailKind};
+use crate::code_gen::soc_gen;
+use crate::diff_solver::{SPFactory, SolverConfig, Verbosity};
+use crate::diff_solver::impossible::{Difference, ImpossibleDifferentialSearch};
+
+/// Returns the division trail table of the S-box given by `table`, where the entry at (u, v) is 1
+/// if there is a division trail from u to v, and 0 otherwise.
+pub fn division_table(table: &[usize], size_in: usize, size_out: usize) -> Vec<Vec<usize>> {
+    assert_eq!(1 << size_in, table.len());
+    let rows = 1 << size_in;
+    let cols = 1 << size_out;
+
+    let mut dt = vec![vec![0; cols]; rows];
+    for v in 0..cols {
+        // The ANF of the product of the output bits selected by v
+        let mut anf: Vec<bool> = table.iter().map(|y| y & v == v).collect();
+        for i in 0..size_in {
+            for x in 0..rows {
+                if x & (1 << i) != 0 {
+                    anf[x] ^= anf[x ^ (1 << i)];
+                }
+            }
+        }
+
+        for (u, row) in dt.iter_mut().enumerate() {
+            if anf.iter().enumerate().any(|(w, coef)| *coef && w & u == u) {
+                row[v] = 1;
+            }
+        }
+    }
+    dt
+}
+
+/// Make the SoC of the division trails over `nr_rounds` rounds. The Shards given by `sh` are
+/// expected to be based on the division trail tables of the S-boxes (see `division_table`).
+///
+/// Returns an `Error` if the initial layer or the linear layer of one of the rounds of `llh` is
+/// not a bit permutation, as the division trails through it are then not the paths of the SoC.
+/// The layers are checked by applying them to unit vectors, `llh` must therefore accept a state
+/// of any number of variables.
+pub fn make_division_soc<L, S>(llh: &L, sh: &S, nr_rounds: usize) -> Result<(System, Vec<Vec<Id>>), Error>
+    where
+        L: LLHandler,
+        S: SBoxHandler,
+{
+    let units = |size: usize| -> Vec<Vob> {
+        (0..size)
+            .map(|i| {
+                let mut unit = Vob::from_elem(size, false);
+                unit.set(i, true);
+                unit
+            })
+            .collect()
+    };
+    let is_permutation = |size: usize, rows: &[Vob]| {
+        let mut seen = Vob::from_elem(size, false);
+        rows.len() == size && rows.iter().all(|row| {
+            let mut vars = row.iter_set_bits(..);
+            match (vars.next(), vars.next()) {
+                (Some(var), None) if !seen[var] => {
+                    seen.set(var, true);
+                    true
+                }
+                _ => false,
+            }
+        })
+    };
+
+    let size = llh.block_size(0);
+    if !is_permutation(size, &llh.apply_initial_layer(units(size))) {
+        return Err(Error::new(ErrorKind::InvalidInput, "The initial layer is not a bit permutation"));
+    }
+    for round in 1..nr_rounds {
+        let size = llh.block_size(round);
+        if !is_permutation(size, &llh.apply_linear_layer(round, units(size))) {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                                  format!("The linear layer of round {} is not a bit permutation", round)));
+        }
+    }
+    Ok(soc_gen::make_soc(llh, sh, nr_rounds))
+}
+
+/// Returns the output bits which are balanced, given as offsets into `out_vars`.
+///
+/// `master` must be the only Shard of a SoC built from division trail tables and solved without
+/// pruning, where the input division vector was fixed before solving. `out_vars` are the variables
+/// of the output of the last S-box layer.
+pub fn balanced_bits(master: &Bdd, out_vars: Range<usize>) -> Vec<usize> {
+    let nvar = master.level(0).unwrap().get_lhs().len();
+    let mut mask = Vob::from_elem(nvar, false);
+    for var in out_vars.clone() {
+        mask.set(var, true);
+    }
+    let units: Vec<Difference> = out_vars
+        .map(|var| {
+            let mut values = Vob::from_elem(nvar, false);
+            values.set(var, true);
+            Difference::new(mask.clone(), values)
+        })
+        .collect();
+
+    // The input is already fixed, so we only need to check which unit vectors reach the source.
+    let search = ImpossibleDifferentialSearch::with_middle(master, 0);
+    let free = Difference::new(Vob::from_elem(nvar, false), Vob::from_elem(nvar, false));
+    search.search(&[free], &units).into_iter()
+        .map(|(_, o)| o)
+        .collect()
+}
+
+/// Returns the output bits of the last S-box layer of the first `nr_rounds` rounds of `cipher`
+/// which are balanced whatever the key, when the input bits `active` take all values and the
+/// others are constant, by increasing index. See the module documentation.
+///
+/// Returns an `Error` as `make_cipher_soc` does for `TrailKind::Division`, e.g. if the linear
+/// layers of `cipher` are not bit permutations.
+pub fn integral_distinguisher<C, F>(cipher: &C, nr_rounds: usize, active: &[usize], progress: F)
+                                    -> Result<Vec<usize>, Error>
+    where
+        C: Cipher,
+        F: SPFactory + PPFactory + Clone + Debug,
+{
+    let config = SolverConfig::new().with_verbosity(Verbosity::Quiet);
+    let mut solver = make_cipher_solver(cipher, TrailKind::Division, nr_rounds, progress, config)?;
+    let mut division = Vob::from_elem(cipher.block_size(), false);
+    for bit in active {
+        division.set(*bit, true);
+    }
+    solver.fix_input(&division);
+    solver.run();
+    let master = solver.finalize().into_run().master;
+    let nvar = master.get_nvar();
+    let (_, bdd) = master.iter_bdds().next().expect("Master is left once all Shards are joined");
+    let balanced = balanced_bits(&bdd.borrow(), nvar - cipher.block_size()..nvar);
+    Ok(balanced)
+}
+
+#[cfg(test)]
+mod test {
+    use crush::algebra::Matrix;
+
+    use crate::code_gen::cipher::SBox;
+    use crate::code_gen::fixture::{self, Silent};
+    use crate::code_gen::gsf::GenericShard;
+    use crate::diff_solver::post_processing_v5::BaseTable;
+
+    use super::*;
+
+    const PRESENT: [usize; 16] = [0xc, 0x5, 0x6, 0xb, 0x9, 0x0, 0xa, 0xd, 0x3, 0xe, 0xf, 0x8, 0x4, 0x7, 0x1, 0x2];
+
+    /// PRESENT over 16 bits: four PRESENT S-boxes, the linear layer sending bit i to bit 4i mod 15
+    /// (bit 15 staying), and the round keys being the key rotated by three bits a round.
+    struct SmallPresent;
+
+    impl Cipher for SmallPresent {
+        fn name(&self) -> String {
+            "small-present".to_string()
+        }
+
+        fn block_size(&self) -> usize {
+            16
+        }
+
+        fn nr_rounds(&self) -> usize {
+            5
+        }
+
+        fn sbox(&self, _round: usize, _pos: usize) -> SBox {
+            SBox::new(PRESENT.to_vec(), 4, 4).unwrap()
+        }
+
+        fn linear_layer(&self, _round: usize) -> Matrix {
+            let mut rows = vec![Vob::from_elem(16, false); 16];
+            for i in 0..16 {
+                let to = if i == 15 { 15 } else { 4 * i % 15 };
+                rows[to].set(i, true);
+            }
+            Matrix::from_rows(rows)
+        }
+
+        fn key_size(&self) -> usize {
+            16
+        }
+
+        fn round_keys(&self, key: &Vob) -> Vec<Vob> {
+            (0..=self.nr_rounds())
+                .map(|round| (0..16).map(|i| key[(i + 3 * round) % 16]).collect())
+                .collect()
+        }
+    }
+
+    /// The balanced bits of `integral_distinguisher`, found by following every division trail of
+    /// the S-box layers and bit permutations of `cipher`, from the division vector of `active`.
+    fn balanced_by_propagation<C: Cipher>(cipher: &C, nr_rounds: usize, active: &[usize]) -> Vec<usize> {
+        let size = cipher.block_size();
+        let mut reached = vec![false; 1 << size];
+        reached[active.iter().fold(0, |k, bit| k | 1 << bit)] = true;
+        for round in 0..nr_rounds {
+            for (pos, inputs) in cipher.sbox_inputs(round).into_iter().enumerate() {
+                let sbox = cipher.sbox(round, pos);
+                let dt = division_table(sbox.table(), sbox.size_in(), sbox.size_out());
+                let mask = ((1 << inputs.len()) - 1) << inputs.start;
+                let mut next = vec![false; 1 << size];
+                for k in (0..1 << size).filter(|k| reached[*k]) {
+                    let u = (k & mask) >> inputs.start;
+                    for v in (0..dt[u].len()).filter(|v| dt[u][*v] == 1) {
+                        next[k & !mask | v << inputs.start] = true;
+                    }
+                }
+                reached = next;
+            }
+            if round + 1 < nr_rounds {
+                let layer = cipher.linear_layer(round);
+                let mut next = vec![false; 1 << size];
+                for k in (0..1 << size).filter(|k| reached[*k]) {
+                    let moved = (0..size).filter(|i| layer.get_row(*i).unwrap().iter_set_bits(..).any(|j| k >> j & 1 == 1));
+                    next[moved.fold(0, |to, i| to | 1 << i)] = true;
+                }
+                reached = next;
+            }
+        }
+        (0..size).filter(|j| !reached[1 << j]).collect()
+    }
+
+    /// Check that the bits `balanced` at the output of the last S-box layer sum to zero over the
+    /// inputs where the bits `active` take all values, for a few keys and constants.
+    fn check_balanced<C: Cipher>(cipher: &C, nr_rounds: usize, active: &[usize], balanced: &[usize]) {
+        let size = cipher.block_size();
+        let to_vob = |x: usize, len: usize| -> Vob { (0..len).map(|i| (x >> i) & 1 == 1).collect() };
+        // The output bit of the last linear layer each bit of the S-box layer moves to
+        let layer = cipher.linear_layer(nr_rounds - 1);
+        let moved = |j: usize| (0..size).find(|i| layer.get_row(*i).unwrap().get(j) == Some(true)).unwrap();
+        let window = crate::code_gen::cipher::RoundWindow::new(0..nr_rounds);
+        for seed in 0..4usize {
+            let key = to_vob(seed.wrapping_mul(0x9e37_79b9) >> 3, cipher.key_size());
+            let constant = to_vob(seed.wrapping_mul(0x85eb_ca6b) >> 5, size);
+            let mut sum = Vob::from_elem(size, false);
+            for x in 0..1usize << active.len() {
+                let mut plaintext = constant.clone();
+                for (i, bit) in active.iter().enumerate() {
+                    plaintext.set(*bit, (x >> i) & 1 == 1);
+                }
+                sum.xor(&cipher.encrypt_rounds(&plaintext, &key, &window));
+            }
+            for j in balanced {
+                assert!(!sum[moved(*j)], "bit {} of round {} isn't balanced for {:?}", j, nr_rounds, active);
+            }
+        }
+    }
+
+    /// Two 2 bit S-boxes, the linear layer of each round swapping the second bits of the S-boxes
+    /// (or XORing the first bit into the second with `xor`).
+    struct Toy {
+        table: Vec<usize>,
+        xor: bool,
+    }
+
+    impl LLHandler for Toy {
+        fn block_size(&self, _round: usize) -> usize {
+            4
+        }
+
+        fn apply_linear_layer(&self, _round: usize, state: Vec<Vob>) -> Vec<Vob> {
+            let mut second = state[1].clone();
+            if self.xor {
+                second.xor(&state[0]);
+            }
+            vec![state[0].clone(), state[3].clone(), state[2].clone(), second]
+        }
+    }
+
+    impl SBoxHandler for Toy {
+        fn num_sboxes(&self, _round: usize) -> usize {
+            2
+        }
+
+        fn sbox_size_in(&self, _round: usize, _pos: usize) -> usize {
+            2
+        }
+
+        fn sbox_size_out(&self, _round: usize, _pos: usize) -> usize {
+            2
+        }
+
+        fn bt_generic_shard(&self, _round: usize, _pos: usize) -> GenericShard {
+            let table = BaseTable::new(division_table(&self.table, 2, 2)).unwrap();
+            GenericShard::new(&table, 2, 2)
+        }
+    }
+
+    #[test]
+    fn division_soc() {
+        let toy = Toy { table: vec![0, 2, 3, 1], xor: false };
+        let (soc, rounds) = make_division_soc(&toy, &toy, 3).unwrap();
+        assert_eq!(vec![vec![Id::new(0), Id::new(1)], vec![Id::new(2), Id::new(3)], vec![Id::new(4), Id::new(5)]],
+                   rounds);
+        assert_eq!(4 + 3 * 4, soc.get_nvar());
+        // The second round reads the outputs of the first one through the permutation
+        let lhss = |id: usize| -> Vec<Vec<usize>> {
+            soc.get_bdd(Id::new(id)).unwrap().borrow().iter_levels()
+                .map(|level| level.iter_set_lhs().collect())
+                .collect()
+        };
+        assert_eq!(vec![vec![4], vec![7], vec![8], vec![9], vec![]], lhss(2));
+        assert_eq!(vec![vec![6], vec![5], vec![10], vec![11], vec![]], lhss(3));
+
+        let toy = Toy { table: vec![0, 2, 3, 1], xor: true };
+        assert!(make_division_soc(&toy, &toy, 1).is_ok());
+        assert!(make_division_soc(&toy, &toy, 2).is_err());
+    }
+
+    #[test]
+    fn integral_distinguishers() {
+        // All the bits of one S-box taking all values, a single trail reaches the next round:
+        // after one round, every bit is balanced
+        assert_eq!((0..8).collect::<Vec<_>>(), integral_distinguisher(&fixture::Toy, 1, &[0, 1, 2, 3], Silent).unwrap());
+        let cases: [&[usize]; 4] = [&[0, 1, 2, 3], &[0, 1, 2, 3, 4, 5, 6], &[1, 2, 3, 4, 5, 6, 7], &[0, 2, 4, 6, 7]];
+        for active in cases.iter() {
+            for nr_rounds in 1..=3 {
+                let balanced = integral_distinguisher(&fixture::Toy, nr_rounds, active, Silent).unwrap();
+                assert_eq!(balanced_by_propagation(&fixture::Toy, nr_rounds, active), balanced, "{:?}", active);
+                check_balanced(&fixture::Toy, nr_rounds, active, &balanced);
+            }
+        }
+
+        // Over 16 bits, 15 active bits leave every bit balanced after 5 rounds, 12 active bits
+        // only 7 bits after 4 rounds
+        let all: Vec<usize> = (0..16).collect();
+        let cases: [(Vec<usize>, usize, &[usize]); 2] = [((1..16).collect(), 5, &all), ((0..12).collect(), 4, &[0, 1, 2, 3, 4, 8, 12])];
+        for (active, nr_rounds, expected) in cases.iter() {
+            let balanced = integral_distinguisher(&SmallPresent, *nr_rounds, active, Silent).unwrap();
+            assert_eq!(expected.to_vec(), balanced);
+            assert_eq!(balanced_by_propagation(&SmallPresent, *nr_rounds, active), balanced);
+            check_balanced(&SmallPresent, *nr_rounds, active, &balanced);
+        }
+    }
+
+    #[test]
+    fn division_table_identity() {
+        let identity: Vec<usize> = (0..8).collect();
+        let dt = division_table(&identity, 3, 3);
+        for u in 0..8 {
+            for v in 0..8 {
+                assert_eq!((v & u == u) as usize, dt[u][v]);
+            }
+        }
+    }
+
+    #[test]
+    fn division_table_present() {
+        let dt = division_table(&PRESENT, 4, 4);
+
+        // Only the zero vector reaches the zero vector
+        assert_eq!(1, dt[0][0]);
+        assert!((1..16).all(|u| dt[u][0] == 0));
+        // The product of all output bits of a permutation has full degree
+        assert!((0..16).all(|v| dt[0xf][v] == (v == 0xf) as usize));
+        // Any trail from u is also a trail from anything u divides
+        for u in 0..16 {
+            for smaller in (0..16).filter(|s| s & u == *s) {
+                assert!((0..16).all(|v| dt[smaller][v] >= dt[u][v]));
+            }
+        }
+    }
+}
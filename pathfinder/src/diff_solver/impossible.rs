@@ -0,0 +1,252 @@
+//! Search for impossible differentials in a solved SoC.
+//!
+//! When the `SimpleSolver` has joined every Shard into `Master` without pruning, each path of
+//! `Master` is a valid differential trail, and a differential (Δin, Δout) is impossible if and
+//! only if no path of `Master` agrees with both Δin and Δout. An unpruned `Master` quickly grows
+//! out of hand, so Δin is typically fixed before solving (see `SimpleSolver::fix_input`), which
+//! keeps only the trails starting with Δin.
+//!
+//! Rather than fixing Δout in the SoC as well (an empty join would then be a contradiction, see
+//! `Bdd::absorb`), the search propagates from both ends of `Master`: Δin is propagated downwards
+//! from the source and Δout upwards from the sink, until they meet at a middle level. The differential is impossible if no node of the middle level is reached from
+//! both sides. As the propagation of a difference only depends on that difference, the reached
+//! nodes are computed once per candidate, and then intersected for every candidate pair.
+//!
+//! A level is fixed by a difference only if all the variables of its LHS are fixed by it. A level
+//! depending on both ends, or on variables which are fixed by neither, is left free. The search
+//! may therefore miss some impossible differentials, but never reports a possible one.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+
+use vob::Vob;
+
+use crush::soc::bdd::Bdd;
+use crush::soc::Id;
+
+/// A difference on some of the variables of a SoC. Variables outside of `mask` are left free.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    mask: Vob,
+    values: Vob,
+}
+
+impl Difference {
+    /// `values` gives the difference of the variables set in `mask`. Both must have the same
+    /// length as the LHSs of the SoC.
+    pub fn new(mask: Vob, values: Vob) -> Difference {
+        assert_eq!(mask.len(), values.len());
+        let mut values = values;
+        values.and(&mask);
+        Difference { mask, values }
+    }
+
+    /// All the differences on the variables `vars` activating a single S-box, where the S-boxes
+    /// are consecutive chunks of `sbox_size` variables. Bit j of the S-box difference is the
+    /// difference of the j'th variable of the chunk. All other variables of `vars` are zero.
+    pub fn single_sbox(nvar: usize, vars: Range<usize>, sbox_size: usize) -> Vec<Difference> {
+        assert_eq!(0, vars.len() % sbox_size);
+        let mut mask = Vob::from_elem(nvar, false);
+        for var in vars.clone() {
+            mask.set(var, true);
+        }
+
+        let mut res = Vec::new();
+        for start in vars.step_by(sbox_size) {
+            for delta in 1..(1usize << sbox_size) {
+                let mut values = Vob::from_elem(nvar, false);
+                for j in 0..sbox_size {
+                    values.set(start + j, (delta >> j) & 1 == 1);
+                }
+                res.push(Difference::new(mask.clone(), values));
+            }
+        }
+        res
+    }
+
+    /// The difference of the variables set in the mask. Any other variable is unset.
+    pub fn values(&self) -> &Vob {
+        &self.values
+    }
+
+    /// The value of `lhs` under this difference, or None if `lhs` is not fixed by it.
+    fn value_of(&self, lhs: &Vob) -> Option<bool> {
+        let mut free = lhs.clone();
+        free.and(&self.mask);
+        if free != *lhs {
+            return None;
+        }
+        free.and(&self.values);
+        Some(free.iter_set_bits(..).count() % 2 == 1)
+    }
+
+    #[inline]
+    fn allows(&self, lhs: &Vob, edge: bool) -> bool {
+        self.value_of(lhs).is_none_or(|value| value == edge)
+    }
+}
+
+impl fmt::Display for Difference {
+    /// The values of the masked variables as a hex string, the first masked variable being the
+    /// least significant bit.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bits: Vec<bool> = self.mask.iter_set_bits(..)
+            .map(|var| self.values.get(var).unwrap())
+            .collect();
+        let digits: String = bits.chunks(4)
+            .rev()
+            .map(|nibble| {
+                let digit = nibble.iter().rev().fold(0, |acc, bit| (acc << 1) | *bit as u32);
+                std::char::from_digit(digit, 16).unwrap()
+            })
+            .collect();
+        write!(f, "0x{}", digits)
+    }
+}
+
+/// Search for impossible differentials in `master`, which must be the only Shard of a solved SoC
+/// where no pruning has taken place.
+pub struct ImpossibleDifferentialSearch<'a> {
+    master: &'a Bdd,
+    middle: usize,
+}
+
+impl<'a> ImpossibleDifferentialSearch<'a> {
+    /// The two ends meet at the middle level of `master`.
+    pub fn new(master: &'a Bdd) -> Self {
+        let middle = master.get_sink_level_index() / 2;
+        Self::with_middle(master, middle)
+    }
+
+    /// The two ends meet at level `middle` of `master`.
+    pub fn with_middle(master: &'a Bdd, middle: usize) -> Self {
+        assert!(middle <= master.get_sink_level_index());
+        ImpossibleDifferentialSearch { master, middle }
+    }
+
+    /// Returns the nodes of the middle level reached from the source, under `delta_in`.
+    pub fn forward(&self, delta_in: &Difference) -> HashSet<Id> {
+        let mut reached: HashSet<Id> = self.master.level(0).unwrap()
+            .iter_nodes()
+            .map(|(id, _)| *id)
+            .collect();
+
+        for depth in 0..self.middle {
+            let level = self.master.level(depth).unwrap();
+            let lhs = level.get_lhs();
+            let mut next = HashSet::new();
+            for id in reached.iter() {
+                let node = level.get_node(id).unwrap();
+                if let Some(e0) = node.get_e0() {
+                    if delta_in.allows(&lhs, false) {
+                        next.insert(e0);
+                    }
+                }
+                if let Some(e1) = node.get_e1() {
+                    if delta_in.allows(&lhs, true) {
+                        next.insert(e1);
+                    }
+                }
+            }
+            reached = next;
+        }
+        reached
+    }
+
+    /// Returns the nodes of the middle level from which the sink is reached, under `delta_out`.
+    pub fn backward(&self, delta_out: &Difference) -> HashSet<Id> {
+        let sink = self.master.get_sink_level_index();
+        let mut reached: HashSet<Id> = self.master.level(sink).unwrap()
+            .iter_nodes()
+            .map(|(id, _)| *id)
+            .collect();
+
+        for depth in (self.middle..sink).rev() {
+            let level = self.master.level(depth).unwrap();
+            let lhs = level.get_lhs();
+            let allow_0 = delta_out.allows(&lhs, false);
+            let allow_1 = delta_out.allows(&lhs, true);
+            reached = level.iter_nodes()
+                .filter(|(_, node)| {
+                    (allow_0 && node.get_e0().is_some_and(|e0| reached.contains(&e0)))
+                        || (allow_1 && node.get_e1().is_some_and(|e1| reached.contains(&e1)))
+                })
+                .map(|(id, _)| *id)
+                .collect();
+        }
+        reached
+    }
+
+    /// Returns true if no trail goes from `delta_in` to `delta_out`.
+    pub fn is_impossible(&self, delta_in: &Difference, delta_out: &Difference) -> bool {
+        let above = self.forward(delta_in);
+        self.backward(delta_out).is_disjoint(&above)
+    }
+
+    /// Try every pair of `inputs` and `outputs`, and return the indices of the impossible ones.
+    pub fn search(&self, inputs: &[Difference], outputs: &[Difference]) -> Vec<(usize, usize)> {
+        let above: Vec<HashSet<Id>> = inputs.iter().map(|d| self.forward(d)).collect();
+        let below: Vec<HashSet<Id>> = outputs.iter().map(|d| self.backward(d)).collect();
+
+        let mut res = Vec::new();
+        for (i, a) in above.iter().enumerate() {
+            for (o, b) in below.iter().enumerate() {
+                if a.is_disjoint(b) {
+                    res.push((i, o));
+                }
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use vob::Vob;
+
+    use crush::soc::utils::{build_bdd_from_spec, BddSpec, LevelSpec, NodeSpec};
+
+    use super::*;
+
+    fn level(var: Option<i64>, nodes: &[(usize, usize, usize)]) -> LevelSpec {
+        LevelSpec::new(var.into_iter().collect(),
+                       nodes.iter()
+                           .map(|(id, e0, e1)| NodeSpec::new(Id::new(*id), Id::new(*e0), Id::new(*e1)))
+                           .collect())
+    }
+
+    fn difference(nvar: usize, fixed: &[(usize, bool)]) -> Difference {
+        let mut mask = Vob::from_elem(nvar, false);
+        let mut values = Vob::from_elem(nvar, false);
+        for (var, value) in fixed {
+            mask.set(*var, true);
+            values.set(*var, *value);
+        }
+        Difference::new(mask, values)
+    }
+
+    #[test]
+    fn miss_in_the_middle() {
+        // x0 = x2, and x1 = 1 => x3 = 0
+        let master = build_bdd_from_spec(&mut BddSpec::new(Id::new(0), vec![
+            level(Some(0), &[(1, 2, 3)]),
+            level(Some(1), &[(2, 4, 5), (3, 6, 7)]),
+            level(Some(2), &[(4, 8, 0), (5, 9, 0), (6, 0, 8), (7, 0, 9)]),
+            level(Some(3), &[(8, 10, 10), (9, 10, 0)]),
+            level(None, &[(10, 0, 0)]),
+        ]), 4);
+        let search = ImpossibleDifferentialSearch::new(&master);
+
+        assert!(!search.is_impossible(&difference(4, &[(0, true)]), &difference(4, &[(2, true)])));
+        assert!(search.is_impossible(&difference(4, &[(0, true)]), &difference(4, &[(2, false)])));
+        assert!(search.is_impossible(&difference(4, &[(1, true)]), &difference(4, &[(3, true)])));
+        // An empty difference fixes no level
+        assert!(!search.is_impossible(&difference(4, &[(0, true), (1, true)]), &difference(4, &[])));
+
+        let inputs = Difference::single_sbox(4, 0..2, 1);
+        let outputs = Difference::single_sbox(4, 2..4, 1);
+        // (x0, x1) = (1, 0) => (x2, x3) = (1, 0) is the only possible pair
+        assert_eq!(vec![(0, 1), (1, 0), (1, 1)], search.search(&inputs, &outputs));
+    }
+}
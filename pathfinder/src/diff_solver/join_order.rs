@@ -0,0 +1,163 @@
+//! Heuristics for which Shard a `SimpleSolver` joins into `Master` next.
+//!
+//! The order of the joins dominates the peak memory of a solving: each join brings linear
+//! dependencies between the LHS's of `Master` and the ones of the joined Shard, and the absorption
+//! of a dependency can double the size of `Master`. The Shards are always joined round by round,
+//! so a heuristic only picks the next Shard among the ones left in the current round, once per
+//! join, as `Master` changes with every join. The dependencies within `Master` are then absorbed
+//! nearest first, whatever the heuristic.
+//!
+//! The heuristics are:
+//! - `JoinOrder::Static`: the order of the rounds given to `SimpleSolver::new`, i.e. the order of
+//!   the Shards in the SoC file;
+//! - `JoinOrder::SmallestProductFirst`: the Shard whose join gives the smallest `Master` in the
+//!   worst case, see `estimated_product`;
+//! - `JoinOrder::FewestNewVariables`: the Shard bringing the fewest variables which `Master`
+//!   doesn't depend on yet, see `new_variables`;
+//! - `JoinOrder::Permutation`: an order supplied by the user.
+//!
+//! Ties are broken by the static order.
+
+use std::fmt;
+
+use vob::Vob;
+
+use crush::algebra::{self, Matrix};
+use crush::soc::Id;
+use crush::soc::system::System;
+
+/// Which Shard to join next, see the module documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum JoinOrder {
+    /// The order given to `SimpleSolver::new`.
+    Static,
+    /// The Shard with the lowest `estimated_product` first.
+    SmallestProductFirst,
+    /// The Shard with the fewest `new_variables` first.
+    FewestNewVariables,
+    /// The Shards in the order of this permutation of their Ids. The Shards missing from it are
+    /// joined last, in the static order.
+    Permutation(Vec<Id>),
+}
+
+impl JoinOrder {
+    /// Return the index in `candidates`, the Shards left to join in the current round, of the one
+    /// to join next into `master`.
+    ///
+    /// Panics if `candidates` is empty, or if one of them or `master` isn't in `soc`.
+    pub fn next(&self, soc: &System, master: Id, candidates: &[Id]) -> usize {
+        assert!(!candidates.is_empty(), "There is no Shard left to join");
+        match self {
+            JoinOrder::Static => 0,
+            JoinOrder::SmallestProductFirst => {
+                let lhs = soc.get_bdd(master).unwrap().borrow().get_lhs();
+                let nodes = soc.get_bdd(master).unwrap().borrow().get_size();
+                min_index(candidates, |id| estimated_product(soc, &lhs, nodes, *id))
+            },
+            JoinOrder::FewestNewVariables => {
+                let variables = variables(&soc.get_bdd(master).unwrap().borrow().get_lhs());
+                min_index(candidates, |id| new_variables(soc, &variables, *id))
+            },
+            JoinOrder::Permutation(order) => {
+                min_index(candidates, |id| order.iter().position(|o| o == id).unwrap_or(order.len()))
+            },
+        }
+    }
+}
+
+impl fmt::Display for JoinOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JoinOrder::Static => write!(f, "static"),
+            JoinOrder::SmallestProductFirst => write!(f, "smallest_product_first"),
+            JoinOrder::FewestNewVariables => write!(f, "fewest_new_variables"),
+            JoinOrder::Permutation(order) => {
+                let ids: Vec<String> = order.iter().map(|id| id.to_string()).collect();
+                write!(f, "permutation({})", ids.join(" "))
+            },
+        }
+    }
+}
+
+/// Return the number of nodes of `Master` after joining the Shard `id` and absorbing the new
+/// linear dependencies, in the worst case: each absorption doubles the size of the joined `Bdd`.
+/// `lhs` and `nodes` are the LHS's and the number of nodes of `Master`.
+pub fn estimated_product(soc: &System, lhs: &[Vob], nodes: usize, id: Id) -> usize {
+    let shard = soc.get_bdd(id).unwrap().borrow();
+    let shard_lhs = shard.get_lhs();
+    let joined = nodes + shard.get_size();
+    let dependencies = new_dependencies(lhs, &shard_lhs);
+    joined.saturating_mul(1 << dependencies.min(usize::BITS as usize - 1))
+}
+
+/// Return the number of variables the LHS's of the Shard `id` depend on, and which aren't in
+/// `variables`, the variables `Master` depends on (see `variables`).
+pub fn new_variables(soc: &System, variables: &Vob, id: Id) -> usize {
+    let shard_variables = self::variables(&soc.get_bdd(id).unwrap().borrow().get_lhs());
+    shard_variables.iter_set_bits(..)
+        .filter(|var| variables.get(*var) != Some(true))
+        .count()
+}
+
+/// Return the variables any of `lhs` depends on.
+pub fn variables(lhs: &[Vob]) -> Vob {
+    let mut variables = Vob::from_elem(lhs.first().map_or(0, |l| l.len()), false);
+    for l in lhs {
+        for var in l.iter_set_bits(..) {
+            variables.set(var, true);
+        }
+    }
+    variables
+}
+
+/// Return the number of linear dependencies between `top` and `bottom` which are not within one
+/// of them.
+fn new_dependencies(top: &[Vob], bottom: &[Vob]) -> usize {
+    let rank = |rows: Vec<Vob>| if rows.is_empty() { 0 } else { algebra::rank(&Matrix::from_rows(rows)) };
+    let joined = top.iter().chain(bottom).cloned().collect();
+    rank(top.to_vec()) + rank(bottom.to_vec()) - rank(joined)
+}
+
+/// Return the index of the first of `candidates` with the lowest `key`.
+fn min_index<K: Ord, F: Fn(&Id) -> K>(candidates: &[Id], key: F) -> usize {
+    candidates.iter()
+        .enumerate()
+        .min_by_key(|(i, id)| (key(id), *i))
+        .map(|(i, _)| i)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn vob(bits: &[usize], len: usize) -> Vob {
+        let mut v = Vob::from_elem(len, false);
+        for bit in bits {
+            v.set(*bit, true);
+        }
+        v
+    }
+
+    #[test]
+    fn dependencies_and_variables() {
+        let top = vec![vob(&[0], 4), vob(&[1], 4)];
+        let bottom = vec![vob(&[0, 1], 4), vob(&[2], 4)];
+        assert_eq!(1, new_dependencies(&top, &bottom));
+        assert_eq!(0, new_dependencies(&top, &[vob(&[3], 4)]));
+        assert_eq!(vob(&[0, 1, 2], 4), variables(&bottom));
+        assert_eq!(0, variables(&[]).len());
+    }
+
+    #[test]
+    fn permutation_order() {
+        let soc = System::new();
+        let ids = [Id::new(3), Id::new(1), Id::new(2)];
+        let order = JoinOrder::Permutation(vec![Id::new(1), Id::new(2)]);
+        assert_eq!(1, order.next(&soc, Id::new(0), &ids));
+        assert_eq!(0, order.next(&soc, Id::new(0), &ids[..1]));
+        assert_eq!(0, JoinOrder::Static.next(&soc, Id::new(0), &ids));
+        assert_eq!("permutation(1 2)", order.to_string());
+    }
+}
@@ -0,0 +1,251 @@
+//! A library on disk of the SoCs built and solved before, such that repeated experiments on the
+//! same cipher skip rebuilding and re-solving identical structures. Where the `Librarian` keeps the
+//! history of a single solving, the `Library` keeps its work across runs.
+//!
+//! An entry is identified by a `LibraryKey`: the name of the cipher, the number of rounds and a
+//! hash of the constraints it was made under (e.g. the kind of trails, the config of the solver or
+//! a fixed input), and holds either
+//! - a SoC along with the Ids of its Shards round by round, i.e. the S-box layer Shards as made by
+//!   `code_gen::cipher::make_cipher_soc`, see `store_soc`, or
+//! - the state of a solver once all Shards are joined, as a checkpoint (see `checkpoint`), from
+//!   which a finished `SimpleSolver` is resumed, see `store_solved`.
+//!
+//! Each entry is a file of the directory of the library, named after its key. A SoC entry starts
+//! with the bytes `LIBRARY_MAGIC` and `LIBRARY_VERSION`, followed by its key, the Ids of its rounds
+//! and the SoC as a binary snapshot (see `crush::soc::binary`). As for the checkpoints, the entries
+//! are written to a temporary file renamed afterwards.
+//!
+//! The constraints hash is FNV-1a, which is stable across runs and platforms. The key doesn't tell
+//! two ciphers of the same name apart, so the name must identify the cipher.
+
+use std::fmt::{self, Debug, Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crush::soc::bdd::differential::PPFactory;
+use crush::soc::Id;
+use crush::soc::system::System;
+
+use super::checkpoint::{read_ids, read_u64, read_usize, write_ids, write_u64};
+use super::config::SolverConfig;
+use super::meta::SPFactory;
+use super::simple_solver::SimpleSolver;
+
+/// The bytes starting a SoC entry of a `Library`.
+pub const LIBRARY_MAGIC: &[u8; 8] = b"PFLIBSOC";
+/// Version of the SoC entries written by this module.
+pub const LIBRARY_VERSION: u64 = 1;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// The key of an entry of a `Library`, see the module documentation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LibraryKey {
+    cipher: String,
+    rounds: usize,
+    constraints: u64,
+}
+
+impl LibraryKey {
+    /// The key of the entries of `rounds` rounds of the cipher `cipher`, without constraints.
+    pub fn new(cipher: &str, rounds: usize) -> LibraryKey {
+        LibraryKey { cipher: cipher.to_string(), rounds, constraints: FNV_OFFSET_BASIS }
+    }
+
+    /// Add `constraint` to the constraints of the key. The order of the constraints matters.
+    pub fn with_constraint(mut self, constraint: &str) -> LibraryKey {
+        // The length first, such that ("ab", "c") and ("a", "bc") differ
+        let len = (constraint.len() as u64).to_le_bytes();
+        for byte in len.iter().chain(constraint.as_bytes()) {
+            self.constraints = (self.constraints ^ *byte as u64).wrapping_mul(FNV_PRIME);
+        }
+        self
+    }
+
+    #[inline]
+    pub fn cipher(&self) -> &str {
+        &self.cipher
+    }
+
+    #[inline]
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    /// The hash of the constraints of the key.
+    #[inline]
+    pub fn constraints(&self) -> u64 {
+        self.constraints
+    }
+
+    /// The name of the file of the entry of the key, with the extension `extension`. The
+    /// characters of the name of the cipher other than ASCII alphanumerics, '-' and '_' are
+    /// replaced by '_'.
+    fn file_name(&self, extension: &str) -> String {
+        let cipher: String = self.cipher.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        format!("{}_{}r_{:016x}.{}", cipher, self.rounds, self.constraints, extension)
+    }
+}
+
+impl Display for LibraryKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {} rounds, constraints {:016x}", self.cipher, self.rounds, self.constraints)
+    }
+}
+
+/// A library of SoCs in a directory, see the module documentation.
+#[derive(Debug, Clone)]
+pub struct Library {
+    dir: PathBuf,
+}
+
+impl Library {
+    /// Open the library in the directory at `dir`, creating the directory if it doesn't exist.
+    pub fn open(dir: &Path) -> io::Result<Library> {
+        fs::create_dir_all(dir)?;
+        Ok(Library { dir: dir.to_path_buf() })
+    }
+
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Store `soc` and the Ids of its Shards round by round, `rounds`, as the SoC entry of `key`,
+    /// replacing the previous one, if any.
+    pub fn store_soc(&self, key: &LibraryKey, soc: &System, rounds: &[Vec<Id>]) -> io::Result<()> {
+        let path = self.dir.join(key.file_name("soc"));
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let file = File::create(&tmp_path)?;
+        let mut writer = BufWriter::new(&file);
+        writer.write_all(LIBRARY_MAGIC)?;
+        write_u64(&mut writer, LIBRARY_VERSION)?;
+        write_u64(&mut writer, key.cipher.len() as u64)?;
+        writer.write_all(key.cipher.as_bytes())?;
+        write_u64(&mut writer, key.rounds as u64)?;
+        write_u64(&mut writer, key.constraints)?;
+        write_u64(&mut writer, rounds.len() as u64)?;
+        for round in rounds.iter() {
+            write_ids(&mut writer, round)?;
+        }
+        soc.serialize_binary(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Load the SoC entry of `key`, stored by `store_soc`, or `None` if there is none.
+    ///
+    /// Returns an `Error` of kind `ErrorKind::Unsupported` if the entry was written by a later
+    /// version, and of kind `ErrorKind::InvalidData` if it is malformed.
+    pub fn load_soc(&self, key: &LibraryKey) -> io::Result<Option<(System, Vec<Vec<Id>>)>> {
+        let file = match File::open(self.dir.join(key.file_name("soc"))) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut reader = BufReader::new(file);
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != LIBRARY_MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "not a SoC entry of a library"));
+        }
+        let version = read_u64(&mut reader)?;
+        if version > LIBRARY_VERSION {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("library entry version {} is newer than the supported {}", version, LIBRARY_VERSION),
+            ));
+        }
+        let mut cipher = vec![0; read_usize(&mut reader)?];
+        reader.read_exact(&mut cipher)?;
+        let rounds = read_usize(&mut reader)?;
+        let constraints = read_u64(&mut reader)?;
+        // Another key whose file name is the same, e.g. a cipher name differing in a replaced
+        // character
+        if cipher != key.cipher.as_bytes() || rounds != key.rounds || constraints != key.constraints {
+            return Ok(None);
+        }
+        let mut ids = Vec::new();
+        for _ in 0..read_usize(&mut reader)? {
+            ids.push(read_ids(&mut reader)?);
+        }
+        let soc = System::deserialize_binary(&mut reader)?;
+        Ok(Some((soc, ids)))
+    }
+
+    /// Store the state of `solver` as the solved entry of `key`, replacing the previous one, if
+    /// any. See `SimpleSolver::set_library` to store it once all Shards are joined.
+    pub fn store_solved<F>(&self, key: &LibraryKey, solver: &SimpleSolver<F>) -> io::Result<()>
+        where
+            F: SPFactory + PPFactory + Clone + Debug,
+    {
+        solver.checkpoint(&self.dir.join(key.file_name("solved")))
+    }
+
+    /// Resume the solver of the solved entry of `key`, stored by `store_solved`, with
+    /// `progress_arena` and `config`, or return `None` if there is none. As for
+    /// `SimpleSolver::resume_from_checkpoint`, `run` skips the rounds already done, i.e. all of
+    /// them for a solver stored once all Shards were joined.
+    pub fn load_solved<F>(&self, key: &LibraryKey, progress_arena: F, config: SolverConfig)
+                          -> io::Result<Option<SimpleSolver<F>>>
+        where
+            F: SPFactory + PPFactory + Clone + Debug,
+    {
+        let path = self.dir.join(key.file_name("solved"));
+        if !path.exists() {
+            return Ok(None);
+        }
+        SimpleSolver::resume_from_checkpoint(&path, progress_arena, config).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::code_gen::cipher::{make_cipher_soc, make_cipher_solver_with_library, TrailKind};
+    use crate::code_gen::fixture::{Silent, Toy};
+    use crate::diff_solver::SolverResult;
+
+    use super::*;
+
+    #[test]
+    fn library() {
+        let dir = std::env::temp_dir().join(format!("pathfinder_library_{}", std::process::id()));
+        let library = Library::open(&dir).unwrap();
+        let key = LibraryKey::new("toy", 3).with_constraint("Differential");
+        assert_ne!(LibraryKey::new("toy", 3), key);
+        assert_ne!(LibraryKey::new("toy", 3).with_constraint("ab").with_constraint("c"),
+                   LibraryKey::new("toy", 3).with_constraint("a").with_constraint("bc"));
+        assert!(library.load_soc(&key).unwrap().is_none());
+
+        // The first run makes the SoC and stores it, then stores the solving
+        let mut solver = make_cipher_solver_with_library(&Toy, TrailKind::Differential, 3, Silent,
+                                                         SolverConfig::new(), &library).unwrap();
+        let (soc, rounds) = library.load_soc(&key).unwrap().unwrap();
+        let (made, made_rounds) = make_cipher_soc(&Toy, TrailKind::Differential, 3).unwrap();
+        assert_eq!(made_rounds, rounds);
+        assert_eq!((made.get_nvar(), made.get_size()), (soc.get_nvar(), soc.get_size()));
+        solver.run();
+        assert!(matches!(solver.finalize(), SolverResult::ProvedOptimal { weight: 3, .. }));
+        assert_eq!(2, std::fs::read_dir(&dir).unwrap().count());
+
+        // The next one resumes the solving, with nothing left to join
+        let mut solver = make_cipher_solver_with_library(&Toy, TrailKind::Differential, 3, Silent,
+                                                         SolverConfig::new(), &library).unwrap();
+        assert_eq!(1, solver.soc().iter_bdds().count());
+        solver.run();
+        assert!(matches!(solver.finalize(), SolverResult::ProvedOptimal { weight: 3, .. }));
+
+        // Under another config, only the SoC is reused
+        let config = SolverConfig::new().with_level_reordering(true);
+        let solver = make_cipher_solver_with_library(&Toy, TrailKind::Differential, 3, Silent, config, &library).unwrap();
+        assert!(solver.soc().iter_bdds().count() > 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+//! Support for linear trails.
+//!
+//! A linear trail is a sequence of masks, one per state, such that each S-box connects its input
+//! mask to its output mask with a non-zero correlation, given by its Linear Approximation Table
+//! (LAT). The search for a linear trail is therefore the same as the search for a differential
+//! trail, except that the Shards of the S-boxes are based on the LAT rather than on the DDT, and
+//! that masks go through the linear layers differently than differences: a mask a at the input of
+//! an invertible linear layer M becomes the mask (M<sup>-1</sup>)<sup>T</sup>a at its output. See
+//! `make_linear_soc`.
+//!
+//! The post processing of a linear SoC is done in `AnalysisMode::Linear`.
+
+use std::io::{Error, ErrorKind};
+
+use vob::Vob;
+
+use crush::algebra::{self, Matrix};
+use crush::soc::Id;
+use crush::soc::system::System;
+
+use crate::code_gen::{apply_matrix, LLHandler, SBoxHandler};
+use crate::code_gen::soc_gen;
+
+/// Returns the LAT of the S-box given by `table`, in absolute values: the entry at (a, b) is
+/// |#{x : a·x = b·S(x)} - 2<sup>size_in - 1</sup>|.
+pub fn lat(table: &[usize], size_in: usize, size_out: usize) -> Vec<Vec<usize>> {
+    assert_eq!(1 << size_in, table.len());
+    let half = 1 << (size_in - 1);
+    let parity = |x: usize| x.count_ones().is_multiple_of(2);
+
+    let mut lat = vec![vec![0; 1 << size_out]; 1 << size_in];
+    for (a, row) in lat.iter_mut().enumerate() {
+        for (b, entry) in row.iter_mut().enumerate() {
+            let agree = table.iter().enumerate()
+                .filter(|(x, y)| parity((x & a) ^ (*y & b)))
+                .count();
+            *entry = (agree as isize - half as isize).unsigned_abs();
+        }
+    }
+    lat
+}
+
+/// An LLHandler propagating masks through the linear layers of the wrapped handler, which
+/// propagates differences (or values). See the module documentation.
+pub struct MaskHandler<'a, L: LLHandler> {
+    inner: &'a L,
+    /// The matrix taking the masks through the linear layer of each round, None for round 0.
+    layers: Vec<Option<Matrix>>,
+}
+
+impl<'a, L: LLHandler> MaskHandler<'a, L> {
+    /// The linear layers of the `nr_rounds` rounds must be invertible, and the initial layer must
+    /// be the identity.
+    ///
+    /// The matrix of each layer is found by applying it to unit vectors, the wrapped handler must
+    /// therefore accept a state of any number of variables.
+    pub fn new(inner: &'a L, nr_rounds: usize) -> Result<Self, Error> {
+        let units = |size: usize| -> Vec<Vob> {
+            (0..size)
+                .map(|i| {
+                    let mut unit = Vob::from_elem(size, false);
+                    unit.set(i, true);
+                    unit
+                })
+                .collect()
+        };
+        let initial = units(inner.block_size(0));
+        if inner.apply_initial_layer(initial.clone()) != initial {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                                  "Masks can only go through an initial layer which is the identity"));
+        }
+
+        let mut layers = vec![None];
+        for round in 1..nr_rounds {
+            let size = inner.block_size(round);
+            let rows = inner.apply_linear_layer(round, units(size));
+            if rows.len() != size || rows.iter().any(|row| row.len() != size) {
+                return Err(Error::new(ErrorKind::InvalidInput,
+                                      format!("The linear layer of round {} is not square", round)));
+            }
+            let inverse = algebra::inverse(&Matrix::from_rows(rows))
+                .ok_or_else(|| Error::new(ErrorKind::InvalidInput,
+                                          format!("The linear layer of round {} is not invertible", round)))?;
+            layers.push(Some(algebra::transpose(&inverse)));
+        }
+        Ok(Self { inner, layers })
+    }
+}
+
+impl<'a, L: LLHandler> LLHandler for MaskHandler<'a, L> {
+    fn block_size(&self, round: usize) -> usize {
+        self.inner.block_size(round)
+    }
+
+    fn apply_linear_layer(&self, round: usize, state: Vec<Vob>) -> Vec<Vob> {
+        let layer = self.layers[round].as_ref()
+            .expect("The MaskHandler was made for fewer rounds");
+        apply_matrix(layer, &state)
+    }
+}
+
+/// Make the SoC of the linear trails over `nr_rounds` rounds. The Shards given by `sh` are
+/// expected to be based on the LATs of the S-boxes (see `lat`), and the linear layers of `llh` to
+/// be the ones of the cipher, see `MaskHandler::new` for the requirements.
+pub fn make_linear_soc<L, S>(llh: &L, sh: &S, nr_rounds: usize) -> Result<(System, Vec<Vec<Id>>), Error>
+    where
+        L: LLHandler,
+        S: SBoxHandler,
+{
+    let mask_handler = MaskHandler::new(llh, nr_rounds)?;
+    Ok(soc_gen::make_soc(&mask_handler, sh, nr_rounds))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PRESENT: [usize; 16] = [0xc, 0x5, 0x6, 0xb, 0x9, 0x0, 0xa, 0xd, 0x3, 0xe, 0xf, 0x8, 0x4, 0x7, 0x1, 0x2];
+
+    /// A 4 bit state, where the linear layer of each round maps x to (x0 ^ x1, x1, x2 ^ x0, x3 ^ x2).
+    struct XorLayer;
+
+    impl LLHandler for XorLayer {
+        fn block_size(&self, _round: usize) -> usize {
+            4
+        }
+
+        fn apply_linear_layer(&self, _round: usize, state: Vec<Vob>) -> Vec<Vob> {
+            let xor = |i: usize, j: usize| {
+                let mut out = state[i].clone();
+                out.xor(&state[j]);
+                out
+            };
+            vec![xor(0, 1), state[1].clone(), xor(2, 0), xor(3, 2)]
+        }
+    }
+
+    #[test]
+    fn lat_present() {
+        let lat = lat(&PRESENT, 4, 4);
+
+        // The zero masks are only connected to each other
+        assert_eq!(8, lat[0][0]);
+        assert!((1..16).all(|b| lat[0][b] == 0 && lat[b][0] == 0));
+        // Parseval: the squared correlations of each row sum to one
+        assert!((0..16).all(|a| lat[a].iter().map(|c| c * c).sum::<usize>() == 64));
+        // The linearity of PRESENT
+        assert_eq!(4, (1..16).flat_map(|a| lat[a][1..].iter()).max().copied().unwrap());
+    }
+
+    #[test]
+    fn masks_through_linear_layer() {
+        let handler = MaskHandler::new(&XorLayer, 2).unwrap();
+        let value = |x: usize, i: usize| (x >> i) & 1 == 1;
+        let dot = |a: usize, x: usize| (a & x).count_ones() % 2 == 1;
+        // The layer of XorLayer on values
+        let layer = |x: usize| {
+            let bits = [value(x, 0) ^ value(x, 1), value(x, 1), value(x, 2) ^ value(x, 0), value(x, 3) ^ value(x, 2)];
+            bits.iter().enumerate().fold(0, |y, (i, bit)| y | ((*bit as usize) << i))
+        };
+        for a in 0..16 {
+            // The mask a as the constant lhs: bit i of the state is set iff bit i of a is
+            let state: Vec<Vob> = (0..4).map(|i| Vob::from_elem(1, value(a, i))).collect();
+            let b = handler.apply_linear_layer(1, state).iter().enumerate()
+                .fold(0, |b, (i, bit)| b | ((bit[0] as usize) << i));
+            assert!((0..16).all(|x| dot(a, x) == dot(b, layer(x))));
+        }
+    }
+
+    #[test]
+    fn mask_handler_needs_invertible_layers() {
+        struct Projection;
+        impl LLHandler for Projection {
+            fn block_size(&self, _round: usize) -> usize {
+                2
+            }
+
+            fn apply_linear_layer(&self, _round: usize, state: Vec<Vob>) -> Vec<Vob> {
+                vec![state[0].clone(), state[0].clone()]
+            }
+        }
+        assert!(MaskHandler::new(&Projection, 1).is_ok());
+        assert!(MaskHandler::new(&Projection, 2).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use std::cell::Cell;
 use std::fmt::{self, Display, Formatter,};
 
+use crush::metrics;
 use crush::soc::bdd::differential::PruneRecord;
 use crush::soc::bdd::differential::StyledProgressBar;
 use crush::soc::Id;
@@ -60,6 +61,7 @@ impl<F> Librarian<F>
     }
 
     pub fn record(&mut self, op: Ops) {
+        Self::report_metrics(&op);
         match &op {
             Ops::Join(rec) => {
                 let pb = self.progress.factory.new_solve_progress(rec.unresolved_deps  as u64);
@@ -98,6 +100,34 @@ impl<F> Librarian<F>
         self.history.push(op);
     }
 
+    /// Report `op` to the installed `crush::metrics` sink, as counters of each kind of operation
+    /// and gauges of the last complexity and weight bounds recorded.
+    fn report_metrics(op: &Ops) {
+        match op {
+            Ops::New(complexity) => {
+                metrics::observe_nodes(*complexity);
+                metrics::gauge("pathfinder.complexity", *complexity as f64);
+            },
+            Ops::Join(rec) => {
+                metrics::counter("pathfinder.joins", 1);
+                metrics::observe_nodes(rec.complexity);
+                metrics::gauge("pathfinder.complexity", rec.complexity as f64);
+                metrics::gauge("pathfinder.unresolved_deps", rec.unresolved_deps as f64);
+            },
+            Ops::Absorb(_) => metrics::counter("pathfinder.absorbs", 1),
+            Ops::Prune(_) => metrics::counter("pathfinder.prunes", 1),
+            Ops::Bounds(b) => {
+                if let Some(lower) = b.lower {
+                    metrics::gauge("pathfinder.weight_bounds.lower", lower as f64);
+                }
+                if let Some(upper) = b.upper {
+                    metrics::gauge("pathfinder.weight_bounds.upper", upper as f64);
+                }
+            },
+            Ops::Text(_) => {},
+        }
+    }
+
     #[allow(dead_code)]
     pub fn print (&self) {
         for ops in self.history.iter() {
@@ -113,8 +143,28 @@ impl<F> Librarian<F>
     }
 }
 
+/// Only the history of a `Librarian` is serialized: its factory and progress bars belong to the
+/// live solving, and a deserialized `Librarian` gets a default factory.
+#[cfg(feature = "serde")]
+impl<F: SPFactory> serde::Serialize for Librarian<F> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.history.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, F: SPFactory + Default> serde::Deserialize<'de> for Librarian<F> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self {
+            progress: ProgressHelper { factory: F::default(), absorb: None },
+            history: Vec::deserialize(deserializer)?,
+        })
+    }
+}
+
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Ops {
 
     /// Recording of the complexity. Useful to start get a record at the start of any operation.
@@ -129,11 +179,50 @@ pub enum Ops {
 
     Prune(PruneRecord),
 
+    /// Recording of the bounds on the weight of the optimal trail, as known at the time.
+    Bounds(WeightBounds),
+
     Text(String),
 }
 
 
+/// An interval known to contain the weight of the optimal (lowest weight, non-trivial) trail.
+///
+/// `lower` is a valid lower bound as soon as it is set, and only ever grows: every trail that was
+/// pruned away had at least that weight, and so had every trail remaining in `Master`.
+/// `upper` is the weight of the best trail found so far, and is only set once all `Shard`s are
+/// joined into `Master`, as only then do the paths of `Master` represent complete trails.
+/// The bounds are valid even if the solver is interrupted, as they are updated as it goes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WeightBounds {
+    pub lower: Option<u32>,
+    pub upper: Option<u32>,
+}
+
+impl WeightBounds {
+    /// Raise the lower bound to `lower`, unless the current lower bound is already higher.
+    pub fn raise_lower(&mut self, lower: u32) {
+        self.lower = Some(self.lower.map_or(lower, |l| l.max(lower)));
+    }
+
+    /// Lower the upper bound to `upper`, unless the current upper bound is already lower.
+    pub fn lower_upper(&mut self, upper: u32) {
+        self.upper = Some(self.upper.map_or(upper, |u| u.min(upper)));
+    }
+
+    /// Returns true if the bounds have met, i.e. the optimal weight is known.
+    pub fn is_tight(&self) -> bool {
+        match (self.lower, self.upper) {
+            (Some(l), Some(u)) => l >= u,
+            _ => false,
+        }
+    }
+}
+
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CoreOps {
     Swap(Depth, Depth),
 
@@ -144,6 +233,7 @@ pub enum CoreOps {
 
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct JoinRec {
     top: Id,
     bottom: Id,
@@ -164,6 +254,7 @@ impl JoinRec {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AbsorbRec {
     pre_absorb: Option<PreAbsorbRec>,
     ops: Vec<CoreOps>,
@@ -194,6 +285,7 @@ impl AbsorbRec {
 
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PreAbsorbRec {
     base: Depth,
     rest: Vec<Depth>,
@@ -264,10 +356,19 @@ impl Display for Ops {
             Prune(rec ) => {
                 write!(f, "{}", rec)
             }
+            Bounds(b) => {write!(f, "Weight bounds: {}", b)}
         }
     }
 }
 
+impl Display for WeightBounds {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let lower = self.lower.map_or("?".to_string(), |l| l.to_string());
+        let upper = self.upper.map_or("?".to_string(), |u| u.to_string());
+        write!(f, "[{}, {}]", lower, upper)
+    }
+}
+
 impl Display for PreAbsorbRec {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let width = 6;
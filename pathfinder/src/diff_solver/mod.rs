@@ -1,8 +1,31 @@
-pub use meta::{Librarian, SPFactory};
-pub use simple_solver::{SimpleSolver, SolverResultOk,};
+pub use boomerang::{bct, make_boomerang_soc, switch_table, SwitchHandler};
+pub use config::{SolverConfig, Verbosity};
+pub use division::{balanced_bits, division_table, integral_distinguisher, make_division_soc};
+pub use impossible::{Difference, ImpossibleDifferentialSearch};
+pub use join_order::JoinOrder;
+pub use library::{Library, LibraryKey};
+pub use linear::{lat, make_linear_soc, MaskHandler};
+pub use meta::{Librarian, SPFactory, WeightBounds};
+pub use progress::ProgressReporter;
+pub use related_key::{make_related_key_soc, KeyScheduleHandler};
+pub use run_result::{PruningStats, RunResult};
+pub use simple_solver::{SimpleSolver, SolverResult, SolverRun};
 
+mod boomerang;
+pub mod checkpoint;
+pub mod config;
+pub mod distributed;
+mod division;
+mod impossible;
+pub mod join_order;
+pub mod library;
+mod linear;
+mod related_key;
+pub mod run_result;
 mod simple_solver;
 mod meta;
+pub mod progress;
+#[cfg(feature = "verify-sat")]
+pub mod verify;
 
-#[allow(dead_code, unused_variables)]
 pub mod post_processing_v5;
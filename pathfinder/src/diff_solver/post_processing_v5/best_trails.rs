@@ -0,0 +1,214 @@
+//! The extraction of the best trails of a solved SoC, rather than of a single one.
+//!
+//! The weight of a trail is its number of active S-boxes, an S-box being active iff the trail
+//! takes a 1-edge in one of its `step` levels of the active area, as for the bounds of the solver.
+//! `extract_best_k_trails` goes through `Master` best first: the lowest weight of the paths from
+//! each node to the sink is computed bottom up, and the partial paths from the source are
+//! extended by increasing weight of their best completion. As this weight is exact, each
+//! complete path popped is the next best trail, and the search never backtracks.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::Debug;
+use std::io::{Error, ErrorKind};
+use std::ops::Range;
+
+use vob::Vob;
+
+use crush::algebra::{self, Matrix};
+use crush::soc::bdd::Bdd as Shard;
+use crush::soc::Id;
+
+use crate::diff_solver::{SolverRun, SPFactory};
+
+/// A trail of `Master`, see `extract_best_k_trails`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trail {
+    /// The number of active S-boxes of the trail.
+    pub weight: usize,
+    /// The value of each variable of the SoC along the trail, the variables fixed by none of the
+    /// levels of `Master` being false.
+    pub values: Vob,
+}
+
+impl Trail {
+    /// Returns the differences (or masks) of the trail round by round, as the values of
+    /// consecutive blocks of `block_size` variables: For the SoC of a cipher, the input of the
+    /// first round, then the output of the S-box layer of each round.
+    pub fn rounds(&self, block_size: usize) -> Vec<Vob> {
+        assert_ne!(block_size, 0, "The block size cannot be 0!");
+        (0..self.values.len())
+            .step_by(block_size)
+            .map(|start| self.values.iter().skip(start).take(block_size).collect())
+            .collect()
+    }
+}
+
+/// Return the `k` non-trivial trails of `Master` of the lowest weights, by increasing weight, `Master`
+/// being the only Shard left in the SoC of `run`. Fewer trails are returned if `Master` has fewer
+/// non-trivial trails, the trails of weight 0 being trivial. Trails of the same weight are
+/// returned in the lexicographic order of their paths, 0-edges first.
+///
+/// Returns an `Error` of kind `ErrorKind::InvalidInput` unless `Master` is the only Shard left,
+/// i.e. unless all Shards were joined.
+pub fn extract_best_k_trails<F>(run: &SolverRun<F>, k: usize) -> Result<Vec<Trail>, Error>
+    where
+        F: SPFactory + Debug,
+{
+    if run.master.iter_bdds().len() != 1 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Expected Master to be the only Shard left, found {} Shards", run.master.iter_bdds().len())));
+    }
+    let (_, master) = run.master.iter_bdds().next().unwrap();
+    let master = master.borrow();
+    let levels = Matrix::from_rows(master.get_lhs());
+    let trails = best_k_paths(&master, &run.active_area, run.step, k)
+        .into_iter()
+        .map(|(weight, path)| {
+            let path: Vob = path.into_iter().collect();
+            let values = algebra::solve(&levels, &path)
+                .expect("The LHS's of the levels of Master are linearly independent");
+            Trail { weight, values }
+        })
+        .collect();
+    Ok(trails)
+}
+
+/// A path from the source down to `node` at `depth`, of weight `weight` so far, `taken` telling
+/// whether it took a 1-edge in the current S-box. `bound` is the weight of its best completion.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Partial {
+    bound: usize,
+    edges: Vec<bool>,
+    weight: usize,
+    taken: bool,
+    depth: usize,
+    node: Id,
+}
+
+/// Return the `k` paths of `shard` of the lowest non-zero weights, with their weight.
+fn best_k_paths(shard: &Shard, active_area: &Range<usize>, step: usize, k: usize) -> Vec<(usize, Vec<bool>)> {
+    assert_ne!(step, 0, "Step cannot be 0!");
+    let sink_depth = shard.get_sink_level_index();
+    // Whether an edge from `depth` is in an S-box, and ends it
+    let in_sbox = |depth: usize| active_area.contains(&depth);
+    let ends_sbox = |depth: usize| {
+        in_sbox(depth) && ((depth + 1 - active_area.start).is_multiple_of(step) || depth + 1 == active_area.end)
+    };
+    // The path taking `edge` from `depth`: its weight added, and whether it took a 1-edge in the
+    // current S-box
+    let take = |depth: usize, taken: bool, edge: bool| -> (usize, bool) {
+        let taken = taken || (in_sbox(depth) && edge);
+        if ends_sbox(depth) { (taken as usize, false) } else { (0, taken) }
+    };
+
+    // The lowest weight from each (node, taken) to the sink, level by level
+    let mut lowest: Vec<HashMap<(Id, bool), usize>> = vec![HashMap::new(); sink_depth + 1];
+    for (id, _) in shard.iter_levels().nth(sink_depth).unwrap().iter_nodes() {
+        lowest[sink_depth].insert((*id, false), 0);
+    }
+    for (depth, level) in shard.iter_levels().enumerate().take(sink_depth).rev() {
+        let mut level_lowest = HashMap::new();
+        for (id, node) in level.iter_nodes() {
+            for taken in [false, true].iter() {
+                let best = [(false, node.get_e0()), (true, node.get_e1())].iter()
+                    .filter_map(|(edge, child)| {
+                        let (added, child_taken) = take(depth, *taken, *edge);
+                        lowest[depth + 1].get(&((*child)?, child_taken)).map(|below| added + below)
+                    })
+                    .min();
+                if let Some(best) = best {
+                    level_lowest.insert((*id, *taken), best);
+                }
+            }
+        }
+        lowest[depth] = level_lowest;
+    }
+
+    let mut queue = BinaryHeap::new();
+    for (id, _) in shard.iter_levels().next().unwrap().iter_nodes() {
+        if let Some(bound) = lowest[0].get(&(*id, false)) {
+            queue.push(Reverse(Partial { bound: *bound, edges: vec![], weight: 0, taken: false, depth: 0, node: *id }));
+        }
+    }
+    let mut paths = Vec::with_capacity(k);
+    while paths.len() < k {
+        let partial = match queue.pop() {
+            Some(Reverse(partial)) => partial,
+            None => break,
+        };
+        if partial.depth == sink_depth {
+            if partial.weight > 0 {
+                paths.push((partial.weight, partial.edges));
+            }
+            continue;
+        }
+        let node = shard.iter_levels().nth(partial.depth).unwrap().get_node(&partial.node).unwrap();
+        for (edge, child) in [(false, node.get_e0()), (true, node.get_e1())].iter() {
+            let child = match child {
+                Some(child) => *child,
+                None => continue,
+            };
+            let (added, taken) = take(partial.depth, partial.taken, *edge);
+            if let Some(below) = lowest[partial.depth + 1].get(&(child, taken)) {
+                let mut edges = partial.edges.clone();
+                edges.push(*edge);
+                queue.push(Reverse(Partial {
+                    bound: partial.weight + added + below,
+                    edges,
+                    weight: partial.weight + added,
+                    taken,
+                    depth: partial.depth + 1,
+                    node: child,
+                }));
+            }
+        }
+    }
+    paths
+}
+
+#[cfg(test)]
+mod test {
+    use crate::code_gen::cipher::TrailKind;
+    use crate::code_gen::fixture::toy_solver;
+    use crate::diff_solver::SolverConfig;
+
+    use super::*;
+
+    #[test]
+    fn best_trails() {
+        let solver = toy_solver(TrailKind::Differential, 3, SolverConfig::new());
+        assert!(extract_best_k_trails(solver.finalize().run(), 1).is_err());
+
+        let mut solver = toy_solver(TrailKind::Differential, 3, SolverConfig::new());
+        solver.run();
+        let result = solver.finalize();
+        let trails = extract_best_k_trails(result.run(), 20).unwrap();
+        assert_eq!(20, trails.len());
+        // The weights of the 20 best non-trivial trails, as counted by the weight distribution
+        let weights: Vec<usize> = result.run().weight_distribution().unwrap()
+            .into_iter()
+            .filter(|(weight, _)| *weight > 0)
+            .flat_map(|(weight, count)| std::iter::repeat_n(weight, count.to_string().parse().unwrap()))
+            .take(20)
+            .collect();
+        assert_eq!(weights, trails.iter().map(|trail| trail.weight).collect::<Vec<_>>());
+
+        let (_, master) = result.run().master.iter_bdds().next().unwrap();
+        for (i, trail) in trails.iter().enumerate() {
+            assert!(master.borrow().accepts(&trail.values));
+            assert!(trails[..i].iter().all(|other| other.values != trail.values));
+            let rounds = trail.rounds(8);
+            assert_eq!(4, rounds.len());
+            assert!(rounds[0].iter_set_bits(..).next().is_some());
+            // An S-box is active iff its output difference isn't zero
+            let active: usize = rounds[1..].iter()
+                .map(|round| (0..2).filter(|sbox| round.iter_set_bits(4 * sbox..4 * sbox + 4).next().is_some()).count())
+                .sum();
+            assert_eq!(trail.weight, active);
+        }
+        assert!(extract_best_k_trails(result.run(), 0).unwrap().is_empty());
+    }
+}
@@ -23,7 +23,7 @@ use crush::soc::bdd::Bdd as Shard;
 use crush::soc::bdd::differential::{Depth, PPFactory, StyledProgressBar};
 use crush::soc::bdd::differential::wd::{NcWDistribution, TransparentFactory, WDCountV2, WDLevel};
 use crush::soc::Node;
-pub use results::{ProcessedResult, DisplayResult, ProcessedResultSection};
+pub use results::{ProcessedResult, DisplayResult, ProcessedResultSection, TrailSummary};
 
 use crate::code_gen::SBoxHandler;
 use crate::diff_solver::post_processing_v5::bt::bthandler_trait::BTHandler;
@@ -146,7 +146,8 @@ impl ResultSectionBuilder {
 
 
 /// Was the Alpha/Beta paths yielded using the create alpha beta or the extract alpha beta approach?
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BuildMode {
     Template,
     Constructed,
@@ -182,7 +183,7 @@ fn extract_a_single_path(master: Arc<Shard>, start_depth: Depth, end_depth: Dept
         .expect("Start level is missing!")
         .get_nodes();
     assert_eq!(start_nodes.len(), 1);
-    let mut current_node = start_nodes.values().next().expect("Start level is empty!");
+    let mut current_node = start_nodes.iter().map(|(_, node)| node).next().expect("Start level is empty!");
 
     let mut current_depth: Depth = start_depth;
     let mut path = Vob::with_capacity(end_depth - start_depth);
@@ -242,7 +243,7 @@ pub(crate) fn extract_all_paths_concurrently<P>(master: Arc<Shard>,
         .get_nodes();
     assert_eq!(start_nodes.len(), 1);
 
-    let start_node = start_nodes.values().next().expect("Start level is empty!").clone();
+    let start_node = start_nodes.iter().map(|(_, node)| node).next().expect("Start level is empty!").clone();
     let mut path = Vob::new();
 
     {
@@ -4,6 +4,8 @@ use std::fmt::Result as FmtResult;
 
 use console::style;
 
+use vob::Vob;
+
 use crush::soc::bdd::differential::wd::{NcWDistribution};
 
 use crate::diff_solver::post_processing_v5::sess_handling::SessEstimate;
@@ -35,6 +37,11 @@ impl ProcessedResult {
         }
     }
 
+    /// Returns the summaries of the sections of the result, see `TrailSummary`.
+    pub fn summaries(&self) -> Vec<TrailSummary> {
+        self.sections.iter().map(TrailSummary::from).collect()
+    }
+
     fn fmt_summary(&self, f: &mut fmt::Formatter) -> FmtResult {
         for section in self.sections.iter() {
             section.fmt_as_summary(f)?;
@@ -178,6 +185,52 @@ impl From<ResultSectionBuilder> for ProcessedResultSection {
 // =================================================================================================
 
 
+/// The outcome of the post-processing for one way of choosing the input and output of the hull,
+/// see `PostProcessor`.
+///
+/// The weights are the exponents of the probabilities (or biases) negated, i.e. a weight of w is
+/// a probability of 2^(-w).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrailSummary {
+    /// Whether the input and output were constructed to be optimal, or extracted from `Master`.
+    pub mode: BuildMode,
+    /// The input difference (or mask) of the hull.
+    pub input: Vob,
+    /// The output difference (or mask) of the hull.
+    pub output: Vob,
+    /// A trail of the hull with the fewest active S-boxes, as the inputs and outputs of the
+    /// S-box layers, round by round. It isn't necessarily the trail of the lowest weight.
+    pub best_trail: Vob,
+    /// The weight of the hull, all the trails used summed up.
+    pub hull_weight: f64,
+    /// The number of trails of each weight in the hull, the weights being multiplied by
+    /// `PROB_FACTOR` and truncated.
+    pub weight_distribution: BTreeMap<usize, usize>,
+    /// The number of trails from the input to the output, `None` if it overflowed a usize.
+    pub trails: Option<usize>,
+    /// The number of these trails left out of the hull weight.
+    pub trails_skipped: usize,
+}
+
+impl From<&ProcessedResultSection> for TrailSummary {
+    fn from(section: &ProcessedResultSection) -> Self {
+        let (trails, overflowed) = section.best_estimate.hull_distribution()
+            .map_or((0, true), |dist| dist.total_number_of_paths_overflowing());
+        TrailSummary {
+            mode: section.mode.clone(),
+            input: Vob::from(&section.alpha_path),
+            output: Vob::from(&section.beta_path),
+            best_trail: Vob::from(&section.example_path),
+            hull_weight: section.hull_probability,
+            weight_distribution: section.probabilities_count.clone(),
+            trails: if overflowed { None } else { Some(trails) },
+            trails_skipped: section.paths_skipped,
+        }
+    }
+}
+
+
 /// The official way of formatting a ProcessedResult for display to various outputs.
 /// Text and formatting will vary depending on the "mode" requested.
 pub enum DisplayResult<'a> {
@@ -1,32 +1,71 @@
+//! The post-processing of a solved SoC: from `Master`, once all Shards are joined into it, find
+//! the input and output of the best differential (or linear hull) and estimate its weight, as
+//! the sum of the trails of `Master` between them.
+//!
+//! `PostProcessor` is the entry point, taking the `SolverRun` of a complete solving along with
+//! the base tables, S-boxes and LHS's of the S-boxes of the cipher (see `sbox_lhss`), and returning
+//! a `TrailSummary` for each way of choosing the input and output (see `BuildMode`):
+//!
+//! ```ignore
+//! let lhss = sbox_lhss(&soc, &rounds)?;
+//! // ... solve the SoC into `result`
+//! let handlers = Handlers::new(bt_handler, sb_handler);
+//! let summaries = PostProcessor::new("present", handlers, AnalysisMode::Differential, lhss)
+//!     .with_trace_file(PathBuf::from("present_trace.txt"))
+//!     .process(result.run(), progress)?;
+//! for summary in summaries.iter() {
+//!     println!("{:?}: weight {}", summary.mode, summary.hull_weight);
+//! }
+//! ```
+//!
+//! `extract_best_k_trails` extracts the trails of the lowest weights of `Master` on its own.
+//!
+//! The rest of the module is the machinery of the post-processing, and is subject to change.
+
 use std::fmt::Debug;
+use std::io::{Error, ErrorKind};
 use std::num::NonZeroUsize;
 use std::ops::Range;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+
+use vob::Vob;
 
-pub use bt::{BaseTable, bthandler_trait::BTHandler};
-use crush::algebra::Matrix;
+pub use best_trails::{extract_best_k_trails, Trail};
+pub use bt::{BaseTable, bthandler_trait::BTHandler, PROB_FACTOR};
+use crush::algebra::{self, Matrix};
+use crush::{metrics, reporting};
 use crush::soc::bdd::Bdd as Shard;
 use crush::soc::bdd::differential::{Depth, PPFactory, StyledProgressBar};
 use crush::soc::bdd::differential::wd::{EndNodeDist, Node2NodeDistribution, WDLevel, WDPresence};
 use crush::soc::bdd::differential::wd::NWDistribution;
 use crush::soc::Id;
-pub use hull_calc::{DisplayResult, ProcessedResult};
+use crush::soc::system::System;
+pub use hull_calc::{BuildMode, DisplayResult, ProcessedResult, TrailSummary};
 pub use logging::TraceLogger;
 pub use sess_handling::{InnerWeight, SessEstimate};
 
 use crate::code_gen::SBoxHandler;
-use crate::diff_solver::post_processing_v5::hull_calc::{BuildMode, ResultSectionBuilder};
+use crate::diff_solver::{RunResult, SolverRun, SPFactory};
+use crate::diff_solver::post_processing_v5::hull_calc::ResultSectionBuilder;
 // todo fix reference to old mod
 use crate::diff_solver::post_processing_v5::logging::{Cache, LogType};
 use crate::diff_solver::post_processing_v5::sess_handling::*;
 
+mod best_trails;
 mod sess_handling;
+// Parts of the logging and of the display of the paths are only kept for debugging
+#[allow(dead_code)]
 mod logging;
+#[allow(dead_code)]
 mod utils;
 mod bt;
 mod hull_calc;
 
+/// The source of the records of the post-processing, see `crush::reporting`.
+const SOURCE: &str = "pathfinder.post_processing";
 
 pub struct SolvedSocMeta {
     active_area: Range<usize>,
@@ -46,6 +85,129 @@ pub enum AnalysisMode{
 }
 
 
+/// The entry point of the post-processing, see the module documentation.
+pub struct PostProcessor<B: BTHandler, S: SBoxHandler> {
+    cipher_name: String,
+    handlers: Handlers<B, S>,
+    mode: AnalysisMode,
+    sbox_lhss: Matrix,
+    trace_file: Option<PathBuf>,
+    run_result: Option<(RunResult, PathBuf)>,
+}
+
+impl<B, S> PostProcessor<B, S>
+    where
+        B: BTHandler + Debug,
+        S: SBoxHandler,
+{
+    /// Construct a post-processor for the trails of `mode` of the cipher `cipher_name`, whose base
+    /// tables and S-boxes are given by `handlers`, and the LHS's of the inputs and outputs of its
+    /// S-boxes by `sbox_lhss`, see `sbox_lhss`.
+    pub fn new(cipher_name: &str, handlers: Handlers<B, S>, mode: AnalysisMode, sbox_lhss: Matrix) -> Self {
+        PostProcessor {
+            cipher_name: cipher_name.to_string(),
+            handlers,
+            mode,
+            sbox_lhss,
+            trace_file: None,
+            run_result: None,
+        }
+    }
+
+    /// Write the metadata of the post-processing to `path` as it goes, see `TraceLogger`.
+    pub fn with_trace_file(mut self, path: PathBuf) -> Self {
+        self.trace_file = Some(path);
+        self
+    }
+
+    /// Write `result`, the `RunResult` of the solving, to `path` as JSON once the post-processing
+    /// is done, completed with its summaries. See `diff_solver::run_result`.
+    pub fn with_run_result(mut self, result: RunResult, path: PathBuf) -> Self {
+        self.run_result = Some((result, path));
+        self
+    }
+
+    /// Post-process `Master` as left by `run`, and return a `TrailSummary` for each way of choosing
+    /// the input and output of the hull. The input is read from the first levels of `Master`, and
+    /// the output from its last levels, one S-box layer each (see `BTHandler::sbox_layer_size`).
+    ///
+    /// Returns an `Error` of kind `ErrorKind::InvalidInput` unless `Master` is the only Shard left
+    /// in the SoC of `run`, with an input and an output, and the LHS's of the S-boxes are sums of
+    /// the LHS's of its levels. Returns the `Error` of the trace file, if any, if it can't be
+    /// created, and the one of the result file, if any, if it can't be written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `Master` has no trail of non-zero weight, i.e. unless the solving found one.
+    pub fn process<F, P>(self, run: &SolverRun<F>, progress: P) -> Result<Vec<TrailSummary>, Error>
+        where
+            F: SPFactory + Debug,
+            P: PPFactory,
+    {
+        let master = match run.master.iter_bdds().collect::<Vec<_>>().as_slice() {
+            [(_, master)] => master.borrow().clone(),
+            shards => return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Expected Master to be the only Shard left, found {} Shards", shards.len()))),
+        };
+        let layer_size = self.handlers.bt_handler.sbox_layer_size();
+        let sink_depth = master.get_sink_level_index();
+        if layer_size == 0 || sink_depth < 2 * layer_size {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Master has {} levels, too few for an input and an output of {} bits", sink_depth, layer_size)));
+        }
+        let step = NonZeroUsize::new(run.step)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "The step of the solving is zero"))?;
+        let master_meta = SolvedSocMeta::new(run.active_area.clone(), step, layer_size, sink_depth - layer_size);
+        // A path of Master gives the values of its levels: The LHS of each bit of the S-boxes is
+        // rewritten as the levels it is the sum of
+        let levels = algebra::transpose(&Matrix::from_rows(master.get_lhs()));
+        let lhss = self.sbox_lhss.iter_rows()
+            .map(|lhs| match lhs.len() == levels.row_size() {
+                true => algebra::solve(&levels, lhs),
+                false => None,
+            })
+            .collect::<Option<Vec<Vob>>>()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput,
+                                      "The LHS's of the S-boxes aren't sums of the LHS's of the levels of Master"))?;
+        let lhss = Matrix::from_rows(lhss);
+
+        let tx = match self.trace_file {
+            Some(path) => {
+                let (tx, rx) = channel();
+                let logger = TraceLogger::new(path, false, rx)?;
+                thread::spawn(move || logger.run());
+                tx
+            }
+            // Nothing is traced, the records sent are dropped
+            None => channel().0,
+        };
+        let result = start_post_processing(master, master_meta, lhss, self.handlers, progress,
+                                           self.cipher_name, tx, self.mode);
+        let summaries = result.summaries();
+        if let Some((run_result, path)) = self.run_result {
+            run_result.with_summaries(summaries.clone()).write_json(&path)?;
+        }
+        Ok(summaries)
+    }
+}
+
+/// Return the LHS's of the inputs and outputs of the S-boxes of a SoC, as expected by
+/// `PostProcessor::new`: the LHS's of the Shards of `rounds`, round by round, each Shard being an
+/// S-box with its inputs before its outputs.
+///
+/// The Shards are joined by the solving, the LHS's are to be taken from the SoC given to the
+/// solver. Returns an `Error` of kind `ErrorKind::NotFound` if a Shard of `rounds` is missing
+/// from `soc`.
+pub fn sbox_lhss(soc: &System, rounds: &[Vec<Id>]) -> Result<Matrix, Error> {
+    let mut lhss = Vec::new();
+    for id in rounds.iter().flatten() {
+        lhss.extend(soc.get_bdd(*id)?.borrow().get_lhs());
+    }
+    Ok(Matrix::from_rows(lhss))
+}
+
 pub fn start_post_processing<B, S, P> (
     mut master: Shard,
     master_meta: SolvedSocMeta,
@@ -62,6 +224,10 @@ pub fn start_post_processing<B, S, P> (
         S: SBoxHandler,
         P: PPFactory,
 {
+    let _post_processing = metrics::phase("pathfinder.post_processing");
+    let _stage = reporting::stage(SOURCE, "post_processing");
+    metrics::observe_nodes(master.get_size());
+    reporting::memory_snapshot(SOURCE, master.get_size());
     let main_pb = progress.new_progress_bar(5);
     main_pb.set_message("Starting PP!");
     //quickfix, out destination to be given as param, not created here
@@ -83,11 +249,13 @@ pub fn start_post_processing<B, S, P> (
 
     main_pb.set_message("Estimating best SESS");
     // Find the best SESS estimate, based on the average weight an active S-box will contribute.
+    let sess_stage = reporting::stage(SOURCE, "sess_estimate");
     let best_estimate = estimate_best_sess(&master,
                                                  &handlers,
                                                  &progress,
                                                  &mut cache,
     );
+    drop(sess_stage);
     cache.register_sess_estimate(best_estimate.clone());
     main_pb.inc(1);
 
@@ -95,7 +263,14 @@ pub fn start_post_processing<B, S, P> (
 
     //todo register in cache!
     main_pb.set_message("Deleting superfluous nodes");
+    let nodes_before = master.get_size();
     delete_non_sess_estimate_nodes(&mut master, &cache.master_md(), &best_estimate);
+    reporting::report(SOURCE, reporting::Event::Pruned {
+        bdd: master.get_id(),
+        nodes_before,
+        nodes_after: master.get_size(),
+        threshold: None,
+    });
     main_pb.inc(1);
 
 
@@ -111,6 +286,7 @@ pub fn start_post_processing<B, S, P> (
         handlers.bt_handler.nr_of_rounds(),
     );
 
+    let _hull_stage = reporting::stage(SOURCE, "hull");
     let res = hull_calc::calculate_hull(
         master,
         &mut cache,
@@ -286,7 +462,7 @@ fn delete_non_sess_estimate_nodes(master: &mut Shard, master_md: &SolvedSocMeta,
     if target_node.is_none() {
         panic!("We failed for some reason to remove the End Node from the set of nodes to be deleted");
     }
-    master.delete_all_marked_nodes_from_level(to_delete.keys().collect(),
+    master.delete_all_marked_nodes_from_level(to_delete.iter().map(|(id, _)| id).collect(),
                                               master_md.beta_lvl_depth);
 
     // Deleting from alpha level
@@ -299,7 +475,7 @@ fn delete_non_sess_estimate_nodes(master: &mut Shard, master_md: &SolvedSocMeta,
     if target_node.is_none() {
         panic!("We failed for some reason to remove the Start Node from the set of nodes to be deleted");
     }
-    master.delete_all_marked_nodes_from_level(to_delete.keys().collect(),
+    master.delete_all_marked_nodes_from_level(to_delete.iter().map(|(id, _)| id).collect(),
                                               master_md.alpha_lvl_depth);
 }
 
@@ -332,4 +508,75 @@ fn make_alpha_beta_level(alpha_candidates: &Vec<Vec<(Id, WDPresence)>>,
     let alpha_beta_dists = Arc::new(cache.make_and_analyse_alpha_beta(master, targets, progress));
 
     alpha_beta_dists
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use crate::code_gen::cipher::{make_cipher_soc, CipherHandler, SBox, TrailKind};
+    use crate::code_gen::fixture::{toy_solver, Silent, Toy, PRESENT};
+    use crate::diff_solver::SolverConfig;
+
+    use super::*;
+
+    #[test]
+    fn post_processing() {
+        /// The base table of Toy over 4 rounds, the DDT of PRESENT in every round.
+        #[derive(Debug)]
+        struct ToyBt(BaseTable);
+
+        impl BTHandler for ToyBt {
+            fn nr_of_rounds(&self) -> usize { 4 }
+            fn bt(&self, _round: usize, _pos: usize) -> &BaseTable { &self.0 }
+            fn prob_exponents(&self, _round: usize, _pos: usize) -> &BTreeMap<usize, usize> { self.0.prob_exponents() }
+            fn k(&self, _round: usize, _pos: usize) -> f64 { self.0.k() }
+            fn sbox_layer_size(&self) -> usize { 8 }
+            fn prob_exponents_for_entry(&self, _round: usize, _pos: usize, entry: usize) -> Option<usize> {
+                self.0.prob_exponent_for_entry(entry)
+            }
+        }
+
+        let (soc, rounds) = make_cipher_soc(&Toy, TrailKind::Differential, 4).unwrap();
+        let lhss = sbox_lhss(&soc, &rounds).unwrap();
+        let post_processor = || {
+            let bt = ToyBt(BaseTable::new(SBox::new(PRESENT.to_vec(), 4, 4).unwrap().ddt()).unwrap());
+            let handlers = Handlers::new(bt, CipherHandler::new(&Toy, TrailKind::Differential));
+            PostProcessor::new("toy", handlers, AnalysisMode::Differential, lhss.clone())
+        };
+
+        // Before the run, Shards are left to join
+        let unsolved = toy_solver(TrailKind::Differential, 4, SolverConfig::new()).finalize();
+        let error = post_processor().process(unsolved.run(), Silent).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidInput, error.kind());
+
+        let mut solver = toy_solver(TrailKind::Differential, 4, SolverConfig::new());
+        solver.run();
+        let result = solver.finalize();
+        let run_result = RunResult::new(&SolverConfig::new(), &result, 1);
+        let path = std::env::temp_dir().join(format!("pathfinder_post_processing_{}.json", std::process::id()));
+        let summaries = post_processor()
+            .with_run_result(run_result.clone(), path.clone())
+            .process(result.run(), Silent)
+            .unwrap();
+        // The result of the run is written completed with the summaries
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(run_result.with_summaries(summaries.clone()).to_json(), written.trim_end());
+        assert!(written.contains("\"summaries\":[{\"mode\":"));
+        assert_eq!(2, summaries.len());
+        for summary in summaries.iter() {
+            assert_eq!(8, summary.input.len());
+            assert!(summary.input.iter_set_bits(..).next().is_some());
+            assert_eq!(8, summary.output.len());
+            assert_eq!(4 * 2 * 8, summary.best_trail.len());
+            assert_eq!(summary.input, summary.best_trail.iter().take(8).collect::<Vob>());
+            // Each of the 4 active S-boxes has a probability of at most 2^-2, and the hull is at
+            // least as likely as any of its trails
+            let lowest = *summary.weight_distribution.keys().next().unwrap();
+            assert!(lowest >= 8 * PROB_FACTOR);
+            assert!(summary.hull_weight > 0.0 && summary.hull_weight <= (lowest / PROB_FACTOR) as f64);
+            assert_eq!(Some(summary.weight_distribution.values().sum()), summary.trails);
+        }
+    }
+}
@@ -454,7 +454,8 @@ impl DisplayPath<'_> {
 
 #[cfg(test)]
 mod tests {
-    use crush::soc::utils::{build_system_from_spec, parse_system_spec_from_file};
+    use crush::soc::io::parse_system_spec_from_file;
+    use crush::soc::utils::build_system_from_spec;
 
     use crate::ciphers::prince::SbMock;
 
@@ -734,8 +735,8 @@ mod tests {
     fn prince2_lhss() -> Matrix {
         let sys_spec = parse_system_spec_from_file(
             // Prince2 soft lim 20:
-            &["SoCs", "PRINCE_2.bdd"].iter().collect());
-        let soc_original = build_system_from_spec(sys_spec);
+            &["SoCs", "PRINCE_2.bdd"].iter().collect()).unwrap();
+        let soc_original = build_system_from_spec(sys_spec).unwrap();
 
         let mut lhss = soc_original.get_system_lhs();
         lhss.sort_unstable_by(|a,b| a.0.cmp(&b.0));
@@ -0,0 +1,30 @@
+//! Progress reporting of a `SimpleSolver`, telling how far a long solving has gotten.
+//!
+//! The solver sends its progress to the `ProgressReporter` set with
+//! `SimpleSolver::set_progress_reporter`, as records of the source `pathfinder.solver`, the same
+//! ones it reports with `crush::reporting`: an `Event::JoinStarted` before each join, then an
+//! `Event::RemainingWork` with the Shards left to join and the linear dependencies to absorb, an
+//! `Event::Absorbed` for each dependency absorbed into `Master` and an `Event::Pruned` for each
+//! pruning. Once a join is done with, an `Event::Step` tells the size of the whole SoC, and an
+//! `Event::RoundDone` tells the weight bounds after each round.
+//!
+//! Every `Reporter` of crush is a `ProgressReporter`: `StderrReporter` prints the progress for a
+//! human, and `JsonReporter` writes it as JSON objects, one per line, for external dashboards.
+
+use crush::reporting::{Record, Reporter};
+
+/// A destination for the progress of a solving, see the module documentation.
+///
+/// The method takes `&self` as the reporter may be shared with other solvers (and possibly
+/// threads), implementations are expected to use interior mutability.
+pub trait ProgressReporter: Send + Sync {
+    /// Report `record`, an event of the progress of a solving.
+    fn report(&self, record: &Record);
+}
+
+impl<R: Reporter + ?Sized> ProgressReporter for R {
+    fn report(&self, record: &Record) {
+        Reporter::report(self, record);
+        self.flush();
+    }
+}
@@ -0,0 +1,220 @@
+//! Support for related-key differential trails.
+//!
+//! In a related-key trail, the key has a difference as well as the state. The key schedule is then
+//! part of the SoC: its key state, initially the key, gives the round key XORed into the state
+//! before the S-box layer of each round, and is updated after each round by a layer of S-boxes and
+//! a linear map (see `KeyScheduleHandler`).
+//!
+//! The variables of the SoC are the input of the first S-box layer, then the key, then the outputs
+//! of the S-boxes of the state and of the key schedule, round by round. The key variables are
+//! shared by all the rounds, the round keys being linear combinations of them and of the outputs of
+//! the S-boxes of the key schedule. As both the input and the key are part of `Master`, the solver
+//! is to be given their sizes summed up as its block size, see `make_related_key_soc`.
+//!
+//! The Shards of the S-boxes of the key schedule are joined along with the ones of the state, and
+//! their active S-boxes count in the weight of the trail. The S-boxes of the key schedule after the
+//! last round are left out, as no round key depends on them.
+
+use std::io::{Error, ErrorKind};
+use std::ops::Range;
+
+use vob::Vob;
+
+use crush::soc::Id;
+use crush::soc::system::System;
+
+use crate::code_gen::{LLHandler, SBoxHandler};
+use crate::code_gen::gsf::GenericShard;
+
+/// The key schedule of a cipher, for the related-key trails. See the module documentation.
+pub trait KeyScheduleHandler {
+    /// The number of bits of the key state, i.e. of the key.
+    fn key_state_size(&self) -> usize;
+
+    /// The round key XORed into the input of the S-box layer of `round`, as linear combinations of
+    /// `key_state`, the key state of the round. Must return one LHS per bit of the block.
+    fn round_key(&self, round: usize, key_state: &[Vob]) -> Vec<Vob>;
+
+    /// The number of S-boxes applied to the key state once the round key of `round` is extracted.
+    fn num_key_sboxes(&self, round: usize) -> usize;
+
+    /// The bits of the key state read by the S-box at `pos` of `round`, and written with its
+    /// output.
+    fn key_sbox_bits(&self, round: usize, pos: usize) -> Range<usize>;
+
+    /// The generic Shard of the S-box at `pos` of `round`, typically based on its DDT.
+    fn key_generic_shard(&self, round: usize, pos: usize) -> GenericShard;
+
+    /// The linear map applied to the key state after the S-boxes of `round`.
+    fn apply_key_update(&self, round: usize, key_state: Vec<Vob>) -> Vec<Vob>;
+}
+
+/// Make the SoC of the related-key trails over `nr_rounds` rounds, the state going through the
+/// layers of `llh` and `sh`, and the key through the key schedule of `kh`. The Shards of each round
+/// are the ones of the S-boxes of the state, followed by the ones of the key schedule.
+///
+/// The solver of the SoC is to be given the block size of the first round plus the size of the key
+/// state as its block size, such that `Master` holds the input and the key.
+///
+/// Returns an `Error` of kind `ErrorKind::InvalidInput` if `nr_rounds` is 0, if a round key doesn't
+/// have the size of the block, or if the S-boxes of the key schedule read bits out of the key state.
+pub fn make_related_key_soc<L, S, K>(llh: &L, sh: &S, kh: &K, nr_rounds: usize)
+                                     -> Result<(System, Vec<Vec<Id>>), Error>
+    where
+        L: LLHandler,
+        S: SBoxHandler,
+        K: KeyScheduleHandler,
+{
+    if nr_rounds == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, "Expected at least one round"));
+    }
+    let block_size = llh.block_size(0);
+    let key_size = kh.key_state_size();
+    let mut nvar = block_size + key_size;
+    for r in 0..nr_rounds {
+        nvar += (0..sh.num_sboxes(r)).map(|s| sh.sbox_size_out(r, s)).sum::<usize>();
+        if r + 1 < nr_rounds {
+            nvar += (0..kh.num_key_sboxes(r)).map(|s| kh.key_sbox_bits(r, s).len()).sum::<usize>();
+        }
+    }
+    let unit = |var: usize| {
+        let mut lhs = Vob::from_elem(nvar, false);
+        lhs.set(var, true);
+        lhs
+    };
+
+    let mut state = llh.apply_initial_layer((0..block_size).map(unit).collect());
+    let mut key_state: Vec<Vob> = (block_size..block_size + key_size).map(unit).collect();
+    let mut next_var = block_size + key_size;
+    let mut shards = Vec::new();
+    let mut rounds = Vec::with_capacity(nr_rounds);
+    for r in 0..nr_rounds {
+        let round_key = kh.round_key(r, &key_state);
+        if round_key.len() != state.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Expected a round key of {} bits in round {}, found {}", state.len(), r, round_key.len())));
+        }
+        let inn: Vec<Vob> = state.into_iter().zip(round_key)
+            .map(|(mut bit, key_bit)| {
+                bit.xor(&key_bit);
+                bit
+            })
+            .collect();
+        let mut round = Vec::new();
+        let mut out = Vec::with_capacity(inn.len());
+        let mut inn_iter = inn.into_iter();
+        for s in 0..sh.num_sboxes(r) {
+            let outputs: Vec<Vob> = (next_var..next_var + sh.sbox_size_out(r, s)).map(unit).collect();
+            next_var += outputs.len();
+            out.extend(outputs.iter().cloned());
+            let id = Id::new(shards.len());
+            shards.push(sh.bt_generic_shard(r, s).into_specific(&mut inn_iter, &mut outputs.into_iter(), id));
+            round.push(id);
+        }
+        out.extend(inn_iter);
+
+        // No round key depends on the key schedule after the last round
+        if r + 1 < nr_rounds {
+            for s in 0..kh.num_key_sboxes(r) {
+                let bits = kh.key_sbox_bits(r, s);
+                if bits.end > key_size {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("The key S-box {} of round {} reads bits {:?} of a key state of {} bits", s, r, bits, key_size)));
+                }
+                let outputs: Vec<Vob> = (next_var..next_var + bits.len()).map(unit).collect();
+                next_var += outputs.len();
+                let inputs: Vec<Vob> = key_state[bits.clone()].to_vec();
+                key_state.splice(bits, outputs.iter().cloned());
+                let id = Id::new(shards.len());
+                shards.push(kh.key_generic_shard(r, s).into_specific(&mut inputs.into_iter(), &mut outputs.into_iter(), id));
+                round.push(id);
+            }
+            key_state = kh.apply_key_update(r, key_state);
+        }
+        rounds.push(round);
+        if r + 1 == nr_rounds {
+            break;
+        }
+        state = llh.apply_linear_layer(r + 1, out);
+    }
+    debug_assert_eq!(nvar, next_var);
+    Ok((System::from_elem(shards).unwrap(), rounds))
+}
+
+#[cfg(test)]
+mod test {
+    use crush::algebra::Matrix;
+
+    use crate::code_gen::cipher::{make_cipher_related_key_soc, make_related_key_solver, Cipher, KeyRound, SBox};
+    use crate::code_gen::fixture::{Silent, Toy, PRESENT};
+    use crate::diff_solver::{SolverConfig, SolverResult};
+
+    use super::*;
+
+    #[test]
+    fn related_key_solver() {
+        // The fewest active S-boxes of a related-key trail, by going through the differences of
+        // each key, round by round
+        let ddt = SBox::new(PRESENT.to_vec(), 4, 4).unwrap().ddt();
+        let linear_layer = |y: usize| (0..8).fold(0, |z, i| z | (((y >> i) & 1) << if i == 7 { 7 } else { 2 * i % 7 }));
+        let rotate = |k: usize, r: usize| (0..8).fold(0, |z, i| z | (((k >> ((i + r) % 8)) & 1) << i));
+        let mut expected = usize::MAX;
+        for key in 0..256 {
+            let mut best = vec![0; 256];
+            // The trivial trail, without any difference
+            if key == 0 {
+                best[0] = usize::MAX;
+            }
+            for r in 0..3 {
+                let mut next = vec![usize::MAX; 256];
+                for (x, cost) in best.iter().enumerate().filter(|(_, cost)| **cost != usize::MAX) {
+                    let a = x ^ rotate(key, r);
+                    let cost = cost + (a & 0xf != 0) as usize + (a >> 4 != 0) as usize;
+                    for b in (0..256).filter(|b| ddt[a & 0xf][b & 0xf] > 0 && ddt[a >> 4][b >> 4] > 0) {
+                        let y = linear_layer(b);
+                        next[y] = next[y].min(cost);
+                    }
+                }
+                best = next;
+            }
+            expected = expected.min(best.into_iter().min().unwrap());
+        }
+
+        let mut solver = make_related_key_solver(&Toy, 3, Silent, SolverConfig::new()).unwrap();
+        solver.run();
+        match solver.finalize() {
+            SolverResult::ProvedOptimal { weight, .. } => assert_eq!(expected as u32, weight),
+            _ => panic!("The solving of the related-key trails wasn't complete"),
+        }
+
+        // A key schedule with an S-box, and none at all
+        struct KeySBox;
+        impl Cipher for KeySBox {
+            fn name(&self) -> String { Toy.name() }
+            fn block_size(&self) -> usize { Toy.block_size() }
+            fn nr_rounds(&self) -> usize { Toy.nr_rounds() }
+            fn sbox(&self, round: usize, pos: usize) -> SBox { Toy.sbox(round, pos) }
+            fn linear_layer(&self, round: usize) -> Matrix { Toy.linear_layer(round) }
+            fn key_size(&self) -> usize { Toy.key_size() }
+            fn key_schedule(&self, round: usize) -> Option<KeyRound> {
+                Toy.key_schedule(round).map(|key_round| KeyRound { sboxes: vec![(4..8, Toy.sbox(round, 0))], ..key_round })
+            }
+        }
+        struct Keyless;
+        impl Cipher for Keyless {
+            fn name(&self) -> String { Toy.name() }
+            fn block_size(&self) -> usize { Toy.block_size() }
+            fn nr_rounds(&self) -> usize { Toy.nr_rounds() }
+            fn sbox(&self, round: usize, pos: usize) -> SBox { Toy.sbox(round, pos) }
+            fn linear_layer(&self, round: usize) -> Matrix { Toy.linear_layer(round) }
+        }
+        let (soc, rounds) = make_cipher_related_key_soc(&KeySBox, 3).unwrap();
+        // No S-box of the key schedule after the last round
+        assert_eq!(vec![3, 3, 2], rounds.iter().map(|ids| ids.len()).collect::<Vec<_>>());
+        assert_eq!(8 + 8 + 3 * 8 + 2 * 4, soc.get_nvar());
+        assert_eq!(ErrorKind::Unsupported, make_cipher_related_key_soc(&Keyless, 3).unwrap_err().kind());
+        assert_eq!(ErrorKind::InvalidInput, make_cipher_related_key_soc(&Toy, 0).unwrap_err().kind());
+    }
+}
@@ -0,0 +1,247 @@
+//! The result of a solving in a machine-readable form, such that the results of large campaigns of
+//! runs can be aggregated by scripts.
+//!
+//! A `RunResult` gathers the config of the solver, how the solving ended and how long it took, the
+//! bounds on the weight of the optimal trail, the best trails found and the statistics of the
+//! pruning, along with the `TrailSummary`s of the post-processing, if any. `to_json` renders it as
+//! a single JSON object, e.g.
+//!
+//! ```text
+//! {"outcome":"proved_optimal","elapsed_ms":1532,"config":{"join_order":"static","soft_limit":null,
+//! "prune_target":1,"hard_limit":null,"reorder_levels":false,"verbosity":"normal","stats_interval":null,
+//! "seed":null},
+//! "bounds":{"lower":3,"upper":3},"best_weight":3,"trails":[{"weight":3,"values":"0110..."}],
+//! "pruning":{"prunings":0,"nodes_pruned":0,"lowest_threshold":null},"summaries":[]}
+//! ```
+//!
+//! on a single line, an unknown value being `null`. The values of a trail (and the differences of a
+//! summary) are strings of bits, bit 0 first. With the `serde` feature, `RunResult` also
+//! implements `Serialize` and `Deserialize`.
+//!
+//! `SimpleSolver::set_result_file` writes the result of the solving when it is finalized, and
+//! `PostProcessor::with_run_result` writes it completed with the summaries of the post-processing.
+
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use vob::Vob;
+
+use super::config::SolverConfig;
+use super::meta::{SPFactory, WeightBounds};
+use super::post_processing_v5::{extract_best_k_trails, Trail, TrailSummary};
+use super::simple_solver::SolverResult;
+
+/// The statistics of the prunings of `Master` during a solving.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PruningStats {
+    /// The number of times `Master` was pruned.
+    pub prunings: usize,
+    /// The number of nodes removed by all the prunings.
+    pub nodes_pruned: usize,
+    /// The lowest prune threshold used, if any pruning removed a node.
+    pub lowest_threshold: Option<u32>,
+}
+
+impl PruningStats {
+    /// Count a pruning from `nodes_before` to `nodes_after` nodes, using `threshold` as its lowest
+    /// prune threshold.
+    pub fn record(&mut self, nodes_before: usize, nodes_after: usize, threshold: Option<u32>) {
+        self.prunings += 1;
+        self.nodes_pruned += nodes_before.saturating_sub(nodes_after);
+        if let Some(threshold) = threshold {
+            self.lowest_threshold = Some(self.lowest_threshold.map_or(threshold, |t| t.min(threshold)));
+        }
+    }
+}
+
+/// The result of a solving, see the module documentation.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunResult {
+    /// The config of the solver.
+    pub config: SolverConfig,
+    /// How the solving ended, see `SolverResult::name`.
+    pub outcome: String,
+    /// The time spent in `SimpleSolver::run`.
+    pub elapsed: Duration,
+    /// The bounds on the weight of the optimal trail known at the end of the solving.
+    pub bounds: WeightBounds,
+    /// The weight of the best trail found, if any.
+    pub best_weight: Option<u32>,
+    /// The best trails found, by increasing weight, see `extract_best_k_trails`.
+    pub trails: Vec<Trail>,
+    pub pruning: PruningStats,
+    /// The summaries of the post-processing, if any, see `PostProcessor`.
+    pub summaries: Vec<TrailSummary>,
+}
+
+impl RunResult {
+    /// The result of the solving of a solver with `config`, which ended with `result`, along with
+    /// its `k` best trails. No trail is extracted unless the solving is complete.
+    pub fn new<F: SPFactory + Debug>(config: &SolverConfig, result: &SolverResult<F>, k: usize) -> RunResult {
+        let run = result.run();
+        let trails = match result.is_complete() {
+            true => extract_best_k_trails(run, k).unwrap_or_default(),
+            false => Vec::new(),
+        };
+        RunResult {
+            config: config.clone(),
+            outcome: result.name().to_string(),
+            elapsed: run.elapsed,
+            bounds: run.bounds,
+            best_weight: run.bounds.upper,
+            trails,
+            pruning: run.pruning,
+            summaries: Vec::new(),
+        }
+    }
+
+    /// Add the summaries of the post-processing of the solving.
+    pub fn with_summaries(mut self, summaries: Vec<TrailSummary>) -> RunResult {
+        self.summaries = summaries;
+        self
+    }
+
+    /// Render the result as a JSON object, see the module documentation.
+    pub fn to_json(&self) -> String {
+        let config = &self.config;
+        let config = format!(
+            "{{\"join_order\":{},\"soft_limit\":{},\"prune_target\":{},\"hard_limit\":{},\
+              \"reorder_levels\":{},\"verbosity\":{},\"stats_interval\":{},\"seed\":{}}}",
+            json_string(&config.join_order().to_string()),
+            json_option(Some(config.soft_limit()).filter(|limit| *limit != usize::MAX)),
+            json_f64(config.prune_target()),
+            json_option(config.hard_limit()),
+            config.reorder_levels(),
+            json_string(&format!("{:?}", config.verbosity()).to_lowercase()),
+            json_option(config.stats_interval()),
+            json_option(config.seed()));
+        let trails: Vec<String> = self.trails.iter()
+            .map(|trail| format!("{{\"weight\":{},\"values\":\"{}\"}}", trail.weight, bits(&trail.values)))
+            .collect();
+        let summaries: Vec<String> = self.summaries.iter().map(summary_json).collect();
+        format!(
+            "{{\"outcome\":{},\"elapsed_ms\":{},\"config\":{},\"bounds\":{{\"lower\":{},\"upper\":{}}},\
+              \"best_weight\":{},\"trails\":[{}],\"pruning\":{{\"prunings\":{},\"nodes_pruned\":{},\
+              \"lowest_threshold\":{}}},\"summaries\":[{}]}}",
+            json_string(&self.outcome),
+            self.elapsed.as_millis(),
+            config,
+            json_option(self.bounds.lower),
+            json_option(self.bounds.upper),
+            json_option(self.best_weight),
+            trails.join(","),
+            self.pruning.prunings,
+            self.pruning.nodes_pruned,
+            json_option(self.pruning.lowest_threshold),
+            summaries.join(","))
+    }
+
+    /// Write the result as JSON to the file at `path`, on a single line, truncating the file if it
+    /// exists.
+    pub fn write_json(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", self.to_json())?;
+        writer.flush()
+    }
+}
+
+fn summary_json(summary: &TrailSummary) -> String {
+    let distribution: Vec<String> = summary.weight_distribution.iter()
+        .map(|(weight, count)| format!("\"{}\":{}", weight, count))
+        .collect();
+    format!(
+        "{{\"mode\":{},\"input\":\"{}\",\"output\":\"{}\",\"best_trail\":\"{}\",\"hull_weight\":{},\
+          \"weight_distribution\":{{{}}},\"trails\":{},\"trails_skipped\":{}}}",
+        json_string(&format!("{:?}", summary.mode).to_lowercase()),
+        bits(&summary.input),
+        bits(&summary.output),
+        bits(&summary.best_trail),
+        json_f64(summary.hull_weight),
+        distribution.join(","),
+        json_option(summary.trails),
+        summary.trails_skipped)
+}
+
+/// The bits of `vob` as a string of 0's and 1's, bit 0 first.
+fn bits(vob: &Vob) -> String {
+    vob.iter().map(|bit| if bit { '1' } else { '0' }).collect()
+}
+
+fn json_option<T: ToString>(value: Option<T>) -> String {
+    value.map_or("null".to_string(), |value| value.to_string())
+}
+
+/// `value` as a JSON number, `null` if it isn't finite.
+fn json_f64(value: f64) -> String {
+    match value.is_finite() {
+        true => value.to_string(),
+        false => "null".to_string(),
+    }
+}
+
+/// `text` as a JSON string.
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod test {
+    use crate::code_gen::cipher::TrailKind;
+    use crate::code_gen::fixture::toy_solver;
+
+    use super::*;
+
+    #[test]
+    fn run_result() {
+        let config = SolverConfig::new();
+        let unsolved = toy_solver(TrailKind::Differential, 3, config.clone()).finalize();
+        let result = RunResult::new(&config, &unsolved, 1);
+        assert_eq!("timed_out", result.outcome);
+        assert_eq!(None, result.best_weight);
+        assert!(result.trails.is_empty());
+        assert!(result.to_json().starts_with("{\"outcome\":\"timed_out\","));
+
+        let path = std::env::temp_dir().join(format!("pathfinder_run_result_{}.json", std::process::id()));
+        let mut solver = toy_solver(TrailKind::Differential, 3, config.clone());
+        solver.set_result_file(path.clone());
+        solver.run();
+        let solved = solver.finalize();
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let result = RunResult::new(&config, &solved, 1);
+        assert_eq!(result.to_json(), written.trim_end());
+        assert_eq!("proved_optimal", result.outcome);
+        assert_eq!(Some(3), result.best_weight);
+        assert_eq!(1, result.trails.len());
+        assert_eq!(3, result.trails[0].weight);
+        assert_eq!(0, result.pruning.prunings);
+        assert!(written.contains("\"best_weight\":3,\"trails\":[{\"weight\":3,\"values\":\""));
+        assert!(written.contains("\"config\":{\"join_order\":\"static\",\"soft_limit\":null,"));
+
+        // Master pruned after each join beyond a few nodes
+        let config = SolverConfig::new().with_soft_limit(8);
+        let mut solver = toy_solver(TrailKind::Differential, 3, config.clone());
+        solver.run();
+        let result = RunResult::new(&config, &solver.finalize(), 1);
+        assert!(result.pruning.prunings > 0);
+        assert!(result.pruning.nodes_pruned > 0);
+        assert!(result.to_json().contains(&format!("\"prunings\":{},", result.pruning.prunings)));
+    }
+}
@@ -1,11 +1,21 @@
 use std::cell::{Ref, RefMut};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
+use std::io;
 use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
+use num_bigint::BigUint;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
 use vob::Vob;
 
 use crush::algebra::{self, Matrix};
+use crush::{metrics, reporting};
+use crush::interrupt::Cancellation;
+use crush::budget::{BudgetStatus, MemoryBudget};
+use crush::reporting::{Event, Record};
 use crush::soc::bdd::Bdd;
 use crush::soc::bdd::differential::{PPFactory, StyledProgressBar};
 use crush::soc::Id;
@@ -14,13 +24,27 @@ use crush::soc::utils;
 
 use crate::diff_solver::SPFactory;
 
-use super::meta::{Librarian, Ops};
+use super::checkpoint::{self, SolverState};
+use super::config::{SolverConfig, Verbosity};
+use super::library::{Library, LibraryKey};
+use super::meta::{Librarian, Ops, WeightBounds};
 use super::meta::{AbsorbRec, JoinRec, PreAbsorbRec};
 use super::meta::CoreOps::*;
 use super::meta::Ops::*;
+use super::progress::ProgressReporter;
+use super::run_result::{PruningStats, RunResult};
+#[cfg(feature = "verify-sat")]
+use super::verify::{verify_trail, TrailCheck};
 
 pub type Depth = usize;
 
+/// Minimum number of nodes of the level below for a swap or an add on `Master` to run in parallel,
+/// see `SimpleSolver::set_num_threads`. Below it, the overhead outweighs the gain.
+const PARALLEL_MIN_LEVEL_SIZE: usize = 4096;
+
+/// The source of the records of the solver, see `crush::reporting`.
+const SOURCE: &str = "pathfinder.solver";
+
 #[allow(dead_code)]
 pub struct SimpleSolver<F>
     where
@@ -33,6 +57,8 @@ pub struct SimpleSolver<F>
     rounds: Vec<Vec<Id>>,
     /// Id's of all `Shard`s which have been joined into `Master`, including `Master`'s own original `Id`.
     joined_w_master: Vec<Id>,
+    /// Number of rounds whose Shards were all joined into `Master`.
+    rounds_done: usize,
     step: usize,
     librarian: Librarian<F>,
     /// Size of Master when constructed.
@@ -42,6 +68,36 @@ pub struct SimpleSolver<F>
     progress_arena: F,
     /// ProgressBar for the progress of joining Shards into Master
     join_progress: <F as SPFactory>::ProgressBar,
+    /// The bounds on the weight of the optimal trail, as currently known.
+    bounds: WeightBounds,
+    /// The lowest prune threshold used so far, if any pruning has taken place.
+    lowest_pruned: Option<u32>,
+    /// Whether `run` stopped early because it was cancelled or an interruption was requested.
+    interrupted: bool,
+    /// The strategy of the solving, see `SolverConfig`.
+    config: SolverConfig,
+    /// The budget on the nodes in memory, see `set_memory_budget`.
+    budget: Option<MemoryBudget>,
+    /// Whether `run` stopped early because the memory budget was exceeded.
+    out_of_budget: bool,
+    /// Whether `run` joined all Shards into `Master`.
+    finished: bool,
+    /// Where to write the checkpoints, and after how many joins, see `set_checkpointing`.
+    checkpointing: Option<(PathBuf, usize)>,
+    /// Number of joins since the last checkpoint.
+    joins_since_checkpoint: usize,
+    /// The threads swapping and adding the wide levels of `Master`, see `set_num_threads`.
+    pool: Option<ThreadPool>,
+    /// The time spent in `run` so far.
+    elapsed: Duration,
+    /// The prunings of `Master` so far.
+    pruning: PruningStats,
+    /// Where to write the `RunResult` of the solving, see `set_result_file`.
+    result_file: Option<PathBuf>,
+    /// Where to store the solving once all Shards are joined, see `set_library`.
+    library: Option<(Library, LibraryKey)>,
+    /// Where to send the progress of `run`, see `set_progress_reporter`.
+    progress: Option<Arc<dyn ProgressReporter>>,
 }
 
 impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
@@ -51,19 +107,22 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
                cohorts: HashMap<Id, Vec<Vob>>,
                master_block_size: usize,
                progress_arena: F,
+               config: SolverConfig,
     )
                -> Self
     {
         let master_id = Self::make_master(master_block_size, &mut soc);
 
         let joined_w_master = vec![];
-        let step = cohorts.iter().next().unwrap().1.len();
+        // The weight counts the active groups of 'step' LHS's. The cohorts are typically of the same
+        // size, but a truncated SoC has cohorts of several words, where each word is a group.
+        let step = cohorts.values().map(|lhss| lhss.len()).min().unwrap();
         // Invariant chek on 'step'
         for lhss in cohorts.values() {
-            if lhss.len() != step {
+            if lhss.len() % step != 0 {
                 panic!(
-                    "Currently only supports the same number of LHS's to be associated with an S-box,\
-                     but got two different values: {}, {}", step, lhss.len());
+                    "Currently only supports cohorts made of whole groups of LHS's,\
+                     but got two incompatible sizes: {}, {}", step, lhss.len());
             }
         }
 
@@ -77,62 +136,453 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
             cohorts,
             rounds,
             joined_w_master,
+            rounds_done: 0,
             step,
             librarian,
             master_block_size,
             progress_arena,
             join_progress,
+            bounds: WeightBounds::default(),
+            lowest_pruned: None,
+            interrupted: false,
+            budget: config.memory_budget(),
+            config,
+            out_of_budget: false,
+            finished: false,
+            checkpointing: None,
+            joins_since_checkpoint: 0,
+            pool: None,
+            elapsed: Duration::default(),
+            pruning: PruningStats::default(),
+            result_file: None,
+            library: None,
+            progress: None,
         };
 
         me
     }
 
+    /// Construct a `SimpleSolver` from the checkpoint at `path` (see `checkpoint`), such that
+    /// `run` continues the solving where it was when the checkpoint was written.
+    ///
+    /// The history of the `Librarian` restarts here, and the solving goes on with `config`, which
+    /// isn't part of the checkpoint. Returns an `Error` if the checkpoint can't be read.
+    pub fn resume_from_checkpoint(path: &Path, progress_arena: F, config: SolverConfig) -> io::Result<Self> {
+        let (state, soc) = checkpoint::read_checkpoint(path)?;
+        let join_progress = SPFactory::new_solve_progress(&progress_arena, soc.iter_bdds().count() as u64);
+        let librarian = Librarian::new(soc.get_size(), progress_arena.clone());
+        Ok(Self {
+            soc,
+            master_id: state.master_id,
+            cohorts: state.cohorts,
+            rounds: state.rounds,
+            joined_w_master: state.joined_w_master,
+            rounds_done: state.rounds_done,
+            step: state.step,
+            librarian,
+            master_block_size: state.master_block_size,
+            progress_arena,
+            join_progress,
+            bounds: state.bounds,
+            lowest_pruned: state.lowest_pruned,
+            interrupted: false,
+            budget: config.memory_budget(),
+            config,
+            out_of_budget: false,
+            finished: false,
+            checkpointing: None,
+            joins_since_checkpoint: 0,
+            pool: None,
+            elapsed: Duration::default(),
+            pruning: PruningStats::default(),
+            result_file: None,
+            library: None,
+            progress: None,
+        })
+    }
 
+    /// Write a checkpoint of the solving at `path`, to be resumed with `resume_from_checkpoint`.
+    /// See the `checkpoint` module for what is kept.
+    pub fn checkpoint(&self, path: &Path) -> io::Result<()> {
+        let state = SolverState {
+            master_id: self.master_id,
+            master_block_size: self.master_block_size,
+            step: self.step,
+            cohorts: self.cohorts.clone(),
+            rounds: self.rounds.clone(),
+            joined_w_master: self.joined_w_master.clone(),
+            rounds_done: self.rounds_done,
+            bounds: self.bounds,
+            lowest_pruned: self.lowest_pruned,
+        };
+        let _checkpoint = metrics::phase("pathfinder.checkpoint");
+        checkpoint::write_checkpoint(path, &state, &self.soc)
+    }
 
+    /// Make `run` write a checkpoint at `path` every `interval` joins, at the end of each round and
+    /// when it is interrupted, replacing the previous one.
+    pub fn set_checkpointing(&mut self, path: PathBuf, interval: usize) {
+        self.checkpointing = Some((path, interval.max(1)));
+    }
 
-    pub fn run(&mut self, soft_lim: usize) {
-        // use console::style;
-        use std::time::Instant;
+    /// Use `num_threads` threads for the swaps and adds absorbing the linear dependencies of
+    /// `Master`, which make up most of the time of a run. Only the levels of at least
+    /// `PARALLEL_MIN_LEVEL_SIZE` nodes are processed in parallel (see `System::par_swap`), the
+    /// result being the same as the sequential one but for the ids of the new nodes. 0 uses one
+    /// thread per core, 1 (the default) runs sequentially, as does a run with a deterministic
+    /// config whatever `num_threads` (see `SolverConfig::deterministic`).
+    ///
+    /// Returns an `Error` if the threads can't be created.
+    pub fn set_num_threads(&mut self, num_threads: usize) -> Result<(), ThreadPoolBuildError> {
+        self.pool = if num_threads == 1 {
+            None
+        } else {
+            Some(ThreadPoolBuilder::new().num_threads(num_threads).build()?)
+        };
+        Ok(())
+    }
 
-        let _start = Instant::now();
-        if self.rounds.len() == 0 { panic!("We cannot check a primitive with no rounds!")}
+    /// Make `run` keep the nodes in memory within `budget`. After each join, once the linear
+    /// dependencies are absorbed, the nodes of the SoC are observed by `budget`:
+    /// - if the budget is approached or exceeded, `Master` is pruned down to half the warning
+    ///   threshold of the budget (or to the prune limit of the config if lower), leaving room for
+    ///   the next join, which can double its size;
+    /// - if the budget is still exceeded after the pruning, `run` stops, and `finalize` returns
+    ///   `SolverResult::OutOfBudget`, with the bounds known at that point.
+    ///
+    /// Nodes are only observed between joins: the limit should leave room for the growth of
+    /// `Master` during one join. Replaces the budget of the hard limit of the config, if any.
+    pub fn set_memory_budget(&mut self, budget: MemoryBudget) {
+        self.budget = Some(budget);
+    }
 
-        let mut round_index = 1;
+    /// Send the progress of `run` to `progress`, on top of `crush::reporting`. See `run` and the
+    /// `progress` module.
+    pub fn set_progress_reporter(&mut self, progress: Arc<dyn ProgressReporter>) {
+        self.progress = Some(progress);
+    }
+
+    /// Returns the strategy of the solving.
+    pub fn config(&self) -> &SolverConfig {
+        &self.config
+    }
+
+    /// Join all Shards into `Master`, round by round, following the strategy of the config: the
+    /// next Shard of a round is picked by its join order (see `join_order`), and `Master` is pruned down to its prune
+    /// limit when it grows beyond its soft limit. See `SolverConfig`.
+    ///
+    /// If an interruption is requested (see `crush::interrupt`), stops after the current join with
+    /// the weight bounds updated, leaving `Master` partially joined. See `interrupted`. The same
+    /// goes if the memory budget is exceeded, see `set_memory_budget`.
+    ///
+    /// For a solver resumed from a checkpoint, the rounds and Shards already joined are skipped.
+    ///
+    /// The progress is reported with `crush::reporting`, from the source `pathfinder.solver`, and to
+    /// the reporter set with `set_progress_reporter`: an `Event::JoinStarted` before each join, an
+    /// `Event::RemainingWork` once joined, an `Event::Absorbed` for each linear dependency absorbed
+    /// into `Master` and an `Event::Pruned` for each pruning. Once a join is done with, an
+    /// `Event::Step` tells the size of the whole SoC, e.g. to plot the dynamics of the solving from
+    /// the output of `crush::reporting::CsvReporter`, and an `Event::RoundDone` tells the weight
+    /// bounds after each round. See the `progress` module.
+    pub fn run(&mut self) {
+        self.run_with(&Cancellation::new());
+    }
+
+    /// Same as `run`, but also stops after the current join once `cancellation` is cancelled or
+    /// its deadline is passed, e.g. for a wall-clock budget of the run:
+    ///
+    /// ```ignore
+    /// solver.set_checkpointing(path, 16);
+    /// solver.run_with(&Cancellation::with_timeout(Duration::from_secs(3600)));
+    /// ```
+    ///
+    /// A checkpoint is then written if checkpointing is set, from which the solving can be resumed
+    /// (see `resume_from_checkpoint`), and `finalize` returns `SolverResult::TimedOut` with the
+    /// weight bounds known at that point. As with an interruption, the current join is completed
+    /// first, the deadline can be overrun by one join.
+    pub fn run_with(&mut self, cancellation: &Cancellation) {
+        let start = Instant::now();
+        self.run_rounds(cancellation);
+        self.elapsed += start.elapsed();
+        if self.finished {
+            self.store_in_library();
+        }
+    }
+
+    fn run_rounds(&mut self, cancellation: &Cancellation) {
+        if self.rounds.len() == 0 { panic!("We cannot check a primitive with no rounds!")}
 
         // Go through and process all Shards in the SoC. The Shards need to joined by round, in order
         // to upheld the linear dependency invariant. (See todo ??).
         let roundss = self.rounds.clone();
-        for round in roundss.iter() {
-            for id in round {
-                if id == &self.master_id { continue }
-                self.join_op(*id);
+        for round in roundss.iter().skip(self.rounds_done) {
+            let round_index = self.rounds_done + 1;
+            let round_start = Instant::now();
+            let _round = reporting::stage(SOURCE, &format!("round {}", round_index));
+            let mut left: Vec<Id> = round.iter()
+                .filter(|id| **id != self.master_id && !self.joined_w_master.contains(id))
+                .cloned()
+                .collect();
+            while !left.is_empty() {
+                let next = self.config.join_order().next(&self.soc, self.master_id, &left);
+                let id = left.remove(next);
+                let join = metrics::phase("pathfinder.join");
+                self.record(Verbosity::Normal, Event::JoinStarted {
+                    bdds: vec![self.master_id, id],
+                    nodes: self.soc.get_size(),
+                });
+                self.join_op(id);
+                self.report_remaining_work();
                 self.join_progress.set_message(&format!("In round {} (of {}). Newest joined Shard: {}", round_index, roundss.len(), id));
 
                 self.resolve_any_deps();
-                self.check_prune(soft_lim);
+                if self.config.reorder_levels() {
+                    self.reorder_levels();
+                }
+                metrics::observe_nodes(self.soc.get_size());
+                let out_of_budget = self.prune_within_budget();
+                drop(join);
+                self.stats_snapshot();
+                self.report_step();
+                if out_of_budget {
+                    self.out_of_budget = true;
+                    self.update_bounds(false);
+                    self.auto_checkpoint(true);
+                    self.join_progress.finish_with_message(&format!(
+                        "Out of memory budget in round {} (of {}). Weight bounds: {}",
+                        round_index, roundss.len(), self.bounds));
+                    return;
+                }
+                if cancellation.is_cancelled() {
+                    self.interrupted = true;
+                    self.update_bounds(false);
+                    self.auto_checkpoint(true);
+                    let why = if cancellation.deadline_passed() { "Deadline reached" } else { "Interrupted" };
+                    self.join_progress.finish_with_message(&format!(
+                        "{} in round {} (of {}). Weight bounds: {}",
+                        why, round_index, roundss.len(), self.bounds));
+                    return;
+                }
+                self.joins_since_checkpoint += 1;
+                self.auto_checkpoint(false);
             }
-            round_index += 1;
+            self.update_bounds(round_index == roundss.len());
+            self.rounds_done += 1;
+            self.record(Verbosity::Normal, Event::RoundDone {
+                round: round_index,
+                rounds: roundss.len(),
+                lower_bound: self.bounds.lower,
+                upper_bound: self.bounds.upper,
+            });
+            self.auto_checkpoint(true);
+            metrics::timing("pathfinder.round", round_start.elapsed());
+            self.join_progress.set_message(&format!("Done with round {} (of {}). Weight bounds: {}",
+                                                    round_index, roundss.len(), self.bounds));
         }
+        self.finished = true;
         self.join_progress.finish_with_message("All Shards are joined into Master");
     }
 
+    /// Fix the values of the input variables of `Master` to `delta_in`, such that only the trails
+    /// starting with `delta_in` are kept when the SoC is solved. Must be called before `run`.
+    pub fn fix_input(&mut self, delta_in: &Vob) {
+        assert!(self.joined_w_master.is_empty(), "Master has already been joined with other Shards");
+        self.soc.pop_bdd(self.master_id).unwrap();
+        let master_id = Self::make_master_with(self.master_block_size, &mut self.soc, Some(delta_in));
+        debug_assert_eq!(master_id, self.master_id);
+    }
+
+    /// Restrict `Master` to the trails whose first S-box layer has the activity of `activity`:
+    /// for each range of input variables (the inputs of an S-box), whether one of them is non
+    /// zero. The input variables in none of the ranges are left free. Must be called before `run`.
+    ///
+    /// Panics if a range is empty, out of the input variables or overlaps another one.
+    pub fn fix_input_activity(&mut self, activity: &[(Range<usize>, bool)]) {
+        assert!(self.joined_w_master.is_empty(), "Master has already been joined with other Shards");
+        let mut ranges: Vec<&Range<usize>> = activity.iter().map(|(range, _)| range).collect();
+        ranges.sort_by_key(|range| range.start);
+        for (i, range) in ranges.iter().enumerate() {
+            assert!(range.start < range.end && range.end <= self.master_block_size,
+                    "The range {:?} isn't a range of input variables", range);
+            assert!(i == 0 || ranges[i - 1].end <= range.start, "The range {:?} overlaps another one", range);
+        }
+        self.soc.pop_bdd(self.master_id).unwrap();
+        let master_id = Self::make_master_with_activity(self.master_block_size, &mut self.soc, activity);
+        debug_assert_eq!(master_id, self.master_id);
+    }
+
+    /// Store the solving in the library under its key, if a library is set (see `set_library`). A
+    /// failure is reported but doesn't stop the solving.
+    fn store_in_library(&mut self) {
+        let (library, key) = match &self.library {
+            Some((library, key)) => (library.clone(), key.clone()),
+            None => return,
+        };
+        match library.store_solved(&key, self) {
+            Ok(()) => self.librarian.record(Ops::Text(format!("Stored in the library as {}", key))),
+            Err(e) => self.report_failure(format!("store the solving in the library at {}", library.dir().display()), &e),
+        }
+    }
+
+    /// Write a checkpoint if checkpointing is set and, unless `force`, enough joins were done since
+    /// the last one. A failure is reported but doesn't stop the solving.
+    fn auto_checkpoint(&mut self, force: bool) {
+        let path = match &self.checkpointing {
+            Some((path, interval)) if force || self.joins_since_checkpoint >= *interval => path.clone(),
+            _ => return,
+        };
+        match self.checkpoint(&path) {
+            Ok(()) => self.joins_since_checkpoint = 0,
+            Err(e) => self.report_failure(format!("write the checkpoint at {}", path.display()), &e),
+        }
+    }
+
+    /// Report a snapshot of the statistics of the SoC if the config asks for one every so many
+    /// joins, and this is one of them. The statistics aren't computed if no reporter is installed.
+    fn stats_snapshot(&self) {
+        match self.config.stats_interval() {
+            Some(joins) if self.joined_w_master.len().is_multiple_of(joins) && reporting::enabled() => {
+                reporting::stats_snapshot(SOURCE, &self.soc.stats());
+            }
+            _ => {}
+        }
+    }
+
+    /// Send `event` to `crush::reporting` and to the progress reporter, if any, if the verbosity of
+    /// the config is at least `verbosity`.
+    fn record(&self, verbosity: Verbosity, event: Event) {
+        if self.config.verbosity() >= verbosity {
+            if let Some(progress) = &self.progress {
+                progress.report(&Record { timestamp: SystemTime::now(), source: SOURCE, event: event.clone() });
+            }
+            reporting::report(SOURCE, event);
+        }
+    }
+
+    /// Record an `Event::Failure` of `operation` whatever the verbosity, the solving going on.
+    fn report_failure(&self, operation: String, error: &io::Error) {
+        self.record(Verbosity::Quiet, Event::Failure { operation, error: error.to_string() });
+    }
+
+    /// Return whether the events recorded at `verbosity` are sent anywhere, for the costly ones to
+    /// be skipped otherwise.
+    fn recording(&self, verbosity: Verbosity) -> bool {
+        self.config.verbosity() >= verbosity && (self.progress.is_some() || reporting::enabled())
+    }
+
+    /// Record an `Event::RemainingWork` after a join: the Shards left to join in all rounds and
+    /// the linear dependencies to absorb.
+    fn report_remaining_work(&self) {
+        if !self.recording(Verbosity::Normal) {
+            return;
+        }
+        let bdds = self.rounds.iter()
+            .flatten()
+            .filter(|id| **id != self.master_id && !self.joined_w_master.contains(id))
+            .count();
+        self.record(Verbosity::Normal, Event::RemainingWork { bdds, dependencies: self.soc.lhs_dependency_count() });
+    }
+
+    /// Record an `Event::Step` for the last join. The widths of the levels are only gone through
+    /// when it is recorded.
+    fn report_step(&self) {
+        if !self.recording(Verbosity::Normal) {
+            return;
+        }
+        let max_width = self.soc.iter_bdds()
+            .map(|(_, bdd)| bdd.borrow().iter_levels().map(|level| level.get_nodes_len()).max().unwrap_or(0))
+            .max()
+            .unwrap_or(0);
+        self.record(Verbosity::Normal, Event::Step {
+            step: self.joined_w_master.len(),
+            bdds: self.soc.iter_bdds().len(),
+            nodes: self.soc.get_size(),
+            max_width,
+        });
+    }
+
+    /// Returns true if the last call to `run` was interrupted or cancelled before all Shards were
+    /// joined.
+    pub fn interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    /// Returns true if the last call to `run` stopped before all Shards were joined, as the memory
+    /// budget was exceeded. See `set_memory_budget`.
+    pub fn out_of_budget(&self) -> bool {
+        self.out_of_budget
+    }
+
     pub fn soc(&self) -> &System {
         &self.soc
     }
 
-    pub fn finalize(self) -> SolverResultOk<F> {
+    /// Returns the bounds on the weight of the optimal trail, as known at the end of the last
+    /// completed round. See `WeightBounds` for details.
+    pub fn bounds(&self) -> WeightBounds {
+        self.bounds
+    }
+
+    /// Store the solving in `library` as the solved entry of `key` once `run` has joined all
+    /// Shards, such that a later run with the same key resumes it rather than solving it again.
+    /// See `Library::load_solved`. A failure to store it is reported as an `Event::Failure`.
+    pub fn set_library(&mut self, library: Library, key: LibraryKey) {
+        self.library = Some((library, key));
+    }
+
+    /// Write the `RunResult` of the solving to `path` as JSON when it is finalized, along with its
+    /// best trail. See `run_result`.
+    pub fn set_result_file(&mut self, path: PathBuf) {
+        self.result_file = Some(path);
+    }
+
+    /// Returns how the solving ended, along with its data. See `SolverResult`.
+    ///
+    /// If a result file is set (see `set_result_file`), a failure to write it is reported as an
+    /// `Event::Failure` with `crush::reporting`, the result being returned all the same.
+    pub fn finalize(self) -> SolverResult<F> {
         let ac = self.active_area();
+        let finished = self.finished;
+        let out_of_budget = self.out_of_budget;
+        let lowest_pruned = self.lowest_pruned;
+        let config = self.config;
+        let result_file = self.result_file;
 
-        SolverResultOk {
+        let run = SolverRun {
             librarian: self.librarian,
             master: self.soc,
             step: self.step,
             active_area: ac,
+            bounds: self.bounds,
+            elapsed: self.elapsed,
+            pruning: self.pruning,
+        };
+        let result = match (finished, run.bounds.upper, lowest_pruned) {
+            (false, _, _) if out_of_budget => SolverResult::OutOfBudget { run },
+            (false, _, _) => SolverResult::TimedOut { run },
+            (true, Some(weight), _) if run.bounds.is_tight() => SolverResult::ProvedOptimal { weight, run },
+            (true, Some(_), _) => SolverResult::FeasibleFound { bounds: run.bounds, run },
+            (true, None, Some(lowest_pruned)) => SolverResult::MemoryLimited { lowest_pruned, run },
+            (true, None, None) => SolverResult::Infeasible { run },
+        };
+        if let Some(path) = result_file {
+            if let Err(e) = RunResult::new(&config, &result, 1).write_json(&path) {
+                reporting::report(SOURCE, Event::Failure {
+                    operation: format!("write the result to {}", path.display()),
+                    error: e.to_string(),
+                });
+            }
         }
+        result
     }
 }
 
-pub struct SolverResultOk<F>
+/// The data of a solving, as left by `SimpleSolver::run`.
+///
+/// With the `serde` feature, only the history of `librarian` is serialized (see `Librarian`).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "", deserialize = "F: Default")))]
+pub struct SolverRun<F>
     where
         F: SPFactory + Debug,
 {
@@ -140,6 +590,123 @@ pub struct SolverResultOk<F>
     pub master: System,
     pub step: usize,
     pub active_area: Range<usize>,
+    /// The bounds on the weight of the optimal trail known at the end of the solving.
+    pub bounds: WeightBounds,
+    /// The time spent in `SimpleSolver::run`, since the solver was constructed or resumed.
+    pub elapsed: Duration,
+    /// The prunings of `Master`, since the solver was constructed or resumed.
+    pub pruning: PruningStats,
+}
+
+impl<F: SPFactory + Debug> SolverRun<F> {
+    /// Returns the exact number of trails of `Master` of each weight, the weight of a trail being
+    /// its number of active S-boxes, as for the bounds (see `Bdd::weight_distribution`). Where the
+    /// bounds only tell the weight of the best trail, the distribution tells how many trails of
+    /// each weight a differential (or linear hull) can be made of. The trivial trail is of weight 0.
+    ///
+    /// Returns `None` unless `Master` is the only Shard left, i.e. unless all Shards were joined.
+    pub fn weight_distribution(&self) -> Option<BTreeMap<usize, BigUint>> {
+        if self.master.iter_bdds().len() != 1 {
+            return None;
+        }
+        let (_, master) = self.master.iter_bdds().next()?;
+        Some(master.borrow().weight_distribution(&self.active_area, self.step))
+    }
+}
+
+/// How a solving ended, see `SimpleSolver::finalize`.
+///
+/// Only `TimedOut` and `OutOfBudget` leave `Master` partially joined, a partial result which can't
+/// be post-processed into complete trails.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "", deserialize = "F: Default")))]
+pub enum SolverResult<F>
+    where
+        F: SPFactory + Debug,
+{
+    /// All Shards were joined and the best trail of `Master` is optimal: nothing pruned could
+    /// have led to a better one. `weight` is the weight of the optimal trail.
+    ProvedOptimal { weight: u32, run: SolverRun<F> },
+    /// All Shards were joined, but pruning might have removed a better trail than the best one
+    /// of `Master`. `bounds` tells how far from optimal it may be.
+    FeasibleFound { bounds: WeightBounds, run: SolverRun<F> },
+    /// `run` stopped before all Shards were joined, because an interruption was requested (see
+    /// `crush::interrupt`, e.g. on Ctrl-C) or the run was cancelled (see `SimpleSolver::run_with`,
+    /// e.g. on a deadline). `Master` is only partially joined, and the bounds are the ones known
+    /// when it stopped.
+    TimedOut { run: SolverRun<F> },
+    /// `run` stopped before all Shards were joined, because the nodes in memory exceeded the
+    /// memory budget even after pruning (see `SimpleSolver::set_memory_budget`). As for
+    /// `TimedOut`, `Master` is only partially joined.
+    OutOfBudget { run: SolverRun<F> },
+    /// All Shards were joined, but no trail is left in `Master`: the pruning needed to stay
+    /// within the node limit removed all of them. `lowest_pruned` is the lowest prune threshold,
+    /// a lower bound on the weight of the optimal trail.
+    MemoryLimited { lowest_pruned: u32, run: SolverRun<F> },
+    /// All Shards were joined without pruning, and no non-trivial trail exists.
+    Infeasible { run: SolverRun<F> },
+}
+
+impl<F: SPFactory + Debug> SolverResult<F> {
+    /// The name of the way the solving ended, e.g. `proved_optimal`, as in `RunResult`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SolverResult::ProvedOptimal { .. } => "proved_optimal",
+            SolverResult::FeasibleFound { .. } => "feasible_found",
+            SolverResult::TimedOut { .. } => "timed_out",
+            SolverResult::OutOfBudget { .. } => "out_of_budget",
+            SolverResult::MemoryLimited { .. } => "memory_limited",
+            SolverResult::Infeasible { .. } => "infeasible",
+        }
+    }
+
+    /// Returns the data of the solving, whichever way it ended.
+    pub fn run(&self) -> &SolverRun<F> {
+        match self {
+            SolverResult::ProvedOptimal { run, .. }
+            | SolverResult::FeasibleFound { run, .. }
+            | SolverResult::TimedOut { run }
+            | SolverResult::OutOfBudget { run }
+            | SolverResult::MemoryLimited { run, .. }
+            | SolverResult::Infeasible { run } => run,
+        }
+    }
+
+    /// Returns the data of the solving, whichever way it ended.
+    pub fn into_run(self) -> SolverRun<F> {
+        match self {
+            SolverResult::ProvedOptimal { run, .. }
+            | SolverResult::FeasibleFound { run, .. }
+            | SolverResult::TimedOut { run }
+            | SolverResult::OutOfBudget { run }
+            | SolverResult::MemoryLimited { run, .. }
+            | SolverResult::Infeasible { run } => run,
+        }
+    }
+
+    /// Returns true if all Shards were joined into `Master`.
+    pub fn is_complete(&self) -> bool {
+        !matches!(self, SolverResult::TimedOut { .. } | SolverResult::OutOfBudget { .. })
+    }
+
+    /// Checks with a SAT solver that the best trail of `Master` is a solution of `original`, the
+    /// `System` given to `SimpleSolver::new`, see `verify::verify_trail`.
+    ///
+    /// Returns `None` if there is no trail to check, i.e. unless a trail was found after joining
+    /// all Shards, and an `Error` as `verify::verify_trail` does.
+    #[cfg(feature = "verify-sat")]
+    pub fn verify_with_sat(&self, original: &System) -> Option<io::Result<TrailCheck>> {
+        let run = match self {
+            SolverResult::ProvedOptimal { run, .. } | SolverResult::FeasibleFound { run, .. } => run,
+            _ => return None,
+        };
+        if run.master.iter_bdds().len() != 1 {
+            return None;
+        }
+        let (_, master) = run.master.iter_bdds().next()?;
+        let trail = master.borrow().extract_an_lsb_path(&run.active_area, run.step);
+        Some(verify_trail(original, &trail))
+    }
 }
 
 
@@ -178,18 +745,57 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
         if dependencies.is_empty() {
             return;
         }
-        debug_assert_eq!(dependencies.row_size(), self.step);
 
         // Absorb all dependencies
         while !dependencies.is_empty() {
             let dep = self.next_to_resolve(dependencies);
+            let nodes_before = self.master().get_size();
             self.resolve_dep(dep);
             // Update dependency matrix
             let lhs = self.master().get_lhs();
             dependencies = algebra::extract_linear_dependencies(matrix![lhs]);
+            let nodes_after = self.master().get_size();
+            self.record(Verbosity::Normal, Event::Absorbed { bdd: self.master_id, levels: 1, nodes_before, nodes_after });
+            if self.config.verbosity() >= Verbosity::Verbose {
+                reporting::memory_snapshot(SOURCE, self.soc.get_size());
+            }
         }
     }
 
+    /// Reorders the levels of `Master` above its active area, keeping each swap of two adjacent
+    /// levels which makes `Master` smaller. The levels of the cohorts are left in place, as the
+    /// pruning expects them to be adjacent.
+    fn reorder_levels(&mut self) {
+        let top = self.active_area().start;
+        if top < 2 {
+            return;
+        }
+        let _stage = if self.config.verbosity() >= Verbosity::Verbose {
+            Some(reporting::stage(SOURCE, "reorder levels"))
+        } else {
+            None
+        };
+        let kept: Vec<Vob> = self.var_mapping_for_master().values()
+            .flat_map(|lhss| lhss.iter())
+            .cloned()
+            .collect();
+        let mut swaps = 0;
+        for above in 0..top - 1 {
+            if kept.contains(&self.master().get_lhs_level(above))
+                || kept.contains(&self.master().get_lhs_level(above + 1)) {
+                continue;
+            }
+            let size = self.master().get_size();
+            self.swap_adjacent(above);
+            if self.master().get_size() < size {
+                swaps += 1;
+            } else {
+                self.swap_adjacent(above);
+            }
+        }
+        self.librarian.record(Text(format!("Reordering above depth {}: {} swaps kept", top, swaps)));
+    }
+
     /// Ensures that the `prune invariants` are upheld
     fn pre_prune(&mut self) {
         self.librarian.record(Text(format!("\nPre-pruning:\n")));
@@ -317,31 +923,104 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
         }
     }
 
-    /// Will execute prune if soft_lim is exceeded
-    fn check_prune(&mut self, soft_lim: usize) {
+    /// Prunes `Master` down to the prune limit of the config if it exceeds the soft limit, or down
+    /// to a lower limit when the memory budget is approached, see `set_memory_budget`. Returns true
+    /// if the budget is still exceeded after the pruning.
+    fn prune_within_budget(&mut self) -> bool {
+        let (soft_lim, prune_lim) = (self.config.soft_limit(), self.config.prune_limit());
+        let status = self.observe_budget();
+        if status == BudgetStatus::Within {
+            self.check_prune(soft_lim, prune_lim);
+            return false;
+        }
+        let budget_lim = self.budget.as_ref().map_or(prune_lim, |budget| budget.warning_nodes() / 2);
+        let lim = prune_lim.min(budget_lim);
+        self.check_prune(lim, lim);
+        self.observe_budget() == BudgetStatus::Exceeded
+    }
+
+    /// Observes the nodes of the SoC with the memory budget, if any, or only reports them.
+    fn observe_budget(&mut self) -> BudgetStatus {
+        match self.budget.as_mut() {
+            Some(budget) => budget.observe_system(SOURCE, &self.soc),
+            None => {
+                if self.config.verbosity() >= Verbosity::Normal {
+                    reporting::memory_snapshot(SOURCE, self.soc.get_size());
+                }
+                BudgetStatus::Within
+            }
+        }
+    }
+
+    /// Will execute prune down to `prune_lim` if soft_lim is exceeded
+    fn check_prune(&mut self, soft_lim: usize, prune_lim: usize) {
         if self.master().get_size() > soft_lim {
             self.pre_prune();
 
             let active_area = self.active_area();
 
             let mut prune_rec = Librarian::<F>::record_prune_helper();
+            let nodes_before = self.master().get_size();
 
             let prune_progress = PPFactory::new_progress_bar(
                 &self.progress_arena,
-                self.master().get_size().checked_sub(soft_lim).unwrap_or(42) as u64);
+                self.master().get_size().checked_sub(prune_lim).unwrap_or(42) as u64);
 
             self.master_mut()
-                .complexity_based_wide_prune_v3(soft_lim,
+                .complexity_based_wide_prune_v3(prune_lim,
                                                 active_area,
                                                 self.step,
                                                 &mut prune_rec,
                                                 prune_progress,
                 );
-            self.librarian.record(Ops::Prune(prune_rec.get_rec().unwrap())); // FIXME
+            let prune_rec = prune_rec.get_rec().unwrap(); // FIXME
+            let threshold = prune_rec.lowest_prune_threshold();
+            if let Some(threshold) = threshold {
+                self.lowest_pruned = Some(self.lowest_pruned.map_or(threshold, |t| t.min(threshold)));
+            }
+            self.librarian.record(Ops::Prune(prune_rec));
+            let nodes_after = self.master().get_size();
+            self.pruning.record(nodes_before, nodes_after, threshold);
+            self.record(Verbosity::Normal, Event::Pruned { bdd: self.master_id, nodes_before, nodes_after, threshold });
 
         }
     }
 
+    /// Updates the bounds on the weight of the optimal trail, and records them.
+    ///
+    /// Any complete trail must pass through one of the trails currently in `Master`, or through
+    /// one of the nodes pruned away. As the weight of a trail never decreases when more rounds are
+    /// added, the lowest non-trivial weight in `Master` and the lowest prune threshold seen so far
+    /// gives a lower bound. If `all_joined`, then the paths of `Master` are complete trails, and
+    /// the lowest non-trivial weight in `Master` is also an upper bound.
+    ///
+    /// The weights are found by a full traversal of the active area of `Master`, rather than
+    /// updated from the levels changed since the last call: the weight of a node depends on every
+    /// level below it, and the joins, absorptions, level swaps and prunings in between rewrite the
+    /// levels down to the sink, so no weight of the previous call can be kept. The bounds are thus
+    /// only updated once per round, and when the solving stops early.
+    fn update_bounds(&mut self, all_joined: bool) {
+        let active_area = self.active_area();
+        let top = active_area.start;
+        let master_lew = self.master()
+            .identify_trails_and_weights(active_area, self.step)
+            .lowest_non_trivial_weight_in_level(&top);
+
+        let lower = match (master_lew, self.lowest_pruned) {
+            (Some(m), Some(p)) => Some(m.min(p)),
+            (m, p) => m.or(p),
+        };
+        if let Some(lower) = lower {
+            self.bounds.raise_lower(lower);
+        }
+        if all_joined {
+            if let Some(upper) = master_lew {
+                self.bounds.lower_upper(upper);
+            }
+        }
+        self.librarian.record(Ops::Bounds(self.bounds));
+    }
+
     /// Returns a Range indicating which levels of the `Master` shard which needs to
     /// abide with the `prune invariants`.
     /// (See identify_trails_and_weights_core() for the invariants: FIXME needs to be moved to a pub fn that makes sense to have them...).
@@ -401,24 +1080,54 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
         // Shift downwards
         if current < to {
             while current < to {
-                self.soc.swap(self.master_id, current, current + 1)
-                    .expect(&format!("Current was at: {}. Master depth: {}. Swap failed",
-                                     current,
-                                     self.master().get_levels_size()));
+                self.swap_adjacent(current);
                 current += 1;
             }
             // Else, shift upwards
         } else if current > to {
             while current > to {
-                self.soc.swap(self.master_id, current - 1, current)
-                    .expect(&format!("Current was at: {}. Master depth: {}. Swap failed",
-                                     current,
-                                     self.master().get_levels_size()));
+                self.swap_adjacent(current - 1);
                 current -= 1;
             }
         }
     }
 
+    /// Swap the levels at `above` and `above + 1` in `Master`, in parallel if the level below is
+    /// wide enough and a thread pool is set.
+    fn swap_adjacent(&mut self, above: Depth) {
+        let parallel = self.parallel(above + 1);
+        let (soc, master_id) = (&mut self.soc, self.master_id);
+        let swapped = match self.pool.as_ref().filter(|_| parallel) {
+            Some(pool) => pool.install(|| soc.par_swap(master_id, above, above + 1)),
+            None => soc.swap(master_id, above, above + 1),
+        };
+        if let Err(e) = swapped {
+            panic!("Current was at: {}. Master depth: {}. Swap failed: {}",
+                   above,
+                   self.master().get_levels_size(),
+                   e);
+        }
+    }
+
+    /// Add the level at `above` to the one at `above + 1` in `Master`, in parallel if the level
+    /// below is wide enough and a thread pool is set.
+    fn add_adjacent(&mut self, above: Depth) {
+        let parallel = self.parallel(above + 1);
+        let (soc, master_id) = (&mut self.soc, self.master_id);
+        match self.pool.as_ref().filter(|_| parallel) {
+            Some(pool) => pool.install(|| soc.par_add(master_id, above, above + 1)),
+            None => soc.add(master_id, above, above + 1),
+        }.expect("Add failed.");
+    }
+
+    /// Whether the config isn't deterministic and the level at `depth` in `Master` has at least
+    /// `PARALLEL_MIN_LEVEL_SIZE` nodes, such that a swap or an add above it runs in the thread
+    /// pool, if one is set.
+    fn parallel(&self, depth: Depth) -> bool {
+        !self.config.is_deterministic() && self.master().iter_levels().nth(depth)
+            .is_some_and(|level| level.get_nodes_len() >= PARALLEL_MIN_LEVEL_SIZE)
+    }
+
     /// Absorb the given "linear dependency".
     fn resolve_dep(&mut self, dependency: Vob) {
 
@@ -431,7 +1140,7 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
             self.swap(base, next + 1);
             recording.record(Swap(base, next+1));
 
-            self.soc.add(self.master_id, *next, next + 1).expect("Add failed.");
+            self.add_adjacent(*next);
             recording.record(Add(*next, next+1),);
 
             base = next + 1;
@@ -589,6 +1298,12 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
     /// Returns the id of Master.
     ///
     fn make_master(block_size: usize, soc: &mut System) -> Id {
+        Self::make_master_with(block_size, soc, None)
+    }
+
+    /// As make_master, but if `fixed` is given, then each input variable only has the edge of
+    /// its value in `fixed`.
+    fn make_master_with(block_size: usize, soc: &mut System, fixed: Option<&Vob>) -> Id {
         use utils::{BddSpec, LevelSpec, NodeSpec};
 
         // Master LHS consists of input variables to first **block** of the cipher. TODO communicate this
@@ -601,8 +1316,13 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
         let mut i = 1;
         let mut levels: Vec<LevelSpec> = lhss.into_iter()
             .map(|lhs| {
+                let (e0, e1) = match fixed.map(|f| f.get(lhs as usize).unwrap()) {
+                    None => (i+1, i+1),
+                    Some(false) => (i+1, 0),
+                    Some(true) => (0, i+1),
+                };
                 let rhs = vec![
-                    NodeSpec::new(Id::new(i), Id::new(i+1), Id::new(i+1))
+                    NodeSpec::new(Id::new(i), Id::new(e0), Id::new(e1))
                 ];
                 i += 1;
 
@@ -632,6 +1352,54 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
         master_id
     }
 
+    /// As make_master, but each input variable in a range of `activity` only has the edges
+    /// keeping the activity of its range: none of the variables of an inactive range is set, and
+    /// at least one of an active range is. A level of an active range has two nodes, the first
+    /// reached while no variable of the range is set yet, and the second once one is.
+    fn make_master_with_activity(block_size: usize, soc: &mut System, activity: &[(Range<usize>, bool)]) -> Id {
+        use utils::{BddSpec, LevelSpec, NodeSpec};
+
+        // The id of the node of `var` reached in `state`, the sink being after the last variable
+        let id = |var: usize, state: usize| {
+            Id::new(if var == block_size { 1 + 2 * block_size } else { 1 + 2 * var + state })
+        };
+        let mut levels: Vec<LevelSpec> = (0..block_size)
+            .map(|var| {
+                let nodes = match activity.iter().find(|(range, _)| range.contains(&var)) {
+                    None => vec![NodeSpec::new(id(var, 0), id(var + 1, 0), id(var + 1, 0))],
+                    Some((_, false)) => vec![NodeSpec::new(id(var, 0), id(var + 1, 0), Id::new(0))],
+                    Some((range, true)) => {
+                        let last = var + 1 == range.end;
+                        let (unset, set) = if last {
+                            (Id::new(0), id(var + 1, 0))
+                        } else {
+                            (id(var + 1, 0), id(var + 1, 1))
+                        };
+                        let mut nodes = vec![NodeSpec::new(id(var, 0), unset, set)];
+                        if var > range.start {
+                            nodes.push(NodeSpec::new(id(var, 1), set, set));
+                        }
+                        nodes
+                    }
+                };
+                LevelSpec::new(vec![var as i64], nodes)
+            })
+            .collect();
+
+        // Push sink
+        levels.push(LevelSpec::new(vec![], vec![NodeSpec::new(id(block_size, 0), Id::new(0), Id::new(0))]));
+
+        let shard_id = soc.iter_bdds()
+            .map(|(id, _)| id)
+            .max().unwrap();
+        let master_id = Id::new(**shard_id + 1);
+
+        let mut master = BddSpec::new(master_id, levels);
+        soc.push_bdd(utils::build_bdd_from_spec(&mut master, soc.get_nvar())).unwrap();
+
+        master_id
+    }
+
     #[cfg(test)]
     #[allow(dead_code)]
     fn debug_master(master: &Bdd) {
@@ -643,7 +1411,7 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
             }
             println!("LHS: {}", lhs_buff);
             println!("RHS: ");
-            for n in level.get_nodes().iter() {
+            for n in level.iter_nodes() {
                 println!("{:?}", n);
             }
         }
@@ -690,4 +1458,205 @@ impl<F: SPFactory + PPFactory + Clone + Debug> SimpleSolver<F> {
 // Fn's potentially suitable for incorporation into a Diff_cipher trait
 //
 
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crush::reporting::InMemoryReporter;
+
+    use crate::code_gen::cipher::TrailKind;
+    use crate::code_gen::fixture::{toy_solver, Silent, ThisThread, REPORTER_LOCK};
+
+    use super::*;
+
+    #[test]
+    fn solver_memory_budget() {
+        let mut solver = toy_solver(TrailKind::Differential, 3, SolverConfig::new());
+        solver.set_memory_budget(MemoryBudget::new(1 << 20));
+        solver.run();
+        assert!(matches!(solver.finalize(), SolverResult::ProvedOptimal { weight: 3, .. }));
+
+        // Even pruned, Master doesn't fit in a single node
+        let config = SolverConfig::new().with_hard_limit(1);
+        let mut solver = toy_solver(TrailKind::Differential, 3, config);
+        solver.run();
+        assert!(solver.out_of_budget());
+        let result = solver.finalize();
+        assert!(matches!(result, SolverResult::OutOfBudget { .. }));
+        assert!(!result.is_complete());
+    }
+
+    #[test]
+    fn solver_deadline() {
+        // Stopped after its first join, the solving is resumed from the checkpoint written then.
+        let path = std::env::temp_dir().join(format!("pathfinder_deadline_{}.checkpoint", std::process::id()));
+        let mut solver = toy_solver(TrailKind::Differential, 3, SolverConfig::new());
+        solver.set_checkpointing(path.clone(), usize::MAX);
+        solver.run_with(&Cancellation::with_deadline(Instant::now()));
+        assert!(solver.interrupted());
+        assert!(matches!(solver.finalize(), SolverResult::TimedOut { .. }));
+
+        let mut solver = SimpleSolver::resume_from_checkpoint(&path, Silent, SolverConfig::new()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        solver.run_with(&Cancellation::new());
+        assert!(!solver.interrupted());
+        match solver.finalize() {
+            SolverResult::ProvedOptimal { weight, .. } => assert_eq!(3, weight),
+            _ => panic!("The resumed solving wasn't complete"),
+        }
+    }
+
+    #[test]
+    fn solver_weight_distribution() {
+        let solver = toy_solver(TrailKind::Differential, 3, SolverConfig::new());
+        assert!(solver.finalize().run().weight_distribution().is_none());
+
+        let mut solver = toy_solver(TrailKind::Differential, 3, SolverConfig::new());
+        solver.run();
+        let result = solver.finalize();
+        let distribution = result.run().weight_distribution().unwrap();
+        // The trivial trail, then the optimal ones of one active S-box per round
+        assert_eq!(Some((&0, &BigUint::from(1_u8))), distribution.iter().next());
+        assert_eq!(Some(&3), distribution.keys().nth(1));
+        assert!(distribution.keys().all(|weight| *weight <= 2 * 3));
+        let (_, master) = result.run().master.iter_bdds().next().unwrap();
+        assert_eq!(master.borrow().count_paths(), distribution.values().sum());
+    }
+
+    #[test]
+    fn solver_records() {
+        let _lock = REPORTER_LOCK.lock().unwrap();
+        let reporter = Arc::new(InMemoryReporter::new());
+        reporting::set_reporter(Arc::new(ThisThread::new(reporter.clone())));
+        let config = SolverConfig::new().with_stats_interval(1);
+        let mut solver = toy_solver(TrailKind::Differential, 2, config);
+        let shards = solver.soc().iter_bdds().len();
+        solver.run();
+        reporting::clear_reporter();
+
+        let events: Vec<Event> = reporter.records()
+            .into_iter()
+            .filter(|record| record.source == "pathfinder.solver")
+            .map(|record| record.event)
+            .collect();
+        assert!(events.contains(&Event::StageStarted { stage: "round 2".to_string() }));
+        assert!(events.iter().any(|event| matches!(event, Event::StageDone { stage, .. } if stage == "round 2")));
+        assert!(events.iter().any(|event| matches!(event, Event::MemorySnapshot { nodes, .. } if *nodes > 0)));
+        assert!(events.iter().any(|event| matches!(event,
+            Event::StatsSnapshot { bdd_nodes, biggest_widths, .. } if !bdd_nodes.is_empty() && !biggest_widths.is_empty())));
+        // Each join is recorded once, then its step once done with, each join leaving one Shard less
+        let joins = events.iter().filter(|event| matches!(event, Event::JoinStarted { bdds, .. } if bdds.len() == 2)).count();
+        let steps: Vec<(usize, usize)> = events.iter()
+            .filter_map(|event| match event {
+                Event::Step { step, bdds, nodes, max_width } => {
+                    assert!(nodes >= max_width && *max_width > 0);
+                    Some((*step, *bdds))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(shards - 1, joins);
+        assert_eq!((1..shards).map(|step| (step, shards - step)).collect::<Vec<_>>(), steps);
+        let rounds: Vec<usize> = events.iter()
+            .filter_map(|event| match event {
+                Event::RoundDone { round, rounds: 2, .. } => Some(*round),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![1, 2], rounds);
+    }
+
+    #[test]
+    fn solver_progress_json_lines() {
+        use crush::reporting::JsonReporter;
+
+        let reporter = Arc::new(JsonReporter::new(Vec::new()));
+        let mut solver = toy_solver(TrailKind::Differential, 2, SolverConfig::new());
+        let shards = solver.soc().iter_bdds().len();
+        solver.set_progress_reporter(reporter.clone());
+        solver.run();
+        drop(solver);
+
+        let out = String::from_utf8(Arc::try_unwrap(reporter).ok().unwrap().into_inner()).unwrap();
+        let mut remaining = Vec::new();
+        for line in out.lines() {
+            assert!(line.starts_with("{\"timestamp_ms\":") && line.ends_with('}'), "{}", line);
+            assert!(line.contains(",\"source\":\"pathfinder.solver\",\"event\":\""), "{}", line);
+            if let Some((_, fields)) = line.split_once("\"event\":\"remaining_work\",") {
+                let fields = fields.trim_end_matches('}');
+                let bdds = fields.split_once(',').and_then(|(bdds, _)| bdds.strip_prefix("\"bdds\":")).unwrap();
+                assert!(fields.contains(",\"dependencies\":"), "{}", line);
+                remaining.push(bdds.parse::<usize>().unwrap());
+            }
+        }
+        // Once per join, each join leaving one Shard less to join into Master
+        assert_eq!((0..shards - 1).rev().collect::<Vec<_>>(), remaining);
+        assert_eq!(shards - 1, out.matches("\"event\":\"step\"").count());
+        assert_eq!(2, out.matches("\"event\":\"round_done\"").count());
+    }
+
+    #[test]
+    fn solver_failures() {
+        // Checkpoints which can't be written are reported whatever the verbosity, the solving
+        // going on
+        let reporter = Arc::new(InMemoryReporter::new());
+        let config = SolverConfig::new().with_verbosity(Verbosity::Quiet);
+        let mut solver = toy_solver(TrailKind::Differential, 2, config);
+        solver.set_progress_reporter(reporter.clone());
+        let dir = std::env::temp_dir().join(format!("pathfinder_missing_{}", std::process::id()));
+        solver.set_checkpointing(dir.join("solver.checkpoint"), 1);
+        solver.run();
+        assert!(solver.finalize().is_complete());
+
+        let failures = reporter.records()
+            .into_iter()
+            .filter(|record| matches!(&record.event,
+                Event::Failure { operation, error } if operation.starts_with("write the checkpoint at") && !error.is_empty()))
+            .count();
+        assert!(failures > 0);
+    }
+
+    #[test]
+    fn solver_csv() {
+        use std::io::{self, Write};
+        use std::sync::Mutex;
+
+        use crush::reporting::CsvReporter;
+
+        /// A writer whose output is kept after the reporter is installed.
+        #[derive(Clone, Default)]
+        struct Shared(Arc<Mutex<Vec<u8>>>);
 
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let _lock = REPORTER_LOCK.lock().unwrap();
+        let out = Shared::default();
+        let mut solver = toy_solver(TrailKind::Differential, 2, SolverConfig::new());
+        let shards = solver.soc().iter_bdds().len();
+        reporting::set_reporter(Arc::new(ThisThread::new(Arc::new(CsvReporter::new(out.clone()).unwrap()))));
+        solver.run();
+        reporting::clear_reporter();
+
+        let csv = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(Some("timestamp_ms,source,event,fields"), lines.next());
+        let steps: Vec<&str> = lines
+            .filter_map(|line| line.split_once(",pathfinder.solver,step,").map(|(_, fields)| fields))
+            .collect();
+        // One step per Shard joined into Master, each join leaving one Shard less
+        assert_eq!(shards - 1, steps.len());
+        for (i, fields) in steps.iter().enumerate() {
+            let prefix = format!("step={} bdds={} nodes=", i + 1, shards - 1 - i);
+            assert!(fields.starts_with(&prefix), "{}", fields);
+            assert!(fields.contains(" max_width="));
+        }
+    }
+}
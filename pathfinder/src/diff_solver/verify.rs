@@ -0,0 +1,87 @@
+//! Verification of the trails found by a `SimpleSolver` with a SAT solver, to catch a trail which
+//! isn't a solution of the original system, e.g. because of a bug in a join, an absorption or a
+//! pruning.
+//!
+//! The original system is Tseitin encoded to CNF (see `System::to_cnf`), along with the linear
+//! equations of the trail, and handed to the SAT solver varisat.
+//!
+//! Only available with the `verify-sat` feature.
+
+use std::io::Error;
+
+use varisat::{CnfFormula, ExtendFormula, Lit, Solver};
+use vob::Vob;
+
+use crush::soc::system::System;
+
+/// The outcome of `verify_trail`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrailCheck {
+    /// The trail is a solution of the system, `assignment` being an assignment of the variables of
+    /// the system satisfying both.
+    Satisfiable { assignment: Vob },
+    /// No assignment satisfies both the system and the trail: the trail is not genuine.
+    Unsatisfiable,
+}
+
+impl TrailCheck {
+    /// Returns true if the trail turned out not to be a solution of the system.
+    pub fn is_discrepancy(&self) -> bool {
+        matches!(self, TrailCheck::Unsatisfiable)
+    }
+}
+
+/// Checks whether `trail`, given as the linear equations of its levels (see
+/// `Bdd::extract_an_lsb_path`), is a solution of `original`.
+///
+/// Returns an `Error` if the SAT solver fails.
+pub fn verify_trail(original: &System, trail: &[(Vob, bool)]) -> Result<TrailCheck, Error> {
+    let (mut cnf, mapping) = original.to_cnf();
+    for (lhs, rhs) in trail {
+        cnf.add_linear_equation(lhs, *rhs);
+    }
+    let mut formula = CnfFormula::new();
+    formula.set_var_count(cnf.get_nvars());
+    for clause in cnf.into_clauses() {
+        let clause: Vec<Lit> = clause.iter().map(|literal| Lit::from_dimacs(*literal as isize)).collect();
+        formula.add_clause(&clause);
+    }
+    let mut solver = Solver::new();
+    solver.add_formula(&formula);
+    let satisfiable = solver.solve().map_err(|e| Error::other(e.to_string()))?;
+    if !satisfiable {
+        return Ok(TrailCheck::Unsatisfiable);
+    }
+    let mut assignment = Vob::from_elem(mapping.nvar, false);
+    for literal in solver.model().expect("A satisfiable formula has a model") {
+        if literal.var().index() < mapping.nvar {
+            assignment.set(literal.var().index(), literal.is_positive());
+        }
+    }
+    Ok(TrailCheck::Satisfiable { assignment })
+}
+#[cfg(test)]
+mod test {
+    use vob::Vob;
+
+    use crate::code_gen::cipher::{make_window_soc, RoundWindow, TrailKind};
+    use crate::code_gen::fixture::{toy_solver, Toy};
+    use crate::diff_solver::SolverConfig;
+
+    use super::{verify_trail, TrailCheck};
+
+    #[test]
+    fn verify_toy_trails() {
+        let (original, _) = make_window_soc(&Toy, TrailKind::Differential, &RoundWindow::new(0..2)).unwrap();
+        let mut solver = toy_solver(TrailKind::Differential, 2, SolverConfig::new());
+        solver.run();
+        let check = solver.finalize().verify_with_sat(&original).unwrap().unwrap();
+        assert!(!check.is_discrepancy());
+
+        // No assignment sets a variable both to 0 and to 1
+        let mut lhs = Vob::from_elem(original.get_nvar(), false);
+        lhs.set(0, true);
+        let check = verify_trail(&original, &[(lhs.clone(), false), (lhs, true)]).unwrap();
+        assert_eq!(TrailCheck::Unsatisfiable, check);
+    }
+}
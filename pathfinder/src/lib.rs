@@ -1,5 +1,6 @@
 pub mod diff_solver;
 pub mod code_gen;
+pub mod ciphers;
 
 #[macro_use]
 extern crate crush;
@@ -74,6 +74,100 @@ pub enum DlOptions {
         silent_mode: bool,
     },
 
+    #[structopt(name = "boomerang")]
+    /// Search for boomerangs, i.e. an upper and a lower differential trail connected by the
+    /// Boomerang Connectivity Table (BCT) of the S-boxes in the switch round. SPN ciphers only.
+    /// The switch only connects S-boxes active on both sides. The probabilities reported are those
+    /// of the trails through the switch, pqr, r being given by the BCT, while the one of the
+    /// boomerang is p²q²r.
+    Boomerang {
+        #[structopt(short = "c", long = "cipher")]
+        /// Name of the cipher to analyze.
+        cipher: String,
+
+        #[structopt(short = "e", long = "exponent")]
+        /// Base2 log of maximum number of nodes the SoC may contain before starting the pruning operation.
+        soft_lim_exponent: usize,
+
+        #[structopt(short = "r", long = "rounds")]
+        num_rounds: usize,
+
+        #[structopt(long = "switch")]
+        /// The round of the boomerang switch, counting from 0.
+        switch_round: usize,
+
+        #[structopt(short = "o", long = "out")]
+        /// Folder to output generated SoC and other results.
+        /// Filename will be deduced from cipher and meta
+        out_parent_folder: PathBuf,
+
+        #[structopt(short = "s")]
+        /// Will hide the end output if set. That is, the progress bars will still show,
+        /// but not the end results.
+        silent_mode: bool,
+    },
+
+    #[structopt(name = "truncated")]
+    /// Search for truncated differential trails, where only the activity of each S-box is kept
+    /// track of. The weight of a truncated trail is its number of active S-boxes.
+    /// Only supported for SPN ciphers with bijective S-boxes.
+    Truncated {
+        #[structopt(short = "c", long = "cipher")]
+        /// Name of the cipher to analyze.
+        cipher: String,
+
+        #[structopt(short = "e", long = "exponent")]
+        /// Base2 log of maximum number of nodes the SoC may contain before starting the pruning operation.
+        soft_lim_exponent: usize,
+
+        #[structopt(short = "r", long = "rounds")]
+        num_rounds: usize,
+
+        #[structopt(short = "o", long = "out")]
+        /// Folder to output generated SoC and other results.
+        /// Filename will be deduced from cipher and meta
+        out_parent_folder: PathBuf,
+    },
+
+    #[structopt(name = "impossible")]
+    /// Search for impossible differentials with a single active S-box at each end.
+    /// The SoC is solved without pruning, which may need a lot of memory.
+    Impossible {
+        #[structopt(short = "c", long = "cipher")]
+        /// Name of the cipher to analyze.
+        cipher: String,
+
+        #[structopt(short = "r", long = "rounds")]
+        num_rounds: usize,
+
+        #[structopt(short = "o", long = "out")]
+        /// Folder to output generated SoC and other results.
+        /// Filename will be deduced from cipher and meta
+        out_parent_folder: PathBuf,
+    },
+
+    #[structopt(name = "integral")]
+    /// Search for integral distinguishers using the bit-based division property.
+    /// Only supported for SPN ciphers with a bit permutation as linear layer.
+    /// The SoC is solved without pruning, which may need a lot of memory.
+    Integral {
+        #[structopt(short = "c", long = "cipher")]
+        /// Name of the cipher to analyze.
+        cipher: String,
+
+        #[structopt(short = "r", long = "rounds")]
+        num_rounds: usize,
+
+        #[structopt(long = "constant")]
+        /// Input bits which are kept constant. All other input bits are active.
+        constant: Vec<usize>,
+
+        #[structopt(short = "o", long = "out")]
+        /// Folder to output generated SoC and other results.
+        /// Filename will be deduced from cipher and meta
+        out_parent_folder: PathBuf,
+    },
+
     #[structopt(name = "cg")]
     CG {
         #[structopt(short = "e", long = "exponent")]
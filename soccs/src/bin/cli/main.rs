@@ -9,7 +9,7 @@ use dl_options::DlOptions;
 use pathfinder::diff_solver::post_processing_v5::DisplayResult;
 use soccs::dl::{DLmode, OutFiles, RawSoc, Setup, SolvedSoC, StopAfter};
 use soccs::dl::builders::cg::{BtHandler, CgBuilder, SbHandler};
-use soccs::dl::cg_original::cipher::{Cipher, name_to_cipher, prince};
+use soccs::dl::cg_original::cipher::{Cipher, CipherStructure, name_to_cipher, prince};
 use soccs::dl::progress::{MyStyledSpinner, Progress};
 use crate::batches::*;
 
@@ -17,6 +17,8 @@ mod dl_options;
 mod batches;
 
 fn main() {
+    #[cfg(feature = "interrupt")]
+    crush::interrupt::install_handler().expect("failed to install the Ctrl-C handler");
 
     match DlOptions::from_args() {
         DlOptions::Diff {
@@ -140,6 +142,167 @@ fn main() {
 
         },
 
+        DlOptions::Boomerang {
+            cipher,
+            soft_lim_exponent,
+            num_rounds,
+            switch_round,
+            out_parent_folder,
+            silent_mode,
+        } => {
+
+            let cipher = match name_to_cipher(cipher.as_ref()) {
+                Some(c) => c,
+                None => {
+                    println!("Cipher not supported. Check --help for supported ciphers.");
+                    return;
+                }
+            };
+            if cipher.structure() != CipherStructure::Spn {
+                println!("Boomerangs are only supported for SPN ciphers.");
+                return;
+            }
+            if switch_round >= num_rounds {
+                println!("The switch round must be less than the number of rounds.");
+                return;
+            }
+
+            let soft_lim = unwrap_soft_lim(None, Some(soft_lim_exponent));
+            // Keep the results apart from those of the differential search
+            let out_files = OutFiles::new(out_parent_folder,
+                                          &format!("{}_boomerang{}", cipher.name(), switch_round),
+                                          num_rounds,
+                                          &DLmode::Differential,
+                                          soft_lim);
+
+            let setup = Setup::new(
+                cipher.name(),
+                cipher.structure(),
+                num_rounds,
+                soft_lim,
+                DLmode::Differential,
+                StopAfter::Process,
+                out_files,
+                None,
+                silent_mode,
+            ).with_switch_round(switch_round);
+
+            run(setup, cipher);
+        },
+
+        DlOptions::Truncated {
+            cipher,
+            soft_lim_exponent,
+            num_rounds,
+            out_parent_folder,
+        } => {
+
+            let cipher = match name_to_cipher(cipher.as_ref()) {
+                Some(c) => c,
+                None => {
+                    println!("Cipher not supported. Check --help for supported ciphers.");
+                    return;
+                }
+            };
+
+            let soft_lim = unwrap_soft_lim(None, Some(soft_lim_exponent));
+            let out_files = OutFiles::new(out_parent_folder, &cipher.name(), num_rounds, &DLmode::Truncated, soft_lim);
+
+            let setup = Setup::new(
+                cipher.name(),
+                cipher.structure(),
+                num_rounds,
+                soft_lim,
+                DLmode::Truncated,
+                StopAfter::Solve,
+                out_files,
+                None,
+                false,
+            );
+
+            run_truncated(setup, cipher);
+        },
+
+        DlOptions::Impossible {
+            cipher,
+            num_rounds,
+            out_parent_folder,
+        } => {
+
+            // See the differential case for why Prince is made manually
+            let cipher: Box<dyn Cipher + Send> =
+                if cipher == "prince".to_string() {
+
+                    let mut prince = prince::Prince::new();
+                    prince.set_num_rounds(num_rounds);
+                    Box::new(prince)
+                } else {
+                    match name_to_cipher(cipher.as_ref()) {
+                        Some(c) => c,
+                        None => {
+                            println!("Cipher not supported. Check --help for supported ciphers.");
+                            return;
+                        }
+                    }
+                };
+
+            // The search never prunes, see RawSoc::impossible_differentials.
+            let soft_lim = usize::MAX;
+            let out_files = OutFiles::new(out_parent_folder, &cipher.name(), num_rounds, &DLmode::Differential, soft_lim);
+
+            let setup = Setup::new(
+                cipher.name(),
+                cipher.structure(),
+                num_rounds,
+                soft_lim,
+                DLmode::Differential,
+                StopAfter::Solve,
+                out_files,
+                None,
+                false,
+            );
+
+            run_impossible(setup, cipher);
+        },
+
+        DlOptions::Integral {
+            cipher,
+            num_rounds,
+            constant,
+            out_parent_folder,
+        } => {
+
+            let cipher = match name_to_cipher(cipher.as_ref()) {
+                Some(c) => c,
+                None => {
+                    println!("Cipher not supported. Check --help for supported ciphers.");
+                    return;
+                }
+            };
+            if cipher.structure() != CipherStructure::Spn {
+                println!("Integral distinguishers are only supported for SPN ciphers.");
+                return;
+            }
+
+            // The search never prunes, see RawSoc::balanced_bits.
+            let soft_lim = usize::MAX;
+            let out_files = OutFiles::new(out_parent_folder, &cipher.name(), num_rounds, &DLmode::Division, soft_lim);
+
+            let setup = Setup::new(
+                cipher.name(),
+                cipher.structure(),
+                num_rounds,
+                soft_lim,
+                DLmode::Division,
+                StopAfter::Solve,
+                out_files,
+                None,
+                false,
+            );
+
+            run_integral(setup, cipher, &constant);
+        },
+
         DlOptions::CG {
             soft_lim_exponent,
             out_parent_folder,
@@ -330,6 +493,82 @@ fn run(setup: Setup, cipher: Box<dyn Cipher + Send>) {
     }
 }
 
+fn run_impossible(setup: Setup, cipher: Box<dyn Cipher + Send>) {
+    let progress_arena = Progress::new();
+    let main_pb = init_main_pb(&progress_arena, &setup, &cipher.name());
+    drive_progress(progress_arena.clone());
+
+    let inputs = match CgBuilder::from_cipher(&setup, cipher.as_ref()).single_sbox_inputs() {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            main_pb.finish_with_message(&format!("{}", e));
+            return;
+        }
+    };
+
+    let mut found = Vec::new();
+    for (i, delta_in) in inputs.iter().enumerate() {
+        main_pb.set_message(&format!("Solving for input difference {} (of {})", i + 1, inputs.len()));
+        let raw_soc = CgBuilder::from_cipher(&setup, cipher.as_ref());
+        for delta_out in raw_soc.impossible_differentials(delta_in, progress_arena.clone()) {
+            found.push((delta_in.clone(), delta_out));
+        }
+    }
+    main_pb.finish_with_message("All done!");
+    //  Allow main pb to be shut down, avoids mixups in the final printout
+    thread::sleep(Duration::from_secs(1));
+
+    println!("Found {} impossible differentials over {} rounds:", found.len(), setup.num_rounds());
+    for (delta_in, delta_out) in found.iter() {
+        println!("{} -> {}", delta_in, delta_out);
+    }
+}
+
+fn run_truncated(setup: Setup, cipher: Box<dyn Cipher + Send>) {
+    let progress_arena = Progress::new();
+    let main_pb = init_main_pb(&progress_arena, &setup, &cipher.name());
+    drive_progress(progress_arena.clone());
+
+    main_pb.set_message("Building truncated SoC");
+    let raw_soc = match CgBuilder::truncated(&setup, cipher.as_ref()) {
+        Ok(raw_soc) => raw_soc,
+        Err(e) => {
+            main_pb.finish_with_message(&format!("{}", e));
+            return;
+        }
+    };
+
+    main_pb.set_message(&format!("Solving: {}", setup.cipher_name()));
+    // The weight bounds are printed by the solver
+    let _ = raw_soc.solve_soc(&setup, progress_arena.clone());
+    main_pb.finish_with_message("Truncated SoC solved, the weight is the number of active S-boxes.");
+    //  Allow main pb to be shut down, avoids mixups in the final printout
+    thread::sleep(Duration::from_secs(1));
+}
+
+fn run_integral(setup: Setup, cipher: Box<dyn Cipher + Send>, constant: &[usize]) {
+    let progress_arena = Progress::new();
+    let main_pb = init_main_pb(&progress_arena, &setup, &cipher.name());
+    drive_progress(progress_arena.clone());
+
+    main_pb.set_message("Solving the division property SoC");
+    let raw_soc = CgBuilder::from_cipher(&setup, cipher.as_ref());
+    let balanced = match raw_soc.balanced_bits(constant, progress_arena.clone()) {
+        Ok(balanced) => balanced,
+        Err(e) => {
+            main_pb.finish_with_message(&format!("{}", e));
+            return;
+        }
+    };
+    main_pb.finish_with_message("All done!");
+    //  Allow main pb to be shut down, avoids mixups in the final printout
+    thread::sleep(Duration::from_secs(1));
+
+    println!("Found {} balanced bits over {} rounds, with constant input bits {:?}:",
+             balanced.len(), setup.num_rounds(), constant);
+    println!("{:?}", balanced);
+}
+
 ///
 fn init_main_pb(progress_arena: &Progress,
                 setup: &Setup,
@@ -1,5 +1,6 @@
 use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
 use std::num::NonZeroUsize;
 use std::ops::Range;
 use std::path::PathBuf;
@@ -10,12 +11,15 @@ use vob::Vob;
 
 use crush::algebra;
 use crush::algebra::Matrix;
+use crush::metrics;
 use crush::soc::bdd::differential::StyledProgressBar;
 use crush::soc::Id;
 use crush::soc::system::System;
 use pathfinder::code_gen::{LLHandler, SBoxHandler};
 use pathfinder::code_gen::gsf::GenericShard;
 use pathfinder::code_gen::soc_gen;
+use pathfinder::code_gen::truncated::{self, TruncatedSbHandler};
+use pathfinder::diff_solver::{make_boomerang_soc, switch_table};
 use pathfinder::diff_solver::post_processing_v5::BaseTable;
 use pathfinder::diff_solver::post_processing_v5::BTHandler;
 
@@ -41,17 +45,21 @@ impl CgBuilder {
     pub fn from_cipher(setup: &Setup, cipher: &dyn Cipher)
                        -> RawSoc<BtHandler, SbHandler>
     {
-        match setup.cipher_structure {
+        let build = metrics::phase("soccs.build");
+        let raw_soc = match setup.cipher_structure {
             CipherStructure::Spn => {
                 Self::spn(setup, cipher)
             },
             CipherStructure::Feistel => {
-                panic!("Unsupported CipherStructure. Feistels are unfortunately not supported (yet?)")
+                panic!("Unsupported CipherStructure. Feistels are unfortunately not supported (yet?), see pathfinder::ciphers::des for DES")
             },
             CipherStructure::Prince => {
                 Self::reflective(setup, cipher)
             },
-        }
+        };
+        metrics::observe_nodes(raw_soc.soc.get_size());
+        drop(build);
+        raw_soc
     }
 
     pub fn from_parent_folder(setup: &Setup,
@@ -66,7 +74,7 @@ impl CgBuilder {
                 Self::spn(setup, cipher)
             },
             CipherStructure::Feistel => {
-                panic!("Unsupported CipherStructure. Feistels are unfortunately not supported (yet?)")
+                panic!("Unsupported CipherStructure. Feistels are unfortunately not supported (yet?), see pathfinder::ciphers::des for DES")
             },
             CipherStructure::Prince => {
                 Self::reflective(setup, cipher)
@@ -81,8 +89,10 @@ impl CgBuilder {
         // Load SolvedSoc from file
         progress_spinner.println(&format!("Soc loaded from file: {}", file_path.display()));
         progress_spinner.set_message(&format!("Loading SoC from file: {}", file_path.display()));
-        let sys_spec = crush::soc::utils::parse_system_spec_from_file(&file_path);
-        let solved_soc = crush::soc::utils::build_system_from_spec(sys_spec);
+        let sys_spec = crush::soc::io::parse_system_spec_from_file(&file_path)
+            .unwrap_or_else(|e| panic!("Failed to load the SoC: {}", e));
+        let solved_soc = crush::soc::utils::build_system_from_spec(sys_spec)
+            .unwrap_or_else(|e| panic!("Failed to build the SoC: {}", e));
 
         // assumes all out bits are equal! (We don't support unequal step anyways).
         let step = raw_soc.sb_handler.sbox_size_out(0,0);
@@ -109,6 +119,9 @@ impl CgBuilder {
     {
         // CipherStructure should be checked elsewhere
         assert_eq!(0, setup.num_rounds() % 2);
+        if let DLmode::Division = setup.dl_mode() {
+            panic!("Unsupported mode. The division property is not supported for reflective ciphers");
+        }
 
         let llh =  Self::reflective_llh(cipher, setup.num_rounds());
         let (bth, sbh) = Self::make_bth_sbh(cipher,
@@ -127,6 +140,16 @@ impl CgBuilder {
             panic!("Invalid argument, cipher is of the wrong CipherStructure.");
         }
 
+        if let Some(switch_round) = setup.switch_round() {
+            return Self::boomerang(setup, cipher, switch_round);
+        }
+        // Division trails only propagate through the SoC's linear layer if it permutes the bits
+        if let DLmode::Division = setup.dl_mode() {
+            if !Self::is_bit_permutation(cipher) {
+                panic!("Unsupported cipher. The division property needs a bit permutation as linear layer");
+            }
+        }
+
         let llh = Self::spn_llh(cipher);
         let (bth, sbh) = Self::make_bth_sbh(cipher,
                                             setup.num_rounds(), setup.dl_mode());
@@ -136,25 +159,84 @@ impl CgBuilder {
         Self::make_rawsoc(soc, Box::new(llh), bth, sbh, setup.clone())
     }
 
+    /// Like `spn`, except that the S-boxes of round `switch_round` are based on their BCT, making
+    /// it the switch of a boomerang, see `pathfinder::diff_solver::switch_table`. Only supported in
+    /// differential mode, for bijective S-boxes.
+    fn boomerang(setup: &Setup, cipher: &dyn Cipher, switch_round: usize)
+        -> RawSoc<BtHandler, SbHandler>
+    {
+        if !matches!(setup.dl_mode(), DLmode::Differential) {
+            panic!("Unsupported mode. Boomerangs are only supported in differential mode");
+        }
+
+        let llh = Self::spn_llh(cipher);
+        let (mut bth, sbh) = Self::make_bth_sbh(cipher,
+                                                setup.num_rounds(), setup.dl_mode());
+
+        // The switch shards replace those of the switch round when building the SoC, while the
+        // switch tables replace the DDTs used in post processing.
+        let mut bt_cache: HashMap<RawTable, Arc<BaseTable>> = HashMap::new();
+        let mut switch = Vec::with_capacity(cipher.num_sboxes());
+        for pos in 0..cipher.num_sboxes() {
+            let sbox = cipher.sbox(switch_round * cipher.num_sboxes() + pos);
+            let raw_table = switch_table(&sbox.bct()
+                .expect("The S-boxes of the boomerang switch must be bijective"));
+
+            let bt = bt_cache.entry(raw_table.clone())
+                .or_insert_with(|| { Arc::new( BaseTable::try_from(raw_table).unwrap() )} );
+            switch.push(GenericShard::new(bt, sbox.size_in(), sbox.size_out()));
+            bth.bt_placement[switch_round][pos] = bt.clone();
+        }
+
+        let soc = make_boomerang_soc(&llh, &sbh, switch_round, switch,
+                                     setup.num_rounds())
+            .expect("Unable to build the boomerang SoC");
+
+        Self::make_rawsoc(soc, Box::new(llh), bth, sbh, setup.clone())
+    }
+
+
+    /// Build a truncated SoC, see `pathfinder::code_gen::truncated`. The BtHandler is the one of
+    /// the differential SoC, but is of little use as the post processing has no truncated mode.
+    pub fn truncated(setup: &Setup, cipher: &dyn Cipher) -> IoResult<RawSoc<BtHandler, TruncatedSbHandler>> {
+        if cipher.structure() != CipherStructure::Spn {
+            return Err(IoError::new(ErrorKind::InvalidInput,
+                                    "Truncated mode is only supported for SPN ciphers"));
+        }
+        if !matches!(setup.dl_mode(), DLmode::Truncated) {
+            return Err(IoError::new(ErrorKind::InvalidInput,
+                                    "The setup must be in truncated mode"));
+        }
+
+        let llh = Self::spn_llh(cipher);
+        let (bth, sbh) = Self::make_bth_sbh(cipher, setup.num_rounds(), setup.dl_mode());
+        let (t_llh, t_sbh) = truncated::truncate_handlers(&llh, &sbh, setup.num_rounds())?;
 
-    fn make_rawsoc((soc, rounds): (System, Vec<Vec<Id>>),
-                   llh: Box<dyn LLHandler>,
-                   bth: BtHandler,
-                   sbh: SbHandler,
-                   setup: Setup,
+        let soc = soc_gen::make_soc(&t_llh, &t_sbh, setup.num_rounds());
+
+        Ok(Self::make_rawsoc(soc, Box::new(t_llh), bth, t_sbh, setup.clone()))
+    }
+
+
+    fn make_rawsoc<S: SBoxHandler>((soc, rounds): (System, Vec<Vec<Id>>),
+                                   llh: Box<dyn LLHandler>,
+                                   bth: BtHandler,
+                                   sbh: S,
+                                   setup: Setup,
     )
-                   -> RawSoc<BtHandler, SbHandler>
+                                   -> RawSoc<BtHandler, S>
     {
         // Due to the way we construct our SoC's, we know that we're interested in the output bits
         // of any Shard. We therefore collect them together into cohorts, as requested by the
         // SimpleSolver.
-        let insize = sbh.sbox_size_in[0][0];
-        let cohorts = soc.iter_bdds()
-            .map(|(id, shard)| {
-                let to_keep = shard.borrow().get_lhs().iter().skip(insize)
+        let cohorts = rounds.iter().enumerate()
+            .flat_map(|(r, ids)| ids.iter().enumerate().map(move |(pos, id)| (r, pos, id)))
+            .map(|(r, pos, id)| {
+                let shard = soc.get_bdd(*id).unwrap().borrow();
+                let to_keep = shard.get_lhs().iter().skip(sbh.sbox_size_in(r, pos))
                     .cloned()
                     .collect();
-                (id.clone(), to_keep)
+                (*id, to_keep)
             }).collect();
 
         // Constructing the Matrix of all left-hand side linear combinations, as this is easiest to
@@ -218,6 +300,19 @@ impl CgBuilder {
         }
     }
 
+    /// True if the linear layer of `cipher` only moves the bits around.
+    fn is_bit_permutation(cipher: &dyn Cipher) -> bool {
+        let mut seen = 0_u128;
+        for i in 0..cipher.size() {
+            let out = cipher.linear_layer(1 << i);
+            if out.count_ones() != 1 || seen & out != 0 {
+                return false;
+            }
+            seen |= out;
+        }
+        true
+    }
+
     fn make_bth_sbh(cipher: &dyn Cipher, nr_rounds: usize, dl_mode: DLmode) -> (BtHandler, SbHandler) {
 
 
@@ -244,8 +339,10 @@ impl CgBuilder {
 
                 // Update BT cache as needed
                 let raw_table = match dl_mode {
-                    DLmode::Differential => sbox.ddt().clone(),
+                    // A truncated SoC is derived from the differential one
+                    DLmode::Differential | DLmode::Truncated => sbox.ddt().clone(),
                     DLmode::Linear => CgBuilder::adjust_lat(sbox.lat()),
+                    DLmode::Division => sbox.division_table(),
                         // panic!("Upadte needed, see comment in code"); sbox.lat()}, // FIXME for each entry e => | (2*e) - 2^in_size | // TODO verify fix
                     // Their LAT is different than the one we thought they were using
                 };
@@ -131,4 +131,17 @@ impl Sbox {
     pub fn ddt(&self) -> &Vec<Vec<usize>> {
         &self.ddt
     }
+
+    /// Returns the BCT of the S-box, or None if the S-box is not bijective.
+    pub fn bct(&self) -> Option<Vec<Vec<usize>>> {
+        let table: Vec<usize> = self.table.iter().map(|y| *y as usize).collect();
+        pathfinder::diff_solver::bct(&table)
+    }
+
+    /// Returns the division trail table of the S-box, see
+    /// `pathfinder::diff_solver::division_table`.
+    pub fn division_table(&self) -> Vec<Vec<usize>> {
+        let table: Vec<usize> = self.table.iter().map(|y| *y as usize).collect();
+        pathfinder::diff_solver::division_table(&table, self.in_size, self.out_size)
+    }
 }
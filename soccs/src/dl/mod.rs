@@ -5,7 +5,7 @@ use std::fmt::{Debug, Error as FmtError};
 use std::fmt::Write as FmtWrite;
 use std::fs;
 use std::fs::OpenOptions;
-use std::io::{BufWriter, Result as IoResult};
+use std::io::{BufWriter, Error as IoError, ErrorKind, Result as IoResult};
 use std::io::Write;
 use std::num::NonZeroUsize;
 use std::ops::Range;
@@ -19,7 +19,8 @@ use crush::soc::bdd::differential::PPFactory;
 use crush::soc::Id;
 use crush::soc::system::System;
 use pathfinder::code_gen::{LLHandler, SBoxHandler};
-use pathfinder::diff_solver::{Librarian, SimpleSolver, SolverResultOk, SPFactory};
+use pathfinder::diff_solver::{balanced_bits, Difference, ImpossibleDifferentialSearch};
+use pathfinder::diff_solver::{Librarian, SimpleSolver, SolverConfig, SolverResult, SolverRun, SPFactory};
 // use pathfinder::diff_solver::post_processing_v3::{PostPFactory, PostProc, ProcessedResult as ProcessedResultV3};
 use pathfinder::diff_solver::post_processing_v5::{AnalysisMode, BTHandler, TraceLogger};
 use pathfinder::diff_solver::post_processing_v5::{DisplayResult, Handlers, ProcessedResult, SolvedSocMeta, start_post_processing};
@@ -43,6 +44,7 @@ pub struct Setup {
     out_files: OutFiles,
     in_parent_folder: Option<PathBuf>,
     silent_mode: bool,
+    switch_round: Option<usize>,
 }
 
 impl Setup {
@@ -66,10 +68,18 @@ impl Setup {
             stop_after,
             out_files,
             in_parent_folder,
-            silent_mode
+            silent_mode,
+            switch_round: None,
         }
     }
 
+    /// Search for boomerangs rather than differential trails, with round `switch_round` as the
+    /// boomerang switch. See `pathfinder::diff_solver::make_boomerang_soc`.
+    pub fn with_switch_round(mut self, switch_round: usize) -> Self {
+        self.switch_round = Some(switch_round);
+        self
+    }
+
     #[inline]
     pub fn cipher_name(&self) -> String {
         self.cipher_name.to_string()
@@ -109,6 +119,11 @@ impl Setup {
     pub fn silent_mode(&self) -> bool {
         self.silent_mode
     }
+
+    #[inline]
+    pub fn switch_round(&self) -> Option<usize> {
+        self.switch_round
+    }
 }
 
 /// What stages should be completed before we are done?
@@ -187,11 +202,17 @@ impl OutFiles {
 /// The mode of operandi to use.
 /// The available options are:
 /// 1) Differential
-/// 2) Linear.
+/// 2) Linear
+/// 3) Division, i.e. the bit-based division property, used to search for integral
+///    distinguishers. Not supported by the post processing.
+/// 4) Truncated, i.e. truncated differentials over the activity of the S-boxes. Not supported by
+///    the post processing.
 #[derive(Debug, Clone)]
 pub enum DLmode{
     Differential,
     Linear,
+    Division,
+    Truncated,
 }
 
 impl From<AnalysisMode> for DLmode {
@@ -209,17 +230,21 @@ impl From<DLmode> for AnalysisMode {
         use DLmode::*;
         match dl {
             Differential => AnalysisMode::Differential,
-            Linear => AnalysisMode::Linear
+            Linear => AnalysisMode::Linear,
+            Division => panic!("The post processing has no division property mode"),
+            Truncated => panic!("The post processing has no truncated mode"),
         }
     }
 }
 
 impl fmt::Display for DLmode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use DLmode::{Linear, Differential};
+        use DLmode::{Linear, Differential, Division, Truncated};
         match self {
             Differential => write!(f, "diff")?,
             Linear => write!(f, "lin")?,
+            Division => write!(f, "div")?,
+            Truncated => write!(f, "trunc")?,
         }
 
         Ok(())
@@ -328,16 +353,34 @@ impl<B, S> RawSoc<B, S>
             self.cohorts.clone(),
             self.ll_handler.block_size(0),
             progress.clone(),
+            SolverConfig::new().with_soft_limit(setup.soft_lim()),
         );
-        solver.run(setup.soft_lim());
-
-        let SolverResultOk {
+        solver.run();
+
+        let result = solver.finalize();
+        match &result {
+            SolverResult::ProvedOptimal { weight, .. } =>
+                println!("Optimal trail has weight {}", weight),
+            SolverResult::FeasibleFound { bounds, .. } =>
+                println!("Trail found, weight bounds of the optimal trail: {}", bounds),
+            SolverResult::TimedOut { run } =>
+                println!("Weight bounds of the optimal trail: {}", run.bounds),
+            SolverResult::OutOfBudget { run } =>
+                println!("Out of memory budget. Weight bounds of the optimal trail: {}", run.bounds),
+            SolverResult::MemoryLimited { lowest_pruned, .. } =>
+                println!("All trails were pruned, the optimal trail has weight at least {}", lowest_pruned),
+            SolverResult::Infeasible { .. } =>
+                println!("No non-trivial trail exists"),
+        }
+        let interrupted = !result.is_complete();
+        let SolverRun {
             librarian,
             master,
             step,
-            active_area
+            active_area,
+            ..
         }
-            = solver.finalize();
+            = result.into_run();
 
         // == Write Shard to .bdd file ==
         let out_setup = setup.out_files();
@@ -348,7 +391,18 @@ impl<B, S> RawSoc<B, S>
             .recursive(true)
             .create(out_setup.out_parent_folder.clone());
 
-        crush::soc::utils::print_system_to_file(&master, &out_setup.bdd_file);
+        if interrupted {
+            // Master is only partially joined, so post-processing makes no sense. Save it and exit.
+            let mut checkpoint = out_setup.bdd_file.clone();
+            checkpoint.set_extension("interrupted.bdd");
+            crush::soc::io::print_checkpoint_to_file(&master, &checkpoint)
+                .unwrap_or_else(|e| panic!("Failed to write the checkpoint: {}", e));
+            println!("Solving interrupted, checkpoint written to {}", checkpoint.display());
+            std::process::exit(crush::interrupt::EXIT_CODE);
+        }
+
+        crush::soc::io::print_system_to_file(&master, &out_setup.bdd_file)
+            .unwrap_or_else(|e| panic!("Failed to write the SoC: {}", e));
 
 
         SolvedSoC {
@@ -365,6 +419,102 @@ impl<B, S> RawSoc<B, S>
         }
     }
 
+    /// The input differences of the first S-box layer with a single active S-box. These are the
+    /// candidate Δin's of the impossible differential search, see `impossible_differentials`.
+    ///
+    /// Returns an Error for Feistel ciphers, as their first S-box layer does not consume the initial
+    /// block.
+    pub fn single_sbox_inputs(&self) -> IoResult<Vec<Difference>> {
+        if let CipherStructure::Feistel = self.setup.cipher_structure {
+            return Err(IoError::new(ErrorKind::Other,
+                                    "Impossible differentials are not supported for Feistel ciphers"));
+        }
+        Ok(Difference::single_sbox(self.soc.get_nvar(),
+                                   0..self.ll_handler.block_size(0),
+                                   self.sb_handler.sbox_size_in(0, 0)))
+    }
+
+    /// Solve the SoC with the input difference fixed to `delta_in`, and return the output
+    /// differences of the last S-box layer with a single active S-box which are not reached by
+    /// any trail. I.e. each returned Δout makes (`delta_in`, Δout) an impossible differential.
+    ///
+    /// The SoC is solved without any pruning, as any pruned trail would be taken as impossible.
+    pub fn impossible_differentials(self, delta_in: &Difference, progress: Progress) -> Vec<Difference> {
+        let nvar = self.soc.get_nvar();
+        let last = self.rounds.len() - 1;
+        let out_size = self.sb_handler.sbox_size_out(last, 0);
+        let outputs = Difference::single_sbox(nvar,
+                                              nvar - self.sb_handler.num_sboxes(last) * out_size..nvar,
+                                              out_size);
+
+        let mut solver = SimpleSolver::new(
+            self.soc,
+            self.rounds,
+            Id::new(0), // TODO remove
+            self.cohorts,
+            self.ll_handler.block_size(0),
+            progress,
+            SolverConfig::new(),
+        );
+        solver.fix_input(delta_in.values());
+        solver.run();
+
+        let SolverRun { master, .. } = solver.finalize().into_run();
+        let (_master_id, master) = master.iter_bdds().next().unwrap();
+        let master = master.borrow();
+
+        // Δin is already fixed, so we only need to check which Δout's can reach the source.
+        let search = ImpossibleDifferentialSearch::with_middle(&master, 0);
+        let free = Difference::new(Vob::from_elem(nvar, false), Vob::from_elem(nvar, false));
+        search.search(&[free], &outputs).into_iter()
+            .map(|(_, o)| outputs[o].clone())
+            .collect()
+    }
+
+    /// Solve the SoC, built in division property mode, with the bits in `constant` fixed and all
+    /// other input bits active. Returns the balanced output bits of the last S-box layer, i.e. an
+    /// integral distinguisher.
+    ///
+    /// The SoC is solved without any pruning, as any pruned division trail would make a bit seem
+    /// balanced.
+    pub fn balanced_bits(self, constant: &[usize], progress: Progress) -> IoResult<Vec<usize>> {
+        if !matches!(self.setup.mode, DLmode::Division) {
+            return Err(IoError::new(ErrorKind::InvalidInput,
+                                    "The SoC must be built in division property mode"));
+        }
+        let nvar = self.soc.get_nvar();
+        let block_size = self.ll_handler.block_size(0);
+        let mut k_in = Vob::from_elem(nvar, false);
+        for bit in 0..block_size {
+            k_in.set(bit, true);
+        }
+        for bit in constant {
+            if *bit >= block_size {
+                return Err(IoError::new(ErrorKind::InvalidInput,
+                                        format!("Bit {} is outside of the block", bit)));
+            }
+            k_in.set(*bit, false);
+        }
+
+        let mut solver = SimpleSolver::new(
+            self.soc,
+            self.rounds,
+            Id::new(0), // TODO remove
+            self.cohorts,
+            block_size,
+            progress,
+            SolverConfig::new(),
+        );
+        solver.fix_input(&k_in);
+        solver.run();
+
+        let SolverRun { master, .. } = solver.finalize().into_run();
+        let (_master_id, master) = master.iter_bdds().next().unwrap();
+        let master = master.borrow();
+
+        Ok(balanced_bits(&master, nvar - block_size..nvar))
+    }
+
     /// Creates a String based on the name of the Cipher, the number of rounds and the soft_lim used.
     /// I.e. perhaps the main setup parameters, and may be useful for identifications of run-through.
     ///
@@ -417,6 +567,11 @@ impl<B, S, F> SolvedSoC<B, S, F>
         where
             P: PPFactory,
     {
+        if let DLmode::Division | DLmode::Truncated = self.setup.mode {
+            return Err(IoError::new(ErrorKind::Other,
+                                    format!("Post processing is not supported in mode {}", self.setup.mode)));
+        }
+
         // Make the SoC into a single Shard
         let master: HashMap<Id, RefCell<Shard>> = self.soc.drain_bdds().collect();
         debug_assert_eq!(master.len(), 1);
@@ -491,9 +646,6 @@ impl<B, S, F> SolvedSoC<B, S, F>
         Ok(pp_res)
     }
 
-
-
-
     fn write_file(pathbuf: PathBuf, content: &str) -> IoResult<()> {
         let write_file = OpenOptions::new()
             .write(true)
@@ -0,0 +1,145 @@
+/* C API of crush, see socs-ffi/src/lib.rs. Regenerate with cbindgen (see cbindgen.toml). */
+
+#ifndef SOCS_H
+#define SOCS_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/* A bdd, e.g. a Shard to add to a system. */
+typedef struct SocsBdd SocsBdd;
+
+/* The solutions of a system, see the crate documentation. */
+typedef struct SocsSolutions SocsSolutions;
+
+/* A system of bdds. */
+typedef struct SocsSystem SocsSystem;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Return the message of the last error of the calling thread, or `NULL` if none. The message is
+ valid until the next error of the thread.
+ */
+const char *socs_last_error(void);
+
+/* Release a string returned by the API. */
+void socs_string_free(char *string);
+
+/* Return a new system of `nvar` variables, without bdd. */
+SocsSystem *socs_system_new(size_t nvar);
+
+/*
+ Read the system of the .bdd file at `path`, or of the binary snapshot if its extension is
+ `snap`.
+ */
+SocsSystem *socs_system_load(const char *path);
+
+/* Build the system of `spec`, in the .bdd format. */
+SocsSystem *socs_system_from_spec(const char *spec);
+
+/* Write `system` to the .bdd file at `path` as a checkpoint, keeping the linear equations found. */
+int socs_system_save(const SocsSystem *system, const char *path);
+
+/* Return a copy of `system`, e.g. to keep it before solving. */
+SocsSystem *socs_system_clone(const SocsSystem *system);
+
+/* Release `system`. */
+void socs_system_free(SocsSystem *system);
+
+/* Return the number of variables of `system`. */
+size_t socs_system_nvar(const SocsSystem *system);
+
+/* Return the number of bdds of `system`. */
+size_t socs_system_nbdds(const SocsSystem *system);
+
+/* Return the number of nodes of the bdds of `system`. */
+size_t socs_system_size(const SocsSystem *system);
+
+/* Return a copy of the bdd of id `id` of `system`. */
+SocsBdd *socs_system_bdd(const SocsSystem *system, size_t id);
+
+/* Add a copy of `bdd` to `system`. Its id must differ from the ones of the bdds of `system`. */
+int socs_system_push_bdd(SocsSystem *system, const SocsBdd *bdd);
+
+/* Fix the variable `var` of `system` to `value`. */
+int socs_system_fix(SocsSystem *system, size_t var, bool value);
+
+/*
+ Constrain the sum of the `nvars` variables `vars` of `system` to `rhs` with a new bdd, whose id
+ is written to `bdd_id` unless it is `NULL`.
+ */
+int socs_system_add_linear_constraint(SocsSystem *system,
+                                      const size_t *vars,
+                                      size_t nvars,
+                                      bool rhs,
+                                      size_t *bdd_id);
+
+/* Return 1 if the `len` values `assignment` of the variables are a solution of `system`, 0 if not. */
+int socs_system_is_solution(const SocsSystem *system, const bool *assignment, size_t len);
+
+/*
+ Resolve every linear dependency of `system` (see `crush::solver::lhs`) and return its solutions,
+ at most `limit` (`SIZE_MAX` for all of them). `system` is left solved, see `socs_system_clone`
+ to keep it.
+ */
+SocsSolutions *socs_system_solve(SocsSystem *system, size_t limit);
+
+/* Return the number of `solutions`. */
+size_t socs_solutions_count(const SocsSolutions *solutions);
+
+/* Return the number of variables of each of `solutions`. */
+size_t socs_solutions_nvar(const SocsSolutions *solutions);
+
+/*
+ Write the values of the variables of the solution `index` of `solutions` to `values`: 0 or 1,
+ or -1 for a free variable.
+ */
+int socs_solutions_get(const SocsSolutions *solutions, size_t index, int8_t *values);
+
+/* Release `solutions`. */
+void socs_solutions_free(SocsSolutions *solutions);
+
+/* Read the bdd written by `bdd_save` of BuDDy, of id `id` over `nvar` variables. */
+SocsBdd *socs_bdd_from_buddy(const char *dump, size_t nvar, size_t id);
+
+/*
+ Read the bdd of the root `root` (from 0) of the dump written in the text mode of the DDDMP
+ format of CUDD, over `nvar` variables. Its id is `root`.
+ */
+SocsBdd *socs_bdd_from_dddmp(const char *dump, size_t nvar, size_t root);
+
+/* Return `bdd` in the text mode of the DDDMP format of CUDD, to release with `socs_string_free`. */
+char *socs_bdd_to_dddmp(const SocsBdd *bdd);
+
+/* Return the id of `bdd`. */
+size_t socs_bdd_id(const SocsBdd *bdd);
+
+/* Set the id of `bdd`, e.g. before adding it to a system. */
+int socs_bdd_set_id(SocsBdd *bdd, size_t id);
+
+/* Return the number of nodes of `bdd`. */
+size_t socs_bdd_size(const SocsBdd *bdd);
+
+/*
+ Return the number of paths of `bdd` in decimal, as it may not fit in an integer, to release
+ with `socs_string_free`.
+ */
+char *socs_bdd_count_paths(const SocsBdd *bdd);
+
+/* Return 1 if the `len` values `assignment` of the variables satisfy `bdd`, 0 if not. */
+int socs_bdd_accepts(const SocsBdd *bdd, const bool *assignment, size_t len);
+
+/* Release `bdd`. */
+void socs_bdd_free(SocsBdd *bdd);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SOCS_H */
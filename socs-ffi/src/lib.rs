@@ -0,0 +1,568 @@
+//! C API of crush, such that C and C++ programs can embed the solver, declared by the header
+//! include/socs.h. The crate builds both a shared and a static library, `libsocs_ffi`.
+//!
+//! The systems, bdds and solutions are opaque handles (`SocsSystem`, `SocsBdd`, `SocsSolutions`),
+//! created by the functions returning a pointer to them and released by the `free` function of
+//! their type. The strings returned are released by `socs_string_free`.
+//!
+//! A function failing returns `NULL` if it returns a pointer, and -1 if it returns an `int`, 0
+//! being a success. The message of the error is then returned by `socs_last_error`, on the same
+//! thread. The panics of crush (e.g. solving a system without solution) are errors as well, and
+//! never cross the API.
+//!
+//! A solution holds the value of each variable: 0 or 1, or -1 for a variable left free, every
+//! assignment of the free variables giving a solution.
+//!
+//! ```c
+//! SocsSystem *system = socs_system_load("present.bdd");
+//! SocsSolutions *solutions = system ? socs_system_solve(system, SIZE_MAX) : NULL;
+//! if (solutions == NULL) {
+//!     fprintf(stderr, "%s\n", socs_last_error());
+//! }
+//! ```
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::io::{Error, ErrorKind};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr;
+
+use vob::Vob;
+
+use crush::soc::{bdd::{Bdd, PathCursor}, Id, io as soc_io, parse, system::System};
+use crush::soc::utils::{self, BddSpec};
+use crush::solver::{lhs::{DefaultSolver, LevelDependency}, Solver};
+
+/// A system of bdds.
+pub struct SocsSystem {
+    system: System,
+}
+
+/// A bdd, e.g. a Shard to add to a system.
+pub struct SocsBdd {
+    bdd: Bdd,
+}
+
+/// The solutions of a system, see the crate documentation.
+pub struct SocsSolutions {
+    nvar: usize,
+    solutions: Vec<Vec<Option<bool>>>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Return the message of the last error of the calling thread, or `NULL` if none. The message is
+/// valid until the next error of the thread.
+#[no_mangle]
+pub extern "C" fn socs_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Release a string returned by the API.
+///
+/// # Safety
+///
+/// `string` must be `NULL` or a string returned by the API, not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn socs_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Return a new system of `nvar` variables, without bdd.
+#[no_mangle]
+pub extern "C" fn socs_system_new(nvar: usize) -> *mut SocsSystem {
+    let mut system = System::new();
+    system.set_nvar(nvar);
+    boxed(SocsSystem { system })
+}
+
+/// Read the system of the .bdd file at `path`, or of the binary snapshot if its extension is
+/// `snap`.
+///
+/// # Safety
+///
+/// `path` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_load(path: *const c_char) -> *mut SocsSystem {
+    guard_ptr(|| {
+        let path = PathBuf::from(string(path)?);
+        let system = if path.extension().is_some_and(|extension| extension == "snap") {
+            soc_io::load_snapshot_from_file(&path)?
+        } else {
+            soc_io::build_system_from_file(&path)?
+        };
+        Ok(SocsSystem { system })
+    })
+}
+
+/// Build the system of `spec`, in the .bdd format.
+///
+/// # Safety
+///
+/// `spec` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_from_spec(spec: *const c_char) -> *mut SocsSystem {
+    guard_ptr(|| Ok(SocsSystem { system: parse::build_system_from_reader(string(spec)?.as_bytes())? }))
+}
+
+/// Write `system` to the .bdd file at `path` as a checkpoint, keeping the linear equations found.
+///
+/// # Safety
+///
+/// `system` must be a live handle and `path` a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_save(system: *const SocsSystem, path: *const c_char) -> c_int {
+    guard_status(|| {
+        let path = PathBuf::from(string(path)?);
+        soc_io::print_checkpoint_to_file(&handle(system)?.system, &path)?;
+        Ok(())
+    })
+}
+
+/// Return a copy of `system`, e.g. to keep it before solving.
+///
+/// # Safety
+///
+/// `system` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_clone(system: *const SocsSystem) -> *mut SocsSystem {
+    guard_ptr(|| Ok(SocsSystem { system: handle(system)?.system.clone() }))
+}
+
+/// Release `system`.
+///
+/// # Safety
+///
+/// `system` must be `NULL` or a live handle, which is no longer live afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_free(system: *mut SocsSystem) {
+    free(system)
+}
+
+/// Return the number of variables of `system`.
+///
+/// # Safety
+///
+/// `system` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_nvar(system: *const SocsSystem) -> usize {
+    handle(system).map_or(0, |system| system.system.get_nvar())
+}
+
+/// Return the number of bdds of `system`.
+///
+/// # Safety
+///
+/// `system` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_nbdds(system: *const SocsSystem) -> usize {
+    handle(system).map_or(0, |system| system.system.iter_bdds().len())
+}
+
+/// Return the number of nodes of the bdds of `system`.
+///
+/// # Safety
+///
+/// `system` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_size(system: *const SocsSystem) -> usize {
+    handle(system).map_or(0, |system| system.system.get_size())
+}
+
+/// Return a copy of the bdd of id `id` of `system`.
+///
+/// # Safety
+///
+/// `system` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_bdd(system: *const SocsSystem, id: usize) -> *mut SocsBdd {
+    guard_ptr(|| Ok(SocsBdd { bdd: handle(system)?.system.get_bdd(Id::new(id))?.borrow().clone() }))
+}
+
+/// Add a copy of `bdd` to `system`. Its id must differ from the ones of the bdds of `system`.
+///
+/// # Safety
+///
+/// `system` and `bdd` must be live handles.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_push_bdd(system: *mut SocsSystem, bdd: *const SocsBdd) -> c_int {
+    guard_status(|| handle_mut(system)?.system.push_bdd(handle(bdd)?.bdd.clone()))
+}
+
+/// Fix the variable `var` of `system` to `value`.
+///
+/// # Safety
+///
+/// `system` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_fix(system: *mut SocsSystem, var: usize, value: bool) -> c_int {
+    guard_status(|| handle_mut(system)?.system.fix_variable(var, value))
+}
+
+/// Constrain the sum of the `nvars` variables `vars` of `system` to `rhs` with a new bdd, whose id
+/// is written to `bdd_id` unless it is `NULL`.
+///
+/// # Safety
+///
+/// `system` must be a live handle, `vars` point to `nvars` variables and `bdd_id` be `NULL` or
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_add_linear_constraint(
+    system: *mut SocsSystem,
+    vars: *const usize,
+    nvars: usize,
+    rhs: bool,
+    bdd_id: *mut usize,
+) -> c_int {
+    guard_status(|| {
+        let id = handle_mut(system)?.system.add_linear_constraint(slice(vars, nvars)?, rhs)?;
+        if !bdd_id.is_null() {
+            *bdd_id = *id;
+        }
+        Ok(())
+    })
+}
+
+/// Return 1 if the `len` values `assignment` of the variables are a solution of `system`, 0 if not.
+///
+/// # Safety
+///
+/// `system` must be a live handle and `assignment` point to `len` values.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_is_solution(system: *const SocsSystem, assignment: *const bool, len: usize) -> c_int {
+    guard(|| {
+        let assignment: Vob = slice(assignment, len)?.iter().copied().collect();
+        Ok(handle(system)?.system.is_solution(&assignment) as c_int)
+    }).unwrap_or(-1)
+}
+
+/// Resolve every linear dependency of `system` (see `crush::solver::lhs`) and return its solutions,
+/// at most `limit` (`SIZE_MAX` for all of them). `system` is left solved, see `socs_system_clone`
+/// to keep it.
+///
+/// # Safety
+///
+/// `system` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_system_solve(system: *mut SocsSystem, limit: usize) -> *mut SocsSolutions {
+    guard_ptr(|| {
+        let system = &mut handle_mut(system)?.system;
+        DefaultSolver.solve::<LevelDependency>(system)?;
+        let (solutions, _) = system.calculate_solutions_from(&PathCursor::start(), limit)?;
+        Ok(SocsSolutions { nvar: system.get_nvar(), solutions })
+    })
+}
+
+/// Return the number of `solutions`.
+///
+/// # Safety
+///
+/// `solutions` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_solutions_count(solutions: *const SocsSolutions) -> usize {
+    handle(solutions).map_or(0, |solutions| solutions.solutions.len())
+}
+
+/// Return the number of variables of each of `solutions`.
+///
+/// # Safety
+///
+/// `solutions` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_solutions_nvar(solutions: *const SocsSolutions) -> usize {
+    handle(solutions).map_or(0, |solutions| solutions.nvar)
+}
+
+/// Write the values of the variables of the solution `index` of `solutions` to `values`: 0 or 1,
+/// or -1 for a free variable.
+///
+/// # Safety
+///
+/// `solutions` must be a live handle and `values` have room for `socs_solutions_nvar` values.
+#[no_mangle]
+pub unsafe extern "C" fn socs_solutions_get(solutions: *const SocsSolutions, index: usize, values: *mut i8) -> c_int {
+    guard_status(|| {
+        let solutions = handle(solutions)?;
+        let solution = solutions.solutions.get(index).ok_or_else(|| {
+            Error::new(ErrorKind::InvalidInput, format!("No solution {} out of {}", index, solutions.solutions.len()))
+        })?;
+        if values.is_null() {
+            return Err(null());
+        }
+        let values = std::slice::from_raw_parts_mut(values, solutions.nvar);
+        for (value, solution) in values.iter_mut().zip(solution.iter()) {
+            *value = solution.map_or(-1, |x| x as i8);
+        }
+        Ok(())
+    })
+}
+
+/// Release `solutions`.
+///
+/// # Safety
+///
+/// `solutions` must be `NULL` or a live handle, which is no longer live afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn socs_solutions_free(solutions: *mut SocsSolutions) {
+    free(solutions)
+}
+
+/// Read the bdd written by `bdd_save` of BuDDy, of id `id` over `nvar` variables.
+///
+/// # Safety
+///
+/// `dump` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn socs_bdd_from_buddy(dump: *const c_char, nvar: usize, id: usize) -> *mut SocsBdd {
+    guard_ptr(|| build(utils::read_buddy(string(dump)?.as_bytes(), Id::new(id))?, nvar))
+}
+
+/// Read the bdd of the root `root` (from 0) of the dump written in the text mode of the DDDMP
+/// format of CUDD, over `nvar` variables. Its id is `root`.
+///
+/// # Safety
+///
+/// `dump` must be a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn socs_bdd_from_dddmp(dump: *const c_char, nvar: usize, root: usize) -> *mut SocsBdd {
+    guard_ptr(|| {
+        let specs = utils::read_dddmp(string(dump)?.as_bytes())?;
+        let nroots = specs.len();
+        let spec = specs.into_iter().nth(root).ok_or_else(|| {
+            Error::new(ErrorKind::InvalidInput, format!("No root {} out of {}", root, nroots))
+        })?;
+        build(spec, nvar)
+    })
+}
+
+/// Return `bdd` in the text mode of the DDDMP format of CUDD, to release with `socs_string_free`.
+///
+/// # Safety
+///
+/// `bdd` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_bdd_to_dddmp(bdd: *const SocsBdd) -> *mut c_char {
+    guard(|| {
+        let mut dump = Vec::new();
+        utils::write_dddmp(&handle(bdd)?.bdd, &mut dump)?;
+        Ok(CString::new(dump).expect("A DDDMP dump is ASCII").into_raw())
+    }).unwrap_or(ptr::null_mut())
+}
+
+/// Return the id of `bdd`.
+///
+/// # Safety
+///
+/// `bdd` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_bdd_id(bdd: *const SocsBdd) -> usize {
+    handle(bdd).map_or(0, |bdd| *bdd.bdd.get_id())
+}
+
+/// Set the id of `bdd`, e.g. before adding it to a system.
+///
+/// # Safety
+///
+/// `bdd` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_bdd_set_id(bdd: *mut SocsBdd, id: usize) -> c_int {
+    guard_status(|| {
+        handle_mut(bdd)?.bdd.set_id(Id::new(id));
+        Ok(())
+    })
+}
+
+/// Return the number of nodes of `bdd`.
+///
+/// # Safety
+///
+/// `bdd` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_bdd_size(bdd: *const SocsBdd) -> usize {
+    handle(bdd).map_or(0, |bdd| bdd.bdd.get_size())
+}
+
+/// Return the number of paths of `bdd` in decimal, as it may not fit in an integer, to release
+/// with `socs_string_free`.
+///
+/// # Safety
+///
+/// `bdd` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn socs_bdd_count_paths(bdd: *const SocsBdd) -> *mut c_char {
+    guard(|| Ok(CString::new(handle(bdd)?.bdd.count_paths().to_string()).unwrap().into_raw()))
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Return 1 if the `len` values `assignment` of the variables satisfy `bdd`, 0 if not.
+///
+/// # Safety
+///
+/// `bdd` must be a live handle and `assignment` point to `len` values.
+#[no_mangle]
+pub unsafe extern "C" fn socs_bdd_accepts(bdd: *const SocsBdd, assignment: *const bool, len: usize) -> c_int {
+    guard(|| {
+        let assignment: Vob = slice(assignment, len)?.iter().copied().collect();
+        Ok(handle(bdd)?.bdd.accepts(&assignment) as c_int)
+    }).unwrap_or(-1)
+}
+
+/// Release `bdd`.
+///
+/// # Safety
+///
+/// `bdd` must be `NULL` or a live handle, which is no longer live afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn socs_bdd_free(bdd: *mut SocsBdd) {
+    free(bdd)
+}
+
+/// Build the bdd of `spec` over `nvar` variables.
+fn build(mut spec: BddSpec, nvar: usize) -> Result<SocsBdd, Error> {
+    utils::check_vars(&spec, nvar)?;
+    Ok(SocsBdd { bdd: utils::build_bdd_from_spec(&mut spec, nvar) })
+}
+
+/// Run `f`, returning its result, or `None` after recording its error or its panic as the last
+/// error of the thread.
+fn guard<T, F: FnOnce() -> Result<T, Error>>(f: F) -> Option<T> {
+    let error = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => return Some(value),
+        Ok(Err(error)) => error.to_string(),
+        Err(panic) => panic_message(panic),
+    };
+    let error = CString::new(error.replace('\0', "")).unwrap();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(error));
+    None
+}
+
+/// `guard`, returning the status of the API.
+fn guard_status<F: FnOnce() -> Result<(), Error>>(f: F) -> c_int {
+    guard(f).map_or(-1, |_| 0)
+}
+
+/// `guard`, returning the handle of the result, or `NULL`.
+fn guard_ptr<T, F: FnOnce() -> Result<T, Error>>(f: F) -> *mut T {
+    guard(f).map_or(ptr::null_mut(), boxed)
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "crush panicked".to_string(),
+    }
+}
+
+fn boxed<T>(value: T) -> *mut T {
+    Box::into_raw(Box::new(value))
+}
+
+unsafe fn free<T>(value: *mut T) {
+    if !value.is_null() {
+        drop(Box::from_raw(value));
+    }
+}
+
+fn null() -> Error {
+    Error::new(ErrorKind::InvalidInput, "Unexpected NULL pointer")
+}
+
+unsafe fn handle<'a, T>(value: *const T) -> Result<&'a T, Error> {
+    value.as_ref().ok_or_else(null)
+}
+
+unsafe fn handle_mut<'a, T>(value: *mut T) -> Result<&'a mut T, Error> {
+    value.as_mut().ok_or_else(null)
+}
+
+unsafe fn string<'a>(value: *const c_char) -> Result<&'a str, Error> {
+    if value.is_null() {
+        return Err(null());
+    }
+    CStr::from_ptr(value).to_str().map_err(|_| Error::new(ErrorKind::InvalidInput, "Expected an UTF-8 string"))
+}
+
+unsafe fn slice<'a, T>(values: *const T, len: usize) -> Result<&'a [T], Error> {
+    match (values.is_null(), len) {
+        (_, 0) => Ok(&[]),
+        (true, _) => Err(null()),
+        (false, _) => Ok(std::slice::from_raw_parts(values, len)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The functions of the API, from the source.
+    fn functions() -> Vec<String> {
+        include_str!("lib.rs").lines()
+            .filter_map(|line| line.strip_prefix("pub unsafe extern \"C\" fn ").or_else(|| line.strip_prefix("pub extern \"C\" fn ")))
+            .map(|line| line[..line.find('(').unwrap()].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn panics_are_errors() {
+        assert!(guard::<(), _>(|| panic!("System has no solutions")).is_none());
+        assert_eq!("System has no solutions", unsafe { CStr::from_ptr(socs_last_error()) }.to_str().unwrap());
+    }
+
+    #[test]
+    fn header_declares_api() {
+        let header = include_str!("../include/socs.h");
+        let functions = functions();
+        assert!(functions.len() > 20);
+        for function in functions {
+            let declared = header.contains(&format!(" {}(", function)) || header.contains(&format!("*{}(", function));
+            assert!(declared, "{} isn't declared by socs.h", function);
+        }
+    }
+
+    #[test]
+    fn solve_through_api() {
+        let spec = CString::new("3 2\n0 3\n0+1:(1;2,3)|\n2:(2;4,0)(3;0,4)|\n:(4;0,0)|\n---\n\
+                                 1 3\n0+1:(1;2,0)|\n0:(2;3,3)|\n:(3;0,0)|\n---\n").unwrap();
+        unsafe {
+            let system = socs_system_from_spec(spec.as_ptr());
+            assert!(!system.is_null());
+            assert_eq!((3, 2), (socs_system_nvar(system), socs_system_nbdds(system)));
+            let mut id = 0;
+            assert_eq!(0, socs_system_add_linear_constraint(system, [0, 2].as_ptr(), 2, true, &mut id));
+            assert_eq!(2, id);
+            let original = socs_system_clone(system);
+            let solutions = socs_system_solve(system, usize::MAX);
+            assert_eq!(1, socs_solutions_count(solutions));
+            let mut values = [0_i8; 3];
+            assert_eq!(0, socs_solutions_get(solutions, 0, values.as_mut_ptr()));
+            assert_eq!([1, 1, 0], values);
+            assert_eq!(1, socs_system_is_solution(original, [true, true, false].as_ptr(), 3));
+            assert_eq!(-1, socs_solutions_get(solutions, 1, values.as_mut_ptr()));
+            assert_eq!("No solution 1 out of 1", CStr::from_ptr(socs_last_error()).to_str().unwrap());
+            socs_solutions_free(solutions);
+
+            // x0 and not x1, of id 3 as the others are 0 to 2
+            let dump = CString::new("2 2\n0 1 \n3 1 1 0\n2 0 0 3\n").unwrap();
+            let bdd = socs_bdd_from_buddy(dump.as_ptr(), 3, 3);
+            let paths = socs_bdd_count_paths(bdd);
+            assert_eq!("1", CStr::from_ptr(paths).to_str().unwrap());
+            socs_string_free(paths);
+            assert_eq!(1, socs_bdd_accepts(bdd, [true, false].as_ptr(), 2));
+            assert_eq!(0, socs_system_push_bdd(original, bdd));
+            assert_eq!(-1, socs_system_push_bdd(original, bdd));
+            assert_eq!(0, socs_system_is_solution(original, [true, true, false].as_ptr(), 3));
+            socs_bdd_free(bdd);
+            socs_system_free(original);
+            socs_system_free(system);
+            assert!(socs_system_load(ptr::null()).is_null());
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Python bindings of crush, such that a system can be loaded, constrained, solved and its
+//! solutions extracted from a Python (or Sage, or Jupyter) session, without writing Rust.
+//!
+//! The `socs` module is built with maturin (`maturin develop --release` in this directory, see
+//! pyproject.toml) and exposes two classes:
+//! - `System`, loaded from a .bdd file or a snapshot (`System.load`) or from the text of the .bdd
+//!   format (`System.from_spec`), constrained by fixing variables (`fix`), adding linear
+//!   constraints (`add_linear_constraint`) or Shards (`push_bdd`), and solved by `solve`;
+//! - `Bdd`, a copy of a bdd of a `System` (`System.bdd`) or a Shard read from the dumps of BuDDy
+//!   or CUDD (`Bdd.from_buddy`, `Bdd.from_dddmp`, see `crush::soc::utils::dump`).
+//!
+//! A solution is a list of the values of the variables, `None` being a variable left free: every
+//! assignment of the free variables gives a solution. The `io::Error`s of crush are raised as the
+//! `OSError` of their kind, e.g. `FileNotFoundError`.
+//!
+//! ```python
+//! import socs
+//!
+//! system = socs.System.load("present.bdd")
+//! system.fix(0, True)
+//! solutions = system.solve(limit=10)
+//! ```
+
+// The code generated by `pymethods` for the methods returning a `PyResult` converts their `PyErr`
+#![allow(clippy::useless_conversion)]
+
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use pyo3::prelude::*;
+use vob::Vob;
+
+use crush::soc::{bdd::{Bdd, PathCursor}, Id, io as soc_io, parse, system::System};
+use crush::soc::utils::{self, BddSpec};
+use crush::solver::{lhs::{DefaultSolver, LevelDependency}, Solver};
+
+/// A system of bdds, see the module documentation.
+#[pyclass(name = "System", unsendable)]
+#[derive(Clone)]
+pub struct PySystem {
+    system: System,
+}
+
+#[pymethods]
+impl PySystem {
+    /// Return an empty system of `nvar` variables.
+    #[new]
+    fn new(nvar: usize) -> PySystem {
+        let mut system = System::new();
+        system.set_nvar(nvar);
+        PySystem { system }
+    }
+
+    /// Read the system of the .bdd file at `path`, or of the binary snapshot if its extension is
+    /// `snap` (see `crush::soc::io::load_snapshot_from_file`).
+    #[staticmethod]
+    fn load(path: PathBuf) -> PyResult<PySystem> {
+        let system = if is_snapshot(&path) {
+            soc_io::load_snapshot_from_file(&path).map_err(Error::from)?
+        } else {
+            soc_io::build_system_from_file(&path).map_err(Error::from)?
+        };
+        Ok(PySystem { system })
+    }
+
+    /// Build the system of `spec`, in the .bdd format.
+    #[staticmethod]
+    fn from_spec(spec: &str) -> PyResult<PySystem> {
+        Ok(PySystem { system: parse::build_system_from_reader(spec.as_bytes())? })
+    }
+
+    /// Write the system to the .bdd file at `path` as a checkpoint, keeping the linear equations
+    /// found, or to a binary snapshot if its extension is `snap`.
+    fn save(&self, path: PathBuf) -> PyResult<()> {
+        if is_snapshot(&path) {
+            soc_io::save_snapshot_to_file(&self.system, &path).map_err(Error::from)?;
+        } else {
+            soc_io::print_checkpoint_to_file(&self.system, &path).map_err(Error::from)?;
+        }
+        Ok(())
+    }
+
+    /// Return a copy of the system, e.g. to keep it before solving.
+    fn copy(&self) -> PySystem {
+        self.clone()
+    }
+
+    /// The number of variables.
+    #[getter]
+    fn nvar(&self) -> usize {
+        self.system.get_nvar()
+    }
+
+    /// The number of nodes of the bdds.
+    #[getter]
+    fn size(&self) -> usize {
+        self.system.get_size()
+    }
+
+    /// Return the ids of the bdds, increasing.
+    fn bdd_ids(&self) -> Vec<usize> {
+        let mut ids: Vec<usize> = self.system.iter_bdds().map(|(id, _)| **id).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Return a copy of the bdd of id `id`.
+    fn bdd(&self, id: usize) -> PyResult<PyBdd> {
+        Ok(PyBdd { bdd: self.system.get_bdd(Id::new(id))?.borrow().clone() })
+    }
+
+    /// Add a copy of `bdd` to the system, e.g. a Shard constraining its variables. Its id must
+    /// differ from the ones of the bdds of the system.
+    fn push_bdd(&mut self, bdd: &PyBdd) -> PyResult<()> {
+        self.system.push_bdd(bdd.bdd.clone())?;
+        Ok(())
+    }
+
+    /// Fix the variable `var` to `value` (see `System::fix_variable`).
+    fn fix(&mut self, var: usize, value: bool) -> PyResult<()> {
+        self.system.fix_variable(var, value)?;
+        Ok(())
+    }
+
+    /// Constrain the sum of the variables `vars` to `rhs` with a new bdd, whose id is returned
+    /// (see `System::add_linear_constraint`).
+    fn add_linear_constraint(&mut self, vars: Vec<usize>, rhs: bool) -> PyResult<usize> {
+        Ok(*self.system.add_linear_constraint(&vars, rhs)?)
+    }
+
+    /// Resolve every linear dependency of the system (see `crush::solver::lhs`) and return its
+    /// solutions, at most `limit` if given. The system is left solved, see `copy` to keep it.
+    ///
+    /// A system without solution raises a `PanicException`, as crush panics once it finds out.
+    #[pyo3(signature = (limit=None))]
+    fn solve(&mut self, limit: Option<usize>) -> PyResult<Vec<Vec<Option<bool>>>> {
+        DefaultSolver.solve::<LevelDependency>(&mut self.system)?;
+        let (solutions, _) = self.system.calculate_solutions_from(&PathCursor::start(), limit.unwrap_or(usize::MAX))?;
+        Ok(solutions)
+    }
+
+    /// Return true if `assignment`, the values of the variables, is a solution of the system.
+    fn is_solution(&self, assignment: Vec<bool>) -> bool {
+        self.system.is_solution(&assignment.into_iter().collect::<Vob>())
+    }
+
+    /// Return the statistics of the system, as printed by `socs-cli`.
+    fn stats(&self) -> String {
+        self.system.stats().to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("System({} variables, {} bdds, {} nodes)",
+                self.system.get_nvar(), self.system.iter_bdds().len(), self.system.get_size())
+    }
+}
+
+/// A bdd, see the module documentation.
+#[pyclass(name = "Bdd", unsendable)]
+#[derive(Clone)]
+pub struct PyBdd {
+    bdd: Bdd,
+}
+
+#[pymethods]
+impl PyBdd {
+    /// Read the bdd written by `bdd_save` of BuDDy, of id `id` over `nvar` variables. The variable
+    /// `i` of BuDDy is the variable `vars[i]` if `vars` is given, and `i` otherwise.
+    #[staticmethod]
+    #[pyo3(signature = (dump, nvar, id=0, vars=None))]
+    fn from_buddy(dump: &str, nvar: usize, id: usize, vars: Option<Vec<usize>>) -> PyResult<PyBdd> {
+        let spec = utils::read_buddy(dump.as_bytes(), Id::new(id))?;
+        Ok(PyBdd { bdd: build(spec, nvar, vars.as_deref())? })
+    }
+
+    /// Read the bdds written in the text mode of the DDDMP format of CUDD over `nvar` variables,
+    /// one per root. The variable `i` of CUDD is the variable `vars[i]` if `vars` is given, and `i`
+    /// otherwise.
+    #[staticmethod]
+    #[pyo3(signature = (dump, nvar, vars=None))]
+    fn from_dddmp(dump: &str, nvar: usize, vars: Option<Vec<usize>>) -> PyResult<Vec<PyBdd>> {
+        let specs = utils::read_dddmp(dump.as_bytes())?;
+        specs.into_iter()
+            .map(|spec| Ok(PyBdd { bdd: build(spec, nvar, vars.as_deref())? }))
+            .collect()
+    }
+
+    /// Return the bdd in the text mode of the DDDMP format of CUDD (see `utils::write_dddmp`).
+    fn to_dddmp(&self) -> PyResult<String> {
+        let mut dump = Vec::new();
+        utils::write_dddmp(&self.bdd, &mut dump)?;
+        Ok(String::from_utf8(dump).expect("A DDDMP dump is ASCII"))
+    }
+
+    /// The id of the bdd.
+    #[getter]
+    fn id(&self) -> usize {
+        *self.bdd.get_id()
+    }
+
+    /// Set the id of the bdd, e.g. before pushing it to a system.
+    #[setter]
+    fn set_id(&mut self, id: usize) {
+        self.bdd.set_id(Id::new(id));
+    }
+
+    /// The number of nodes.
+    #[getter]
+    fn size(&self) -> usize {
+        self.bdd.get_size()
+    }
+
+    /// Return the variables of the LHS of each level, the sink excluded.
+    fn levels(&self) -> Vec<Vec<usize>> {
+        let nlevels = self.bdd.get_sink_level_index();
+        self.bdd.iter_levels().take(nlevels).map(|level| level.iter_set_lhs().collect()).collect()
+    }
+
+    /// Return the number of paths from the source to the sink.
+    fn count_paths<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let paths = self.bdd.count_paths().to_string();
+        py.import_bound("builtins")?.getattr("int")?.call1((paths,))
+    }
+
+    /// Return true if `assignment`, the values of the variables, satisfies the bdd.
+    fn accepts(&self, assignment: Vec<bool>) -> bool {
+        self.bdd.accepts(&assignment.into_iter().collect::<Vob>())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Bdd(id {}, {} levels, {} nodes)", self.bdd.get_id(), self.bdd.get_sink_level_index(), self.bdd.get_size())
+    }
+}
+
+/// The `socs` Python module.
+#[pymodule]
+fn socs(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PySystem>()?;
+    module.add_class::<PyBdd>()?;
+    Ok(())
+}
+
+/// Build the bdd of `spec` over `nvar` variables, its variable `i` being relabeled `vars[i]` if
+/// `vars` is given.
+///
+/// Return an `Error` of kind `ErrorKind::InvalidInput` if a variable isn't in `vars`, and of kind
+/// `ErrorKind::InvalidData` if it is beyond `nvar` (see `utils::check_vars`).
+fn build(mut spec: BddSpec, nvar: usize, vars: Option<&[usize]>) -> Result<Bdd, Error> {
+    if let Some(vars) = vars {
+        if let Some(var) = spec.max_var().filter(|var| *var >= vars.len()) {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("Variable {} has no label", var)));
+        }
+        spec.relabel_vars(|var| vars[var]);
+    }
+    utils::check_vars(&spec, nvar)?;
+    Ok(utils::build_bdd_from_spec(&mut spec, nvar))
+}
+
+fn is_snapshot(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "snap")
+}
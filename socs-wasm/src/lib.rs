@@ -0,0 +1,87 @@
+//! WebAssembly bindings of crush, returning the visualization data of the shards of a system to a
+//! browser, such that a visualizer can be built without GraphViz or a server.
+//!
+//! Crush is built without its default features but `parse`, i.e. without touching the file system
+//! or spawning processes. The module is built with wasm-pack (`wasm-pack build --target web` in this
+//! directory) and exposes the class `System`, built from the text of the .bdd format, which
+//! returns:
+//! - the .dot text of a shard or of the whole system (see `crush::soc::dot`), e.g. for a
+//!   GraphViz compiled to WebAssembly;
+//! - the layout of a shard as JSON (see `crush::soc::layout`), the positions of its nodes being
+//!   computed by crush, such that it can be drawn directly on a canvas or with SVG.
+//!
+//! The errors of crush are thrown as JavaScript `Error`s.
+//!
+//! ```javascript
+//! import init, { System } from "./pkg/socs_wasm.js";
+//!
+//! await init();
+//! const system = new System(await (await fetch("present.bdd")).text());
+//! const layout = JSON.parse(system.layout(system.ids()[0]));
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crush::soc::{dot::DotOptions, parse, system::System, Id};
+
+/// A system of bdds, see the crate documentation.
+#[wasm_bindgen(js_name = System)]
+pub struct WasmSystem {
+    system: System,
+}
+
+#[wasm_bindgen(js_class = System)]
+impl WasmSystem {
+    /// Build the system of `spec`, in the .bdd format.
+    #[wasm_bindgen(constructor)]
+    pub fn new(spec: &str) -> Result<WasmSystem, JsError> {
+        Ok(WasmSystem { system: parse::build_system_from_reader(spec.as_bytes())? })
+    }
+
+    /// The number of variables.
+    #[wasm_bindgen(getter)]
+    pub fn nvar(&self) -> usize {
+        self.system.get_nvar()
+    }
+
+    /// Return the ids of the shards, increasing.
+    pub fn ids(&self) -> Vec<usize> {
+        let mut ids: Vec<usize> = self.system.iter_bdds().map(|(id, _)| **id).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Return the .dot text of the shard of id `id`, its nodes labelled with the number of
+    /// accepted paths going through them if `path_counts`, and its edges coloured by their share
+    /// of these paths if `edge_weights` (see `DotOptions`).
+    pub fn dot(&self, id: usize, path_counts: bool, edge_weights: bool) -> Result<String, JsError> {
+        let options = DotOptions { path_counts, edge_weights, ..Default::default() };
+        Ok(self.system.get_bdd(Id::new(id))?.borrow().to_dot(&options))
+    }
+
+    /// Return the .dot text of the whole system, the shards linked by the variables they share.
+    #[wasm_bindgen(js_name = systemDot)]
+    pub fn system_dot(&self) -> String {
+        self.system.to_dot()
+    }
+
+    /// Return the layout of the shard of id `id` as JSON, see `ShardLayout::to_json`.
+    pub fn layout(&self, id: usize) -> Result<String, JsError> {
+        Ok(self.system.get_bdd(Id::new(id))?.borrow().layout().to_json())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn visualization_data() {
+        let system = WasmSystem::new("3 2\n0 3\n0+1:(1;2,3)|\n2:(2;4,0)(3;0,4)|\n:(4;0,0)|\n---\n\
+                                      1 3\n0+1:(1;2,0)|\n0:(2;3,3)|\n:(3;0,0)|\n---\n").ok().unwrap();
+        assert_eq!((3, vec![0, 1]), (system.nvar(), system.ids()));
+        assert!(system.dot(0, true, false).ok().unwrap().contains("\"10000\" [label = \"2\"; shape = ellipse; fontsize = 10];"));
+        assert!(system.system_dot().contains("subgraph \"cluster_1\" {"));
+        assert!(system.layout(1).ok().unwrap().starts_with("{\"id\":1,\"paths\":\"2\",\"levels\":[{\"lhs\":[0,1],\"label\":\"x0 + x1\""));
+    }
+}